@@ -4,20 +4,40 @@ use ::core::cell::OnceCell;
 
 mod pio_ops;
 
+/// Number of interrupt event CBs that the bridge must hold before enabling the interrupt
+const NE2K_MIN_EVENT_PEND: u8 = 2;
+/// Total number of interrupt event CBs (the remainder are spares)
+const NE2K_NUM_INTR_EVENT_CBS: u8 = 4;
+/// Number of multicast addresses tracked for the hash filter
+const NE2K_MAX_MULTICAST: usize = 16;
+
 #[derive(Default)]
 struct Driver
 {
 	init: OnceCell<Init>,
 	intr_bound: ::udi::async_helpers::Wait< ::udi::Result<()> >,
+	intr_pool: ::udi::meta_bridge::IntrEventPool,
 	rx_cb_queue: ::udi::meta_nic::ReadCbQueue,
+	/// Transmit queue, the card only has a single transmit in progress at a time
+	tx_queue: ::udi::meta_nic::TxQueue,
 	channels: OnceCell<Channels>,
 	vals: Vals,
+	/// Set between `enable_req` and `disable_req`
+	enabled: Cell<bool>,
+	stats: ::udi::meta_nic::Stats,
+	/// Receive filter state, set by control requests
+	rx_filter: Cell<RxFilter>,
 }
 struct Init {
 	pio_handles: pio_ops::PioHandles,
-	#[allow(dead_code)]	// Only here to hold the handle
-	intr_channel: ::udi::imc::ChannelHandle,
+	/// Attached interrupt, taken when unbinding
+	intr: Cell<Option<::udi::meta_bridge::IntrHandle>>,
+	/// Channel to the bus bridge, used to unbind
+	bus_channel: ::udi::ffi::udi_channel_t,
+	/// Factory MAC address, read from the EEPROM
 	mac_addr: [u8; 6],
+	/// MAC address programmed into the PAR registers
+	cur_mac_addr: Cell<[u8; 6]>,
 }
 struct Channels {
 	#[allow(dead_code)]	// Only here to hold the handle
@@ -37,6 +57,76 @@ impl Default for Vals {
 		}
     }
 }
+#[derive(Default,Copy,Clone)]
+struct RxFilter {
+	promisc: bool,
+	all_multi: bool,
+	bad_rxpkt: bool,
+	multicast: [[u8; 6]; NE2K_MAX_MULTICAST],
+	n_multicast: usize,
+}
+impl RxFilter {
+	fn add_multicast(&mut self, addr: [u8; 6]) -> ::udi::Result<()> {
+		if self.multicast[..self.n_multicast].contains(&addr) {
+			return Ok(());
+		}
+		if self.n_multicast == NE2K_MAX_MULTICAST {
+			return ::udi::Error::from_status(::udi::ffi::UDI_STAT_RESOURCE_UNAVAIL as _);
+		}
+		self.multicast[self.n_multicast] = addr;
+		self.n_multicast += 1;
+		Ok(())
+	}
+	fn del_multicast(&mut self, addr: [u8; 6]) {
+		if let Some(i) = self.multicast[..self.n_multicast].iter().position(|a| *a == addr) {
+			self.multicast.copy_within(i+1..self.n_multicast, i);
+			self.n_multicast -= 1;
+		}
+	}
+	/// Receive Configuration Register value
+	fn rcr(&self) -> u8 {
+		let mut rv = 0x04;	// AB: Accept broadcast
+		if self.all_multi || self.n_multicast > 0 {
+			rv |= 0x08;	// AM: Accept multicast (matching MAR)
+		}
+		if self.promisc {
+			rv |= 0x10;	// PRO: Promiscuous
+		}
+		if self.bad_rxpkt {
+			rv |= 0x03;	// SEP/AR: Accept packets with errors, and runts
+		}
+		rv
+	}
+	/// Multicast Address Registers - a hash filter indexed by the top six bits of the address CRC
+	fn mar(&self) -> [u8; 8] {
+		if self.all_multi {
+			return [0xFF; 8];
+		}
+		let mut rv = [0; 8];
+		for addr in &self.multicast[..self.n_multicast] {
+			let crc = ether_crc(addr);
+			rv[(crc >> 29) as usize] |= 1 << ((crc >> 26) & 7);
+		}
+		rv
+	}
+}
+/// Ethernet CRC-32 (big-endian) of an address, as used by the DP8390 multicast filter
+fn ether_crc(addr: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &b in addr {
+		let mut b = b;
+		for _ in 0..8 {
+			let carry = (crc >> 31) ^ (b as u32 & 1);
+			crc <<= 1;
+			if carry != 0 {
+				crc ^= 0x04C11DB7;
+			}
+			b >>= 1;
+		}
+	}
+	crc
+}
+
 impl Driver {
 	fn mac_addr(&self) -> &[u8; 6] {
 		&self.init.get().expect("Not bound to bus").mac_addr
@@ -44,6 +134,15 @@ impl Driver {
 	fn pio_handles(&self) -> &pio_ops::PioHandles {
 		&self.init.get().expect("Not bound to bus").pio_handles
 	}
+	/// Program the receive filter and current MAC address into the card
+	async fn apply_config(&self, gcb: ::udi::CbRef<'_, ::udi::ffi::udi_cb_t>) -> ::udi::Result<()> {
+		let filter = self.rx_filter.get();
+		let mut config = [0; pio_ops::CONFIG_LEN];
+		config[0] = filter.rcr();
+		config[1..7].copy_from_slice(&self.init.get().expect("Not bound to bus").cur_mac_addr.get());
+		config[7..15].copy_from_slice(&filter.mar());
+		self.pio_handles().config(gcb, &mut config).await
+	}
 }
 
 impl ::udi::init::Driver for ::udi::init::RData<Driver>
@@ -78,15 +177,15 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
 				(::udi::init::EnumerateResultOk::new::<OpsList::Ctrl>(0).into(), attrs_out)
 				},
 			udi::init::EnumerateLevel::Next => (::udi::init::EnumerateResult::Done, attrs_out),
-			udi::init::EnumerateLevel::New => todo!(),
-			udi::init::EnumerateLevel::Directed => todo!(),
-			udi::init::EnumerateLevel::Release => todo!(),
+			udi::init::EnumerateLevel::New
+			|udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+			udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
 			}
 		}
     }
 
     type Future_devmgmt<'s> = impl ::core::future::Future<Output=::udi::Result<u8>> + 's;
-    fn devmgmt_req<'s>(&'s self, _cb: ::udi::init::CbRefMgmt<'s>, mgmt_op: udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+    fn devmgmt_req<'s>(&'s self, cb: ::udi::init::CbRefMgmt<'s>, mgmt_op: udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
         async move {
 			use ::udi::init::MgmtOp;
 			match mgmt_op
@@ -96,7 +195,16 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
 			MgmtOp::Shutdown => todo!(),
 			MgmtOp::ParentSuspend => todo!(),
 			MgmtOp::Resume => todo!(),
-			MgmtOp::Unbind => todo!(),
+			MgmtOp::Unbind => {
+				let init = self.init.get().expect("Unbind before bind");
+				// Tear down the interrupt before unbinding from the bridge
+				if let Some(intr) = init.intr.take() {
+					intr.detach(cb.gcb()).await;
+				}
+				let bind_cb = ::udi::cb::alloc::<CbList::BusBind>(cb.gcb(), init.bus_channel).await;
+				let _ = ::udi::meta_bridge::bus_unbind(cb.gcb(), bind_cb).await;
+				Ok(0)
+				},
 			}
 		}
     }
@@ -116,21 +224,19 @@ impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
 			let (pio_handles, pio_irq_ack) = pio_ops::PioHandles::new(cb.gcb()).await;
 
 			// Save the channel used to bind to the parent, so we can unbind later on.
-			//self.parent_channel = cb.gcb.channel;
-			//::udi::debug_printf!("parent_channel=%p", self.parent_channel);
+			let bus_channel = cb.gcb.channel;
 
 			// Spawn channel
 			let intr_channel = ::udi::imc::channel_spawn::<OpsList::Irq>(cb.gcb(), self, /*interrupt number*/0.into()).await;
-			::udi::meta_bridge::intr_attach_req({
-				let mut intr_cb = ::udi::cb::alloc::<CbList::Intr>(cb.gcb(), ::udi::get_gcb_channel().await).await;
-				intr_cb.init(0.into(), 2, pio_irq_ack);	// NOTE: This transfers ownership
-				intr_cb});
+			let intr_cb = ::udi::cb::alloc::<CbList::Intr>(cb.gcb(), ::udi::get_gcb_channel().await).await;
+			::udi::meta_bridge::intr_attach(intr_cb, 0.into(), NE2K_MIN_EVENT_PEND, Some(pio_irq_ack));	// NOTE: This transfers ownership
 			self.intr_bound.wait(cb.gcb()).await?;
+			let detach_cb = ::udi::cb::alloc::<CbList::IntrDetach>(cb.gcb(), bus_channel).await;
+			let intr = ::udi::meta_bridge::IntrHandle::new(intr_channel, 0.into(), detach_cb);
 
-			for _ in 0 .. 4/*NE2K_NUM_INTR_EVENT_CBS*/ {
-				let intr_event_cb = ::udi::cb::alloc::<CbList::IntrEvent>(cb.gcb(), intr_channel.raw()).await;
-				::udi::meta_bridge::intr_event_rdy(intr_event_cb);
-			}
+			// Keep the bridge supplied with event CBs, with spares to cover events being handled
+			self.intr_pool.set_target(NE2K_MIN_EVENT_PEND);
+			self.intr_pool.fill::<CbList::IntrEvent>(cb.gcb(), intr.channel(), NE2K_NUM_INTR_EVENT_CBS - NE2K_MIN_EVENT_PEND).await;
 
 			// Reset the hardware, and get the MAC addres
 			let mut mac_addr = [0; 6];
@@ -138,8 +244,10 @@ impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
 
 			if let Err(_) = self.init.set(Init {
 				pio_handles,
-				intr_channel,
+				intr: Cell::new(Some(intr)),
+				bus_channel,
 				mac_addr,
+				cur_mac_addr: Cell::new(mac_addr),
 			}) {
 				panic!("Bound twice?")
 			}
@@ -173,10 +281,16 @@ impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
 }
 impl ::udi::meta_bridge::IntrHandler for ::udi::init::RData<Driver>
 {
+	fn intr_event_pool(&self) -> Option<&::udi::meta_bridge::IntrEventPool> {
+		Some(&self.intr_pool)
+	}
+
     type Future_intr_event_ind<'s> = impl ::core::future::Future<Output=()>+'s;
-    fn intr_event_ind<'a>(&'a self, cb: ::udi::meta_bridge::CbRefEvent<'a>, _flags: u8) -> Self::Future_intr_event_ind<'a> {
+    fn intr_event_ind<'a>(&'a self, cb: ::udi::meta_bridge::CbRefEvent<'a>, _flags: ::udi::meta_bridge::IntrEventFlags) -> Self::Future_intr_event_ind<'a> {
 		async move {
-			if cb.intr_result & 0x01 != 0 {
+			// The IRQACK handle returns the ISR in the upper byte
+			let isr = cb.intr_result().to_raw() >> 8;
+			if isr & 0x01 != 0 {
 				// RX complete
 				// - Pop a RX CB off the list
 				if let Some(mut rx_cb) = self.rx_cb_queue.pop() {
@@ -191,10 +305,12 @@ impl ::udi::meta_bridge::IntrHandler for ::udi::init::RData<Driver>
 						self.inner.vals.rx_next_page.set(page);
 						// If that succeeded, then set the size and hand to the NSR
 						buf.truncate(res);
+						self.stats.inc(::udi::meta_nic::Counter::RxPackets);
 						::udi::meta_nic::nsr_rx_ind(rx_cb);
 						},
 					Err(_e) => {
 						// Otherwise, return the cb to the queue
+						self.stats.inc(::udi::meta_nic::Counter::RxErrors);
 						self.rx_cb_queue.push(rx_cb);
 						}
 					}
@@ -202,24 +318,29 @@ impl ::udi::meta_bridge::IntrHandler for ::udi::init::RData<Driver>
 				else {
 					// RX undeflow :(
 					// - TODO: Flush from the device
+					self.stats.inc(::udi::meta_nic::Counter::RxDiscards);
 				}
 			}
-			if cb.intr_result & 0x02 != 0 {
+			if isr & 0x02 != 0 {
 				// TX complete
 			}
-			if cb.intr_result & 0x04 != 0 {
+			if isr & 0x04 != 0 {
 				// RX complete, but had errors
+				self.stats.inc(::udi::meta_nic::Counter::RxErrors);
 			}
-			if cb.intr_result & 0x08 != 0 {
+			if isr & 0x08 != 0 {
 				// Transmission halted due to excessive collisions
+				self.stats.inc(::udi::meta_nic::Counter::Collisions);
+				self.stats.inc(::udi::meta_nic::Counter::TxErrors);
 			}
-			if cb.intr_result & 0x10 != 0 {
+			if isr & 0x10 != 0 {
 				// RX buffer exhausted
+				self.stats.inc(::udi::meta_nic::Counter::RxOverrun);
 			}
-			if cb.intr_result & 0x40 != 0 {
+			if isr & 0x40 != 0 {
 				// Remote DMA is complete
 			}
-			if cb.intr_result & 0x80 != 0 {
+			if isr & 0x80 != 0 {
 				// Card reset complet
 				// - ignore
 			}
@@ -239,6 +360,13 @@ impl ::udi::meta_nic::Control for ::udi::ChildBind<Driver,()>
 			}) {
 				panic!("Bound twice?")
 			}
+			// Hand the NSR some TX CBs to send packets with
+			let channel_tx = &self.dev().channels.get().unwrap().tx;
+			let mut tx_cbs = ::udi::cb::alloc_batch::<CbList::NicTx>(cb.gcb(), 4, Some((1520, ::udi::ffi::buf::UDI_NULL_PATH_BUF))).await;
+			while let Some(mut tx_cb) = tx_cbs.pop_front() {
+				tx_cb.set_channel(channel_tx);
+				::udi::meta_nic::nsr_tx_rdy(tx_cb);
+			}
 			let mac_addr = self.dev().mac_addr();
 			::udi::debug_printf!("NIC mac_addr = %02x:%02x:%02x:%02x:%02x:%02x",
 				mac_addr[0] as _, mac_addr[1] as _, mac_addr[2] as _,
@@ -249,20 +377,13 @@ impl ::udi::meta_nic::Control for ::udi::ChildBind<Driver,()>
 				::udi::debug_printf!("NIC ether_type = %s", s);
 			}
 			Ok(::udi::meta_nic::NicInfo {
-				media_type: ::udi::ffi::meta_nic::MediaType::UDI_NIC_ETHER,
+				media_type: ::udi::meta_nic::MediaType::Ethernet,
 				min_pdu_size: 0,
 				max_pdu_size: 0,
 				rx_hw_threshold: 2,
-				capabilities: 0,
-				max_perfect_multicast: 0,
-				max_total_multicast: 0,
-				mac_addr_len: 6,
-				mac_addr: [
-					mac_addr[0], mac_addr[1], mac_addr[2],
-					mac_addr[3], mac_addr[4], mac_addr[5],
-					0,0,0,0,
-					0,0,0,0,0,0,0,0,0,0,
-				],
+				capabilities: ::udi::meta_nic::Capabilities::NONE,
+				multicast: ::udi::meta_nic::MulticastFilter { max_perfect: 0, max_total: NE2K_MAX_MULTICAST as u8 },
+				mac_addr: ::udi::meta_nic::MacAddress::new(mac_addr),
 			})
 		}
     }
@@ -281,6 +402,11 @@ impl ::udi::meta_nic::Control for ::udi::ChildBind<Driver,()>
     fn enable_req<'a>(&'a self, cb: ::udi::meta_nic::CbRefNic<'a>) -> Self::Future_enable_req<'a> {
         async move {
 			self.dev().pio_handles().enable( cb.gcb() ).await?;
+			self.dev().apply_config( cb.gcb() ).await?;
+			self.dev().enabled.set(true);
+			// NOTE: The NE2000 doesn't report link state, so just report that it's up
+			let status_cb = ::udi::cb::alloc::<CbList::NicStatus>(cb.gcb(), cb.gcb.channel).await;
+			::udi::meta_nic::nsr_status_ind(status_cb, ::udi::meta_nic::NicStatus::LinkUp);
 			Ok( () )
 		}
     }
@@ -289,79 +415,119 @@ impl ::udi::meta_nic::Control for ::udi::ChildBind<Driver,()>
     fn disable_req<'a>(&'a self, cb: ::udi::meta_nic::CbRefNic<'a>) -> Self::Future_disable_req<'a> {
         async move {
 			self.dev().pio_handles().disable( cb.gcb() ).await;
+			self.dev().enabled.set(false);
 		}
     }
 
-	type Future_ctrl_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
-    fn ctrl_req<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicCtrl<'a>) -> Self::Future_ctrl_req<'a> {
-        async move { todo!() }
+	type Future_ctrl_req<'s> = impl ::core::future::Future<Output=::udi::Result<Option<::udi::meta_nic::MacAddress>>> + 's;
+    fn ctrl_req<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicCtrl<'a>, op: ::udi::meta_nic::CtrlOp<'a>) -> Self::Future_ctrl_req<'a> {
+        async move {
+			use ::udi::meta_nic::{CtrlOp,MacAddress};
+			let dev = self.dev();
+			let init = dev.init.get().expect("Not bound to bus");
+			let mut filter = dev.rx_filter.get();
+			match op
+			{
+			CtrlOp::AddMulti(addrs) => for addr in addrs.iter() {
+				let Ok(addr) = addr.as_bytes().try_into() else {
+					return ::udi::Error::from_status(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD as _).map(|_| None);
+				};
+				filter.add_multicast(addr)?;
+				},
+			CtrlOp::DelMulti(addrs) => for addr in addrs.iter() {
+				if let Ok(addr) = addr.as_bytes().try_into() {
+					filter.del_multicast(addr);
+				}
+				},
+			CtrlOp::AllMulti(v) => filter.all_multi = v,
+			CtrlOp::Promisc(v) => filter.promisc = v,
+			CtrlOp::BadRxPkt(v) => filter.bad_rxpkt = v,
+			CtrlOp::GetCurrMac => return Ok(Some(MacAddress::new(&init.cur_mac_addr.get()))),
+			CtrlOp::GetFactMac => return Ok(Some(MacAddress::new(&init.mac_addr))),
+			CtrlOp::SetCurrMac(addr) => {
+				let Ok(addr) = addr.as_bytes().try_into() else {
+					return ::udi::Error::from_status(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD as _).map(|_| None);
+				};
+				init.cur_mac_addr.set(addr);
+				},
+			CtrlOp::HwReset => {
+				// Reset clears all configuration, so restart the card if it was running
+				let mut mac_addr = [0; 6];
+				dev.pio_handles().reset(cb.gcb(), &mut mac_addr).await?;
+				dev.vals.rx_next_page.set(mem::RX_FIRST_PG);
+				dev.vals.tx_next_page.set(mem::TX_FIRST);
+				if dev.enabled.get() {
+					dev.pio_handles().enable(cb.gcb()).await?;
+				}
+				dev.apply_config(cb.gcb()).await?;
+				let status_cb = ::udi::cb::alloc::<CbList::NicStatus>(cb.gcb(), cb.gcb.channel).await;
+				::udi::meta_nic::nsr_status_ind(status_cb, ::udi::meta_nic::NicStatus::LinkReset);
+				return Ok(None);
+				},
+			}
+			dev.rx_filter.set(filter);
+			dev.apply_config(cb.gcb()).await?;
+			Ok(None)
+		}
     }
 
-	type Future_info_req<'s> = impl ::core::future::Future<Output=()> + 's;
-    fn info_req<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicInfo<'a>, _reset_statistics: bool) -> Self::Future_info_req<'a> {
-        async move { todo!() }
+	type Future_info_req<'s> = impl ::core::future::Future<Output=::udi::meta_nic::InfoAck> + 's;
+    fn info_req<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicInfo<'a>, reset_statistics: bool) -> Self::Future_info_req<'a> {
+        async move {
+			// NOTE: The NE2000 doesn't report link state, so assume 10BASE-T half-duplex
+			::udi::meta_nic::InfoAck {
+				interface_is_active: self.dev().enabled.get(),
+				link_is_active: true,
+				is_full_duplex: false,
+				link_speed: ::udi::meta_nic::LinkSpeed::Mbps(10),
+				stats: self.dev().stats.report(reset_statistics),
+			}
+		}
     }
 }
 
-unsafe impl ::udi::meta_nic::NdTx for ::udi::init::RData<Driver>
+impl ::udi::meta_nic::NdTx for ::udi::init::RData<Driver>
 {
 	type Future_tx_req<'s> = impl ::core::future::Future<Output=()> + 's;
-    fn tx_req<'a>(&'a self, mut cb: ::udi::meta_nic::CbHandleNicTx) -> Self::Future_tx_req<'a> {
+    fn tx_req<'a>(&'a self, cbs: ::udi::meta_nic::TxRequest<'a>) -> Self::Future_tx_req<'a> {
+		// The request's CB (which holds this task) stays valid until the task completes
+		let gcb = cbs.gcb();
+		// Queue up the packets, if another request is already transmitting then it will pick these up
+		cbs.enqueue(&self.tx_queue);
         async move {
-			let mut rv: Option<::udi::meta_nic::CbHandleNicTx> = None;
-			// Linked list of cbs for multiple packets
-			loop {
-				let (mut cur_cb, next) = cb.unlink();
+			while let Some(mut cur_cb) = self.tx_queue.next_submit()
+			{
 				let mut buf = ::core::mem::take(cur_cb.tx_buf_mut());
 
 				let page = {
 					let p = self.vals.tx_next_page.get();
-					let mut next_p = p.wrapping_add( ((buf.len() + 256-1) / 256) as u8 );
+					let mut next_p = p.wrapping_add( buf.len().div_ceil(256) as u8 );
 					if next_p > mem::TX_LAST {
 						next_p -= mem::TX_BUF_SIZE;
 					}
 					self.vals.tx_next_page.set(next_p);
 					p
 				};
-				match self.pio_handles().tx(cur_cb.gcb(), &mut buf, page).await
+				match self.pio_handles().tx(gcb, &mut buf, page).await
 				{
-				Ok(_) => {},
-				Err(_) => {},
+				Ok(_) => self.stats.inc(::udi::meta_nic::Counter::TxPackets),
+				Err(_) => self.stats.inc(::udi::meta_nic::Counter::TxErrors),
 				}
 				*cur_cb.tx_buf_mut() = buf;
-				//buf.free();
-
-				if let Some(ref mut d) = rv {
-					d.link_front(cur_cb);
-				}
-				else {
-					rv = Some(cur_cb);
-				}
-				if let Some(next) = next {
-					cb = next;
-				}
-				else {
-					break;
-				}
-			}
-			if let Some(cb) = rv {
-				// TODO: Is there a risk that `scratch` is resized here?
-				::udi::meta_nic::nsr_tx_rdy(cb);
+				self.tx_queue.complete(cur_cb);
 			}
 		}
     }
 
 	type Future_exp_tx_req<'s> = impl ::core::future::Future<Output=()> + 's;
-    fn exp_tx_req<'a>(&'a self, cb: ::udi::meta_nic::CbHandleNicTx) -> Self::Future_exp_tx_req<'a> {
-        self.tx_req(cb)
+    fn exp_tx_req<'a>(&'a self, cbs: ::udi::meta_nic::TxRequest<'a>) -> Self::Future_exp_tx_req<'a> {
+        self.tx_req(cbs)
     }
 }
-unsafe impl ::udi::meta_nic::NdRx for ::udi::init::RData<Driver>
+impl ::udi::meta_nic::NdRx for ::udi::init::RData<Driver>
 {
-	type Future_rx_rdy<'s> = impl ::core::future::Future<Output=()> + 's;
-    fn rx_rdy<'a>(&'a self, cb: ::udi::meta_nic::CbHandleNicRx) -> Self::Future_rx_rdy<'a> {
-		self.rx_cb_queue.push(cb);
-        async move {}
+    fn rx_rdy(&self, cbs: ::udi::meta_nic::RxChain) {
+		self.rx_cb_queue.push(cbs);
     }
 }
 
@@ -403,7 +569,11 @@ mod regs {
 	pub const PG0_IMR  : u8 = 0x0F;
 
 	// -- Page 1
+	/// Physical Address Registers (6)
+	pub const PG1_PAR0: u8 = 1;
 	pub const PG1_CURR: u8 = 7;
+	/// Multicast Address Registers (8)
+	pub const PG1_MAR0: u8 = 8;
 }
 
 mod mem {
@@ -440,11 +610,12 @@ pub mod udiprops {
 		Intr     : ::udi::ffi::meta_bridge @ udi_intr_attach_cb_t,
 		IntrEvent: ::udi::ffi::meta_bridge @ udi_intr_event_cb_t,
 
-		_IntrDetach: ::udi::ffi::meta_bridge @ udi_intr_detach_cb_t,
+		IntrDetach: ::udi::ffi::meta_bridge @ udi_intr_detach_cb_t,
 
 		Nic    : ::udi::ffi::meta_nic @ udi_nic_cb_t,
 		NicBind: ::udi::ffi::meta_nic @ udi_nic_bind_cb_t,
 		NicCtrl: ::udi::ffi::meta_nic @ udi_nic_ctrl_cb_t,
+		NicStatus: ::udi::ffi::meta_nic @ udi_nic_status_cb_t,
 		NicInfo: ::udi::ffi::meta_nic @ udi_nic_info_cb_t,
 		NicTx  : ::udi::ffi::meta_nic @ udi_nic_tx_cb_t,
 		NicRx  : ::udi::ffi::meta_nic @ udi_nic_rx_cb_t,
@@ -2,6 +2,20 @@ use ::udi::future_ext::FutureExt;
 use super::regs;
 use super::mem;
 
+/// Size of the register window mapped for all of the PIO programs
+const PIO_LENGTH: u32 = 0x20;
+// Check the programs against the mapping at compile time
+const _: () = {
+	use ::udi::pio::validate;
+	assert!(validate(&RESET, PIO_LENGTH).is_ok());
+	assert!(validate(&ENABLE, PIO_LENGTH).is_ok());
+	assert!(validate(&DISBALE, PIO_LENGTH).is_ok());
+	assert!(validate(&RX, PIO_LENGTH).is_ok());
+	assert!(validate(&TX, PIO_LENGTH).is_ok());
+	assert!(validate(&IRQACK, PIO_LENGTH).is_ok());
+	assert!(validate(&CONFIG, PIO_LENGTH).is_ok());
+};
+
 #[derive(Default)]
 pub struct PioHandles {
 	reset: ::udi::pio::Handle,
@@ -9,11 +23,12 @@ pub struct PioHandles {
 	disable: ::udi::pio::Handle,
 	rx: ::udi::pio::Handle,
 	tx: ::udi::pio::Handle,
+	config: ::udi::pio::Handle,
 }
 impl PioHandles {
     pub async fn new(gcb: ::udi::CbRef<'_, ::udi::ffi::udi_cb_t>) -> (Self, ::udi::pio::Handle) {
         let pio_map = |trans_list| ::udi::pio::map(
-            gcb, 0/*UDI_PCI_BAR_0*/, 0x00,0x20,
+            gcb, 0/*UDI_PCI_BAR_0*/, 0x00,PIO_LENGTH,
             trans_list, ::udi::ffi::pio::UDI_PIO_LITTLE_ENDIAN, 0, 0.into()
         );
         (
@@ -23,6 +38,7 @@ impl PioHandles {
                 disable : pio_map(&DISBALE).await,
                 rx      : pio_map(&RX).await,
                 tx      : pio_map(&TX).await,
+                config  : pio_map(&CONFIG).await,
             },
             pio_map(&IRQACK).await
             )
@@ -50,11 +66,21 @@ impl PioHandles {
         ::udi::pio::trans(gcb, &self.rx, 0.into(),Some(buf), Some(unsafe { ::udi::pio::MemPtr::new(::core::slice::from_mut(rx_next_page)) }))
             .map(|res| match res { Ok(s) => Ok(s as usize), Err(e) => Err(e) })
     }
+    pub fn config<'a>(&'a self, gcb: ::udi::CbRef<'_, ::udi::ffi::udi_cb_t>, config: &'a mut [u8; CONFIG_LEN])
+        -> impl ::std::future::Future<Output=::udi::Result<()>> + 'a {
+        ::udi::pio::trans(gcb, &self.config, 0.into(), None, Some(unsafe { ::udi::pio::MemPtr::new(config) }))
+            .map(|res| match res { Ok(_) => Ok(()), Err(e) => Err(e) })
+    }
     pub async fn tx(&self, gcb: ::udi::CbRef<'_, ::udi::ffi::udi_cb_t>, buf: &mut ::udi::buf::Handle, tx_next_page: u8) -> ::udi::Result<()> {
-        let len = (buf.len() as u16).to_ne_bytes();
+        let data_len = buf.len();
+        // The data is written a word at a time, so pad odd lengths out to a whole word
+        buf.ensure_size(gcb, (data_len + 1) & !1).await;
+        let len = (data_len as u16).to_ne_bytes();
         let mut mem_buf = [len[0], len[1], tx_next_page];
         let mem_ptr = unsafe { ::udi::pio::MemPtr::new(&mut mem_buf) };
-        ::udi::pio::trans(gcb, &self.tx, ::udi::ffi::udi_index_t(0), Some(buf), Some(mem_ptr)).await?;
+        let rv = ::udi::pio::trans(gcb, &self.tx, ::udi::ffi::udi_index_t(0), Some(buf), Some(mem_ptr)).await;
+        buf.truncate(data_len);
+        rv?;
         Ok( () )
     }
 }
@@ -64,11 +90,11 @@ impl PioHandles {
 	IN.B R0, regs::APG_RESET as _;
 	OUT.B regs::APG_RESET as _, R0;
 	// Wait for ISR[7] to set
-	LABEL 1;
+	LABEL wait_reset;
 	 IN.B R0, regs::PG0_ISR as _;
 	 AND_IMM.B R0, 0x80;
 	 CSKIP.B R0 NZ;
-	 BRANCH 1;
+	 BRANCH wait_reset;
 	// Write back the 0x80 currently in `R0`
 	OUT.B regs::PG0_ISR as _, R0;
 
@@ -133,6 +159,9 @@ impl PioHandles {
     // TPSR = 0x40 [TX Start]
     LOAD_IMM.B R0, 0x40;
     OUT.B regs::PG0W_TPSR as _, R0;
+    // IMR = 0x3F [Enable, cleared by a reset]
+    LOAD_IMM.B R0, 0x3F;
+    OUT.B regs::PG0_IMR as _, R0;
     END_IMM 0;
 }
 ::udi::define_pio_ops!{pub DISBALE =
@@ -142,6 +171,36 @@ impl PioHandles {
     END_IMM 0;
 }
 
+/// Size of the memory buffer for [CONFIG]
+pub const CONFIG_LEN: usize = 1 + 6 + 8;
+// Expects `mem` to be `rcr: u8, par: [u8; 6], mar: [u8; 8]`
+::udi::define_pio_ops!{pub CONFIG =
+    // RCR
+    LOAD_IMM.B R0, 0;
+    LOAD.B R1, [mem R0];
+    OUT.B regs::PG0_RCR as _, R1;
+    // CMD = Page1 [NoDMA, keep Start/Stop]
+    IN.B R2, regs::APG_CMD as _;
+    AND_IMM.B R2, 0x03;
+    LOAD.B R3, R2;
+    OR_IMM.B R3, 0x60;
+    OUT.B regs::APG_CMD as _, R3;
+    // PAR0-5
+    LOAD_IMM.B R0, 1;
+    LOAD_IMM.B R1, regs::PG1_PAR0;
+    LOAD_IMM.B R4, 6;
+    REP_OUT_IND.B [mem R0 STEP1], R1 STEP1, R4;
+    // MAR0-7
+    LOAD_IMM.B R0, 1+6;
+    LOAD_IMM.B R1, regs::PG1_MAR0;
+    LOAD_IMM.B R4, 8;
+    REP_OUT_IND.B [mem R0 STEP1], R1 STEP1, R4;
+    // CMD = Page0 [NoDMA, keep Start/Stop]
+    OR_IMM.B R2, 0x20;
+    OUT.B regs::APG_CMD as _, R2;
+    END_IMM 0;
+}
+
 // NOTE: This expects `rx_next_page` as the input memory buffer
 ::udi::define_pio_ops!{pub RX =
     // Get current page into R7
@@ -173,11 +232,11 @@ impl PioHandles {
     // - Subtract 256-4 from R5(length), if <=0 we've grabbed the entire packet
     LOAD_IMM.B R3, (256-4) as u8;   // R3 is the offset in the output buffer, we've loaded 126 words currently
     ADD_IMM.S R5, -(256i16-4) as u16;
-LABEL 2;
+LABEL check_length;
     CSKIP.S R5 NZ;
-    BRANCH 1;   // 1: End if R5(length)==0
+    BRANCH done;   // End if R5(length)==0
     CSKIP.S R5 NNeg;
-    BRANCH 1;   // 1: End if R5(length)<0
+    BRANCH done;   // End if R5(length)<0
     // - Read pages until all of packet RXd
     ADD_IMM.B R7, 1;
     STORE.B R0, R7;
@@ -207,10 +266,10 @@ LABEL 2;
     ADD_IMM.S R3, 256;
     ADD_IMM.S R5, -256i16 as u16;
     // - Jump to length check
-    BRANCH 2;   // 2: Check against length
+    BRANCH check_length;
 
     // Cleanup
-    LABEL 1;
+    LABEL done;
     // - Update next RX page, return status
     OUT.B regs::PG0_BNRY as _, R7;
     STORE.S R7, R6;  // Saved tuple of Status,Next
@@ -252,14 +311,14 @@ LABEL 2;
     LOAD_IMM.B R1, regs::APG_MEM;
     LOAD.S R2, R6;
     ADD_IMM.S R2, 1;    SHIFT_RIGHT.S R2, 1;    // (len+1)/2 to write u16s
-    REP_OUT_IND.S [buf R0 STEP1], R1, R2;
+    REP_OUT_IND.S [buf R0 STEP2], R1, R2;
 
     // Wait for 0x40 (RDMA Complete) in ISR
-    LABEL 1;
+    LABEL wait_rdma;
     IN.B R0, regs::PG0_ISR as _;
     AND_IMM.B R0, 0x40;
     CSKIP.B R0 NZ;
-    BRANCH 1;
+    BRANCH wait_rdma;
     // ACK/clear the Interrupt
     OUT.B regs::PG0_ISR as _, R0;
 
@@ -284,16 +343,14 @@ LABEL 2;
         OUT.B regs::PG0_ISR as _, R0;
         END_IMM 0;
         // 1: Normal
+        // - Returns the ISR in the upper byte, clear of the `UDI_INTR_*` result flags
         LABEL 1;
         IN.B R0, regs::PG0_ISR as _;
         OUT.B regs::PG0_ISR as _, R0;
-        CSKIP.B R0 Z;   // if R0!=0
+        CSKIP.B R0 NZ;  // if R0==0
+        END_IMM ::udi::ffi::meta_bridge::UDI_INTR_UNCLAIMED;    // - No IRQ, quiet quit
+        SHIFT_LEFT.S R0, 8;
         END.S R0;
-        // - No IRQ, quiet quit
-        LOAD_IMM.B R0, ::udi::ffi::meta_bridge::UDI_INTR_UNCLAIMED as _;
-        LOAD_IMM.B R1, 0;   // scratch offset
-        STORE.B [scratch R1], R0;
-        END_IMM 0;
         // 2: Overrun
         LABEL 2;
         // 3: Overrun irqs
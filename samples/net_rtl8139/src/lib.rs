@@ -17,6 +17,8 @@ struct Driver
 	init: ::core::cell::OnceCell<InitState>,
 	/// Wait state for the interrupt handler registration/binding
 	intr_bound: ::udi::async_helpers::Wait< ::udi::Result<()> >,
+	/// Interrupt event CBs
+	intr_pool: ::udi::meta_bridge::IntrEventPool,
 
 	/// A FIFO queue of RX CBs for incoming packets
 	rx_cb_queue: ::udi::meta_nic::ReadCbQueue,
@@ -93,9 +95,9 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
 				(::udi::init::EnumerateResultOk::new::<OpsList::Ctrl>(0).into(), attrs_out)
 				},
 			udi::init::EnumerateLevel::Next => (::udi::init::EnumerateResult::Done, attrs_out),
-			udi::init::EnumerateLevel::New => todo!(),
-			udi::init::EnumerateLevel::Directed => todo!(),
-			udi::init::EnumerateLevel::Release => todo!(),
+			udi::init::EnumerateLevel::New
+			|udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+			udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
 			}
 		}
     }
@@ -131,22 +133,21 @@ impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
 			let (pio_handles,irq_ack) = pio_ops::PioHandles::new(cb.gcb()).await;
 
 			let intr_channel = ::udi::imc::channel_spawn::<OpsList::Irq>(cb.gcb(), self, /*interrupt number*/0.into()).await;
-			let mut intr_cb = ::udi::cb::alloc::<CbList::Intr>(cb.gcb(), ::udi::get_gcb_channel().await).await;
-			intr_cb.init(0.into(), 2, irq_ack);
-			::udi::meta_bridge::intr_attach_req(intr_cb);
+			let intr_cb = ::udi::cb::alloc::<CbList::Intr>(cb.gcb(), ::udi::get_gcb_channel().await).await;
+			::udi::meta_bridge::intr_attach(intr_cb, 0.into(), 2, Some(irq_ack));
 			self.intr_bound.wait(cb.gcb()).await?;
 
 			let dma_constraints = {
-				use ::udi::ffi::physio::udi_dma_constraints_attr_spec_t as Spec;
-				use ::udi::ffi::physio;
-				let mut dc = dma_constraints;
-				dc.set(cb.gcb(), &[
+				let attrs = ::udi::physio::dma::ConstraintsBuilder::new()
 					// 32-bit device
-					Spec { attr_type: physio::UDI_DMA_ADDRESSABLE_BITS, attr_value: 32 },
+					.addressable_bits(32)
 					// Maximum scatter-gather elements = 1 (only one TX slot)
-					Spec { attr_type: physio::UDI_DMA_SCGTH_MAX_ELEMENTS, attr_value: 1 },
-					Spec { attr_type: physio::UDI_DMA_NO_PARTIAL, attr_value: 1 },
-					]).await?;
+					.max_scgth_elements(1)
+					.no_partial(true)
+					.build()
+					.expect("Invalid DMA constraints");
+				let mut dc = dma_constraints;
+				dc.set(cb.gcb(), &attrs).await?;
 				dc
 				};
 			let dma_handles = {
@@ -188,10 +189,9 @@ impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
 				mac_addr[4] as _,
 				mac_addr[5] as _,
 				);
-			for _ in 0 .. 4/*NUM_INTR_EVENT_CBS*/ {
-				let intr_event_cb = ::udi::cb::alloc::<CbList::IntrEvent>(cb.gcb(), intr_channel.raw()).await;
-				::udi::meta_bridge::intr_event_rdy(intr_event_cb);
-			}
+			// Two CBs posted (matching `min_event_pend`), and two spares
+			self.intr_pool.set_target(2);
+			self.intr_pool.fill::<CbList::IntrEvent>(cb.gcb(), &intr_channel, 2).await;
 			if let Err(_) = self.init.set(InitState {
 				pio_handles,
 				intr_channel,
@@ -227,10 +227,15 @@ impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
 }
 impl ::udi::meta_bridge::IntrHandler for ::udi::init::RData<Driver>
 {
+	fn intr_event_pool(&self) -> Option<&::udi::meta_bridge::IntrEventPool> {
+		Some(&self.intr_pool)
+	}
+
     type Future_intr_event_ind<'s> = impl ::core::future::Future<Output=()>+'s;
-    fn intr_event_ind<'a>(&'a self, cb: ::udi::meta_bridge::CbRefEvent<'a>, _flags: u8) -> Self::Future_intr_event_ind<'a> {
+    fn intr_event_ind<'a>(&'a self, cb: ::udi::meta_bridge::CbRefEvent<'a>, _flags: ::udi::meta_bridge::IntrEventFlags) -> Self::Future_intr_event_ind<'a> {
 		async move {
-			let isr = cb.intr_result;
+			// The IRQACK handle returns the ISR in the upper byte
+			let isr = cb.intr_result().to_raw() >> 8;
 			::udi::debug_printf!("intr_event_ind: ISR=0x%04hx", isr);
 			if isr & pio_ops::FLAG_ISR_ROK != 0 {
 				// RX OK
@@ -355,20 +360,13 @@ impl ::udi::meta_nic::Control for ::udi::ChildBind<Driver,()>
 
 			let mac_addr = &self.dev().init.get().unwrap().mac_addr;
 			Ok(::udi::meta_nic::NicInfo {
-				media_type: ::udi::ffi::meta_nic::MediaType::UDI_NIC_ETHER,
+				media_type: ::udi::meta_nic::MediaType::FastEthernet,
 				min_pdu_size: 0,
 				max_pdu_size: 0,
 				rx_hw_threshold: 2,
-				capabilities: 0,
-				max_perfect_multicast: 0,
-				max_total_multicast: 0,
-				mac_addr_len: 6,
-				mac_addr: [
-					mac_addr[0], mac_addr[1], mac_addr[2],
-					mac_addr[3], mac_addr[4], mac_addr[5],
-					0,0,0,0,
-					0,0,0,0,0,0,0,0,0,0,
-				],
+				capabilities: ::udi::meta_nic::Capabilities::NONE,
+				multicast: ::udi::meta_nic::MulticastFilter { max_perfect: 0, max_total: 0 },
+				mac_addr: ::udi::meta_nic::MacAddress::new(mac_addr),
 			})
 		}
     }
@@ -391,12 +389,12 @@ impl ::udi::meta_nic::Control for ::udi::ChildBind<Driver,()>
 		self.dev().pio_handles().disable(cb.gcb())
     }
 
-	type Future_ctrl_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
-    fn ctrl_req<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicCtrl<'a>) -> Self::Future_ctrl_req<'a> {
+	type Future_ctrl_req<'s> = impl ::core::future::Future<Output=::udi::Result<Option<::udi::meta_nic::MacAddress>>> + 's;
+    fn ctrl_req<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicCtrl<'a>, _op: ::udi::meta_nic::CtrlOp<'a>) -> Self::Future_ctrl_req<'a> {
         async move { todo!() }
     }
 
-	type Future_info_req<'s> = impl ::core::future::Future<Output=()> + 's;
+	type Future_info_req<'s> = impl ::core::future::Future<Output=::udi::meta_nic::InfoAck> + 's;
     fn info_req<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicInfo<'a>, _reset_statistics: bool) -> Self::Future_info_req<'a> {
         async move { todo!() }
     }
@@ -453,7 +451,7 @@ impl Driver
 }
 // SAFE? Not sure if storing in `self.tx_cbs` is fully sound, as it might get completed/used while the CB is processing.
 // - Depends on if the serial-ness of a region is only between async calls
-unsafe impl ::udi::meta_nic::NdTx for ::udi::init::RData<Driver>
+unsafe impl ::udi::meta_nic::compat::NdTx for ::udi::init::RData<Driver>
 {
 	type Future_tx_req<'s> = impl ::core::future::Future<Output=()> + 's;
     fn tx_req<'a>(&'a self, mut cb: ::udi::meta_nic::CbHandleNicTx) -> Self::Future_tx_req<'a> {
@@ -483,7 +481,7 @@ unsafe impl ::udi::meta_nic::NdTx for ::udi::init::RData<Driver>
     }
 }
 // SAFE: Just pushes to a list
-unsafe impl ::udi::meta_nic::NdRx for ::udi::init::RData<Driver>
+unsafe impl ::udi::meta_nic::compat::NdRx for ::udi::init::RData<Driver>
 {
 	type Future_rx_rdy<'s> = impl ::core::future::Future<Output=()> + 's;
     fn rx_rdy<'a>(&'a self, cb: ::udi::meta_nic::CbHandleNicRx) -> Self::Future_rx_rdy<'a> {
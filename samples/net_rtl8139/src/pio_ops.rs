@@ -330,7 +330,11 @@ impl MemRxUpdate {
     // - Read ISR and ack all set bits
     IN.S R0, Regs::Isr as _;
     OUT.S Regs::Isr as _, R0;
-    END.B R0;
+    CSKIP.S R0 NZ;  // if R0==0
+    END_IMM ::udi::ffi::meta_bridge::UDI_INTR_UNCLAIMED;
+    // - Return the low byte of the ISR in the upper byte, clear of the `UDI_INTR_*` result flags
+    SHIFT_LEFT.S R0, 8;
+    END.S R0;
     // 2: Overrun
     LABEL 2;
     // 3: Hande overrun irqs
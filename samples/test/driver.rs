@@ -31,9 +31,9 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
 			|::udi::init::EnumerateLevel::StartRescan
 			|::udi::init::EnumerateLevel::Next
 				=> (::udi::init::EnumerateResult::Done, attrs_out),
-			::udi::init::EnumerateLevel::New => todo!("EnumerateLevel::New"),
-			::udi::init::EnumerateLevel::Directed => todo!(),
-			::udi::init::EnumerateLevel::Release => todo!(),
+			::udi::init::EnumerateLevel::New
+			|::udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+			::udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
 			}
 		}
     }
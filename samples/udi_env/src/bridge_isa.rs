@@ -0,0 +1,273 @@
+//! Sample PCI-to-ISA bridge
+//!
+//! Binds to the PCI bridge as a device, and enumerates a fixed set of emulated ISA devices. Each child gets ISA DMA
+//! constraints, and its interrupt is routed through a [::udi::meta_bridge::IntrDispatchTable].
+#[derive(Default)]
+struct Driver {
+    enum_dev_idx: ::std::cell::Cell<usize>,
+    /// Channel to the PCI bridge, set once bound
+    bus_channel: ::std::cell::Cell<Option<::udi::ffi::udi_channel_t>>,
+}
+
+struct IsaDevice {
+    factory: fn() -> Box<dyn crate::emulated_devices::PioDevice>,
+    io_base: u16,
+    irq: u8,
+}
+/// ISA devices don't identify themselves, so this is the configuration of the emulated machine
+static DEVICES: &[IsaDevice] = &[
+    // An NE2000 compatible at its traditional address
+    IsaDevice { factory: || crate::emulated_devices::Rtl8029::new_boxed(), io_base: 0x300, irq: 9 },
+    // COM1
+    IsaDevice { factory: || crate::emulated_devices::XTSerial::new_boxed(), io_base: 0x3F8, irq: 4 },
+];
+
+fn get_driver_instance(gcb: ::udi::CbRef<::udi::ffi::udi_cb_t>) -> ::std::sync::Arc<crate::DriverInstance> {
+    unsafe { crate::channels::get_other_instance(&gcb.channel) }
+}
+
+impl ::udi::init::Driver for ::udi::init::RData<Driver>
+{
+    const MAX_ATTRS: u8 = 3;
+    type Future_init<'s> = impl ::core::future::Future<Output=()>;
+    fn usage_ind<'s>(&'s self, _cb: udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+        async move { }
+    }
+
+    type Future_enumerate<'s> = impl ::core::future::Future<Output=(udi::init::EnumerateResult,udi::init::AttrSink<'s>)> + 's;
+    fn enumerate_req<'s>(
+        &'s self,
+        _cb: udi::init::CbRefEnumerate<'s>,
+        level: udi::init::EnumerateLevel,
+        mut attrs_out: udi::init::AttrSink<'s>
+    ) -> Self::Future_enumerate<'s>
+    {
+        fn enumerate_dev(this: &Driver, attrs_out: &mut udi::init::AttrSink<'_>) -> ::udi::init::EnumerateResult {
+            let child_idx = this.enum_dev_idx.get();
+            if let Some(c) = DEVICES.get(child_idx) {
+                this.enum_dev_idx.set( child_idx + 1 );
+                attrs_out.push_string("bus_type", "isa");
+                attrs_out.push_u32("isa_io_base", c.io_base as _);
+                attrs_out.push_u32("isa_irq", c.irq as _);
+                ::udi::init::EnumerateResult::ok::<OpsList::Bridge>(child_idx as _)
+            }
+            else {
+                ::udi::init::EnumerateResult::Done
+            }
+        }
+        async move {
+            match level
+            {
+            ::udi::init::EnumerateLevel::Start
+            |::udi::init::EnumerateLevel::StartRescan => {
+                self.enum_dev_idx.set( 0 );
+                let rv = enumerate_dev(self, &mut attrs_out);
+                (rv, attrs_out)
+                },
+            udi::init::EnumerateLevel::Next => {
+                let rv = enumerate_dev(self, &mut attrs_out);
+                (rv, attrs_out)
+                },
+            udi::init::EnumerateLevel::New
+            |udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+            udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
+            }
+        }
+    }
+
+    type Future_devmgmt<'s> = impl ::core::future::Future<Output=::udi::Result<u8>> + 's;
+    fn devmgmt_req<'s>(&'s self, cb: udi::init::CbRefMgmt<'s>, mgmt_op: udi::init::MgmtOp, _parent_id: udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+        async move {
+            match mgmt_op
+            {
+            // Children are unbound by the MA before their parent, so only the PCI binding remains
+            ::udi::init::MgmtOp::Unbind => {
+                if let Some(bus_channel) = self.bus_channel.take() {
+                    let bind_cb = ::udi::cb::alloc::<CbList::BusBind>(cb.gcb(), bus_channel).await;
+                    let _ = ::udi::meta_bridge::bus_unbind(cb.gcb(), bind_cb).await;
+                }
+                },
+            // Nothing to save or restore, the bridge function has no state of its own
+            _ => {},
+            }
+            Ok(0)
+        }
+    }
+}
+
+impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver>
+{
+    type Future_bind_ack<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn bus_bind_ack<'a>(
+        &'a self,
+        cb: ::udi::meta_bridge::CbRefBind<'a>,
+        _dma_constraints: ::udi::physio::dma::DmaConstraints,
+        _preferred_endianness: ::udi::meta_bridge::PreferredEndianness,
+        status: ::udi::ffi::udi_status_t
+    ) -> Self::Future_bind_ack<'a> {
+        async move {
+            // The bridge function itself doesn't do DMA or raise interrupts, so there's nothing to set up
+            println!("ISA bridge bound to PCI");
+            ::udi::Error::from_status(status)?;
+            self.bus_channel.set(Some(cb.gcb.channel));
+            Ok( () )
+        }
+    }
+
+    type Future_unbind_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn bus_unbind_ack<'a>(&'a self, _cb: ::udi::meta_bridge::CbRefBind<'a>) -> Self::Future_unbind_ack<'a> {
+        async move {
+        }
+    }
+
+    type Future_intr_attach_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn intr_attach_ack<'a>(&'a self, _cb: ::udi::meta_bridge::CbRefIntrAttach<'a>, _status: ::udi::ffi::udi_status_t) -> Self::Future_intr_attach_ack<'a> {
+        async move {
+        }
+    }
+
+    type Future_intr_detach_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn intr_detach_ack<'a>(&'a self, _cb: ::udi::meta_bridge::CbRefIntrDetach<'a>) -> Self::Future_intr_detach_ack<'a> {
+        async move {
+        }
+    }
+}
+
+#[derive(Default)]
+struct ChildState {
+    interrupts: ::std::sync::Arc< ::udi::meta_bridge::IntrDispatchTable<1> >,
+}
+impl ::udi::meta_bridge::BusBridge for ::udi::ChildBind<Driver,ChildState>
+{
+    type Future_bind_req<'s> = impl ::core::future::Future<Output=::udi::Result<(::udi::meta_bridge::PreferredEndianness,::udi::physio::dma::DmaConstraints)>> + 's;
+    fn bus_bind_req<'a>(&'a self, cb: ::udi::meta_bridge::CbRefBind<'a>) -> Self::Future_bind_req<'a> {
+        async move {
+            let dev_desc = &DEVICES[self.child_id() as usize];
+            println!("ISA Bind Request: #{:#x} (io {:#x})", self.child_id(), dev_desc.io_base);
+            get_driver_instance(cb.gcb())
+                .device
+                .set( (dev_desc.factory)() )
+                .ok()
+                .expect("Driver instance bound to multiple devices?");
+            // The ISA DMA controller: 24-bit addresses, a single element per transfer, and at most 64KiB per element
+            let attrs = ::udi::physio::dma::ConstraintsBuilder::new()
+                .addressable_bits(24)
+                .max_scgth_elements(1)
+                .element_length_bits(16)
+                .build()
+                .expect("ISA DMA constraints");
+            let mut dma_constraints = ::udi::physio::dma::DmaConstraints::null();
+            dma_constraints.set(cb.gcb(), &attrs).await?;
+            Ok((::udi::meta_bridge::PreferredEndianness::Little, dma_constraints))
+        }
+    }
+
+    type Future_unbind_req<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn bus_unbind_req<'a>(&'a self, cb: ::udi::meta_bridge::CbRefBind<'a>) -> Self::Future_unbind_req<'a> {
+        async move {
+            let di = get_driver_instance(cb.gcb());
+            for interrupt_idx in self.interrupts.attached() {
+                println!("ISA Unbind Request: #{:#x} still had interrupt {} attached", self.child_id(), interrupt_idx.0);
+                self.detach_interrupt(&di, interrupt_idx);
+            }
+        }
+    }
+
+    type Future_intr_attach_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn intr_attach_req<'a>(&'a self, cb: ::udi::meta_bridge::CbRefIntrAttach<'a>) -> Self::Future_intr_attach_req<'a> {
+        async move {
+            // Each ISA device has a single IRQ line
+            if cb.interrupt_index.0 != 0 {
+                return ::udi::Error::from_status(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD as _);
+            }
+            self.interrupts.attach::<OpsList::Interrupt>(cb, self).await?;
+
+            let dev_desc = &DEVICES[self.child_id() as usize];
+            println!("ISA #{:#x}: Routing IRQ {} to interrupt {}", self.child_id(), dev_desc.irq, cb.interrupt_index.0);
+            get_driver_instance(cb.gcb())
+                .device.get().expect("Driver instance not bound to a device")
+                .irq(cb.interrupt_index.0)
+                .bind(::std::sync::Arc::new(InterruptRoute {
+                    table: self.interrupts.clone(),
+                    interrupt_idx: cb.interrupt_index,
+                }));
+            Ok( () )
+        }
+    }
+
+    type Future_intr_detach_req<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn intr_detach_req<'a>(&'a self, cb: ::udi::meta_bridge::CbRefIntrDetach<'a>) -> Self::Future_intr_detach_req<'a> {
+        if self.interrupts.is_attached(cb.interrupt_idx) {
+            self.detach_interrupt(&get_driver_instance(cb.gcb()), cb.interrupt_idx);
+        }
+        else {
+            println!("intr_detach_req: Interrupt {} is not attached", cb.interrupt_idx.0);
+        }
+        async move { }
+    }
+}
+impl ChildState {
+    /// Disconnect an interrupt from the device, then release its channel and event CBs
+    fn detach_interrupt(&self, di: &crate::DriverInstance, interrupt_idx: ::udi::ffi::udi_index_t) {
+        di.device.get().expect("Driver instance not bound to a device")
+            .irq(interrupt_idx.0)
+            .unbind();
+        self.interrupts.detach(interrupt_idx);
+    }
+}
+impl ::udi::meta_bridge::IntrDispatcher for ::udi::ChildBind<Driver,ChildState>
+{
+    type Future_intr_event_rdy<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn intr_event_rdy<'a>(&'a self, _cb: ::udi::meta_bridge::CbRefEvent) -> Self::Future_intr_event_rdy<'a> {
+        async move {
+        }
+    }
+
+    fn intr_event_ret(&self, cb: udi::meta_bridge::CbHandleEvent) {
+        if let Some(interrupt_idx) = self.interrupts.event_ret(cb) {
+            println!("ISA #{:#x}: Interrupt {} enabled", self.child_id(), interrupt_idx.0);
+        }
+    }
+}
+
+/// Routes an emulated device's interrupt line to the dispatch table
+struct InterruptRoute {
+    table: ::std::sync::Arc< ::udi::meta_bridge::IntrDispatchTable<1> >,
+    interrupt_idx: ::udi::ffi::udi_index_t,
+}
+impl crate::emulated_devices::InterruptHandler for InterruptRoute {
+    fn raise(&self) {
+        if !self.table.dispatch(self.interrupt_idx) {
+            println!("ISA: Interrupt {} not delivered", self.interrupt_idx.0);
+        }
+    }
+}
+
+::udi_macros::udiprops!("
+name 100
+properties_version 0x101
+requires udi_bridge 0x101
+meta 1 udi_bridge
+# Meta 1, region 0, Ops 1 (Device), CB 1 (BusBind)
+parent_bind_ops 1 0 1 1
+child_bind_ops 1 0 2
+device 101 1 bus_type string pci pci_base_class ubit32 6 pci_sub_class ubit32 1
+message 100 PCI-to-ISA bridge
+message 101 ISA bridge
+region 0
+");
+const META_BRIDGE: ::udi::ffi::udi_index_t = udiprops::meta::udi_bridge;
+::udi::define_driver! {
+    Driver as INIT_INFO_ISA;
+    ops: {
+        Device   : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_bus_device_ops_t,
+        Bridge   : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_bus_bridge_ops_t : ChildBind<_,ChildState>,
+        Interrupt: Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_dispatcher_ops_t : ChildBind<_,ChildState>,
+    },
+    cbs: {
+        BusBind    : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_bus_bind_cb_t,
+        _IntrAttach: Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_attach_cb_t,
+        _IntrDetach: Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_detach_cb_t,
+        _IntrEvent : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_event_cb_t,
+    }
+}
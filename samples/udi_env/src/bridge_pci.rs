@@ -14,6 +14,8 @@ static DEVICES: &[EmulatedDevice] = &[
     EmulatedDevice { factory: |_| crate::emulated_devices::rtl8139::Device::new_boxed(), vendor_id: 0x10ec, device_id: 0x8139, class_word: 0 },
     // A "Generic XT-Compatible Serial Controller"
     EmulatedDevice { factory: |_| crate::emulated_devices::XTSerial::new_boxed(), vendor_id: 0x8086, device_id: 0xFFFF, class_word: 0x07_00_00 },
+    // A PCI-to-ISA bridge (see `bridge_isa`)
+    EmulatedDevice { factory: |_| crate::emulated_devices::IsaBridge::new_boxed(), vendor_id: 0x8086, device_id: 0x7000, class_word: 0x06_01_00 },
 ];
 
 fn get_driver_instance(gcb: ::udi::CbRef<::udi::ffi::udi_cb_t>) -> ::std::sync::Arc<crate::DriverInstance> {
@@ -65,9 +67,9 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
                 let rv = enumerate_dev(self, &mut attrs_out);
                 (rv, attrs_out)
                 },
-			udi::init::EnumerateLevel::New => todo!(),
-			udi::init::EnumerateLevel::Directed => todo!(),
-			udi::init::EnumerateLevel::Release => todo!(),
+			udi::init::EnumerateLevel::New
+			|udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+			udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
 			}
         }
     }
@@ -75,7 +77,8 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
     type Future_devmgmt<'s> = impl ::core::future::Future<Output=::udi::Result<u8>> + 's;
     fn devmgmt_req<'s>(&'s self, _cb: udi::init::CbRefMgmt<'s>, _mgmt_op: udi::init::MgmtOp, _parent_id: udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
         async move {
-            todo!("devmgmt_req");
+            // The root bridge has no parent, and its children are unbound by the MA before it
+            Ok(0)
         }
     }
 }
@@ -89,8 +92,11 @@ struct ChildState {
 }
 struct InterruptHandler {
     cbs: ::std::sync::Arc<CbQueue>,
+    interrupt_idx: ::udi::ffi::udi_index_t,
     channel: ::udi::imc::ChannelHandle,
     preproc: ::udi::pio::Handle,
+    /// Set when an interrupt is dropped due to no CBs being available, reported with the next event
+    overrun: ::std::sync::atomic::AtomicBool,
 }
 #[derive(Default)]
 struct CbQueue {
@@ -98,7 +104,7 @@ struct CbQueue {
 }
 impl ::udi::meta_bridge::BusBridge for ::udi::ChildBind<Driver,ChildState>
 {
-    type Future_bind_req<'s> = impl ::core::future::Future<Output=::udi::Result<(::udi::meta_bridge::PreferredEndianness,)>> + 's;
+    type Future_bind_req<'s> = impl ::core::future::Future<Output=::udi::Result<(::udi::meta_bridge::PreferredEndianness,::udi::physio::dma::DmaConstraints)>> + 's;
     fn bus_bind_req<'a>(&'a self, cb: ::udi::meta_bridge::CbRefBind<'a>) -> Self::Future_bind_req<'a> {
         async move {
             println!("PCI Bind Request: #{:#x}", self.child_id());
@@ -110,7 +116,14 @@ impl ::udi::meta_bridge::BusBridge for ::udi::ChildBind<Driver,ChildState>
                 .set( (dev_desc.factory)( dev_desc ) )
                 .ok()
                 .expect("Driver instance bound to multiple devices?");
-            Ok((::udi::meta_bridge::PreferredEndianness::Little,))
+            // PCI bus mastering: 32-bit addresses, with unrestricted scatter-gather
+            let attrs = ::udi::physio::dma::ConstraintsBuilder::new()
+                .addressable_bits(32)
+                .build()
+                .expect("PCI DMA constraints");
+            let mut dma_constraints = ::udi::physio::dma::DmaConstraints::null();
+            dma_constraints.set(cb.gcb(), &attrs).await?;
+            Ok((::udi::meta_bridge::PreferredEndianness::Little, dma_constraints))
         }
     }
 
@@ -119,6 +132,11 @@ impl ::udi::meta_bridge::BusBridge for ::udi::ChildBind<Driver,ChildState>
         async move {
             let di = get_driver_instance(cb.gcb());
             assert!(di.device.get().is_some());
+            // Clean up after drivers that didn't detach their interrupt
+            if let Some(handler) = self.handler.take() {
+                println!("PCI Unbind Request: #{:#x} still had an interrupt attached", self.child_id());
+                self.detach_handler(&di, handler);
+            }
         }
     }
 
@@ -133,8 +151,10 @@ impl ::udi::meta_bridge::BusBridge for ::udi::ChildBind<Driver,ChildState>
 
             let handler = ::std::sync::Arc::new(InterruptHandler {
                 cbs: self.irq_cbs.clone(),
+                interrupt_idx: cb.interrupt_index,
                 channel,
                 preproc: preproc_handle,
+                overrun: Default::default(),
             });
             self.min_event_pend.set( cb.min_event_pend );
             self.handler.set( Some(handler.clone()) );
@@ -150,10 +170,29 @@ impl ::udi::meta_bridge::BusBridge for ::udi::ChildBind<Driver,ChildState>
     type Future_intr_detach_req<'s> = impl ::core::future::Future<Output=()> + 's;
     fn intr_detach_req<'a>(&'a self, cb: ::udi::meta_bridge::CbRefIntrDetach<'a>) -> Self::Future_intr_detach_req<'a> {
         let di = get_driver_instance(cb.gcb());
+        match self.handler.take() {
+        Some(handler) if handler.interrupt_idx == cb.interrupt_idx => self.detach_handler(&di, handler),
+        handler => {
+            println!("intr_detach_req: Interrupt {} is not attached", cb.interrupt_idx.0);
+            self.handler.set(handler);
+            },
+        }
+        async move { }
+    }
+}
+impl ChildState {
+    /// Disconnect an interrupt handler from the device, and release its event CBs
+    fn detach_handler(&self, di: &crate::DriverInstance, handler: ::std::sync::Arc<InterruptHandler>) {
         di.device.get().expect("Driver instance not bound to a device")
-            .irq(cb.interrupt_idx.0)
+            .irq(handler.interrupt_idx.0)
             .unbind();
-        async move { }
+        self.intr_enabled.set(false);
+        // Free the pending CBs, then the handler (closing the dispatcher's end of the channel)
+        let mut cbs = ::core::mem::take(&mut *self.irq_cbs.queue.lock().unwrap());
+        while let Some(cb) = cbs.pop_front() {
+            drop(cb);
+        }
+        drop(handler);
     }
 }
 impl ::udi::meta_bridge::IntrDispatcher for ::udi::ChildBind<Driver,ChildState>
@@ -188,6 +227,12 @@ impl ::udi::meta_bridge::IntrDispatcher for ::udi::ChildBind<Driver,ChildState>
         }
     }
 }
+impl Drop for InterruptHandler {
+    fn drop(&mut self) {
+        // SAFE: This is the dispatcher's end of the channel, and it's no longer used
+        unsafe { ::udi::ffi::imc::udi_channel_close(::core::mem::take(&mut self.channel).into_raw()); }
+    }
+}
 impl InterruptHandler {
     fn enable(&self) {
         if !self.preproc.as_raw().is_null() {
@@ -234,18 +279,20 @@ impl crate::emulated_devices::InterruptHandler for InterruptHandler {
             let mut cb_queue = self.cbs.queue.lock().unwrap();
             let Some(cb) = cb_queue.pop_front() else {
                 println!("No interrupt CBs!");
+                self.overrun.store(true, ::std::sync::atomic::Ordering::SeqCst);
                 return ;
             };
-            if cb_queue.is_empty() {
-                // Overrun! - set a flag so the overrun happenss
-            }
             cb
         };
+        let flags = if self.overrun.swap(false, ::std::sync::atomic::Ordering::SeqCst) {
+            ::udi::ffi::meta_bridge::UDI_INTR_OVERRUN_OCCURRED
+        } else {
+            0
+        };
 
         cb.set_channel(&self.channel);
         if self.preproc.as_raw().is_null() {
             println!("No preprocess");
-            let flags = 0;
             unsafe {
                 ::udi::ffi::meta_bridge::udi_intr_event_ind(cb.into_raw(), flags);
             }
@@ -253,7 +300,8 @@ impl crate::emulated_devices::InterruptHandler for InterruptHandler {
         else {
             unsafe {
                 // NOTE: `scratch` has at least one byte available, as it was used for async above
-                ::core::ptr::write(cb.gcb.scratch as *mut u8, 0);
+                // - Stash the flags there for the completion callback
+                ::core::ptr::write(cb.gcb.scratch as *mut u8, flags);
                 let buf = cb.event_buf;
                 ::udi::ffi::pio::udi_pio_trans(
                     callback, cb.into_raw() as *mut _,
@@ -272,7 +320,7 @@ impl crate::emulated_devices::InterruptHandler for InterruptHandler {
                     println!("Preprocess complete");
                     let cb = gcb as *mut ::udi::ffi::meta_bridge::udi_intr_event_cb_t;
                     let flags = ::core::ptr::read( (*cb).gcb.scratch as *const u8 )
-                        /*| ::udi::ffi::meta_bridge::UDI_INTR_PREPROCESSED*/
+                        | ::udi::ffi::meta_bridge::UDI_INTR_PREPROCESSED
                         ;
                     (*cb).event_buf = buf;
                     (*cb).intr_result = res;
@@ -161,26 +161,69 @@ pub unsafe fn anchor_with_context<T: 'static>(
 pub unsafe fn remote_call<O: udi::metalang_trait::MetalangOpsHandler, Cb: udi::metalang_trait::MetalangCb>(
     name: &'static str, cb: *mut Cb, call: impl FnOnce(&O, *mut Cb) + 'static)
 {
-    // Get the channel currently in the cb, and reverse it
     let gcb = cb as *mut ::udi::ffi::udi_cb_t;
+    let meta_name = <Cb::MetalangSpec as ::udi::metalang_trait::Metalanguage>::name();
+    let ch_side = switch_to_remote(gcb, meta_name, Cb::META_CB_NUM, ::core::any::type_name::<O>(), name, ::core::any::type_name::<Cb>());
+
+    // Then check that the metalanguage ops in that side matches the expectation
+    if ch_side.ops.type_id() != ::std::any::TypeId::of::<O>() {
+        panic!("Metalang mismatch: Expected {:?}, got {:?}", ch_side.ops.type_name(), ::std::any::type_name::<O>());
+    }
+    let ops = &*(ch_side.ops as *const _ as *const O);
+
+    crate::async_call(cb as *mut _, move |cb| call(ops, cb as *mut Cb));
+}
+/// Call through a channel for a metalanguage called using `udi_mei_call`
+/// 
+/// - `spec` is the metalanguage, and `meta_ops_num` is the expected ops structure on the remote end
+/// - `call` invokes the operation using the remote ops vector
+pub unsafe fn remote_call_mei(
+    name: &'static str,
+    gcb: *mut ::udi::ffi::udi_cb_t,
+    meta_name: &str,
+    spec: &dyn udi::metalang_trait::Metalanguage,
+    meta_ops_num: ::udi::ffi::udi_index_t,
+    meta_cb_num: ::udi::ffi::udi_index_t,
+    call: impl FnOnce(::udi::ffi::udi_ops_vector_t, *mut ::udi::ffi::udi_cb_t) + 'static)
+{
+    let ch_side = switch_to_remote(gcb, meta_name, meta_cb_num, meta_name, name, "(mei)");
+
+    let ops_vector = ch_side.ops as *const _ as ::udi::ffi::udi_ops_vector_t;
+    match spec.get_ops(meta_ops_num, ops_vector) {
+    Some(expected) if expected.type_id() == ch_side.ops.type_id() => {},
+    _ => panic!("Metalang mismatch: Expected ops {} of {}, got {:?}", meta_ops_num, meta_name, ch_side.ops.type_name()),
+    }
+
+    crate::async_call(gcb, move |cb| call(ops_vector, cb));
+}
+/// Common part of remote calls: Moves the CB to the other end of its channel (updating the scratch size)
+unsafe fn switch_to_remote<'a>(
+    gcb: *mut ::udi::ffi::udi_cb_t,
+    meta_name: &str,
+    meta_cb_num: ::udi::ffi::udi_index_t,
+    ops_name: &str,
+    name: &str,
+    cb_name: &str,
+) -> &'a ChannelInnerSide
+{
+    // Get the channel currently in the cb, and reverse it
     let ch = ChannelRef::from_handle((*gcb).channel);
     let ch_side = ch.0.sides[!ch.1 as usize].get().expect("Calling with no remote handle");
     
     // Get the scratch as the max of all CB instances for this type
     let driver_module = &*ch_side.driver_instance.module;
-    let meta_name = <Cb::MetalangSpec as ::udi::metalang_trait::Metalanguage>::name();
     let Some(meta_idx) = driver_module.get_metalang_by_name(meta_name) else {
         panic!("No metalang `{}` in driver ({driver_module:p}) '{}'?!", meta_name, driver_module.name());
     };
     let scratch_requirement = driver_module.cbs.iter()
         .filter(|cb| cb.meta_idx == meta_idx)
-        .filter(|cb| cb.meta_cb_num == Cb::META_CB_NUM)
+        .filter(|cb| cb.meta_cb_num == meta_cb_num)
         .map(|cb| cb.scratch_requirement)
         .max();
     println!("remote_call({}[{}]cb={}): Context = {:p}, scratch_requirement = {:?}",
-        ::core::any::type_name::<O>(),
+        ops_name,
         name,
-        ::core::any::type_name::<Cb>(),
+        cb_name,
         ch_side.context, scratch_requirement);
 
     (*gcb).channel = ch.get_handle_reversed();
@@ -189,14 +232,7 @@ pub unsafe fn remote_call<O: udi::metalang_trait::MetalangOpsHandler, Cb: udi::m
     if let Some(scratch_requirement) = scratch_requirement {
         (*gcb).scratch = ::libc::realloc((*gcb).scratch, scratch_requirement);
     }
-
-    // Then check that the metalanguage ops in that side matches the expectation
-    if ch_side.ops.type_id() != ::std::any::TypeId::of::<O>() {
-        panic!("Metalang mismatch: Expected {:?}, got {:?}", ch_side.ops.type_name(), ::std::any::type_name::<O>());
-    }
-    let ops = &*(ch_side.ops as *const _ as *const O);
-
-    crate::async_call(cb as *mut _, move |cb| call(ops, cb as *mut Cb));
+    ch_side
 }
 
 /// Call `udi_event_ind` over the channel, for an internal bind event
@@ -19,6 +19,9 @@ mod helpers;
 mod xt_serial;
 mod rtl8029;
 pub mod rtl8139;
+mod isa_bridge;
+pub mod ring_consumer;
 
 pub use xt_serial::XTSerial;
-pub use rtl8029::Rtl8029;
\ No newline at end of file
+pub use rtl8029::Rtl8029;
+pub use isa_bridge::IsaBridge;
\ No newline at end of file
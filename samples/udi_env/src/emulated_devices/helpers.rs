@@ -62,8 +62,15 @@ impl DmaHandle {
 pub struct DmaPool
 {
     buffers: ::std::sync::RwLock<Vec<DmaHandle>>,
+    /// Size of the address space, if less than the full 32 bits
+    limit: Option<u32>,
 }
 impl DmaPool {
+    /// Create a pool with only `size` bytes of address space
+    pub fn with_limit(size: u32) -> DmaPool {
+        DmaPool { buffers: Default::default(), limit: Some(size) }
+    }
+    /// Allocate `size` bytes of device memory, returning `None` if there is no space left
     pub fn allocate(&self, size: usize) -> Option<DmaHandle> {
         let mut buffers = self.buffers.write().unwrap();
         let mut cur = 0;
@@ -79,10 +86,18 @@ impl DmaPool {
             }
             cur = ent.base + ent.len;
         }
+        let limit = self.limit.map_or(1 << 32, |v| v as u64);
+        if cur as u64 + size as u64 > limit {
+            return None;
+        }
         let (rv, ent) = DmaHandle::new_pair(cur, size);
         buffers.push(ent);
         Some(rv)
     }
+    /// Check if there are no outstanding allocations
+    pub fn is_empty(&self) -> bool {
+        self.buffers.read().unwrap().is_empty()
+    }
     pub fn free(&self, handle: DmaHandle) {
         let mut buffers = self.buffers.write().unwrap();
         let opt_pos = match buffers.binary_search_by_key(&handle.base, |v| v.base)
@@ -93,11 +108,15 @@ impl DmaPool {
             };
         match opt_pos {
         None => panic!("Failed to find matching element for `{:#x}+{:#x} {:p}`", handle.base, handle.len, handle.data_ptr),
-        Some(i) => { buffers.remove(i); },
+        Some(i) => {
+            buffers.remove(i);
+            // SAFE: Allocated in `new_pair`, and the pool's copy was just removed
+            unsafe { ::libc::free(handle.data_ptr as *mut _); }
+            },
         }
     }
 
-    pub(super) fn read(&self, addr: u32, len: u32) -> Vec<u8> {
+    pub fn read(&self, addr: u32, len: u32) -> Vec<u8> {
         let buffers = self.buffers.read().unwrap();
         let idx = match buffers.binary_search_by_key(&addr, |v| v.base)
             {
@@ -119,7 +138,7 @@ impl DmaPool {
         }
         rv
     }
-    pub(super) fn write(&self, addr: u32, src: &[u8]) {
+    pub fn write(&self, addr: u32, src: &[u8]) {
         let buffers = self.buffers.write().unwrap();
         let idx = match buffers.binary_search_by_key(&addr, |v| v.base)
             {
@@ -162,7 +181,11 @@ impl Interrupt
     pub fn unbind(&self) {
         self.inner.lock().unwrap().handler = None;
     }
-    pub(super) fn raise(&self) {
+    pub fn is_bound(&self) -> bool {
+        self.inner.lock().unwrap().handler.is_some()
+    }
+    /// Raise the interrupt, as the device does (or another device sharing the line)
+    pub fn raise(&self) {
         let mut inner = self.inner.lock().unwrap();
         if let Some(ref mut h) = inner.handler {
             h.raise();
@@ -0,0 +1,22 @@
+/// PCI-to-ISA bridge function
+///
+/// Has no registers of its own, the devices behind it are emulated individually (see `bridge_isa`)
+#[derive(Default)]
+pub struct IsaBridge;
+impl IsaBridge {
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::default())
+    }
+}
+impl super::PioDevice for IsaBridge {
+    fn poll(&self, _actions: &mut super::Actions) {
+    }
+
+    fn pio_read(&self, regset_idx: u32, reg: u32, dst: &mut [u8]) {
+        println!("IsaBridge: Read from unimplemented register {}:{:#x}", regset_idx, reg);
+        dst.fill(0xFF);
+    }
+    fn pio_write(&self, regset_idx: u32, reg: u32, src: &[u8]) {
+        println!("IsaBridge: Write to unimplemented register {}:{:#x} = {:x?}", regset_idx, reg, src);
+    }
+}
@@ -0,0 +1,93 @@
+//! A minimal device that consumes a DMA descriptor ring
+//!
+//! Descriptors are `[length, status]` pairs of little-endian `u32`s. The driver programs the ring with
+//! [regs::RING_BASE] and [regs::RING_SIZE], then writes its running count of queued descriptors to
+//! [regs::DOORBELL]. Each poll completes every descriptor between [regs::CONSUMER] and the doorbell, setting the
+//! status to [DONE] ORed with the length.
+
+/// Status bit set on completed descriptors
+pub const DONE: u32 = 0x8000_0000;
+
+pub mod regs {
+    /// Bus address of the first descriptor
+    pub const RING_BASE: u32 = 0x0;
+    /// Number of descriptors in the ring
+    pub const RING_SIZE: u32 = 0x4;
+    /// Running count of descriptors queued by the driver
+    pub const DOORBELL: u32 = 0x8;
+    /// Running count of descriptors completed by the device (read-only)
+    pub const CONSUMER: u32 = 0xC;
+}
+
+/// Size of one descriptor in bytes
+const DESC_SIZE: u32 = 8;
+
+#[derive(Default)]
+struct Regs {
+    base: u32,
+    size: u32,
+    doorbell: u32,
+    consumer: u32,
+}
+
+#[derive(Default)]
+pub struct RingConsumer {
+    regs: ::std::sync::Mutex<Regs>,
+    dma: super::DmaPool,
+}
+impl RingConsumer {
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(Self::default())
+    }
+}
+impl super::PioDevice for RingConsumer {
+    fn poll(&self, _actions: &mut super::Actions) {
+        let mut regs = self.regs.lock().unwrap();
+        if regs.size == 0 {
+            return ;
+        }
+        while regs.consumer != regs.doorbell {
+            let addr = regs.base + (regs.consumer % regs.size) * DESC_SIZE;
+            let desc = self.dma.read(addr, DESC_SIZE);
+            let len = u32::from_le_bytes(desc[..4].try_into().unwrap());
+            println!("RingConsumer {:#x} len={}", addr, len);
+            self.dma.write(addr + 4, &(DONE | len).to_le_bytes());
+            regs.consumer = regs.consumer.wrapping_add(1);
+        }
+    }
+
+    fn pio_read(&self, regset_idx: u32, reg: u32, dst: &mut [u8]) {
+        assert!(regset_idx == 0);
+        let regs = self.regs.lock().unwrap();
+        let val = match reg {
+            regs::RING_BASE => regs.base,
+            regs::RING_SIZE => regs.size,
+            regs::DOORBELL => regs.doorbell,
+            regs::CONSUMER => regs.consumer,
+            _ => panic!("RingConsumer: Read of unknown register {:#x}", reg),
+            };
+        assert!(dst.len() == 4, "RingConsumer: Register {:#x} accessed with size {}", reg, dst.len());
+        dst.copy_from_slice(&val.to_le_bytes());
+    }
+    fn pio_write(&self, regset_idx: u32, reg: u32, src: &[u8]) {
+        assert!(regset_idx == 0);
+        let Ok(val) = src.try_into().map(u32::from_le_bytes) else {
+            panic!("RingConsumer: Register {:#x} accessed with size {}", reg, src.len())
+        };
+        let mut regs = self.regs.lock().unwrap();
+        match reg {
+        regs::RING_BASE => regs.base = val,
+        regs::RING_SIZE => {
+            // Resizing the ring restarts it
+            regs.size = val;
+            regs.doorbell = 0;
+            regs.consumer = 0;
+            },
+        regs::DOORBELL => regs.doorbell = val,
+        regs::CONSUMER => panic!("RingConsumer: Write to read-only CONSUMER"),
+        _ => panic!("RingConsumer: Write to unknown register {:#x}", reg),
+        }
+    }
+
+    fn dma(&self) -> &super::DmaPool { &self.dma }
+}
@@ -69,6 +69,9 @@ impl super::PioDevice for Rtl8029 {
                 println!("RTL8019 RX {}--{}", first_page, regs.rx_next);
                 regs.isr |= 1 << 0; // PRX - Packet RX
             }
+            for frame in regs.tx_frames.drain(..) {
+                actions.push("nic_tx", &frame);
+            }
             regs.isr & regs.imr != 0
         };
         if is_int {
@@ -99,8 +102,7 @@ impl super::PioDevice for Rtl8029 {
             (0, 15) => regs.cntr[2],
             (_, 0x10 ..= 0x17) => {
                 regs.card_ram.read(regs.cadr, dst);
-                regs.cadr += dst.len() as u16;
-                regs.current_byte_count -= dst.len() as u16;
+                regs.remote_dma_advance(dst.len());
                 return;
                 },   // Remote DMA
             (_, 0x18 ..= 0x1F) => 0,   // Reset
@@ -111,7 +113,7 @@ impl super::PioDevice for Rtl8029 {
 
     fn pio_write(&self, regset_idx: u32, reg: u32, src: &[u8]) {
         assert!(regset_idx == 0);
-        assert!(src.len() == 1);
+        assert!(src.len() == 1 || reg & !0x7 == 0x10, "Unexpected length ({}) writing to {:#x}", src.len(), reg);
         let mut regs = self.regs.lock().unwrap();
         let regs = &mut *regs;
         let v = src[0];
@@ -133,6 +135,16 @@ impl super::PioDevice for Rtl8029 {
                 // Stop/abort RDMA
             },
             }
+            if regs.cmd & 0x04 != 0 {
+                // TXP - Transmit the packet at TPSR, then clear the bit
+                let mut frame = vec![0; u16::from_le_bytes(regs.tbcr) as usize];
+                regs.card_ram.read(regs.tpsr as u16 * 256, &mut frame);
+                println!("RTL8019 TX {:#x}+{}", regs.tpsr, frame.len());
+                regs.tx_frames.push(frame);
+                regs.cmd &= !0x04;
+                regs.tsr = 0x01;    // PTX - Packet transmitted
+                regs.isr |= 1 << 1; // PTX - Packet TX
+            }
         },
         (0, 1) => {
             regs.pstart = v;
@@ -159,8 +171,7 @@ impl super::PioDevice for Rtl8029 {
             => regs.mar[reg as usize - 8] = v,
         (_, 0x10 ..= 0x17) => {   // Remote DMA
             regs.card_ram.write(regs.cadr, src);
-            regs.cadr += src.len() as u16;
-            regs.current_byte_count -= src.len() as u16;
+            regs.remote_dma_advance(src.len());
         },
         (_, 0x18 ..= 0x1F) => regs.reset(),   // Reset
         (_, 0x20..) => panic!("Invalid reg"),
@@ -240,6 +251,8 @@ struct Regs
     current_byte_count: u16,
     /// Next RX page
     rx_next: u8,
+    /// Transmitted frames, pushed as `nic_tx` actions on the next poll
+    tx_frames: Vec<Vec<u8>>,
 
     card_ram: CardMem,
 }
@@ -252,11 +265,20 @@ impl Regs
     fn cmd_pg(&self) -> u8 {
         self.cmd >> 6
     }
+    /// Advance the remote DMA address after an access of `len` bytes, flagging RDC once the count is reached
+    fn remote_dma_advance(&mut self, len: usize) {
+        self.cadr += len as u16;
+        // A word-wide transfer of an odd count overruns by a byte
+        self.current_byte_count = self.current_byte_count.saturating_sub(len as u16);
+        if self.current_byte_count == 0 {
+            self.isr |= 1 << 6; // RDC - Remote DMA complete
+        }
+    }
 }
-struct CardMem(Box<[u8; 0x4000]>); // 16KiB
+struct CardMem(Box<[u8; 0x8000]>); // 32KiB
 impl Default for CardMem {
     fn default() -> Self {
-        CardMem(Box::new([0; 0x4000]))
+        CardMem(Box::new([0; 0x8000]))
     }
 }
 impl CardMem {
@@ -266,7 +288,7 @@ impl CardMem {
         }
         match addr {
         0 ..= 5 => copy_from(&[0x12,0x34,0x56,0x00,0x00,0x01], addr-0, dst),
-        0x40_00 ..= 0x7F_FF => copy_from(&*self.0, addr - 0x4000, dst),
+        0x40_00 ..= 0xBF_FF => copy_from(&*self.0, addr - 0x4000, dst),
         _ => panic!("Out-of-bounds read: {:#x}+{}", addr, dst.len()),
         }
     }
@@ -275,7 +297,7 @@ impl CardMem {
             dst[rel_addr as usize..][..src.len()].copy_from_slice(src);
         }
         match addr {
-        0x40_00 ..= 0x80_00 => copy_to(&mut *self.0, addr - 0x40_00, src),
+        0x40_00 ..= 0xBF_FF => copy_to(&mut *self.0, addr - 0x40_00, src),
         _ => panic!("Out-of-bounds write: {:#x}+{}", addr, src.len()),
         }
     }
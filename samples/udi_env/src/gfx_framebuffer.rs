@@ -0,0 +1,320 @@
+//! In-memory framebuffer, exposed as a GFX provider
+//!
+//! Has a single [ConnectorType::MemBuffer] connector fed by a single framebuffer engine, with one buffer
+//! ([BUFFER_INDEX]) of `R8G8B8X8` pixels. The engine's width and height can be changed (within [WIDTH_RANGE] and
+//! [HEIGHT_RANGE]), which clears the framebuffer, as does putting the engine into [EnableState::Reset].
+//!
+//! There is no enumeration, the provider channel is created by the caller (e.g. a test harness).
+use ::std::cell::{Cell, RefCell};
+use ::udi::meta_gfx::{
+    BindInfo, BufferArea, BufferFlags, BufferInfo, ConnectorType, Element, EnableState, Operator, OperatorEntry, Property,
+    Range, SignalType, INPUT_NONE,
+};
+
+/// Index of the framebuffer buffer
+pub const BUFFER_INDEX: u32 = 0;
+/// Bits per pixel in the framebuffer
+pub const BITS_PER_PIXEL: u32 = 32;
+/// Valid engine widths
+pub const WIDTH_RANGE: Range = Range { min: 8, max: 1024, step: 8 };
+/// Valid engine heights
+pub const HEIGHT_RANGE: Range = Range { min: 1, max: 768, step: 1 };
+/// Initial size of the framebuffer
+pub const DEFAULT_SIZE: (u32, u32) = (64, 48);
+/// Operator list of the engine: each pixel is read from the buffer, then split into red, green, and blue bytes
+pub const OPERATORS: [OperatorEntry; 7] = [
+    OperatorEntry::new(Operator::Rgb, 1, 2, 3),
+    OperatorEntry::new(Operator::Seg, 4, 0, 8),
+    OperatorEntry::new(Operator::Seg, 4, 8, 8),
+    OperatorEntry::new(Operator::Seg, 4, 16, 8),
+    OperatorEntry::new(Operator::Buffer, 5, 6, BITS_PER_PIXEL),
+    OperatorEntry::new(Operator::X, 0, 0, 0),
+    OperatorEntry::new(Operator::Y, 0, 0, 0),
+];
+
+const CONNECTOR: Element = Element::Connector(0);
+const ENGINE: Element = Element::Engine(0);
+
+pub struct Driver {
+    connector_enable: Cell<EnableState>,
+    connector_input: Cell<u32>,
+    engine_enable: Cell<EnableState>,
+    width: Cell<u32>,
+    height: Cell<u32>,
+    /// Pixel data, row-major with no padding
+    pixels: RefCell<Vec<u8>>,
+}
+impl Default for Driver {
+    fn default() -> Self {
+        let (width, height) = DEFAULT_SIZE;
+        Driver {
+            connector_enable: Cell::new(EnableState::Disabled),
+            connector_input: Cell::new(0),
+            engine_enable: Cell::new(EnableState::Enabled),
+            width: Cell::new(width),
+            height: Cell::new(height),
+            pixels: RefCell::new(vec![0; (width * height * BITS_PER_PIXEL / 8) as usize]),
+        }
+    }
+}
+impl Driver {
+    /// Current size of the framebuffer
+    pub fn size(&self) -> (u32, u32) {
+        (self.width.get(), self.height.get())
+    }
+    /// Get the bytes of the pixel at (`x`,`y`)
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let ofs = self.offset(x, y);
+        self.pixels.borrow()[ofs..][..4].try_into().unwrap()
+    }
+    /// Check if the connector is producing output
+    pub fn is_displaying(&self) -> bool {
+        self.connector_enable.get() == EnableState::Enabled
+            && self.connector_input.get() == ENGINE.index()
+            && self.engine_enable.get() == EnableState::Enabled
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        ((y * self.width.get() + x) * BITS_PER_PIXEL / 8) as usize
+    }
+    fn resize(&self, width: u32, height: u32) {
+        self.width.set(width);
+        self.height.set(height);
+        self.clear();
+    }
+    fn clear(&self) {
+        *self.pixels.borrow_mut() = vec![0; (self.width.get() * self.height.get() * BITS_PER_PIXEL / 8) as usize];
+    }
+    fn buffer_info(&self) -> BufferInfo {
+        BufferInfo {
+            width: self.width.get(),
+            height: self.height.get(),
+            bits_per_entry: BITS_PER_PIXEL,
+            flags: BufferFlags::READ | BufferFlags::WRITE,
+        }
+    }
+    /// Check that a buffer request can be handled, returning the buffer format
+    fn check_area(&self, area: &BufferArea) -> ::udi::Result<BufferInfo> {
+        if self.engine_enable.get() == EnableState::Reset {
+            return Err(error(::udi::ffi::UDI_STAT_INVALID_STATE));
+        }
+        let info = self.buffer_info();
+        if area.index != BUFFER_INDEX || !info.contains(area) {
+            return Err(error(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD));
+        }
+        Ok(info)
+    }
+
+    /// Valid values of a property, `None` if the element does not exist
+    fn ranges(&self, element: Element, prop: Property) -> Option<Vec<Range>> {
+        let enable = Range::inclusive(EnableState::Disabled.to_raw(), EnableState::Reset.to_raw());
+        Some(match (element, prop) {
+        (CONNECTOR, Property::Enable) => vec![enable],
+        (CONNECTOR, Property::Input) => vec![Range::single(ENGINE.index()), Range::single(INPUT_NONE)],
+        (CONNECTOR, Property::Width) => vec![Range::single(self.width.get())],
+        (CONNECTOR, Property::Height) => vec![Range::single(self.height.get())],
+        (CONNECTOR, Property::Signal) => vec![Range::single(SignalType::Integrated.to_raw())],
+        (CONNECTOR, Property::ConnectorType) => vec![Range::single(ConnectorType::MemBuffer.to_raw())],
+        (ENGINE, Property::Enable) => vec![enable],
+        (ENGINE, Property::Input) => vec![Range::single(INPUT_NONE)],
+        (ENGINE, Property::Width) => vec![WIDTH_RANGE],
+        (ENGINE, Property::Height) => vec![HEIGHT_RANGE],
+        (ENGINE, Property::ColorBits) => vec![Range::single(8)],
+        (CONNECTOR|ENGINE, _) => vec![],
+        _ => return None,
+        })
+    }
+    fn get(&self, element: Element, prop: Property) -> u32 {
+        match (element, prop) {
+        (CONNECTOR, Property::Enable) => self.connector_enable.get().to_raw(),
+        (CONNECTOR, Property::Input) => self.connector_input.get(),
+        (ENGINE, Property::Enable) => self.engine_enable.get().to_raw(),
+        (ENGINE, Property::Input) => INPUT_NONE,
+        (CONNECTOR|ENGINE, Property::Width) => self.width.get(),
+        (CONNECTOR|ENGINE, Property::Height) => self.height.get(),
+        // Properties with a single valid value
+        _ => match self.ranges(element, prop).as_deref() {
+            Some([r]) if r.min == r.max => r.min,
+            _ => 0,
+            },
+        }
+    }
+    fn set(&self, element: Element, prop: Property, value: u32) -> ::udi::Result<()> {
+        let Some(ranges) = self.ranges(element, prop) else {
+            return Err(error(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD));
+        };
+        if !Range::list_contains(&ranges, value) {
+            return Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED));
+        }
+        match (element, prop) {
+        (CONNECTOR, Property::Enable) => self.connector_enable.set(EnableState::from_raw(value)),
+        (CONNECTOR, Property::Input) => self.connector_input.set(value),
+        (ENGINE, Property::Enable) => {
+            let state = EnableState::from_raw(value);
+            if state == EnableState::Reset {
+                self.clear();
+            }
+            self.engine_enable.set(state)
+        },
+        (ENGINE, Property::Width) => if value != self.width.get() {
+            self.resize(value, self.height.get())
+        },
+        (ENGINE, Property::Height) => if value != self.height.get() {
+            self.resize(self.width.get(), value)
+        },
+        // Everything else has only one valid value, i.e. is unchanged
+        _ => {},
+        }
+        Ok(())
+    }
+}
+
+fn error(status: ::udi::ffi::StatusValues) -> ::udi::Error {
+    ::udi::Error::from_status(status as _).unwrap_err()
+}
+
+impl ::udi::init::Driver for ::udi::init::RData<Driver>
+{
+    const MAX_ATTRS: u8 = 0;
+    type Future_init<'s> = ::core::future::Ready<()>;
+    fn usage_ind<'s>(&'s self, _cb: ::udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+        ::core::future::ready(())
+    }
+
+    type Future_enumerate<'s> = ::core::future::Ready<(::udi::init::EnumerateResult,::udi::init::AttrSink<'s>)>;
+    fn enumerate_req<'s>(
+        &'s self,
+        _cb: ::udi::init::CbRefEnumerate<'s>,
+        _level: ::udi::init::EnumerateLevel,
+        attrs_out: ::udi::init::AttrSink<'s>
+    ) -> Self::Future_enumerate<'s>
+    {
+        ::core::future::ready((::udi::init::EnumerateResult::Done, attrs_out))
+    }
+
+    type Future_devmgmt<'s> = ::core::future::Ready<::udi::Result<u8>>;
+    fn devmgmt_req<'s>(&'s self, _cb: ::udi::init::CbRefMgmt<'s>, _mgmt_op: ::udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+        ::core::future::ready(Ok(0))
+    }
+}
+
+impl ::udi::meta_gfx::Provider for ::udi::init::RData<Driver>
+{
+    type Future_bind_req<'s> = ::core::future::Ready<::udi::Result<BindInfo>>;
+    fn bind_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_bind_cb_t>) -> Self::Future_bind_req<'s> {
+        ::core::future::ready(Ok(BindInfo { connectors: 1, engines: 1 }))
+    }
+
+    type Future_unbind_req<'s> = ::core::future::Ready<()>;
+    fn unbind_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_bind_cb_t>) -> Self::Future_unbind_req<'s> {
+        self.connector_enable.set(EnableState::Disabled);
+        ::core::future::ready(())
+    }
+
+    type Future_set_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn set_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_state_cb_t>, element: Element, prop: Property, value: u32) -> Self::Future_set_req<'s> {
+        ::core::future::ready(self.set(element, prop, value))
+    }
+
+    type Future_get_req<'s> = ::core::future::Ready<u32>;
+    fn get_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_state_cb_t>, element: Element, prop: Property) -> Self::Future_get_req<'s> {
+        ::core::future::ready(self.get(element, prop))
+    }
+
+    type Future_range_req<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn range_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_range_cb_t>, element: Element, prop: Property, ranges: &'s mut ::udi::buf::Handle) -> Self::Future_range_req<'s> {
+        async move {
+            let v = self.ranges(element, prop).unwrap_or_default();
+            ::udi::meta_gfx::write_ranges(cb.gcb(), ranges, &v).await;
+        }
+    }
+
+    type Future_get_engine_operator_req<'s> = ::core::future::Ready<OperatorEntry>;
+    fn get_engine_operator_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_range_cb_t>, engine: u32, index: u32) -> Self::Future_get_engine_operator_req<'s> {
+        let entry = match OPERATORS.get(index as usize) {
+            Some(e) if engine == ENGINE.index() => *e,
+            // Out of range, just produce black
+            _ => OperatorEntry::new(Operator::Const, 0, 0, 0),
+            };
+        ::core::future::ready(entry)
+    }
+
+    // No command sequences are supported, they're ignored
+    type Future_connector_command_req<'s> = ::core::future::Ready<()>;
+    fn connector_command_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_command_cb_t>, _data: &'s mut ::udi::buf::Handle) -> Self::Future_connector_command_req<'s> {
+        ::core::future::ready(())
+    }
+    type Future_engine_command_req<'s> = ::core::future::Ready<()>;
+    fn engine_command_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_command_cb_t>, _data: &'s mut ::udi::buf::Handle) -> Self::Future_engine_command_req<'s> {
+        ::core::future::ready(())
+    }
+
+    type Future_buffer_info_req<'s> = ::core::future::Ready<BufferInfo>;
+    fn buffer_info_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_buffer_info_cb_t>, index: u32) -> Self::Future_buffer_info_req<'s> {
+        ::core::future::ready(if index == BUFFER_INDEX { self.buffer_info() } else { BufferInfo::default() })
+    }
+
+    type Future_buffer_read_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn buffer_read_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_buffer_cb_t>, area: BufferArea, data: &'s mut ::udi::buf::Handle) -> Self::Future_buffer_read_req<'s> {
+        async move {
+            let info = self.check_area(&area)?;
+            let row_len = info.area_bytes(&BufferArea { height: 1, ..area });
+            let mut v = Vec::with_capacity(info.area_bytes(&area));
+            {
+                let pixels = self.pixels.borrow();
+                for y in area.y .. area.y + area.height {
+                    v.extend_from_slice(&pixels[self.offset(area.x, y)..][..row_len]);
+                }
+            }
+            data.write(cb.gcb(), .., &v).await;
+            Ok(())
+        }
+    }
+
+    type Future_buffer_write_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn buffer_write_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_gfx::udi_gfx_buffer_cb_t>, area: BufferArea, data: &'s ::udi::buf::Handle) -> Self::Future_buffer_write_req<'s> {
+        ::core::future::ready(self.check_area(&area).and_then(|info| {
+            let len = info.area_bytes(&area);
+            if data.len() < len {
+                return Err(error(::udi::ffi::UDI_STAT_DATA_UNDERRUN));
+            }
+            let row_len = info.area_bytes(&BufferArea { height: 1, ..area });
+            let mut pixels = self.pixels.borrow_mut();
+            for (i, y) in (area.y .. area.y + area.height).enumerate() {
+                let ofs = self.offset(area.x, y);
+                data.read(i * row_len, &mut pixels[ofs..][..row_len]);
+            }
+            Ok(())
+        }))
+    }
+}
+
+::udi_macros::udiprops!("
+properties_version 0x101
+requires udi_gfx 0x101
+meta 1 udi_gfx
+region 0
+");
+const META_GFX: ::udi::ffi::udi_index_t = udiprops::meta::udi_gfx;
+::udi::define_driver! {
+    Driver as INIT_INFO_GFX_FRAMEBUFFER;
+    ops: {
+        Gfx: Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_provider_ops_t,
+    },
+    cbs: {
+        _Bind  : Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_bind_cb_t,
+        _State : Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_state_cb_t,
+        _Range : Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_range_cb_t,
+        _Command: Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_command_cb_t,
+        _BufferInfo: Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_buffer_info_cb_t,
+        _Buffer: Meta=META_GFX, ::udi::ffi::meta_gfx::udi_gfx_buffer_cb_t,
+    }
+}
+/// Ops index of the provider channel
+pub const OPS_GFX: ::udi::ffi::udi_index_t = OpsList::Gfx;
+
+/// Get the driver module for the framebuffer
+pub fn module() -> crate::DriverModule<'static> {
+    // SAFE: The init info and udiprops are from the same driver
+    unsafe { crate::DriverModule::new(&INIT_INFO_GFX_FRAMEBUFFER, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+}
@@ -0,0 +1,129 @@
+//! RAM disk, exposed as a GIO block device
+//!
+//! An orphan driver that enumerates a single `gio_type=block` child, backed by [::udi::meta_gio::block::BlockProvider].
+//! Transfers are limited to [MAX_BLOCKS] blocks so larger requests get split.
+use ::std::cell::RefCell;
+
+/// Block size of the disk
+pub const BLOCK_SIZE: u32 = 512;
+/// Number of blocks on the disk
+pub const CAPACITY: u64 = 16;
+/// Maximum number of blocks per read/write
+pub const MAX_BLOCKS: u32 = 2;
+
+struct Driver {
+    data: RefCell<Vec<u8>>,
+    enumerated: ::core::cell::Cell<bool>,
+}
+impl Default for Driver {
+    fn default() -> Self {
+        Driver {
+            data: RefCell::new(vec![0; (CAPACITY * BLOCK_SIZE as u64) as usize]),
+            enumerated: Default::default(),
+        }
+    }
+}
+
+impl ::udi::init::Driver for ::udi::init::RData<Driver>
+{
+    const MAX_ATTRS: u8 = 1;
+    type Future_init<'s> = impl ::core::future::Future<Output=()>;
+    fn usage_ind<'s>(&'s self, _cb: udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+        async move { }
+    }
+
+    type Future_enumerate<'s> = impl ::core::future::Future<Output=(udi::init::EnumerateResult,udi::init::AttrSink<'s>)> + 's;
+    fn enumerate_req<'s>(
+        &'s self,
+        _cb: udi::init::CbRefEnumerate<'s>,
+        level: udi::init::EnumerateLevel,
+        mut attrs_out: udi::init::AttrSink<'s>
+    ) -> Self::Future_enumerate<'s>
+    {
+        async move {
+            match level
+            {
+            ::udi::init::EnumerateLevel::Start
+            |::udi::init::EnumerateLevel::StartRescan => self.enumerated.set(false),
+            ::udi::init::EnumerateLevel::Next => {},
+            udi::init::EnumerateLevel::New
+            |udi::init::EnumerateLevel::Directed => return (::udi::init::EnumerateResult::Failed, attrs_out),
+            udi::init::EnumerateLevel::Release(_) => return (::udi::init::EnumerateResult::Released, attrs_out),
+            }
+            if self.enumerated.replace(true) {
+                (::udi::init::EnumerateResult::Done, attrs_out)
+            }
+            else {
+                attrs_out.push_string("gio_type", "block");
+                (::udi::init::EnumerateResult::ok::<OpsList::Gio>(0), attrs_out)
+            }
+        }
+    }
+
+    type Future_devmgmt<'s> = impl ::core::future::Future<Output=::udi::Result<u8>> + 's;
+    fn devmgmt_req<'s>(&'s self, _cb: udi::init::CbRefMgmt<'s>, _mgmt_op: udi::init::MgmtOp, _parent_id: udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+        async move {
+            // Nothing to prepare or release, the data lives in region memory
+            Ok(0)
+        }
+    }
+}
+
+impl ::udi::meta_gio::block::BlockProvider for ::udi::ChildBind<Driver,()>
+{
+    fn block_size(&self) -> u32 {
+        BLOCK_SIZE
+    }
+    fn capacity(&self) -> u64 {
+        CAPACITY
+    }
+    fn max_blocks(&self) -> u32 {
+        MAX_BLOCKS
+    }
+
+    type Future_read_blocks<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn read_blocks<'s>(&'s self, gcb: ::udi::CbRef<'s, ::udi::ffi::udi_cb_t>, lba: u64, count: u32, buf: &'s mut ::udi::buf::Handle, buf_ofs: usize) -> Self::Future_read_blocks<'s> {
+        async move {
+            let data = self.dev().data.borrow()[block_range(lba, count)].to_vec();
+            buf.write(gcb, buf_ofs .. buf_ofs + data.len(), &data).await;
+            Ok(())
+        }
+    }
+
+    type Future_write_blocks<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn write_blocks<'s>(&'s self, _gcb: ::udi::CbRef<'s, ::udi::ffi::udi_cb_t>, lba: u64, count: u32, buf: &'s ::udi::buf::Handle, buf_ofs: usize) -> Self::Future_write_blocks<'s> {
+        buf.read(buf_ofs, &mut self.dev().data.borrow_mut()[block_range(lba, count)]);
+        ::core::future::ready(Ok(()))
+    }
+
+    type Future_flush<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn flush<'s>(&'s self, _gcb: ::udi::CbRef<'s, ::udi::ffi::udi_cb_t>) -> Self::Future_flush<'s> {
+        // Nothing is cached
+        ::core::future::ready(Ok(()))
+    }
+}
+
+fn block_range(lba: u64, count: u32) -> ::core::ops::Range<usize> {
+    let start = (lba * BLOCK_SIZE as u64) as usize;
+    start .. start + (count * BLOCK_SIZE) as usize
+}
+
+::udi_macros::udiprops!("
+properties_version 0x101
+requires udi_gio 0x101
+meta 1 udi_gio
+child_bind_ops 1 0 1
+region 0
+");
+const META_GIO: ::udi::ffi::udi_index_t = udiprops::meta::udi_gio;
+::udi::define_driver! {
+    Driver as INIT_INFO_RAMDISK;
+    ops: {
+        Gio: Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_provider_ops_t : ChildBind<_,()>,
+    },
+    cbs: {
+        _Bind : Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_bind_cb_t,
+        _Xfer : Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t,
+        _Event: Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_event_cb_t,
+    }
+}
@@ -9,8 +9,13 @@ pub mod channels;
 pub mod udi_impl;
 
 pub mod bridge_pci;
+pub mod bridge_isa;
 pub mod sink_nsr;
 pub mod sink_gio_serial;
+pub mod sink_gio_block;
+pub mod gio_ramdisk;
+pub mod usb_mock_host;
+pub mod gfx_framebuffer;
 
 pub mod management_agent;
 
@@ -139,7 +144,16 @@ impl<'a> DriverModule<'a> {
         "udi_gio" => &::udi::meta_gio::METALANG_SPEC,
         "udi_bridge" => &::udi::meta_bridge::METALANG_SPEC,
         "udi_nic" => &::udi::meta_nic::METALANG_SPEC,
-        name => todo!("Unknown metalang {:?}", name),
+        "udi_scsi" => &::udi::meta_scsi::METALANG_SPEC,
+        "usbdi" => &::udi::meta_usb::METALANG_SPEC,
+        "udi_gfx" => &::udi::meta_gfx::METALANG_SPEC,
+        name => match get_registered_metalang(name) {
+            Some(spec) => spec,
+            None => {
+                eprintln!("WARNING: Metalanguage {:?} is not built in or registered", name);
+                return None;
+                },
+            },
         })
     }
     pub unsafe fn get_meta_ops(&self, ops: &::udi::ffi::init::udi_ops_init_t) -> &'static dyn udi::metalang_trait::MetalangOpsHandler {
@@ -158,6 +172,28 @@ impl<'a> DriverModule<'a> {
     }
 }
 
+/// Metalanguages registered with [register_metalanguage]
+static REGISTERED_METALANGS: ::std::sync::Mutex<Vec<(&'static str, &'static (dyn udi::metalang_trait::Metalanguage + Sync))>>
+    = ::std::sync::Mutex::new(Vec::new());
+/// Register a metalanguage that isn't built into the environment (e.g. one from [udi::define_metalanguage])
+pub fn register_metalanguage<M: udi::metalang_trait::Metalanguage + Sync>(spec: &'static M) {
+    let mut list = REGISTERED_METALANGS.lock().unwrap();
+    if !list.iter().any(|(name,_)| *name == M::name()) {
+        list.push((M::name(), spec));
+    }
+}
+fn get_registered_metalang(des_name: &str) -> Option<&'static dyn udi::metalang_trait::Metalanguage> {
+    REGISTERED_METALANGS.lock().unwrap().iter()
+        .find(|(name,_)| *name == des_name)
+        .map(|&(_,spec)| spec as &dyn udi::metalang_trait::Metalanguage)
+}
+/// Find a registered metalanguage by its MEI description, returning the name and specification
+pub fn get_metalang_by_mei(mei: *const ::udi::ffi::mei::udi_mei_init_t) -> Option<(&'static str, &'static dyn udi::metalang_trait::Metalanguage)> {
+    REGISTERED_METALANGS.lock().unwrap().iter()
+        .find(|(_,spec)| spec.mei_init().is_some_and(|v| ::core::ptr::eq(v, mei)))
+        .map(|&(name,spec)| (name, spec as &dyn udi::metalang_trait::Metalanguage))
+}
+
 /// Get a slice from a NUL-terminated list
 /// 
 /// SAFETY: The caller attests that the input pointer is either NULL, or points to a list that ends with an
@@ -282,6 +318,8 @@ impl DriverRegion
 }
 pub struct DriverChild {
     pub is_bound: ::std::cell::Cell<bool>,
+    /// Instance bound to this child (if it was bound by something that tracks it)
+    pub instance: ::std::cell::OnceCell<::std::sync::Weak<DriverInstance>>,
     pub child_id: ::udi::ffi::udi_ubit32_t,
     pub meta_idx: ::udi::ffi::udi_index_t,
     pub ops_idx: ::udi::ffi::udi_index_t,
@@ -308,7 +346,13 @@ impl Operation {
 unsafe fn async_call(gcb: *mut ::udi::ffi::udi_cb_t, op: impl FnOnce(*mut ::udi::ffi::udi_cb_t)+'static) {
     // TODO: Check the stack depth, and if it's too deep push onto the region's queue
     if true {
-        let region = channels::get_region( &(*gcb).channel );
+        let region = if (*gcb).channel.is_null() {
+                // Management agent CB, runs in the primary region
+                &(*((*gcb).origin as *const DriverInstance)).regions[0]
+            }
+            else {
+                channels::get_region( &(*gcb).channel )
+            };
         region.task_queue.lock().unwrap()
             .push_back(crate::Operation::new(gcb, op))
     }
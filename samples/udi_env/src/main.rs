@@ -21,6 +21,10 @@ struct GlobalState {
 }
 
 fn main() {
+    // `UDI_PIO_VERBOSE=0` disables the per-operation PIO trace
+    if ::std::env::var_os("UDI_PIO_VERBOSE").is_some_and(|v| v == "0") {
+        ::udi_environment::udi_impl::pio::set_verbose(false);
+    }
     let mut state = GlobalState{
         modules: vec![],
         instances: vec![],
@@ -32,6 +36,11 @@ fn main() {
         let udiprops = ::udiprops_parse::load_from_raw_section(&raw_udiprops);
         ::std::sync::Arc::new( DriverModule::new(&INIT_INFO_PCI, udiprops) )
     });
+    register_driver_module(&mut state, unsafe {
+        use ::udi_environment::bridge_isa::{INIT_INFO_ISA,udiprops::udiprops as raw_udiprops};
+        let udiprops = ::udiprops_parse::load_from_raw_section(&raw_udiprops);
+        ::std::sync::Arc::new( DriverModule::new(&INIT_INFO_ISA, udiprops) )
+    });
     register_driver_module(&mut state, unsafe {
         use ::udi_environment::sink_nsr::{INIT_INFO_NSR,udiprops::udiprops as raw_udiprops};
         let udiprops = ::udiprops_parse::load_from_raw_section(&raw_udiprops);
@@ -42,6 +51,16 @@ fn main() {
         let udiprops = ::udiprops_parse::load_from_raw_section(&raw_udiprops);
         ::std::sync::Arc::new( DriverModule::new(&INIT_INFO_GIOSERIAL, udiprops) )
     });
+    register_driver_module(&mut state, unsafe {
+        use ::udi_environment::sink_gio_block::{INIT_INFO_GIOBLOCK,udiprops::udiprops as raw_udiprops};
+        let udiprops = ::udiprops_parse::load_from_raw_section(&raw_udiprops);
+        ::std::sync::Arc::new( DriverModule::new(&INIT_INFO_GIOBLOCK, udiprops) )
+    });
+    register_driver_module(&mut state, unsafe {
+        use ::udi_environment::gio_ramdisk::{INIT_INFO_RAMDISK,udiprops::udiprops as raw_udiprops};
+        let udiprops = ::udiprops_parse::load_from_raw_section(&raw_udiprops);
+        ::std::sync::Arc::new( DriverModule::new(&INIT_INFO_RAMDISK, udiprops) )
+    });
 
     // ----
     let driver_module_ne2000 = unsafe {
@@ -115,6 +134,10 @@ fn main() {
             NextOp::Op(t) => inst.regions[0].task_queue.lock().unwrap().push_back(t),
             NextOp::InitComplete => {
                 assert!(inst.management_state.is_ready());
+                bind_children(&mut new_instances, &state.modules, inst);
+                inst.management_state.watch_new();
+                },
+            NextOp::ChildrenChanged => {
                 bind_children(&mut new_instances, &state.modules, inst);
                 },
             }
@@ -133,6 +156,9 @@ fn main() {
                 }
             }
         }
+        while let Some(frame) = actions.pull("nic_tx") {
+            println!("NIC TX {:x?}", frame);
+        }
         assert!( actions.is_empty(), "Devices did not pull the actions" );
         if !new_instances.is_empty() {
             action_happened = true;
@@ -361,7 +387,9 @@ fn maybe_child_bind(
         driver_module.name(),
         parent.module.name(), child.child_id
         );
-    Some( create_driver_instance(driver_module.clone(), Some(channel_child)) )
+    let instance = create_driver_instance(driver_module.clone(), Some(channel_child));
+    let _ = child.instance.set(Arc::downgrade(&instance));
+    Some(instance)
 }
 
 fn create_driver_instance<'a>(driver_module: Arc<DriverModule<'static>>, channel_to_parent: Option<::udi::ffi::udi_channel_t>) -> Arc<DriverInstance>
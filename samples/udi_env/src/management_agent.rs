@@ -14,6 +14,27 @@ pub struct ManagementAgent
 struct ManagementAgentInner {
     running: bool,
     state: ManagementState,
+    hotplug: HotplugState,
+    /// A `UDI_DMGMT_UNBIND` has been requested, but not yet sent
+    pending_unbind: bool,
+    /// The driver has acknowledged `UDI_DMGMT_UNBIND`
+    unbound: bool,
+}
+/// Enumeration after initialisation has completed (hot-plug, and directed enumeration)
+#[derive(Default)]
+struct HotplugState {
+    /// Keep a `UDI_ENUMERATE_NEW` request outstanding
+    watching: bool,
+    /// The outstanding `UDI_ENUMERATE_NEW` request, held by the driver until a child is added or removed
+    new_cb: Option<*mut ::udi::ffi::meta_mgmt::udi_enumerate_cb_t>,
+    /// Removed children, waiting for a `UDI_ENUMERATE_RELEASE` (held until the child is unbound)
+    pending_release: ::std::collections::VecDeque<::udi::ffi::udi_ubit32_t>,
+    /// Attributes for `UDI_ENUMERATE_DIRECTED` requests
+    pending_directed: ::std::collections::VecDeque<Vec<::udi::ffi::attr::udi_instance_attr_list_t>>,
+    /// A child has been added since the last poll
+    children_changed: bool,
+    /// The driver reported `UDI_ENUMERATE_REMOVED_SELF`
+    removed_self: bool,
 }
 #[derive(Default)]
 enum ManagementState {
@@ -50,6 +71,8 @@ pub enum NextOp {
     Op(super::Operation),
     /// Initialisation is now complete, children should now be bound
     InitComplete,
+    /// New children have been enumerated after initialisation, and should be bound
+    ChildrenChanged,
 }
 
 impl ManagementAgent
@@ -73,10 +96,33 @@ impl ManagementAgent
         ManagementState::Initialised => true,
         }
     }
+    /// Keep a `UDI_ENUMERATE_NEW` request outstanding once initialised, to pick up hot-plugged children
+    ///
+    /// Stops if the driver replies with `UDI_ENUMERATE_FAILED` (i.e. it doesn't support hot-plug)
+    pub fn watch_new(&self) {
+        self.inner.lock().unwrap().hotplug.watching = true;
+    }
+    /// Queue a `UDI_ENUMERATE_DIRECTED` request, creating a child with the given attributes
+    pub fn request_directed(&self, attrs: Vec<::udi::ffi::attr::udi_instance_attr_list_t>) {
+        self.inner.lock().unwrap().hotplug.pending_directed.push_back(attrs);
+    }
+    /// Has the driver reported that its device has been removed?
+    pub fn is_removed(&self) -> bool {
+        self.inner.lock().unwrap().hotplug.removed_self
+    }
+    /// Queue a `UDI_DMGMT_UNBIND` request, unbinding the driver from its parent
+    pub fn request_unbind(&self) {
+        self.inner.lock().unwrap().pending_unbind = true;
+    }
+    /// Has the driver acknowledged a `UDI_DMGMT_UNBIND`?
+    pub fn is_unbound(&self) -> bool {
+        self.inner.lock().unwrap().unbound
+    }
     /// Check if the MA has anything to do
     pub fn poll(&self, instance: &Arc<crate::DriverInstance>) -> NextOp
     {
         let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
         if inner.running {
             return NextOp::Idle;
         }
@@ -92,7 +138,34 @@ impl ManagementAgent
                     NextOp::InitComplete
                 }
                 }
-            ManagementState::Initialised => return NextOp::Idle,
+            ManagementState::Initialised => {
+                if inner.unbound {
+                    return NextOp::Idle;
+                }
+                let hp = &mut inner.hotplug;
+                if ::core::mem::take(&mut hp.children_changed) {
+                    return NextOp::ChildrenChanged;
+                }
+                if ::core::mem::take(&mut inner.pending_unbind) {
+                    NextOp::Op(devmgmt_op(instance, ::udi::ffi::meta_mgmt::UDI_DMGMT_UNBIND, 0))
+                }
+                else if let Some(i) = hp.pending_release.iter().position(|&child_id| take_released_child(instance, child_id)) {
+                    let child_id = hp.pending_release.remove(i).unwrap();
+                    NextOp::Op(enumerate_op(instance, ::udi::init::EnumerateLevel::Release(child_id), &[]).0)
+                }
+                else if let Some(attrs) = hp.pending_directed.pop_front() {
+                    NextOp::Op(enumerate_op(instance, ::udi::init::EnumerateLevel::Directed, &attrs).0)
+                }
+                else if hp.watching && !hp.removed_self && hp.new_cb.is_none() {
+                    // Not counted as running, the driver holds onto this until something changes
+                    let (op, cb) = enumerate_op(instance, ::udi::init::EnumerateLevel::New, &[]);
+                    hp.new_cb = Some(cb);
+                    return NextOp::Op(op);
+                }
+                else {
+                    return NextOp::Idle;
+                }
+                },
             };
         if let NextOp::Op(_) = rv {
             inner.running = true;
@@ -129,60 +202,81 @@ impl ManagementAgent
         enumeration_result: ::udi::init::EnumerateResult
     ) {
         let mut inner = self.inner.lock().unwrap();
-        assert!(inner.running);
-        inner.running = false;
-        let is = match inner.state
-            {
-            ManagementState::PreInit => panic!("enumerate_ack when in PreInit"),
-            ManagementState::Init(ref mut is) => is,
-            ManagementState::Initialised => panic!("enumerate_ack when already initialised"),
-            };
-        let DriverState::EnumChildren { ref mut flagged_complete } = is.state else {
-            panic!("`enumerate_ack` called when not expected");
-        };
-
-        match enumeration_result
+        let inner = &mut *inner;
+        match inner.state
         {
-        udi::init::EnumerateResult::Ok(child_info) => {
-            // The driver now owns this pointer
-            unsafe { cb.get_mut().child_data = ::core::ptr::null_mut(); }
-            let attrs = unsafe { ::core::slice::from_raw_parts((*cb).attr_list, (*cb).attr_valid_length as usize) };
-            for a in attrs {
-                let a_name = {
-                    let name_len = a.attr_name.iter().position(|v| *v == 0).unwrap_or(a.attr_name.len());
-                    let name = &a.attr_name[..name_len];
-                    ::std::str::from_utf8(name).unwrap_or("")
-                    };
-                println!("enumerate_ack: attr = {:?} {} {:?}", a_name, a.attr_type, &a.attr_value[..a.attr_length as usize]);
+        ManagementState::PreInit => panic!("enumerate_ack when in PreInit"),
+        ManagementState::Init(ref mut is) => {
+            assert!(inner.running);
+            inner.running = false;
+            let DriverState::EnumChildren { ref mut flagged_complete } = is.state else {
+                panic!("`enumerate_ack` called when not expected");
+            };
+            match enumeration_result
+            {
+            udi::init::EnumerateResult::Ok(child_info) => add_child(instance, &mut cb, child_info),
+            udi::init::EnumerateResult::Leaf => { *flagged_complete = true; },
+            udi::init::EnumerateResult::Done => { *flagged_complete = true; },
+            udi::init::EnumerateResult::Rescan => {
+                // Start again, forgetting about any children that haven't been bound yet
+                instance.children.lock().unwrap().retain(|c| c.is_bound.get());
+                is.state = DriverState::EnumChildrenStart;
+                },
+            udi::init::EnumerateResult::Removed(_)
+            |udi::init::EnumerateResult::RemovedSelf
+            |udi::init::EnumerateResult::Released => panic!("Unexpected enumeration result for START/NEXT: {:?}", enumeration_result),
+            udi::init::EnumerateResult::Failed => { *flagged_complete = true; },
             }
-
-            let mut child_bind_ops = None;
-            for entry in instance.module.udiprops.clone() {
-                if let ::udiprops_parse::Entry::ChildBindOps { meta_idx, region_idx, ops_idx } = entry {
-                    if ops_idx == child_info.ops_idx() {
-                        child_bind_ops = Some((meta_idx, region_idx));
+            },
+        ManagementState::Initialised => {
+            let hp = &mut inner.hotplug;
+            let is_new = hp.new_cb == Some(&*cb as *const _ as *mut _);
+            if is_new {
+                hp.new_cb = None;
+            }
+            else {
+                assert!(inner.running);
+                inner.running = false;
+            }
+            match enumeration_result
+            {
+            udi::init::EnumerateResult::Ok(child_info) => {
+                add_child(instance, &mut cb, child_info);
+                hp.children_changed = true;
+                },
+            udi::init::EnumerateResult::Rescan => {
+                instance.children.lock().unwrap().retain(|c| c.is_bound.get());
+                inner.state = ManagementState::Init(InitState { channel_to_parent: None, state: DriverState::EnumChildrenStart });
+                },
+            udi::init::EnumerateResult::Removed(child_id) => {
+                let mut children = instance.children.lock().unwrap();
+                match children.iter().position(|c| c.child_id == child_id)
+                {
+                Some(i) if children[i].is_bound.get() => {
+                    // Unbind the child's instance first, `poll` holds the release until that completes
+                    match children[i].instance.get().and_then(|v| v.upgrade())
+                    {
+                    Some(child_instance) => child_instance.management_state.request_unbind(),
+                    None => println!("enumerate_ack: Removed child {} is bound to an unknown instance", child_id),
                     }
+                    },
+                Some(i) => { children.remove(i); },
+                None => println!("enumerate_ack: Removed unknown child {}", child_id),
                 }
+                hp.pending_release.push_back(child_id);
+                },
+            udi::init::EnumerateResult::RemovedSelf => {
+                hp.removed_self = true;
+                },
+            udi::init::EnumerateResult::Released => {},
+            udi::init::EnumerateResult::Leaf
+            |udi::init::EnumerateResult::Done
+            |udi::init::EnumerateResult::Failed => if is_new {
+                // Hot-plug isn't supported
+                hp.watching = false;
+                },
             }
-            if let Some((meta_idx, region_idx)) = child_bind_ops {
-                let region_idx_real = instance.module.get_region_index(region_idx).unwrap();
-                instance.children.lock().unwrap().push(crate::DriverChild {
-                    is_bound: Default::default(),
-                    child_id: child_info.child_id(),
-                    meta_idx,
-                    ops_idx: child_info.ops_idx(),
-                    region_idx_real,
-                    attrs: attrs.to_vec()
-                });
-            }
-        },
-        udi::init::EnumerateResult::Leaf => { *flagged_complete = true; },
-        udi::init::EnumerateResult::Done => { *flagged_complete = true; },
-        udi::init::EnumerateResult::Rescan => todo!(),
-        udi::init::EnumerateResult::Removed(_) => todo!(),
-        udi::init::EnumerateResult::RemovedSelf => todo!(),
-        udi::init::EnumerateResult::Released => todo!(),
-        udi::init::EnumerateResult::Failed => { *flagged_complete = true; },
+            },
         }
         unsafe {
             if ! cb.child_data.is_null() {
@@ -195,19 +289,25 @@ impl ManagementAgent
     pub fn devmgmt_ack(
         &self,
         instance: &crate::DriverInstance,
-        _cb: ::udi::cb::CbHandle<::udi::ffi::meta_mgmt::udi_mgmt_cb_t>,
+        cb: ::udi::cb::CbHandle<::udi::ffi::meta_mgmt::udi_mgmt_cb_t>,
         flags: u8,
         status: ::udi::Result<()>
     ) {
         let _ = instance;
+        let mut inner = self.inner.lock().unwrap();
+        assert!(inner.running);
+        inner.running = false;
+        // Only `UDI_DMGMT_UNBIND` is sent
         match status {
         Ok( () ) => {
-            todo!("devmgmt_ack: flags={:#x}", flags);
+            println!("devmgmt_ack: Unbound, flags={:#x}", flags);
+            inner.unbound = true;
             }
         Err(e) => {
-            todo!("devmgmt_ack: Error {:?}", e);
+            println!("devmgmt_ack: Unbind failed: {:?}", e);
             }
         }
+        drop(cb);
     }
     pub fn final_cleanup_ack(
         &self,
@@ -370,23 +470,119 @@ impl InitState {
     fn next_op_enumerate(&mut self, instance: &Arc<crate::DriverInstance>, is_first: bool) -> crate::Operation
     {
         //println!("next_op_enumerate");
-        let pri_init = instance.module.pri_init;
         let level = if is_first {
-            ::udi::ffi::meta_mgmt::UDI_ENUMERATE_START
+            ::udi::init::EnumerateLevel::Start
         }
         else {
-            ::udi::ffi::meta_mgmt::UDI_ENUMERATE_NEXT
+            ::udi::init::EnumerateLevel::Next
         };
-        unsafe {
-            let cb: *mut ::udi::ffi::meta_mgmt::udi_enumerate_cb_t = alloc_cb_raw(&instance);
-            (*cb).gcb.scratch = ::libc::calloc(1, pri_init.mgmt_scratch_requirement);
-            (*cb).attr_list = ::libc::calloc(pri_init.enumeration_attr_list_length as _, ::core::mem::size_of::<udi::ffi::attr::udi_instance_attr_list_t>()) as _;
-            (*cb).child_data = if pri_init.child_data_size > 0 { ::libc::malloc(pri_init.child_data_size) } else { ::core::ptr::null_mut() };
-            //(*cb).trace_mask = 0;
-            //(*cb).meta_idx = 0;
-            crate::Operation::new( cb, move |cb| (pri_init.mgmt_ops.enumerate_req_op)(cb, level) )
+        enumerate_op(instance, level, &[]).0
+    }
+}
+
+/// Record a child reported with `UDI_ENUMERATE_OK`
+fn add_child(
+    instance: &crate::DriverInstance,
+    cb: &mut ::udi::cb::CbHandle<::udi::ffi::meta_mgmt::udi_enumerate_cb_t>,
+    child_info: ::udi::init::EnumerateResultOk
+) {
+    // The driver now owns this pointer
+    unsafe { cb.get_mut().child_data = ::core::ptr::null_mut(); }
+    let attrs = unsafe { ::core::slice::from_raw_parts(cb.attr_list, cb.attr_valid_length as usize) };
+    for a in attrs {
+        let a_name = {
+            let name_len = a.attr_name.iter().position(|v| *v == 0).unwrap_or(a.attr_name.len());
+            let name = &a.attr_name[..name_len];
+            ::std::str::from_utf8(name).unwrap_or("")
+            };
+        println!("enumerate_ack: attr = {:?} {} {:?}", a_name, a.attr_type, &a.attr_value[..a.attr_length as usize]);
+    }
+
+    let mut child_bind_ops = None;
+    for entry in instance.module.udiprops.clone() {
+        if let ::udiprops_parse::Entry::ChildBindOps { meta_idx, region_idx, ops_idx } = entry {
+            if ops_idx == child_info.ops_idx() {
+                child_bind_ops = Some((meta_idx, region_idx));
+            }
         }
     }
+    if let Some((meta_idx, region_idx)) = child_bind_ops {
+        let region_idx_real = instance.module.get_region_index(region_idx).unwrap();
+        let mut children = instance.children.lock().unwrap();
+        // A rescan can report children that are already bound
+        if children.iter().any(|c| c.child_id == child_info.child_id()) {
+            return ;
+        }
+        children.push(crate::DriverChild {
+            is_bound: Default::default(),
+            instance: Default::default(),
+            child_id: child_info.child_id(),
+            meta_idx,
+            ops_idx: child_info.ops_idx(),
+            region_idx_real,
+            attrs: attrs.to_vec()
+        });
+    }
+}
+
+/// Check if a removed child can be released (i.e. it is no longer bound), and forget about it if so
+fn take_released_child(instance: &crate::DriverInstance, child_id: ::udi::ffi::udi_ubit32_t) -> bool {
+    let mut children = instance.children.lock().unwrap();
+    let Some(i) = children.iter().position(|c| c.child_id == child_id) else {
+        return true;
+    };
+    if children[i].is_bound.get() {
+        match children[i].instance.get().map(|v| v.upgrade())
+        {
+        // The child's instance has gone away
+        Some(None) => {},
+        Some(Some(child_instance)) if child_instance.management_state.is_unbound() => {},
+        _ => return false,
+        }
+        children[i].is_bound.set(false);
+    }
+    children.remove(i);
+    true
+}
+
+/// Create an `udi_enumerate_req` operation, returning the CB so it can be matched with the ack
+fn enumerate_op(
+    instance: &Arc<crate::DriverInstance>,
+    level: ::udi::init::EnumerateLevel,
+    attrs: &[::udi::ffi::attr::udi_instance_attr_list_t]
+) -> (crate::Operation, *mut ::udi::ffi::meta_mgmt::udi_enumerate_cb_t)
+{
+    let pri_init = instance.module.pri_init;
+    let attr_list_len = pri_init.enumeration_attr_list_length as usize;
+    unsafe {
+        let cb: *mut ::udi::ffi::meta_mgmt::udi_enumerate_cb_t = alloc_cb_raw(&instance);
+        (*cb).gcb.scratch = ::libc::calloc(1, pri_init.mgmt_scratch_requirement);
+        (*cb).attr_list = ::libc::calloc(attr_list_len, ::core::mem::size_of::<udi::ffi::attr::udi_instance_attr_list_t>()) as _;
+        (*cb).child_data = if pri_init.child_data_size > 0 { ::libc::malloc(pri_init.child_data_size) } else { ::core::ptr::null_mut() };
+        (*cb).child_id = match level
+            {
+            ::udi::init::EnumerateLevel::Release(child_id) => child_id,
+            _ => 0,
+            };
+        let attrs = &attrs[..usize::min(attrs.len(), attr_list_len)];
+        ::core::ptr::copy_nonoverlapping(attrs.as_ptr(), (*cb).attr_list, attrs.len());
+        (*cb).attr_valid_length = attrs.len() as _;
+        //(*cb).trace_mask = 0;
+        //(*cb).meta_idx = 0;
+        let level = level.to_raw();
+        (crate::Operation::new( cb, move |cb| (pri_init.mgmt_ops.enumerate_req_op)(cb, level) ), cb)
+    }
+}
+
+/// Create an `udi_devmgmt_req` operation
+fn devmgmt_op(instance: &Arc<crate::DriverInstance>, mgmt_op: ::udi::ffi::udi_ubit8_t, parent_id: ::udi::ffi::udi_ubit8_t) -> crate::Operation
+{
+    let pri_init = instance.module.pri_init;
+    unsafe {
+        let cb: *mut ::udi::ffi::meta_mgmt::udi_mgmt_cb_t = alloc_cb_raw(instance);
+        (*cb).gcb.scratch = ::libc::calloc(1, pri_init.mgmt_scratch_requirement);
+        crate::Operation::new(cb, move |cb| (pri_init.mgmt_ops.devmgmt_req_op)(cb, mgmt_op, parent_id))
+    }
 }
 
 unsafe fn alloc_cb_raw<T>(instance: &super::DriverInstance) -> *mut T {
@@ -396,7 +592,8 @@ unsafe fn alloc_cb_raw<T>(instance: &super::DriverInstance) -> *mut T {
         context: instance.regions[0].context(),
         scratch: ::core::ptr::null_mut(),
         initiator_context: instance as *const _ as *mut _,
-        origin: ::core::ptr::null_mut(),
+        // No channel, so record the instance for `udi_cb_alloc` and friends
+        origin: instance as *const _ as *mut _,
     });
     rv as *mut T
 }
@@ -0,0 +1,159 @@
+//! GIO block device front-end
+//!
+//! Binds to a `gio_type=block` provider (e.g. [crate::gio_ramdisk]), writes a pattern spanning several blocks, reads
+//! it back and checks it, then flushes the device.
+use ::udi::meta_gio::{GioOp, RwParams};
+
+/// Byte offset of the test transfer
+const TEST_OFFSET: u64 = 512;
+/// Length of the test transfer
+const TEST_LEN: usize = 5*512;
+
+#[derive(Default)]
+struct Driver {
+    channel: ::core::cell::OnceCell< ::udi::ffi::udi_channel_t >,
+}
+
+fn test_pattern() -> Vec<u8> {
+    (0 .. TEST_LEN).map(|i| (i * 7 + i / 512) as u8).collect()
+}
+
+impl ::udi::init::Driver for ::udi::init::RData<Driver>
+{
+    const MAX_ATTRS: u8 = 0;
+    type Future_init<'s> = impl ::core::future::Future<Output=()>;
+    fn usage_ind<'s>(&'s self, _cb: udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+        async move { }
+    }
+
+    type Future_enumerate<'s> = impl ::core::future::Future<Output=(udi::init::EnumerateResult,udi::init::AttrSink<'s>)> + 's;
+    fn enumerate_req<'s>(
+        &'s self,
+        _cb: udi::init::CbRefEnumerate<'s>,
+        level: udi::init::EnumerateLevel,
+        attrs_out: udi::init::AttrSink<'s>
+    ) -> Self::Future_enumerate<'s>
+    {
+        async move {
+            match level
+            {
+            ::udi::init::EnumerateLevel::Start
+            |::udi::init::EnumerateLevel::StartRescan
+            |::udi::init::EnumerateLevel::Next => (::udi::init::EnumerateResult::Done, attrs_out),
+            udi::init::EnumerateLevel::New
+            |udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+            udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
+            }
+        }
+    }
+
+    type Future_devmgmt<'s> = impl ::core::future::Future<Output=::udi::Result<u8>> + 's;
+    fn devmgmt_req<'s>(&'s self, _cb: udi::init::CbRefMgmt<'s>, _mgmt_op: udi::init::MgmtOp, _parent_id: udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+        async move {
+            Ok(0)
+        }
+    }
+}
+
+impl ::udi::meta_gio::Client for ::udi::init::RData<Driver>
+{
+    type Future_bind_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn bind_ack<'s>(&'s self, cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_bind_cb_t>, size: ::udi::Result<u64>) -> Self::Future_bind_ack<'s> {
+        async move {
+            let size = match size {
+                Ok(v) => v,
+                Err(e) => { println!("GIO block: Bind failure: {:?}", e); return; },
+                };
+            println!("GIO block: {} bytes, {:?}", size, cb.xfer_constraints());
+            if self.channel.set(cb.gcb.channel).is_err() {
+                panic!("Bound twice?")
+            }
+
+            let mut xfer_cb = ::udi::meta_gio::alloc_xfer::<CbList::Xfer, _>(cb.gcb(), cb.gcb.channel, GioOp::Write, RwParams::new(TEST_OFFSET)).await;
+            let data = test_pattern();
+            xfer_cb.data_buf_mut().write(cb.gcb(), 0..0, &data).await;
+            ::udi::meta_gio::xfer_req(xfer_cb);
+        }
+    }
+
+    type Future_unbind_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn unbind_ack<'s>(&'s self, _cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_bind_cb_t>) -> Self::Future_unbind_ack<'s> {
+        async move {
+            println!("GIO block: Unbound");
+        }
+    }
+
+    type Future_xfer_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn xfer_ack<'s>(&'s self, cb: ::udi::cb::CbRef<'s, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t>) -> Self::Future_xfer_ack<'s> {
+        async move {
+            let channel = *self.channel.get().unwrap();
+            match cb.op()
+            {
+            GioOp::Write => {
+                println!("GIO block: Wrote {} bytes", cb.data_buf().len());
+                let mut xfer_cb = ::udi::meta_gio::alloc_xfer::<CbList::Xfer, _>(cb.gcb(), channel, GioOp::Read, RwParams::new(TEST_OFFSET)).await;
+                xfer_cb.data_buf_mut().ensure_size(cb.gcb(), TEST_LEN).await;
+                ::udi::meta_gio::xfer_req(xfer_cb);
+                },
+            GioOp::Read => {
+                let buf = cb.data_buf();
+                let mut data = vec![0; buf.len()];
+                buf.read(0, &mut data);
+                if data == test_pattern() {
+                    println!("GIO block: Read back {} bytes, data matches", data.len());
+                }
+                else {
+                    println!("GIO block: Read back {} bytes, DATA MISMATCH", data.len());
+                }
+                let xfer_cb = ::udi::meta_gio::alloc_xfer::<CbList::Xfer, _>(cb.gcb(), channel, ::udi::meta_gio::block::OP_FLUSH, ()).await;
+                ::udi::meta_gio::xfer_req(xfer_cb);
+                },
+            op if op == ::udi::meta_gio::block::OP_FLUSH => println!("GIO block: Flushed"),
+            op => println!("GIO block: Unexpected xfer_ack for {:?}", op),
+            }
+        }
+    }
+
+    type Future_xfer_nak<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn xfer_nak<'s>(&'s self, cb: ::udi::cb::CbRef<'s, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t>, res: ::udi::Result<()>) -> Self::Future_xfer_nak<'s> {
+        async move {
+            println!("GIO block: {:?} failed: {:?}", cb.op(), res);
+        }
+    }
+
+    fn xfer_ret(&self, cb: ::udi::cb::CbHandle<udi::ffi::meta_gio::udi_gio_xfer_cb_t>) {
+        drop(cb);
+    }
+
+    type Future_event_ind<'s> = impl ::core::future::Future<Output=()> + 's;
+    fn event_ind<'s>(&'s self, _cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_event_cb_t>, event: ::udi::meta_gio::GioEvent) -> Self::Future_event_ind<'s> {
+        async move {
+            println!("GIO block: Unexpected event {:?}", event);
+        }
+    }
+}
+
+::udi_macros::udiprops!("
+name 100
+properties_version 0x101
+requires udi_gio 0x101
+meta 1 udi_gio
+device 101 1 gio_type string block
+parent_bind_ops 1 0 1 1
+message 100 Sink GIO block
+message 101 Block Device
+
+region 0
+");
+const META_GIO: ::udi::ffi::udi_index_t = udiprops::meta::udi_gio;
+::udi::define_driver! {
+    Driver as INIT_INFO_GIOBLOCK;
+    ops: {
+        Client: Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_client_ops_t,
+    },
+    cbs: {
+        _Bind : Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_bind_cb_t,
+        Xfer : Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t,
+        _Event: Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_event_cb_t,
+    }
+}
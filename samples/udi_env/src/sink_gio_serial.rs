@@ -28,9 +28,9 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
 			|::udi::init::EnumerateLevel::Next => {
                 (::udi::init::EnumerateResult::Done, attrs_out)
                 },
-			udi::init::EnumerateLevel::New => todo!(),
-			udi::init::EnumerateLevel::Directed => todo!(),
-			udi::init::EnumerateLevel::Release => todo!(),
+			udi::init::EnumerateLevel::New
+			|udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+			udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
 			}
         }
     }
@@ -68,7 +68,7 @@ impl ::udi::meta_gio::Client for ::udi::init::RData<Driver>
                 // TEST: Send some data
                 let mut tx_cb = self.cb_pool.pop_front().unwrap();
                 {
-                    tx_cb.set_op(::udi::ffi::meta_gio::UDI_GIO_DIR_WRITE);
+                    tx_cb.set_op(::udi::meta_gio::GioOp::Write);
                     let buf = tx_cb.data_buf_mut();
                     buf.write(cb.gcb(), 0..buf.len(), b"hello").await;
                 }
@@ -90,13 +90,13 @@ impl ::udi::meta_gio::Client for ::udi::init::RData<Driver>
     type Future_xfer_ack<'s> = impl ::core::future::Future<Output=()>;
     fn xfer_ack<'s>(&'s self, cb: ::udi::cb::CbRef<'s, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t>) -> Self::Future_xfer_ack<'s> {
         async move {
-            match cb.op
+            match cb.op()
             {
-            ::udi::ffi::meta_gio::UDI_GIO_OP_READ => {
+            ::udi::meta_gio::GioOp::Read => {
                 self.handle_read(cb.data_buf(), false).await;
                 },
-            ::udi::ffi::meta_gio::UDI_GIO_OP_WRITE => {},
-            _ => todo!("xfer_ack - Unknown operation: {:#x}", cb.op),
+            ::udi::meta_gio::GioOp::Write => {},
+            op => todo!("xfer_ack - Unknown operation: {:?}", op),
             }
         }
     }
@@ -106,7 +106,7 @@ impl ::udi::meta_gio::Client for ::udi::init::RData<Driver>
         async move {
             match res {
             Ok(_) => {},
-            Err(e) if e.into_inner() == ::udi::ffi::UDI_STAT_DATA_UNDERRUN as _ && cb.op == ::udi::ffi::meta_gio::UDI_GIO_OP_READ => {
+            Err(e) if e.into_inner() == ::udi::ffi::UDI_STAT_DATA_UNDERRUN as _ && cb.op() == ::udi::meta_gio::GioOp::Read => {
                 // Signal the drivers
                 self.handle_read(cb.data_buf(), true).await;
                 },
@@ -122,11 +122,11 @@ impl ::udi::meta_gio::Client for ::udi::init::RData<Driver>
     }
 
     type Future_event_ind<'s> = impl ::core::future::Future<Output=()>;
-    fn event_ind<'s>(&'s self, _cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_event_cb_t>) -> Self::Future_event_ind<'s> {
+    fn event_ind<'s>(&'s self, _cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_event_cb_t>, _event: ::udi::meta_gio::GioEvent) -> Self::Future_event_ind<'s> {
         async move {
             // Grab a CB and populate it for read
             if let Some(mut xfer_cb) = self.cb_pool.pop_front() {
-                xfer_cb.set_op(::udi::ffi::meta_gio::UDI_GIO_OP_READ);
+                xfer_cb.set_op(::udi::meta_gio::GioOp::Read);
                 ::udi::meta_gio::xfer_req(xfer_cb);
             }
             else {
@@ -1,14 +1,8 @@
 #[derive(Default)]
 struct Driver {
-    channels: ::core::cell::OnceCell<Channels>,
-    parent_channel: ::core::cell::OnceCell<::udi::ffi::udi_channel_t>,
-
-    tx_cbs: ::udi::cb::SharedQueue<::udi::ffi::meta_nic::udi_nic_tx_cb_t>,
-}
-struct Channels {
-    rx: ::udi::imc::ChannelHandle,
-    #[allow(dead_code)]
-    tx: ::udi::imc::ChannelHandle,
+    nsr: ::udi::meta_nic::nsr::Binding,
+    /// Number of packets received, checked against the device's statistics
+    rx_count: ::core::cell::Cell<u32>,
 }
 
 impl ::udi::init::Driver for ::udi::init::RData<Driver>
@@ -35,9 +29,9 @@ impl ::udi::init::Driver for ::udi::init::RData<Driver>
 			|::udi::init::EnumerateLevel::Next => {
                 (::udi::init::EnumerateResult::Done, attrs_out)
                 },
-			udi::init::EnumerateLevel::New => todo!(),
-			udi::init::EnumerateLevel::Directed => todo!(),
-			udi::init::EnumerateLevel::Release => todo!(),
+			udi::init::EnumerateLevel::New
+			|udi::init::EnumerateLevel::Directed => (::udi::init::EnumerateResult::Failed, attrs_out),
+			udi::init::EnumerateLevel::Release(_) => (::udi::init::EnumerateResult::Released, attrs_out),
 			}
         }
     }
@@ -54,51 +48,28 @@ impl ::udi::meta_nic::NsrControl for ::udi::init::RData<Driver>
 {
     type Future_gbc<'s> = impl ::core::future::Future<Output=::udi::meta_nic::BindChannels>;
     fn get_bind_channels<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicBind<'a>) -> Self::Future_gbc<'a> {
-        async move {
-            let rv = ::udi::meta_nic::BindChannels {
-                rx: 1.into(),
-                tx: 2.into(),
-            };
-            if let Err(_) = self.channels.set(Channels {
-                rx: ::udi::imc::channel_spawn::<OpsList::Rx>(cb.gcb(), self, rv.rx).await,
-                tx: ::udi::imc::channel_spawn::<OpsList::Tx>(cb.gcb(), self, rv.tx).await,
-            }) {
-                panic!("Bound twice?")
-            }
-            rv
-        }
+        self.nsr.spawn_channels::<OpsList::Rx, OpsList::Tx, _>(cb, self, ::udi::meta_nic::BindChannels {
+            rx: 1.into(),
+            tx: 2.into(),
+        })
     }
     
     type Future_bind_ack<'s> = impl ::core::future::Future<Output=()>;
     fn bind_ack<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicBind<'a>, res: ::udi::Result<()>) -> Self::Future_bind_ack<'a> {
         async move {
-            match res {
-            Ok(()) => {
-                if let Err(_) = self.parent_channel.set( cb.gcb.channel ) {
-                    panic!("Bound twice?")
-                }
-                println!("--- SINK_NSR: New device, MAC: {:x?}", &cb.mac_addr[..cb.mac_addr_len as usize]);
-
-                // Allocate a collection of RX CBs and hand them to the device
-                let mut rx_cbs = ::udi::cb::alloc_batch::<CbList::_NicRx>(cb.gcb(), 6, Some((1520, ::udi::ffi::buf::UDI_NULL_PATH_BUF))).await;
-                while let Some(mut rx_cb) = rx_cbs.pop_front() {
-                    rx_cb.set_channel(&self.channels.get().unwrap().rx);
-                    ::udi::meta_nic::nd_rx_rdy(rx_cb);
-                }
-
-                ::udi::meta_nic::nd_enable_req({
-                    ::udi::cb::alloc::<CbList::_Nic>(cb.gcb(), cb.gcb.channel).await
-                });
-
-                // Send a test packet
-                if let Some(mut tx_cb) = self.tx_cbs.pop_front() {
-                    let buf = tx_cb.tx_buf_mut();
-                    buf.write(cb.gcb(), 0..buf.len(), b"TestPacketContent").await;
-                    ::udi::meta_nic::nd_tx_req(tx_cb);
-                }
-                },
-            Err(e) => println!("Error: {:?}", e),
+            if let Err(e) = res {
+                println!("Error: {:?}", e);
+                return;
             }
+            let info = match self.nsr.bound(cb) {
+                Ok(v) => v,
+                Err(e) => panic!("Invalid device description: {}", e),
+                };
+            println!("--- SINK_NSR: New device, MAC: {:?} ({:?}, PDU {:?})", info.mac_addr, info.media_type, info.pdu_range());
+
+            // Hand a collection of RX CBs to the device, and enable it
+            self.nsr.post_rx::<CbList::_NicRx>(cb.gcb(), 6).await;
+            self.nsr.enable::<CbList::_Nic>(cb.gcb()).await;
         }
     }
 
@@ -108,53 +79,95 @@ impl ::udi::meta_nic::NsrControl for ::udi::init::RData<Driver>
     }
 
     type Future_enable_ack<'s> = impl ::core::future::Future<Output=()>;
-    fn enable_ack<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNic<'a>, _res: ::udi::Result<()>) -> Self::Future_enable_ack<'a> {
-        async move { }
+    fn enable_ack<'a>(&'a self, cb: ::udi::meta_nic::CbRefNic<'a>, res: ::udi::Result<()>) -> Self::Future_enable_ack<'a> {
+        async move {
+            if let Err(e) = self.nsr.enable_ack(res) {
+                println!("NSR: Enable failed: {:?}", e);
+                return ;
+            }
+            // Send a test packet, using one of the TX CBs the ND handed over while binding
+            if let Err(e) = self.nsr.send(cb.gcb(), b"TestPacketContent").await {
+                println!("NSR: No test packet sent: {:?}", e);
+            }
+            // Exercise the control operations: join a multicast group, and read back the MAC address
+            use ::udi::meta_nic::{CtrlOp,MacAddress,MulticastList};
+            let groups = [MacAddress::new(&[0x01,0x00,0x5E,0x00,0x00,0x01])];
+            for op in [CtrlOp::AddMulti(MulticastList::from_slice(&groups)), CtrlOp::GetFactMac] {
+                let mut ctrl_cb = ::udi::cb::alloc::<CbList::NicCtrl>(cb.gcb(), cb.gcb.channel).await;
+                ctrl_cb.set_ctrl_op(cb.gcb(), op).await;
+                ::udi::meta_nic::nd_ctrl_req(ctrl_cb);
+            }
+        }
     }
 
     type Future_ctrl_ack<'s> = impl ::core::future::Future<Output=()>;
-    fn ctrl_ack<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicCtrl<'a>, _res: ::udi::Result<()>) -> Self::Future_ctrl_ack<'a> {
-        async move { todo!("ctrl_ack") }
+    fn ctrl_ack<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicCtrl<'a>, res: ::udi::Result<()>) -> Self::Future_ctrl_ack<'a> {
+        async move {
+            let op = ::udi::meta_nic::CtrlOp::from_cb(cb);
+            println!("NSR: Control {:?} = {:?}", op, res);
+            if let Err(e) = res {
+                panic!("Control request failed: {:?}", e);
+            }
+            if let Ok(::udi::meta_nic::CtrlOp::GetFactMac) = op {
+                let mac_addr = cb.read_mac_addr().expect("Malformed MAC address");
+                assert_eq!(Some(mac_addr), self.nsr.info().map(|i| i.mac_addr), "Factory MAC differs from the bound MAC");
+            }
+        }
     }
 
     type Future_info_ack<'s> = impl ::core::future::Future<Output=()>;
-    fn info_ack<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicInfo<'a>) -> Self::Future_info_ack<'a> {
-        async move { todo!("info_ack") }
+    fn info_ack<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicInfo<'a>) -> Self::Future_info_ack<'a> {
+        async move {
+            let info = ::udi::meta_nic::InfoAck::from_cb(&cb);
+            println!("NSR: Info {:?}", info);
+            assert!(info.interface_is_active, "Device reports inactive after enable");
+            // The device counts a packet before indicating it, so can be ahead of the NSR
+            assert!(info.stats.rx_packets >= self.rx_count.get(),
+                "Device reported {} RX packets, but {} were indicated", info.stats.rx_packets, self.rx_count.get());
+            assert_eq!(info.stats.rx_errors, 0);
+            assert_eq!(info.stats.tx_errors, 0);
+        }
     }
 
     type Future_status_ind<'s> = impl ::core::future::Future<Output=()>;
-    fn status_ind<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicStatus<'a>) -> Self::Future_status_ind<'a> {
-        async move { todo!("status_ind") }
+    fn status_ind<'a>(&'a self, _cb: ::udi::meta_nic::CbRefNicStatus<'a>, status: ::udi::meta_nic::NicStatus) -> Self::Future_status_ind<'a> {
+        async move {
+            println!("NSR: Link status {:?}", status);
+            self.nsr.status_ind(status);
+        }
     }
 }
-// SAFE: Just pushes to a list
-unsafe impl ::udi::meta_nic::NsrTx for ::udi::init::RData<Driver>
+impl ::udi::meta_nic::NsrTx for ::udi::init::RData<Driver>
 {
-    type Future_tx_rdy<'s> = impl ::core::future::Future<Output=()>;
-    fn tx_rdy<'a>(&'a self, cb: ::udi::meta_nic::CbHandleNicTx) -> Self::Future_tx_rdy<'a> {
-        self.tx_cbs.push_back(cb);
-        async move {}
+    fn tx_rdy(&self, cbs: ::udi::meta_nic::TxChain) {
+        self.nsr.tx_rdy(cbs);
     }
 }
-// SAFE: Panics
-unsafe impl ::udi::meta_nic::NsrRx for ::udi::init::RData<Driver>
+impl ::udi::meta_nic::NsrRx for ::udi::init::RData<Driver>
 {
     type Future_rx_ind<'s> = impl ::core::future::Future<Output=()>;
-    fn rx_ind<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicRx<'a>) -> Self::Future_rx_ind<'a> {
+    fn rx_ind<'a>(&'a self, cbs: ::udi::meta_nic::RxChainRef<'a>) -> Self::Future_rx_ind<'a> {
         async move {
-            let buf = cb.rx_buf_ref();
-            let mut local_buf = vec![0; buf.len()];
-            buf.read(0, &mut local_buf);
-            println!("NSR: RX packet {:x?}", local_buf);
+            for cb in cbs.iter() {
+                let buf = cb.rx_buf_ref();
+                let mut local_buf = vec![0; buf.len()];
+                buf.read(0, &mut local_buf);
+                println!("NSR: RX packet {:x?}", local_buf);
+                self.rx_count.set(self.rx_count.get() + 1);
+            }
+
+            // Check the device's statistics
+            let info_cb = ::udi::cb::alloc::<CbList::NicInfo>(cbs.gcb(), self.nsr.nd_channel()).await;
+            ::udi::meta_nic::nd_info_req(info_cb, false);
         }
     }
 
     type Future_exp_rx_ind<'s> = impl ::core::future::Future<Output=()>;
-    fn exp_rx_ind<'a>(&'a self, cb: ::udi::meta_nic::CbRefNicRx<'a>) -> Self::Future_exp_rx_ind<'a> {
-        self.rx_ind(cb)
+    fn exp_rx_ind<'a>(&'a self, cbs: ::udi::meta_nic::RxChainRef<'a>) -> Self::Future_exp_rx_ind<'a> {
+        self.rx_ind(cbs)
     }
-    fn rx_cb_ret(&self, cb: ::udi::meta_nic::CbHandleNicRx) {
-        ::udi::meta_nic::nd_rx_rdy(cb);
+    fn rx_cb_ret(&self, cbs: ::udi::meta_nic::RxChain) {
+        self.nsr.rx_cb_ret(cbs);
     }
 }
 
@@ -181,8 +194,9 @@ region 0
     cbs: {
         _Nic    : Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_cb_t,
         _NicBind: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_bind_cb_t,
-        _NicCtrl: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_ctrl_cb_t,
-        _NicInfo: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_info_cb_t,
+        NicCtrl : Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_ctrl_cb_t,
+        NicInfo : Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_info_cb_t,
+        _NicStatus: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_status_cb_t,
         _NicRx  : Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_rx_cb_t,
         _NicTx  : Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_tx_cb_t,
     }
@@ -103,14 +103,14 @@ impl RealUdiBuf {
         assert!(off <= self.len());
         self.invalidate_tags(off, 0);   // Zero length
         let old_len = self.len();
-        self.inner.resize(count, 0);
+        self.inner.resize(old_len + count, 0);
         self.inner.copy_within(off..old_len, off+count);
         self.raw.buf_size = self.inner.len();
 
         // Update the tag offsets. None should overlap due to `invalidate_tags` above
         for tag in self.tags.iter_mut() {
             if tag.tag_off >= off {
-                tag.tag_off -= count;
+                tag.tag_off += count;
             }
         }
     }
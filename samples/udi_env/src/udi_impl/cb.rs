@@ -20,7 +20,7 @@ impl<T: ::udi::metalang_trait::MetalangCb> MetalangCb for T {
 #[no_mangle]
 unsafe extern "C" fn udi_cb_alloc(callback: udi_cb_alloc_call_t, gcb: *mut udi_cb_t, cb_idx: udi_index_t, default_channel: udi_channel_t)
 {
-    let driver_module = &crate::channels::get_driver_instance(&(*gcb).channel).module;
+    let driver_module = &*get_driver_module(gcb);
     let rv = alloc(driver_module, cb_idx, (*gcb).context, default_channel);
     callback(gcb, rv);
 }
@@ -35,7 +35,7 @@ unsafe extern "C" fn udi_cb_alloc_dynamic(
     inline_layout: *const udi_layout_t
 )
 {
-    let driver_module = &crate::channels::get_driver_instance(&(*gcb).channel).module;
+    let driver_module = &*get_driver_module(gcb);
     let rv = alloc_internal(driver_module, cb_idx, (*gcb).context, default_channel, None, None, Some((inline_size, inline_layout)));
     crate::async_call(gcb, move |gcb| callback(gcb, rv))
 }
@@ -51,7 +51,7 @@ unsafe extern "C" fn udi_cb_alloc_batch(
     path_handle: udi_buf_path_t
 )
 {
-    let driver_module = &crate::channels::get_driver_instance(&(*gcb).channel).module;
+    let driver_module = &*get_driver_module(gcb);
     let mut prev_cb = ::core::ptr::null_mut();
     for _i in 0..count.0 {
         prev_cb = alloc_internal(
@@ -70,6 +70,20 @@ unsafe extern "C" fn udi_cb_free(cb: *mut udi_cb_t)
 
 // --------------------------------------------------------------------
 
+/// Get the module of the driver that owns `gcb`
+/// 
+/// Management agent CBs don't have a channel, instead the owning instance is stored in `origin`
+unsafe fn get_driver_module(gcb: *const udi_cb_t) -> ::std::sync::Arc<crate::DriverModule<'static>>
+{
+    if (*gcb).channel.is_null() {
+        assert!(!(*gcb).origin.is_null(), "CB has neither a channel nor an origin instance");
+        (*((*gcb).origin as *const crate::DriverInstance)).module.clone()
+    }
+    else {
+        crate::channels::get_driver_instance(&(*gcb).channel).module.clone()
+    }
+}
+
 pub fn alloc(driver_module: &crate::DriverModule, cb_idx: udi_index_t, context: *mut ::udi::ffi::c_void, default_channel: udi_channel_t) -> *mut udi_cb_t
 {
     alloc_internal(driver_module, cb_idx, context, default_channel, None, None, None)
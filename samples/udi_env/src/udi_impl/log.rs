@@ -12,7 +12,7 @@ pub unsafe extern "C" fn udi_debug_printf(fmt: *const ::core::ffi::c_char, mut a
     let format = ::core::ffi::CStr::from_ptr(fmt);
     let mut sink = Sink([0; 64], 0);
     print!("udi_debug_printf: ");
-    super::libc::snprintf_inner(&mut sink, format.to_bytes(), args.as_va_list());
+    super::libc::snprintf_inner(&mut sink, format.to_bytes(), args);
     ::std::io::stdout().write_all(&sink.0[..sink.1]).unwrap();
     println!("");
 }
@@ -49,7 +49,7 @@ pub unsafe extern "C" fn udi_trace_write(
 
     let mut sink = Sink([0; 64], 0);
     print!("udi_trace_write[{} T {}]: ", trace_event, meta);
-    super::libc::snprintf_inner(&mut sink, format.as_bytes(), args.as_va_list());
+    super::libc::snprintf_inner(&mut sink, format.as_bytes(), args);
     ::std::io::stdout().write_all(&sink.0[..sink.1]).unwrap();
     println!("");
 }
@@ -66,7 +66,7 @@ pub unsafe extern "C" fn udi_log_write(
 
     let mut sink = Sink([0; 64], 0);
     print!("udi_log_write[{} {} {}]: ", trace_event, severity, meta);
-    super::libc::snprintf_inner(&mut sink, format.as_bytes(), args.as_va_list());
+    super::libc::snprintf_inner(&mut sink, format.as_bytes(), args);
     ::std::io::stdout().write_all(&sink.0[..sink.1]).unwrap();
     println!("");
 
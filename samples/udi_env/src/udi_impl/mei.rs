@@ -0,0 +1,46 @@
+//! Metalanguage-to-Environment Interface
+//! 
+//! Used by metalanguages that aren't built into the environment, which are registered with
+//! [crate::register_metalanguage]
+use ::udi::ffi::*;
+use ::udi::ffi::mei::udi_mei_init_t;
+
+/// Call an operation of a MEI metalanguage, arguments are marshalled using the op's marshal layout and then passed
+/// to its backend stub once the call is dispatched.
+#[no_mangle]
+pub unsafe extern "C" fn udi_mei_call(
+    gcb: *mut udi_cb_t,
+    meta_info: *mut udi_mei_init_t,
+    meta_ops_num: udi_index_t,
+    vec_idx: udi_index_t,
+    args: ...
+)
+{
+    let Some((meta_name, spec)) = crate::get_metalang_by_mei(meta_info) else {
+        panic!("udi_mei_call: Unregistered metalanguage {:p}", meta_info);
+    };
+    let Some(op) = ::udi::mei::get_op_template(&*meta_info, meta_ops_num, vec_idx) else {
+        panic!("udi_mei_call: No op {}:{} in `{}`", meta_ops_num, vec_idx, meta_name);
+    };
+    let name = ::core::ffi::CStr::from_ptr(op.op_name).to_str().unwrap_or("?");
+    let backend_stub = op.backend_stub.expect("udi_mei_call: No backend stub");
+
+    // Marshal the arguments now, as they are only valid during this call
+    let marshal_layout = ::std::slice::from_raw_parts(op.marshal_layout, terminated_len(op.marshal_layout));
+    let mut marshal_space = vec![0usize; ::udi::mei::marshal_size(marshal_layout) / ::core::mem::size_of::<usize>()];
+    ::udi::mei::marshal_va(op.marshal_layout, args, marshal_space.as_mut_ptr() as *mut c_void);
+
+    crate::channels::remote_call_mei(name, gcb, meta_name, spec, meta_ops_num, op.meta_cb_num, move |ops_vector, gcb| {
+        let mut marshal_space = marshal_space;
+        backend_stub(*ops_vector.offset(vec_idx.0 as isize), gcb, marshal_space.as_mut_ptr() as *mut c_void)
+    });
+}
+
+/// Get the length of a layout, including the `UDI_DL_END`
+unsafe fn terminated_len(layout: *const udi_layout_t) -> usize {
+    let mut len = 1;
+    while *layout.add(len - 1) != layout::UDI_DL_END {
+        len += 1;
+    }
+    len
+}
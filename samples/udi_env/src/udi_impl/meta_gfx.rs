@@ -0,0 +1,73 @@
+use ::udi::ffi::*;
+use ::udi::ffi::meta_gfx::*;
+
+dispatch_call! {
+    fn udi_gfx_bind_req(cb: *mut udi_gfx_bind_cb_t)
+        => udi_gfx_provider_ops_t:gfx_bind_req_op;
+    fn udi_gfx_bind_ack(cb: *mut udi_gfx_bind_cb_t, sockets: udi_index_t, engines: udi_index_t, status: udi_status_t)
+        => udi_gfx_client_ops_t:gfx_bind_ack_op;
+    fn udi_gfx_unbind_req(cb: *mut udi_gfx_bind_cb_t)
+        => udi_gfx_provider_ops_t:gfx_unbind_req_op;
+    fn udi_gfx_unbind_ack(cb: *mut udi_gfx_bind_cb_t)
+        => udi_gfx_client_ops_t:gfx_unbind_ack_op;
+
+    fn udi_gfx_set_connector_req(cb: *mut udi_gfx_state_cb_t, value: udi_ubit32_t)
+        => udi_gfx_provider_ops_t:gfx_set_connector_req_op;
+    fn udi_gfx_set_engine_req(cb: *mut udi_gfx_state_cb_t, value: udi_ubit32_t)
+        => udi_gfx_provider_ops_t:gfx_set_engine_req_op;
+    fn udi_gfx_set_connector_ack(cb: *mut udi_gfx_state_cb_t)
+        => udi_gfx_client_ops_t:gfx_set_connector_ack_op;
+    fn udi_gfx_set_engine_ack(cb: *mut udi_gfx_state_cb_t)
+        => udi_gfx_client_ops_t:gfx_set_engine_ack_op;
+    fn udi_gfx_set_connector_nak(cb: *mut udi_gfx_state_cb_t, status: udi_status_t)
+        => udi_gfx_client_ops_t:gfx_set_connector_nak_op;
+    fn udi_gfx_set_engine_nak(cb: *mut udi_gfx_state_cb_t, status: udi_status_t)
+        => udi_gfx_client_ops_t:gfx_set_engine_nak_op;
+    fn udi_gfx_get_connector_req(cb: *mut udi_gfx_state_cb_t)
+        => udi_gfx_provider_ops_t:gfx_get_connector_req_op;
+    fn udi_gfx_get_engine_req(cb: *mut udi_gfx_state_cb_t)
+        => udi_gfx_provider_ops_t:gfx_get_engine_req_op;
+    fn udi_gfx_get_connector_ack(cb: *mut udi_gfx_state_cb_t, value: udi_ubit32_t)
+        => udi_gfx_client_ops_t:gfx_get_connector_ack_op;
+    fn udi_gfx_get_engine_ack(cb: *mut udi_gfx_state_cb_t, value: udi_ubit32_t)
+        => udi_gfx_client_ops_t:gfx_get_engine_ack_op;
+
+    fn udi_gfx_range_connector_req(cb: *mut udi_gfx_range_cb_t)
+        => udi_gfx_provider_ops_t:gfx_range_connector_req_op;
+    fn udi_gfx_range_engine_req(cb: *mut udi_gfx_range_cb_t)
+        => udi_gfx_provider_ops_t:gfx_range_engine_req_op;
+    fn udi_gfx_range_connector_ack(cb: *mut udi_gfx_range_cb_t)
+        => udi_gfx_client_ops_t:gfx_range_connector_ack_op;
+    fn udi_gfx_range_engine_ack(cb: *mut udi_gfx_range_cb_t)
+        => udi_gfx_client_ops_t:gfx_range_engine_ack_op;
+    fn udi_gfx_get_engine_operator_req(cb: *mut udi_gfx_range_cb_t)
+        => udi_gfx_provider_ops_t:gfx_get_engine_operator_req_op;
+    fn udi_gfx_get_engine_operator_ack(cb: *mut udi_gfx_range_cb_t, op: udi_ubit32_t, arg1: udi_ubit32_t, arg2: udi_ubit32_t, arg3: udi_ubit32_t)
+        => udi_gfx_client_ops_t:gfx_get_engine_operator_ack_op;
+
+    fn udi_gfx_connector_command_req(cb: *mut udi_gfx_command_cb_t)
+        => udi_gfx_provider_ops_t:gfx_connector_command_req_op;
+    fn udi_gfx_engine_command_req(cb: *mut udi_gfx_command_cb_t)
+        => udi_gfx_provider_ops_t:gfx_engine_command_req_op;
+    fn udi_gfx_connector_command_ack(cb: *mut udi_gfx_command_cb_t)
+        => udi_gfx_client_ops_t:gfx_connector_command_ack_op;
+    fn udi_gfx_engine_command_ack(cb: *mut udi_gfx_command_cb_t)
+        => udi_gfx_client_ops_t:gfx_engine_command_ack_op;
+
+    fn udi_gfx_buffer_info_req(cb: *mut udi_gfx_buffer_info_cb_t)
+        => udi_gfx_provider_ops_t:gfx_buffer_info_req_op;
+    fn udi_gfx_buffer_info_ack(cb: *mut udi_gfx_buffer_info_cb_t, width: udi_ubit32_t, height: udi_ubit32_t, bitsper: udi_ubit32_t, flags: udi_ubit32_t)
+        => udi_gfx_client_ops_t:gfx_buffer_info_ack_op;
+    fn udi_gfx_buffer_read_req(cb: *mut udi_gfx_buffer_cb_t)
+        => udi_gfx_provider_ops_t:gfx_buffer_read_req_op;
+    fn udi_gfx_buffer_write_req(cb: *mut udi_gfx_buffer_cb_t)
+        => udi_gfx_provider_ops_t:gfx_buffer_write_req_op;
+    fn udi_gfx_buffer_read_ack(cb: *mut udi_gfx_buffer_cb_t)
+        => udi_gfx_client_ops_t:gfx_buffer_read_ack_op;
+    fn udi_gfx_buffer_write_ack(cb: *mut udi_gfx_buffer_cb_t)
+        => udi_gfx_client_ops_t:gfx_buffer_write_ack_op;
+    fn udi_gfx_buffer_read_nak(cb: *mut udi_gfx_buffer_cb_t, status: udi_status_t)
+        => udi_gfx_client_ops_t:gfx_buffer_read_nak_op;
+    fn udi_gfx_buffer_write_nak(cb: *mut udi_gfx_buffer_cb_t, status: udi_status_t)
+        => udi_gfx_client_ops_t:gfx_buffer_write_nak_op;
+}
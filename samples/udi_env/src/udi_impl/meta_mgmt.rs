@@ -14,18 +14,9 @@ pub unsafe extern "C" fn udi_enumerate_ack(cb: *mut udi_enumerate_cb_t, enumerat
 {
     let cb = ::udi::cb::CbHandle::from_raw(cb);
     let instance = &*(cb.gcb.initiator_context as *mut crate::DriverInstance);
-    let enumeration_result = match enumeration_result
-        {
-        0 => ::udi::init::EnumerateResult::Ok(::udi::init::EnumerateResultOk::from_raw(ops_idx, (*cb).child_id)),
-        1 => ::udi::init::EnumerateResult::Leaf,
-        2 => ::udi::init::EnumerateResult::Done,
-        3 => ::udi::init::EnumerateResult::Rescan,
-        4 => ::udi::init::EnumerateResult::Removed((*cb).child_id),
-        5 => ::udi::init::EnumerateResult::RemovedSelf,
-        6 => ::udi::init::EnumerateResult::Released,
-        255 => ::udi::init::EnumerateResult::Failed,
-        _ => panic!("Unexpected value for enumeration_result {}", enumeration_result),
-        };
+    let Some(enumeration_result) = ::udi::init::EnumerateResult::from_raw(enumeration_result, ops_idx, (*cb).child_id) else {
+        panic!("Unexpected value for enumeration_result {}", enumeration_result);
+    };
     instance.management_state.enumerate_ack(&instance, cb, enumeration_result)
 }
 #[no_mangle]
@@ -0,0 +1,33 @@
+use ::udi::ffi::*;
+use ::udi::ffi::meta_scsi::*;
+
+dispatch_call! {
+    fn udi_scsi_bind_req(
+        cb: *mut udi_scsi_bind_cb_t,
+        bind_flags: udi_ubit16_t,
+        queue_depth: udi_ubit16_t,
+        max_sense_len: udi_ubit16_t,
+        aen_buf_size: udi_ubit16_t)
+        => udi_scsi_hd_ops_t:bind_req_op;
+    fn udi_scsi_bind_ack(cb: *mut udi_scsi_bind_cb_t, hd_timeout_increase: udi_ubit32_t, status: udi_status_t)
+        => udi_scsi_pd_ops_t:bind_ack_op;
+    fn udi_scsi_unbind_req(cb: *mut udi_scsi_bind_cb_t)
+        => udi_scsi_hd_ops_t:unbind_req_op;
+    fn udi_scsi_unbind_ack(cb: *mut udi_scsi_bind_cb_t)
+        => udi_scsi_pd_ops_t:unbind_ack_op;
+
+    fn udi_scsi_io_req(cb: *mut udi_scsi_io_cb_t)
+        => udi_scsi_hd_ops_t:io_req_op;
+    fn udi_scsi_io_ack(cb: *mut udi_scsi_io_cb_t)
+        => udi_scsi_pd_ops_t:io_ack_op;
+    fn udi_scsi_io_nak(cb: *mut udi_scsi_io_cb_t, status: udi_scsi_status_t, sense_buf: *mut udi_buf_t)
+        => udi_scsi_pd_ops_t:io_nak_op;
+
+    fn udi_scsi_ctl_req(cb: *mut udi_scsi_ctl_cb_t)
+        => udi_scsi_hd_ops_t:ctl_req_op;
+    fn udi_scsi_ctl_ack(cb: *mut udi_scsi_ctl_cb_t, status: udi_status_t)
+        => udi_scsi_pd_ops_t:ctl_ack_op;
+
+    fn udi_scsi_event_ind(cb: *mut udi_scsi_event_cb_t) => udi_scsi_pd_ops_t:event_ind_op;
+    fn udi_scsi_event_res(cb: *mut udi_scsi_event_cb_t) => udi_scsi_hd_ops_t:event_res_op;
+}
@@ -0,0 +1,99 @@
+use ::udi::ffi::*;
+use ::udi::ffi::meta_usb::*;
+
+dispatch_call! {
+    fn usbdi_bind_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:bind_req_op;
+    fn usbdi_bind_ack(cb: *mut usbdi_misc_cb_t, n_intfc: udi_index_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:bind_ack_op;
+    fn usbdi_unbind_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:unbind_req_op;
+    fn usbdi_unbind_ack(cb: *mut usbdi_misc_cb_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:unbind_ack_op;
+    fn usbdi_intfc_open_req(cb: *mut usbdi_misc_cb_t, alternate_intfc: udi_ubit8_t, open_flag: udi_ubit8_t)
+        => usbdi_usbd_intfc_ops_t:intfc_open_req_op;
+    fn usbdi_intfc_open_ack(cb: *mut usbdi_misc_cb_t, n_edpt: udi_index_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:intfc_open_ack_op;
+    fn usbdi_intfc_close_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:intfc_close_req_op;
+    fn usbdi_intfc_close_ack(cb: *mut usbdi_misc_cb_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:intfc_close_ack_op;
+
+    fn usbdi_frame_number_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:frame_number_req_op;
+    fn usbdi_frame_number_ack(cb: *mut usbdi_misc_cb_t, frame_number: udi_ubit32_t)
+        => usbdi_ldd_intfc_ops_t:frame_number_ack_op;
+    fn usbdi_device_speed_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:device_speed_req_op;
+    fn usbdi_device_speed_ack(cb: *mut usbdi_misc_cb_t, device_speed: udi_ubit8_t)
+        => usbdi_ldd_intfc_ops_t:device_speed_ack_op;
+    fn usbdi_reset_device_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:reset_device_req_op;
+    fn usbdi_reset_device_ack(cb: *mut usbdi_misc_cb_t)
+        => usbdi_ldd_intfc_ops_t:reset_device_ack_op;
+    fn usbdi_intfc_abort_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:intfc_abort_req_op;
+    fn usbdi_intfc_abort_ack(cb: *mut usbdi_misc_cb_t)
+        => usbdi_ldd_intfc_ops_t:intfc_abort_ack_op;
+    fn usbdi_intfc_state_set_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_intfc_ops_t:intfc_state_set_req_op;
+    fn usbdi_intfc_state_set_ack(cb: *mut usbdi_state_cb_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:intfc_state_set_ack_op;
+    fn usbdi_intfc_state_get_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_intfc_ops_t:intfc_state_get_req_op;
+    fn usbdi_intfc_state_get_ack(cb: *mut usbdi_state_cb_t)
+        => usbdi_ldd_intfc_ops_t:intfc_state_get_ack_op;
+    fn usbdi_desc_req(cb: *mut usbdi_desc_cb_t)
+        => usbdi_usbd_intfc_ops_t:desc_req_op;
+    fn usbdi_desc_ack(cb: *mut usbdi_desc_cb_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:desc_ack_op;
+    fn usbdi_device_state_get_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_intfc_ops_t:device_state_get_req_op;
+    fn usbdi_device_state_get_ack(cb: *mut usbdi_state_cb_t)
+        => usbdi_ldd_intfc_ops_t:device_state_get_ack_op;
+    fn usbdi_config_set_req(cb: *mut usbdi_misc_cb_t, config_value: udi_ubit16_t)
+        => usbdi_usbd_intfc_ops_t:config_set_req_op;
+    fn usbdi_config_set_ack(cb: *mut usbdi_misc_cb_t, status: udi_status_t)
+        => usbdi_ldd_intfc_ops_t:config_set_ack_op;
+    fn usbdi_async_event_ind(cb: *mut usbdi_misc_cb_t, async_event: udi_ubit16_t)
+        => usbdi_ldd_intfc_ops_t:async_event_ind_op;
+    fn usbdi_async_event_res(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_intfc_ops_t:async_event_res_op;
+
+    fn usbdi_intr_bulk_xfer_req(cb: *mut usbdi_intr_bulk_xfer_cb_t)
+        => usbdi_usbd_pipe_ops_t:intr_bulk_xfer_req_op;
+    fn usbdi_intr_bulk_xfer_ack(cb: *mut usbdi_intr_bulk_xfer_cb_t)
+        => usbdi_ldd_pipe_ops_t:intr_bulk_xfer_ack_op;
+    fn usbdi_intr_bulk_xfer_nak(cb: *mut usbdi_intr_bulk_xfer_cb_t, status: udi_status_t)
+        => usbdi_ldd_pipe_ops_t:intr_bulk_xfer_nak_op;
+    fn usbdi_control_xfer_req(cb: *mut usbdi_control_xfer_cb_t)
+        => usbdi_usbd_pipe_ops_t:control_xfer_req_op;
+    fn usbdi_control_xfer_ack(cb: *mut usbdi_control_xfer_cb_t, status: udi_status_t)
+        => usbdi_ldd_pipe_ops_t:control_xfer_ack_op;
+    fn usbdi_isoc_xfer_req(cb: *mut usbdi_isoc_xfer_cb_t)
+        => usbdi_usbd_pipe_ops_t:isoc_xfer_req_op;
+    fn usbdi_isoc_xfer_ack(cb: *mut usbdi_isoc_xfer_cb_t)
+        => usbdi_ldd_pipe_ops_t:isoc_xfer_ack_op;
+    fn usbdi_isoc_xfer_nak(cb: *mut usbdi_isoc_xfer_cb_t, status: udi_status_t)
+        => usbdi_ldd_pipe_ops_t:isoc_xfer_nak_op;
+    fn usbdi_pipe_abort_req(cb: *mut usbdi_misc_cb_t)
+        => usbdi_usbd_pipe_ops_t:pipe_abort_req_op;
+    fn usbdi_pipe_abort_ack(cb: *mut usbdi_misc_cb_t)
+        => usbdi_ldd_pipe_ops_t:pipe_abort_ack_op;
+    fn usbdi_pipe_state_set_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_pipe_ops_t:pipe_state_set_req_op;
+    fn usbdi_pipe_state_set_ack(cb: *mut usbdi_state_cb_t, status: udi_status_t)
+        => usbdi_ldd_pipe_ops_t:pipe_state_set_ack_op;
+    fn usbdi_pipe_state_get_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_pipe_ops_t:pipe_state_get_req_op;
+    fn usbdi_pipe_state_get_ack(cb: *mut usbdi_state_cb_t)
+        => usbdi_ldd_pipe_ops_t:pipe_state_get_ack_op;
+    fn usbdi_edpt_state_set_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_pipe_ops_t:edpt_state_set_req_op;
+    fn usbdi_edpt_state_set_ack(cb: *mut usbdi_state_cb_t, status: udi_status_t)
+        => usbdi_ldd_pipe_ops_t:edpt_state_set_ack_op;
+    fn usbdi_edpt_state_get_req(cb: *mut usbdi_state_cb_t)
+        => usbdi_usbd_pipe_ops_t:edpt_state_get_req_op;
+    fn usbdi_edpt_state_get_ack(cb: *mut usbdi_state_cb_t, status: udi_status_t)
+        => usbdi_ldd_pipe_ops_t:edpt_state_get_ack_op;
+}
@@ -7,6 +7,7 @@ pub mod buf;
 pub mod libc;
 pub mod log;
 pub mod physio;
+pub mod mei;
 
 macro_rules! dispatch_call {
     ( $($vis:vis fn $name:ident(cb: *mut $cb_ty:ty $(, $a_name:ident: $a_ty:ty)*) => $ops_ty:ty : $ops_name:ident;)+) => {
@@ -22,4 +23,7 @@ macro_rules! dispatch_call {
 pub mod meta_bus;
 pub mod meta_mgmt;
 pub mod meta_nic;
-pub mod meta_gio;
\ No newline at end of file
+pub mod meta_gio;
+pub mod meta_scsi;
+pub mod meta_usb;
+pub mod meta_gfx;
\ No newline at end of file
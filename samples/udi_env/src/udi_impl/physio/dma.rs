@@ -31,31 +31,211 @@ struct BackingRaw {
     /// The raw buffer
     buffer: *mut ::core::ffi::c_void,
 }
+impl Drop for BackingRaw {
+    fn drop(&mut self) {
+        unsafe { ::libc::free(self.buffer); }
+    }
+}
 struct DmaInfo {
-    data_handle: crate::emulated_devices::DmaHandle,
-    scgth_handle: crate::emulated_devices::DmaHandle,
+    /// Instance owning the device that the handles below were allocated from
+    instance: ::std::sync::Arc<crate::DriverInstance>,
+    data_handle: Option<crate::emulated_devices::DmaHandle>,
+    scgth_handle: Option<crate::emulated_devices::DmaHandle>,
     scgth: ffi::udi_scgth_t,
+    /// Driver-mapped copy of the scatter-gather list (`scgth.scgth_elements` points into this)
+    scgth_data: Box<[u64]>,
 }
 impl DmaInfo {
-    fn alloc(device: &dyn crate::emulated_devices::PioDevice, _constraints: &super::dma_constraints::ConstaintsReal, data_size: usize) -> Option<DmaInfo> {
+    fn alloc(instance: &::std::sync::Arc<crate::DriverInstance>, constraints: &super::dma_constraints::ConstaintsReal, data_size: usize) -> Option<DmaInfo> {
+        let device = instance.device.get().expect("DMA allocation with no registered device");
         let data_handle = device.dma().allocate(data_size)?;
-        let scgth_data = vec![
-            ffi::udi_scgth_element_32_t { block_busaddr: data_handle.addr(), block_length: data_handle.len(), },
-        ].into_boxed_slice();
+        let layout = ScgthLayout::new(constraints, data_handle.addr(), data_handle.len());
+        let Some(mut scgth_handle) = device.dma().allocate(layout.entries.len() * layout.entry_size()) else {
+            device.dma().free(data_handle);
+            return None;
+        };
+        let mut scgth_data = layout.encode(scgth_handle.addr());
+        // SAFE: Plain data
+        scgth_handle.write(0, unsafe { ::core::slice::from_raw_parts(scgth_data.as_ptr() as *const u8, scgth_data.len() * 8) });
+
+        let first_segment = if layout.num_data == 1 {
+            // A single element is passed directly
+            layout.entries[0]
+        }
+        else {
+            // Otherwise, point at the first segment of the list in device memory
+            ScgthEntry::Ext(scgth_handle.addr(), (layout.segment_len(0) * layout.entry_size()) as u32)
+        };
+        let el_ptr = if layout.driver_mapped { scgth_data.as_mut_ptr() } else { ::core::ptr::null_mut() };
+        let (format, first_segment, elements) = if layout.is_64 {
+            (
+                ffi::UDI_SCGTH_64,
+                ffi::udi_scgth_t_scgth_first_segment { el64: first_segment.encode_64(0, layout.swap) },
+                ffi::udi_scgth_t_scgth_elements { el64p: el_ptr as *mut _ },
+            )
+        }
+        else {
+            (
+                ffi::UDI_SCGTH_32,
+                ffi::udi_scgth_t_scgth_first_segment { el32: first_segment.encode_32(0, layout.swap) },
+                ffi::udi_scgth_t_scgth_elements { el32p: el_ptr as *mut _ },
+            )
+        };
         Some(DmaInfo {
-            data_handle,
-            scgth_handle: device.dma().allocate(::core::mem::size_of_val(&*scgth_data)).expect("Unable to allocate scgth handle"),
+            instance: instance.clone(),
+            scgth_handle: Some(scgth_handle),
+            data_handle: Some(data_handle),
             scgth: ffi::udi_scgth_t {
-                scgth_num_elements: scgth_data.len() as _,
-                scgth_format: ffi::UDI_SCGTH_32,
-                scgth_must_swap: ::udi::ffi::FALSE,
-                scgth_first_segment: ffi::udi_scgth_t_scgth_first_segment {
-                    el32: scgth_data[0],
-                },
-                scgth_elements: ffi::udi_scgth_t_scgth_elements { el32p: Box::into_raw(scgth_data) as *mut _ },
-            }
+                scgth_num_elements: layout.entries.len() as _,
+                scgth_format: format | layout.mapping_flags,
+                scgth_must_swap: if layout.swap { ::udi::ffi::TRUE } else { ::udi::ffi::FALSE },
+                scgth_first_segment: first_segment,
+                scgth_elements: elements,
+            },
+            scgth_data,
         })
     }
+    fn data_handle(&mut self) -> &mut crate::emulated_devices::DmaHandle {
+        self.data_handle.as_mut().unwrap()
+    }
+}
+impl Drop for DmaInfo {
+    fn drop(&mut self) {
+        let dma = self.instance.device.get().unwrap().dma();
+        dma.free(self.data_handle.take().unwrap());
+        dma.free(self.scgth_handle.take().unwrap());
+    }
+}
+
+/// A scatter-gather list entry, before encoding
+#[derive(Clone, Copy)]
+enum ScgthEntry {
+    /// Data element: bus address and length
+    Data(u32, u32),
+    /// Extension element: bus address and length (in bytes) of the next segment
+    Ext(u32, u32),
+}
+impl ScgthEntry {
+    /// Get the raw address and length fields (extension addresses are relative to `list_base`)
+    fn fields(&self, list_base: u32) -> (u32, u32, bool) {
+        match *self {
+        ScgthEntry::Data(a, l) => (a, l, false),
+        ScgthEntry::Ext(a, l) => (list_base + a, l, true),
+        }
+    }
+    fn encode_32(&self, list_base: u32, swap: bool) -> ffi::udi_scgth_element_32_t {
+        let (addr, len, ext) = self.fields(list_base);
+        let len = if ext { len | ffi::UDI_SCGTH_EXT } else { len };
+        let sw = |v: u32| if swap { v.swap_bytes() } else { v };
+        ffi::udi_scgth_element_32_t { block_busaddr: sw(addr), block_length: sw(len) }
+    }
+    fn encode_64(&self, list_base: u32, swap: bool) -> ffi::udi_scgth_element_64_t {
+        let (addr, len, ext) = self.fields(list_base);
+        let sw = |v: u32| if swap { v.swap_bytes() } else { v };
+        ffi::udi_scgth_element_64_t {
+            block_busaddr: if swap { (addr as u64).swap_bytes() } else { addr as u64 },
+            block_length: sw(len),
+            el_reserved: sw(if ext { ffi::UDI_SCGTH_EXT } else { 0 }),
+        }
+    }
+}
+/// Layout of a scatter-gather list, following the relevant DMA constraints
+struct ScgthLayout {
+    is_64: bool,
+    swap: bool,
+    driver_mapped: bool,
+    mapping_flags: u8,
+    /// Number of data elements
+    num_data: usize,
+    /// Number of data elements in each segment
+    per_segment: usize,
+    /// All entries (data and extension), with extension addresses relative to the start of the list
+    entries: Vec<ScgthEntry>,
+}
+impl ScgthLayout {
+    fn new(constraints: &super::dma_constraints::ConstaintsReal, addr: u32, len: u32) -> ScgthLayout {
+        let format = constraints.get(ffi::UDI_DMA_SCGTH_FORMAT).unwrap_or(ffi::UDI_SCGTH_32 as u32) as u8;
+        // Emulated bus addresses are 32-bit, so only use the 64-bit format if the driver requires it
+        let is_64 = format & ffi::UDI_SCGTH_32 == 0 && format & ffi::UDI_SCGTH_64 != 0;
+        let mapping_flags = format & (ffi::UDI_SCGTH_DMA_MAPPED|ffi::UDI_SCGTH_DRIVER_MAPPED);
+        let driver_mapped = mapping_flags != ffi::UDI_SCGTH_DMA_MAPPED;
+        let swap = match constraints.get(ffi::UDI_DMA_SCGTH_ENDIANNESS).map(|v| v as u8) {
+            Some(ffi::UDI_DMA_BIG_ENDIAN) => cfg!(target_endian = "little"),
+            Some(ffi::UDI_DMA_LITTLE_ENDIAN) => cfg!(target_endian = "big"),
+            _ => false,
+            };
+
+        // Split the data into elements no longer than the device can handle
+        let max_len = match constraints.get(ffi::UDI_DMA_ELEMENT_LENGTH_BITS) {
+            Some(bits @ 1 .. 32) => (1u32 << bits) - 1,
+            _ => u32::MAX,
+            };
+        let mut data = Vec::new();
+        let mut ofs = 0;
+        loop {
+            let l = (len - ofs).min(max_len);
+            data.push(ScgthEntry::Data(addr + ofs, l));
+            ofs += l;
+            if ofs == len {
+                break;
+            }
+        }
+
+        // Then group into segments, each (except the last) ending with an extension element
+        let per_segment = match constraints.get(ffi::UDI_DMA_SCGTH_MAX_EL_PER_SEG) {
+            Some(v) if v >= 2 && (v as usize) < data.len() => v as usize - 1,
+            _ => data.len(),
+            };
+        let mut rv = ScgthLayout {
+            is_64,
+            swap,
+            driver_mapped,
+            mapping_flags,
+            num_data: data.len(),
+            per_segment,
+            entries: Vec::new(),
+        };
+        let el_size = rv.entry_size();
+        let mut segments = data.chunks(per_segment).enumerate().peekable();
+        while let Some((i, seg)) = segments.next() {
+            rv.entries.extend_from_slice(seg);
+            if segments.peek().is_some() {
+                let next_ofs = rv.entries.len() + 1;
+                rv.entries.push(ScgthEntry::Ext((next_ofs * el_size) as u32, (rv.segment_len(i + 1) * el_size) as u32));
+            }
+        }
+        rv
+    }
+    fn entry_size(&self) -> usize {
+        if self.is_64 {
+            ::core::mem::size_of::<ffi::udi_scgth_element_64_t>()
+        }
+        else {
+            ::core::mem::size_of::<ffi::udi_scgth_element_32_t>()
+        }
+    }
+    /// Number of entries in a segment (including the trailing extension element)
+    fn segment_len(&self, idx: usize) -> usize {
+        let first = idx * self.per_segment;
+        let data = (self.num_data - first).min(self.per_segment);
+        if first + data < self.num_data { data + 1 } else { data }
+    }
+    /// Encode the list for placing at bus address `list_base`
+    fn encode(&self, list_base: u32) -> Box<[u64]> {
+        let mut rv = Vec::with_capacity(self.entries.len() * self.entry_size() / 8);
+        for e in &self.entries {
+            // SAFE: Both element types are plain data with sizes that are a multiple of 8
+            unsafe {
+                if self.is_64 {
+                    rv.extend_from_slice(&::core::mem::transmute::<_, [u64; 2]>(e.encode_64(list_base, self.swap)));
+                }
+                else {
+                    rv.push(::core::mem::transmute::<_, u64>(e.encode_32(list_base, self.swap)));
+                }
+            }
+        }
+        rv.into_boxed_slice()
+    }
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -75,6 +255,16 @@ impl Direction {
     }
 }
 
+#[no_mangle]
+unsafe extern "C" fn udi_dma_limits(dma_limits: *mut ffi::udi_dma_limits_t)
+{
+    *dma_limits = ffi::udi_dma_limits_t {
+        max_legal_contig_alloc: 1 << 20,
+        max_safe_contig_alloc: 0x1000,
+        cache_line_size: 64,
+    };
+}
+
 #[no_mangle]
 unsafe extern "C" fn udi_dma_prepare(
     callback: ffi::udi_dma_prepare_call_t,
@@ -115,7 +305,6 @@ unsafe extern "C" fn udi_dma_buf_map(
     println!("-- udi_dma_buf_map");
     let dma_handle = &mut *(dma_handle as *mut DmaHandleInner);
     let instance = crate::channels::get_driver_instance( &(*gcb).channel );
-    let device = &**instance.device.get().unwrap();
 
     let Some(dir) = Direction::from_flags(flags) else {
         panic!("`udi_dma_buf_map` with no direction flags")
@@ -142,7 +331,7 @@ unsafe extern "C" fn udi_dma_buf_map(
         backing.dir = dir;
     }
 
-    dma_handle.dma_info = Some(match DmaInfo::alloc(device, &backing.constraints, range.end - range.start)
+    dma_handle.dma_info = Some(match DmaInfo::alloc(&instance, &backing.constraints, range.end - range.start)
         {
         Some(dma_info) => dma_info,
         None => {
@@ -174,8 +363,8 @@ unsafe extern "C" fn udi_dma_buf_unmap(
     if !backing.buf.is_null() {
         assert!(new_buf_size <= (*backing.buf).buf_size);
         (*backing.buf).buf_size = new_buf_size;
-
-        // Free DMA handles?
+        // The device no longer has access to this buffer, so release the device view
+        dma_handle.dma_info = None;
     }
     else {
         println!("Warning: `udi_dma_buf_unmap` called with no mapped buffer");
@@ -196,7 +385,7 @@ unsafe extern "C" fn udi_dma_mem_alloc(
 {
     println!("-- udi_dma_mem_alloc");
     let instance = crate::channels::get_driver_instance( &(*gcb).channel );
-    let dev = instance.device.get().expect("Calling `udi_dma_mem_alloc` with no registered device");
+    assert!(instance.device.get().is_some(), "Calling `udi_dma_mem_alloc` with no registered device");
     let constraints = super::dma_constraints::ConstaintsReal::from_ref(&constraints);
     let Some(dir) = Direction::from_flags(flags) else {
         panic!("Calling `udi_dma_mem_alloc` with no direction flag set")
@@ -209,13 +398,22 @@ unsafe extern "C" fn udi_dma_mem_alloc(
         todo!("udi_dma_mem_alloc: Handle returning single_element")
     }
     let total_size = (element_size + pad) * nelements as usize;
-    let dma_info = match DmaInfo::alloc(&**dev, constraints, total_size)
-        {
-        Some(dma_info) => dma_info,
-        None => todo!("Error handling in `udi_dma_mem_alloc`"),
-        };
+    let Some(dma_info) = DmaInfo::alloc(&instance, constraints, total_size) else {
+        // The callback has no status, so failure is reported as a null handle (as for `UDI_MEM_NOWAIT`)
+        if flags & ::udi::ffi::mem::UDI_MEM_NOWAIT == 0 {
+            println!("Warning: `udi_dma_mem_alloc` of {} bytes failed without UDI_MEM_NOWAIT", total_size);
+        }
+        return crate::async_call(gcb, move |gcb| callback(gcb,
+            ::core::ptr::null_mut(), ::core::ptr::null_mut(), 0, ::udi::ffi::FALSE, ::core::ptr::null_mut(), ::udi::ffi::FALSE
+            ));
+    };
 
-    let mem_ptr = ::libc::malloc(total_size);
+    let mem_ptr = if flags & ::udi::ffi::mem::UDI_MEM_NOZERO != 0 {
+        ::libc::malloc(total_size)
+    }
+    else {
+        ::libc::calloc(1, total_size)
+    };
 
     let mut rv = Box::new(DmaHandleInner {
         backing: BackingData::RawData(BackingRaw {
@@ -285,10 +483,10 @@ unsafe extern "C" fn udi_dma_sync(
     let raw_data = &mut raw_data[offset..][..length];
     match dir {
     Direction::In => {
-        dma_info.data_handle.read(offset, raw_data);
+        dma_info.data_handle().read(offset, raw_data);
         //println!("udi_dma_sync: IN {:x?}", &raw_data[..raw_data.len().min(32)]);
         },
-    Direction::Out => dma_info.data_handle.write(offset, raw_data),
+    Direction::Out => dma_info.data_handle().write(offset, raw_data),
     Direction::BiDir => todo!("udi_dma_sync In+Out"),
     }
 
@@ -304,16 +502,12 @@ unsafe extern "C" fn udi_dma_scgth_sync(
 {
     let dma_handle = &mut *(dma_handle as *mut DmaHandleInner);
     let dma_info = dma_handle.dma_info.as_mut().expect("Calling `udi_dma_scgth_sync` with no DMA mapping");
-    let dst = {
-        let ele_size = if dma_info.scgth.scgth_format == ffi::UDI_SCGTH_32 {
-            ::core::mem::size_of::<ffi::udi_scgth_element_32_t>()
-        }
-        else {
-            ::core::mem::size_of::<ffi::udi_scgth_element_64_t>()
-        };
-        ::core::slice::from_raw_parts_mut(dma_info.scgth.scgth_elements.el32p as *mut u8, ele_size * dma_info.scgth.scgth_num_elements as usize)
-    };
-    dma_info.scgth_handle.read(0, dst);
+    // Only driver-mapped lists have a copy to update
+    if !dma_info.scgth.scgth_elements.el32p.is_null() {
+        let len = dma_info.scgth_data.len() * 8;
+        let dst = ::core::slice::from_raw_parts_mut(dma_info.scgth_data.as_mut_ptr() as *mut u8, len);
+        dma_info.scgth_handle.as_ref().unwrap().read(0, dst);
+    }
     crate::async_call(gcb, move |gcb| callback(gcb));
 }
 
@@ -327,7 +521,17 @@ unsafe extern "C" fn udi_dma_mem_barrier(dma_handle: ffi::udi_dma_handle_t)
 #[no_mangle]
 unsafe extern "C" fn udi_dma_free(dma_handle: ffi::udi_dma_handle_t)
 {
-    drop(Box::from_raw(dma_handle as *mut DmaHandleInner));
+    if dma_handle.is_null() {
+        return ;
+    }
+    let dma_handle = Box::from_raw(dma_handle as *mut DmaHandleInner);
+    if let BackingData::Buffer(ref backing) = dma_handle.backing {
+        // A still-mapped buffer is freed along with the handle
+        if !backing.buf.is_null() {
+            ::udi::ffi::buf::udi_buf_free(backing.buf);
+        }
+    }
+    drop(dma_handle);
 }
 
 #[no_mangle]
@@ -340,11 +544,14 @@ unsafe extern "C" fn udi_dma_mem_to_buf(
     mut dst_buf: *mut udi_buf_t
 )
 {
-    let dma_handle = &mut *(dma_handle as *mut DmaHandleInner);
+    println!("-- udi_dma_mem_to_buf");
+    // The handle (and its memory) is released by this call
+    let dma_handle = Box::from_raw(dma_handle as *mut DmaHandleInner);
     let backing = match dma_handle.backing {
         BackingData::Buffer(_) => panic!("`udi_dma_mem_to_buf` with buffer-mapped handle"),
-        BackingData::RawData(ref mut backing) => backing,
+        BackingData::RawData(ref backing) => backing,
         };
+    assert!(src_off + src_len <= backing.size, "`udi_dma_mem_to_buf` range {}+{} outside of allocation ({})", src_off, src_len, backing.size);
     
     let data = ::core::slice::from_raw_parts(backing.buffer as *const u8, backing.size);
     if !dst_buf.is_null() {
@@ -352,6 +559,7 @@ unsafe extern "C" fn udi_dma_mem_to_buf(
     }
 
     crate::udi_impl::buf::write(&mut dst_buf, 0..0, &data[src_off..][..src_len]);
+    drop(dma_handle);
 
     crate::async_call(gcb, move |gcb| callback(gcb, dst_buf));
 }
\ No newline at end of file
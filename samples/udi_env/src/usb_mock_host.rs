@@ -0,0 +1,417 @@
+//! Mock USB host (USBD), for testing USB function drivers
+//!
+//! Presents a single full-speed device with one configuration, containing one interface with three endpoints:
+//! - [EP_BULK_OUT]: bulk OUT, data written is stored in a loopback buffer
+//! - [EP_BULK_IN]: bulk IN, returns (and clears) the loopback buffer
+//! - [EP_ISOC_IN]: isochronous IN, each frame is filled with its frame number
+//!
+//! The default control pipe (address 0) handles GET_DESCRIPTOR, GET/SET_CONFIGURATION and clearing endpoint halts.
+//!
+//! There is no enumeration, the pipe channels are created by the caller (e.g. a test harness) and registered with
+//! [Driver::add_pipe].
+use ::std::cell::{Cell, RefCell};
+use ::std::collections::HashMap;
+use ::udi::meta_usb::{
+    DescRequest, DescriptorType, DeviceRequest, DeviceSpeed, DeviceState, Direction, PipeState, Recipient,
+    ControlXfer, IntrBulkXfer, IsocXfer,
+};
+use ::udi::ffi::meta_usb::usbdi_isoc_frame_request_t;
+
+/// Address of the bulk OUT endpoint
+pub const EP_BULK_OUT: u8 = 0x01;
+/// Address of the bulk IN endpoint
+pub const EP_BULK_IN: u8 = 0x81;
+/// Address of the isochronous IN endpoint
+pub const EP_ISOC_IN: u8 = 0x82;
+/// `bConfigurationValue` of the only configuration
+pub const CONFIG_VALUE: u8 = 1;
+
+/// Device descriptor
+pub const DEVICE_DESCRIPTOR: [u8; 18] = [
+    18, 0x01, 0x00,0x02, 0xFF,0x00,0x00, 64,
+    0x34,0x12, 0x78,0x56, 0x00,0x01,
+    0,0,0, 1,
+    ];
+/// Configuration descriptor set (configuration, interface, and endpoint descriptors)
+pub const CONFIG_DESCRIPTOR: [u8; 9+9+7*3] = [
+    9, 0x02, 39,0, 1, CONFIG_VALUE, 0, 0x80, 50,
+    9, 0x04, 0, 0, 3, 0xFF,0x00,0x00, 0,
+    7, 0x05, EP_BULK_OUT, 0x02, 64,0, 0,
+    7, 0x05, EP_BULK_IN, 0x02, 64,0, 0,
+    7, 0x05, EP_ISOC_IN, 0x01, 0,1, 1,
+    ];
+
+#[derive(Default)]
+pub struct Driver {
+    /// Selected configuration (zero if unconfigured)
+    config: Cell<u8>,
+    /// Interface has been opened
+    open: Cell<bool>,
+    /// Current frame number
+    frame_number: Cell<u32>,
+    /// Pipes, indexed by the host's end of the channel
+    pipes: RefCell<HashMap<usize,Pipe>>,
+    /// Data written to the bulk OUT endpoint
+    loopback: RefCell<Vec<u8>>,
+}
+struct Pipe {
+    address: u8,
+    state: PipeState,
+}
+impl Driver {
+    /// Register a pipe channel (the host's end) for the endpoint with address `address` (zero for the control pipe)
+    pub fn add_pipe(&self, channel: ::udi::ffi::udi_channel_t, address: u8) {
+        self.pipes.borrow_mut().insert(channel as usize, Pipe { address, state: PipeState::Active });
+    }
+    /// Stall the endpoint with address `address` (as if the device returned a STALL handshake)
+    pub fn stall(&self, address: u8) {
+        self.set_edpt_state(address, PipeState::Stalled);
+    }
+    /// Get the state of the endpoint with address `address`
+    pub fn edpt_state(&self, address: u8) -> Option<PipeState> {
+        self.pipes.borrow().values().find(|p| p.address == address).map(|p| p.state)
+    }
+    /// Get the selected configuration
+    pub fn config(&self) -> u8 {
+        self.config.get()
+    }
+
+    fn set_edpt_state(&self, address: u8, state: PipeState) {
+        for p in self.pipes.borrow_mut().values_mut().filter(|p| p.address == address) {
+            p.state = state;
+        }
+    }
+    /// Get the pipe for a CB's channel, checking that it can accept transfers
+    fn pipe_for_xfer(&self, gcb: &::udi::ffi::udi_cb_t) -> ::udi::Result<u8> {
+        let pipes = self.pipes.borrow();
+        let Some(pipe) = pipes.get(&(gcb.channel as usize)) else {
+            return Err(error(::udi::ffi::UDI_STAT_INVALID_STATE));
+        };
+        // The control pipe is usable before the configuration is set, others need an open interface
+        if pipe.address != 0 && !(self.open.get() && self.config.get() != 0) {
+            return Err(error(::udi::ffi::UDI_STAT_INVALID_STATE));
+        }
+        if !pipe.state.accepts_transfers() {
+            return Err(error(::udi::ffi::UDI_STAT_INVALID_STATE));
+        }
+        Ok(pipe.address)
+    }
+    fn with_pipe<R>(&self, gcb: &::udi::ffi::udi_cb_t, f: impl FnOnce(&mut Pipe)->R) -> ::udi::Result<R> {
+        match self.pipes.borrow_mut().get_mut(&(gcb.channel as usize)) {
+        Some(p) => Ok(f(p)),
+        None => Err(error(::udi::ffi::UDI_STAT_INVALID_STATE)),
+        }
+    }
+    fn descriptor(&self, desc_type: DescriptorType, index: u8) -> ::udi::Result<&'static [u8]> {
+        match (desc_type, index) {
+        (DescriptorType::Device, 0) => Ok(&DEVICE_DESCRIPTOR),
+        (DescriptorType::Config, 0) => Ok(&CONFIG_DESCRIPTOR),
+        _ => Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)),
+        }
+    }
+    fn set_config(&self, config_value: u16) -> ::udi::Result<()> {
+        if config_value != 0 && config_value != CONFIG_VALUE as u16 {
+            return Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED));
+        }
+        self.config.set(config_value as u8);
+        Ok(())
+    }
+}
+
+fn error(status: ::udi::ffi::StatusValues) -> ::udi::Error {
+    ::udi::Error::from_status(status as _).unwrap_err()
+}
+
+impl ::udi::init::Driver for ::udi::init::RData<Driver>
+{
+    const MAX_ATTRS: u8 = 0;
+    type Future_init<'s> = ::core::future::Ready<()>;
+    fn usage_ind<'s>(&'s self, _cb: ::udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+        ::core::future::ready(())
+    }
+
+    type Future_enumerate<'s> = ::core::future::Ready<(::udi::init::EnumerateResult,::udi::init::AttrSink<'s>)>;
+    fn enumerate_req<'s>(
+        &'s self,
+        _cb: ::udi::init::CbRefEnumerate<'s>,
+        _level: ::udi::init::EnumerateLevel,
+        attrs_out: ::udi::init::AttrSink<'s>
+    ) -> Self::Future_enumerate<'s>
+    {
+        ::core::future::ready((::udi::init::EnumerateResult::Done, attrs_out))
+    }
+
+    type Future_devmgmt<'s> = ::core::future::Ready<::udi::Result<u8>>;
+    fn devmgmt_req<'s>(&'s self, _cb: ::udi::init::CbRefMgmt<'s>, _mgmt_op: ::udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+        ::core::future::ready(Ok(0))
+    }
+}
+
+impl ::udi::meta_usb::UsbdIntfc for ::udi::init::RData<Driver>
+{
+    type Future_bind_req<'s> = ::core::future::Ready<::udi::Result<u8>>;
+    fn bind_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_bind_req<'s> {
+        ::core::future::ready(Ok(1))
+    }
+
+    type Future_unbind_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn unbind_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_unbind_req<'s> {
+        self.open.set(false);
+        ::core::future::ready(Ok(()))
+    }
+
+    type Future_intfc_open_req<'s> = ::core::future::Ready<::udi::Result<u8>>;
+    fn intfc_open_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>, alternate_intfc: u8, _open_flag: u8) -> Self::Future_intfc_open_req<'s> {
+        ::core::future::ready(if alternate_intfc != 0 {
+            Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED))
+        }
+        else {
+            self.open.set(true);
+            Ok(::udi::meta_usb::Descriptors::new(&CONFIG_DESCRIPTOR).interface_endpoints(0, 0).count() as u8)
+        })
+    }
+
+    type Future_intfc_close_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn intfc_close_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_intfc_close_req<'s> {
+        self.open.set(false);
+        ::core::future::ready(Ok(()))
+    }
+
+    type Future_frame_number_req<'s> = ::core::future::Ready<u32>;
+    fn frame_number_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_frame_number_req<'s> {
+        ::core::future::ready(self.frame_number.get())
+    }
+
+    type Future_device_speed_req<'s> = ::core::future::Ready<DeviceSpeed>;
+    fn device_speed_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_device_speed_req<'s> {
+        ::core::future::ready(DeviceSpeed::Full)
+    }
+
+    type Future_reset_device_req<'s> = ::core::future::Ready<()>;
+    fn reset_device_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_reset_device_req<'s> {
+        self.config.set(0);
+        for p in self.pipes.borrow_mut().values_mut() {
+            p.state = PipeState::Active;
+        }
+        ::core::future::ready(())
+    }
+
+    type Future_intfc_abort_req<'s> = ::core::future::Ready<()>;
+    fn intfc_abort_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_intfc_abort_req<'s> {
+        // Transfers complete immediately, so there is never anything to abort
+        ::core::future::ready(())
+    }
+
+    type Future_intfc_state_set_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn intfc_state_set_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>, state: PipeState) -> Self::Future_intfc_state_set_req<'s> {
+        let mut pipes = self.pipes.borrow_mut();
+        let rv = pipes.values_mut()
+            .filter(|p| p.address != 0)
+            .try_for_each(|p| {
+                p.state.check_set(state)?;
+                p.state = state;
+                Ok(())
+            });
+        ::core::future::ready(rv)
+    }
+
+    type Future_intfc_state_get_req<'s> = ::core::future::Ready<PipeState>;
+    fn intfc_state_get_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>) -> Self::Future_intfc_state_get_req<'s> {
+        // Report a stall on any endpoint, otherwise the state of the first (they're all set together)
+        let pipes = self.pipes.borrow();
+        let mut states = pipes.values().filter(|p| p.address != 0).map(|p| p.state);
+        let rv = if states.clone().any(|s| s == PipeState::Stalled) {
+            PipeState::Stalled
+        }
+        else {
+            states.next().unwrap_or(PipeState::Idle)
+        };
+        ::core::future::ready(rv)
+    }
+
+    type Future_desc_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn desc_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_desc_cb_t>, req: DescRequest, buf: &'s mut ::udi::buf::Handle) -> Self::Future_desc_req<'s> {
+        async move {
+            let data = self.descriptor(req.desc_type, req.index)?;
+            let data = &data[..data.len().min(req.length as usize)];
+            buf.write(cb.gcb(), .., data).await;
+            Ok(())
+        }
+    }
+
+    type Future_device_state_get_req<'s> = ::core::future::Ready<DeviceState>;
+    fn device_state_get_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>) -> Self::Future_device_state_get_req<'s> {
+        ::core::future::ready(if self.config.get() != 0 { DeviceState::CONFIGURED } else { DeviceState::NONE })
+    }
+
+    type Future_config_set_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn config_set_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>, config_value: u16) -> Self::Future_config_set_req<'s> {
+        ::core::future::ready(self.set_config(config_value))
+    }
+
+    type Future_async_event_res<'s> = ::core::future::Ready<()>;
+    fn async_event_res<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_async_event_res<'s> {
+        ::core::future::ready(())
+    }
+    fn async_event_ret(&self, cb: ::udi::cb::CbHandle<::udi::ffi::meta_usb::usbdi_misc_cb_t>) {
+        drop(cb);
+    }
+}
+
+impl ::udi::meta_usb::UsbdPipe for ::udi::init::RData<Driver>
+{
+    type Future_intr_bulk_xfer_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn intr_bulk_xfer_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_intr_bulk_xfer_cb_t>, xfer: IntrBulkXfer, data: &'s mut ::udi::buf::Handle) -> Self::Future_intr_bulk_xfer_req<'s> {
+        async move {
+            match (self.pipe_for_xfer(&cb.gcb)?, xfer.direction) {
+            (EP_BULK_OUT, Direction::Out) => {
+                let mut v = vec![0; data.len()];
+                data.read(0, &mut v);
+                self.loopback.borrow_mut().extend(v);
+                Ok(())
+            },
+            (EP_BULK_IN, Direction::In) => {
+                let len = data.len();
+                let v: Vec<u8> = {
+                    let mut lb = self.loopback.borrow_mut();
+                    let n = lb.len().min(len);
+                    lb.drain(..n).collect()
+                    };
+                data.write(cb.gcb(), .., &v).await;
+                if v.len() < len && !xfer.short_ok {
+                    return Err(error(::udi::ffi::UDI_STAT_DATA_UNDERRUN));
+                }
+                Ok(())
+            },
+            _ => Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)),
+            }
+        }
+    }
+
+    type Future_control_xfer_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn control_xfer_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_control_xfer_cb_t>, xfer: ControlXfer, data: &'s mut ::udi::buf::Handle) -> Self::Future_control_xfer_req<'s> {
+        async move {
+            if self.pipe_for_xfer(&cb.gcb)? != 0 {
+                return Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED));
+            }
+            let req = xfer.request;
+            let reply: &[u8] = match req.request {
+                DeviceRequest::GET_DESCRIPTOR => self.descriptor(DescriptorType::from_raw((req.value >> 8) as u8), req.value as u8)?,
+                DeviceRequest::GET_CONFIGURATION => &[self.config.get()],
+                DeviceRequest::SET_CONFIGURATION => {
+                    self.set_config(req.value)?;
+                    &[]
+                },
+                DeviceRequest::CLEAR_FEATURE
+                    if req.request_type.recipient == Recipient::Endpoint && req.value == DeviceRequest::FEATURE_ENDPOINT_HALT
+                    => {
+                    self.set_edpt_state(req.index as u8, PipeState::Active);
+                    &[]
+                },
+                _ => return Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)),
+                };
+            if req.request_type.direction == Direction::In {
+                let len = reply.len().min(req.length as usize);
+                data.write(cb.gcb(), .., &reply[..len]).await;
+                if len < req.length as usize && !xfer.short_ok {
+                    return Err(error(::udi::ffi::UDI_STAT_DATA_UNDERRUN));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    type Future_isoc_xfer_req<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+    fn isoc_xfer_req<'s>(&'s self,
+        cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_isoc_xfer_cb_t>,
+        xfer: IsocXfer,
+        data: &'s mut ::udi::buf::Handle,
+        frames: &'s mut [usbdi_isoc_frame_request_t]
+    ) -> Self::Future_isoc_xfer_req<'s> {
+        async move {
+            if self.pipe_for_xfer(&cb.gcb)? != EP_ISOC_IN || xfer.direction != Direction::In {
+                return Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED));
+            }
+            let start = if xfer.asap { self.frame_number.get() } else { xfer.frame_number.max(self.frame_number.get()) };
+            let mut v = Vec::new();
+            for (i,f) in frames.iter_mut().enumerate() {
+                v.extend(::core::iter::repeat_n((start as usize + i) as u8, f.frame_len as usize));
+                f.frame_status = ::udi::ffi::UDI_OK as _;
+            }
+            self.frame_number.set(start + frames.len() as u32);
+            data.write(cb.gcb(), .., &v).await;
+            Ok(())
+        }
+    }
+
+    type Future_pipe_abort_req<'s> = ::core::future::Ready<()>;
+    fn pipe_abort_req<'s>(&'s self, _cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_misc_cb_t>) -> Self::Future_pipe_abort_req<'s> {
+        ::core::future::ready(())
+    }
+
+    type Future_pipe_state_set_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn pipe_state_set_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>, state: PipeState) -> Self::Future_pipe_state_set_req<'s> {
+        let rv = self.with_pipe(&cb.gcb, |p| {
+            p.state.check_set(state)?;
+            p.state = state;
+            Ok(())
+        });
+        ::core::future::ready(rv.and_then(|v| v))
+    }
+
+    type Future_pipe_state_get_req<'s> = ::core::future::Ready<PipeState>;
+    fn pipe_state_get_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>) -> Self::Future_pipe_state_get_req<'s> {
+        ::core::future::ready(self.with_pipe(&cb.gcb, |p| p.state).unwrap_or(PipeState::Halted))
+    }
+
+    type Future_edpt_state_set_req<'s> = ::core::future::Ready<::udi::Result<()>>;
+    fn edpt_state_set_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>, state: PipeState) -> Self::Future_edpt_state_set_req<'s> {
+        let rv = self.with_pipe(&cb.gcb, |p| (p.state, p.address))
+            .and_then(|(cur, address)| {
+                cur.check_set(state)?;
+                self.set_edpt_state(address, state);
+                Ok(())
+            });
+        ::core::future::ready(rv)
+    }
+
+    type Future_edpt_state_get_req<'s> = ::core::future::Ready<::udi::Result<PipeState>>;
+    fn edpt_state_get_req<'s>(&'s self, cb: ::udi::CbRef<'s, ::udi::ffi::meta_usb::usbdi_state_cb_t>) -> Self::Future_edpt_state_get_req<'s> {
+        ::core::future::ready(self.with_pipe(&cb.gcb, |p| p.state))
+    }
+}
+
+::udi_macros::udiprops!("
+properties_version 0x101
+requires usbdi 0x100
+meta 1 usbdi
+region 0
+");
+const META_USBDI: ::udi::ffi::udi_index_t = udiprops::meta::usbdi;
+::udi::define_driver! {
+    Driver as INIT_INFO_USB_MOCK_HOST;
+    ops: {
+        Intfc: Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_usbd_intfc_ops_t,
+        Pipe : Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_usbd_pipe_ops_t,
+    },
+    cbs: {
+        Misc : Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_misc_cb_t,
+        _State: Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_state_cb_t,
+        _Desc : Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_desc_cb_t,
+        _IntrBulk: Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_intr_bulk_xfer_cb_t,
+        _Control : Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_control_xfer_cb_t,
+        _Isoc    : Meta=META_USBDI, ::udi::ffi::meta_usb::usbdi_isoc_xfer_cb_t,
+    }
+}
+/// Ops index of the interface channel
+pub const OPS_INTFC: ::udi::ffi::udi_index_t = OpsList::Intfc;
+/// Ops index of the pipe channels
+pub const OPS_PIPE: ::udi::ffi::udi_index_t = OpsList::Pipe;
+/// CB index for [::udi::meta_usb::async_event_ind]
+pub const CB_MISC: ::udi::ffi::udi_index_t = <CbList::Misc as ::udi::cb::CbDefinition>::INDEX;
+
+/// Get the driver module for the mock host
+pub fn module() -> crate::DriverModule<'static> {
+    // SAFE: The init info and udiprops are from the same driver
+    unsafe { crate::DriverModule::new(&INIT_INFO_USB_MOCK_HOST, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+}
@@ -0,0 +1,356 @@
+//! Helpers shared by the environment tests: connecting and binding instances, running them, and allocating CBs
+// Each test only uses some of these
+#![allow(dead_code)]
+use ::std::cell::RefCell;
+use ::std::marker::PhantomData;
+use ::std::sync::Arc;
+use ::udi::ffi::{udi_channel_t, udi_index_t};
+use ::udi_environment::{DriverInstance, DriverModule};
+use ::udi_environment::management_agent::NextOp;
+
+/// Create an instance of `module` (with the management agent not yet started)
+pub fn instance(module: DriverModule<'static>) -> Arc<DriverInstance> {
+    Arc::new(DriverInstance::new(Arc::new(module)))
+}
+
+/// Connect the primary regions of two instances with a new channel, anchored to the ops vectors `a_ops` and `b_ops`
+///
+/// Returns the `a` and `b` ends of the channel
+pub fn connect(a: &Arc<DriverInstance>, a_ops: udi_index_t, b: &Arc<DriverInstance>, b_ops: udi_index_t) -> (udi_channel_t, udi_channel_t) {
+    let (a_end, b_end) = ::udi_environment::channels::spawn_raw();
+    // SAFE: The ops indexes come from the module's own `define_driver!`, and each end is anchored once
+    unsafe {
+        let ops = a.module.get_meta_ops(a.module.get_ops_init(a_ops).unwrap());
+        ::udi_environment::channels::anchor(a_end, a.clone(), ops, a.regions[0].context());
+        let ops = b.module.get_meta_ops(b.module.get_ops_init(b_ops).unwrap());
+        ::udi_environment::channels::anchor(b_end, b.clone(), ops, b.regions[0].context());
+    }
+    (a_end, b_end)
+}
+
+/// Bind a new instance of `module` to child `child_id` of `parent`, and start its management agent
+pub fn bind_child(parent: &Arc<DriverInstance>, child_id: u32, module: Arc<DriverModule<'static>>) -> Arc<DriverInstance> {
+    let (channel_child, channel_parent) = ::udi_environment::channels::spawn_raw();
+    let inst = Arc::new(DriverInstance::new(module));
+    {
+        let children = parent.children.lock().unwrap();
+        let child = children.iter().find(|c| c.child_id == child_id).expect("No such child");
+        child.is_bound.set(true);
+        let _ = child.instance.set(Arc::downgrade(&inst));
+        let ops_init = parent.module.get_ops_init(child.ops_idx).unwrap();
+        // SAFE: The ops are the parent's bind ops for this child, and the channel is new
+        unsafe {
+            ::udi_environment::channels::anchor_with_context(
+                channel_parent, parent.clone(), parent.module.get_meta_ops(ops_init), ops_init.chan_context_size,
+                ::udi::ffi::init::udi_child_chan_context_t {
+                    rdata: parent.regions[child.region_idx_real].context(),
+                    child_id,
+                }
+            );
+        }
+    }
+    inst.management_state.start_init(Some(channel_child));
+    inst
+}
+
+/// Run queued operations in the primary region of each instance until they are all idle
+pub fn run(instances: &[&Arc<DriverInstance>]) {
+    loop {
+        let mut any = false;
+        for inst in instances {
+            let op = inst.regions[0].task_queue.lock().unwrap().pop_front();
+            if let Some(op) = op {
+                any = true;
+                op.invoke();
+            }
+        }
+        if !any {
+            break;
+        }
+    }
+}
+
+/// Management agent events seen by [run_managed]
+#[derive(Debug,PartialEq)]
+pub enum MaEvent {
+    InitComplete,
+    ChildrenChanged,
+}
+/// Run the management agents and queued operations of each instance until they are all idle
+pub fn run_managed(instances: &[&Arc<DriverInstance>]) -> Vec<MaEvent> {
+    let mut rv = Vec::new();
+    loop {
+        let mut busy = false;
+        for inst in instances {
+            match inst.management_state.poll(inst)
+            {
+            NextOp::Idle => {},
+            NextOp::Op(op) => { inst.regions[0].task_queue.lock().unwrap().push_back(op); busy = true; },
+            NextOp::InitComplete => { rv.push(MaEvent::InitComplete); busy = true; },
+            NextOp::ChildrenChanged => { rv.push(MaEvent::ChildrenChanged); busy = true; },
+            }
+            let op = inst.regions[0].task_queue.lock().unwrap().pop_front();
+            if let Some(op) = op {
+                op.invoke();
+                busy = true;
+            }
+        }
+        if !busy {
+            break;
+        }
+    }
+    rv
+}
+
+/// The emulated PCI bridge (always `instances[0]`), and the instances bound below it
+pub struct Platform {
+    pub instances: Vec<Arc<DriverInstance>>,
+    /// Actions seen by the emulated devices
+    pub actions: ::udi_environment::emulated_devices::Actions,
+}
+impl Platform {
+    /// Create the PCI bridge instance, and start its management agent
+    pub fn new() -> Platform {
+        let pci = Arc::new(DriverInstance::new(pci_module()));
+        pci.management_state.start_init(None);
+        Platform { instances: vec![pci], actions: Default::default() }
+    }
+    /// Run management agents, devices, and queued operations until everything is idle
+    pub fn run(&mut self) {
+        loop {
+            let mut busy = false;
+            for inst in &self.instances {
+                match inst.management_state.poll(inst)
+                {
+                NextOp::Idle => {},
+                NextOp::Op(op) => { inst.regions[0].task_queue.lock().unwrap().push_back(op); busy = true; },
+                NextOp::InitComplete | NextOp::ChildrenChanged => busy = true,
+                }
+                if let Some(dev) = inst.device.get() {
+                    dev.poll(&mut self.actions);
+                }
+            }
+            for inst in &self.instances {
+                for rgn in &inst.regions {
+                    let op = rgn.task_queue.lock().unwrap().pop_front();
+                    if let Some(op) = op {
+                        op.invoke();
+                        busy = true;
+                    }
+                }
+            }
+            if !busy {
+                break;
+            }
+        }
+    }
+    /// Bind a new instance of `module` to child `child_id` of `parent`
+    pub fn bind_child(&mut self, parent: &Arc<DriverInstance>, child_id: u32, module: Arc<DriverModule<'static>>) -> Arc<DriverInstance> {
+        let inst = bind_child(parent, child_id, module);
+        self.instances.push(inst.clone());
+        inst
+    }
+}
+pub fn pci_module() -> Arc<DriverModule<'static>> {
+    use ::udi_environment::bridge_pci::{INIT_INFO_PCI, udiprops::udiprops as raw_udiprops};
+    Arc::new(unsafe { DriverModule::new(&INIT_INFO_PCI, ::udiprops_parse::load_from_raw_section(&raw_udiprops)) })
+}
+/// The sample PCI-to-ISA bridge, which is PCI child 3
+pub fn isa_module() -> Arc<DriverModule<'static>> {
+    use ::udi_environment::bridge_isa::{INIT_INFO_ISA, udiprops::udiprops as raw_udiprops};
+    Arc::new(unsafe { DriverModule::new(&INIT_INFO_ISA, ::udiprops_parse::load_from_raw_section(&raw_udiprops)) })
+}
+
+/// Allocate a CB in the primary region of `inst`
+pub fn alloc_raw(inst: &DriverInstance, cb_idx: udi_index_t, channel: udi_channel_t) -> *mut ::udi::ffi::udi_cb_t {
+    ::udi_environment::udi_impl::cb::alloc(&inst.module, cb_idx, inst.regions[0].context(), channel)
+}
+/// Allocate a CB in the primary region of `inst`, as a handle
+///
+/// `T` must be the type of the `cb_idx` CB
+pub fn alloc<T: ::udi::metalang_trait::MetalangCb + Unpin + 'static>(inst: &DriverInstance, cb_idx: udi_index_t, channel: udi_channel_t) -> ::udi::cb::CbHandle<T> {
+    // SAFE: The caller ensures the CB index matches the type
+    unsafe { ::udi::cb::CbHandle::from_raw(alloc_raw(inst, cb_idx, channel) as *mut T) }
+}
+
+/// Get the region data of the primary region of `inst`
+///
+/// Region data is initialised by the first operation in the region, so a test must do a round trip (or run the
+/// management agent) before touching it.
+///
+/// `T` must be the driver's region data type
+pub fn rdata<T>(inst: &DriverInstance) -> &::udi::init::RData<T> {
+    // SAFE: The caller ensures the type matches, and the context lives as long as the instance
+    unsafe { &*(inst.regions[0].context() as *const _) }
+}
+
+/// Events recorded by a test driver's ops, for the test to take after each run
+pub struct Recorder<E>(RefCell<Vec<E>>);
+impl<E> Default for Recorder<E> {
+    fn default() -> Self {
+        Recorder(RefCell::new(Vec::new()))
+    }
+}
+impl<E> Recorder<E> {
+    pub fn push(&self, ev: E) {
+        self.0.borrow_mut().push(ev);
+    }
+    /// Take the events recorded since the last call
+    pub fn take(&self) -> Vec<E> {
+        self.0.take()
+    }
+}
+/// A test driver that records its events, returned by [Pair::run]
+pub trait Recording {
+    type Event;
+    fn events(&self) -> &Recorder<Self::Event>;
+}
+
+/// A driver under test and the instance it talks to, connected without management agents
+///
+/// `D` and `P` are the region data types of the driver and its peer, and `C` holds the ends of the channels between
+/// them (as returned by the `connect` closure given to [Pair::new])
+pub struct Pair<D, P, C> {
+    driver: Arc<DriverInstance>,
+    peer: Arc<DriverInstance>,
+    pub channels: C,
+    _rdata: PhantomData<fn() -> (D, P)>,
+}
+impl<D, P, C> Pair<D, P, C> {
+    /// Create an instance of each module, and connect them with `connect` (see [connect])
+    pub fn new(
+        driver: DriverModule<'static>,
+        peer: DriverModule<'static>,
+        connect: impl FnOnce(&Arc<DriverInstance>, &Arc<DriverInstance>) -> C
+    ) -> Self {
+        let driver = instance(driver);
+        let peer = instance(peer);
+        let channels = connect(&driver, &peer);
+        Pair { driver, peer, channels, _rdata: PhantomData }
+    }
+    /// Allocate a CB in the driver's primary region
+    pub fn alloc_raw(&self, cb_idx: udi_index_t, channel: udi_channel_t) -> *mut ::udi::ffi::udi_cb_t {
+        alloc_raw(&self.driver, cb_idx, channel)
+    }
+    /// Allocate a CB in the driver's primary region, as a handle (see [alloc])
+    pub fn alloc<T: ::udi::metalang_trait::MetalangCb + Unpin + 'static>(&self, cb_idx: udi_index_t, channel: udi_channel_t) -> ::udi::cb::CbHandle<T> {
+        alloc(&self.driver, cb_idx, channel)
+    }
+    /// Get the driver's region data (see [rdata])
+    pub fn driver(&self) -> &::udi::init::RData<D> {
+        rdata(&self.driver)
+    }
+    /// Get the peer's region data (see [rdata])
+    pub fn peer(&self) -> &::udi::init::RData<P> {
+        rdata(&self.peer)
+    }
+}
+impl<D: Recording, P, C> Pair<D, P, C> {
+    /// Run queued operations in both instances until they are idle, and take the driver's events
+    pub fn run(&self) -> Vec<D::Event> {
+        run(&[&self.driver, &self.peer]);
+        self.driver().events().take()
+    }
+}
+
+/// Implement `udi::init::Driver` for `RData<$driver>`, for drivers that are never given management operations
+macro_rules! unmanaged_driver {
+    ($driver:ty) => {
+        impl ::udi::init::Driver for ::udi::init::RData<$driver> {
+            const MAX_ATTRS: u8 = 0;
+            type Future_init<'s> = ::core::future::Pending<()>;
+            fn usage_ind<'s>(&'s self, _cb: ::udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+                unreachable!()
+            }
+            type Future_enumerate<'s> = ::core::future::Pending<(::udi::init::EnumerateResult,::udi::init::AttrSink<'s>)>;
+            fn enumerate_req<'s>(&'s self, _cb: ::udi::init::CbRefEnumerate<'s>, _level: ::udi::init::EnumerateLevel, _attrs_out: ::udi::init::AttrSink<'s>) -> Self::Future_enumerate<'s> {
+                unreachable!()
+            }
+            type Future_devmgmt<'s> = ::core::future::Pending<::udi::Result<u8>>;
+            fn devmgmt_req<'s>(&'s self, _cb: ::udi::init::CbRefMgmt<'s>, _mgmt_op: ::udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+                unreachable!()
+            }
+        }
+    };
+}
+pub(crate) use unmanaged_driver;
+
+/// A driver with no ops, for tests that call the environment's services directly
+pub mod bare {
+    #[derive(Default)]
+    pub struct Driver;
+    super::unmanaged_driver!(Driver);
+
+    ::udi_macros::udiprops!("
+region 0
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {},
+        cbs: {}
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+}
+
+/// A bare `udi_cb_t` on a channel anchored to an instance, for calling environment services (and the library's
+/// async wrappers) directly
+pub struct Gcb {
+    instance: Arc<DriverInstance>,
+    gcb: Box<::udi::ffi::udi_cb_t>,
+    /// Backing for `gcb.scratch`, used by async tasks
+    scratch: Box<[u64; 512]>,
+}
+impl Gcb {
+    pub fn new(instance: &Arc<DriverInstance>) -> Gcb {
+        /// Channel ops for the test's end of the channel (never invoked)
+        struct NoOps;
+        impl ::udi::metalang_trait::MetalangOpsHandler for NoOps {
+            fn type_name(&self) -> &'static str { "NoOps" }
+            fn type_id(&self) -> ::core::any::TypeId { ::core::any::TypeId::of::<Self>() }
+            fn channel_event_ind_op(&self) -> ::udi::ffi::imc::udi_channel_event_ind_op_t {
+                unsafe extern "C" fn f(_: *mut ::udi::ffi::imc::udi_channel_event_cb_t) {}
+                f
+            }
+        }
+        let (channel, _) = ::udi_environment::channels::spawn_raw();
+        // SAFE: The channel is new, and the ops ignore everything
+        unsafe { ::udi_environment::channels::anchor(channel, instance.clone(), &NoOps, instance.regions[0].context()); }
+        let mut scratch = Box::new([0; 512]);
+        Gcb {
+            instance: instance.clone(),
+            gcb: Box::new(::udi::ffi::udi_cb_t {
+                channel,
+                context: ::core::ptr::null_mut(),
+                scratch: scratch.as_mut_ptr() as *mut _,
+                initiator_context: ::core::ptr::null_mut(),
+                origin: ::core::ptr::null_mut(),
+            }),
+            scratch,
+        }
+    }
+    pub fn as_ptr(&mut self) -> *mut ::udi::ffi::udi_cb_t {
+        &mut *self.gcb
+    }
+    /// Run an async task (using the library's wrappers) to completion
+    pub fn block_on<T, R>(&mut self, task: impl FnOnce(::udi::CbRef<'static, ::udi::ffi::udi_cb_t>) -> T) -> R
+    where
+        T: 'static + ::core::future::Future<Output=R>,
+        R: 'static,
+    {
+        let rv = ::std::rc::Rc::new(::std::cell::RefCell::new(None));
+        let slot = rv.clone();
+        let scratch_size = ::core::mem::size_of_val(&*self.scratch);
+        // SAFE: The GCB and its scratch outlive the task, which is run to completion here
+        unsafe { ::udi::async_helpers::spawn_on_gcb(self.as_ptr(), scratch_size, task, move |r| *slot.borrow_mut() = Some(r)); }
+        run(&[&self.instance]);
+        let rv = rv.borrow_mut().take();
+        rv.expect("Task did not complete")
+    }
+}
+
+/// Get the error for a (non-OK) status value
+pub fn error(status: ::udi::ffi::StatusValues) -> ::udi::Error {
+    ::udi::Error::from_status(status as _).unwrap_err()
+}
@@ -0,0 +1,214 @@
+//! A metalanguage defined outside of `udi`, called through `udi_mei_call`
+//!
+//! A counter provider and a recording client are connected by a channel, and requests are driven through the
+//! generated request functions.
+use ::udi::ffi::udi_channel_t;
+
+mod common;
+
+mod counter {
+    use ::udi::ffi::*;
+    ::udi::define_metalanguage! {
+        NAME acme_counter;
+        CBS {
+            /// Control block for all counter operations
+            1 => acme_counter_cb_t {
+                /// Label, returned unchanged
+                label: udi_ubit8_t,
+                /// Unused data buffer
+                data: *mut udi_buf_t,
+            } : BUF data,
+        }
+        OPS {
+            /// Counter provider operations
+            1 => acme_counter_provider_ops_t : Provider {
+                /// Add `amount * scale` to the counter, replying with the new total
+                REQ fn add_req(cb: acme_counter_cb_t, amount: udi_ubit32_t, scale: udi_ubit8_t) -> add_ack(total: udi_ubit32_t, wrapped: udi_boolean_t) as Future_add_req;
+                /// Reset the counter to `value` (or zero if negative)
+                REQ fn reset_req(cb: acme_counter_cb_t, value: udi_sbit16_t) -> reset_ack() as Future_reset_req;
+            },
+            /// Counter client operations
+            2 => acme_counter_client_ops_t : Client {
+                /// Acknowledgement of [add_req]
+                ACK fn add_ack(cb: acme_counter_cb_t, total: udi_ubit32_t, wrapped: udi_boolean_t) as Future_add_ack;
+                /// Acknowledgement of [reset_req]
+                ACK fn reset_ack(cb: acme_counter_cb_t) as Future_reset_ack;
+            },
+        }
+    }
+}
+use counter::acme_counter_cb_t;
+
+#[derive(Debug,PartialEq)]
+pub enum Event {
+    Add { label: u8, total: u32, wrapped: bool },
+    Reset { label: u8 },
+}
+
+mod provider {
+    use ::std::cell::Cell;
+    use ::udi::ffi::*;
+    use super::acme_counter_cb_t;
+
+    #[derive(Default)]
+    pub struct Driver {
+        total: Cell<u32>,
+    }
+    crate::common::unmanaged_driver!(Driver);
+    impl super::counter::Provider for ::udi::init::RData<Driver> {
+        type Future_add_req<'s> = ::core::future::Ready<(udi_ubit32_t, udi_boolean_t)>;
+        fn add_req<'s>(&'s self, _cb: ::udi::CbRef<'s, acme_counter_cb_t>, amount: udi_ubit32_t, scale: udi_ubit8_t) -> Self::Future_add_req<'s> {
+            let (total, wrapped) = self.total.get().overflowing_add(amount.wrapping_mul(scale as u32));
+            self.total.set(total);
+            ::core::future::ready((total, udi_boolean_t(wrapped as u8)))
+        }
+        type Future_reset_req<'s> = ::core::future::Ready<()>;
+        fn reset_req<'s>(&'s self, _cb: ::udi::CbRef<'s, acme_counter_cb_t>, value: udi_sbit16_t) -> Self::Future_reset_req<'s> {
+            self.total.set(value.max(0) as u32);
+            ::core::future::ready(())
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 acme_counter
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Counter: Meta=udiprops::meta::acme_counter, crate::counter::acme_counter_provider_ops_t,
+        },
+        cbs: {
+            Counter: Meta=udiprops::meta::acme_counter, crate::counter::acme_counter_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+    pub const OPS_COUNTER: udi_index_t = OpsList::Counter;
+}
+
+mod client {
+    use ::udi::ffi::*;
+    use super::{acme_counter_cb_t, Event};
+
+    #[derive(Default)]
+    pub struct Driver {
+        events: crate::common::Recorder<Event>,
+        pub(super) released: ::std::cell::Cell<usize>,
+    }
+    impl crate::common::Recording for Driver {
+        type Event = Event;
+        fn events(&self) -> &crate::common::Recorder<Event> {
+            &self.events
+        }
+    }
+    crate::common::unmanaged_driver!(Driver);
+    impl super::counter::Client for ::udi::init::RData<Driver> {
+        type Future_add_ack<'s> = ::core::future::Ready<()>;
+        fn add_ack<'s>(&'s self, cb: ::udi::CbRef<'s, acme_counter_cb_t>, total: udi_ubit32_t, wrapped: udi_boolean_t) -> Self::Future_add_ack<'s> {
+            self.events.push(Event::Add { label: cb.label, total, wrapped: wrapped.0 != 0 });
+            ::core::future::ready(())
+        }
+        type Future_reset_ack<'s> = ::core::future::Ready<()>;
+        fn reset_ack<'s>(&'s self, cb: ::udi::CbRef<'s, acme_counter_cb_t>) -> Self::Future_reset_ack<'s> {
+            self.events.push(Event::Reset { label: cb.label });
+            ::core::future::ready(())
+        }
+        fn cb_ret<C: ::udi::metalang_trait::MetalangCb + ::core::any::Any + Unpin>(&self, cb: ::udi::cb::CbHandle<C>) {
+            self.released.set(self.released.get() + 1);
+            drop(cb);
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 acme_counter
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Counter: Meta=udiprops::meta::acme_counter, crate::counter::acme_counter_client_ops_t,
+        },
+        cbs: {
+            Counter: Meta=udiprops::meta::acme_counter, crate::counter::acme_counter_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+    pub const OPS_COUNTER: udi_index_t = OpsList::Counter;
+    pub const CB_COUNTER: udi_index_t = <CbList::Counter as ::udi::cb::CbDefinition>::INDEX;
+}
+
+/// A client and counter provider, connected by a channel (the client's end of which is `channels`)
+type Harness = common::Pair<client::Driver, provider::Driver, udi_channel_t>;
+fn harness() -> Harness {
+    ::udi_environment::register_metalanguage(&counter::METALANG_SPEC);
+    Harness::new(client::module(), provider::module(), |client, provider| common::connect(client, client::OPS_COUNTER, provider, provider::OPS_COUNTER).0)
+}
+impl Harness {
+    /// Allocate a counter CB, with `label` to identify its acknowledgement
+    fn counter_cb(&self, label: u8) -> ::udi::cb::CbHandle<acme_counter_cb_t> {
+        let mut cb = self.alloc::<acme_counter_cb_t>(client::CB_COUNTER, self.channels);
+        // SAFE: Only a plain data field is changed
+        unsafe { cb.get_mut().label = label; }
+        cb
+    }
+}
+
+#[test]
+fn mei_description() {
+    use ::udi::ffi::{layout::*, mei::*, udi_index_t};
+    let template = |ops: u8, vec: u8| ::udi::mei::get_op_template(&counter::MEI_INIT, udi_index_t(ops), udi_index_t(vec));
+    let layout = |l: *const u8| unsafe { ::core::slice::from_raw_parts(l, 1 + (0..).take_while(|&i| *l.add(i) != UDI_DL_END).count()) };
+    let name = |t: &udi_mei_op_template_t| unsafe { ::core::ffi::CStr::from_ptr(t.op_name) }.to_str().unwrap();
+
+    let add_req = template(1, 1).unwrap();
+    assert_eq!(name(add_req), "add_req");
+    assert_eq!(add_req.op_category, UDI_MEI_OPCAT_REQ);
+    assert_eq!(add_req.meta_cb_num, udi_index_t(1));
+    // BUF has zero parameters, so can't be walked by `layout`
+    assert_eq!(unsafe { ::core::slice::from_raw_parts(add_req.visible_layout, 6) }, [UDI_DL_UBIT8_T, UDI_DL_BUF, 0, 0, 1, UDI_DL_END]);
+    assert_eq!(layout(add_req.marshal_layout), [UDI_DL_UBIT32_T, UDI_DL_UBIT8_T, UDI_DL_END]);
+    assert_eq!(::udi::mei::marshal_size(layout(add_req.marshal_layout)), 8);
+    assert!(add_req.direct_stub.is_some() && add_req.backend_stub.is_some());
+
+    let reset_req = template(1, 2).unwrap();
+    assert_eq!(name(reset_req), "reset_req");
+    assert_eq!(layout(reset_req.marshal_layout), [UDI_DL_SBIT16_T, UDI_DL_END]);
+    let add_ack = template(2, 1).unwrap();
+    assert_eq!((name(add_ack), add_ack.op_category), ("add_ack", UDI_MEI_OPCAT_ACK));
+    assert_eq!(layout(add_ack.marshal_layout), [UDI_DL_UBIT32_T, UDI_DL_BOOLEAN_T, UDI_DL_END]);
+    assert_eq!(layout(template(2, 2).unwrap().marshal_layout), [UDI_DL_END]);
+
+    // Out of range
+    assert!(template(1, 0).is_none());
+    assert!(template(1, 3).is_none());
+    assert!(template(3, 1).is_none());
+}
+
+#[test]
+fn calls() {
+    let h = harness();
+    counter::add_req(h.counter_cb(1), 5, 2);
+    counter::add_req(h.counter_cb(2), 3, 1);
+    assert_eq!(h.run(), [
+        Event::Add { label: 1, total: 10, wrapped: false },
+        Event::Add { label: 2, total: 13, wrapped: false },
+        ]);
+    assert_eq!(h.driver().released.get(), 2);
+
+    // Wrapping, and resetting
+    counter::add_req(h.counter_cb(3), u32::MAX, 1);
+    counter::reset_req(h.counter_cb(4), -5);
+    counter::add_req(h.counter_cb(5), 0x100, 0xFF);
+    counter::reset_req(h.counter_cb(6), 1000);
+    counter::add_req(h.counter_cb(7), 0, 0);
+    assert_eq!(h.run(), [
+        Event::Add { label: 3, total: 12, wrapped: true },
+        Event::Reset { label: 4 },
+        Event::Add { label: 5, total: 0xFF00, wrapped: false },
+        Event::Reset { label: 6 },
+        Event::Add { label: 7, total: 1000, wrapped: false },
+        ]);
+    assert_eq!(h.driver().released.get(), 7);
+}
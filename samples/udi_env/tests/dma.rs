@@ -0,0 +1,375 @@
+//! DMA allocation and release, driven directly through the environment's C API
+use ::udi::ffi::physio as ffi;
+use ::udi::ffi::udi_cb_t;
+use ::std::cell::RefCell;
+use ::udi_environment::emulated_devices::PioDevice;
+
+mod common;
+
+/// A device with no registers, only a DMA address space
+#[derive(Default)]
+struct DmaOnly(::udi_environment::emulated_devices::DmaPool);
+impl PioDevice for DmaOnly {
+    fn poll(&self, _actions: &mut ::udi_environment::emulated_devices::Actions) {}
+    fn pio_read(&self, _regset_idx: u32, _reg: u32, _dst: &mut [u8]) {}
+    fn pio_write(&self, _regset_idx: u32, _reg: u32, _src: &[u8]) {}
+    fn dma(&self) -> &::udi_environment::emulated_devices::DmaPool { &self.0 }
+}
+
+thread_local! {
+    static RESULTS: RefCell<Vec<*mut ::udi::ffi::c_void>> = RefCell::new(Vec::new());
+}
+fn push_result(v: *mut ::udi::ffi::c_void) {
+    RESULTS.with(|r| r.borrow_mut().push(v));
+}
+
+struct Harness {
+    instance: ::std::sync::Arc<::udi_environment::DriverInstance>,
+    gcb: common::Gcb,
+}
+impl Harness {
+    fn new() -> Harness {
+        Harness::with_pool(Default::default())
+    }
+    fn with_pool(pool: ::udi_environment::emulated_devices::DmaPool) -> Harness {
+        Harness::with_device(Box::new(DmaOnly(pool)))
+    }
+    fn with_device(device: Box<dyn PioDevice>) -> Harness {
+        let instance = common::instance(common::bare::module());
+        instance.device.set(device).ok().unwrap();
+        Harness {
+            gcb: common::Gcb::new(&instance),
+            instance,
+        }
+    }
+    fn gcb(&mut self) -> *mut udi_cb_t {
+        self.gcb.as_ptr()
+    }
+    /// Run queued callbacks, and return the values they reported
+    fn run(&self) -> Vec<*mut ::udi::ffi::c_void> {
+        common::run(&[&self.instance]);
+        RESULTS.with(|r| ::core::mem::take(&mut *r.borrow_mut()))
+    }
+    /// Run an async task (using the library's wrappers) to completion
+    fn block_on<T, R>(&mut self, task: impl FnOnce(::udi::CbRef<'static, udi_cb_t>) -> T) -> R
+    where
+        T: 'static + ::core::future::Future<Output=R>,
+        R: 'static,
+    {
+        self.gcb.block_on(task)
+    }
+    fn device(&self) -> &dyn PioDevice {
+        &**self.instance.device.get().unwrap()
+    }
+    fn dma(&self) -> &::udi_environment::emulated_devices::DmaPool {
+        self.device().dma()
+    }
+    fn dma_idle(&self) -> bool {
+        self.dma().is_empty()
+    }
+
+    fn constraints(&mut self) -> ffi::udi_dma_constraints_t {
+        unsafe extern "C" fn cb(_: *mut udi_cb_t, c: ffi::udi_dma_constraints_t, status: ::udi::ffi::udi_status_t) {
+            assert_eq!(status, ::udi::ffi::UDI_OK as _);
+            push_result(c as _);
+        }
+        let attrs = [ffi::udi_dma_constraints_attr_spec_t { attr_type: ffi::UDI_DMA_SCGTH_FORMAT, attr_value: ffi::UDI_SCGTH_32 as _ }];
+        unsafe { ffi::udi_dma_constraints_attr_set(cb, self.gcb(), ffi::UDI_NULL_DMA_CONSTRAINTS, attrs.as_ptr(), 1, 0); }
+        self.run()[0] as _
+    }
+    /// Allocate `size` bytes, returning the handle and memory pointer
+    fn alloc(&mut self, constraints: ffi::udi_dma_constraints_t, size: usize) -> (ffi::udi_dma_handle_t, *mut u8) {
+        self.alloc_flags(constraints, size, ffi::UDI_DMA_IN)
+    }
+    fn alloc_flags(&mut self, constraints: ffi::udi_dma_constraints_t, size: usize, flags: u8) -> (ffi::udi_dma_handle_t, *mut u8) {
+        unsafe extern "C" fn cb(
+            _: *mut udi_cb_t,
+            handle: ffi::udi_dma_handle_t,
+            mem_ptr: *mut ::udi::ffi::c_void,
+            _gap: ::udi::ffi::udi_size_t,
+            _single: ::udi::ffi::udi_boolean_t,
+            _scgth: *mut ffi::udi_scgth_t,
+            _must_swap: ::udi::ffi::udi_boolean_t,
+        ) {
+            push_result(handle as _);
+            push_result(mem_ptr);
+        }
+        unsafe { ffi::udi_dma_mem_alloc(cb, self.gcb(), constraints, flags, 1, size, 0); }
+        let r = self.run();
+        (r[0] as _, r[1] as _)
+    }
+}
+
+#[test]
+fn alloc_free() {
+    let mut h = Harness::new();
+    let constraints = h.constraints();
+    let (handle, _) = h.alloc(constraints, 64);
+    assert!(!h.dma_idle());
+    unsafe { ffi::udi_dma_free(handle); }
+    assert!(h.dma_idle());
+    // Freeing the null handle is a no-op
+    unsafe { ffi::udi_dma_free(::core::ptr::null_mut()); }
+    unsafe { ffi::udi_dma_constraints_free(constraints); }
+}
+
+#[test]
+fn alloc_fail() {
+    let mut h = Harness::with_pool(::udi_environment::emulated_devices::DmaPool::with_limit(256));
+    let constraints = h.constraints();
+    let flags = ffi::UDI_DMA_IN | ::udi::ffi::mem::UDI_MEM_NOWAIT;
+    // Larger than the address space
+    assert_eq!(h.alloc_flags(constraints, 512, flags), (::core::ptr::null_mut(), ::core::ptr::null_mut()));
+    assert!(h.dma_idle());
+    // The data fits, but the scatter-gather list doesn't
+    assert_eq!(h.alloc_flags(constraints, 256, flags), (::core::ptr::null_mut(), ::core::ptr::null_mut()));
+    assert!(h.dma_idle());
+    // Smaller allocations still work
+    let (handle, _) = h.alloc_flags(constraints, 64, flags);
+    assert!(!handle.is_null());
+    unsafe { ffi::udi_dma_free(handle); }
+    assert!(h.dma_idle());
+    unsafe { ffi::udi_dma_constraints_free(constraints); }
+}
+
+#[test]
+fn mem_to_buf() {
+    unsafe extern "C" fn cb(_: *mut udi_cb_t, new_dst_buf: *mut ::udi::ffi::udi_buf_t) {
+        push_result(new_dst_buf as _);
+    }
+    let mut h = Harness::new();
+    let constraints = h.constraints();
+    let (handle, mem_ptr) = h.alloc(constraints, 16);
+    unsafe { ::core::slice::from_raw_parts_mut(mem_ptr, 16).copy_from_slice(b"0123456789ABCDEF"); }
+
+    // Copy out the middle of the allocation into a new buffer, which also releases the handle
+    unsafe { ffi::udi_dma_mem_to_buf(cb, h.gcb(), handle, 4, 8, ::core::ptr::null_mut()); }
+    let buf = h.run()[0] as *mut ::udi::ffi::udi_buf_t;
+    assert!(h.dma_idle());
+    let mut data = [0; 8];
+    assert_eq!(unsafe { ::udi_environment::udi_impl::buf::read(buf, 0, &mut data) }, Some(8));
+    assert_eq!(&data, b"456789AB");
+    assert_eq!(unsafe { (*buf).buf_size }, 8);
+
+    // Replacing the contents of an existing buffer
+    let (handle, mem_ptr) = h.alloc(constraints, 4);
+    unsafe { ::core::slice::from_raw_parts_mut(mem_ptr, 4).copy_from_slice(b"wxyz"); }
+    unsafe { ffi::udi_dma_mem_to_buf(cb, h.gcb(), handle, 0, 2, buf); }
+    let buf = h.run()[0] as *mut ::udi::ffi::udi_buf_t;
+    assert!(h.dma_idle());
+    assert_eq!(unsafe { (*buf).buf_size }, 2);
+    assert_eq!(unsafe { ::udi_environment::udi_impl::buf::read(buf, 0, &mut data[..2]) }, Some(2));
+    assert_eq!(&data[..2], b"wx");
+
+    unsafe { ::udi::ffi::buf::udi_buf_free(buf); }
+    unsafe { ffi::udi_dma_constraints_free(constraints); }
+}
+
+#[test]
+fn limits() {
+    let limits = ::udi::physio::dma::limits();
+    assert!(limits.max_safe_contig_alloc >= ffi::UDI_DMA_MIN_ALLOC_LIMIT);
+    assert!(limits.max_legal_contig_alloc >= limits.max_safe_contig_alloc);
+    let plan = ::udi::physio::dma::AllocPlan::new(&limits, &::udi::physio::dma::DmaConstraints::null(), limits.max_safe_contig_alloc, 2).unwrap();
+    assert_eq!(plan.num_allocs(), 1);
+}
+
+#[test]
+fn plan() {
+    use ::udi::physio::dma::{AllocPlan, Attribute, ConstraintsBuilder, DmaConstraints, Limits, PlanError};
+    const LIMITS: Limits = Limits { max_legal_contig_alloc: 0x10000, max_safe_contig_alloc: 0x2000, cache_line_size: 64 };
+    let mut h = Harness::new();
+    let mut set = |b: ConstraintsBuilder| h.block_on(|cb| async move {
+        let attrs = b.build().unwrap();
+        let mut constraints = DmaConstraints::null();
+        constraints.set(cb, &attrs).await.unwrap();
+        constraints
+    });
+
+    // No constraints: everything fits in one call
+    let none = DmaConstraints::null();
+    let p = AllocPlan::new(&LIMITS, &none, 16, 256).unwrap();
+    assert_eq!(p.allocs().collect::<Vec<_>>(), [256]);
+    // `nelements` is limited to 16 bits
+    let p = AllocPlan::new(&LIMITS, &none, 1, 100_000).unwrap();
+    assert_eq!(p.allocs().collect::<Vec<_>>(), [0xFFFF, (100_000 - 0xFFFF) as u16]);
+    assert_eq!(AllocPlan::new(&LIMITS, &none, 0, 1), Err(PlanError::Empty));
+    assert_eq!(AllocPlan::new(&LIMITS, &none, 0x2001, 1), Err(PlanError::ElementTooLarge { element_size: 0x2001, max: 0x2000 }));
+
+    // Up to four 0x2000 blocks per allocation, with a partial final allocation
+    let mut four = set(ConstraintsBuilder::new().max_scgth_elements(4));
+    assert_eq!(four.get(Attribute::ScgthMaxElements), Some(4));
+    let p = AllocPlan::new(&LIMITS, &four, 0x800, 40).unwrap();
+    assert_eq!(p.elements_per_alloc, 16);
+    assert_eq!(p.num_allocs(), 3);
+    assert_eq!(p.allocs().collect::<Vec<_>>(), [16, 16, 8]);
+    // Resetting the attribute returns to the default (unlimited)
+    four.reset_attr(Attribute::ScgthMaxElements);
+    assert_eq!(four.get(Attribute::ScgthMaxElements), None);
+    assert_eq!(AllocPlan::new(&LIMITS, &four, 0x800, 40).unwrap().num_allocs(), 1);
+
+    // Element length field limits the contiguous size further
+    let bits = set(ConstraintsBuilder::new().element_length_bits(10));
+    assert_eq!(AllocPlan::new(&LIMITS, &bits, 0x400, 1), Err(PlanError::ElementTooLarge { element_size: 0x400, max: 0x3FF }));
+
+    // Attributes of a raw handle (e.g. from a parent) aren't known until set or reset
+    let mut raw = unsafe { DmaConstraints::from_raw(h.constraints()) };
+    assert!(!raw.is_known(Attribute::ScgthMaxElements));
+    assert_eq!(AllocPlan::new(&LIMITS, &raw, 16, 256), Err(PlanError::UnknownAttribute(Attribute::ElementLengthBits)));
+    raw.reset_attr(Attribute::ElementLengthBits);
+    assert_eq!(AllocPlan::new(&LIMITS, &raw, 16, 256), Err(PlanError::UnknownAttribute(Attribute::ScgthMaxElements)));
+    raw.reset_attr(Attribute::ScgthMaxElements);
+    assert_eq!(AllocPlan::new(&LIMITS, &raw, 16, 256).unwrap().num_allocs(), 1);
+}
+
+#[test]
+fn ring() {
+    use ::udi::physio::dma::{DmaConstraints, DmaRing, Endianness};
+    use ::udi::ffi::physio::udi_dma_constraints_attr_spec_t as Spec;
+    use ::udi_environment::emulated_devices::ring_consumer::{regs, RingConsumer, DONE};
+    let write_reg = |h: &Harness, reg, val: u32| h.device().pio_write(0, reg, &val.to_le_bytes());
+    let poll = |h: &Harness| h.device().poll(&mut Default::default());
+
+    let mut h = Harness::with_device(RingConsumer::new_boxed());
+    let (constraints, mut ring) = h.block_on(|cb| async move {
+        let mut constraints = DmaConstraints::null();
+        constraints.set(cb, &[Spec { attr_type: ffi::UDI_DMA_SCGTH_MAX_ELEMENTS, attr_value: 1 }]).await.unwrap();
+        let ring = DmaRing::<[u32; 2]>::alloc(cb, &constraints, Endianness::Little, 4).await;
+        (constraints, ring)
+    });
+    assert_eq!(ring.capacity(), 4);
+    assert!(ring.is_empty());
+    // Slots are contiguous
+    assert_eq!(ring.bus_addr(3), ring.bus_addr(0) + 3*8);
+    write_reg(&h, regs::RING_BASE, ring.bus_addr(0) as u32);
+    write_reg(&h, regs::RING_SIZE, ring.capacity() as u32);
+
+    // Queue three descriptors, and hand them to the device
+    for len in [100, 200, 300] {
+        ring.push([len, 0]).unwrap();
+    }
+    assert_eq!((ring.tail(), ring.head(), ring.len()), (0, 3, 3));
+    let ring = h.block_on(|cb| async move { ring.sync_for_device(cb, 0..3).await; ring });
+    write_reg(&h, regs::DOORBELL, 3);
+    poll(&h);
+
+    // Retire them, and check that the device's writes are visible
+    let mut ring = h.block_on(|cb| async move { ring.sync_for_cpu(cb, 0..3).await; ring });
+    assert_eq!(ring.pop(), Some((0, [100, DONE|100])));
+    assert_eq!(ring.pop(), Some((1, [200, DONE|200])));
+    assert_eq!(ring.pop(), Some((2, [300, DONE|300])));
+    assert_eq!(ring.pop(), None);
+
+    // Wrap around the end of the ring
+    for len in [1, 2, 3, 4] {
+        ring.push([len, 0]).unwrap();
+    }
+    assert!(ring.is_full());
+    assert_eq!(ring.push([5, 0]), Err([5, 0]));
+    assert_eq!((ring.tail(), ring.head()), (3, 3));
+    let ring = h.block_on(|cb| async move {
+        ring.sync_for_device(cb, 3..4).await;
+        ring.sync_for_device(cb, 0..3).await;
+        ring
+    });
+    write_reg(&h, regs::DOORBELL, 7);
+    poll(&h);
+    let mut consumer = [0; 4];
+    h.device().pio_read(0, regs::CONSUMER, &mut consumer);
+    assert_eq!(u32::from_le_bytes(consumer), 7);
+    let mut ring = h.block_on(|cb| async move { ring.sync_for_cpu(cb, 0..4).await; ring });
+    let lens: Vec<_> = ::core::iter::from_fn(|| ring.pop()).map(|(slot, [len, status])| (slot, len, status == DONE|len)).collect();
+    assert_eq!(lens, [(3, 1, true), (0, 2, true), (1, 3, true), (2, 4, true)]);
+
+    drop(ring);
+    drop(constraints);
+    assert!(h.dma_idle());
+}
+
+#[test]
+fn scgth_formats() {
+    let mut h = Harness::new();
+    formats(&mut h);
+    assert!(h.dma_idle());
+}
+/// Allocations are all released on return, so the caller can check for leaks
+fn formats(h: &mut Harness) {
+    use ::udi::physio::dma::{DmaAlloc, DmaConstraints, Direction, Endianness, ScgthRaw, ScatterListBuilder, ScatterListError};
+    use ::udi::ffi::physio::udi_dma_constraints_attr_spec_t as Spec;
+    const SIZE: usize = 1024;
+    fn alloc(h: &mut Harness, attrs: &[(u8, u32)]) -> DmaAlloc {
+        let attrs: Vec<_> = attrs.iter().map(|&(attr_type, attr_value)| Spec { attr_type, attr_value }).collect();
+        h.block_on(|cb| async move {
+            let mut constraints = DmaConstraints::null();
+            constraints.set(cb, &attrs).await.unwrap();
+            DmaAlloc::alloc_single(cb, &constraints, Direction::In, Endianness::Little, false, SIZE).await
+        })
+    }
+    /// Check that the elements cover the allocation, and return their lengths
+    fn lengths(alloc: &DmaAlloc) -> Vec<u32> {
+        let els: Vec<_> = alloc.scgth().iter().unwrap().collect();
+        let base = els[0].0;
+        let mut ofs = 0;
+        for &(addr, len) in &els {
+            assert_eq!(addr, base + ofs, "{:x?}", els);
+            ofs += len as u64;
+        }
+        assert_eq!(ofs, SIZE as u64);
+        els.into_iter().map(|e| e.1).collect()
+    }
+
+    let (fmt, len_bits, per_seg, endian) = (ffi::UDI_DMA_SCGTH_FORMAT, ffi::UDI_DMA_ELEMENT_LENGTH_BITS, ffi::UDI_DMA_SCGTH_MAX_EL_PER_SEG, ffi::UDI_DMA_SCGTH_ENDIANNESS);
+
+    // Default: a single 32-bit element
+    let a = alloc(h, &[(len_bits, 31)]);
+    assert!(!a.scgth().is_64() && !a.scgth().must_swap() && a.scgth().is_driver_mapped());
+    assert_eq!(lengths(&a), [SIZE as u32]);
+    assert!(a.scgth().single_entry_32().is_some());
+
+    // 64-bit only
+    let a = alloc(h, &[(fmt, ffi::UDI_SCGTH_64 as u32)]);
+    assert!(a.scgth().is_64());
+    assert_eq!(lengths(&a), [SIZE as u32]);
+
+    // Split by the maximum element length
+    let a = alloc(h, &[(len_bits, 8)]);
+    assert_eq!(lengths(&a), [255, 255, 255, 255, 4]);
+    assert!(matches!(a.scgth().raw_entries(), ScgthRaw::Bits32(e) if e.len() == 5));
+
+    // Then chained into segments of two elements and an extension element
+    for format in [ffi::UDI_SCGTH_32, ffi::UDI_SCGTH_64] {
+        let a = alloc(h, &[(fmt, format as u32), (len_bits, 8), (per_seg, 3)]);
+        assert_eq!(lengths(&a), [255, 255, 255, 255, 4]);
+        let num_raw = match a.scgth().raw_entries() {
+            ScgthRaw::Bits32(e) => e.len(),
+            ScgthRaw::Bits64(e) => e.len(),
+            };
+        assert_eq!(num_raw, 7);
+    }
+
+    // Big-endian list, so must be swapped on this (little-endian) host
+    let a = alloc(h, &[(len_bits, 8), (per_seg, 3), (endian, ffi::UDI_DMA_BIG_ENDIAN as u32)]);
+    assert!(a.scgth().must_swap());
+    assert_eq!(lengths(&a), [255, 255, 255, 255, 4]);
+    assert!(matches!(a.scgth().raw_entries(), ScgthRaw::Bits32(e) if e[0].block_length == 255u32.swap_bytes()));
+
+    // DMA-mapped only: the driver only sees the first segment
+    let a = alloc(h, &[(fmt, (ffi::UDI_SCGTH_32|ffi::UDI_SCGTH_DMA_MAPPED) as u32)]);
+    assert!(!a.scgth().is_driver_mapped());
+    assert_eq!(lengths(&a), [SIZE as u32]);
+    // - Which can't be iterated if it has multiple elements
+    let a = alloc(h, &[(fmt, (ffi::UDI_SCGTH_32|ffi::UDI_SCGTH_DMA_MAPPED) as u32), (len_bits, 8)]);
+    assert_eq!(a.scgth().iter().err(), Some(::udi::physio::dma::NotDriverMapped));
+
+    // Writing a device list from split elements: contiguous elements are merged unless disabled
+    let a = alloc(h, &[(len_bits, 8), (per_seg, 3)]);
+    let mut dev_list = [(0u64, 0u32, false); 16];
+    let base = a.scgth().iter().unwrap().next().unwrap().0;
+    let n = ScatterListBuilder::new(16).build(a.scgth().iter().unwrap(), &mut dev_list, |a, l, last| (a, l, last)).unwrap();
+    assert_eq!(&dev_list[..n], [(base, SIZE as u32, true)]);
+    let split = ScatterListBuilder::new(16).coalesce(false).max_entry_len(128);
+    let n = split.build(a.scgth().iter().unwrap(), &mut dev_list, |a, l, last| (a, l, last)).unwrap();
+    assert_eq!(dev_list[..n].iter().map(|e| e.1).collect::<Vec<_>>(), [128, 127, 128, 127, 128, 127, 128, 127, 4]);
+    assert!(dev_list[n-1].2);
+    assert_eq!(split.build(a.scgth().iter().unwrap(), &mut dev_list[..8], |a, l, last| (a, l, last)), Err(ScatterListError::TooManyEntries { max: 8 }));
+}
@@ -0,0 +1,307 @@
+//! GFX against the in-memory framebuffer
+//!
+//! A minimal client records every acknowledgement it gets, while the test drives requests through the channel.
+use ::udi::ffi::udi_channel_t;
+use ::udi::ffi::meta_gfx as ffi;
+use ::udi::meta_gfx::{BufferArea, BufferFlags, BufferInfo, ConnectorType, Element, EnableState, Operator, OperatorEntry, Property, Range, INPUT_NONE};
+use ::udi_environment::gfx_framebuffer as fb;
+use self::common::error;
+
+mod common;
+
+#[derive(Debug,PartialEq)]
+pub enum Event {
+    Set(Element, Property, ::udi::Result<()>),
+    Get(Element, Property, u32),
+    Range(Element, Property, Vec<Range>),
+    Operator(u32, u32, OperatorEntry),
+    ConnectorCommand,
+    BufferInfo(u32, BufferInfo),
+    Read(BufferArea, ::udi::Result<()>, Vec<u8>),
+    Write(BufferArea, ::udi::Result<()>),
+}
+
+mod client {
+    use super::Event;
+    use ::udi::ffi::meta_gfx as ffi;
+    use ::udi::meta_gfx::{BindInfo, BufferArea, BufferInfo, Element, OperatorEntry, Property};
+
+    #[derive(Default)]
+    pub struct Driver {
+        events: crate::common::Recorder<Event>,
+    }
+    impl crate::common::Recording for Driver {
+        type Event = Event;
+        fn events(&self) -> &crate::common::Recorder<Event> {
+            &self.events
+        }
+    }
+    crate::common::unmanaged_driver!(Driver);
+
+    impl ::udi::meta_gfx::Client for ::udi::init::RData<Driver> {
+        type Future_bind_ack<'s> = ::core::future::Ready<()>;
+        fn bind_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_bind_cb_t>, _res: ::udi::Result<BindInfo>) -> Self::Future_bind_ack<'s> {
+            unreachable!()
+        }
+        type Future_unbind_ack<'s> = ::core::future::Ready<()>;
+        fn unbind_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_bind_cb_t>) -> Self::Future_unbind_ack<'s> {
+            unreachable!()
+        }
+        type Future_set_ack<'s> = ::core::future::Ready<()>;
+        fn set_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_state_cb_t>, element: Element, prop: Property, res: ::udi::Result<()>) -> Self::Future_set_ack<'s> {
+            self.events.push(Event::Set(element, prop, res));
+            ::core::future::ready(())
+        }
+        type Future_get_ack<'s> = ::core::future::Ready<()>;
+        fn get_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_state_cb_t>, element: Element, prop: Property, value: u32) -> Self::Future_get_ack<'s> {
+            self.events.push(Event::Get(element, prop, value));
+            ::core::future::ready(())
+        }
+        type Future_range_ack<'s> = ::core::future::Ready<()>;
+        fn range_ack<'s>(&'s self, cb: ::udi::CbRef<'s, ffi::udi_gfx_range_cb_t>, element: Element, prop: Property) -> Self::Future_range_ack<'s> {
+            self.events.push(Event::Range(element, prop, cb.ranges().collect()));
+            ::core::future::ready(())
+        }
+        type Future_get_engine_operator_ack<'s> = ::core::future::Ready<()>;
+        fn get_engine_operator_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_range_cb_t>, engine: u32, index: u32, entry: OperatorEntry) -> Self::Future_get_engine_operator_ack<'s> {
+            self.events.push(Event::Operator(engine, index, entry));
+            ::core::future::ready(())
+        }
+        type Future_connector_command_ack<'s> = ::core::future::Ready<()>;
+        fn connector_command_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_command_cb_t>) -> Self::Future_connector_command_ack<'s> {
+            self.events.push(Event::ConnectorCommand);
+            ::core::future::ready(())
+        }
+        type Future_engine_command_ack<'s> = ::core::future::Ready<()>;
+        fn engine_command_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_command_cb_t>) -> Self::Future_engine_command_ack<'s> {
+            unreachable!()
+        }
+        type Future_buffer_info_ack<'s> = ::core::future::Ready<()>;
+        fn buffer_info_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_buffer_info_cb_t>, index: u32, info: BufferInfo) -> Self::Future_buffer_info_ack<'s> {
+            self.events.push(Event::BufferInfo(index, info));
+            ::core::future::ready(())
+        }
+        type Future_buffer_read_ack<'s> = ::core::future::Ready<()>;
+        fn buffer_read_ack<'s>(&'s self, cb: ::udi::CbRef<'s, ffi::udi_gfx_buffer_cb_t>, area: BufferArea, res: ::udi::Result<()>) -> Self::Future_buffer_read_ack<'s> {
+            let mut data = vec![0; cb.data_buf().len()];
+            if !data.is_empty() {
+                cb.data_buf().read(0, &mut data);
+            }
+            self.events.push(Event::Read(area, res, data));
+            ::core::future::ready(())
+        }
+        type Future_buffer_write_ack<'s> = ::core::future::Ready<()>;
+        fn buffer_write_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::udi_gfx_buffer_cb_t>, area: BufferArea, res: ::udi::Result<()>) -> Self::Future_buffer_write_ack<'s> {
+            self.events.push(Event::Write(area, res));
+            ::core::future::ready(())
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 udi_gfx
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Gfx: Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_client_ops_t,
+        },
+        cbs: {
+            Bind      : Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_bind_cb_t,
+            State     : Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_state_cb_t,
+            Range     : Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_range_cb_t,
+            Command   : Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_command_cb_t,
+            BufferInfo: Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_buffer_info_cb_t,
+            Buffer    : Meta=udiprops::meta::udi_gfx, ::udi::ffi::meta_gfx::udi_gfx_buffer_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+    pub const OPS_GFX: ::udi::ffi::udi_index_t = OpsList::Gfx;
+    pub const CB_STATE: ::udi::ffi::udi_index_t = <CbList::State as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_RANGE: ::udi::ffi::udi_index_t = <CbList::Range as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_COMMAND: ::udi::ffi::udi_index_t = <CbList::Command as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_BUFFER_INFO: ::udi::ffi::udi_index_t = <CbList::BufferInfo as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_BUFFER: ::udi::ffi::udi_index_t = <CbList::Buffer as ::udi::cb::CbDefinition>::INDEX;
+}
+
+const CONNECTOR: Element = Element::Connector(0);
+const ENGINE: Element = Element::Engine(0);
+
+/// A client and framebuffer instance, connected by a channel (the client's end of which is `channels`)
+type Harness = common::Pair<client::Driver, fb::Driver, udi_channel_t>;
+fn harness() -> Harness {
+    let rv = Harness::new(client::module(), fb::module(), |client, fb| common::connect(client, client::OPS_GFX, fb, fb::OPS_GFX).0);
+    // Initialise the region data (see `common::rdata`)
+    rv.get(ENGINE, Property::Width);
+    assert_eq!(rv.run(), [Event::Get(ENGINE, Property::Width, fb::DEFAULT_SIZE.0)]);
+    rv
+}
+impl Harness {
+    fn set(&self, element: Element, prop: Property, value: u32) {
+        ::udi::meta_gfx::set_req(self.alloc(client::CB_STATE, self.channels), element, prop, value);
+    }
+    fn get(&self, element: Element, prop: Property) {
+        ::udi::meta_gfx::get_req(self.alloc(client::CB_STATE, self.channels), element, prop);
+    }
+    fn range(&self, element: Element, prop: Property) -> Vec<Range> {
+        ::udi::meta_gfx::range_req(self.alloc(client::CB_RANGE, self.channels), element, prop);
+        let [Event::Range(e, p, ranges)] = &mut self.run()[..] else { panic!() };
+        assert_eq!((*e, *p), (element, prop));
+        ::core::mem::take(ranges)
+    }
+    fn write(&self, area: BufferArea, data: &[u8]) {
+        let mut cb = self.alloc::<ffi::udi_gfx_buffer_cb_t>(client::CB_BUFFER, self.channels);
+        // SAFE: The buffer pointer is valid (null)
+        unsafe { ::udi_environment::udi_impl::buf::write(&mut cb.get_mut().buffer, 0..0, data); }
+        ::udi::meta_gfx::buffer_write_req(cb, area);
+    }
+    fn read(&self, area: BufferArea) {
+        ::udi::meta_gfx::buffer_read_req(self.alloc(client::CB_BUFFER, self.channels), area);
+    }
+}
+
+#[test]
+fn properties() {
+    let h = harness();
+    h.get(CONNECTOR, Property::ConnectorType);
+    h.get(CONNECTOR, Property::Input);
+    h.get(ENGINE, Property::Input);
+    h.get(ENGINE, Property::ColorBits);
+    // Unknown properties and elements read as zero
+    h.get(ENGINE, Property::DotClock);
+    h.get(Element::Connector(1), Property::Width);
+    assert_eq!(h.run(), [
+        Event::Get(CONNECTOR, Property::ConnectorType, ConnectorType::MemBuffer.to_raw()),
+        Event::Get(CONNECTOR, Property::Input, 0),
+        Event::Get(ENGINE, Property::Input, INPUT_NONE),
+        Event::Get(ENGINE, Property::ColorBits, 8),
+        Event::Get(ENGINE, Property::DotClock, 0),
+        Event::Get(Element::Connector(1), Property::Width, 0),
+        ]);
+
+    // Turning on the output
+    assert!(!h.peer().is_displaying());
+    h.set(CONNECTOR, Property::Enable, EnableState::Enabled.to_raw());
+    assert_eq!(h.run(), [Event::Set(CONNECTOR, Property::Enable, Ok(()))]);
+    assert!(h.peer().is_displaying());
+    h.set(CONNECTOR, Property::Input, INPUT_NONE);
+    assert_eq!(h.run(), [Event::Set(CONNECTOR, Property::Input, Ok(()))]);
+    assert!(!h.peer().is_displaying());
+
+    // Resizing the engine, the connector follows
+    h.set(ENGINE, Property::Width, 128);
+    h.set(ENGINE, Property::Height, 96);
+    h.get(CONNECTOR, Property::Width);
+    h.get(CONNECTOR, Property::Height);
+    assert_eq!(h.run(), [
+        Event::Set(ENGINE, Property::Width, Ok(())),
+        Event::Set(ENGINE, Property::Height, Ok(())),
+        Event::Get(CONNECTOR, Property::Width, 128),
+        Event::Get(CONNECTOR, Property::Height, 96),
+        ]);
+    assert_eq!(h.peer().size(), (128, 96));
+
+    // Values out of range, hardwired properties, and unknown elements
+    h.set(ENGINE, Property::Width, 100);
+    h.set(CONNECTOR, Property::Width, 64);
+    h.set(ENGINE, Property::Enable, 3);
+    h.set(ENGINE, Property::DotClock, 0);
+    h.set(Element::Engine(1), Property::Width, 64);
+    assert_eq!(h.run(), [
+        Event::Set(ENGINE, Property::Width, Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED))),
+        Event::Set(CONNECTOR, Property::Width, Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED))),
+        Event::Set(ENGINE, Property::Enable, Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED))),
+        Event::Set(ENGINE, Property::DotClock, Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED))),
+        Event::Set(Element::Engine(1), Property::Width, Err(error(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD))),
+        ]);
+    assert_eq!(h.peer().size(), (128, 96));
+}
+
+#[test]
+fn ranges() {
+    let h = harness();
+    assert_eq!(h.range(ENGINE, Property::Width), [fb::WIDTH_RANGE]);
+    assert_eq!(h.range(ENGINE, Property::Enable), [Range::inclusive(0, 2)]);
+    assert_eq!(h.range(CONNECTOR, Property::Input), [Range::single(0), Range::single(INPUT_NONE)]);
+    assert_eq!(h.range(CONNECTOR, Property::Width), [Range::single(fb::DEFAULT_SIZE.0)]);
+    assert_eq!(h.range(ENGINE, Property::DotClock), []);
+    assert_eq!(h.range(Element::Engine(1), Property::Width), []);
+
+    // Every valid width can be set
+    let widths = h.range(ENGINE, Property::Width);
+    for w in [8, 16, 1024] {
+        assert!(Range::list_contains(&widths, w));
+        h.set(ENGINE, Property::Width, w);
+        assert_eq!(h.run(), [Event::Set(ENGINE, Property::Width, Ok(()))]);
+    }
+}
+
+#[test]
+fn operators() {
+    let h = harness();
+    for i in 0 .. fb::OPERATORS.len() as u32 + 1 {
+        ::udi::meta_gfx::get_engine_operator_req(h.alloc(client::CB_RANGE, h.channels), 0, i);
+    }
+    let ops: Vec<_> = h.run().into_iter()
+        .map(|e| match e { Event::Operator(0, _, entry) => entry, _ => panic!("{:?}", e) })
+        .collect();
+    assert_eq!(ops[..fb::OPERATORS.len()], fb::OPERATORS);
+    // Past the end of the list
+    assert_eq!(ops.last(), Some(&OperatorEntry::new(Operator::Const, 0, 0, 0)));
+
+    // Commands are accepted, but do nothing
+    ::udi::meta_gfx::connector_command_req(h.alloc(client::CB_COMMAND, h.channels));
+    assert_eq!(h.run(), [Event::ConnectorCommand]);
+}
+
+#[test]
+fn buffer() {
+    let h = harness();
+    ::udi::meta_gfx::buffer_info_req(h.alloc(client::CB_BUFFER_INFO, h.channels), fb::BUFFER_INDEX);
+    ::udi::meta_gfx::buffer_info_req(h.alloc(client::CB_BUFFER_INFO, h.channels), 1);
+    let (width, height) = fb::DEFAULT_SIZE;
+    assert_eq!(h.run(), [
+        Event::BufferInfo(0, BufferInfo { width, height, bits_per_entry: 32, flags: BufferFlags::READ | BufferFlags::WRITE }),
+        Event::BufferInfo(1, BufferInfo::default()),
+        ]);
+
+    // Write a 2x2 square, then read it back with a border
+    let square: Vec<u8> = (1 ..= 16).collect();
+    let area = BufferArea::new(0, 1, 1, 2, 2);
+    h.write(area, &square);
+    assert_eq!(h.run(), [Event::Write(area, Ok(()))]);
+    assert_eq!(h.peer().pixel(1, 1), [1,2,3,4]);
+    assert_eq!(h.peer().pixel(2, 2), [13,14,15,16]);
+    let area = BufferArea::new(0, 0, 0, 3, 3);
+    h.read(area);
+    let mut expected = vec![0; 3*3*4];
+    expected[16..24].copy_from_slice(&square[..8]);
+    expected[28..36].copy_from_slice(&square[8..]);
+    assert_eq!(h.run(), [Event::Read(area, Ok(()), expected)]);
+
+    // Bad requests
+    let outside = BufferArea::new(0, width - 1, 0, 2, 1);
+    let bad_index = BufferArea::new(1, 0, 0, 1, 1);
+    let short = BufferArea::new(0, 0, 0, 4, 1);
+    h.write(outside, &[0; 8]);
+    h.read(bad_index);
+    h.write(short, &[0; 15]);
+    assert_eq!(h.run(), [
+        Event::Write(outside, Err(error(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD))),
+        Event::Read(bad_index, Err(error(::udi::ffi::UDI_STAT_NOT_UNDERSTOOD)), vec![]),
+        Event::Write(short, Err(error(::udi::ffi::UDI_STAT_DATA_UNDERRUN))),
+        ]);
+
+    // Resetting the engine releases the framebuffer
+    h.set(ENGINE, Property::Enable, EnableState::Reset.to_raw());
+    h.read(BufferArea::new(0, 0, 0, 1, 1));
+    h.set(ENGINE, Property::Enable, EnableState::Enabled.to_raw());
+    h.read(BufferArea::new(0, 1, 1, 1, 1));
+    assert_eq!(h.run(), [
+        Event::Set(ENGINE, Property::Enable, Ok(())),
+        Event::Read(BufferArea::new(0, 0, 0, 1, 1), Err(error(::udi::ffi::UDI_STAT_INVALID_STATE)), vec![]),
+        Event::Set(ENGINE, Property::Enable, Ok(())),
+        Event::Read(BufferArea::new(0, 1, 1, 1, 1), Ok(()), vec![0; 4]),
+        ]);
+}
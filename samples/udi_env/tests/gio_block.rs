@@ -0,0 +1,178 @@
+//! GIO block transfers through the RAM disk
+//!
+//! A client bound to [udi_environment::gio_ramdisk] writes a pattern spanning more than
+//! [MAX_BLOCKS](udi_environment::gio_ramdisk::MAX_BLOCKS) blocks (so the request is split), reads it back, flushes,
+//! and then reads past the end of the disk.
+#![feature(impl_trait_in_assoc_type)]
+use ::std::cell::RefCell;
+use ::std::sync::Arc;
+use ::udi::meta_gio::GioOp;
+use ::udi_environment::DriverModule;
+use ::udi_environment::gio_ramdisk::{BLOCK_SIZE, CAPACITY, MAX_BLOCKS};
+use self::common::error;
+
+mod common;
+
+/// Byte offset of the round-trip transfers
+const TEST_OFFSET: u64 = BLOCK_SIZE as u64;
+/// Length of the round-trip transfers (more than `MAX_BLOCKS` blocks, and not a multiple of it)
+const TEST_LEN: usize = (2 * MAX_BLOCKS + 1) as usize * BLOCK_SIZE as usize;
+/// Number of blocks in the read past the end of the disk, only the first of which exists
+const TAIL_BLOCKS: u64 = 3;
+
+/// A completed transfer, as seen by the client
+#[derive(Debug,PartialEq)]
+struct Completion {
+    op: GioOp,
+    res: ::udi::Result<()>,
+    data: Vec<u8>,
+}
+thread_local! {
+    static COMPLETIONS: RefCell<Vec<Completion>> = const { RefCell::new(Vec::new()) };
+}
+
+fn test_pattern() -> Vec<u8> {
+    (0 .. TEST_LEN).map(|i| (i * 7 + i / BLOCK_SIZE as usize) as u8).collect()
+}
+
+mod client {
+    use ::udi::meta_gio::{GioOp, RwParams};
+
+    #[derive(Default)]
+    pub struct Driver {
+        channel: ::core::cell::OnceCell< ::udi::ffi::udi_channel_t >,
+    }
+    impl ::udi::init::Driver for ::udi::init::RData<Driver> {
+        const MAX_ATTRS: u8 = 0;
+        type Future_init<'s> = ::core::future::Ready<()>;
+        fn usage_ind<'s>(&'s self, _cb: ::udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+            ::core::future::ready(())
+        }
+        type Future_enumerate<'s> = ::core::future::Ready<(::udi::init::EnumerateResult,::udi::init::AttrSink<'s>)>;
+        fn enumerate_req<'s>(&'s self, _cb: ::udi::init::CbRefEnumerate<'s>, _level: ::udi::init::EnumerateLevel, attrs_out: ::udi::init::AttrSink<'s>) -> Self::Future_enumerate<'s> {
+            ::core::future::ready((::udi::init::EnumerateResult::Done, attrs_out))
+        }
+        type Future_devmgmt<'s> = ::core::future::Ready<::udi::Result<u8>>;
+        fn devmgmt_req<'s>(&'s self, _cb: ::udi::init::CbRefMgmt<'s>, _mgmt_op: ::udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+            ::core::future::ready(Ok(0))
+        }
+    }
+    impl Driver {
+        /// Start a read of `len` bytes at `offset`
+        async fn read(&self, gcb: ::udi::CbRef<'_, ::udi::ffi::udi_cb_t>, offset: u64, len: usize) {
+            let mut xfer_cb = ::udi::meta_gio::alloc_xfer::<CbList::Xfer, _>(gcb, *self.channel.get().unwrap(), GioOp::Read, RwParams::new(offset)).await;
+            xfer_cb.data_buf_mut().ensure_size(gcb, len).await;
+            ::udi::meta_gio::xfer_req(xfer_cb);
+        }
+    }
+    impl ::udi::meta_gio::Client for ::udi::init::RData<Driver> {
+        type Future_bind_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+        fn bind_ack<'s>(&'s self, cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_bind_cb_t>, size: ::udi::Result<u64>) -> Self::Future_bind_ack<'s> {
+            async move {
+                assert_eq!(size, Ok(super::CAPACITY * super::BLOCK_SIZE as u64));
+                assert!(self.channel.set(cb.gcb.channel).is_ok(), "Bound twice");
+                let mut xfer_cb = ::udi::meta_gio::alloc_xfer::<CbList::Xfer, _>(cb.gcb(), cb.gcb.channel, GioOp::Write, RwParams::new(super::TEST_OFFSET)).await;
+                xfer_cb.data_buf_mut().write(cb.gcb(), 0..0, &super::test_pattern()).await;
+                ::udi::meta_gio::xfer_req(xfer_cb);
+            }
+        }
+        type Future_unbind_ack<'s> = ::core::future::Ready<()>;
+        fn unbind_ack<'s>(&'s self, _cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_bind_cb_t>) -> Self::Future_unbind_ack<'s> {
+            ::core::future::ready(())
+        }
+        type Future_xfer_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+        fn xfer_ack<'s>(&'s self, cb: ::udi::cb::CbRef<'s, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t>) -> Self::Future_xfer_ack<'s> {
+            async move {
+                super::complete(&cb, Ok(()));
+                match cb.op()
+                {
+                GioOp::Write => self.read(cb.gcb(), super::TEST_OFFSET, super::TEST_LEN).await,
+                GioOp::Read => {
+                    let xfer_cb = ::udi::meta_gio::alloc_xfer::<CbList::Xfer, _>(cb.gcb(), *self.channel.get().unwrap(), ::udi::meta_gio::block::OP_FLUSH, ()).await;
+                    ::udi::meta_gio::xfer_req(xfer_cb);
+                    },
+                _ => {
+                    // Flushed, now read from the last block onwards
+                    let len = (super::TAIL_BLOCKS * super::BLOCK_SIZE as u64) as usize;
+                    self.read(cb.gcb(), (super::CAPACITY - 1) * super::BLOCK_SIZE as u64, len).await;
+                    },
+                }
+            }
+        }
+        type Future_xfer_nak<'s> = ::core::future::Ready<()>;
+        fn xfer_nak<'s>(&'s self, cb: ::udi::cb::CbRef<'s, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t>, res: ::udi::Result<()>) -> Self::Future_xfer_nak<'s> {
+            super::complete(&cb, res);
+            ::core::future::ready(())
+        }
+        fn xfer_ret(&self, cb: ::udi::cb::CbHandle<::udi::ffi::meta_gio::udi_gio_xfer_cb_t>) {
+            drop(cb);
+        }
+        type Future_event_ind<'s> = ::core::future::Ready<()>;
+        fn event_ind<'s>(&'s self, _cb: ::udi::cb::CbRef<'s,::udi::ffi::meta_gio::udi_gio_event_cb_t>, event: ::udi::meta_gio::GioEvent) -> Self::Future_event_ind<'s> {
+            panic!("Unexpected event {:?}", event)
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 udi_gio
+parent_bind_ops 1 0 1 1
+region 0
+");
+    const META_GIO: ::udi::ffi::udi_index_t = udiprops::meta::udi_gio;
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Client: Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_client_ops_t,
+        },
+        cbs: {
+            _Bind : Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_bind_cb_t,
+            Xfer : Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t,
+            _Event: Meta=META_GIO, ::udi::ffi::meta_gio::udi_gio_event_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+}
+
+/// Record a completed transfer
+fn complete(cb: &::udi::cb::CbRef<'_, ::udi::ffi::meta_gio::udi_gio_xfer_cb_t>, res: ::udi::Result<()>) {
+    let buf = cb.data_buf();
+    let mut data = vec![0; buf.len()];
+    if !data.is_empty() {
+        buf.read(0, &mut data);
+    }
+    COMPLETIONS.with(|c| c.borrow_mut().push(Completion { op: cb.op(), res, data }));
+}
+
+fn ramdisk_module() -> DriverModule<'static> {
+    use ::udi_environment::gio_ramdisk::{INIT_INFO_RAMDISK, udiprops::udiprops as raw_udiprops};
+    unsafe { DriverModule::new(&INIT_INFO_RAMDISK, ::udiprops_parse::load_from_raw_section(&raw_udiprops)) }
+}
+
+#[test]
+fn round_trip() {
+    let disk = common::instance(ramdisk_module());
+    disk.management_state.start_init(None);
+    common::run_managed(&[&disk]);
+    assert!(disk.management_state.is_ready());
+
+    // Bind the client to the disk's only child
+    let client = common::bind_child(&disk, 0, Arc::new(client::module()));
+    common::run_managed(&[&disk, &client]);
+    assert!(client.management_state.is_ready());
+
+    let completions = COMPLETIONS.take();
+    let ops: Vec<_> = completions.iter().map(|c| (c.op, c.res)).collect();
+    let underrun = Err(error(::udi::ffi::UDI_STAT_DATA_UNDERRUN));
+    assert_eq!(ops, [
+        (GioOp::Write, Ok(())),
+        (GioOp::Read, Ok(())),
+        (::udi::meta_gio::block::OP_FLUSH, Ok(())),
+        (GioOp::Read, underrun),
+        ]);
+    // The split read returns what the split write stored
+    assert_eq!(completions[1].data, test_pattern());
+    // Only the last block was read, the untouched disk is zeroed
+    assert_eq!(completions[3].data, vec![0; BLOCK_SIZE as usize]);
+}
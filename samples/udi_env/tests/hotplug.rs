@@ -0,0 +1,229 @@
+//! Enumeration after initialisation: hot-plug (`NEW`), removal and `RELEASE`, and `DIRECTED` enumeration
+//!
+//! A driver with numbered slots is driven through the management agent, with children added and removed by the
+//! test via its [::udi::init::ChildTracker].
+#![feature(impl_trait_in_assoc_type)]
+use ::std::sync::Arc;
+use self::common::MaEvent as Event;
+
+mod common;
+
+/// Metalanguage used by the children (nothing is ever bound)
+mod slot {
+    ::udi::define_metalanguage! {
+        NAME acme_slot;
+        CBS {
+            /// Slot control block
+            1 => acme_slot_cb_t {
+            },
+        }
+        OPS {
+            /// Slot operations
+            1 => acme_slot_ops_t : Slot {
+                /// Unused
+                IND fn ping_ind(cb: acme_slot_cb_t) as Future_ping_ind;
+            },
+        }
+    }
+    // Children are enumerated with these ops, but never bound
+    impl ::udi::ops_markers::ChildBind for acme_slot_ops_t {
+        const ASSERT: () = ();
+    }
+}
+
+mod driver {
+    use ::udi::init::{AttrSink, ChildEvent, EnumerateLevel, EnumerateResult};
+
+    #[derive(Default)]
+    pub struct Driver {
+        pub children: ::udi::init::ChildTracker<4>,
+        /// Child IDs released by `EnumerateLevel::Release`
+        pub released: crate::common::Recorder<u32>,
+    }
+    impl ::udi::init::Driver for ::udi::init::RData<Driver> {
+        const MAX_ATTRS: u8 = 2;
+        type Future_init<'s> = ::core::future::Ready<()>;
+        fn usage_ind<'s>(&'s self, _cb: ::udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+            ::core::future::ready(())
+        }
+        type Future_enumerate<'s> = impl ::core::future::Future<Output=(EnumerateResult,AttrSink<'s>)> + 's;
+        fn enumerate_req<'s>(&'s self, cb: ::udi::init::CbRefEnumerate<'s>, level: EnumerateLevel, mut attrs_out: AttrSink<'s>) -> Self::Future_enumerate<'s> {
+            async move {
+                match level
+                {
+                // Slot 1 is always present
+                EnumerateLevel::Start
+                |EnumerateLevel::StartRescan => {
+                    assert!(self.children.enumerated(1));
+                    attrs_out.push_u32("slot", 1);
+                    (EnumerateResult::ok::<OpsList::Slot>(1), attrs_out)
+                    },
+                EnumerateLevel::Next => (EnumerateResult::Done, attrs_out),
+                EnumerateLevel::New => match self.children.wait_event(cb).await
+                    {
+                    ChildEvent::Added(child_id) => {
+                        attrs_out.push_u32("slot", child_id);
+                        (EnumerateResult::ok::<OpsList::Slot>(child_id), attrs_out)
+                        },
+                    ChildEvent::Removed(child_id) => (EnumerateResult::Removed(child_id), attrs_out),
+                    },
+                EnumerateLevel::Directed => match attrs_out.directed_u32("slot")
+                    {
+                    Some(child_id) if !self.children.is_present(child_id) && self.children.enumerated(child_id) => {
+                        attrs_out.keep_directed();
+                        attrs_out.push_string("origin", "directed");
+                        (EnumerateResult::ok::<OpsList::Slot>(child_id), attrs_out)
+                        },
+                    _ => (EnumerateResult::Failed, attrs_out),
+                    },
+                EnumerateLevel::Release(child_id) => {
+                    assert!(self.children.release(child_id));
+                    self.released.push(child_id);
+                    (EnumerateResult::Released, attrs_out)
+                    },
+                }
+            }
+        }
+        type Future_devmgmt<'s> = ::core::future::Ready<::udi::Result<u8>>;
+        fn devmgmt_req<'s>(&'s self, _cb: ::udi::init::CbRefMgmt<'s>, _mgmt_op: ::udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+            // Only used when an instance stands in for a bound child, which has nothing to tear down
+            ::core::future::ready(Ok(0))
+        }
+    }
+    impl crate::slot::Slot for ::udi::ChildBind<Driver,()> {
+        type Future_ping_ind<'s> = ::core::future::Ready<()>;
+        fn ping_ind<'s>(&'s self, _cb: ::udi::CbRef<'s, crate::slot::acme_slot_cb_t>) -> Self::Future_ping_ind<'s> {
+            unreachable!()
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 acme_slot
+child_bind_ops 1 0 1
+region 0
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Slot: Meta=udiprops::meta::acme_slot, crate::slot::acme_slot_ops_t : ChildBind<_,()>,
+        },
+        cbs: {
+            _Slot: Meta=udiprops::meta::acme_slot, crate::slot::acme_slot_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+}
+
+/// Create an instance of the driver, and start its management agent
+fn start() -> Arc<::udi_environment::DriverInstance> {
+    ::udi_environment::register_metalanguage(&slot::METALANG_SPEC);
+    let inst = common::instance(driver::module());
+    inst.management_state.start_init(None);
+    inst
+}
+fn driver(inst: &::udi_environment::DriverInstance) -> &::udi::init::RData<driver::Driver> {
+    common::rdata(inst)
+}
+/// Child IDs, and their `slot` attribute
+fn children(inst: &::udi_environment::DriverInstance) -> Vec<(u32, Option<u32>)> {
+    inst.children.lock().unwrap().iter()
+        .map(|c| (c.child_id, c.attrs.iter().find(|a| a.attr_name.starts_with(b"slot\0")).map(|a| u32::from_ne_bytes(a.attr_value[..4].try_into().unwrap()))))
+        .collect()
+}
+
+fn attr_u32(name: &str, val: u32) -> ::udi::ffi::attr::udi_instance_attr_list_t {
+    // SAFE: All-zero is valid for this POD structure
+    let mut rv: ::udi::ffi::attr::udi_instance_attr_list_t = unsafe { ::core::mem::zeroed() };
+    rv.attr_name[..name.len()].copy_from_slice(name.as_bytes());
+    rv.attr_type = ::udi::ffi::attr::UDI_ATTR_UBIT32 as _;
+    rv.attr_length = 4;
+    rv.attr_value[..4].copy_from_slice(&val.to_ne_bytes());
+    rv
+}
+
+#[test]
+fn hotplug() {
+    let inst = start();
+    assert_eq!(common::run_managed(&[&inst]), [Event::InitComplete]);
+    assert!(inst.management_state.is_ready());
+    assert_eq!(children(&inst), [(1, Some(1))]);
+
+    // Nothing happens until a child is added
+    inst.management_state.watch_new();
+    assert_eq!(common::run_managed(&[&inst]), []);
+    assert!(driver(&inst).children.add(2));
+    assert_eq!(children(&inst), [(1, Some(1)), (2, Some(2))]);
+    assert_eq!(common::run_managed(&[&inst]), [Event::ChildrenChanged]);
+    assert_eq!(driver(&inst).children.active().collect::<Vec<_>>(), [1, 2]);
+
+    // Removal is reported, and the ID is released
+    assert!(driver(&inst).children.remove(1));
+    assert_eq!(children(&inst), [(2, Some(2))]);
+    assert!(!driver(&inst).children.add(1), "ID re-used before release");
+    assert_eq!(common::run_managed(&[&inst]), []);
+    assert_eq!(driver(&inst).released.take(), [1]);
+    assert!(!driver(&inst).children.is_present(1));
+
+    // And can then be re-used
+    assert!(driver(&inst).children.add(1));
+    assert_eq!(common::run_managed(&[&inst]), [Event::ChildrenChanged]);
+    assert_eq!(children(&inst), [(2, Some(2)), (1, Some(1))]);
+}
+
+#[test]
+fn directed() {
+    let inst = start();
+    assert_eq!(common::run_managed(&[&inst]), [Event::InitComplete]);
+
+    inst.management_state.request_directed(vec![attr_u32("slot", 3)]);
+    assert_eq!(common::run_managed(&[&inst]), [Event::ChildrenChanged]);
+    assert_eq!(children(&inst), [(1, Some(1)), (3, Some(3))]);
+    // The supplied attribute was kept, and the driver's added after it
+    {
+        let children = inst.children.lock().unwrap();
+        let names: Vec<_> = children[1].attrs.iter().map(|a| ::std::str::from_utf8(&a.attr_name[..a.attr_name.iter().position(|&c| c == 0).unwrap()]).unwrap().to_owned()).collect();
+        assert_eq!(names, ["slot", "origin"]);
+    }
+
+    // Already present, or missing the attribute
+    inst.management_state.request_directed(vec![attr_u32("slot", 1)]);
+    inst.management_state.request_directed(vec![attr_u32("other", 4)]);
+    assert_eq!(common::run_managed(&[&inst]), []);
+    assert_eq!(children(&inst), [(1, Some(1)), (3, Some(3))]);
+
+    // A child added by directed enumeration can be hot-removed
+    inst.management_state.watch_new();
+    assert!(driver(&inst).children.remove(3));
+    assert_eq!(common::run_managed(&[&inst]), []);
+    assert_eq!(driver(&inst).released.take(), [3]);
+    assert_eq!(children(&inst), [(1, Some(1))]);
+}
+
+#[test]
+fn remove_bound() {
+    let inst = start();
+    assert_eq!(common::run_managed(&[&inst]), [Event::InitComplete]);
+    // Another (orphan) instance stands in for a driver bound to slot 1
+    let bound = common::instance(driver::module());
+    bound.management_state.start_init(None);
+    assert_eq!(common::run_managed(&[&bound]), [Event::InitComplete]);
+    {
+        let children = inst.children.lock().unwrap();
+        children[0].is_bound.set(true);
+        let _ = children[0].instance.set(Arc::downgrade(&bound));
+    }
+
+    // The release is held until the bound instance has been unbound
+    inst.management_state.watch_new();
+    assert!(driver(&inst).children.remove(1));
+    assert_eq!(common::run_managed(&[&inst]), []);
+    assert!(driver(&inst).released.take().is_empty());
+    assert_eq!(children(&inst), [(1, Some(1))]);
+
+    assert_eq!(common::run_managed(&[&inst, &bound]), []);
+    assert!(bound.management_state.is_unbound());
+    assert_eq!(driver(&inst).released.take(), [1]);
+    assert_eq!(children(&inst), []);
+}
@@ -0,0 +1,205 @@
+//! Interrupt dispatch through the sample ISA bridge's `udi::meta_bridge::IntrDispatchTable`
+//!
+//! A minimal handler is bound to the serial port (ISA child 1), with a preprocessing handle that reads the received
+//! byte and returns it in the upper byte of the result. A NUL byte is consumed as `UDI_INTR_NO_EVENT`, and an interrupt
+//! with no data (e.g. from another device on the line) is `UDI_INTR_UNCLAIMED`. Neither is indicated, and the event CB
+//! is kept by the bridge for the next interrupt.
+//!
+//! On unbind, the handler drops its `IntrHandle` (so the detach acknowledgement goes to `intr_detach_cb_ret`) and then
+//! waits in `bus_unbind`. Both request CBs carry a driver value in `initiator_context`, which the library must leave
+//! alone.
+#![feature(impl_trait_in_assoc_type)]
+use ::std::cell::RefCell;
+use ::std::sync::Arc;
+use ::udi::meta_bridge::IntrEventFlags;
+
+mod common;
+
+thread_local! {
+    /// `(intr_result, flags)` of each indication seen by the handler
+    static EVENTS: RefCell<Vec<(u16, IntrEventFlags)>> = const { RefCell::new(Vec::new()) };
+    /// `initiator_context` of the CBs returned to the handler on unbind
+    static RETURNED: RefCell<Vec<(&'static str, usize)>> = const { RefCell::new(Vec::new()) };
+}
+/// Driver value placed in `initiator_context` of the unbind requests (with the low bit set, as an odd index would)
+const DRIVER_CONTEXT: usize = 0x1235;
+
+mod handler {
+    use ::core::cell::{Cell, OnceCell};
+
+    /// Number of event CBs given to the bridge, all of which it must hold to enable the interrupt
+    pub const NUM_EVENT_CBS: u8 = 2;
+
+    ::udi::define_pio_ops!{IRQACK =
+        const RBR = 0;
+        const IER = 1;
+        const LSR = 5;
+        // 0: Enable the receive interrupt
+        LOAD_IMM.B R0, 0x01;    // IER.ERBI
+        OUT.B IER, R0;
+        END_IMM 0;
+        // 1: Read the received byte
+        LABEL 1;
+        IN.B R0, LSR;
+        AND_IMM.B R0, 0x01;     // LSR.DR
+        CSKIP.B R0 NZ;
+        END_IMM ::udi::ffi::meta_bridge::UDI_INTR_UNCLAIMED;
+        IN.B R0, RBR;
+        CSKIP.B R0 NZ;
+        END_IMM ::udi::ffi::meta_bridge::UDI_INTR_NO_EVENT;
+        SHIFT_LEFT.S R0, 8;
+        END.S R0;
+    }
+
+    #[derive(Default)]
+    pub struct Driver {
+        /// Handler end of the interrupt channel, until the attach is acknowledged
+        intr_channel: Cell<Option<::udi::imc::ChannelHandle>>,
+        /// Attached interrupt, dropped on unbind
+        intr: Cell<Option<::udi::meta_bridge::IntrHandle>>,
+        bus_channel: OnceCell<::udi::ffi::udi_channel_t>,
+    }
+    impl ::udi::init::Driver for ::udi::init::RData<Driver> {
+        const MAX_ATTRS: u8 = 0;
+        type Future_init<'s> = ::core::future::Ready<()>;
+        fn usage_ind<'s>(&'s self, _cb: ::udi::init::CbRefUsage<'s>, _resouce_level: u8) -> Self::Future_init<'s> {
+            ::core::future::ready(())
+        }
+        type Future_enumerate<'s> = ::core::future::Ready<(::udi::init::EnumerateResult,::udi::init::AttrSink<'s>)>;
+        fn enumerate_req<'s>(&'s self, _cb: ::udi::init::CbRefEnumerate<'s>, _level: ::udi::init::EnumerateLevel, attrs_out: ::udi::init::AttrSink<'s>) -> Self::Future_enumerate<'s> {
+            ::core::future::ready((::udi::init::EnumerateResult::Done, attrs_out))
+        }
+        type Future_devmgmt<'s> = impl ::core::future::Future<Output=::udi::Result<u8>> + 's;
+        fn devmgmt_req<'s>(&'s self, cb: ::udi::init::CbRefMgmt<'s>, mgmt_op: ::udi::init::MgmtOp, _parent_id: ::udi::ffi::udi_ubit8_t) -> Self::Future_devmgmt<'s> {
+            async move {
+                assert!(matches!(mgmt_op, ::udi::init::MgmtOp::Unbind), "Unexpected management op");
+                // Detach without waiting, the acknowledgement goes to `intr_detach_cb_ret`
+                drop(self.intr.take());
+                let mut bind_cb = ::udi::cb::alloc::<CbList::Bind>(cb.gcb(), *self.bus_channel.get().unwrap()).await;
+                // SAFE: Owned, and `initiator_context` is the driver's to set
+                unsafe { bind_cb.get_mut().gcb.initiator_context = super::DRIVER_CONTEXT as *mut _; }
+                let bind_cb = ::udi::meta_bridge::bus_unbind(cb.gcb(), bind_cb).await;
+                super::RETURNED.with(|r| r.borrow_mut().push(("bus_unbind", bind_cb.gcb.initiator_context as usize)));
+                Ok(0)
+            }
+        }
+    }
+    impl ::udi::meta_bridge::BusDevice for ::udi::init::RData<Driver> {
+        type Future_bind_ack<'s> = impl ::core::future::Future<Output=::udi::Result<()>> + 's;
+        fn bus_bind_ack<'a>(
+            &'a self,
+            cb: ::udi::meta_bridge::CbRefBind<'a>,
+            _dma_constraints: ::udi::physio::dma::DmaConstraints,
+            _preferred_endianness: ::udi::meta_bridge::PreferredEndianness,
+            _status: ::udi::ffi::udi_status_t
+        ) -> Self::Future_bind_ack<'a> {
+            async move {
+                assert!(self.bus_channel.set(cb.gcb.channel).is_ok(), "Bound twice");
+                let irq_ack = ::udi::pio::map(cb.gcb(), 0, 0, 8, &IRQACK, ::udi::ffi::pio::UDI_PIO_LITTLE_ENDIAN, 0, 0.into()).await;
+                let channel = ::udi::imc::channel_spawn::<OpsList::Irq>(cb.gcb(), self, 0.into()).await;
+                self.intr_channel.set(Some(channel));
+                let intr_cb = ::udi::cb::alloc::<CbList::Intr>(cb.gcb(), cb.gcb.channel).await;
+                ::udi::meta_bridge::intr_attach(intr_cb, 0.into(), NUM_EVENT_CBS, Some(irq_ack));
+                Ok(())
+            }
+        }
+        type Future_unbind_ack<'s> = ::core::future::Ready<()>;
+        fn bus_unbind_ack<'a>(&'a self, _cb: ::udi::meta_bridge::CbRefBind<'a>) -> Self::Future_unbind_ack<'a> {
+            ::core::future::ready(())
+        }
+        type Future_intr_attach_ack<'s> = impl ::core::future::Future<Output=()> + 's;
+        fn intr_attach_ack<'a>(&'a self, cb: ::udi::meta_bridge::CbRefIntrAttach<'a>, status: ::udi::ffi::udi_status_t) -> Self::Future_intr_attach_ack<'a> {
+            async move {
+                assert_eq!(status, ::udi::ffi::UDI_OK as _);
+                let channel = self.intr_channel.take().unwrap();
+                for _ in 0 .. NUM_EVENT_CBS {
+                    let event_cb = ::udi::cb::alloc::<CbList::IntrEvent>(cb.gcb(), channel.raw()).await;
+                    ::udi::meta_bridge::intr_event_rdy(event_cb);
+                }
+                let mut detach_cb = ::udi::cb::alloc::<CbList::Detach>(cb.gcb(), cb.gcb.channel).await;
+                // SAFE: Owned, and `initiator_context` is the driver's to set
+                unsafe { detach_cb.get_mut().gcb.initiator_context = super::DRIVER_CONTEXT as *mut _; }
+                self.intr.set(Some(::udi::meta_bridge::IntrHandle::new(channel, 0.into(), detach_cb)));
+            }
+        }
+        type Future_intr_detach_ack<'s> = ::core::future::Ready<()>;
+        fn intr_detach_ack<'a>(&'a self, _cb: ::udi::meta_bridge::CbRefIntrDetach<'a>) -> Self::Future_intr_detach_ack<'a> {
+            ::core::future::ready(())
+        }
+        fn intr_detach_cb_ret(&self, cb: ::udi::meta_bridge::CbHandleIntrDetach) {
+            super::RETURNED.with(|r| r.borrow_mut().push(("intr_detach_cb_ret", cb.gcb.initiator_context as usize)));
+        }
+    }
+    impl ::udi::meta_bridge::IntrHandler for ::udi::init::RData<Driver> {
+        type Future_intr_event_ind<'s> = ::core::future::Ready<()>;
+        fn intr_event_ind<'a>(&'a self, cb: ::udi::meta_bridge::CbRefEvent<'a>, flags: ::udi::meta_bridge::IntrEventFlags) -> Self::Future_intr_event_ind<'a> {
+            super::EVENTS.with(|e| e.borrow_mut().push((cb.intr_result().to_raw(), flags)));
+            ::core::future::ready(())
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 udi_bridge
+parent_bind_ops 1 0 1 1
+region 0
+");
+    const META_BRIDGE: ::udi::ffi::udi_index_t = udiprops::meta::udi_bridge;
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Dev: Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_bus_device_ops_t,
+            Irq: Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_handler_ops_t,
+        },
+        cbs: {
+            Bind     : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_bus_bind_cb_t,
+            Intr     : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_attach_cb_t,
+            IntrEvent: Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_event_cb_t,
+            Detach   : Meta=META_BRIDGE, ::udi::ffi::meta_bridge::udi_intr_detach_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+}
+
+#[test]
+fn preprocessing() {
+    let mut h = common::Platform::new();
+    h.run();
+    let pci = h.instances[0].clone();
+    let isa = h.bind_child(&pci, 3, common::isa_module());
+    h.run();
+    // ISA child 1 is the serial port at 0x3F8, IRQ 4
+    let serial = h.bind_child(&isa, 1, Arc::new(handler::module()));
+    h.run();
+    assert!(serial.management_state.is_ready());
+    let irq = || serial.device.get().expect("Device not bound").irq(0);
+    assert!(irq().is_bound());
+
+    // A received byte is indicated, with the preprocessing result
+    h.actions.push("uart_rx", b"A");
+    h.run();
+    let preprocessed = IntrEventFlags::PREPROCESSED;
+    assert_eq!(EVENTS.take(), [((b'A' as u16) << 8, preprocessed)]);
+
+    // Interrupts with nothing for the handler are not indicated, and keep their CB (so use more than were provided)
+    for _ in 0 .. handler::NUM_EVENT_CBS + 1 {
+        irq().raise();
+        h.run();
+        h.actions.push("uart_rx", b"\0");
+        h.run();
+    }
+    assert_eq!(EVENTS.take(), []);
+
+    // Nothing was lost as an overrun, so the next byte is indicated normally
+    h.actions.push("uart_rx", b"B");
+    h.run();
+    assert_eq!(EVENTS.take(), [((b'B' as u16) << 8, preprocessed)]);
+
+    // Unbinding returns each request CB with the driver's `initiator_context` intact
+    serial.management_state.request_unbind();
+    h.run();
+    assert!(serial.management_state.is_unbound());
+    assert!(!irq().is_bound());
+    assert_eq!(RETURNED.take(), [("intr_detach_cb_ret", DRIVER_CONTEXT), ("bus_unbind", DRIVER_CONTEXT)]);
+}
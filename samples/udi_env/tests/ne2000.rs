@@ -0,0 +1,88 @@
+//! NE2000 driver bound to the emulated PCI bridge (directly, or via the sample ISA bridge), then unbound by the
+//! management agent
+//!
+//! The driver's `UDI_DMGMT_UNBIND` handling detaches its interrupt and unbinds from the bridge, waiting for each
+//! acknowledgement (`udi::meta_bridge::intr_detach` and `udi::meta_bridge::bus_unbind`).
+//!
+//! The sample NSR is also bound on top, to check that packets it sends reach the wire.
+use ::std::sync::Arc;
+use ::udi_environment::DriverModule;
+use self::common::isa_module;
+
+mod common;
+
+fn ne2000_module() -> Arc<DriverModule<'static>> {
+    let udiprops = unsafe {
+        ::core::slice::from_raw_parts(::udi_net_ne2000::udiprops::udiprops.as_ptr(), ::udi_net_ne2000::udiprops::_LEN)
+    };
+    Arc::new(unsafe { DriverModule::new(&::udi_net_ne2000::udi_init_info, ::udiprops_parse::load_from_raw_section(udiprops)) })
+}
+fn sink_nsr_module() -> Arc<DriverModule<'static>> {
+    use ::udi_environment::sink_nsr::{INIT_INFO_NSR, udiprops::udiprops as raw_udiprops};
+    Arc::new(unsafe { DriverModule::new(&INIT_INFO_NSR, ::udiprops_parse::load_from_raw_section(&raw_udiprops)) })
+}
+
+#[test]
+fn unbind() {
+    let mut h = common::Platform::new();
+    h.run();
+    assert!(h.instances[0].management_state.is_ready());
+    // Child 0 is the RTL8029 (NE2000-compatible)
+    let pci = h.instances[0].clone();
+    let nic = h.bind_child(&pci, 0, ne2000_module());
+    h.run();
+    assert!(nic.management_state.is_ready());
+    let irq_bound = || nic.device.get().expect("Device not bound").irq(0).is_bound();
+    assert!(irq_bound());
+
+    nic.management_state.request_unbind();
+    h.run();
+    assert!(nic.management_state.is_unbound());
+    // The interrupt was detached by the driver (not cleaned up by the bridge's unbind)
+    assert!(!irq_bound());
+}
+
+#[test]
+fn nsr_send() {
+    let mut h = common::Platform::new();
+    h.run();
+    let pci = h.instances[0].clone();
+    let nic = h.bind_child(&pci, 0, ne2000_module());
+    h.run();
+    assert!(nic.management_state.is_ready());
+    // The sink NSR sends a test packet once the device is enabled
+    let nsr = h.bind_child(&nic, 0, sink_nsr_module());
+    h.run();
+    assert!(nsr.management_state.is_ready());
+    assert_eq!(h.actions.pull("nic_tx").as_deref(), Some(&b"TestPacketContent"[..]));
+    assert!(h.actions.pull("nic_tx").is_none());
+}
+
+#[test]
+fn isa() {
+    let mut h = common::Platform::new();
+    h.run();
+    let pci = h.instances[0].clone();
+    // Child 3 is the PCI-to-ISA bridge
+    let isa = h.bind_child(&pci, 3, isa_module());
+    h.run();
+    assert!(isa.management_state.is_ready());
+    assert_eq!(isa.children.lock().unwrap().len(), 2, "ISA bridge should enumerate its two devices");
+
+    // ISA child 0 is the NE2000 at 0x300, IRQ 9
+    let nic = h.bind_child(&isa, 0, ne2000_module());
+    h.run();
+    assert!(nic.management_state.is_ready());
+    let irq_bound = || nic.device.get().expect("Device not bound").irq(0).is_bound();
+    assert!(irq_bound(), "Interrupt should be routed through the ISA bridge's dispatch table");
+
+    nic.management_state.request_unbind();
+    h.run();
+    assert!(nic.management_state.is_unbound());
+    assert!(!irq_bound());
+
+    // Then the bridge itself, which unbinds from PCI
+    isa.management_state.request_unbind();
+    h.run();
+    assert!(isa.management_state.is_unbound());
+}
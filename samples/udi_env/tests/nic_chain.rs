@@ -0,0 +1,450 @@
+//! NIC Rx/Tx CB ownership
+//!
+//! A loopback ND (each transmitted packet is indicated on a receive CB) is connected to an NSR that tracks which
+//! side holds every CB it allocates, so a CB returned twice or never returned is caught.
+#![feature(impl_trait_in_assoc_type)]
+use ::std::cell::{Cell, RefCell};
+use ::std::collections::HashMap;
+use ::udi::meta_nic::{RxChain, TxChain, TxQueue};
+
+mod common;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+enum Holder {
+    Nsr,
+    Nd,
+}
+thread_local! {
+    /// Current holder of each CB allocated by the NSR
+    static HOLDERS: RefCell<HashMap<usize, Holder>> = RefCell::new(HashMap::new());
+    /// Have the ND keep transmitted CBs instead of returning them
+    static ND_HOLD: Cell<bool> = const { Cell::new(false) };
+    /// CBs kept by the ND (when `ND_HOLD` is set)
+    static ND_HELD: Cell<*mut ::udi::ffi::meta_nic::udi_nic_tx_cb_t> = const { Cell::new(::core::ptr::null_mut()) };
+    /// Ownership errors seen by `pass` (can't panic, as the ops are called through `extern "C"` functions)
+    static ERRORS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+/// Record that a CB has been passed to the other side
+fn pass<T>(cb: *const T, from: Holder, to: Holder, what: &str) {
+    HOLDERS.with(|h| {
+        let mut h = h.borrow_mut();
+        let holder = h.entry(cb as usize).or_insert(from);
+        if *holder != from {
+            let msg = match from {
+                Holder::Nd => format!("{what} CB {cb:p} returned twice"),
+                Holder::Nsr => format!("{what} CB {cb:p} passed to the ND while held by {holder:?}"),
+                };
+            ERRORS.with(|e| e.borrow_mut().push(msg));
+        }
+        *holder = to;
+    });
+}
+/// Take the ownership errors seen so far
+fn take_errors() -> Vec<String> {
+    ERRORS.take()
+}
+/// Count the allocated CBs held by `holder`
+fn count_held(holder: Holder) -> usize {
+    HOLDERS.with(|h| h.borrow().values().filter(|v| **v == holder).count())
+}
+
+mod nd {
+    use ::std::cell::Cell;
+
+    pub struct Driver {
+        rx_cbs: ::udi::meta_nic::ReadCbQueue,
+        tx_queue: ::udi::meta_nic::TxQueue,
+        pub tx_count: Cell<usize>,
+    }
+    impl Default for Driver {
+        fn default() -> Self {
+            Driver {
+                rx_cbs: Default::default(),
+                // Enough slots that CBs kept by `ND_HOLD` don't stall transmission
+                tx_queue: ::udi::meta_nic::TxQueue::new(16, 1),
+                tx_count: Default::default(),
+            }
+        }
+    }
+    impl ::udi::meta_nic::NdTx for ::udi::init::RData<Driver> {
+        type Future_tx_req<'s> = impl ::core::future::Future<Output=()> + 's;
+        fn tx_req<'a>(&'a self, cbs: ::udi::meta_nic::TxRequest<'a>) -> Self::Future_tx_req<'a> {
+            cbs.enqueue(&self.tx_queue);
+            async move {
+                while let Some(cb) = self.tx_queue.next_submit() {
+                    self.tx_count.set(self.tx_count.get() + 1);
+                    if let Some(rx_cb) = self.rx_cbs.pop() {
+                        ::udi::meta_nic::nsr_rx_ind(rx_cb);
+                    }
+                    if super::ND_HOLD.get() {
+                        let mut held = ::udi::meta_nic::TxChain::from(cb);
+                        let prev = super::ND_HELD.replace(::core::ptr::null_mut());
+                        if !prev.is_null() {
+                            let mut prev = unsafe { ::udi::meta_nic::TxChain::from_raw(prev) };
+                            prev.append(held);
+                            held = prev;
+                        }
+                        super::ND_HELD.set(held.into_raw());
+                    }
+                    else {
+                        self.tx_queue.complete(cb);
+                    }
+                }
+            }
+        }
+        type Future_exp_tx_req<'s> = impl ::core::future::Future<Output=()> + 's;
+        fn exp_tx_req<'a>(&'a self, cbs: ::udi::meta_nic::TxRequest<'a>) -> Self::Future_exp_tx_req<'a> {
+            self.tx_req(cbs)
+        }
+    }
+    impl ::udi::meta_nic::NdRx for ::udi::init::RData<Driver> {
+        fn rx_rdy(&self, cbs: ::udi::meta_nic::RxChain) {
+            self.rx_cbs.push(cbs);
+        }
+    }
+    crate::common::unmanaged_driver!(Driver);
+
+    ::udi_macros::udiprops!("
+meta 1 udi_nic
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Tx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nd_tx_ops_t,
+            Rx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nd_rx_ops_t,
+        },
+        cbs: {
+            _NicTx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_tx_cb_t,
+            _NicRx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_rx_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+    pub const OPS_TX: ::udi::ffi::udi_index_t = OpsList::Tx;
+    pub const OPS_RX: ::udi::ffi::udi_index_t = OpsList::Rx;
+}
+
+mod nsr {
+    use super::Holder;
+
+    #[derive(Default)]
+    pub struct Driver {
+        pub tx_cbs: ::udi::cb::SharedQueue<::udi::ffi::meta_nic::udi_nic_tx_cb_t>,
+        /// Addresses of the RX CBs indicated
+        rx_cbs: crate::common::Recorder<usize>,
+    }
+    impl crate::common::Recording for Driver {
+        type Event = usize;
+        fn events(&self) -> &crate::common::Recorder<usize> {
+            &self.rx_cbs
+        }
+    }
+    impl ::udi::meta_nic::NsrTx for ::udi::init::RData<Driver> {
+        fn tx_rdy(&self, cbs: ::udi::meta_nic::TxChain) {
+            for cb in cbs.iter() {
+                super::pass(cb.to_raw(), Holder::Nd, Holder::Nsr, "TX");
+            }
+            self.tx_cbs.push_back(cbs.into_handle());
+        }
+    }
+    impl ::udi::meta_nic::NsrRx for ::udi::init::RData<Driver> {
+        type Future_rx_ind<'s> = ::core::future::Ready<()>;
+        fn rx_ind<'a>(&'a self, cbs: ::udi::meta_nic::RxChainRef<'a>) -> Self::Future_rx_ind<'a> {
+            for cb in cbs.iter() {
+                super::pass(cb.to_raw(), Holder::Nd, Holder::Nsr, "RX");
+                self.rx_cbs.push(cb.to_raw() as usize);
+            }
+            ::core::future::ready(())
+        }
+        type Future_exp_rx_ind<'s> = impl ::core::future::Future<Output=()> + 's;
+        fn exp_rx_ind<'a>(&'a self, cbs: ::udi::meta_nic::RxChainRef<'a>) -> Self::Future_exp_rx_ind<'a> {
+            self.rx_ind(cbs)
+        }
+        fn rx_cb_ret(&self, cbs: ::udi::meta_nic::RxChain) {
+            for cb in cbs.iter() {
+                super::pass(cb.to_raw(), Holder::Nsr, Holder::Nd, "RX");
+            }
+            ::udi::meta_nic::nd_rx_rdy(cbs);
+        }
+    }
+    crate::common::unmanaged_driver!(Driver);
+
+    ::udi_macros::udiprops!("
+meta 1 udi_nic
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Tx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nsr_tx_ops_t,
+            Rx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nsr_rx_ops_t,
+        },
+        cbs: {
+            NicTx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_tx_cb_t,
+            NicRx: Meta=udiprops::meta::udi_nic, ::udi::ffi::meta_nic::udi_nic_rx_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+    pub const OPS_TX: ::udi::ffi::udi_index_t = OpsList::Tx;
+    pub const OPS_RX: ::udi::ffi::udi_index_t = OpsList::Rx;
+    pub const CB_TX: ::udi::ffi::udi_index_t = <CbList::NicTx as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_RX: ::udi::ffi::udi_index_t = <CbList::NicRx as ::udi::cb::CbDefinition>::INDEX;
+}
+
+
+/// An NSR and ND instance, connected by a TX and RX channel
+type Harness = common::Pair<nsr::Driver, nd::Driver, Channels>;
+struct Channels {
+    /// NSR end of the TX channel
+    tx: ::udi::ffi::udi_channel_t,
+    /// ND end of the TX channel
+    tx_nd: ::udi::ffi::udi_channel_t,
+    /// NSR end of the RX channel
+    rx: ::udi::ffi::udi_channel_t,
+}
+fn harness() -> Harness {
+    Harness::new(nsr::module(), nd::module(), |nsr, nd| {
+        let (tx, tx_nd) = common::connect(nsr, nsr::OPS_TX, nd, nd::OPS_TX);
+        let (rx, _) = common::connect(nsr, nsr::OPS_RX, nd, nd::OPS_RX);
+        Channels { tx, tx_nd, rx }
+        })
+}
+impl Harness {
+    /// Allocate a CB in the NSR, and track it
+    fn alloc_tracked(&self, cb_idx: ::udi::ffi::udi_index_t, channel: ::udi::ffi::udi_channel_t) -> *mut ::udi::ffi::udi_cb_t {
+        let cb = self.alloc_raw(cb_idx, channel);
+        HOLDERS.with(|h| h.borrow_mut().insert(cb as usize, Holder::Nsr));
+        cb
+    }
+    /// Allocate a chain of `count` TX CBs
+    fn alloc_tx(&self, count: usize) -> TxChain {
+        let mut rv = unsafe { TxChain::from_raw(self.alloc_tracked(nsr::CB_TX, self.channels.tx) as _) };
+        for _ in 1 .. count {
+            rv.append(unsafe { TxChain::from_raw(self.alloc_tracked(nsr::CB_TX, self.channels.tx) as _) });
+        }
+        rv
+    }
+    /// Allocate a chain of `count` TX CBs, as if they had been passed to the ND (for driving a `TxQueue` directly)
+    fn alloc_tx_nd(&self, count: usize) -> TxChain {
+        let alloc_one = || {
+            let cb = self.alloc_tracked(nsr::CB_TX, self.channels.tx_nd);
+            pass(cb, Holder::Nsr, Holder::Nd, "TX");
+            unsafe { TxChain::from_raw(cb as _) }
+        };
+        let mut rv = alloc_one();
+        for _ in 1 .. count {
+            rv.append(alloc_one());
+        }
+        rv
+    }
+    /// Allocate a chain of `count` RX CBs
+    fn alloc_rx(&self, count: usize) -> RxChain {
+        let mut rv = unsafe { RxChain::from_raw(self.alloc_tracked(nsr::CB_RX, self.channels.rx) as _) };
+        for _ in 1 .. count {
+            rv.append(unsafe { RxChain::from_raw(self.alloc_tracked(nsr::CB_RX, self.channels.rx) as _) });
+        }
+        rv
+    }
+    fn tx_req(&self, cbs: TxChain) {
+        for cb in cbs.iter() {
+            pass(cb.to_raw(), Holder::Nsr, Holder::Nd, "TX");
+        }
+        ::udi::meta_nic::nd_tx_req(cbs);
+    }
+    fn rx_rdy(&self, cbs: RxChain) {
+        for cb in cbs.iter() {
+            pass(cb.to_raw(), Holder::Nsr, Holder::Nd, "RX");
+        }
+        ::udi::meta_nic::nd_rx_rdy(cbs);
+    }
+}
+
+#[test]
+fn loopback() {
+    let h = harness();
+    h.rx_rdy(h.alloc_rx(4));
+    h.run();
+    assert_eq!(count_held(Holder::Nd), 4);
+
+    let tx = h.alloc_tx(3);
+    assert_eq!(tx.count(), 3);
+    h.tx_req(tx);
+    h.tx_req(h.alloc_tx(1));
+    assert_eq!(h.run().len(), 4, "Packets indicated");
+    assert_eq!(h.peer().tx_count.get(), 4);
+    // Every TX CB is back with the NSR, and the RX CBs have been returned to the ND
+    assert_eq!(count_held(Holder::Nsr), 4);
+    assert_eq!(count_held(Holder::Nd), 4);
+
+    // And the returned CBs can be sent again
+    let tx = h.driver().tx_cbs.take_all().unwrap();
+    h.tx_req(tx.into());
+    assert_eq!(h.run().len(), 4, "Packets indicated");
+    assert_eq!(h.peer().tx_count.get(), 8);
+    assert_eq!(count_held(Holder::Nsr), 4);
+    assert_eq!(take_errors(), Vec::<String>::new());
+}
+
+#[test]
+fn leaked_cb() {
+    let h = harness();
+    ND_HOLD.set(true);
+    h.tx_req(h.alloc_tx(2));
+    h.run();
+    assert_eq!(h.peer().tx_count.get(), 2);
+    assert_eq!(count_held(Holder::Nd), 2, "CBs kept by the ND should be seen as outstanding");
+    assert!(h.driver().tx_cbs.is_empty());
+
+    // Returning them late clears the leak
+    ND_HOLD.set(false);
+    ::udi::meta_nic::nsr_tx_rdy(unsafe { TxChain::from_raw(ND_HELD.replace(::core::ptr::null_mut())) });
+    h.run();
+    assert_eq!(count_held(Holder::Nd), 0);
+    assert_eq!(take_errors(), Vec::<String>::new());
+}
+
+#[test]
+fn double_return() {
+    let h = harness();
+    ND_HOLD.set(true);
+    h.tx_req(h.alloc_tx(1));
+    h.run();
+    let cb = ND_HELD.get();
+    ::udi::meta_nic::nsr_tx_rdy(unsafe { TxChain::from_raw(cb) });
+    h.run();
+    // A misbehaving ND returns the CB again (after the NSR has taken it back)
+    let cb = h.driver().tx_cbs.pop_front().unwrap().into_raw();
+    unsafe { (*cb).gcb.channel = h.channels.tx_nd; }
+    ::udi::meta_nic::nsr_tx_rdy(unsafe { TxChain::from_raw(cb) });
+    h.run();
+    let errors = take_errors();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(errors[0].ends_with("returned twice"), "{errors:?}");
+    // The NSR now has the same CB queued twice, forget one of them
+    let _ = h.driver().tx_cbs.pop_front().unwrap().into_raw();
+}
+
+/// Address of a CB, for checking the order CBs are returned in
+fn addr(cb: &::udi::cb::CbHandle<::udi::ffi::meta_nic::udi_nic_tx_cb_t>) -> usize {
+    &**cb as *const _ as usize
+}
+
+#[test]
+fn tx_queue_order() {
+    let h = harness();
+    let q = TxQueue::new(2, 2);
+    let first = h.alloc_tx_nd(3);
+    let order: Vec<usize> = first.iter().map(|cb| cb.to_raw() as usize).collect();
+    q.enqueue(first);
+    let last = h.alloc_tx_nd(1);
+    let order: Vec<usize> = order.into_iter().chain(last.iter().map(|cb| cb.to_raw() as usize)).collect();
+    q.enqueue(last);
+    assert_eq!((q.pending(), q.in_flight()), (4, 0));
+
+    // Submitted in the order queued, limited by the hardware slots
+    let a = q.next_submit().unwrap();
+    let b = q.next_submit().unwrap();
+    assert!(q.next_submit().is_none(), "Both hardware slots are in use");
+    assert_eq!([addr(&a), addr(&b)], [order[0], order[1]]);
+    assert_eq!((q.pending(), q.in_flight()), (2, 2));
+
+    q.complete(a);
+    let c = q.next_submit().unwrap();
+    assert_eq!(addr(&c), order[2]);
+    q.complete(b);
+    let d = q.next_submit().unwrap();
+    assert_eq!(addr(&d), order[3]);
+    q.complete(c);
+    q.complete(d);
+    assert!(q.is_idle());
+    h.run();
+
+    let returned: Vec<_> = ::std::iter::from_fn(|| h.driver().tx_cbs.pop_front().map(|cb| cb.into_raw())).collect();
+    assert_eq!(returned.len(), 4);
+    assert_eq!(count_held(Holder::Nd), 0);
+    assert_eq!(take_errors(), Vec::<String>::new());
+    for cb in returned {
+        drop(unsafe { ::udi::cb::CbHandle::from_raw(cb) });
+    }
+}
+
+#[test]
+fn tx_queue_stats() {
+    let h = harness();
+    let q = TxQueue::new(1, 4);
+    q.set_watermarks(1, 3);
+    q.enqueue(h.alloc_tx_nd(2));
+    assert!(!q.is_congested());
+    q.enqueue(h.alloc_tx_nd(2));
+    assert!(q.is_congested(), "4 pending is above the high watermark");
+
+    let a = q.next_submit().unwrap();
+    assert!(q.next_submit().is_none());
+    assert!(q.next_submit().is_none());
+    assert!(q.is_congested(), "3 pending is still above the low watermark");
+    q.complete(a);
+    let b = q.next_submit().unwrap();
+    assert!(q.is_congested(), "2 pending is below the high watermark, but hasn't reached the low one");
+    q.complete(b);
+    let c = q.next_submit().unwrap();
+    assert!(!q.is_congested());
+    q.complete(c);
+    q.enqueue(h.alloc_tx_nd(3));
+    assert!(q.is_congested(), "Congested again at the high watermark");
+    while let Some(cb) = q.next_submit() {
+        q.complete(cb);
+    }
+    assert!(q.is_idle());
+
+    assert_eq!(q.stats(), ::udi::meta_nic::TxQueueStats {
+        enqueued: 7,
+        returned: 7,
+        stalls: 2,
+        congestion_events: 2,
+        max_pending: 4,
+    });
+    h.run();
+    assert_eq!(count_held(Holder::Nd), 0);
+    assert_eq!(take_errors(), Vec::<String>::new());
+}
+
+#[test]
+fn tx_queue_drain() {
+    let h = harness();
+    let q = TxQueue::new(2, 4);
+    q.enqueue(h.alloc_tx_nd(3));
+    let a = q.next_submit().unwrap();
+    let b = q.next_submit().unwrap();
+    q.complete(a);
+    q.complete(b);
+    h.run();
+    // Less than a batch has completed, and one is still pending, so nothing has been returned
+    assert_eq!(q.stats().returned, 0);
+    assert_eq!(count_held(Holder::Nd), 3);
+
+    // Completing the last CB empties the queue, which returns everything without waiting for a full batch
+    let c = q.next_submit().unwrap();
+    q.complete(c);
+    assert!(q.is_idle());
+    assert_eq!(q.stats().returned, 3);
+    h.run();
+    assert_eq!(count_held(Holder::Nd), 0);
+
+    // A partial batch can also be returned early with `flush`
+    q.enqueue(h.alloc_tx_nd(2));
+    let a = q.next_submit().unwrap();
+    q.complete(a);
+    assert_eq!(q.stats().returned, 3);
+    q.flush();
+    assert_eq!(q.stats().returned, 4);
+    h.run();
+    assert_eq!(count_held(Holder::Nd), 1, "The CB that wasn't submitted is still held");
+    while let Some(cb) = q.next_submit() {
+        q.complete(cb);
+    }
+    h.run();
+    assert_eq!(count_held(Holder::Nd), 0);
+    assert_eq!(take_errors(), Vec::<String>::new());
+}
@@ -0,0 +1,395 @@
+//! USBDI against the mock USB host
+//!
+//! A minimal LDD records every acknowledgement it gets, while the test drives requests through the interface and
+//! pipe channels.
+use ::udi::ffi::udi_channel_t;
+use ::udi::ffi::meta_usb as ffi;
+use ::udi::meta_usb::{ControlXfer, DescRequest, DescriptorType, DeviceRequest, DeviceSpeed, DeviceState, Direction, IntrBulkXfer, IsocXfer, PipeState};
+use ::udi_environment::usb_mock_host as host;
+use self::common::error;
+
+mod common;
+
+#[derive(Debug,PartialEq)]
+pub enum Event {
+    Opened(::udi::Result<u8>),
+    Closed(::udi::Result<()>),
+    ConfigSet(::udi::Result<()>),
+    FrameNumber(u32),
+    Speed(DeviceSpeed),
+    DeviceState(DeviceState),
+    IntfcState(::udi::Result<PipeState>),
+    Desc(::udi::Result<()>, Vec<u8>),
+    Control(::udi::Result<()>, Vec<u8>),
+    Bulk(::udi::Result<()>, Vec<u8>),
+    Isoc(::udi::Result<()>, Vec<u8>, Vec<u32>),
+    PipeState(::udi::Result<PipeState>),
+    StateSet(::udi::Result<()>),
+    EdptState(::udi::Result<PipeState>),
+}
+
+mod ldd {
+    use super::Event;
+    use ::udi::ffi::meta_usb as ffi;
+    use ::udi::meta_usb::{DeviceSpeed, DeviceState, PipeState};
+
+    #[derive(Default)]
+    pub struct Driver {
+        events: crate::common::Recorder<Event>,
+    }
+    impl crate::common::Recording for Driver {
+        type Event = Event;
+        fn events(&self) -> &crate::common::Recorder<Event> {
+            &self.events
+        }
+    }
+    fn buf_data(buf: &::udi::buf::Handle) -> Vec<u8> {
+        let mut rv = vec![0; buf.len()];
+        // The buffer may be null (e.g. a control transfer with no data stage)
+        if !rv.is_empty() {
+            buf.read(0, &mut rv);
+        }
+        rv
+    }
+    crate::common::unmanaged_driver!(Driver);
+
+    impl ::udi::meta_usb::LddIntfc for ::udi::init::RData<Driver> {
+        type Future_bind_ack<'s> = ::core::future::Ready<()>;
+        fn bind_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, _n_intfc: ::udi::Result<u8>) -> Self::Future_bind_ack<'s> {
+            unreachable!()
+        }
+        type Future_unbind_ack<'s> = ::core::future::Ready<()>;
+        fn unbind_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, _res: ::udi::Result<()>) -> Self::Future_unbind_ack<'s> {
+            unreachable!()
+        }
+        type Future_intfc_open_ack<'s> = ::core::future::Ready<()>;
+        fn intfc_open_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, n_edpt: ::udi::Result<u8>) -> Self::Future_intfc_open_ack<'s> {
+            self.events.push(Event::Opened(n_edpt));
+            ::core::future::ready(())
+        }
+        type Future_intfc_close_ack<'s> = ::core::future::Ready<()>;
+        fn intfc_close_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, res: ::udi::Result<()>) -> Self::Future_intfc_close_ack<'s> {
+            self.events.push(Event::Closed(res));
+            ::core::future::ready(())
+        }
+        type Future_frame_number_ack<'s> = ::core::future::Ready<()>;
+        fn frame_number_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, frame_number: u32) -> Self::Future_frame_number_ack<'s> {
+            self.events.push(Event::FrameNumber(frame_number));
+            ::core::future::ready(())
+        }
+        type Future_device_speed_ack<'s> = ::core::future::Ready<()>;
+        fn device_speed_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, speed: DeviceSpeed) -> Self::Future_device_speed_ack<'s> {
+            self.events.push(Event::Speed(speed));
+            ::core::future::ready(())
+        }
+        type Future_reset_device_ack<'s> = ::core::future::Ready<()>;
+        fn reset_device_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>) -> Self::Future_reset_device_ack<'s> {
+            unreachable!()
+        }
+        type Future_intfc_abort_ack<'s> = ::core::future::Ready<()>;
+        fn intfc_abort_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>) -> Self::Future_intfc_abort_ack<'s> {
+            unreachable!()
+        }
+        type Future_intfc_state_set_ack<'s> = ::core::future::Ready<()>;
+        fn intfc_state_set_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, res: ::udi::Result<()>) -> Self::Future_intfc_state_set_ack<'s> {
+            self.events.push(Event::StateSet(res));
+            ::core::future::ready(())
+        }
+        type Future_intfc_state_get_ack<'s> = ::core::future::Ready<()>;
+        fn intfc_state_get_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, state: ::udi::Result<PipeState>) -> Self::Future_intfc_state_get_ack<'s> {
+            self.events.push(Event::IntfcState(state));
+            ::core::future::ready(())
+        }
+        type Future_desc_ack<'s> = ::core::future::Ready<()>;
+        fn desc_ack<'s>(&'s self, cb: ::udi::CbRef<'s, ffi::usbdi_desc_cb_t>, res: ::udi::Result<()>) -> Self::Future_desc_ack<'s> {
+            self.events.push(Event::Desc(res, buf_data(cb.desc_buf())));
+            ::core::future::ready(())
+        }
+        type Future_device_state_get_ack<'s> = ::core::future::Ready<()>;
+        fn device_state_get_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, state: DeviceState) -> Self::Future_device_state_get_ack<'s> {
+            self.events.push(Event::DeviceState(state));
+            ::core::future::ready(())
+        }
+        type Future_config_set_ack<'s> = ::core::future::Ready<()>;
+        fn config_set_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, res: ::udi::Result<()>) -> Self::Future_config_set_ack<'s> {
+            self.events.push(Event::ConfigSet(res));
+            ::core::future::ready(())
+        }
+        type Future_async_event_ind<'s> = ::core::future::Ready<()>;
+        fn async_event_ind<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>, _async_event: u16) -> Self::Future_async_event_ind<'s> {
+            unreachable!()
+        }
+    }
+    impl ::udi::meta_usb::LddPipe for ::udi::init::RData<Driver> {
+        type Future_intr_bulk_xfer_ack<'s> = ::core::future::Ready<()>;
+        fn intr_bulk_xfer_ack<'s>(&'s self, cb: ::udi::CbRef<'s, ffi::usbdi_intr_bulk_xfer_cb_t>, res: ::udi::Result<()>) -> Self::Future_intr_bulk_xfer_ack<'s> {
+            self.events.push(Event::Bulk(res, buf_data(cb.data_buf())));
+            ::core::future::ready(())
+        }
+        type Future_control_xfer_ack<'s> = ::core::future::Ready<()>;
+        fn control_xfer_ack<'s>(&'s self, cb: ::udi::CbRef<'s, ffi::usbdi_control_xfer_cb_t>, res: ::udi::Result<()>) -> Self::Future_control_xfer_ack<'s> {
+            self.events.push(Event::Control(res, buf_data(cb.data_buf())));
+            ::core::future::ready(())
+        }
+        type Future_isoc_xfer_ack<'s> = ::core::future::Ready<()>;
+        fn isoc_xfer_ack<'s>(&'s self, cb: ::udi::CbRef<'s, ffi::usbdi_isoc_xfer_cb_t>, res: ::udi::Result<()>) -> Self::Future_isoc_xfer_ack<'s> {
+            let frames = cb.frames().iter().map(|f| f.frame_status).collect();
+            self.events.push(Event::Isoc(res, buf_data(cb.data_buf()), frames));
+            ::core::future::ready(())
+        }
+        type Future_pipe_abort_ack<'s> = ::core::future::Ready<()>;
+        fn pipe_abort_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_misc_cb_t>) -> Self::Future_pipe_abort_ack<'s> {
+            unreachable!()
+        }
+        type Future_pipe_state_set_ack<'s> = ::core::future::Ready<()>;
+        fn pipe_state_set_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, res: ::udi::Result<()>) -> Self::Future_pipe_state_set_ack<'s> {
+            self.events.push(Event::StateSet(res));
+            ::core::future::ready(())
+        }
+        type Future_pipe_state_get_ack<'s> = ::core::future::Ready<()>;
+        fn pipe_state_get_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, state: ::udi::Result<PipeState>) -> Self::Future_pipe_state_get_ack<'s> {
+            self.events.push(Event::PipeState(state));
+            ::core::future::ready(())
+        }
+        type Future_edpt_state_set_ack<'s> = ::core::future::Ready<()>;
+        fn edpt_state_set_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, res: ::udi::Result<()>) -> Self::Future_edpt_state_set_ack<'s> {
+            self.events.push(Event::StateSet(res));
+            ::core::future::ready(())
+        }
+        type Future_edpt_state_get_ack<'s> = ::core::future::Ready<()>;
+        fn edpt_state_get_ack<'s>(&'s self, _cb: ::udi::CbRef<'s, ffi::usbdi_state_cb_t>, state: ::udi::Result<PipeState>) -> Self::Future_edpt_state_get_ack<'s> {
+            self.events.push(Event::EdptState(state));
+            ::core::future::ready(())
+        }
+    }
+
+    ::udi_macros::udiprops!("
+meta 1 usbdi
+");
+    ::udi::define_driver! {
+        Driver as INIT_INFO;
+        ops: {
+            Intfc: Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_ldd_intfc_ops_t,
+            Pipe : Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_ldd_pipe_ops_t,
+        },
+        cbs: {
+            Misc    : Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_misc_cb_t,
+            State   : Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_state_cb_t,
+            Desc    : Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_desc_cb_t,
+            IntrBulk: Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_intr_bulk_xfer_cb_t,
+            Control : Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_control_xfer_cb_t,
+            Isoc    : Meta=udiprops::meta::usbdi, ::udi::ffi::meta_usb::usbdi_isoc_xfer_cb_t,
+        }
+    }
+    pub fn module() -> ::udi_environment::DriverModule<'static> {
+        unsafe { ::udi_environment::DriverModule::new(&INIT_INFO, ::udiprops_parse::load_from_raw_section(&udiprops::udiprops)) }
+    }
+    pub const OPS_INTFC: ::udi::ffi::udi_index_t = OpsList::Intfc;
+    pub const OPS_PIPE: ::udi::ffi::udi_index_t = OpsList::Pipe;
+    pub const CB_MISC: ::udi::ffi::udi_index_t = <CbList::Misc as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_STATE: ::udi::ffi::udi_index_t = <CbList::State as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_DESC: ::udi::ffi::udi_index_t = <CbList::Desc as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_INTR_BULK: ::udi::ffi::udi_index_t = <CbList::IntrBulk as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_CONTROL: ::udi::ffi::udi_index_t = <CbList::Control as ::udi::cb::CbDefinition>::INDEX;
+    pub const CB_ISOC: ::udi::ffi::udi_index_t = <CbList::Isoc as ::udi::cb::CbDefinition>::INDEX;
+}
+
+/// An LDD and mock host instance, connected by an interface channel and a pipe for each endpoint
+type Harness = common::Pair<ldd::Driver, host::Driver, Channels>;
+/// LDD ends of the channels to the mock host
+struct Channels {
+    intfc: udi_channel_t,
+    /// Default control pipe
+    control: udi_channel_t,
+    bulk_out: udi_channel_t,
+    bulk_in: udi_channel_t,
+    isoc_in: udi_channel_t,
+}
+fn harness() -> Harness {
+    let mut host_pipes = Vec::new();
+    let rv = Harness::new(ldd::module(), host::module(), |ldd, host| {
+        let connect = |ldd_ops, host_ops| common::connect(ldd, ldd_ops, host, host_ops);
+        let mut pipe = |address| {
+            let (ldd_end, host_end) = connect(ldd::OPS_PIPE, host::OPS_PIPE);
+            host_pipes.push((address, host_end));
+            ldd_end
+        };
+        Channels {
+            intfc: connect(ldd::OPS_INTFC, host::OPS_INTFC).0,
+            control: pipe(0),
+            bulk_out: pipe(host::EP_BULK_OUT),
+            bulk_in: pipe(host::EP_BULK_IN),
+            isoc_in: pipe(host::EP_ISOC_IN),
+        }
+        });
+    // Initialise the region data (see `common::rdata`)
+    ::udi::meta_usb::frame_number_req(rv.alloc(ldd::CB_MISC, rv.channels.intfc));
+    assert_eq!(rv.run(), [Event::FrameNumber(0)]);
+    for (address, host_end) in host_pipes {
+        rv.peer().add_pipe(host_end, address);
+    }
+    rv
+}
+impl Harness {
+    fn open_and_configure(&self) {
+        ::udi::meta_usb::intfc_open_req(self.alloc(ldd::CB_MISC, self.channels.intfc), 0, 0);
+        ::udi::meta_usb::config_set_req(self.alloc(ldd::CB_MISC, self.channels.intfc), host::CONFIG_VALUE as u16);
+        assert_eq!(self.run(), [Event::Opened(Ok(3)), Event::ConfigSet(Ok(()))]);
+    }
+    fn control(&self, req: DeviceRequest) {
+        let mut cb = self.alloc::<ffi::usbdi_control_xfer_cb_t>(ldd::CB_CONTROL, self.channels.control);
+        cb.set_request(&ControlXfer::new(req).short_ok());
+        ::udi::meta_usb::control_xfer_req(cb);
+    }
+    fn bulk(&self, channel: udi_channel_t, xfer: IntrBulkXfer, data: &[u8]) {
+        let mut cb = self.alloc::<ffi::usbdi_intr_bulk_xfer_cb_t>(ldd::CB_INTR_BULK, channel);
+        cb.set_request(&xfer);
+        // SAFE: The buffer pointer is valid (null)
+        unsafe { ::udi_environment::udi_impl::buf::write(&mut cb.get_mut().data_buf, 0..0, data); }
+        ::udi::meta_usb::intr_bulk_xfer_req(cb);
+    }
+    fn state(&self, channel: udi_channel_t) -> ::udi::cb::CbHandle<ffi::usbdi_state_cb_t> {
+        self.alloc(ldd::CB_STATE, channel)
+    }
+}
+
+#[test]
+fn interface() {
+    let h = harness();
+    // Pipes can't be used until the interface is opened and configured
+    h.bulk(h.channels.bulk_out, IntrBulkXfer::write(), &[1]);
+    assert_eq!(h.run(), [Event::Bulk(Err(error(::udi::ffi::UDI_STAT_INVALID_STATE)), vec![1])]);
+
+    ::udi::meta_usb::device_state_get_req(h.state(h.channels.intfc));
+    ::udi::meta_usb::intfc_open_req(h.alloc(ldd::CB_MISC, h.channels.intfc), 1, 0);
+    assert_eq!(h.run(), [Event::DeviceState(DeviceState::NONE), Event::Opened(Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)))]);
+    h.open_and_configure();
+    assert_eq!(h.peer().config(), host::CONFIG_VALUE);
+
+    ::udi::meta_usb::device_state_get_req(h.state(h.channels.intfc));
+    ::udi::meta_usb::device_speed_req(h.alloc(ldd::CB_MISC, h.channels.intfc));
+    ::udi::meta_usb::frame_number_req(h.alloc(ldd::CB_MISC, h.channels.intfc));
+    ::udi::meta_usb::intfc_state_get_req(h.state(h.channels.intfc));
+    assert_eq!(h.run(), [
+        Event::DeviceState(DeviceState::CONFIGURED),
+        Event::Speed(DeviceSpeed::Full),
+        Event::FrameNumber(0),
+        Event::IntfcState(Ok(PipeState::Active)),
+        ]);
+
+    // Descriptors, truncated to the requested length
+    let desc = |req| {
+        let mut cb = h.alloc::<ffi::usbdi_desc_cb_t>(ldd::CB_DESC, h.channels.intfc);
+        cb.set_request(&req);
+        ::udi::meta_usb::desc_req(cb);
+        h.run()
+    };
+    assert_eq!(desc(DescRequest::new(DescriptorType::Device, 0, 64)), [Event::Desc(Ok(()), host::DEVICE_DESCRIPTOR.to_vec())]);
+    assert_eq!(desc(DescRequest::new(DescriptorType::Config, 0, 9)), [Event::Desc(Ok(()), host::CONFIG_DESCRIPTOR[..9].to_vec())]);
+    assert_eq!(desc(DescRequest::new(DescriptorType::String, 1, 255)), [Event::Desc(Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)), vec![])]);
+
+    // Halting every pipe
+    ::udi::meta_usb::intfc_state_set_req(h.state(h.channels.intfc), PipeState::Halted);
+    ::udi::meta_usb::intfc_state_get_req(h.state(h.channels.intfc));
+    ::udi::meta_usb::intfc_close_req(h.alloc(ldd::CB_MISC, h.channels.intfc));
+    assert_eq!(h.run(), [Event::StateSet(Ok(())), Event::IntfcState(Ok(PipeState::Halted)), Event::Closed(Ok(()))]);
+    assert_eq!(h.peer().edpt_state(host::EP_BULK_IN), Some(PipeState::Halted));
+    // The control pipe is not part of the interface
+    assert_eq!(h.peer().edpt_state(0), Some(PipeState::Active));
+}
+
+#[test]
+fn control() {
+    let h = harness();
+    // The default pipe can be used before configuration
+    h.control(DeviceRequest::get_descriptor(DescriptorType::Device, 0, 0, 8));
+    assert_eq!(h.run(), [Event::Control(Ok(()), host::DEVICE_DESCRIPTOR[..8].to_vec())]);
+    h.control(DeviceRequest::set_configuration(host::CONFIG_VALUE));
+    assert_eq!(h.run(), [Event::Control(Ok(()), vec![])]);
+    h.control(DeviceRequest::get_configuration());
+    assert_eq!(h.run(), [Event::Control(Ok(()), vec![host::CONFIG_VALUE])]);
+    h.control(DeviceRequest::set_interface(0, 1));
+    assert_eq!(h.run(), [Event::Control(Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)), vec![])]);
+
+    // The full configuration descriptor parses
+    h.control(DeviceRequest::get_descriptor(DescriptorType::Config, 0, 0, 255));
+    let [Event::Control(Ok(()), data)] = &h.run()[..] else { panic!() };
+    let eps: Vec<_> = ::udi::meta_usb::Descriptors::new(data).interface_endpoints(0, 0).map(|e| e.address).collect();
+    assert_eq!(eps, [host::EP_BULK_OUT, host::EP_BULK_IN, host::EP_ISOC_IN]);
+}
+
+#[test]
+fn bulk_loopback() {
+    let h = harness();
+    h.open_and_configure();
+    h.bulk(h.channels.bulk_out, IntrBulkXfer::write(), b"hello");
+    h.bulk(h.channels.bulk_out, IntrBulkXfer::write(), b" world");
+    assert_eq!(h.run(), [Event::Bulk(Ok(()), b"hello".to_vec()), Event::Bulk(Ok(()), b" world".to_vec())]);
+    h.bulk(h.channels.bulk_in, IntrBulkXfer::read(), &[0; 8]);
+    assert_eq!(h.run(), [Event::Bulk(Ok(()), b"hello wo".to_vec())]);
+    // Not enough data left, only allowed as a short transfer
+    h.bulk(h.channels.bulk_in, IntrBulkXfer::read().short_ok(), &[0; 8]);
+    assert_eq!(h.run(), [Event::Bulk(Ok(()), b"rld".to_vec())]);
+    h.bulk(h.channels.bulk_in, IntrBulkXfer::read(), &[0; 8]);
+    assert_eq!(h.run(), [Event::Bulk(Err(error(::udi::ffi::UDI_STAT_DATA_UNDERRUN)), vec![])]);
+    // Wrong direction for the endpoint
+    h.bulk(h.channels.bulk_in, IntrBulkXfer::write(), &[1]);
+    assert_eq!(h.run(), [Event::Bulk(Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)), vec![1])]);
+}
+
+#[test]
+fn stall() {
+    let h = harness();
+    h.open_and_configure();
+    h.peer().stall(host::EP_BULK_OUT);
+    h.bulk(h.channels.bulk_out, IntrBulkXfer::write(), &[1]);
+    ::udi::meta_usb::pipe_state_get_req(h.state(h.channels.bulk_out));
+    ::udi::meta_usb::intfc_state_get_req(h.state(h.channels.intfc));
+    // Only the device can stall a pipe
+    ::udi::meta_usb::pipe_state_set_req(h.state(h.channels.bulk_in), PipeState::Stalled);
+    assert_eq!(h.run(), [
+        Event::Bulk(Err(error(::udi::ffi::UDI_STAT_INVALID_STATE)), vec![1]),
+        Event::PipeState(Ok(PipeState::Stalled)),
+        Event::IntfcState(Ok(PipeState::Stalled)),
+        Event::StateSet(Err(error(::udi::ffi::UDI_STAT_INVALID_STATE))),
+        ]);
+
+    // Clearing the stall on the endpoint
+    ::udi::meta_usb::edpt_state_set_req(h.state(h.channels.bulk_out), PipeState::Active);
+    ::udi::meta_usb::edpt_state_get_req(h.state(h.channels.bulk_out));
+    h.bulk(h.channels.bulk_out, IntrBulkXfer::write(), &[2]);
+    assert_eq!(h.run(), [
+        Event::StateSet(Ok(())),
+        Event::EdptState(Ok(PipeState::Active)),
+        Event::Bulk(Ok(()), vec![2]),
+        ]);
+
+    // Or with CLEAR_FEATURE(ENDPOINT_HALT) on the control pipe
+    h.peer().stall(host::EP_BULK_OUT);
+    h.control(DeviceRequest::clear_halt(host::EP_BULK_OUT));
+    h.bulk(h.channels.bulk_out, IntrBulkXfer::write(), &[3]);
+    assert_eq!(h.run(), [Event::Control(Ok(()), vec![]), Event::Bulk(Ok(()), vec![3])]);
+}
+
+#[test]
+fn isochronous() {
+    let h = harness();
+    h.open_and_configure();
+    let mut frames = [ffi::usbdi_isoc_frame_request_t { frame_len: 2, frame_status: !0 }; 3];
+    frames[1].frame_len = 1;
+    let mut cb = h.alloc::<ffi::usbdi_isoc_xfer_cb_t>(ldd::CB_ISOC, h.channels.isoc_in);
+    // SAFE: `frames` outlives the request (it's acknowledged within `run`)
+    unsafe {
+        cb.get_mut().frame_array = frames.as_mut_ptr();
+        cb.get_mut().frame_count = frames.len() as u8;
+    }
+    cb.set_request(&IsocXfer::at_frame(Direction::In, 5));
+    ::udi::meta_usb::isoc_xfer_req(cb);
+    assert_eq!(h.run(), [Event::Isoc(Ok(()), vec![5,5, 6, 7,7], vec![::udi::ffi::UDI_OK as _; 3])]);
+    ::udi::meta_usb::frame_number_req(h.alloc(ldd::CB_MISC, h.channels.intfc));
+    assert_eq!(h.run(), [Event::FrameNumber(8)]);
+
+    // The isochronous pipe only takes isochronous transfers
+    h.bulk(h.channels.isoc_in, IntrBulkXfer::read(), &[0]);
+    assert_eq!(h.run(), [Event::Bulk(Err(error(::udi::ffi::UDI_STAT_NOT_SUPPORTED)), vec![0])]);
+}
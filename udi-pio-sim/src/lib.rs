@@ -0,0 +1,450 @@
+//! Host-side simulator for UDI PIO transaction lists
+//!
+//! Runs a `udi_pio_trans_t` list against a user-provided [RegisterSpace], recording every device access.
+//! This allows the exact register sequences of a driver's PIO programs to be checked in plain `#[test]`s.
+//!
+//! ```
+//! ::udi::define_pio_ops!{ PULSE =
+//!     LOAD_IMM.B R0, 1;
+//!     OUT.B 4, R0;
+//!     LOAD_IMM.B R0, 0;
+//!     OUT.B 4, R0;
+//!     END_IMM 0;
+//! }
+//! let mut dev = [0u8; 8];
+//! let out = ::udi_pio_sim::Simulator::new(&PULSE, 8).run(&mut dev, 0).unwrap();
+//! assert_eq!(out.writes().collect::<Vec<_>>(), [(4, &[1][..]), (4, &[0][..])]);
+//! ```
+use ::udi::ffi::pio::udi_pio_trans_t;
+use ::udi::pio::{Op, MemRef, Condition, RepArgs, DecodeError};
+
+mod regval;
+
+use self::regval::RegVal;
+
+/// Maximum number of operations executed before assuming the program is stuck
+pub const MAX_OPERATIONS: usize = 1000;
+
+/// The device register window used by a transaction list
+pub trait RegisterSpace {
+    /// Read from the device at `offset` (relative to the start of the mapping)
+    fn read(&mut self, offset: u32, dst: &mut [u8]);
+    /// Write to the device at `offset` (relative to the start of the mapping)
+    fn write(&mut self, offset: u32, src: &[u8]);
+}
+/// A plain byte array acts as a set of memory-like registers
+impl<const N: usize> RegisterSpace for [u8; N] {
+    fn read(&mut self, offset: u32, dst: &mut [u8]) {
+        dst.copy_from_slice(&self[offset as usize..][..dst.len()]);
+    }
+    fn write(&mut self, offset: u32, src: &[u8]) {
+        self[offset as usize..][..src.len()].copy_from_slice(src);
+    }
+}
+
+/// A memory space accessible to the transaction list (scratch, buffer, or `mem_ptr`)
+pub trait Memory {
+    /// Read `dst.len()` bytes from `offset`, returning `false` if out of range
+    fn read(&self, offset: usize, dst: &mut [u8]) -> bool;
+    /// Write `src` to `offset`, returning `false` if out of range
+    fn write(&mut self, offset: usize, src: &[u8]) -> bool;
+}
+impl<const N: usize> Memory for [u8; N] {
+    fn read(&self, offset: usize, dst: &mut [u8]) -> bool {
+        slice_read(self, offset, dst)
+    }
+    fn write(&mut self, offset: usize, src: &[u8]) -> bool {
+        slice_write(self, offset, src)
+    }
+}
+/// Stand-in for a `udi_buf_t`: reads must be within the current size, writes extend the buffer
+impl Memory for Vec<u8> {
+    fn read(&self, offset: usize, dst: &mut [u8]) -> bool {
+        slice_read(self, offset, dst)
+    }
+    fn write(&mut self, offset: usize, src: &[u8]) -> bool {
+        if self.len() < offset + src.len() {
+            self.resize(offset + src.len(), 0);
+        }
+        slice_write(self, offset, src)
+    }
+}
+fn slice_read(src: &[u8], offset: usize, dst: &mut [u8]) -> bool {
+    match src.get(offset..).and_then(|s| s.get(..dst.len())) {
+    Some(src) => { dst.copy_from_slice(src); true },
+    None => false,
+    }
+}
+fn slice_write(dst: &mut [u8], offset: usize, src: &[u8]) -> bool {
+    match dst.get_mut(offset..).and_then(|s| s.get_mut(..src.len())) {
+    Some(dst) => { dst.copy_from_slice(src); true },
+    None => false,
+    }
+}
+
+/// Translation applied to values sent to the device (from the `udi_pio_map` attributes)
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum DataTranslation {
+    /// Never swap, only byte IO accesses allowed
+    NeverSwap,
+    /// Use big-endian ordering
+    BigEndian,
+    /// Use little-endian ordering
+    LittleEndian,
+}
+
+/// Memory spaces that can be referenced by a transaction list
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Space {
+    /// `UDI_PIO_SCRATCH`
+    Scratch,
+    /// `UDI_PIO_BUF`
+    Buf,
+    /// `UDI_PIO_MEM`
+    Mem,
+}
+
+/// A single device access made by the transaction list
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Access {
+    /// Read from `offset`, returning `data` (in device byte order)
+    Read { offset: u32, data: Vec<u8> },
+    /// Write of `data` (in device byte order) to `offset`
+    Write { offset: u32, data: Vec<u8> },
+}
+
+/// Result of a successful run
+#[derive(Debug)]
+pub struct Outcome {
+    /// Value returned by the `END`/`END_IMM` operation
+    pub result: u16,
+    /// All device accesses, in order
+    pub trace: Vec<Access>,
+}
+impl Outcome {
+    /// Iterate the device writes as `(offset, data)`
+    pub fn writes(&self) -> impl Iterator<Item=(u32, &[u8])> {
+        self.trace.iter().filter_map(|a| match a {
+            Access::Write { offset, data } => Some((*offset, &data[..])),
+            Access::Read { .. } => None,
+            })
+    }
+    /// Iterate the device reads as `(offset, data)`
+    pub fn reads(&self) -> impl Iterator<Item=(u32, &[u8])> {
+        self.trace.iter().filter_map(|a| match a {
+            Access::Read { offset, data } => Some((*offset, &data[..])),
+            Access::Write { .. } => None,
+            })
+    }
+}
+
+/// Errors raised during simulation
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Error {
+    /// The operation at the given index could not be decoded
+    Decode(usize, DecodeError),
+    /// Start label, or a `BRANCH` target, doesn't exist
+    MissingLabel(u16),
+    /// Device access outside of the mapped length
+    DeviceRange { offset: u32, size: u32 },
+    /// Non-byte access to a `NeverSwap` mapping
+    NeverSwap { offset: u32, size: u32 },
+    /// Access to a memory space that wasn't provided
+    MissingSpace(Space),
+    /// Access outside of a memory space
+    MemoryRange { space: Space, offset: usize },
+    /// Shift count larger than the operation size
+    BadShift(usize),
+    /// Execution ran past the end of the list
+    FellOffEnd,
+    /// [MAX_OPERATIONS] was reached without hitting an `END`
+    TooManyOperations,
+}
+
+/// A configured transaction list, ready to run
+pub struct Simulator<'a> {
+    trans_list: &'a [udi_pio_trans_t],
+    length: u32,
+    data_translation: DataTranslation,
+    scratch: Option<&'a mut dyn Memory>,
+    buf: Option<&'a mut dyn Memory>,
+    mem: Option<&'a mut dyn Memory>,
+    verbose: bool,
+}
+impl<'a> Simulator<'a> {
+    /// Prepare to run `trans_list`, mapped with the given `length` (little-endian translation)
+    pub fn new(trans_list: &'a [udi_pio_trans_t], length: u32) -> Self {
+        Simulator {
+            trans_list,
+            length,
+            data_translation: DataTranslation::LittleEndian,
+            scratch: None,
+            buf: None,
+            mem: None,
+            verbose: false,
+        }
+    }
+    /// Set the data translation used for device accesses
+    pub fn data_translation(mut self, v: DataTranslation) -> Self {
+        self.data_translation = v;
+        self
+    }
+    /// Provide the scratch space (`UDI_PIO_SCRATCH`)
+    pub fn scratch(mut self, m: &'a mut dyn Memory) -> Self {
+        self.scratch = Some(m);
+        self
+    }
+    /// Provide the buffer (`UDI_PIO_BUF`), e.g. a `Vec<u8>`
+    pub fn buf(mut self, m: &'a mut dyn Memory) -> Self {
+        self.buf = Some(m);
+        self
+    }
+    /// Provide the `mem_ptr` block (`UDI_PIO_MEM`)
+    pub fn mem(mut self, m: &'a mut dyn Memory) -> Self {
+        self.mem = Some(m);
+        self
+    }
+    /// Print every operation and device access to stdout
+    pub fn verbose(mut self, v: bool) -> Self {
+        self.verbose = v;
+        self
+    }
+
+    /// Run the list from `start_label` against the device
+    pub fn run(&mut self, dev: &mut dyn RegisterSpace, start_label: u8) -> Result<Outcome,Error> {
+        let mut m = Machine {
+            sim: self,
+            dev,
+            registers: Default::default(),
+            trace: Vec::new(),
+        };
+        let result = m.run(start_label)?;
+        Ok(Outcome { result, trace: m.trace })
+    }
+
+    fn find_label(&self, label: u16) -> Result<usize,Error> {
+        if label == 0 {
+            return Ok(0);
+        }
+        self.trans_list.iter()
+            .position(move |op| op.pio_op == ::udi::pio::vals::ops_group_c::LABEL && op.operand == label)
+            .ok_or(Error::MissingLabel(label))
+    }
+}
+
+struct Machine<'s, 'a> {
+    sim: &'s mut Simulator<'a>,
+    dev: &'s mut dyn RegisterSpace,
+    registers: [RegVal; 8],
+    trace: Vec<Access>,
+}
+impl Machine<'_, '_> {
+    fn run(&mut self, start_label: u8) -> Result<u16,Error> {
+        let mut ofs = self.sim.find_label(start_label as u16)?;
+        if self.sim.verbose {
+            println!("pio_trans_inner: Start at +{} of {}", ofs, self.sim.trans_list.len());
+        }
+        for _ in 0 .. MAX_OPERATIONS {
+            let raw = self.sim.trans_list.get(ofs).ok_or(Error::FellOffEnd)?;
+            let op = Op::decode(raw).map_err(|e| Error::Decode(ofs, e))?;
+            if self.sim.verbose {
+                println!("pio_trans_inner: +{} OP 0x{:02x} 0x{:04x}: {}", ofs, raw.pio_op, raw.operand, op);
+            }
+            match op {
+            Op::In { size, dst, reg } => {
+                let val = self.dev_read(reg as u32, size)?;
+                self.write(dst, val, size)?;
+                },
+            Op::Out { size, src, reg } => {
+                let val = self.read(src, size)?;
+                self.dev_write(reg as u32, val, size)?;
+                },
+            Op::Load { size, src, dst } => {
+                let val = self.read(src, size)?;
+                self.write(MemRef::Direct(dst), val, size)?;
+                },
+            Op::Store { size, dst, src } => {
+                let val = self.read(MemRef::Direct(src), size)?;
+                self.write(dst, val, size)?;
+                },
+            Op::LoadImm { size, dst, imm } => self.write(MemRef::Direct(dst), RegVal::from_u16(imm), size)?,
+            Op::CSkip { size, reg, cond } => {
+                let val = self.read(MemRef::Direct(reg), size)?;
+                let skip = match cond {
+                    Condition::Z => val.is_zero(),
+                    Condition::NZ => !val.is_zero(),
+                    Condition::Neg => val.is_neg(size),
+                    Condition::NNeg => !val.is_neg(size),
+                    };
+                if skip {
+                    ofs += 1;
+                }
+                },
+            Op::InInd { size, dst, pio_reg } => {
+                let reg = self.read(MemRef::Direct(pio_reg), size)?.to_u32();
+                let val = self.dev_read(reg, size)?;
+                self.write(MemRef::Direct(dst), val, size)?;
+                },
+            Op::OutInd { size, src, pio_reg } => {
+                let val = self.read(MemRef::Direct(src), size)?;
+                let reg = self.read(MemRef::Direct(pio_reg), size)?.to_u32();
+                self.dev_write(reg, val, size)?;
+                },
+            Op::ShiftLeft { size, reg, bits } | Op::ShiftRight { size, reg, bits } => {
+                if bits > 8 << size {
+                    return Err(Error::BadShift(ofs));
+                }
+                let val = self.read(MemRef::Direct(reg), size)?;
+                let val = if let Op::ShiftLeft { .. } = op { val << bits as u8 } else { val >> bits as u8 };
+                self.write(MemRef::Direct(reg), val, size)?;
+                },
+            Op::AndImm { size, dst, imm } => self.update(dst, size, |v| v & RegVal::from_u16(imm))?,
+            Op::OrImm { size, dst, imm } => self.update(dst, size, |v| v | RegVal::from_u16(imm))?,
+            Op::AddImm { size, dst, imm } => self.update(dst, size, |v| v + RegVal::from_u16_signed(imm))?,
+            Op::And { size, dst, src } => { let r = self.read(MemRef::Direct(src), size)?; self.update(dst, size, |v| v & r)? },
+            Op::Or { size, dst, src } => { let r = self.read(MemRef::Direct(src), size)?; self.update(dst, size, |v| v | r)? },
+            Op::Xor { size, dst, src } => { let r = self.read(MemRef::Direct(src), size)?; self.update(dst, size, |v| v ^ r)? },
+            Op::Add { size, dst, src } => { let r = self.read(MemRef::Direct(src), size)?; self.update(dst, size, |v| v + r)? },
+            Op::Sub { size, dst, src } => { let r = self.read(MemRef::Direct(src), size)?; self.update(dst, size, |v| v - r)? },
+            Op::Label(_) => {},
+            Op::Branch(label) => {
+                ofs = self.sim.find_label(label)?;
+                // Explicitly skip the `ofs += 1`, so this can branch to label 0 (which doesn't have a label instruction)
+                continue ;
+                },
+            Op::RepInInd { size, args } => self.rep(size, &args, false)?,
+            Op::RepOutInd { size, args } => self.rep(size, &args, true)?,
+            Op::Delay(_) | Op::Barrier(_) | Op::Sync(_) | Op::SyncOut(_) | Op::Debug(_) => {},
+            Op::End { size, reg } => return Ok(self.read(MemRef::Direct(reg), size)?.to_u16()),
+            Op::EndImm(v) => return Ok(v),
+            }
+            ofs += 1;
+        }
+        Err(Error::TooManyOperations)
+    }
+
+    fn update(&mut self, reg: u8, size: u8, f: impl FnOnce(RegVal) -> RegVal) -> Result<(),Error> {
+        let val = self.read(MemRef::Direct(reg), size)?;
+        self.write(MemRef::Direct(reg), f(val), size)
+    }
+
+    fn rep(&mut self, size: u8, args: &RepArgs, is_out: bool) -> Result<(),Error> {
+        let mem_reg = MemRef::Direct(args.mem.reg());
+        let mem_stride = RepArgs::stride_distance(args.mem_stride) as u8;
+        let pio_stride = RepArgs::stride_distance(args.pio_stride);
+        let orig_mem_val = self.read(mem_reg, 5)?;
+        let count = self.read(MemRef::Direct(args.count_reg), 1)?.to_u32();
+        let mut reg = self.read(MemRef::Direct(args.pio_reg), 1)?.to_u32();
+        for _ in 0 .. count {
+            if is_out {
+                let val = self.read(args.mem, size)?;
+                self.dev_write(reg, val, size)?;
+            }
+            else {
+                let val = self.dev_read(reg, size)?;
+                self.write(args.mem, val, size)?;
+            }
+            if !matches!(args.mem, MemRef::Direct(_)) {
+                let v = self.read(mem_reg, 5)?;
+                self.write(mem_reg, v + RegVal::from_u8(mem_stride), 5)?;
+            }
+            reg += pio_stride;
+        }
+        // The memory offset register is left unchanged
+        if !matches!(args.mem, MemRef::Direct(_)) {
+            self.write(mem_reg, orig_mem_val, 5)?;
+        }
+        Ok( () )
+    }
+
+    fn little_to_native(val: &mut RegVal, size: u8) -> usize {
+        let len = 1 << size;
+        if cfg!(target_endian = "big") {
+            val.bytes[..len].reverse();
+        }
+        len
+    }
+    fn space(&mut self, loc: MemRef) -> Result<(Space, &mut dyn Memory),Error> {
+        let (space, m) = match loc {
+            MemRef::Direct(_) => unreachable!(),
+            MemRef::Scratch(_) => (Space::Scratch, &mut self.sim.scratch),
+            MemRef::Buf(_) => (Space::Buf, &mut self.sim.buf),
+            MemRef::Mem(_) => (Space::Mem, &mut self.sim.mem),
+            };
+        match m {
+        Some(m) => Ok((space, &mut **m)),
+        None => Err(Error::MissingSpace(space)),
+        }
+    }
+    fn write(&mut self, loc: MemRef, mut val: RegVal, size: u8) -> Result<(),Error> {
+        let reg = &mut self.registers[loc.reg() as usize];
+        if let MemRef::Direct(r) = loc {
+            *reg = val.masked(size);
+            if self.sim.verbose {
+                println!("> R{} = {}", r, reg.display(size));
+            }
+            return Ok( () );
+        }
+        let addr = reg.to_u32() as usize;
+        let len = Self::little_to_native(&mut val, size);
+        let (space, m) = self.space(loc)?;
+        if !m.write(addr, &val.bytes[..len]) {
+            return Err(Error::MemoryRange { space, offset: addr });
+        }
+        Ok( () )
+    }
+    fn read(&mut self, loc: MemRef, size: u8) -> Result<RegVal,Error> {
+        let reg = &self.registers[loc.reg() as usize];
+        if let MemRef::Direct(_) = loc {
+            return Ok(reg.masked(size));
+        }
+        let addr = reg.to_u32() as usize;
+        let mut val = RegVal::default();
+        let (space, m) = self.space(loc)?;
+        if !m.read(addr, &mut val.bytes[..1 << size]) {
+            return Err(Error::MemoryRange { space, offset: addr });
+        }
+        Self::little_to_native(&mut val, size);
+        Ok(val)
+    }
+
+    fn check_dev(&self, reg: u32, size: u8) -> Result<(),Error> {
+        let len = 1u32 << size;
+        if reg.checked_add(len).map_or(true, |end| end > self.sim.length) {
+            return Err(Error::DeviceRange { offset: reg, size: len });
+        }
+        if size != 0 && self.sim.data_translation == DataTranslation::NeverSwap {
+            return Err(Error::NeverSwap { offset: reg, size: len });
+        }
+        Ok( () )
+    }
+    fn dev_read(&mut self, reg: u32, size: u8) -> Result<RegVal,Error> {
+        self.check_dev(reg, size)?;
+        let mut rv = RegVal::default();
+        {
+            let dst = &mut rv.bytes[..1 << size];
+            self.dev.read(reg, dst);
+            self.trace.push(Access::Read { offset: reg, data: dst.to_vec() });
+            if self.sim.data_translation == DataTranslation::BigEndian {
+                dst.reverse();
+            }
+        }
+        if self.sim.verbose {
+            println!("PIO Read {:#x},l={} - {}", reg, 1<<size, rv.display(size));
+        }
+        Ok(rv)
+    }
+    fn dev_write(&mut self, reg: u32, mut val: RegVal, size: u8) -> Result<(),Error> {
+        if self.sim.verbose {
+            println!("PIO Write {:#x}+{} = {}", reg, 1<<size, val.display(size));
+        }
+        self.check_dev(reg, size)?;
+        let src = &mut val.bytes[..1 << size];
+        if self.sim.data_translation == DataTranslation::BigEndian {
+            src.reverse();
+        }
+        self.dev.write(reg, src);
+        self.trace.push(Access::Write { offset: reg, data: src.to_vec() });
+        Ok( () )
+    }
+}
@@ -0,0 +1,187 @@
+//! Register values, with arithmetic across the full register width
+
+/// A PIO register value (up to 32 bytes, stored little-endian)
+#[derive(Default,Copy,Clone)]
+pub struct RegVal {
+    pub bytes: [u8; 32],
+}
+impl RegVal {
+    pub fn from_bytes(v: &[u8]) -> RegVal {
+        let mut bytes = [0; 32];
+        bytes[..v.len()].copy_from_slice(v);
+        RegVal { bytes }
+    }
+    pub fn from_u8(v: u8) -> RegVal {
+        Self::from_bytes(&[v])
+    }
+    pub fn from_u16(v: u16) -> RegVal {
+        Self::from_bytes(&v.to_le_bytes())
+    }
+    pub fn from_u16_signed(v: u16) -> RegVal {
+        let mut rv = Self::from_bytes(&v.to_le_bytes());
+        if rv.bytes[1] & 0x80 != 0 {
+            rv.bytes[2..32].fill(0xFF);
+        }
+        rv
+    }
+    pub fn to_u16(&self) -> u16 {
+        u16::from_le_bytes(self.bytes[..2].try_into().unwrap())
+    }
+    pub fn to_u32(&self) -> u32 {
+        u32::from_le_bytes(self.bytes[..4].try_into().unwrap())
+    }
+    pub fn masked(&self, size: u8) -> RegVal {
+        assert!(size <= 5);
+        let len = 1 << size;
+        Self::from_bytes(&self.bytes[..len])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.bytes.iter().all(|v| *v == 0)
+    }
+    pub fn is_neg(&self, size: u8) -> bool {
+        assert!(size <= 5);
+        let final_byte = (1 << size) - 1;
+        self.bytes[final_byte] & 0x80 != 0
+    }
+    pub fn display(&self, size: u8) -> impl ::core::fmt::Display + '_ {
+        return Display(self, size);
+        struct Display<'a>(&'a RegVal, u8);
+        impl ::core::fmt::Display for Display<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("0x")?;
+                for byte in (0 .. 1 << self.1).rev() {
+                    f.write_fmt(format_args!("{:02x}", self.0.bytes[byte]))?;
+                    if byte != 0 && byte % 4 == 0 {
+                        f.write_str("_")?;
+                    }
+                }
+                Ok( () )
+            }
+        }
+    }
+}
+impl ::core::ops::BitOr for RegVal {
+    type Output = RegVal;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let mut rv = RegVal::default();
+        for (d,(a,b)) in rv.bytes.iter_mut().zip( self.bytes.iter().zip(rhs.bytes.iter()) ) {
+            *d = *a | *b;
+        }
+        rv
+    }
+}
+impl ::core::ops::BitAnd for RegVal {
+    type Output = RegVal;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let mut rv = RegVal::default();
+        for (d,(a,b)) in rv.bytes.iter_mut().zip( self.bytes.iter().zip(rhs.bytes.iter()) ) {
+            *d = *a & *b;
+        }
+        rv
+    }
+}
+impl ::core::ops::BitXor for RegVal {
+    type Output = RegVal;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let mut rv = RegVal::default();
+        for (d,(a,b)) in rv.bytes.iter_mut().zip( self.bytes.iter().zip(rhs.bytes.iter()) ) {
+            *d = *a ^ *b;
+        }
+        rv
+    }
+}
+fn carrying_add(a: u8, b: u8, carry: bool) -> (u8, bool) {
+    let new_carry2;
+    let (mut rv,new_carry) = a.overflowing_add(b);
+    (rv,new_carry2) = rv.overflowing_add(carry as u8);
+    (rv, new_carry | new_carry2)
+
+    //a.carrying_add(b, carry)
+}
+impl ::core::ops::Add for RegVal {
+    type Output = RegVal;
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut rv = RegVal::default();
+        let mut carry = false;
+        for (d,(&a,&b)) in rv.bytes.iter_mut().zip( self.bytes.iter().zip(rhs.bytes.iter()) ) {
+            (*d,carry) = carrying_add(a, b, carry);
+            //(*d,carry) = a.carrying_add(*b, carry);
+        }
+        rv
+    }
+}
+impl ::core::ops::Sub for RegVal {
+    type Output = RegVal;
+    fn sub(self, rhs: Self) -> Self::Output {
+        let mut rv = RegVal::default();
+        let mut carry = true;
+        for (d,(&a,&b)) in rv.bytes.iter_mut().zip( self.bytes.iter().zip(rhs.bytes.iter()) ) {
+            (*d,carry) = carrying_add(a, !b, carry);
+            //(*d,carry) = a.carrying_add(!b, carry);
+        }
+        rv
+    }
+}
+impl ::core::ops::Shl<u8> for RegVal {
+    type Output = RegVal;
+    fn shl(self, rhs: u8) -> Self::Output {
+        let mut rv = RegVal::default();
+        let bytes = rhs / 8;
+        let bits = rhs % 8;
+        // Start at the LSB, since we're shifting left/up
+        let src = [0; 32].iter().copied()
+            .chain(self.bytes.iter().copied())
+            .chain(std::iter::repeat(0))
+            .skip(32 - bytes as usize)
+            .take(self.bytes.len());
+        let mut prev = 0;
+        for (d, v) in rv.bytes.iter_mut().zip( src ) {
+            if bits == 0 {
+                *d = v;
+            }
+            else {
+                *d = (v << bits) | (prev >> (8-bits));
+            }
+            prev = v;
+        }
+        rv
+    }
+}
+impl ::core::ops::Shr<u8> for RegVal {
+    type Output = RegVal;
+    fn shr(self, rhs: u8) -> Self::Output {
+        let mut rv = RegVal::default();
+        let bytes = rhs / 8;
+        let bits = rhs % 8;
+        // Start at the MSB, since we're shifting right/down
+        let src = [0; 32].iter().copied()
+            .chain(self.bytes.iter().copied().rev())
+            .chain([0; 32].iter().copied())
+            .skip(32 - bytes as usize)
+            .take(self.bytes.len())
+            ;
+        let mut prev = 0;
+        for (d, v) in rv.bytes.iter_mut().rev().zip( src ) {
+            if bits == 0 {
+                *d = v;
+            }
+            else {
+                *d = (v >> bits) | (prev >> (8-bits));
+            }
+            prev = v;
+        }
+        return rv;
+    }
+}
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn val_shift() {
+        let v1 = super::RegVal::from_u16(0x1234);
+        assert_eq!((v1 >> 8).to_u32(), 0x12);
+        assert_eq!((v1 << 8).to_u32(), 0x123400);
+    }
+
+}
@@ -0,0 +1,87 @@
+use ::udi_pio_sim::{Simulator, Access, Error, Space, DataTranslation};
+
+::udi::define_pio_ops!{ READ_FIFO =
+    // Read `[mem 0]` bytes from register 0x10 into the buffer
+    LOAD_IMM.B R0, 0;
+    LOAD.B R2, [mem R0];
+    LOAD_IMM.B R1, 0x10;
+    REP_IN_IND.B [buf R0 STEP1], R1, R2;
+    END.B R2;
+}
+
+/// A device that returns an incrementing counter from each read
+#[derive(Default)]
+struct Counter(u8);
+impl ::udi_pio_sim::RegisterSpace for Counter {
+    fn read(&mut self, _offset: u32, dst: &mut [u8]) {
+        for b in dst {
+            self.0 += 1;
+            *b = self.0;
+        }
+    }
+    fn write(&mut self, _offset: u32, _src: &[u8]) {
+    }
+}
+
+#[test]
+fn rep_into_buf() {
+    let mut mem = [3u8];
+    let mut buf = Vec::new();
+    let out = Simulator::new(&READ_FIFO, 0x20)
+        .mem(&mut mem)
+        .buf(&mut buf)
+        .run(&mut Counter::default(), 0)
+        .unwrap();
+    assert_eq!(out.result, 3);
+    assert_eq!(out.reads().collect::<Vec<_>>(), [(0x10, &[1][..]), (0x10, &[2][..]), (0x10, &[3][..])]);
+    assert_eq!(buf, [1,2,3]);
+}
+
+#[test]
+fn missing_space() {
+    let mut buf = Vec::new();
+    let rv = Simulator::new(&READ_FIFO, 0x20)
+        .buf(&mut buf)
+        .run(&mut Counter::default(), 0);
+    assert_eq!(rv.unwrap_err(), Error::MissingSpace(Space::Mem));
+}
+
+#[test]
+fn write_sequence() {
+    ::udi::define_pio_ops!{ INIT =
+        LOAD_IMM.S R0, 0x1234;
+        OUT.S 2, R0;
+        LOAD_IMM.B R1, 4;
+        OUT_IND.B R1, R0;
+        END_IMM 0;
+    }
+    let mut regs = [0u8; 8];
+    let out = Simulator::new(&INIT, 8).run(&mut regs, 0).unwrap();
+    assert_eq!(out.trace, [
+        Access::Write { offset: 2, data: vec![0x34, 0x12] },
+        Access::Write { offset: 4, data: vec![0x34] },
+        ]);
+    assert_eq!(regs, [0, 0, 0x34, 0x12, 0x34, 0, 0, 0]);
+
+    let mut regs = [0u8; 8];
+    let out = Simulator::new(&INIT, 8).data_translation(DataTranslation::BigEndian).run(&mut regs, 0).unwrap();
+    assert_eq!(out.writes().next(), Some((2, &[0x12, 0x34][..])));
+
+    let rv = Simulator::new(&INIT, 4).run(&mut [0u8; 8], 0);
+    assert_eq!(rv.unwrap_err(), Error::DeviceRange { offset: 4, size: 1 });
+}
+
+#[test]
+fn entry_labels() {
+    ::udi::define_pio_ops!{ OPS =
+        END_IMM 1;
+        LABEL 1;
+        CSKIP.B R0 Z;   // Uninitialised registers are zero
+        END_IMM 2;
+        BRANCH 0;
+    }
+    let mut regs = [0u8; 1];
+    assert_eq!(Simulator::new(&OPS, 1).run(&mut regs, 0).unwrap().result, 1);
+    assert_eq!(Simulator::new(&OPS, 1).run(&mut regs, 1).unwrap().result, 1);
+    assert_eq!(Simulator::new(&OPS, 1).run(&mut regs, 2).unwrap_err(), Error::MissingLabel(2));
+}
@@ -129,6 +129,7 @@ pub enum StatusValues
 	UDI_STAT_ATTR_MISMATCH          = 20,
 }
 pub use StatusValues::*;
+pub const UDI_STAT_META_SPECIFIC: udi_status_t = 0x00008000;
 
 extern "C" {
 	pub fn udi_assert(expr: udi_boolean_t);
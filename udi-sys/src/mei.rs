@@ -8,9 +8,11 @@ use super::*;
 /// metalanguage library.
 #[repr(C)]
 pub struct udi_mei_init_t {
+    /// Sequence terminated by `meta_ops_num=0`
     pub ops_vec_template_list: *const udi_mei_ops_vec_template_t,
-    pub mei_enumeration_rank: *const udi_mei_enumeration_rank_func_t,
+    pub mei_enumeration_rank: Option<udi_mei_enumeration_rank_func_t>,
 }
+unsafe impl Sync for udi_mei_init_t {}
 
 #[repr(C)]
 pub struct udi_mei_ops_vec_template_t {
@@ -23,8 +25,19 @@ pub struct udi_mei_ops_vec_template_t {
     /// `relationship`` defines the valid relationships between the regions on
     /// opposite ends of a channel when using an ops vector of this type.
     pub relationship: udi_ubit8_t,
+    /// Sequence terminated by a NULL `op_name`, indexed by `vec_idx - 1`
     pub op_template_list: *const udi_mei_op_template_t,
 }
+unsafe impl Sync for udi_mei_ops_vec_template_t {}
+impl udi_mei_ops_vec_template_t {
+    pub const fn end_of_list() -> Self {
+        udi_mei_ops_vec_template_t {
+            meta_ops_num: udi_index_t(0),
+            relationship: 0,
+            op_template_list: ::core::ptr::null(),
+        }
+    }
+}
 
 pub const UDI_MEI_REL_INITIATOR: udi_ubit8_t = 1<<0;
 pub const UDI_MEI_REL_BIND     : udi_ubit8_t = 1<<1;
@@ -35,18 +48,37 @@ pub const UDI_MEI_REL_SINGLE   : udi_ubit8_t = 1<<4;
 
 #[repr(C)]
 pub struct udi_mei_op_template_t {
-    op_name: *const ::core::ffi::c_char,
-    op_category: udi_ubit8_t,
-    op_flags: udi_ubit8_t,
-    meta_cb_num: udi_index_t,
-    completion_ops_num: udi_index_t,
-    completion_vec_idx: udi_index_t,
-    exception_ops_num: udi_index_t,
-    exception_vec_idx: udi_index_t,
-    direct_stub: *mut udi_mei_direct_stub_t,
-    backend_stub: *mut udi_mei_backend_stub_t,
-    visible_layout: *mut udi_layout_t,
-    marshal_layout: *mut udi_layout_t,
+    pub op_name: *const ::core::ffi::c_char,
+    pub op_category: udi_ubit8_t,
+    pub op_flags: udi_ubit8_t,
+    pub meta_cb_num: udi_index_t,
+    pub completion_ops_num: udi_index_t,
+    pub completion_vec_idx: udi_index_t,
+    pub exception_ops_num: udi_index_t,
+    pub exception_vec_idx: udi_index_t,
+    pub direct_stub: Option<udi_mei_direct_stub_t>,
+    pub backend_stub: Option<udi_mei_backend_stub_t>,
+    pub visible_layout: *const udi_layout_t,
+    pub marshal_layout: *const udi_layout_t,
+}
+unsafe impl Sync for udi_mei_op_template_t {}
+impl udi_mei_op_template_t {
+    pub const fn end_of_list() -> Self {
+        udi_mei_op_template_t {
+            op_name: ::core::ptr::null(),
+            op_category: 0,
+            op_flags: 0,
+            meta_cb_num: udi_index_t(0),
+            completion_ops_num: udi_index_t(0),
+            completion_vec_idx: udi_index_t(0),
+            exception_ops_num: udi_index_t(0),
+            exception_vec_idx: udi_index_t(0),
+            direct_stub: None,
+            backend_stub: None,
+            visible_layout: ::core::ptr::null(),
+            marshal_layout: ::core::ptr::null(),
+        }
+    }
 }
 
 /* Values for op_category */
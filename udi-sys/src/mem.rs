@@ -7,4 +7,5 @@ extern "C" {
 }
 
 pub const UDI_MEM_NOZERO: super::udi_ubit8_t = 1 << 0;
+pub const UDI_MEM_NOWAIT: super::udi_ubit8_t = 1 << 1;
 pub const UDI_MEM_MOVABLE: super::udi_ubit8_t = 1 << 0;
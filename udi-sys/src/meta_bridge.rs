@@ -128,4 +128,9 @@ pub struct udi_intr_event_cb_t
     pub intr_result: u16,
 }
 pub const UDI_INTR_UNCLAIMED: u16 = 1 << 0;
-pub const UDI_INTR_NO_EVENT: u16 = 1 << 1;
\ No newline at end of file
+pub const UDI_INTR_NO_EVENT: u16 = 1 << 1;
+
+// `flags` values for `udi_intr_event_ind`
+pub const UDI_INTR_MASKING_NOT_REQUIRED: u8 = 1 << 0;
+pub const UDI_INTR_OVERRUN_OCCURRED: u8 = 1 << 1;
+pub const UDI_INTR_PREPROCESSED: u8 = 1 << 2;
\ No newline at end of file
@@ -342,7 +342,7 @@ pub struct udi_gfx_client_ops_t {
     pub gfx_get_engine_ack_op: udi_gfx_get_engine_ack_op_t,
     pub gfx_range_connector_ack_op: udi_gfx_range_connector_ack_op_t,
     pub gfx_range_engine_ack_op: udi_gfx_range_engine_ack_op_t,
-    pub gfx_get_engine_operator_ack_op: udi_gfx_get_engine_operator_req_op_t,
+    pub gfx_get_engine_operator_ack_op: udi_gfx_get_engine_operator_ack_op_t,
     pub gfx_connector_command_ack_op: udi_gfx_connector_command_ack_op_t,
     pub gfx_engine_command_ack_op: udi_gfx_engine_command_ack_op_t,
     pub gfx_buffer_info_ack_op: udi_gfx_buffer_info_ack_op_t,
@@ -351,7 +351,7 @@ pub struct udi_gfx_client_ops_t {
     pub gfx_buffer_read_nak_op: udi_gfx_buffer_read_nak_op_t,
     pub gfx_buffer_write_nak_op: udi_gfx_buffer_write_nak_op_t,
 }
-pub const UDI_GFX_CLIENT_OPS_NUM: u8 = 1;
+pub const UDI_GFX_CLIENT_OPS_NUM: u8 = 2;
 
 #[repr(C)]
 /// Contains the operations of a driver binding request
@@ -434,12 +434,12 @@ a!{
 }
 
 #[repr(C)]
-// /// Contains a description of a buffer, or area thereof
+/// Contains a request for the format of a buffer
 pub struct udi_gfx_buffer_info_cb_t {
     pub gcb: udi_cb_t,
     pub buffer_index: udi_ubit32_t,
 }
-//pub const UDI_GFX_BUFFER_INFO_CB_NUM: u8 = 5;
+pub const UDI_GFX_BUFFER_INFO_CB_NUM: u8 = 5;
 a!{
     udi_gfx_buffer_info_req_op_t = fn udi_gfx_buffer_info_req(cb: *mut udi_gfx_buffer_info_cb_t);
     udi_gfx_buffer_info_ack_op_t = fn udi_gfx_buffer_info_ack(
@@ -463,7 +463,7 @@ pub struct udi_gfx_buffer_cb_t {
     pub height: udi_ubit32_t,
     pub buffer: *mut udi_buf_t,
 }
-//pub const UDI_GFX_BUFFER_CB_NUM: u8 = 6;
+pub const UDI_GFX_BUFFER_CB_NUM: u8 = 6;
 a!{
     udi_gfx_buffer_write_req_op_t = fn udi_gfx_buffer_write_req(cb: *mut udi_gfx_buffer_cb_t);
     udi_gfx_buffer_read_req_op_t  = fn udi_gfx_buffer_read_req (cb: *mut udi_gfx_buffer_cb_t);
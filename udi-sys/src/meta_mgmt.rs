@@ -53,6 +53,14 @@ pub struct udi_filter_element_t
 	pub attr_stride: udi_ubit32_t,
 }
 
+// Values for `mgmt_op`
+pub const UDI_DMGMT_PREPARE_TO_SUSPEND: u8 = 1;
+pub const UDI_DMGMT_SUSPEND           : u8 = 2;
+pub const UDI_DMGMT_SHUTDOWN          : u8 = 3;
+pub const UDI_DMGMT_PARENT_SUSPEND    : u8 = 4;
+pub const UDI_DMGMT_RESUME            : u8 = 5;
+pub const UDI_DMGMT_UNBIND            : u8 = 6;
+
 // Values for `enumeration_level`
 pub const UDI_ENUMERATE_START       : u8 = 1;
 pub const UDI_ENUMERATE_START_RESCAN: u8 = 2;
@@ -61,3 +69,13 @@ pub const UDI_ENUMERATE_NEW         : u8 = 4;
 pub const UDI_ENUMERATE_DIRECTED    : u8 = 5;
 pub const UDI_ENUMERATE_RELEASE     : u8 = 6;
 
+
+// Values for `enumeration_result`
+pub const UDI_ENUMERATE_OK          : u8 = 0;
+pub const UDI_ENUMERATE_LEAF        : u8 = 1;
+pub const UDI_ENUMERATE_DONE        : u8 = 2;
+pub const UDI_ENUMERATE_RESCAN      : u8 = 3;
+pub const UDI_ENUMERATE_REMOVED     : u8 = 4;
+pub const UDI_ENUMERATE_REMOVED_SELF: u8 = 5;
+pub const UDI_ENUMERATE_RELEASED    : u8 = 6;
+pub const UDI_ENUMERATE_FAILED      : u8 = 255;
@@ -29,6 +29,13 @@ pub enum MediaType {
 pub use MediaType::*;
 pub const UDI_NIC_MAC_ADDRESS_SIZE: usize = 20;
 
+/* Capability Flags (udi_nic_bind_cb_t.capabilities) */
+pub const UDI_NIC_CAP_TX_IP_CKSUM : udi_ubit32_t = 1 << 0;
+pub const UDI_NIC_CAP_TX_TCP_CKSUM: udi_ubit32_t = 1 << 1;
+pub const UDI_NIC_CAP_TX_UDP_CKSUM: udi_ubit32_t = 1 << 2;
+pub const UDI_NIC_CAP_MCAST_LOOPBK: udi_ubit32_t = 1 << 3;
+pub const UDI_NIC_CAP_BCAST_LOOPBK: udi_ubit32_t = 1 << 4;
+
 #[repr(C)]
 pub struct udi_nic_bind_cb_t
 {
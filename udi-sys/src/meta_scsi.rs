@@ -99,11 +99,11 @@ pub const UDI_SCSI_DATA_IN: udi_ubit16_t   = 1<<0;
 pub const UDI_SCSI_DATA_OUT: udi_ubit16_t  = 1<<1;
 pub const UDI_SCSI_NO_DISCONNECT: udi_ubit16_t = 1<<2;
 /* SCSI Task Attributes */
-pub const UDI_SCSI_SIMPLE_TASK: udi_ubit16_t = 1;
-pub const UDI_SCSI_ORDERED_TASK: udi_ubit16_t = 2;
-pub const UDI_SCSI_HEAD_OF_Q_TASK: udi_ubit16_t = 3;
-pub const UDI_SCSI_ACA_TASK: udi_ubit16_t = 4;
-pub const UDI_SCSI_UNTAGGED_TASK: udi_ubit16_t = 5;
+pub const UDI_SCSI_SIMPLE_TASK: udi_ubit8_t = 1;
+pub const UDI_SCSI_ORDERED_TASK: udi_ubit8_t = 2;
+pub const UDI_SCSI_HEAD_OF_Q_TASK: udi_ubit8_t = 3;
+pub const UDI_SCSI_ACA_TASK: udi_ubit8_t = 4;
+pub const UDI_SCSI_UNTAGGED_TASK: udi_ubit8_t = 5;
 
 extern "C" {
     pub fn udi_scsi_io_req(cb: *mut udi_scsi_io_cb_t);
@@ -120,6 +120,17 @@ pub struct udi_scsi_status_t
     pub scsi_status: udi_ubit8_t,
     pub sense_status: udi_ubit8_t,
 }
+/* SCSI-specific values for `req_status` */
+pub const UDI_SCSI_STAT_ACA_PENDING: udi_status_t           = crate::UDI_STAT_META_SPECIFIC | 1;
+pub const UDI_SCSI_STAT_NONZERO_STATUS_BYTE: udi_status_t   = crate::UDI_STAT_META_SPECIFIC | 2;
+pub const UDI_SCSI_STAT_ABORTED_HD_BUS_RESET: udi_status_t  = crate::UDI_STAT_META_SPECIFIC | 3;
+pub const UDI_SCSI_STAT_ABORTED_RMT_BUS_RESET: udi_status_t = crate::UDI_STAT_META_SPECIFIC | 4;
+pub const UDI_SCSI_STAT_ABORTED_REQ_BUS_RESET: udi_status_t = crate::UDI_STAT_META_SPECIFIC | 5;
+pub const UDI_SCSI_STAT_ABORTED_REQ_TGT_RESET: udi_status_t = crate::UDI_STAT_META_SPECIFIC | 6;
+pub const UDI_SCSI_STAT_LINK_FAILURE: udi_status_t          = crate::UDI_STAT_META_SPECIFIC | 7;
+pub const UDI_SCSI_STAT_SELECTION_TIMEOUT: udi_status_t     = crate::UDI_STAT_META_SPECIFIC | 8;
+pub const UDI_SCSI_STAT_UNEXPECTED_BUS_FREE: udi_status_t   = crate::UDI_STAT_META_SPECIFIC | 9;
+pub const UDI_SCSI_STAT_DEVICE_PHASE_ERROR: udi_status_t    = crate::UDI_STAT_META_SPECIFIC | 10;
 
 // ------ Control Operations ---------
 #[repr(C)]
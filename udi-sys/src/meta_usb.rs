@@ -6,6 +6,10 @@
 use super::*;
 use super::imc::udi_channel_event_ind_op_t;
 
+pub macro metalang_name( $($prefix:ident::)* ) {
+    $($prefix::)*usbdi
+}
+
 macro_rules! define_calls {
     ( $( $(#[$a:meta])* $op_name:ident = fn $name:ident($($a_name:ident: $a_ty:ty),*); )+ ) => {
         extern "C" {
@@ -106,7 +110,7 @@ extern "C" {
 #[repr(C)]
 pub struct usbdi_ldd_intfc_ops_t
 {
-    pub /*udi_channel_*/event_ind_op:   udi_channel_event_ind_op_t,
+    pub channel_event_ind_op:   udi_channel_event_ind_op_t,
     pub /*usbdi_*/bind_ack_op:  usbdi_bind_ack_op_t,
     pub /*usbdi_*/unbind_ack_op:    usbdi_unbind_ack_op_t,
     pub /*usbdi_*/intfc_open_ack_op:    usbdi_intfc_open_ack_op_t,
@@ -127,7 +131,7 @@ pub const USBDI_LDD_INTFC_OPS_NUM: u8 = 1;
 #[repr(C)]
 pub struct usbdi_ldd_pipe_ops_t
 {
-    pub /*udi_channel_*/event_ind_op:   udi_channel_event_ind_op_t,
+    pub channel_event_ind_op:   udi_channel_event_ind_op_t,
     pub /*usbdi_*/intr_bulk_xfer_ack_op: usbdi_intr_bulk_xfer_ack_op_t,
     pub /*usbdi_*/intr_bulk_xfer_nak_op: usbdi_intr_bulk_xfer_nak_op_t,
     pub /*usbdi_*/control_xfer_ack_op: usbdi_control_xfer_ack_op_t,
@@ -144,12 +148,13 @@ pub const USBDI_LDD_PIPE_OPS_NUM: u8 = 2;
 #[repr(C)]
 pub struct usbdi_usbd_intfc_ops_t
 {
-    pub /*udi_channel_*/event_ind_op: udi_channel_event_ind_op_t,
+    pub channel_event_ind_op: udi_channel_event_ind_op_t,
     pub /*usbdi_*/bind_req_op: usbdi_bind_req_op_t,
     pub /*usbdi_*/unbind_req_op: usbdi_unbind_req_op_t,
     pub /*usbdi_*/intfc_open_req_op: usbdi_intfc_open_req_op_t,
     pub /*usbdi_*/intfc_close_req_op: usbdi_intfc_close_req_op_t,
     pub /*usbdi_*/frame_number_req_op: usbdi_frame_number_req_op_t,
+    pub /*usbdi_*/device_speed_req_op: usbdi_device_speed_req_op_t,
     pub /*usbdi_*/reset_device_req_op: usbdi_reset_device_req_op_t,
     pub /*usbdi_*/intfc_abort_req_op: usbdi_intfc_abort_req_op_t,
     pub /*usbdi_*/intfc_state_set_req_op: usbdi_intfc_state_set_req_op_t,
@@ -159,11 +164,11 @@ pub struct usbdi_usbd_intfc_ops_t
     pub /*usbdi_*/config_set_req_op: usbdi_config_set_req_op_t,
     pub /*usbdi_*/async_event_res_op: usbdi_async_event_res_op_t,
 }
-//pub const USBDI_USBD_INTFC_OPS_NUM: u8 = 3;
+pub const USBDI_USBD_INTFC_OPS_NUM: u8 = 3;
 #[repr(C)]
 pub struct usbdi_usbd_pipe_ops_t
 {
-    pub /*udi_channel_*/event_ind_op: udi_channel_event_ind_op_t,
+    pub channel_event_ind_op: udi_channel_event_ind_op_t,
     pub /*usbdi_*/intr_bulk_xfer_req_op: usbdi_intr_bulk_xfer_req_op_t,
     pub /*usbdi_*/control_xfer_req_op: usbdi_control_xfer_req_op_t,
     pub /*usbdi_*/isoc_xfer_req_op: usbdi_isoc_xfer_req_op_t,
@@ -173,7 +178,7 @@ pub struct usbdi_usbd_pipe_ops_t
     pub /*usbdi_*/edpt_state_set_req_op: usbdi_edpt_state_set_req_op_t,
     pub /*usbdi_*/edpt_state_get_req_op: usbdi_edpt_state_get_req_op_t,
 }
-//pub const USBDI_USBD_PIPE_OPS_NUM: u8 = 4;
+pub const USBDI_USBD_PIPE_OPS_NUM: u8 = 4;
 
 
 #[repr(C)]
@@ -231,6 +236,7 @@ pub struct usb_device_request_t
 
 
 #[repr(C)]
+#[derive(Copy,Clone)]
 pub struct usbdi_isoc_frame_request_t
 {
     pub frame_len: udi_ubit32_t,
@@ -248,7 +254,7 @@ pub struct usbdi_isoc_xfer_cb_t
     pub xfer_flags: u8,
     pub frame_number: udi_ubit32_t,
 }
-pub const USBDI_ISOC_XFER_CB_NUM: u8 = 4;   // DUPLICATE! with `USBDI_STATE_CB_NUM`
+pub const USBDI_ISOC_XFER_CB_NUM: u8 = 4;
 
 pub const USBDI_XFER_ASAP: u8 = 1 << 4;
 
@@ -258,13 +264,20 @@ pub struct usbdi_state_cb_t
     pub gcb: udi_cb_t,
     pub state: udi_ubit8_t,
 }
-pub const USBDI_STATE_CB_NUM: u8 = 4;   // DUPLICATE! with `USBDI_ISOC_XFER_CB_NUM`
+// The spec gives this the same number as `USBDI_ISOC_XFER_CB_NUM` (4), use the next free number instead
+pub const USBDI_STATE_CB_NUM: u8 = 6;
 
 pub const USBDI_STATE_ACTIVE : udi_ubit8_t = 1;
 pub const USBDI_STATE_STALLED: udi_ubit8_t = 2;
 pub const USBDI_STATE_IDLE   : udi_ubit8_t = 3;
 pub const USBDI_STATE_HALTED : udi_ubit8_t = 4;
 
+// Device speeds (`device_speed` in `usbdi_device_speed_ack`)
+// - Not listed in the spec text, these follow the order of the speeds in the USB specification
+pub const USBDI_DEVICE_SPEED_LOW : udi_ubit8_t = 1;
+pub const USBDI_DEVICE_SPEED_FULL: udi_ubit8_t = 2;
+pub const USBDI_DEVICE_SPEED_HIGH: udi_ubit8_t = 3;
+
 pub const USBDI_STATE_CONFIGURED: udi_ubit8_t = 1 << 1;
 pub const USBDI_STATE_SUSPENDED: udi_ubit8_t = 1 << 2;
 
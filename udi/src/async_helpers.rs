@@ -59,4 +59,27 @@ where
             crate::async_trickery::signal_waiter(gcb, crate::async_trickery::WaitRes::Pointer(0 as _));
         }
     }
-}
\ No newline at end of file
+}
+/// Run a future using a bare `udi_cb_t` as its context, calling `finally` with the result once it completes
+/// 
+/// Drivers don't need this (the ops wrappers start tasks), it is for environment tests that want to call the
+/// library's async wrappers directly, so it is only available with the `test-support` feature.
+/// 
+/// # Safety
+/// `gcb` must be valid until `finally` is called, and `gcb.scratch` must point to `scratch_size` bytes
+/// (pointer aligned) that are not used for anything else until then.
+#[cfg(feature="test-support")]
+pub unsafe fn spawn_on_gcb<T, R, F>(
+    gcb: *mut crate::ffi::udi_cb_t,
+    scratch_size: usize,
+    task: impl FnOnce(crate::CbRef<'static, crate::ffi::udi_cb_t>) -> T,
+    mut finally: F
+)
+where
+    T: 'static + ::core::future::Future<Output=R>,
+    R: 'static,
+    F: 'static + FnMut(R),
+{
+    let task = task(crate::CbRef::new(gcb));
+    crate::async_trickery::start_task(gcb, scratch_size, task, move |_, res| finally(res));
+}
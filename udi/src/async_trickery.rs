@@ -37,7 +37,7 @@ pub(crate) enum WaitRes {
 /// Initialise a task
 /// 
 /// SAFETY: Caller must ensure that `cb`'s `scratch` is valid for this task (correct size, not yet initialised)
-pub(crate) unsafe fn init_task<Cb, T, R, F>(cb: *mut Cb, inner: T, finally: F)
+pub unsafe fn init_task<Cb, T, R, F>(cb: *mut Cb, inner: T, finally: F)
 where
 	Cb: GetCb,
 	T: 'static + Future<Output=R>,
@@ -47,6 +47,22 @@ where
 	::core::ptr::write((*cb).get_gcb().scratch as *mut _, Task::<Cb,T,R,F>::new(inner, finally));
 	// NOTE: Can't run here, as that makes miri unhappy (if the task doesn't yield, and the drop happens in here)
 }
+/// Initialise and start a task on a bare GCB, checking that the scratch space can hold it
+/// 
+/// SAFETY: `gcb.scratch` must point to at least `scratch_size` bytes (aligned for a pointer) that are unused by anything else
+#[cfg(feature="test-support")]
+pub(crate) unsafe fn start_task<T, R, F>(gcb: *mut udi_cb_t, scratch_size: usize, inner: T, finally: F)
+where
+	T: 'static + Future<Output=R>,
+	R: 'static,
+	F: 'static + FnMut(*mut udi_cb_t, R),
+{
+	let size = ::core::mem::size_of::<Task<udi_cb_t,T,R,F>>();
+	assert!(size <= scratch_size, "Task ({} bytes) doesn't fit in scratch ({} bytes)", size, scratch_size);
+	assert!(((*gcb).scratch as *const Task<udi_cb_t,T,R,F>).is_aligned(), "Scratch is misaligned");
+	init_task(gcb, inner, finally);
+	run(gcb);
+}
 /// Get the size of the task state (for scratch) for a given async state structure
 pub(crate) const fn task_size<T: 'static>() -> usize {
 	::core::mem::size_of::<Task<udi_cb_t,T,(),()>>()
@@ -64,7 +80,7 @@ pub(crate) unsafe fn abort_task(cb: *mut udi_cb_t)
 /// Obtain a pointer to the driver instance from a cb
 /// 
 /// SAFETY: Caller must ensure that `T` is valid for the context paraneter of the Cb
-pub(crate) unsafe fn get_rdata_t<T: CbContext, Cb: GetCb>(cb: &Cb) -> &T {
+pub unsafe fn get_rdata_t<T: CbContext, Cb: GetCb>(cb: &Cb) -> &T {
 	let rv_raw = cb.get_gcb().context as *mut T;
 	if !(*rv_raw).is_init() {
 		(*rv_raw).maybe_init();
@@ -291,7 +307,7 @@ where
 
 /// Obtain the GCB (`udi_cb_t`) from a waker
 fn gcb_from_waker_raw(waker: &::core::task::Waker) -> *const udi_cb_t {
-	let raw_waker = waker.as_raw();
+	let raw_waker = waker;
 	let have_vt = raw_waker.vtable();
 	if have_vt as *const _ != &VTABLE_CB_T as *const _ {
 		panic!("Unexpected context used!");
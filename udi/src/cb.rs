@@ -147,6 +147,39 @@ where
 		)
 }
 
+/// Allocate a new control block with `inline_size` bytes of inline data (described by `inline_layout`, which must
+/// be terminated by `UDI_DL_END`)
+///
+/// # Safety
+/// `default_channel` must be a valid channel (or null), and `inline_layout` must describe the `inline_size` bytes
+/// of inline data (the environment uses it to copy the data between regions)
+pub unsafe fn alloc_dynamic<CbDef>(
+	cb: crate::CbRef<crate::ffi::udi_cb_t>,
+	default_channel: udi_channel_t,
+	inline_size: usize,
+	inline_layout: &'static [crate::ffi::udi_layout_t],
+	) -> impl ::core::future::Future<Output=CbHandle<CbDef::Cb>>
+where
+	CbDef: CbDefinition,
+	CbDef::Cb: crate::async_trickery::GetCb
+{
+	unsafe extern "C" fn callback(gcb: *mut crate::ffi::udi_cb_t, new_cb: *mut crate::ffi::udi_cb_t) {
+		unsafe { crate::async_trickery::signal_waiter(gcb, crate::WaitRes::Pointer(new_cb as *mut ())); }
+	}
+	assert!(inline_layout.last() == Some(&crate::ffi::layout::UDI_DL_END), "`inline_layout` must end with UDI_DL_END");
+	crate::async_trickery::wait_task::<crate::ffi::udi_cb_t, _,_,_>(
+		cb,
+		move |cb| unsafe {
+			crate::ffi::cb::udi_cb_alloc_dynamic(callback, cb as *const _ as *mut _, CbDef::INDEX, default_channel,
+				inline_size, inline_layout.as_ptr())
+			},
+		|res| {
+			let crate::WaitRes::Pointer(p) = res else { panic!(""); };
+			CbHandle(p as *mut _)
+			}
+		)
+}
+
 /// Allocate a collection of CBs
 pub fn alloc_batch<CbDef>(
 	cb: crate::CbRef<crate::ffi::udi_cb_t>,
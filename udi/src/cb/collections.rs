@@ -54,6 +54,26 @@ where
             self.tail.set(tail);
         }
     }
+    /// Push a (potentially chained) CB onto the front of the queue, keeping the order of the chain
+    pub fn push_front(&self, cb: CbHandle<T>) {
+        let cb = cb.into_raw();
+        // SAFE: Trusting the `chain` on incoming cbs to be a valid single-linked list
+        unsafe {
+            let mut tail = cb;
+            loop {
+                let s = get_chain_slot(&mut *tail);
+                if s.is_null() {
+                    break;
+                }
+                tail = *s;
+            }
+            *get_chain_slot(&mut *tail) = self.head.get();
+            if self.head.get().is_null() {
+                self.tail.set(tail);
+            }
+        }
+        self.head.set(cb);
+    }
     /// Pop a single CB from the front of the queue
     pub fn pop_front(&self) -> Option< CbHandle<T> > {
         let rv = self.head.get();
@@ -72,6 +92,22 @@ where
             }
         }
     }
+    /// Remove all CBs from the queue, returning them as a chain (in queue order)
+    pub fn take_all(&self) -> Option< CbHandle<T> > {
+        let rv = self.head.replace(::core::ptr::null_mut());
+        self.tail.set(::core::ptr::null_mut());
+        if rv.is_null() {
+            None
+        }
+        else {
+            // SAFE: The chain is a valid singularly-linked list of owned pointers
+            Some( unsafe { CbHandle::from_raw(rv) } )
+        }
+    }
+    /// Test if the queue is currently empty
+    pub fn is_empty(&self) -> bool {
+        self.head.get().is_null()
+    }
 }
 
 /// A chain of CBs, as returned by [super::alloc_batch]
@@ -5,7 +5,7 @@ use ::udi_sys as ffi;
 pub type Result<T> = ::core::result::Result<T,Error>;
 
 /// A wrapper around `udi_status_t` that cannot be `UDI_OK`
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,PartialEq,Eq)]
 pub struct Error(::core::num::NonZeroU32);
 impl Error {
 	/// Get the `udi_status_t` value
@@ -31,6 +31,12 @@ impl ChannelHandle {
     pub fn raw(&self) -> ::udi_sys::udi_channel_t{
         self.0
     }
+    /// Release ownership of the raw UDI channel handle (e.g. when the environment or peer will close it)
+    pub fn into_raw(self) -> ::udi_sys::udi_channel_t {
+        let rv = self.0;
+        ::core::mem::forget(self);
+        rv
+    }
 }
 
 unsafe impl crate::async_trickery::GetCb for udi_channel_event_cb_t {
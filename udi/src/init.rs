@@ -49,6 +49,7 @@ pub trait Driver: 'static + crate::async_trickery::CbContext {
 }
 
 /// Enumeration operation to perform in [Driver::enumerate_req]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
 pub enum EnumerateLevel
 {
 	/// Start a fresh round of enumeration
@@ -57,14 +58,52 @@ pub enum EnumerateLevel
 	StartRescan,
 	/// Get the next device in the current round of enumeration
 	Next,
-	/// Start enumerating any devices that weren't present last time enumeration was run.
+	/// Report the next child that has been added or removed since it was last enumerated
+	///
+	/// The driver should hold this request until there is something to report (see [ChildTracker::wait_event]), and
+	/// reply with [EnumerateResult::Ok] for a new child, or [EnumerateResult::Removed] for a removed one. Drivers
+	/// that cannot detect hot-plug should reply with [EnumerateResult::Failed].
 	New,
-	/// Create a new child with the attributes already in the list - used for external configuration
+	/// Create a new child with the attributes already in the list (see [AttrSink::directed_attrs]) - used for
+	/// external configuration. Reply with [EnumerateResult::Ok], or [EnumerateResult::Failed] if the child cannot
+	/// be created.
 	Directed,
-	/// Release resources associated with a child (TODO: Unimplemented - cannot be represented)
-	Release,
+	/// Release resources associated with a child that was reported with [EnumerateResult::Removed]
+	///
+	/// The child ID can be re-used once this has been acknowledged with [EnumerateResult::Released]
+	Release(crate::ffi::udi_ubit32_t),
+}
+impl EnumerateLevel {
+	/// Convert from a `UDI_ENUMERATE_*` level, using `child_id` from the CB for [EnumerateLevel::Release]
+	pub fn from_raw(level: crate::ffi::udi_ubit8_t, child_id: crate::ffi::udi_ubit32_t) -> Option<Self> {
+		use crate::ffi::meta_mgmt::*;
+		Some(match level
+		{
+		UDI_ENUMERATE_START => EnumerateLevel::Start,
+		UDI_ENUMERATE_START_RESCAN => EnumerateLevel::StartRescan,
+		UDI_ENUMERATE_NEXT => EnumerateLevel::Next,
+		UDI_ENUMERATE_NEW => EnumerateLevel::New,
+		UDI_ENUMERATE_DIRECTED => EnumerateLevel::Directed,
+		UDI_ENUMERATE_RELEASE => EnumerateLevel::Release(child_id),
+		_ => return None,
+		})
+	}
+	/// Get the `UDI_ENUMERATE_*` level value (the child ID for [EnumerateLevel::Release] is passed in the CB)
+	pub fn to_raw(&self) -> crate::ffi::udi_ubit8_t {
+		use crate::ffi::meta_mgmt::*;
+		match self
+		{
+		EnumerateLevel::Start => UDI_ENUMERATE_START,
+		EnumerateLevel::StartRescan => UDI_ENUMERATE_START_RESCAN,
+		EnumerateLevel::Next => UDI_ENUMERATE_NEXT,
+		EnumerateLevel::New => UDI_ENUMERATE_NEW,
+		EnumerateLevel::Directed => UDI_ENUMERATE_DIRECTED,
+		EnumerateLevel::Release(_) => UDI_ENUMERATE_RELEASE,
+		}
+	}
 }
 /// Successful enumeration of a child
+#[derive(Debug,PartialEq,Eq)]
 pub struct EnumerateResultOk {
 	ops_idx: crate::ffi::udi_index_t,
 	child_id: crate::ffi::udi_ubit32_t,
@@ -94,6 +133,7 @@ impl EnumerateResultOk {
 	}
 }
 /// Result of [Driver::enumerate_req]
+#[derive(Debug,PartialEq,Eq)]
 pub enum EnumerateResult
 {
 	/// Successfully enumerated a child
@@ -108,8 +148,9 @@ pub enum EnumerateResult
 	///
 	/// The child ID must be kept unused/valid until a [EnumerateLevel::Release] call
     Removed(crate::ffi::udi_ubit32_t),
-	/// ?This device has been removed, and all children are now invalid
-	// "Treated like `UDI_ENUMERATE_REMOVED`, except that this indicates that the enumerating device itself has been removed."
+	/// (Only valid for [EnumerateLevel::New]) This device has been removed, and all children are now invalid
+	///
+	/// Treated like [EnumerateResult::Removed], except that it is the enumerating device itself that has been removed.
     RemovedSelf,
 	/// (Only valid for [EnumerateLevel::Release]) Confirms that the child ID has been released/deallocated
     Released,
@@ -121,6 +162,38 @@ impl EnumerateResult {
 	pub fn ok<Ops: crate::ops_wrapper_markers::ChildBind>(child_id: crate::ffi::udi_ubit32_t) -> Self {
 		EnumerateResult::Ok(EnumerateResultOk::new::<Ops>(child_id))
 	}
+	/// Convert from the arguments of `udi_enumerate_ack`, using `child_id` from the CB
+	pub fn from_raw(result: crate::ffi::udi_ubit8_t, ops_idx: crate::ffi::udi_index_t, child_id: crate::ffi::udi_ubit32_t) -> Option<Self> {
+		use crate::ffi::meta_mgmt::*;
+		Some(match result
+		{
+		UDI_ENUMERATE_OK => EnumerateResult::Ok(EnumerateResultOk { ops_idx, child_id }),
+		UDI_ENUMERATE_LEAF => EnumerateResult::Leaf,
+		UDI_ENUMERATE_DONE => EnumerateResult::Done,
+		UDI_ENUMERATE_RESCAN => EnumerateResult::Rescan,
+		UDI_ENUMERATE_REMOVED => EnumerateResult::Removed(child_id),
+		UDI_ENUMERATE_REMOVED_SELF => EnumerateResult::RemovedSelf,
+		UDI_ENUMERATE_RELEASED => EnumerateResult::Released,
+		UDI_ENUMERATE_FAILED => EnumerateResult::Failed,
+		_ => return None,
+		})
+	}
+	/// Get the `enumeration_result` and `ops_idx` for `udi_enumerate_ack`, and the child ID to store in the CB (if any)
+	pub fn to_raw(&self) -> (crate::ffi::udi_ubit8_t, crate::ffi::udi_index_t, Option<crate::ffi::udi_ubit32_t>) {
+		use crate::ffi::meta_mgmt::*;
+		let none = crate::ffi::udi_index_t(0);
+		match *self
+		{
+		EnumerateResult::Ok(ref res) => (UDI_ENUMERATE_OK, res.ops_idx, Some(res.child_id)),
+		EnumerateResult::Leaf => (UDI_ENUMERATE_LEAF, none, None),
+		EnumerateResult::Done => (UDI_ENUMERATE_DONE, none, None),
+		EnumerateResult::Rescan => (UDI_ENUMERATE_RESCAN, none, None),
+		EnumerateResult::Removed(child_id) => (UDI_ENUMERATE_REMOVED, none, Some(child_id)),
+		EnumerateResult::RemovedSelf => (UDI_ENUMERATE_REMOVED_SELF, none, None),
+		EnumerateResult::Released => (UDI_ENUMERATE_RELEASED, none, None),
+		EnumerateResult::Failed => (UDI_ENUMERATE_FAILED, none, None),
+		}
+	}
 }
 impl From<EnumerateResultOk> for EnumerateResult {
 	fn from(value: EnumerateResultOk) -> Self {
@@ -128,14 +201,59 @@ impl From<EnumerateResultOk> for EnumerateResult {
 	}
 }
 /// A place to store attributes, limited to [Driver::MAX_ATTRS]
+///
+/// For [EnumerateLevel::Directed], this also holds the attributes supplied for the new child. These are overwritten
+/// as new attributes are pushed, unless [AttrSink::keep_directed] is called first.
 pub struct AttrSink<'a>
 {
+	base: *mut crate::ffi::attr::udi_instance_attr_list_t,
 	dst: *mut crate::ffi::attr::udi_instance_attr_list_t,
 	remaining_space: usize,
+	/// Number of supplied attributes (from `base`) that are still intact
+	directed: usize,
 	pd: ::core::marker::PhantomData<&'a mut crate::ffi::attr::udi_instance_attr_list_t>,
 }
 impl<'a> AttrSink<'a>
 {
+	/// Attributes supplied with an [EnumerateLevel::Directed] request (empty for other requests)
+	pub fn directed_attrs(&self) -> &[crate::ffi::attr::udi_instance_attr_list_t] {
+		// SAFE: `directed` only covers entries that are within the list, and not yet overwritten
+		unsafe { ::core::slice::from_raw_parts(self.base, self.directed) }
+	}
+	/// Look up a supplied attribute by name
+	pub fn directed_attr(&self, name: &str) -> Option<&crate::ffi::attr::udi_instance_attr_list_t> {
+		self.directed_attrs().iter().find(|a| {
+			let len = a.attr_name.iter().position(|&c| c == 0).unwrap_or(a.attr_name.len());
+			&a.attr_name[..len] == name.as_bytes()
+		})
+	}
+	/// Look up a supplied `u32` attribute
+	pub fn directed_u32(&self, name: &str) -> Option<u32> {
+		let a = self.directed_attr(name)?;
+		if a.attr_type != crate::ffi::attr::UDI_ATTR_UBIT32 as _ || a.attr_length != 4 {
+			return None;
+		}
+		Some(u32::from_ne_bytes(a.attr_value[..4].try_into().unwrap()))
+	}
+	/// Look up a supplied string attribute
+	pub fn directed_string(&self, name: &str) -> Option<&str> {
+		let a = self.directed_attr(name)?;
+		if a.attr_type != crate::ffi::attr::UDI_ATTR_STRING as _ {
+			return None;
+		}
+		::core::str::from_utf8(a.attr_value.get(..a.attr_length as usize)?).ok()
+	}
+	/// Keep the supplied attributes in the result, so pushed attributes are added after them
+	///
+	/// Has no effect if attributes have already been pushed
+	pub fn keep_directed(&mut self) {
+		if self.dst == self.base {
+			// SAFE: `directed` is within the list
+			self.dst = unsafe { self.dst.add(self.directed) };
+			self.remaining_space -= self.directed;
+		}
+	}
+
 	fn get_entry(&mut self) -> Option<&mut crate::ffi::attr::udi_instance_attr_list_t> {
 		if self.remaining_space == 0 {
 			None
@@ -145,6 +263,8 @@ impl<'a> AttrSink<'a>
 			// SAFE: This type controls the `*mut` as a unique borrow, pointer is in-range
 			unsafe {
 				let rv = self.dst;
+				// This entry is about to be overwritten, so is no longer a supplied attribute
+				self.directed = usize::min(self.directed, rv.offset_from(self.base) as usize);
 				self.dst = self.dst.offset(1);
 				Some(&mut *rv)
 			}
@@ -199,6 +319,181 @@ impl<'a> AttrSink<'a>
 	}
 }
 
+/// A change to report for [EnumerateLevel::New], from [ChildTracker]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ChildEvent
+{
+	/// A child has been added, and should be reported with [EnumerateResult::Ok]
+	Added(crate::ffi::udi_ubit32_t),
+	/// A child has been removed, and should be reported with [EnumerateResult::Removed]
+	Removed(crate::ffi::udi_ubit32_t),
+}
+impl ChildEvent {
+	/// Get the child ID
+	pub fn child_id(&self) -> crate::ffi::udi_ubit32_t {
+		match *self
+		{
+		ChildEvent::Added(child_id) => child_id,
+		ChildEvent::Removed(child_id) => child_id,
+		}
+	}
+}
+#[derive(Copy,Clone,PartialEq)]
+enum ChildSlot
+{
+	Free,
+	/// Added, but not yet reported
+	Added(crate::ffi::udi_ubit32_t),
+	/// Reported to the MA
+	Active(crate::ffi::udi_ubit32_t),
+	/// Removed, but not yet reported
+	Removed(crate::ffi::udi_ubit32_t),
+	/// Removal reported, waiting for [EnumerateLevel::Release]
+	Releasing(crate::ffi::udi_ubit32_t),
+}
+impl ChildSlot {
+	fn child_id(&self) -> Option<crate::ffi::udi_ubit32_t> {
+		match *self
+		{
+		ChildSlot::Free => None,
+		ChildSlot::Added(v)|ChildSlot::Active(v)|ChildSlot::Removed(v)|ChildSlot::Releasing(v) => Some(v),
+		}
+	}
+}
+/// Tracking of child IDs for hot-plug, with space for `N` children
+///
+/// Children reported by the `Start`/`Next` enumeration are recorded with [ChildTracker::enumerated]. Children that
+/// appear or disappear later are passed to [ChildTracker::add] and [ChildTracker::remove], which wakes the
+/// [EnumerateLevel::New] request waiting in [ChildTracker::wait_event]. A removed child ID stays in use until
+/// [ChildTracker::release] is called for [EnumerateLevel::Release].
+pub struct ChildTracker<const N: usize>
+{
+	slots: [::core::cell::Cell<ChildSlot>; N],
+	waiter: ::core::cell::Cell<*mut crate::ffi::udi_cb_t>,
+}
+impl<const N: usize> Default for ChildTracker<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<const N: usize> ChildTracker<N>
+{
+	/// Create an empty tracker
+	pub const fn new() -> Self {
+		ChildTracker {
+			slots: [const { ::core::cell::Cell::new(ChildSlot::Free) }; N],
+			waiter: ::core::cell::Cell::new(::core::ptr::null_mut()),
+		}
+	}
+
+	fn find(&self, child_id: crate::ffi::udi_ubit32_t) -> Option<&::core::cell::Cell<ChildSlot>> {
+		self.slots.iter().find(|s| s.get().child_id() == Some(child_id))
+	}
+	fn insert(&self, slot: ChildSlot) -> bool {
+		match self.slots.iter().find(|s| s.get() == ChildSlot::Free)
+		{
+		Some(s) => { s.set(slot); true },
+		None => false,
+		}
+	}
+	fn wake(&self) {
+		let gcb = self.waiter.replace(::core::ptr::null_mut());
+		if !gcb.is_null() {
+			// SAFE: The waiter is only set while the `New` request is waiting
+			unsafe {
+				crate::async_trickery::signal_waiter(gcb, crate::async_trickery::WaitRes::Pointer(::core::ptr::null_mut()));
+			}
+		}
+	}
+
+	/// Record a child that was reported by `Start`/`Next` enumeration
+	///
+	/// Returns `false` if there is no space, or the ID is still waiting to be released
+	pub fn enumerated(&self, child_id: crate::ffi::udi_ubit32_t) -> bool {
+		match self.find(child_id)
+		{
+		Some(s) => match s.get()
+			{
+			ChildSlot::Added(_)|ChildSlot::Active(_) => { s.set(ChildSlot::Active(child_id)); true },
+			_ => false,
+			},
+		None => self.insert(ChildSlot::Active(child_id)),
+		}
+	}
+	/// Add a new child, to be reported by [EnumerateLevel::New]
+	///
+	/// Returns `false` if there is no space, or the ID is already in use
+	pub fn add(&self, child_id: crate::ffi::udi_ubit32_t) -> bool {
+		if self.find(child_id).is_some() || !self.insert(ChildSlot::Added(child_id)) {
+			return false;
+		}
+		self.wake();
+		true
+	}
+	/// Remove a child (i.e. it has been hot-unplugged), to be reported by [EnumerateLevel::New]
+	///
+	/// Returns `false` if the child is not present
+	pub fn remove(&self, child_id: crate::ffi::udi_ubit32_t) -> bool {
+		let Some(s) = self.find(child_id) else {
+			return false;
+		};
+		match s.get()
+		{
+		// Never reported, so can just be forgotten
+		ChildSlot::Added(_) => s.set(ChildSlot::Free),
+		ChildSlot::Active(_) => {
+			s.set(ChildSlot::Removed(child_id));
+			self.wake();
+			},
+		_ => return false,
+		}
+		true
+	}
+	/// Handle [EnumerateLevel::Release], freeing the child ID
+	///
+	/// Returns `false` if the child's removal has not been reported
+	pub fn release(&self, child_id: crate::ffi::udi_ubit32_t) -> bool {
+		match self.find(child_id)
+		{
+		Some(s) if s.get() == ChildSlot::Releasing(child_id) => { s.set(ChildSlot::Free); true },
+		_ => false,
+		}
+	}
+	/// Check if a child is present (added, and not removed)
+	pub fn is_present(&self, child_id: crate::ffi::udi_ubit32_t) -> bool {
+		matches!(self.find(child_id).map(|s| s.get()), Some(ChildSlot::Added(_)|ChildSlot::Active(_)))
+	}
+	/// Iterate the IDs of children that have been reported to the MA and not removed
+	pub fn active(&self) -> impl Iterator<Item=crate::ffi::udi_ubit32_t> + '_ {
+		self.slots.iter().filter_map(|s| match s.get() { ChildSlot::Active(v) => Some(v), _ => None })
+	}
+
+	/// Take the next change to report, if there is one
+	pub fn take_event(&self) -> Option<ChildEvent> {
+		for s in &self.slots {
+			match s.get()
+			{
+			ChildSlot::Added(v) => { s.set(ChildSlot::Active(v)); return Some(ChildEvent::Added(v)) },
+			ChildSlot::Removed(v) => { s.set(ChildSlot::Releasing(v)); return Some(ChildEvent::Removed(v)) },
+			_ => {},
+			}
+		}
+		None
+	}
+	/// Wait for the next change to report, for use in [EnumerateLevel::New]
+	///
+	/// Only one request can be waiting at a time
+	pub async fn wait_event(&self, cb: CbRefEnumerate<'_>) -> ChildEvent {
+		loop {
+			if let Some(ev) = self.take_event() {
+				return ev;
+			}
+			let waiter = &self.waiter;
+			crate::async_trickery::wait_task(cb, move |gcb| waiter.set(gcb), |_| ()).await;
+		}
+	}
+}
+
 /// Operations for [Driver::devmgmt_req]
 pub enum MgmtOp
 {
@@ -300,43 +595,27 @@ impl<Driver> ::core::ops::DerefMut for RData<Driver>
 // - Probably in `context`, as `scratch` is limited and not always available :(
 // - But, what are the rules for `context` being updated?
 future_wrapper!{enumerate_req_op => <T as Driver>(cb: *mut udi_enumerate_cb_t, enumeration_level: u8) val @ {
-	let attrs = AttrSink {
+	let mut attrs = AttrSink {
+		base: cb.attr_list,
 		dst: cb.attr_list,
 		remaining_space: T::MAX_ATTRS as usize,
+		directed: 0,
 		pd: ::core::marker::PhantomData,
 		};
-	let enumeration_level = match enumeration_level
-		{
-		crate::ffi::meta_mgmt::UDI_ENUMERATE_START => EnumerateLevel::Start,
-		crate::ffi::meta_mgmt::UDI_ENUMERATE_START_RESCAN => EnumerateLevel::StartRescan,
-		crate::ffi::meta_mgmt::UDI_ENUMERATE_NEXT => EnumerateLevel::Next,
-		crate::ffi::meta_mgmt::UDI_ENUMERATE_NEW => EnumerateLevel::New,
-		crate::ffi::meta_mgmt::UDI_ENUMERATE_DIRECTED => EnumerateLevel::Directed,
-		crate::ffi::meta_mgmt::UDI_ENUMERATE_RELEASE => EnumerateLevel::Release,
-		_ => todo!(),
-		};
+	let Some(enumeration_level) = EnumerateLevel::from_raw(enumeration_level, cb.child_id) else {
+		panic!("Unexpected value for `enumeration_level`: {}", enumeration_level);
+	};
+	if enumeration_level == EnumerateLevel::Directed {
+		attrs.directed = usize::min(cb.attr_valid_length as usize, attrs.remaining_space);
+	}
 	val.enumerate_req(cb, enumeration_level, attrs)
 } finally( (res,attrs) ) {
 	// Return this CB to the pool on completion
 	unsafe {
-		use ffi::udi_index_t;
-		let (res,ops_idx) = match res
-			{
-			EnumerateResult::Ok(res) => {
-				(*cb).child_id = res.child_id;
-				(0,res.ops_idx)
-				},
-			EnumerateResult::Leaf => (1,udi_index_t(0)),
-			EnumerateResult::Done => (2,udi_index_t(0)),
-			EnumerateResult::Rescan => (3,udi_index_t(0)),
-			EnumerateResult::Removed(child) => {
-				(*cb).child_id = child;
-				(4,udi_index_t(0))
-				},
-			EnumerateResult::RemovedSelf => (5,udi_index_t(0)),
-			EnumerateResult::Released => (6,udi_index_t(0)),
-			EnumerateResult::Failed => (255,udi_index_t(0)),
-			};
+		let (res,ops_idx,child_id) = res.to_raw();
+		if let Some(child_id) = child_id {
+			(*cb).child_id = child_id;
+		}
 		(*cb).attr_valid_length = attrs.dst.offset_from((*cb).attr_list).try_into().expect("BUG: Attr list too long");
 		crate::ffi::meta_mgmt::udi_enumerate_ack(cb, res, ops_idx)
 	}
@@ -376,22 +655,22 @@ future_wrapper!{final_cleanup_req_op => <T as Driver>(cb: *mut udi_mgmt_cb_t) va
 	unsafe { crate::ffi::meta_mgmt::udi_final_cleanup_ack(cb) }
 }}
 
-impl<T,CbList> crate::OpsStructure<::udi_sys::meta_mgmt::udi_mgmt_ops_t, RData<T> ,CbList>
+unsafe impl<T,CbList> crate::OpsFor<RData<T> ,CbList> for ::udi_sys::meta_mgmt::udi_mgmt_ops_t
 where
 	RData<T>: Driver,
 	T: Default,
 {
 	/// The amount of scratch space required for tasks within this type
-    pub const fn scratch_requirement() -> usize {
+    const SCRATCH_REQUIREMENT: usize = {
         let rv = 0;
 		let rv = crate::const_max(rv, async_trickery::task_size::< <RData<T> as Driver>::Future_init<'static> >());
 		let rv = crate::const_max(rv, enumerate_req_op::task_size::<RData<T>>());
 		let rv = crate::const_max(rv, devmgmt_req_op::task_size::<RData<T>>());
 		let rv = crate::const_max(rv, final_cleanup_req_op::task_size::<RData<T>>());
 		rv
-    }
+    };
 	/// Generate an ops instance for this `T`
-    pub const unsafe fn for_driver() -> ffi::meta_mgmt::udi_mgmt_ops_t {
+    const OPS: Self = {
         // ENTRYPOINT: mgmt_ops.usage_ind
         unsafe extern "C" fn usage_ind<T>(cb: *mut udi_usage_cb_t, resource_level: u8)
 		where
@@ -417,5 +696,5 @@ where
             devmgmt_req_op: devmgmt_req_op::<RData<T>>,
             final_cleanup_req_op: final_cleanup_req_op::<RData<T>>,
 			}
-    }
+    };
 }
\ No newline at end of file
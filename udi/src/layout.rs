@@ -207,4 +207,9 @@ impl_layout_simple!{
     crate::ffi::udi_origin_t => UDI_DL_ORIGIN_T,
     // The rest are more complex, so are handled in the derive
 }
+/// A buffer with no preserve flag (matching the default from the derive)
+unsafe impl GetLayout for *mut crate::ffi::udi_buf_t {
+    const LEN: usize = 4;
+    const LAYOUT: &'static [u8] = &[crate::ffi::layout::UDI_DL_BUF, 0, 0, 1];
+}
 
@@ -8,6 +8,8 @@
 #![feature(const_mut_refs)]	// Used for getting size of tasks
 #![feature(extern_types)]	// Handle types
 #![feature(fundamental)]
+#![feature(impl_trait_in_assoc_type)]
+#![feature(c_variadic)]	// For MEI direct stubs
 #![cfg_attr(not(feature="std"),allow(internal_features))]
 #![cfg_attr(not(feature="std"),feature(lang_items))]
 
@@ -21,7 +23,8 @@ pub use self::future_ext::FutureExt;
 #[macro_use]
 pub mod future_ext;
 #[macro_use]
-mod async_trickery;
+#[doc(hidden)]
+pub mod async_trickery;	// Public for the macros
 pub mod async_helpers;
 
 #[macro_use]
@@ -44,6 +47,9 @@ pub mod meta_bridge;
 pub mod meta_gio;
 pub mod meta_nic;
 pub mod meta_scsi;
+pub mod meta_usb;
+pub mod meta_gfx;
+pub mod mei;
 // Note: This is at the bottom in order to order the `impl` block docs for `CbRef`
 pub mod cb;
 
@@ -129,6 +135,36 @@ pub struct OpsStructure<Ops, T, CbList>
 {
 	pd: ::core::marker::PhantomData<(Ops,T,CbList)>,
 }
+impl<Ops, T, CbList> OpsStructure<Ops, T, CbList>
+where
+	Ops: OpsFor<T, CbList>,
+{
+	/// Obtain the amount of scratch space required for ops in this structure
+	pub const fn scratch_requirement() -> usize {
+		Ops::SCRATCH_REQUIREMENT
+	}
+	/// Generate an ops structure for a driver
+	/// 
+	/// # Safety
+	/// Caller must ensure that the ops are only used with matching `T` region, and that the scratch size is at
+	/// least [Self::scratch_requirement]
+	pub const unsafe fn for_driver() -> Ops {
+		Ops::OPS
+	}
+}
+/// Implemented on `udi_*_ops_t` structures for each context type `T` that can handle them, used via [OpsStructure]
+/// 
+/// This is a trait (instead of inherent methods on [OpsStructure]) so it can be implemented by metalanguages
+/// defined outside of this crate, see [define_metalanguage]
+/// 
+/// # Safety
+/// The entrypoints in `OPS` must be valid for a context of `T`, with at most `SCRATCH_REQUIREMENT` bytes of scratch
+pub unsafe trait OpsFor<T, CbList>: Sized {
+	/// Amount of scratch space required for ops in this structure
+	const SCRATCH_REQUIREMENT: usize;
+	/// The ops structure
+	const OPS: Self;
+}
 
 /// Traits used in [crate::define_driver] to specify different wrappers on the context
 pub mod ops_wrapper_markers {
@@ -322,6 +358,12 @@ macro_rules! define_driver
 	(@indexes_inner $this_name:ident $($name:ident)* =_8 ) => { pub type _8 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _9); };
 	(@indexes_inner $this_name:ident $($name:ident)* =_9 ) => { pub type _9 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _10); };
 	(@indexes_inner $this_name:ident $($name:ident)* =_10 ) => { pub type _10 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _11); };
+	(@indexes_inner $this_name:ident $($name:ident)* =_11 ) => { pub type _11 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _12); };
+	(@indexes_inner $this_name:ident $($name:ident)* =_12 ) => { pub type _12 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _13); };
+	(@indexes_inner $this_name:ident $($name:ident)* =_13 ) => { pub type _13 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _14); };
+	(@indexes_inner $this_name:ident $($name:ident)* =_14 ) => { pub type _14 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _15); };
+	(@indexes_inner $this_name:ident $($name:ident)* =_15 ) => { pub type _15 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _16); };
+	(@indexes_inner $this_name:ident $($name:ident)* =_16 ) => { pub type _16 = $this_name; $crate::define_driver!(@indexes_inner $($name)* = _17); };
 	(@indexes_inner =$out:ident ) => { };
 }
 
@@ -0,0 +1,429 @@
+//! Metalanguage-to-Environment Interface (MEI) support, for metalanguages defined outside of this crate
+//!
+//! The standard metalanguages (e.g. [crate::meta_gio]) are called through dedicated environment functions, a custom
+//! metalanguage is instead described to the environment by a `udi_mei_init_t` and its operations are called through
+//! `udi_mei_call`. [crate::define_metalanguage] generates both sides of this, along with the typed traits for drivers.
+use crate::ffi::layout::*;
+use crate::ffi::udi_layout_t;
+
+/// An operation argument that can be passed through `udi_mei_call`
+///
+/// # Safety
+/// `Va` must be the type that `Self` is promoted to when passed as a variadic argument, and the layout must be one
+/// handled by [marshal_va]
+pub unsafe trait MarshalArg: crate::layout::GetLayout + Copy {
+    /// Promoted (variadic) form of the value
+    type Va;
+    /// Convert into the promoted form
+    fn to_va(self) -> Self::Va;
+}
+macro_rules! impl_marshal_arg {
+    ( $( $t:ty => $va:ty = |$v:ident| $e:expr, )* ) => {
+        $(
+        unsafe impl MarshalArg for $t {
+            type Va = $va;
+            fn to_va(self) -> $va {
+                let $v = self;
+                $e
+            }
+        }
+        )*
+    };
+}
+impl_marshal_arg! {
+    crate::ffi::udi_ubit8_t => ::core::ffi::c_uint = |v| v as _,
+    crate::ffi::udi_sbit8_t => ::core::ffi::c_int = |v| v as _,
+    crate::ffi::udi_ubit16_t => ::core::ffi::c_uint = |v| v as _,
+    crate::ffi::udi_sbit16_t => ::core::ffi::c_int = |v| v as _,
+    crate::ffi::udi_ubit32_t => crate::ffi::udi_ubit32_t = |v| v,
+    crate::ffi::udi_sbit32_t => crate::ffi::udi_sbit32_t = |v| v,
+    crate::ffi::udi_boolean_t => ::core::ffi::c_uint = |v| v.0 as _,
+    crate::ffi::udi_index_t => ::core::ffi::c_uint = |v| v.0 as _,
+    crate::ffi::udi_channel_t => crate::ffi::udi_channel_t = |v| v,
+    crate::ffi::udi_origin_t => crate::ffi::udi_origin_t = |v| v,
+}
+
+/// Size (and alignment) of a layout entry when marshalled
+const fn marshal_element_size(l: udi_layout_t) -> usize {
+    match l {
+    UDI_DL_UBIT8_T|UDI_DL_SBIT8_T|UDI_DL_BOOLEAN_T|UDI_DL_INDEX_T => 1,
+    UDI_DL_UBIT16_T|UDI_DL_SBIT16_T => 2,
+    UDI_DL_UBIT32_T|UDI_DL_SBIT32_T|UDI_DL_STATUS_T => 4,
+    UDI_DL_CHANNEL_T|UDI_DL_ORIGIN_T => ::core::mem::size_of::<*mut ()>(),
+    _ => panic!("Unsupported layout entry for marshalling"),
+    }
+}
+const fn align_up(v: usize, a: usize) -> usize {
+    v.next_multiple_of(a)
+}
+
+/// Get the size of the marshal space for a `UDI_DL_END` terminated layout
+///
+/// Arguments are marshalled in order, each aligned to its size (i.e. matching a `#[repr(C)]` structure of the
+/// argument types)
+pub const fn marshal_size(layout: &[udi_layout_t]) -> usize {
+    let mut ofs = 0;
+    let mut i = 0;
+    while layout[i] != UDI_DL_END {
+        let size = marshal_element_size(layout[i]);
+        ofs = align_up(ofs, size) + size;
+        i += 1;
+    }
+    align_up(ofs, ::core::mem::size_of::<*mut ()>())
+}
+
+/// Marshal variadic arguments (as passed to `udi_mei_call`) into `space`
+///
+/// # Safety
+/// - `layout` must be terminated by `UDI_DL_END`, and only contain entries handled by [marshal_size]
+/// - `ap` must contain arguments matching the layout
+/// - `space` must be valid for [marshal_size] bytes, and aligned to a pointer
+pub unsafe fn marshal_va(layout: *const udi_layout_t, mut ap: ::core::ffi::VaList, space: *mut crate::ffi::c_void) {
+    let mut ofs = 0;
+    let mut l = layout;
+    while *l != UDI_DL_END {
+        let size = marshal_element_size(*l);
+        ofs = align_up(ofs, size);
+        let dst = (space as *mut u8).add(ofs);
+        match *l {
+        UDI_DL_UBIT8_T|UDI_DL_SBIT8_T|UDI_DL_BOOLEAN_T|UDI_DL_INDEX_T => *dst = ap.next_arg::<::core::ffi::c_uint>() as u8,
+        UDI_DL_UBIT16_T|UDI_DL_SBIT16_T => *(dst as *mut u16) = ap.next_arg::<::core::ffi::c_uint>() as u16,
+        UDI_DL_UBIT32_T|UDI_DL_SBIT32_T|UDI_DL_STATUS_T => *(dst as *mut u32) = ap.next_arg::<u32>(),
+        _ => *(dst as *mut *mut crate::ffi::c_void) = ap.next_arg::<*mut crate::ffi::c_void>(),
+        }
+        ofs += size;
+        l = l.offset(1);
+    }
+}
+
+/// Marshalling details of an operation, used by [direct_stub]
+pub trait OpMarshal {
+    /// Marshal layout of the operation's arguments, terminated by `UDI_DL_END`
+    const MARSHAL: &'static [udi_layout_t];
+    /// Storage for the marshalled arguments, at least [marshal_size] bytes and pointer aligned
+    type Space;
+    /// Back-end stub, called with the marshalled arguments
+    const BACKEND: crate::ffi::mei::udi_mei_backend_stub_t;
+}
+/// Direct stub for an operation: marshals the variadic arguments then calls the back-end stub
+///
+/// # Safety
+/// `arglist` must contain arguments matching `M::MARSHAL`, and `op`/`gcb` must be valid for `M::BACKEND`
+pub unsafe extern "C" fn direct_stub<M: OpMarshal>(op: crate::ffi::udi_op_t, gcb: *mut crate::ffi::udi_cb_t, arglist: ::core::ffi::VaList) {
+    let mut space = ::core::mem::MaybeUninit::<M::Space>::zeroed();
+    marshal_va(M::MARSHAL.as_ptr(), arglist, space.as_mut_ptr() as *mut _);
+    (M::BACKEND)(op, gcb, space.as_mut_ptr() as *mut _)
+}
+
+/// Concatenate layouts into a fixed-size array (`N` must be the total length)
+pub const fn layout_concat<const N: usize>(parts: &[&[udi_layout_t]]) -> [udi_layout_t; N] {
+    let mut rv = [UDI_DL_END; N];
+    let mut ofs = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let mut j = 0;
+        while j < parts[i].len() {
+            rv[ofs] = parts[i][j];
+            ofs += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(ofs == N, "Incorrect layout length");
+    rv
+}
+
+/// Look up the template for an operation
+pub fn get_op_template(
+    mei: &crate::ffi::mei::udi_mei_init_t,
+    meta_ops_num: crate::ffi::udi_index_t,
+    vec_idx: crate::ffi::udi_index_t
+) -> Option<&crate::ffi::mei::udi_mei_op_template_t> {
+    // SAFE: The lists are terminated as documented on the structures
+    unsafe {
+        let mut ops = mei.ops_vec_template_list;
+        while (*ops).meta_ops_num.0 != 0 {
+            if (*ops).meta_ops_num == meta_ops_num {
+                let mut op = (*ops).op_template_list;
+                for _ in 1 .. vec_idx.0 {
+                    if (*op).op_name.is_null() {
+                        return None;
+                    }
+                    op = op.offset(1);
+                }
+                return if vec_idx.0 == 0 || (*op).op_name.is_null() { None } else { Some(&*op) };
+            }
+            ops = ops.offset(1);
+        }
+        None
+    }
+}
+
+/// Marker for the channel handling of metalanguages from [crate::define_metalanguage]
+pub struct DefaultChannel;
+impl<T> crate::imc::ChannelHandler<DefaultChannel> for T
+where
+    T: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit,
+{
+}
+
+/// Define a custom metalanguage
+///
+/// This generates:
+/// - A `#[repr(C)]` structure for each control block, starting with `gcb` and with visible layout from the fields
+/// - An ops structure and a driver-side trait for each ops vector, for use in [crate::define_driver]
+/// - A request function for each operation, that calls `udi_mei_call`
+/// - The MEI description (`MEI_INIT`) with op templates, marshalling layouts, and direct/backend stubs
+/// - The metalanguage specification (`METALANG_SPEC`) for the environment
+///
+/// Operations are declared with a category (`REQ`, `ACK`, `NAK`, `IND`, `RES`, or `RDY`) and may name a reply
+/// operation. The trait method for an operation with a reply returns the reply's arguments, and the CB is sent back
+/// using that operation. Otherwise the CB is passed to the trait's `cb_ret` once the method completes.
+///
+/// Arguments must implement [MarshalArg], and control block fields must implement [crate::layout::GetLayout].
+/// Binding to a parent/child is not handled, these metalanguages are for spawned channels (e.g. between regions or
+/// secondary bindings). Only one metalanguage can be defined per module.
+///
+/// ```ignore
+/// ::udi::define_metalanguage! {
+///     NAME acme_counter;
+///     CBS {
+///         /// Control block for counter operations
+///         1 => acme_counter_cb_t {
+///             /// Counter label
+///             label: udi_ubit8_t,
+///         },
+///     }
+///     OPS {
+///         /// Counter provider operations
+///         1 => acme_counter_provider_ops_t : Provider {
+///             /// Add to the counter, replying with the new total
+///             REQ fn add_req(cb: acme_counter_cb_t, amount: udi_ubit32_t) -> add_ack(total: udi_ubit32_t) as Future_add_req;
+///         },
+///         /// Counter client operations
+///         2 => acme_counter_client_ops_t : Client {
+///             /// Acknowledgement of [add_req]
+///             ACK fn add_ack(cb: acme_counter_cb_t, total: udi_ubit32_t) as Future_add_ack;
+///         },
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_metalanguage {
+    (
+        NAME $name:ident;
+        CBS {
+            $(
+            $(#[$cb_a:meta])*
+            $cb_idx:literal => $cb_name:ident {
+                $( $(#[$f_a:meta])* $f_name:ident : $f_ty:ty ),* $(,)?
+            } $(: BUF $buf_fld:ident)?
+            ),* $(,)?
+        }
+        OPS {
+            $(
+            $(#[$ops_a:meta])*
+            $ops_idx:literal => $ops_name:ident : $trait_name:ident {
+                $($ops_body:tt)*
+            }
+            ),* $(,)?
+        }
+    ) => {
+        $(
+        $(#[$cb_a])*
+        #[repr(C)]
+        #[allow(non_camel_case_types)]
+        pub struct $cb_name {
+            /// Generic control block header
+            pub gcb: $crate::ffi::udi_cb_t,
+            $( $(#[$f_a])* pub $f_name: $f_ty, )*
+        }
+        unsafe impl $crate::layout::GetLayout for $cb_name {
+            const LEN: usize = 0 $(+ <$f_ty as $crate::layout::GetLayout>::LEN)*;
+            const LAYOUT: &'static [u8] = &$crate::mei::layout_concat::<{0 $(+ <$f_ty as $crate::layout::GetLayout>::LEN)*}>(
+                &[$(<$f_ty as $crate::layout::GetLayout>::LAYOUT),*]
+                );
+        }
+        )*
+
+        $crate::impl_metalanguage!{
+            static METALANG_SPEC;
+            NAME $name;
+            MEI MEI_INIT;
+            OPS $( $ops_idx => $ops_name ),* ;
+            CBS $( $cb_idx => $cb_name $(: BUF $buf_fld)? ),* ;
+        }
+
+        /// MEI description of the metalanguage, to be passed to `udi_mei_call`
+        pub static MEI_INIT: $crate::ffi::mei::udi_mei_init_t = $crate::ffi::mei::udi_mei_init_t {
+            ops_vec_template_list: [
+                $(
+                $crate::ffi::mei::udi_mei_ops_vec_template_t {
+                    meta_ops_num: $crate::ffi::udi_index_t($ops_idx),
+                    relationship: $crate::ffi::mei::UDI_MEI_REL_EXTERNAL | $crate::ffi::mei::UDI_MEI_REL_INTERNAL,
+                    op_template_list: $ops_name::MEI_OP_TEMPLATES.as_ptr(),
+                },
+                )*
+                $crate::ffi::mei::udi_mei_ops_vec_template_t::end_of_list()
+            ].as_ptr(),
+            mei_enumeration_rank: None,
+        };
+
+        $(
+        $crate::define_metalanguage!{@ops_pub $ops_idx $ops_name $trait_name [$(#[$ops_a])*] $($ops_body)*}
+        )*
+
+        #[doc(hidden)]
+        mod __mei_ops {
+            #[allow(unused_imports)]
+            use super::*;
+            /// The request functions, as the entrypoints below shadow them
+            mod requests {
+                #[allow(unused_imports)]
+                pub(super) use super::super::*;
+            }
+            $crate::define_metalanguage!{@ops_impls [$($cb_name,)*] $( {$ops_name $trait_name $($ops_body)*} )*}
+        }
+    };
+    (@ops_impls $cbs:tt $( {$ops_name:ident $trait_name:ident $($ops_body:tt)*} )*) => {
+        $(
+        $crate::define_metalanguage!{@ops_impl $ops_name $trait_name $cbs $($ops_body)*}
+        )*
+    };
+
+    // Public items for an ops vector: The ops structure, driver trait, and request functions
+    (@ops_pub $ops_idx:literal $ops_name:ident $trait_name:ident [$($ops_a:tt)*]
+        $(
+        $(#[$op_a:meta])*
+        $cat:ident fn $op:ident(cb: $op_cb:ident $(, $a_n:ident: $a_ty:ty)*) $(-> $reply:ident($($r_n:ident: $r_ty:ty),*))? as $future_name:ident;
+        )*
+    ) => {
+        $($ops_a)*
+        #[repr(C)]
+        #[allow(non_camel_case_types)]
+        pub struct $ops_name {
+            /// Channel event handler
+            pub channel_event_ind_op: $crate::ffi::imc::udi_channel_event_ind_op_t,
+            $(
+            $(#[$op_a])*
+            pub $op: unsafe extern "C" fn(cb: *mut $op_cb $(, $a_n: $a_ty)*),
+            )*
+        }
+        impl $ops_name {
+            /// MEI templates for the operations in this ops vector
+            pub const MEI_OP_TEMPLATES: &'static [$crate::ffi::mei::udi_mei_op_template_t] = &[
+                $( $crate::define_metalanguage!(@template $cat $op $op_cb ($($a_n: $a_ty),*)), )*
+                $crate::ffi::mei::udi_mei_op_template_t::end_of_list()
+            ];
+        }
+
+        $($ops_a)*
+        pub trait $trait_name: 'static + $crate::imc::ChannelInit + $crate::async_trickery::CbContext
+        {
+            $(
+            $crate::async_method!(
+                $(#[$op_a])*
+                fn $op(&'s self, cb: $crate::CbRef<'s, $op_cb> $(, $a_n: $a_ty)*) -> $crate::define_metalanguage!(@reply_ty $( $($r_ty),* )?)
+                as $future_name
+            );
+            )*
+            /// Release a CB received by an operation that has no reply
+            fn cb_ret<C: $crate::metalang_trait::MetalangCb + ::core::any::Any + Unpin>(&self, cb: $crate::cb::CbHandle<C>) { let _ = cb; }
+        }
+
+        $(
+        $(#[$op_a])*
+        pub fn $op(cb: $crate::cb::CbHandle<$op_cb> $(, $a_n: $a_ty)*) {
+            let vec_idx = ::core::mem::offset_of!($ops_name, $op) / ::core::mem::size_of::<$crate::ffi::udi_op_t>();
+            unsafe {
+                $crate::ffi::mei::udi_mei_call(
+                    cb.into_raw() as *mut $crate::ffi::udi_cb_t,
+                    &MEI_INIT as *const _ as *mut _,
+                    $crate::ffi::udi_index_t($ops_idx),
+                    $crate::ffi::udi_index_t(vec_idx as u8)
+                    $(, $crate::mei::MarshalArg::to_va($a_n) )*
+                    )
+            }
+        }
+        )*
+    };
+    // Op template, with layouts and stubs
+    (@template $cat:ident $op:ident $op_cb:ident ($($a_n:ident: $a_ty:ty),*)) => {{
+        #[repr(C)]
+        #[allow(dead_code)]
+        struct Args { $($a_n: $a_ty,)* }
+        const VISIBLE: [u8; <$op_cb as $crate::layout::GetLayout>::LEN + 1] = $crate::mei::layout_concat(&[
+            <$op_cb as $crate::layout::GetLayout>::LAYOUT,
+            &[$crate::ffi::layout::UDI_DL_END],
+            ]);
+        const MARSHAL: [u8; 0 $(+ <$a_ty as $crate::layout::GetLayout>::LEN)* + 1] = $crate::mei::layout_concat(&[
+            $(<$a_ty as $crate::layout::GetLayout>::LAYOUT,)*
+            &[$crate::ffi::layout::UDI_DL_END],
+            ]);
+        const _: () = assert!($crate::mei::marshal_size(&MARSHAL) >= ::core::mem::size_of::<Args>());
+        unsafe extern "C" fn backend(op: $crate::ffi::udi_op_t, gcb: *mut $crate::ffi::udi_cb_t, marshal_space: *mut $crate::ffi::c_void) {
+            #[allow(unused_variables)]
+            let Args { $($a_n,)* } = ::core::ptr::read(marshal_space as *const Args);
+            let op: unsafe extern "C" fn(*mut $op_cb $(, $a_ty)*) = ::core::mem::transmute(op);
+            op(gcb as *mut $op_cb $(, $a_n)*)
+        }
+        struct Marshal;
+        impl $crate::mei::OpMarshal for Marshal {
+            const MARSHAL: &'static [$crate::ffi::udi_layout_t] = &MARSHAL;
+            type Space = [usize; $crate::mei::marshal_size(&MARSHAL) / ::core::mem::size_of::<usize>()];
+            const BACKEND: $crate::ffi::mei::udi_mei_backend_stub_t = backend;
+        }
+        $crate::ffi::mei::udi_mei_op_template_t {
+            op_name: concat!(stringify!($op), "\0").as_ptr() as *const _,
+            op_category: $crate::define_metalanguage!(@category $cat),
+            op_flags: 0,
+            meta_cb_num: <$op_cb as $crate::metalang_trait::MetalangCb>::META_CB_NUM,
+            completion_ops_num: $crate::ffi::udi_index_t(0),
+            completion_vec_idx: $crate::ffi::udi_index_t(0),
+            exception_ops_num: $crate::ffi::udi_index_t(0),
+            exception_vec_idx: $crate::ffi::udi_index_t(0),
+            direct_stub: Some($crate::mei::direct_stub::<Marshal>),
+            backend_stub: Some(backend),
+            visible_layout: VISIBLE.as_ptr(),
+            marshal_layout: MARSHAL.as_ptr(),
+        }
+    }};
+    (@category REQ) => { $crate::ffi::mei::UDI_MEI_OPCAT_REQ };
+    (@category ACK) => { $crate::ffi::mei::UDI_MEI_OPCAT_ACK };
+    (@category NAK) => { $crate::ffi::mei::UDI_MEI_OPCAT_NAK };
+    (@category IND) => { $crate::ffi::mei::UDI_MEI_OPCAT_IND };
+    (@category RES) => { $crate::ffi::mei::UDI_MEI_OPCAT_RES };
+    (@category RDY) => { $crate::ffi::mei::UDI_MEI_OPCAT_RDY };
+    (@reply_ty $($r_ty:ty),*) => { ($($r_ty),*) };
+
+    // Entrypoints for an ops vector: wrappers that call the trait and then send the reply (or release the CB)
+    (@ops_impl $ops_name:ident $trait_name:ident [$($cb_name:ident,)*]
+        $(
+        $(#[$op_a:meta])*
+        $cat:ident fn $op:ident(cb: $op_cb:ident $(, $a_n:ident: $a_ty:ty)*) $(-> $reply:ident($($r_n:ident: $r_ty:ty),*))? as $future_name:ident;
+        )*
+    ) => {
+        $(
+        $crate::future_wrapper!($op => <T as $trait_name>(cb: *mut $op_cb $(, $a_n: $a_ty)*) val @ {
+            val.$op(cb $(, $a_n)*)
+        } finally( $crate::define_metalanguage!(@reply_pat $($($r_n),*)?) ) {
+            $crate::define_metalanguage!(@reply val cb $( $reply($($r_n),*) )?);
+        });
+        )*
+        $crate::map_ops_structure!{
+            $ops_name => $trait_name,$crate::mei::DefaultChannel {
+                $($op,)*
+            }
+            CBS {
+                $($cb_name,)*
+            }
+        }
+    };
+    (@reply_pat $($r_n:ident),*) => { ($($r_n),*) };
+    (@reply $val:ident $cb:ident $reply:ident($($r_n:ident),*)) => {
+        requests::$reply(unsafe { $crate::cb::CbHandle::from_raw($cb) } $(, $r_n)*)
+    };
+    (@reply $val:ident $cb:ident) => {
+        $val.cb_ret(unsafe { $crate::cb::CbHandle::from_raw($cb) })
+    };
+}
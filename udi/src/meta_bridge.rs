@@ -1,5 +1,7 @@
 //! Bus Bridge metalanguage (Phsical I/O Specification)
 //! 
+use ::core::cell::Cell;
+use ::core::sync::atomic::{AtomicPtr, Ordering};
 use ::udi_sys::meta_bridge::{udi_intr_event_cb_t, udi_intr_attach_cb_t};
 use ::udi_sys::meta_bridge::udi_bus_device_ops_t;
 use ::udi_sys::meta_bridge::udi_bus_bridge_ops_t;
@@ -84,8 +86,10 @@ pub trait BusDevice: 'static + crate::async_trickery::CbContext + crate::imc::Ch
 
     /// Return/release an interrupt-attach CB
     fn intr_attach_cb_ret(&self, cb: CbHandleIntrAttach) { let _ = cb; }
-    /// Return/release an interrupt-detach CB
+    /// Return/release an interrupt-detach CB (unless it was sent by [intr_detach])
     fn intr_detach_cb_ret(&self, cb: CbHandleIntrDetach) { let _ = cb; }
+    /// Return/release a bus bind CB used to unbind (unless it was sent by [bus_unbind])
+    fn bus_unbind_cb_ret(&self, cb: crate::cb::CbHandle<udi_bus_bind_cb_t>) { let _ = cb; }
 }
 struct MarkerBusDevice;
 impl<T> crate::imc::ChannelHandler<MarkerBusDevice> for T
@@ -105,7 +109,10 @@ where
 pub trait BusBridge: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext {
     async_method!(
         /// Handle a request to bind to the bus
-        fn bus_bind_req(&'a self, cb: CbRefBind<'a>) -> crate::Result<(PreferredEndianness,)>
+        ///
+        /// Returns the preferred endianness, and the DMA constraints for the child (ownership passes to the child,
+        /// use [crate::physio::dma::DmaConstraints::null] if the child cannot do DMA)
+        fn bus_bind_req(&'a self, cb: CbRefBind<'a>) -> crate::Result<(PreferredEndianness,crate::physio::dma::DmaConstraints)>
         as Future_bind_req
     );
     async_method!(
@@ -153,7 +160,10 @@ future_wrapper!(bus_bind_ack_op => <T as BusDevice>(
 future_wrapper!(bus_unbind_ack_op => <T as BusDevice>(cb: *mut udi_bus_bind_cb_t) val @ {
     val.bus_unbind_ack(cb)
 } finally( () ) {
-    unsafe { crate::async_trickery::channel_event_complete::<T,udi_bus_bind_cb_t>(cb, 0 /*res*/) }
+    // SAFE: The CB is owned by this op
+    if let Some(cb) = unsafe { ack_to_waiter(cb) } {
+        val.bus_unbind_cb_ret(cb);
+    }
 });
 future_wrapper!(intr_attach_ack_op => <T as BusDevice>(cb: *mut crate::ffi::meta_bridge::udi_intr_attach_cb_t, status: crate::ffi::udi_status_t) val @ {
     val.intr_attach_ack(cb, status)
@@ -163,8 +173,140 @@ future_wrapper!(intr_attach_ack_op => <T as BusDevice>(cb: *mut crate::ffi::meta
 future_wrapper!(intr_detach_ack_op => <T as BusDevice>(cb: *mut crate::ffi::meta_bridge::udi_intr_detach_cb_t) val @ {
     val.intr_detach_ack(cb)
 } finally( () ) {
-    val.intr_detach_cb_ret(unsafe { CbHandleIntrDetach::from_raw(cb) });
+    // SAFE: The CB is owned by this op
+    if let Some(cb) = unsafe { ack_to_waiter(cb) } {
+        val.intr_detach_cb_ret(cb);
+    }
 });
+/// Number of acknowledgements that can be awaited by [send_and_wait] at once
+const MAX_ACK_WAITERS: usize = 8;
+/// Requests sent by [send_and_wait], each with the task waiting for its acknowledgement
+///
+/// Kept by the library, as the request CB's own fields (e.g. `initiator_context`) belong to the driver.
+static ACK_WAITERS: [AckWaiter; MAX_ACK_WAITERS] = [const { AckWaiter::new() }; MAX_ACK_WAITERS];
+struct AckWaiter {
+    /// The request CB, null if this entry is free
+    cb: AtomicPtr<crate::ffi::udi_cb_t>,
+    /// CB of the waiting task
+    waiter: AtomicPtr<crate::ffi::udi_cb_t>,
+}
+impl AckWaiter {
+    const fn new() -> Self {
+        AckWaiter { cb: AtomicPtr::new(::core::ptr::null_mut()), waiter: AtomicPtr::new(::core::ptr::null_mut()) }
+    }
+}
+/// Hand an acknowledged request CB back to the task waiting in [send_and_wait], or return it if there is no waiter
+///
+/// SAFETY: `cb` must be owned by the caller
+unsafe fn ack_to_waiter<Cb: crate::async_trickery::GetCb>(cb: *mut Cb) -> Option<crate::cb::CbHandle<Cb>> {
+    let gcb = cb as *mut crate::ffi::udi_cb_t;
+    match ACK_WAITERS.iter().find(|w| w.cb.load(Ordering::Acquire) == gcb) {
+    None => Some(crate::cb::CbHandle::from_raw(cb)),
+    Some(w) => {
+        let waiter = w.waiter.swap(::core::ptr::null_mut(), Ordering::Acquire);
+        w.cb.store(::core::ptr::null_mut(), Ordering::Release);
+        crate::async_trickery::signal_waiter(waiter, crate::WaitRes::Pointer(cb as *mut ()));
+        None
+        },
+    }
+}
+/// Send a request CB, with the acknowledgement returned to the current task (see [ACK_WAITERS])
+fn send_and_wait<'a, Cb: crate::async_trickery::GetCb + 'static>(
+    gcb: crate::CbRef<'a, crate::ffi::udi_cb_t>,
+    cb: crate::cb::CbHandle<Cb>,
+    send: unsafe extern "C" fn(*mut Cb),
+) -> impl ::core::future::Future<Output=crate::cb::CbHandle<Cb>> + 'a {
+    let cb = cb.into_raw();
+    crate::async_trickery::wait_task(gcb,
+        move |gcb| unsafe {
+            let null = ::core::ptr::null_mut();
+            let Some(w) = ACK_WAITERS.iter().find(|w| w.cb.compare_exchange(null, cb as *mut _, Ordering::AcqRel, Ordering::Relaxed).is_ok()) else {
+                panic!("send_and_wait: More than {} acknowledgements awaited at once", MAX_ACK_WAITERS)
+            };
+            w.waiter.store(gcb, Ordering::Release);
+            send(cb)
+        },
+        move |res| {
+            let crate::WaitRes::Pointer(p) = res else { panic!("send_and_wait: Expected the acknowledged CB pointer") };
+            // SAFE: The acknowledged CB is now owned by this task
+            unsafe { crate::cb::CbHandle::from_raw(p as *mut Cb) }
+        })
+}
+
+/// Unbind from the bus bridge (e.g. when handling [crate::init::MgmtOp::Unbind]), waiting for the acknowledgement
+///
+/// `cb` must be for the channel to the bridge, and is returned once the bridge has acknowledged the unbind. Any
+/// interrupts should be detached first.
+pub fn bus_unbind<'a>(
+    gcb: crate::CbRef<'a, crate::ffi::udi_cb_t>,
+    cb: crate::cb::CbHandle<udi_bus_bind_cb_t>,
+) -> impl ::core::future::Future<Output=crate::cb::CbHandle<udi_bus_bind_cb_t>> + 'a {
+    send_and_wait(gcb, cb, crate::ffi::meta_bridge::udi_bus_unbind_req)
+}
+/// Detach the interrupt handler from interrupt `interrupt_idx`, waiting for the acknowledgement
+///
+/// `cb` must be for the channel to the bridge, and is returned once the bridge has acknowledged the detach. See also
+/// [IntrHandle], which detaches automatically.
+pub fn intr_detach<'a>(
+    gcb: crate::CbRef<'a, crate::ffi::udi_cb_t>,
+    mut cb: CbHandleIntrDetach<'a>,
+    interrupt_idx: ::udi_sys::udi_index_t,
+) -> impl ::core::future::Future<Output=CbHandleIntrDetach<'a>> + 'a {
+    // SAFE: Owned
+    unsafe { cb.get_mut().interrupt_idx = interrupt_idx; }
+    send_and_wait(gcb, cb, crate::ffi::meta_bridge::udi_intr_detach_req)
+}
+
+/// An attached interrupt, detached when dropped (or explicitly with [IntrHandle::detach])
+///
+/// Holds the interrupt handler channel and a pre-allocated detach CB, so that dropping never needs to allocate.
+pub struct IntrHandle {
+    channel: crate::imc::ChannelHandle,
+    interrupt_idx: ::udi_sys::udi_index_t,
+    detach_cb: Option<crate::cb::CbHandle<crate::ffi::meta_bridge::udi_intr_detach_cb_t>>,
+}
+impl IntrHandle {
+    /// Wrap an attached interrupt (see [intr_attach])
+    ///
+    /// - `channel` is the interrupt handler channel that was passed to [intr_attach]
+    /// - `detach_cb` is a CB for the channel to the bridge
+    pub fn new(
+        channel: crate::imc::ChannelHandle,
+        interrupt_idx: ::udi_sys::udi_index_t,
+        detach_cb: crate::cb::CbHandle<crate::ffi::meta_bridge::udi_intr_detach_cb_t>,
+    ) -> Self {
+        IntrHandle { channel, interrupt_idx, detach_cb: Some(detach_cb) }
+    }
+    /// The interrupt handler channel
+    pub fn channel(&self) -> &crate::imc::ChannelHandle {
+        &self.channel
+    }
+    /// The bridge's interrupt index
+    pub fn interrupt_idx(&self) -> ::udi_sys::udi_index_t {
+        self.interrupt_idx
+    }
+    /// Detach the interrupt, waiting for the bridge to acknowledge
+    pub fn detach<'a>(mut self, gcb: crate::CbRef<'a, crate::ffi::udi_cb_t>) -> impl ::core::future::Future<Output=()> + 'a {
+        let cb = self.detach_cb.take().unwrap();
+        let idx = self.interrupt_idx;
+        drop(self);
+        async move {
+            let cb = intr_detach(gcb, cb, idx).await;
+            drop(cb);
+        }
+    }
+}
+impl Drop for IntrHandle {
+    fn drop(&mut self) {
+        if let Some(mut cb) = self.detach_cb.take() {
+            // SAFE: Owned, and not sent by `send_and_wait`, so is returned to `BusDevice::intr_detach_cb_ret` on ack
+            unsafe { cb.get_mut().interrupt_idx = self.interrupt_idx; }
+            unsafe { crate::ffi::meta_bridge::udi_intr_detach_req(cb.into_raw()) }
+        }
+        // The bridge closes the handler channel once detached, so release it without closing.
+        let _ = ::core::mem::take(&mut self.channel).into_raw();
+    }
+}
 map_ops_structure!{
     ::udi_sys::meta_bridge::udi_bus_device_ops_t => BusDevice,MarkerBusDevice {
         bus_bind_ack_op,
@@ -186,14 +328,14 @@ future_wrapper!(bus_bind_req_op => <T as BusBridge>(cb: *mut udi_bus_bind_cb_t)
     unsafe {
         let (status,dma,endian) = match res
             {
-            Ok((endian,)) => {
+            Ok((endian,dma_constraints)) => {
                 let endian = match endian
                     {
                     PreferredEndianness::Any => crate::ffi::meta_bridge::UDI_DMA_ANY_ENDIAN,
                     PreferredEndianness::Big => crate::ffi::meta_bridge::UDI_DMA_BIG_ENDIAN,
                     PreferredEndianness::Little => crate::ffi::meta_bridge::UDI_DMA_LITTLE_ENDIAN,
                     };
-                (0,crate::ffi::physio::UDI_NULL_DMA_CONSTRAINTS,endian)
+                (0,dma_constraints.into_raw(),endian)
                 },
             Err(e) => (e.into_inner(),crate::ffi::physio::UDI_NULL_DMA_CONSTRAINTS,0),
             };
@@ -234,6 +376,20 @@ map_ops_structure!{
 pub fn intr_attach_req(cb: super::cb::CbHandle<udi_intr_attach_cb_t>) {
     unsafe { crate::ffi::meta_bridge::udi_intr_attach_req(cb.into_raw()) }
 }
+/// Populate and send an interrupt attach request for interrupt `interrupt_index`
+///
+/// `preprocessing` is an optional PIO handle that the bus bridge runs when the interrupt fires (e.g. to read and
+/// acknowledge the interrupt status), the result is passed as `intr_result` in the event CB. Ownership of the
+/// handle passes to the bridge.
+pub fn intr_attach(
+    mut cb: super::cb::CbHandle<udi_intr_attach_cb_t>,
+    interrupt_index: ::udi_sys::udi_index_t,
+    min_event_pend: u8,
+    preprocessing: Option<crate::pio::Handle>,
+) {
+    cb.init(interrupt_index, min_event_pend, preprocessing.unwrap_or_default());
+    intr_attach_req(cb)
+}
 /// Return a handled interrupt event CB to the bus driver 
 pub fn intr_event_rdy(cb: CbHandleEvent) {
     unsafe { crate::ffi::meta_bridge::udi_intr_event_rdy(cb.into_raw()) }
@@ -255,16 +411,180 @@ impl super::cb::CbHandle<udi_intr_attach_cb_t>
     }
 }
 
+def_flags!{
+    /// Flags passed with an interrupt event indication
+    IntrEventFlags: u8 {
+        /// The interrupt is already masked/acknowledged (e.g. by the preprocessing handle), and doesn't need the handler to do so
+        MASKING_NOT_REQUIRED = crate::ffi::meta_bridge::UDI_INTR_MASKING_NOT_REQUIRED,
+        /// At least one interrupt was lost (due to no event CBs being available) since the previous indication
+        OVERRUN_OCCURRED = crate::ffi::meta_bridge::UDI_INTR_OVERRUN_OCCURRED,
+        /// The preprocessing handle was run, and `intr_result` contains its result
+        PREPROCESSED = crate::ffi::meta_bridge::UDI_INTR_PREPROCESSED,
+    }
+}
+impl IntrEventFlags {
+    /// See [IntrEventFlags::MASKING_NOT_REQUIRED]
+    pub const fn masking_not_required(self) -> bool {
+        self.contains(Self::MASKING_NOT_REQUIRED)
+    }
+    /// See [IntrEventFlags::OVERRUN_OCCURRED]
+    pub const fn overrun_occurred(self) -> bool {
+        self.contains(Self::OVERRUN_OCCURRED)
+    }
+    /// See [IntrEventFlags::PREPROCESSED]
+    pub const fn preprocessed(self) -> bool {
+        self.contains(Self::PREPROCESSED)
+    }
+}
+def_flags!{
+    /// Result of the preprocessing handle (`intr_result` in the event CB)
+    ///
+    /// The remaining bits are defined by the driver, e.g. to pass the device's interrupt status to the handler.
+    IntrResult: u16 {
+        /// The interrupt was not raised by this device (`UDI_INTR_UNCLAIMED`)
+        UNCLAIMED = crate::ffi::meta_bridge::UDI_INTR_UNCLAIMED,
+        /// The interrupt was handled by the preprocessing handle, so there is nothing to indicate (`UDI_INTR_NO_EVENT`)
+        NO_EVENT = crate::ffi::meta_bridge::UDI_INTR_NO_EVENT,
+    }
+}
+impl IntrResult {
+    /// Check if the result calls for an indication, i.e. neither [IntrResult::UNCLAIMED] nor [IntrResult::NO_EVENT] is set
+    pub const fn has_event(self) -> bool {
+        self.0 & (Self::UNCLAIMED.0 | Self::NO_EVENT.0) == 0
+    }
+}
+impl crate::cb::CbRef<'_, udi_intr_event_cb_t>
+{
+    /// Result of the preprocessing handle, if [IntrEventFlags::PREPROCESSED] was set
+    pub fn intr_result(&self) -> IntrResult {
+        IntrResult::from_raw(self.intr_result)
+    }
+}
+
+/// Keeps an interrupt dispatcher supplied with event CBs
+///
+/// The pool keeps `target` CBs posted to the dispatcher (see [intr_event_rdy]), and holds a number of spares. When an
+/// event arrives a spare is posted in its place, so the dispatcher isn't left short while the handler runs, and
+/// once handled the event's CB becomes a spare. Return the pool from [IntrHandler::intr_event_pool] to have event CBs
+/// recycled through it.
+pub struct IntrEventPool {
+    /// Number of CBs to keep posted
+    target: Cell<u8>,
+    /// Number of CBs to keep as spares
+    spare_target: Cell<u8>,
+    /// Number of CBs currently held by the dispatcher
+    posted: Cell<u8>,
+    /// Number of CBs in `spares`
+    spare_count: Cell<u8>,
+    /// Spare CBs (a `Chain` is used as it doesn't require the incoming CB's chain slot to be clear)
+    spares: Cell<crate::cb::Chain<udi_intr_event_cb_t>>,
+}
+impl Default for IntrEventPool {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+impl IntrEventPool {
+    /// Create an empty pool that will keep `target` CBs posted (usually the `min_event_pend` used when attaching)
+    pub const fn new(target: u8) -> Self {
+        IntrEventPool {
+            target: Cell::new(target),
+            spare_target: Cell::new(0),
+            posted: Cell::new(0),
+            spare_count: Cell::new(0),
+            spares: Cell::new(crate::cb::Chain::new()),
+        }
+    }
+    /// Number of CBs currently posted to the dispatcher
+    pub fn posted(&self) -> u8 {
+        self.posted.get()
+    }
+    /// Number of spare CBs held by the pool
+    pub fn spares(&self) -> u8 {
+        self.spare_count.get()
+    }
+    /// Number of CBs that the pool tries to keep posted
+    pub fn target(&self) -> u8 {
+        self.target.get()
+    }
+    /// Change the number of CBs kept posted
+    ///
+    /// Raising the target posts any available spares (see [IntrEventPool::fill] to allocate more), lowering it
+    /// releases CBs as events are handled.
+    pub fn set_target(&self, target: u8) {
+        self.target.set(target);
+        self.top_up();
+    }
+
+    /// Allocate CBs (of type `CbDef`) for the interrupt handler channel `channel`, until `target` CBs are posted and
+    /// `spare` CBs are held
+    pub fn fill<'a, CbDef>(
+        &'a self,
+        gcb: crate::CbRef<'a, crate::ffi::udi_cb_t>,
+        channel: &'a crate::imc::ChannelHandle,
+        spare: u8,
+    ) -> impl ::core::future::Future<Output=()> + 'a
+    where
+        CbDef: crate::cb::CbDefinition<Cb=udi_intr_event_cb_t> + 'a,
+    {
+        self.spare_target.set(spare);
+        async move {
+            self.top_up();
+            while self.posted.get() < self.target.get() || self.spare_count.get() < spare {
+                let cb = crate::cb::alloc::<CbDef>(gcb, channel.raw()).await;
+                self.add_spare(cb);
+                self.top_up();
+            }
+        }
+    }
+
+    fn add_spare(&self, cb: CbHandleEvent) {
+        let mut spares = self.spares.take();
+        spares.push_front(cb);
+        self.spares.set(spares);
+        self.spare_count.set(self.spare_count.get() + 1);
+    }
+    /// Post spares until the dispatcher has `target` CBs
+    fn top_up(&self) {
+        while self.posted.get() < self.target.get() {
+            let mut spares = self.spares.take();
+            let cb = spares.pop_front();
+            self.spares.set(spares);
+            let Some(cb) = cb else { break };
+            self.spare_count.set(self.spare_count.get() - 1);
+            self.posted.set(self.posted.get() + 1);
+            intr_event_rdy(cb);
+        }
+    }
+    /// An event CB has been received from the dispatcher
+    fn event_received(&self) {
+        self.posted.set(self.posted.get().saturating_sub(1));
+        self.top_up();
+    }
+    /// An event has been handled, so its CB can be reused
+    fn event_done(&self, cb: CbHandleEvent) {
+        if self.posted.get() < self.target.get() || self.spare_count.get() < self.spare_target.get() {
+            self.add_spare(cb);
+            self.top_up();
+        }
+        // Otherwise, drop (and thus free) the excess CB
+    }
+}
+
 /// Trait for an interrupt handler endpoint
 pub trait IntrHandler: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit
 {
     async_method!(
         /// Handle an interrupt indication
-        ///
-        /// TODO: Translate flags - `UDI_INTR_MASKING_NOT_REQUIRED`, `UDI_INTR_OVERRUN_OCCURRED`, `UDI_INTR_PREPROCESSED`
-        fn intr_event_ind(&'a self, cb: CbRefEvent<'a>, flags: u8)->()
+        fn intr_event_ind(&'a self, cb: CbRefEvent<'a>, flags: IntrEventFlags)->()
         as Future_intr_event_ind
     );
+    /// Get the pool that event CBs are recycled through, if any
+    ///
+    /// Without a pool, each CB is returned to the dispatcher once the event has been handled.
+    fn intr_event_pool(&self) -> Option<&IntrEventPool> {
+        None
+    }
 }
 struct MarkerIntrHandler;
 impl<T> crate::imc::ChannelHandler<MarkerIntrHandler> for T
@@ -274,10 +594,16 @@ where
 }
 
 future_wrapper!(intr_event_ind_op => <T as IntrHandler>(cb: *mut udi_intr_event_cb_t, flags: u8) val @ {
-    val.intr_event_ind(cb, flags)
+    if let Some(pool) = val.intr_event_pool() {
+        pool.event_received();
+    }
+    val.intr_event_ind(cb, IntrEventFlags::from_raw(flags))
 } finally( () ) {
-    // Return this CB to the pool on completion
-    unsafe { crate::ffi::meta_bridge::udi_intr_event_rdy(cb) }
+    // Return this CB to the pool (or directly to the dispatcher) on completion
+    match val.intr_event_pool() {
+    Some(pool) => pool.event_done(unsafe { CbHandleEvent::from_raw(cb) }),
+    None => unsafe { crate::ffi::meta_bridge::udi_intr_event_rdy(cb) },
+    }
 });
 map_ops_structure!{
     ::udi_sys::meta_bridge::udi_intr_handler_ops_t => IntrHandler,MarkerIntrHandler {
@@ -320,3 +646,305 @@ map_ops_structure!{
         udi_intr_event_cb_t,
     }
 }
+
+/// Routes a child's interrupts to its interrupt handlers (the dispatcher side of [intr_attach]), for use by bus bridges
+///
+/// Holds up to `N` attached interrupts, each with the dispatcher end of the interrupt channel, the preprocessing handle,
+/// and the event CBs supplied by the handler. A bridge calls [IntrDispatchTable::attach] and
+/// [IntrDispatchTable::detach] from [BusBridge::intr_attach_req] and [BusBridge::intr_detach_req], passes CBs from
+/// [IntrDispatcher::intr_event_ret] to [IntrDispatchTable::event_ret], and calls [IntrDispatchTable::dispatch] when the
+/// device raises an interrupt.
+///
+/// The preprocessing handle is run on one event at a time for each interrupt, and events that it reports as
+/// [IntrResult::UNCLAIMED] or [IntrResult::NO_EVENT] (or where it fails) are not indicated. The table must not move
+/// while any interrupts are attached (e.g. keep it in region data, or behind an `Arc`).
+pub struct IntrDispatchTable<const N: usize> {
+    routes: [IntrRoute; N],
+}
+struct IntrRoute {
+    /// Attached interrupt index, `None` if this entry is free
+    interrupt_idx: Cell<Option<::udi_sys::udi_index_t>>,
+    /// Dispatcher end of the interrupt channel
+    channel: Cell<::udi_sys::udi_channel_t>,
+    preprocessing: Cell<crate::ffi::pio::udi_pio_handle_t>,
+    min_event_pend: Cell<u8>,
+    /// Event CBs available for indications
+    cbs: Cell<crate::cb::Chain<udi_intr_event_cb_t>>,
+    /// Set once `min_event_pend` CBs have been received
+    enabled: Cell<bool>,
+    /// Set when an interrupt is dropped due to no CBs being available, reported with the next event
+    overrun: Cell<bool>,
+    /// Event CB that the preprocessing handle is running on
+    ///
+    /// While it runs, the CB's `context` points at this entry (and is cleared if the entry is cleared first). The
+    /// dispatcher owns `context` while it holds the CB, whereas `initiator_context` belongs to the handler and
+    /// `scratch` is used by the trans list.
+    busy: Cell<*mut udi_intr_event_cb_t>,
+    /// Flags for the event being preprocessed on `busy`, `None` for the enable sequence
+    busy_flags: Cell<Option<IntrEventFlags>>,
+    /// Set when the interrupt fires while `busy` is running, so it is dispatched again once it completes
+    pending: Cell<bool>,
+}
+impl<const N: usize> Default for IntrDispatchTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<const N: usize> Drop for IntrDispatchTable<N> {
+    fn drop(&mut self) {
+        self.detach_all();
+    }
+}
+impl<const N: usize> IntrDispatchTable<N> {
+    /// Create an empty table
+    pub const fn new() -> Self {
+        IntrDispatchTable { routes: [const { IntrRoute::new() }; N] }
+    }
+
+    /// Check if interrupt `interrupt_idx` is attached
+    pub fn is_attached(&self, interrupt_idx: ::udi_sys::udi_index_t) -> bool {
+        self.find(interrupt_idx).is_some()
+    }
+    /// Check if interrupt `interrupt_idx` has received its `min_event_pend` CBs (see [IntrDispatchTable::event_ret])
+    pub fn is_enabled(&self, interrupt_idx: ::udi_sys::udi_index_t) -> bool {
+        self.find(interrupt_idx).is_some_and(|r| r.enabled.get())
+    }
+    /// Iterate the indexes of all attached interrupts
+    pub fn attached(&self) -> impl Iterator<Item=::udi_sys::udi_index_t> + '_ {
+        self.routes.iter().filter_map(|r| r.interrupt_idx.get())
+    }
+
+    /// Attach a handler as requested by `cb`, spawning the dispatcher end of the channel with the `Ops` ops
+    ///
+    /// Takes ownership of the preprocessing handle. Fails if the interrupt is already attached, or the table is full.
+    pub fn attach<'a, Ops>(
+        &'a self,
+        cb: CbRefIntrAttach<'a>,
+        context: &'a impl AsRef<Ops::Context>,
+    ) -> impl ::core::future::Future<Output=crate::Result<()>> + 'a
+    where
+        Ops: crate::ops_markers::Ops,
+    {
+        let interrupt_idx = cb.interrupt_index;
+        // Reserve an entry before waiting, the null channel means that no CBs can be routed to it yet
+        let route = if self.is_attached(interrupt_idx) {
+                Err(crate::ffi::UDI_STAT_CANNOT_BIND)
+            }
+            else if let Some(route) = self.routes.iter().find(|r| r.interrupt_idx.get().is_none()) {
+                route.interrupt_idx.set(Some(interrupt_idx));
+                Ok(route)
+            }
+            else {
+                Err(crate::ffi::UDI_STAT_RESOURCE_UNAVAIL)
+            };
+        async move {
+            let route = match route {
+                Ok(route) => route,
+                Err(status) => return crate::Error::from_status(status as _),
+                };
+            let channel = crate::imc::channel_spawn::<Ops>(cb.gcb(), context, interrupt_idx).await;
+            route.channel.set(channel.into_raw());
+            route.preprocessing.set(cb.preprocessing_handle);
+            route.min_event_pend.set(cb.min_event_pend);
+            Ok( () )
+        }
+    }
+    /// Detach interrupt `interrupt_idx`, closing the channel, and releasing the event CBs and preprocessing handle
+    ///
+    /// Returns `false` if the interrupt was not attached
+    pub fn detach(&self, interrupt_idx: ::udi_sys::udi_index_t) -> bool {
+        match self.find(interrupt_idx) {
+        Some(route) => { route.clear(); true },
+        None => false,
+        }
+    }
+    /// Detach all interrupts (e.g. when the child unbinds without detaching)
+    pub fn detach_all(&self) {
+        for route in &self.routes {
+            if route.interrupt_idx.get().is_some() {
+                route.clear();
+            }
+        }
+    }
+
+    /// Take an event CB returned by the handler
+    ///
+    /// Returns the interrupt index when the interrupt has just received `min_event_pend` CBs, at which point the
+    /// preprocessing handle (if any) has been run with label 0, and the bridge should start delivering interrupts.
+    pub fn event_ret(&self, cb: CbHandleEvent) -> Option<::udi_sys::udi_index_t> {
+        let channel = cb.gcb.channel;
+        let Some(route) = self.routes.iter().find(|r| r.interrupt_idx.get().is_some() && r.channel.get() == channel) else {
+            // Not for an attached interrupt (e.g. it raced with a detach), so just free it
+            return None;
+        };
+        let count = route.push_cb(cb);
+        if !route.enabled.get() && count >= route.min_event_pend.get() as usize {
+            route.enabled.set(true);
+            route.run_enable();
+            route.interrupt_idx.get()
+        }
+        else {
+            None
+        }
+    }
+    /// Indicate an interrupt to the handler attached to `interrupt_idx`
+    ///
+    /// Runs the preprocessing handle (with label 1) first if one was provided, and only indicates the event if the
+    /// result [has an event](IntrResult::has_event), otherwise the CB is kept for the next interrupt. If the handle is
+    /// still running for an earlier interrupt, this one is dispatched once it completes. Returns `false` if the interrupt
+    /// could not be indicated, either because it isn't attached and enabled or there were no event CBs (which is
+    /// reported to the handler as an overrun with the next event).
+    pub fn dispatch(&self, interrupt_idx: ::udi_sys::udi_index_t) -> bool {
+        self.find(interrupt_idx).is_some_and(|r| r.dispatch())
+    }
+
+    fn find(&self, interrupt_idx: ::udi_sys::udi_index_t) -> Option<&IntrRoute> {
+        self.routes.iter().find(|r| r.interrupt_idx.get() == Some(interrupt_idx))
+    }
+}
+impl IntrRoute {
+    const fn new() -> Self {
+        IntrRoute {
+            interrupt_idx: Cell::new(None),
+            channel: Cell::new(::core::ptr::null_mut()),
+            preprocessing: Cell::new(::core::ptr::null_mut()),
+            min_event_pend: Cell::new(0),
+            cbs: Cell::new(crate::cb::Chain::new()),
+            enabled: Cell::new(false),
+            overrun: Cell::new(false),
+            busy: Cell::new(::core::ptr::null_mut()),
+            busy_flags: Cell::new(None),
+            pending: Cell::new(false),
+        }
+    }
+    /// Add a CB to the list, returning the new number of CBs
+    fn push_cb(&self, cb: CbHandleEvent) -> usize {
+        let mut cbs = self.cbs.take();
+        cbs.push_front(cb);
+        let rv = cbs.count();
+        self.cbs.set(cbs);
+        rv
+    }
+    fn pop_cb(&self) -> Option<CbHandleEvent> {
+        let mut cbs = self.cbs.take();
+        let rv = cbs.pop_front();
+        self.cbs.set(cbs);
+        rv
+    }
+    /// See [IntrDispatchTable::dispatch]
+    fn dispatch(&self) -> bool {
+        if !self.enabled.get() {
+            return false;
+        }
+        if !self.busy.get().is_null() {
+            self.pending.set(true);
+            return true;
+        }
+        let Some(cb) = self.pop_cb() else {
+            self.overrun.set(true);
+            return false;
+        };
+        let flags = if self.overrun.replace(false) { IntrEventFlags::OVERRUN_OCCURRED } else { IntrEventFlags::NONE };
+        if self.preprocessing.get().is_null() {
+            let mut cb = cb;
+            // SAFE: Owned CB, and the channel is the one that the handler provided this CB on
+            unsafe {
+                cb.get_mut().gcb.channel = self.channel.get();
+                crate::ffi::meta_bridge::udi_intr_event_ind(cb.into_raw(), flags.to_raw())
+            }
+        }
+        else {
+            self.run_preprocessing(cb, Some(flags));
+        }
+        true
+    }
+    /// Run the preprocessing handle's enable sequence (label 0), using one of the event CBs
+    fn run_enable(&self) {
+        if self.preprocessing.get().is_null() {
+            return ;
+        }
+        if let Some(cb) = self.pop_cb() {
+            self.run_preprocessing(cb, None);
+        }
+    }
+    /// Run the preprocessing handle on `cb`, for an event with `flags` (label 1), or the enable sequence (label 0)
+    fn run_preprocessing(&self, mut cb: CbHandleEvent, flags: Option<IntrEventFlags>) {
+        let (label, buf) = match flags {
+            Some(_) => (1, cb.event_buf),
+            None => (0, ::core::ptr::null_mut()),
+            };
+        self.busy_flags.set(flags);
+        // SAFE: Owned CB, which is tracked by `busy` until the completion
+        unsafe {
+            let gcb = &mut cb.get_mut().gcb;
+            gcb.channel = self.channel.get();
+            gcb.context = self as *const Self as *mut _;
+            let cb = cb.into_raw();
+            self.busy.set(cb);
+            crate::ffi::pio::udi_pio_trans(
+                Self::preprocessing_complete, cb as *mut _,
+                self.preprocessing.get(), label.into(),
+                buf, ::core::ptr::null_mut()
+                );
+        }
+    }
+    unsafe extern "C" fn preprocessing_complete(gcb: *mut crate::ffi::udi_cb_t, buf: *mut crate::ffi::udi_buf_t, status: crate::ffi::udi_status_t, result: u16) {
+        let mut cb = CbHandleEvent::from_raw(gcb as *mut udi_intr_event_cb_t);
+        // SAFE: A non-null `context` points to the entry, which clears it before being cleared itself
+        let Some(route) = ((*gcb).context as *const Self).as_ref() else {
+            // The entry was cleared while this was running
+            drop(cb);
+            return ;
+        };
+        route.busy.set(::core::ptr::null_mut());
+        match route.busy_flags.take() {
+        None => {
+            route.push_cb(cb);
+            },
+        Some(flags) => {
+            cb.get_mut().event_buf = buf;
+            if status == crate::ffi::UDI_OK as _ && IntrResult::from_raw(result).has_event() {
+                cb.get_mut().intr_result = result;
+                crate::ffi::meta_bridge::udi_intr_event_ind(cb.into_raw(), (flags | IntrEventFlags::PREPROCESSED).to_raw());
+            }
+            else {
+                // Nothing for the handler, so keep the CB (and any overrun for the next event)
+                if flags.overrun_occurred() {
+                    route.overrun.set(true);
+                }
+                route.push_cb(cb);
+            }
+            },
+        }
+        if route.pending.replace(false) {
+            route.dispatch();
+        }
+    }
+    /// Release everything held by this entry, and mark it as free
+    fn clear(&self) {
+        self.interrupt_idx.set(None);
+        self.enabled.set(false);
+        self.overrun.set(false);
+        self.pending.set(false);
+        let mut cbs = self.cbs.take();
+        while let Some(cb) = cbs.pop_front() {
+            drop(cb);
+        }
+        // SAFE: These handles are owned by this entry
+        unsafe {
+            let busy = self.busy.replace(::core::ptr::null_mut());
+            if !busy.is_null() {
+                // Still running, the completion frees it
+                (*busy).gcb.context = ::core::ptr::null_mut();
+            }
+            let channel = self.channel.replace(::core::ptr::null_mut());
+            if !channel.is_null() {
+                crate::ffi::imc::udi_channel_close(channel);
+            }
+            let preprocessing = self.preprocessing.replace(::core::ptr::null_mut());
+            if !preprocessing.is_null() {
+                crate::ffi::pio::udi_pio_unmap(preprocessing);
+            }
+        }
+    }
+}
@@ -0,0 +1,1062 @@
+//! Graphics (GFX) metalanguage (draft)
+//!
+//! A graphics provider (the device driver) exposes a set of *connectors* (outputs, e.g. a VGA port or a memory
+//! buffer) and *engines* (stages of the pixel pipeline, e.g. a framebuffer), both addressed using [Element]. A client
+//! binds to the provider, learning how many of each exist (see [BindInfo]), then configures them by reading and
+//! writing [Property] values. The values a property accepts are queried using [range_req], and returned as a list of
+//! [Range]s.
+//!
+//! Engines can expose buffers (e.g. the framebuffer's memory), which are described by [buffer_info_req] and accessed
+//! a rectangle at a time using [buffer_read_req] and [buffer_write_req].
+use ::udi_sys::meta_gfx as ffi;
+
+impl_metalanguage!{
+    static METALANG_SPEC;
+    NAME udi_gfx;
+    OPS
+        1 => ffi::udi_gfx_provider_ops_t,
+        2 => ffi::udi_gfx_client_ops_t,
+        ;
+    CBS
+        1 => ffi::udi_gfx_bind_cb_t,
+        2 => ffi::udi_gfx_state_cb_t,
+        3 => ffi::udi_gfx_range_cb_t : BUF rangedata,
+        4 => ffi::udi_gfx_command_cb_t : BUF commanddata,
+        5 => ffi::udi_gfx_buffer_info_cb_t,
+        6 => ffi::udi_gfx_buffer_cb_t : BUF buffer,
+        ;
+}
+
+impl crate::ops_markers::ParentBind<ffi::udi_gfx_bind_cb_t> for ffi::udi_gfx_client_ops_t {
+    const ASSERT: () = ();
+}
+impl crate::ops_markers::ChildBind for ffi::udi_gfx_provider_ops_t {
+    const ASSERT: () = ();
+}
+
+/// Request unbind from the provider
+pub fn unbind_req(cb: crate::cb::CbHandle<ffi::udi_gfx_bind_cb_t>) {
+    unsafe { ffi::udi_gfx_unbind_req(cb.into_raw()) }
+}
+/// Set the value of a property on a connector or engine
+pub fn set_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_state_cb_t>, element: Element, prop: Property, value: u32) {
+    unsafe {
+        cb.get_mut().subsystem = element.index();
+        cb.get_mut().attribute = prop.to_raw();
+        match element {
+        Element::Connector(_) => ffi::udi_gfx_set_connector_req(cb.into_raw(), value),
+        Element::Engine(_) => ffi::udi_gfx_set_engine_req(cb.into_raw(), value),
+        }
+    }
+}
+/// Get the value of a property on a connector or engine
+pub fn get_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_state_cb_t>, element: Element, prop: Property) {
+    unsafe {
+        cb.get_mut().subsystem = element.index();
+        cb.get_mut().attribute = prop.to_raw();
+        match element {
+        Element::Connector(_) => ffi::udi_gfx_get_connector_req(cb.into_raw()),
+        Element::Engine(_) => ffi::udi_gfx_get_engine_req(cb.into_raw()),
+        }
+    }
+}
+/// Request the valid values of a property on a connector or engine (returned in the CB's buffer)
+pub fn range_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_range_cb_t>, element: Element, prop: Property) {
+    unsafe {
+        cb.get_mut().subsystem = element.index();
+        cb.get_mut().attribute = prop.to_raw();
+        match element {
+        Element::Connector(_) => ffi::udi_gfx_range_connector_req(cb.into_raw()),
+        Element::Engine(_) => ffi::udi_gfx_range_engine_req(cb.into_raw()),
+        }
+    }
+}
+/// Request entry `index` in the operator list of engine `engine`
+pub fn get_engine_operator_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_range_cb_t>, engine: u32, index: u32) {
+    unsafe {
+        cb.get_mut().subsystem = engine;
+        cb.get_mut().attribute = index;
+        ffi::udi_gfx_get_engine_operator_req(cb.into_raw())
+    }
+}
+/// Send a connector command sequence (in the CB's buffer)
+pub fn connector_command_req(cb: crate::cb::CbHandle<ffi::udi_gfx_command_cb_t>) {
+    unsafe { ffi::udi_gfx_connector_command_req(cb.into_raw()) }
+}
+/// Send an engine command sequence (in the CB's buffer)
+pub fn engine_command_req(cb: crate::cb::CbHandle<ffi::udi_gfx_command_cb_t>) {
+    unsafe { ffi::udi_gfx_engine_command_req(cb.into_raw()) }
+}
+/// Request the format of buffer `index`
+pub fn buffer_info_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_buffer_info_cb_t>, index: u32) {
+    unsafe {
+        cb.get_mut().buffer_index = index;
+        ffi::udi_gfx_buffer_info_req(cb.into_raw())
+    }
+}
+/// Read an area of a buffer into the CB's buffer
+pub fn buffer_read_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_buffer_cb_t>, area: BufferArea) {
+    unsafe {
+        area.write_cb(cb.get_mut());
+        ffi::udi_gfx_buffer_read_req(cb.into_raw())
+    }
+}
+/// Write the contents of the CB's buffer to an area of a buffer
+pub fn buffer_write_req(mut cb: crate::cb::CbHandle<ffi::udi_gfx_buffer_cb_t>, area: BufferArea) {
+    unsafe {
+        area.write_cb(cb.get_mut());
+        ffi::udi_gfx_buffer_write_req(cb.into_raw())
+    }
+}
+
+/// Define an enum over the values of a `udi_gfx` enumeration, with an `Other` variant for unknown values
+///
+/// Each variant's documentation is suffixed with the name of the raw value
+macro_rules! def_enum {
+    (
+        $(#[$a:meta])* $name:ident {
+            $( $(#[$va:meta])* $var:ident = $val:ident, )*
+        }
+    ) => {
+        $(#[$a])*
+        #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+        pub enum $name {
+            $( $(#[$va])* #[doc = concat!("(`", stringify!($val), "`)")] $var, )*
+            /// Unknown value
+            Other(u32),
+        }
+        impl $name {
+            /// Decode a raw value
+            pub fn from_raw(v: u32) -> Self {
+                $( if v == ffi::$val as u32 { return $name::$var; } )*
+                $name::Other(v)
+            }
+            /// Get the raw value
+            pub fn to_raw(self) -> u32 {
+                match self {
+                $( $name::$var => ffi::$val as u32, )*
+                $name::Other(v) => v,
+                }
+            }
+        }
+    };
+}
+
+def_enum!{
+    /// A connector or engine property (`UDI_GFX_PROP_*`)
+    Property {
+        /// State of the element, see [EnableState]
+        Enable = UDI_GFX_PROP_ENABLE,
+        /// Engine feeding this element, or [INPUT_NONE] for only black pixels
+        Input = UDI_GFX_PROP_INPUT,
+        /// Width in pixels
+        Width = UDI_GFX_PROP_WIDTH,
+        /// Height in pixels
+        Height = UDI_GFX_PROP_HEIGHT,
+        /// Handling of pixels outside of the image
+        Clip = UDI_GFX_PROP_CLIP,
+        /// Granularity of the width
+        UnitWidth = UDI_GFX_PROP_UNIT_WIDTH,
+        /// Granularity of the height
+        UnitHeight = UDI_GFX_PROP_UNIT_HEIGHT,
+        /// Horizontal offset of the image
+        TranslateX = UDI_GFX_PROP_TRANSLATEX,
+        /// Vertical offset of the image
+        TranslateY = UDI_GFX_PROP_TRANSLATEY,
+        /// Supported OpenGL version
+        GlVersion = UDI_GFX_PROP_GL_VERSION,
+        /// Supported OpenGL ES version
+        GlesVersion = UDI_GFX_PROP_GLES_VERSION,
+        /// Saved state block
+        StateBlock = UDI_GFX_PROP_STATE_BLOCK,
+        /// Number of bits per colour component
+        ColorBits = UDI_GFX_PROP_COLOR_BITS,
+        /// Output signal of a connector, see [SignalType]
+        Signal = UDI_GFX_PROP_SIGNAL,
+        /// Physical type of a connector, see [ConnectorType]
+        ConnectorType = UDI_GFX_PROP_CONNECTOR_TYPE,
+        /// VGA horizontal front porch
+        VgaHFrontPorch = UDI_GFX_PROP_VGA_H_FRONT_PORCH,
+        /// VGA horizontal back porch
+        VgaHBackPorch = UDI_GFX_PROP_VGA_H_BACK_PORCH,
+        /// VGA horizontal sync width
+        VgaHSync = UDI_GFX_PROP_VGA_H_SYNC,
+        /// VGA vertical front porch
+        VgaVFrontPorch = UDI_GFX_PROP_VGA_V_FRONT_PORCH,
+        /// VGA vertical back porch
+        VgaVBackPorch = UDI_GFX_PROP_VGA_V_BACK_PORCH,
+        /// VGA vertical sync width
+        VgaVSync = UDI_GFX_PROP_VGA_V_SYNC,
+        /// Pixel clock
+        DotClock = UDI_GFX_PROP_DOT_CLOCK,
+        /// VGA horizontal sync polarity
+        VgaHSyncPol = UDI_GFX_PROP_VGA_H_SYNC_POL,
+        /// VGA vertical sync polarity
+        VgaVSyncPol = UDI_GFX_PROP_VGA_V_SYNC_POL,
+    }
+}
+impl Property {
+    /// Device-specific property number `n`
+    pub fn custom(n: u32) -> Self {
+        Property::Other(ffi::UDI_GFX_PROP_CUSTOM as u32 + n)
+    }
+    /// Get the device-specific property number, if this is a custom property
+    pub fn custom_index(self) -> Option<u32> {
+        self.to_raw().checked_sub(ffi::UDI_GFX_PROP_CUSTOM as u32)
+    }
+}
+/// Value of [Property::Input] selecting no engine (the element only produces black pixels)
+pub const INPUT_NONE: u32 = !0;
+
+def_enum!{
+    /// Value of [Property::Enable]
+    EnableState {
+        /// Not producing output, but can be enabled quickly
+        Disabled = UDI_GFX_PROP_ENABLE_DISABLED,
+        /// Producing output
+        Enabled = UDI_GFX_PROP_ENABLE_ENABLED,
+        /// Powered down, with resources released
+        Reset = UDI_GFX_PROP_ENABLE_RESET,
+    }
+}
+def_enum!{
+    /// Value of [Property::Signal]
+    SignalType {
+        Integrated = UDI_GFX_SIGNAL_INTEGRATED,
+        Rgbhv = UDI_GFX_SIGNAL_RGBHV,
+        Rgbs = UDI_GFX_SIGNAL_RGBS,
+        Rgsb = UDI_GFX_SIGNAL_RGSB,
+        YPbPr = UDI_GFX_SIGNAL_YPBPR,
+        DviD = UDI_GFX_SIGNAL_DVID,
+        Yuv = UDI_GFX_SIGNAL_YUV,
+        Yiq = UDI_GFX_SIGNAL_YIQ,
+        YUv = UDI_GFX_SIGNAL_Y_UV,
+        YIq = UDI_GFX_SIGNAL_Y_IQ,
+        Hdmi = UDI_GFX_SIGNAL_HDMI,
+        Text = UDI_GFX_SIGNAL_TEXT,
+        Custom = UDI_GFX_SIGNAL_CUSTOM,
+    }
+}
+def_enum!{
+    /// Value of [Property::ConnectorType]
+    ConnectorType {
+        Hidden = UDI_GFX_CONNECTOR_HIDDEN,
+        Vga = UDI_GFX_CONNECTOR_VGA,
+        Dvi = UDI_GFX_CONNECTOR_DVI,
+        SVideo = UDI_GFX_CONNECTOR_SVIDEO,
+        Component = UDI_GFX_CONNECTOR_COMPONENT,
+        Hdmi = UDI_GFX_CONNECTOR_HDMI,
+        Rf = UDI_GFX_CONNECTOR_RF,
+        Scart = UDI_GFX_CONNECTOR_SCART,
+        Composite = UDI_GFX_CONNECTOR_COMPOSITE,
+        /// Output to a memory buffer
+        MemBuffer = UDI_GFX_CONNECTOR_MEMBUFFER,
+    }
+}
+def_enum!{
+    /// Stock pixel formats, for simple framebuffer devices
+    StockFormat {
+        Unknown = UDI_GFX_STOCK_FORMAT_UNKNOWN,
+        R8G8B8X8 = UDI_GFX_STOCK_FORMAT_R8G8B8X8,
+        B8G8R8X8 = UDI_GFX_STOCK_FORMAT_B8G8R8X8,
+        R8G8B8 = UDI_GFX_STOCK_FORMAT_R8G8B8,
+        B8G8R8 = UDI_GFX_STOCK_FORMAT_B8G8R8,
+        R5G6B5 = UDI_GFX_STOCK_FORMAT_R5G6B5,
+        B5G6R5 = UDI_GFX_STOCK_FORMAT_B5G6R5,
+        R5G5B5X1 = UDI_GFX_STOCK_FORMAT_R5G5B5X1,
+        B5G5R5X1 = UDI_GFX_STOCK_FORMAT_B5G5R5X1,
+        N8 = UDI_GFX_STOCK_FORMAT_N8,
+    }
+}
+def_enum!{
+    /// An engine operator (see [OperatorEntry], and the `UDI_GFX_OPERATOR_*` documentation for the semantics)
+    Operator {
+        Rgb = UDI_GFX_OPERATOR_RGB,
+        Yuv = UDI_GFX_OPERATOR_YUV,
+        Yiq = UDI_GFX_OPERATOR_YIQ,
+        I = UDI_GFX_OPERATOR_I,
+        Alpha = UDI_GFX_OPERATOR_ALPHA,
+        Add = UDI_GFX_OPERATOR_ADD,
+        Sub = UDI_GFX_OPERATOR_SUB,
+        Mul = UDI_GFX_OPERATOR_MUL,
+        Div = UDI_GFX_OPERATOR_DIV,
+        Mad = UDI_GFX_OPERATOR_MAD,
+        Frc = UDI_GFX_OPERATOR_FRC,
+        Shr = UDI_GFX_OPERATOR_SHR,
+        Shl = UDI_GFX_OPERATOR_SHL,
+        Ror = UDI_GFX_OPERATOR_ROR,
+        Rol = UDI_GFX_OPERATOR_ROL,
+        Sar = UDI_GFX_OPERATOR_SAR,
+        Sal = UDI_GFX_OPERATOR_SAL,
+        And = UDI_GFX_OPERATOR_AND,
+        Or = UDI_GFX_OPERATOR_OR,
+        Not = UDI_GFX_OPERATOR_NOT,
+        Xor = UDI_GFX_OPERATOR_XOR,
+        Neg = UDI_GFX_OPERATOR_NEG,
+        Seg = UDI_GFX_OPERATOR_SEG,
+        Range = UDI_GFX_OPERATOR_RANGE,
+        Const = UDI_GFX_OPERATOR_CONST,
+        Attr = UDI_GFX_OPERATOR_ATTR,
+        Switch = UDI_GFX_OPERATOR_SWITCH,
+        Buffer = UDI_GFX_OPERATOR_BUFFER,
+        X = UDI_GFX_OPERATOR_X,
+        Y = UDI_GFX_OPERATOR_Y,
+        Tx = UDI_GFX_OPERATOR_TX,
+        Ty = UDI_GFX_OPERATOR_TY,
+        TxOff = UDI_GFX_OPERATOR_TXOFF,
+        TyOff = UDI_GFX_OPERATOR_TYOFF,
+        Input = UDI_GFX_OPERATOR_INPUT,
+        DInput = UDI_GFX_OPERATOR_DINPUT,
+    }
+}
+
+/// An entry in an engine's operator list, as returned by [get_engine_operator_req]
+///
+/// Arguments refer either to other entries in the list (by index) or are immediate values, depending on the operator
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct OperatorEntry {
+    /// Operation performed
+    pub op: Operator,
+    /// Arguments (`a1`/`v1` to `a3`/`v3` in the operator documentation)
+    pub args: [u32; 3],
+}
+impl OperatorEntry {
+    /// Construct an entry
+    pub const fn new(op: Operator, a1: u32, a2: u32, a3: u32) -> Self {
+        OperatorEntry { op, args: [a1, a2, a3] }
+    }
+}
+
+/// A connector or engine, addressed by its index
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Element {
+    /// An output
+    Connector(u32),
+    /// A stage of the pixel pipeline
+    Engine(u32),
+}
+impl Element {
+    /// Index of the connector or engine (the CB's `subsystem` field)
+    pub fn index(self) -> u32 {
+        match self {
+        Element::Connector(i) => i,
+        Element::Engine(i) => i,
+        }
+    }
+}
+
+/// Number of connectors and engines exposed by a provider, returned by the binding
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct BindInfo {
+    /// Number of connectors
+    pub connectors: u8,
+    /// Number of engines
+    pub engines: u8,
+}
+impl BindInfo {
+    /// Iterate over all connectors, then all engines
+    pub fn elements(self) -> impl Iterator<Item=Element> {
+        let c = (0 .. self.connectors as u32).map(Element::Connector);
+        let e = (0 .. self.engines as u32).map(Element::Engine);
+        c.chain(e)
+    }
+}
+
+/// A set of valid values for a property, as returned by [range_req]
+///
+/// Contains every `v` where `min <= v <= max` and `v - min` is a multiple of `step`. The range data buffer is a
+/// sequence of these, each encoded as three native-endian `udi_ubit32_t`s.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Range {
+    /// Lowest valid value
+    pub min: u32,
+    /// Highest valid value (inclusive)
+    pub max: u32,
+    /// Spacing between valid values
+    pub step: u32,
+}
+impl Range {
+    /// Size of an encoded range, in bytes
+    pub const SIZE: usize = 3*4;
+
+    /// A range containing only `v`
+    pub fn single(v: u32) -> Self {
+        Range { min: v, max: v, step: 1 }
+    }
+    /// Every value in `min ..= max`
+    pub fn inclusive(min: u32, max: u32) -> Self {
+        Range { min, max, step: 1 }
+    }
+    /// Every multiple of `step` (offset by `min`) in `min ..= max`
+    pub fn stepped(min: u32, max: u32, step: u32) -> Self {
+        Range { min, max, step }
+    }
+    /// Check if `v` is within this range (a zero `step` only allows `min`)
+    pub fn contains(&self, v: u32) -> bool {
+        if v < self.min || v > self.max {
+            false
+        }
+        else if self.step == 0 {
+            v == self.min
+        }
+        else {
+            (v - self.min).is_multiple_of(self.step)
+        }
+    }
+    /// Check if `v` is within any of `ranges`
+    pub fn list_contains(ranges: &[Range], v: u32) -> bool {
+        ranges.iter().any(|r| r.contains(v))
+    }
+
+    /// Decode from the range data format
+    pub fn from_bytes(b: &[u8; Self::SIZE]) -> Self {
+        let w = |i: usize| u32::from_ne_bytes([b[i*4], b[i*4+1], b[i*4+2], b[i*4+3]]);
+        Range { min: w(0), max: w(1), step: w(2) }
+    }
+    /// Encode to the range data format
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut rv = [0; Self::SIZE];
+        rv[0..4].copy_from_slice(&self.min.to_ne_bytes());
+        rv[4..8].copy_from_slice(&self.max.to_ne_bytes());
+        rv[8..12].copy_from_slice(&self.step.to_ne_bytes());
+        rv
+    }
+}
+/// Replace the contents of `buf` with the encoded `ranges`
+pub async fn write_ranges(gcb: crate::CbRef<'_, crate::ffi::udi_cb_t>, buf: &mut crate::buf::Handle, ranges: &[Range]) {
+    buf.truncate(0);
+    for r in ranges {
+        let ofs = buf.len();
+        buf.write(gcb, ofs..ofs, &r.to_bytes()).await;
+    }
+}
+/// Iterator over the ranges in a range data buffer, see [crate::cb::CbRef::ranges]
+pub struct Ranges<'a> {
+    buf: &'a crate::buf::Handle,
+    ofs: usize,
+}
+impl Iterator for Ranges<'_> {
+    type Item = Range;
+    fn next(&mut self) -> Option<Range> {
+        if self.ofs + Range::SIZE > self.buf.len() {
+            return None;
+        }
+        let mut b = [0; Range::SIZE];
+        self.buf.read(self.ofs, &mut b);
+        self.ofs += Range::SIZE;
+        Some(Range::from_bytes(&b))
+    }
+}
+
+def_flags!{
+    /// Buffer access flags, as returned by [buffer_info_req]
+    BufferFlags: u32 {
+        /// The buffer can be read (`UDI_GFX_BUFFER_INFO_FLAG_R`)
+        READ = ffi::UDI_GFX_BUFFER_INFO_FLAG_R,
+        /// The buffer can be written (`UDI_GFX_BUFFER_INFO_FLAG_W`)
+        WRITE = ffi::UDI_GFX_BUFFER_INFO_FLAG_W,
+        /// Entries smaller than a byte are padded to a byte boundary (`UDI_GFX_BUFFER_INFO_FLAG_BITALIGN_ENTRY`)
+        BITALIGN_ENTRY = ffi::UDI_GFX_BUFFER_INFO_FLAG_BITALIGN_ENTRY,
+        /// Each row starts on a byte boundary (`UDI_GFX_BUFFER_INFO_FLAG_BITALIGN_ROW`)
+        BITALIGN_ROW = ffi::UDI_GFX_BUFFER_INFO_FLAG_BITALIGN_ROW,
+    }
+}
+/// Format of a buffer, as returned by [buffer_info_req]
+///
+/// A buffer that does not exist is reported with a zero size and no flags (see [BufferInfo::is_valid])
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct BufferInfo {
+    /// Width in entries
+    pub width: u32,
+    /// Height in entries
+    pub height: u32,
+    /// Size of each entry, in bits
+    pub bits_per_entry: u32,
+    /// Access flags
+    pub flags: BufferFlags,
+}
+impl BufferInfo {
+    /// Check if the buffer exists
+    pub fn is_valid(&self) -> bool {
+        self.width != 0 && self.height != 0
+    }
+    /// Size of a row in bytes, assuming rows are padded to a byte boundary
+    pub fn row_bytes(&self) -> usize {
+        (self.width as usize * self.bits_per_entry as usize).div_ceil(8)
+    }
+    /// Size of `area` in bytes, assuming entries are a multiple of 8 bits
+    pub fn area_bytes(&self, area: &BufferArea) -> usize {
+        area.width as usize * area.height as usize * (self.bits_per_entry as usize / 8)
+    }
+    /// Check that `area` is entirely within the buffer
+    pub fn contains(&self, area: &BufferArea) -> bool {
+        let x_end = area.x as u64 + area.width as u64;
+        let y_end = area.y as u64 + area.height as u64;
+        x_end <= self.width as u64 && y_end <= self.height as u64
+    }
+}
+
+/// A rectangle within a buffer, for [buffer_read_req] and [buffer_write_req]
+///
+/// The data is transferred a row at a time, with no padding between rows.
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct BufferArea {
+    /// Buffer index
+    pub index: u32,
+    /// Left edge, in entries
+    pub x: u32,
+    /// Top edge, in entries
+    pub y: u32,
+    /// Width, in entries
+    pub width: u32,
+    /// Height, in entries
+    pub height: u32,
+}
+impl BufferArea {
+    /// Construct an area of buffer `index`
+    pub fn new(index: u32, x: u32, y: u32, width: u32, height: u32) -> Self {
+        BufferArea { index, x, y, width, height }
+    }
+    /// Decode from a buffer CB
+    pub fn from_cb(cb: &ffi::udi_gfx_buffer_cb_t) -> Self {
+        BufferArea { index: cb.buffer_index, x: cb.x, y: cb.y, width: cb.width, height: cb.height }
+    }
+    /// Populate a buffer CB
+    pub fn write_cb(&self, cb: &mut ffi::udi_gfx_buffer_cb_t) {
+        cb.buffer_index = self.index;
+        cb.x = self.x;
+        cb.y = self.y;
+        cb.width = self.width;
+        cb.height = self.height;
+    }
+}
+
+impl crate::cb::CbRef<'_, ffi::udi_gfx_range_cb_t>
+{
+    /// Get the range data buffer
+    pub fn range_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.rangedata) }
+    }
+    /// Iterate the ranges in the range data buffer (valid in [Client::range_ack])
+    pub fn ranges(&self) -> Ranges<'_> {
+        Ranges { buf: self.range_buf(), ofs: 0 }
+    }
+}
+impl crate::cb::CbRef<'_, ffi::udi_gfx_command_cb_t>
+{
+    /// Get the command data buffer
+    pub fn command_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.commanddata) }
+    }
+}
+impl crate::cb::CbHandle<ffi::udi_gfx_command_cb_t>
+{
+    /// Get a mutable handle to the command data buffer
+    pub fn command_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().commanddata) }
+    }
+}
+impl crate::cb::CbRef<'_, ffi::udi_gfx_buffer_cb_t>
+{
+    /// Get the data buffer
+    pub fn data_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.buffer) }
+    }
+    /// Decode the requested area
+    pub fn area(&self) -> BufferArea {
+        BufferArea::from_cb(self)
+    }
+}
+impl crate::cb::CbHandle<ffi::udi_gfx_buffer_cb_t>
+{
+    /// Get a mutable handle to the data buffer
+    pub fn data_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().buffer) }
+    }
+}
+
+/// Client side of the graphics metalanguage
+pub trait Client: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    async_method!(
+        /// Acknowledgement of the binding, with the number of connectors and engines
+        fn bind_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_bind_cb_t>, res: crate::Result<BindInfo>)->()
+        as Future_bind_ack
+    );
+    async_method!(
+        /// Acknowledgement of [unbind_req]
+        fn unbind_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_bind_cb_t>)->()
+        as Future_unbind_ack
+    );
+    async_method!(
+        /// Acknowledgement of [set_req]
+        fn set_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_state_cb_t>, element: Element, prop: Property, res: crate::Result<()>)->()
+        as Future_set_ack
+    );
+    async_method!(
+        /// Current value of a property, requested with [get_req]
+        fn get_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_state_cb_t>, element: Element, prop: Property, value: u32)->()
+        as Future_get_ack
+    );
+    async_method!(
+        /// Valid values of a property, in the CB's buffer (see [crate::cb::CbRef::ranges])
+        fn range_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_range_cb_t>, element: Element, prop: Property)->()
+        as Future_range_ack
+    );
+    async_method!(
+        /// Entry `index` of the operator list for engine `engine`
+        fn get_engine_operator_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_range_cb_t>, engine: u32, index: u32, entry: OperatorEntry)->()
+        as Future_get_engine_operator_ack
+    );
+    async_method!(
+        /// A connector command sequence has been processed
+        fn connector_command_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_command_cb_t>)->()
+        as Future_connector_command_ack
+    );
+    async_method!(
+        /// An engine command sequence has been processed
+        fn engine_command_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_command_cb_t>)->()
+        as Future_engine_command_ack
+    );
+    async_method!(
+        /// Format of buffer `index`
+        fn buffer_info_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_buffer_info_cb_t>, index: u32, info: BufferInfo)->()
+        as Future_buffer_info_ack
+    );
+    async_method!(
+        /// Completion of [buffer_read_req], with the data in the CB's buffer
+        fn buffer_read_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_buffer_cb_t>, area: BufferArea, res: crate::Result<()>)->()
+        as Future_buffer_read_ack
+    );
+    async_method!(
+        /// Completion of [buffer_write_req]
+        fn buffer_write_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_buffer_cb_t>, area: BufferArea, res: crate::Result<()>)->()
+        as Future_buffer_write_ack
+    );
+    /// Release the CB used for [unbind_req]
+    fn bind_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gfx_bind_cb_t>) { let _ = cb; }
+    /// Release a CB used for a property request
+    fn state_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gfx_state_cb_t>) { let _ = cb; }
+    /// Release a CB used for a range or operator request
+    fn range_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gfx_range_cb_t>) { let _ = cb; }
+    /// Release a CB used for a command sequence
+    fn command_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gfx_command_cb_t>) { let _ = cb; }
+    /// Release a CB used for a buffer format request
+    fn buffer_info_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gfx_buffer_info_cb_t>) { let _ = cb; }
+    /// Release a CB used for a buffer read or write
+    fn buffer_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gfx_buffer_cb_t>) { let _ = cb; }
+}
+/// Provider (device driver) side of the graphics metalanguage
+pub trait Provider: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    async_method!(
+        /// A binding has been requested, returns the number of connectors and engines
+        fn bind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_bind_cb_t>)->crate::Result<BindInfo>
+        as Future_bind_req
+    );
+    async_method!(
+        /// The client is unbinding
+        fn unbind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_bind_cb_t>)->()
+        as Future_unbind_req
+    );
+    async_method!(
+        /// Set a property, failing with `UDI_STAT_NOT_SUPPORTED` if the value is not in its range
+        fn set_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_state_cb_t>, element: Element, prop: Property, value: u32)->crate::Result<()>
+        as Future_set_req
+    );
+    async_method!(
+        /// Get the value of a property (unknown properties or elements should return zero)
+        fn get_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_state_cb_t>, element: Element, prop: Property)->u32
+        as Future_get_req
+    );
+    async_method!(
+        /// Populate `ranges` with the valid values of a property (see [write_ranges])
+        fn range_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_range_cb_t>, element: Element, prop: Property, ranges: &'s mut crate::buf::Handle)->()
+        as Future_range_req
+    );
+    async_method!(
+        /// Get entry `index` of the operator list for engine `engine`
+        fn get_engine_operator_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_range_cb_t>, engine: u32, index: u32)->OperatorEntry
+        as Future_get_engine_operator_req
+    );
+    async_method!(
+        /// Process a connector command sequence
+        fn connector_command_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_command_cb_t>, data: &'s mut crate::buf::Handle)->()
+        as Future_connector_command_req
+    );
+    async_method!(
+        /// Process an engine command sequence
+        fn engine_command_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_command_cb_t>, data: &'s mut crate::buf::Handle)->()
+        as Future_engine_command_req
+    );
+    async_method!(
+        /// Get the format of buffer `index` (see [BufferInfo] for missing buffers)
+        fn buffer_info_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_buffer_info_cb_t>, index: u32)->BufferInfo
+        as Future_buffer_info_req
+    );
+    async_method!(
+        /// Read `area` of a buffer into `data`, replacing its contents
+        fn buffer_read_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_buffer_cb_t>, area: BufferArea, data: &'s mut crate::buf::Handle)->crate::Result<()>
+        as Future_buffer_read_req
+    );
+    async_method!(
+        /// Write `data` to `area` of a buffer, failing with `UDI_STAT_DATA_UNDERRUN` if it is too short
+        fn buffer_write_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gfx_buffer_cb_t>, area: BufferArea, data: &'s crate::buf::Handle)->crate::Result<()>
+        as Future_buffer_write_req
+    );
+}
+
+struct MarkerClient;
+impl<T> crate::imc::ChannelHandler<MarkerClient> for T
+where
+    T: Client
+{
+    fn channel_bound(&self, params: &crate::ffi::imc::udi_channel_event_cb_t_params) {
+        unsafe {
+            ffi::udi_gfx_bind_req(params.parent_bound.bind_cb as *mut ffi::udi_gfx_bind_cb_t);
+        }
+    }
+}
+future_wrapper!(gfx_bind_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_bind_cb_t,
+    connectors: crate::ffi::udi_index_t,
+    engines: crate::ffi::udi_index_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.bind_ack(cb, crate::Error::from_status(status).map(|()| BindInfo { connectors: connectors.0, engines: engines.0 }))
+} finally( () ) {
+    unsafe { crate::async_trickery::channel_event_complete::<T,ffi::udi_gfx_bind_cb_t>(cb, crate::ffi::UDI_OK as _) }
+});
+future_wrapper!(gfx_unbind_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_bind_cb_t
+) val @ {
+    val.unbind_ack(cb)
+} finally( () ) {
+    val.bind_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_set_connector_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_state_cb_t
+) val @ {
+    val.set_ack(cb, Element::Connector(cb.subsystem), Property::from_raw(cb.attribute), Ok(()))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_set_engine_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_state_cb_t
+) val @ {
+    val.set_ack(cb, Element::Engine(cb.subsystem), Property::from_raw(cb.attribute), Ok(()))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_set_connector_nak_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_state_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.set_ack(cb, Element::Connector(cb.subsystem), Property::from_raw(cb.attribute), crate::Error::from_status(status))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_set_engine_nak_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_state_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.set_ack(cb, Element::Engine(cb.subsystem), Property::from_raw(cb.attribute), crate::Error::from_status(status))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_get_connector_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_state_cb_t,
+    value: crate::ffi::udi_ubit32_t
+) val @ {
+    val.get_ack(cb, Element::Connector(cb.subsystem), Property::from_raw(cb.attribute), value)
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_get_engine_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_state_cb_t,
+    value: crate::ffi::udi_ubit32_t
+) val @ {
+    val.get_ack(cb, Element::Engine(cb.subsystem), Property::from_raw(cb.attribute), value)
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_range_connector_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_range_cb_t
+) val @ {
+    val.range_ack(cb, Element::Connector(cb.subsystem), Property::from_raw(cb.attribute))
+} finally( () ) {
+    val.range_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_range_engine_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_range_cb_t
+) val @ {
+    val.range_ack(cb, Element::Engine(cb.subsystem), Property::from_raw(cb.attribute))
+} finally( () ) {
+    val.range_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_get_engine_operator_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_range_cb_t,
+    op: crate::ffi::udi_ubit32_t,
+    arg1: crate::ffi::udi_ubit32_t,
+    arg2: crate::ffi::udi_ubit32_t,
+    arg3: crate::ffi::udi_ubit32_t
+) val @ {
+    val.get_engine_operator_ack(cb, cb.subsystem, cb.attribute, OperatorEntry::new(Operator::from_raw(op), arg1, arg2, arg3))
+} finally( () ) {
+    val.range_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_connector_command_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_command_cb_t
+) val @ {
+    val.connector_command_ack(cb)
+} finally( () ) {
+    val.command_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_engine_command_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_command_cb_t
+) val @ {
+    val.engine_command_ack(cb)
+} finally( () ) {
+    val.command_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_buffer_info_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_buffer_info_cb_t,
+    width: crate::ffi::udi_ubit32_t,
+    height: crate::ffi::udi_ubit32_t,
+    bitsper: crate::ffi::udi_ubit32_t,
+    flags: crate::ffi::udi_ubit32_t
+) val @ {
+    let info = BufferInfo { width, height, bits_per_entry: bitsper, flags: BufferFlags::from_raw(flags) };
+    val.buffer_info_ack(cb, cb.buffer_index, info)
+} finally( () ) {
+    val.buffer_info_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_buffer_read_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_buffer_cb_t
+) val @ {
+    val.buffer_read_ack(cb, cb.area(), Ok(()))
+} finally( () ) {
+    val.buffer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_buffer_write_ack_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_buffer_cb_t
+) val @ {
+    val.buffer_write_ack(cb, cb.area(), Ok(()))
+} finally( () ) {
+    val.buffer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_buffer_read_nak_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_buffer_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.buffer_read_ack(cb, cb.area(), crate::Error::from_status(status))
+} finally( () ) {
+    val.buffer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(gfx_buffer_write_nak_op => <T as Client>(
+    cb: *mut ffi::udi_gfx_buffer_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.buffer_write_ack(cb, cb.area(), crate::Error::from_status(status))
+} finally( () ) {
+    val.buffer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+map_ops_structure!{
+    ffi::udi_gfx_client_ops_t => Client,MarkerClient {
+        gfx_bind_ack_op,
+        gfx_unbind_ack_op,
+        gfx_set_connector_ack_op,
+        gfx_set_engine_ack_op,
+        gfx_set_connector_nak_op,
+        gfx_set_engine_nak_op,
+        gfx_get_connector_ack_op,
+        gfx_get_engine_ack_op,
+        gfx_range_connector_ack_op,
+        gfx_range_engine_ack_op,
+        gfx_get_engine_operator_ack_op,
+        gfx_connector_command_ack_op,
+        gfx_engine_command_ack_op,
+        gfx_buffer_info_ack_op,
+        gfx_buffer_read_ack_op,
+        gfx_buffer_write_ack_op,
+        gfx_buffer_read_nak_op,
+        gfx_buffer_write_nak_op,
+    }
+    CBS {
+        ffi::udi_gfx_bind_cb_t,
+        ffi::udi_gfx_state_cb_t,
+        ffi::udi_gfx_range_cb_t,
+        ffi::udi_gfx_command_cb_t,
+        ffi::udi_gfx_buffer_info_cb_t,
+        ffi::udi_gfx_buffer_cb_t,
+    }
+}
+
+struct MarkerProvider;
+impl<T> crate::imc::ChannelHandler<MarkerProvider> for T
+where
+    T: Provider
+{
+}
+future_wrapper!(gfx_bind_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_bind_cb_t
+) val @ {
+    val.bind_req(cb)
+} finally( res ) {
+    let info = *res.as_ref().unwrap_or(&BindInfo::default());
+    let (c, e) = (crate::ffi::udi_index_t(info.connectors), crate::ffi::udi_index_t(info.engines));
+    unsafe { ffi::udi_gfx_bind_ack(cb, c, e, crate::Error::to_status(res.map(|_| ()))) }
+});
+future_wrapper!(gfx_unbind_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_bind_cb_t
+) val @ {
+    val.unbind_req(cb)
+} finally( () ) {
+    unsafe { ffi::udi_gfx_unbind_ack(cb) }
+});
+future_wrapper!(gfx_set_connector_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_state_cb_t,
+    value: crate::ffi::udi_ubit32_t
+) val @ {
+    val.set_req(cb, Element::Connector(cb.subsystem), Property::from_raw(cb.attribute), value)
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::udi_gfx_set_connector_ack(cb) },
+    Err(e) => unsafe { ffi::udi_gfx_set_connector_nak(cb, e.into_inner()) },
+    }
+});
+future_wrapper!(gfx_set_engine_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_state_cb_t,
+    value: crate::ffi::udi_ubit32_t
+) val @ {
+    val.set_req(cb, Element::Engine(cb.subsystem), Property::from_raw(cb.attribute), value)
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::udi_gfx_set_engine_ack(cb) },
+    Err(e) => unsafe { ffi::udi_gfx_set_engine_nak(cb, e.into_inner()) },
+    }
+});
+future_wrapper!(gfx_get_connector_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_state_cb_t
+) val @ {
+    val.get_req(cb, Element::Connector(cb.subsystem), Property::from_raw(cb.attribute))
+} finally( value ) {
+    unsafe { ffi::udi_gfx_get_connector_ack(cb, value) }
+});
+future_wrapper!(gfx_get_engine_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_state_cb_t
+) val @ {
+    val.get_req(cb, Element::Engine(cb.subsystem), Property::from_raw(cb.attribute))
+} finally( value ) {
+    unsafe { ffi::udi_gfx_get_engine_ack(cb, value) }
+});
+future_wrapper!(gfx_range_connector_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_range_cb_t
+) val @ {
+    let (element, prop) = (Element::Connector(cb.subsystem), Property::from_raw(cb.attribute));
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let buf = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).rangedata) };
+    val.range_req(cb, element, prop, buf)
+} finally( () ) {
+    unsafe { ffi::udi_gfx_range_connector_ack(cb) }
+});
+future_wrapper!(gfx_range_engine_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_range_cb_t
+) val @ {
+    let (element, prop) = (Element::Engine(cb.subsystem), Property::from_raw(cb.attribute));
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let buf = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).rangedata) };
+    val.range_req(cb, element, prop, buf)
+} finally( () ) {
+    unsafe { ffi::udi_gfx_range_engine_ack(cb) }
+});
+future_wrapper!(gfx_get_engine_operator_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_range_cb_t
+) val @ {
+    val.get_engine_operator_req(cb, cb.subsystem, cb.attribute)
+} finally( entry ) {
+    let [a1, a2, a3] = entry.args;
+    unsafe { ffi::udi_gfx_get_engine_operator_ack(cb, entry.op.to_raw(), a1, a2, a3) }
+});
+future_wrapper!(gfx_connector_command_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_command_cb_t
+) val @ {
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let data = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).commanddata) };
+    val.connector_command_req(cb, data)
+} finally( () ) {
+    unsafe { ffi::udi_gfx_connector_command_ack(cb) }
+});
+future_wrapper!(gfx_engine_command_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_command_cb_t
+) val @ {
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let data = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).commanddata) };
+    val.engine_command_req(cb, data)
+} finally( () ) {
+    unsafe { ffi::udi_gfx_engine_command_ack(cb) }
+});
+future_wrapper!(gfx_buffer_info_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_buffer_info_cb_t
+) val @ {
+    val.buffer_info_req(cb, cb.buffer_index)
+} finally( info ) {
+    unsafe { ffi::udi_gfx_buffer_info_ack(cb, info.width, info.height, info.bits_per_entry, info.flags.to_raw()) }
+});
+future_wrapper!(gfx_buffer_read_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_buffer_cb_t
+) val @ {
+    let area = cb.area();
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let data = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).buffer) };
+    val.buffer_read_req(cb, area, data)
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::udi_gfx_buffer_read_ack(cb) },
+    Err(e) => unsafe { ffi::udi_gfx_buffer_read_nak(cb, e.into_inner()) },
+    }
+});
+future_wrapper!(gfx_buffer_write_req_op => <T as Provider>(
+    cb: *mut ffi::udi_gfx_buffer_cb_t
+) val @ {
+    let area = cb.area();
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let data = unsafe { crate::buf::Handle::from_ref(&(*cb.to_raw()).buffer) };
+    val.buffer_write_req(cb, area, data)
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::udi_gfx_buffer_write_ack(cb) },
+    Err(e) => unsafe { ffi::udi_gfx_buffer_write_nak(cb, e.into_inner()) },
+    }
+});
+map_ops_structure!{
+    ffi::udi_gfx_provider_ops_t => Provider,MarkerProvider {
+        gfx_bind_req_op,
+        gfx_unbind_req_op,
+        gfx_set_connector_req_op,
+        gfx_set_engine_req_op,
+        gfx_get_connector_req_op,
+        gfx_get_engine_req_op,
+        gfx_range_connector_req_op,
+        gfx_range_engine_req_op,
+        gfx_get_engine_operator_req_op,
+        gfx_connector_command_req_op,
+        gfx_engine_command_req_op,
+        gfx_buffer_info_req_op,
+        gfx_buffer_read_req_op,
+        gfx_buffer_write_req_op,
+    }
+    CBS {
+        ffi::udi_gfx_bind_cb_t,
+        ffi::udi_gfx_state_cb_t,
+        ffi::udi_gfx_range_cb_t,
+        ffi::udi_gfx_command_cb_t,
+        ffi::udi_gfx_buffer_info_cb_t,
+        ffi::udi_gfx_buffer_cb_t,
+    }
+}
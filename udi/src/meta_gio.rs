@@ -5,10 +5,227 @@
 use ::udi_sys::meta_gio::*;
 use ::udi_sys::meta_gio as ffi;
 
+pub mod block;
+
 /// Dispatch a transfer request CB to the other end of the associated channel
 pub fn xfer_req(cb: crate::cb::CbHandle<ffi::udi_gio_xfer_cb_t>) {
     unsafe { ffi::udi_gio_xfer_req(cb.into_raw()) }
 }
+/// Acknowledge successful completion of a transfer (from a provider)
+pub fn xfer_ack(cb: crate::cb::CbHandle<ffi::udi_gio_xfer_cb_t>) {
+    unsafe { ffi::udi_gio_xfer_ack(cb.into_raw()) }
+}
+/// Report a failed (or partial) transfer to the client
+pub fn xfer_nak(cb: crate::cb::CbHandle<ffi::udi_gio_xfer_cb_t>, status: crate::Error) {
+    unsafe { ffi::udi_gio_xfer_nak(cb.into_raw(), status.into_inner()) }
+}
+/// Indicate an event to the client, which responds with [Provider::event_res]
+pub fn event_ind(mut cb: crate::cb::CbHandle<ffi::udi_gio_event_cb_t>, event: GioEvent) {
+    unsafe {
+        cb.get_mut().event_code = event.code;
+        ffi::udi_gio_event_ind(cb.into_raw())
+    }
+}
+
+/// Allocate a transfer CB (of type `CbDef`) for `op`, with `params` as its inline `tr_params`
+///
+/// Panics if `P` is not the parameter type for `op` (see [TrParams::valid_for])
+pub async fn alloc_xfer<CbDef, P>(gcb: crate::CbRef<'_, crate::ffi::udi_cb_t>, channel: crate::ffi::udi_channel_t, op: GioOp, params: P)
+    -> crate::cb::CbHandle<ffi::udi_gio_xfer_cb_t>
+where
+    CbDef: crate::cb::CbDefinition<Cb=ffi::udi_gio_xfer_cb_t>,
+    P: TrParams,
+{
+    assert!(P::valid_for(op), "{} is not the parameter type for {:?}", ::core::any::type_name::<P>(), op);
+    // SAFE: `P::LAYOUT` describes `P` (`InlineParams` is an unsafe trait)
+    let mut cb = unsafe { crate::cb::alloc_dynamic::<CbDef>(gcb, channel, ::core::mem::size_of::<P>(), P::LAYOUT) }.await;
+    // SAFE: The inline data was allocated with the size and layout of `P`
+    unsafe {
+        let c = cb.get_mut();
+        c.op = op.to_raw();
+        ::core::ptr::write(c.tr_params as *mut P, params);
+    }
+    cb
+}
+/// Allocate an event CB (of type `CbDef`) for `event`, with `params` as its inline `event_params`
+pub async fn alloc_event<CbDef, P>(gcb: crate::CbRef<'_, crate::ffi::udi_cb_t>, channel: crate::ffi::udi_channel_t, event: GioEvent, params: P)
+    -> crate::cb::CbHandle<ffi::udi_gio_event_cb_t>
+where
+    CbDef: crate::cb::CbDefinition<Cb=ffi::udi_gio_event_cb_t>,
+    P: InlineParams,
+{
+    // SAFE: `P::LAYOUT` describes `P` (`InlineParams` is an unsafe trait)
+    let mut cb = unsafe { crate::cb::alloc_dynamic::<CbDef>(gcb, channel, ::core::mem::size_of::<P>(), P::LAYOUT) }.await;
+    // SAFE: The inline data was allocated with the size and layout of `P`
+    unsafe {
+        let c = cb.get_mut();
+        c.event_code = event.code;
+        ::core::ptr::write(c.event_params as *mut P, params);
+    }
+    cb
+}
+
+/// GIO transfer operation (`udi_gio_xfer_cb_t.op`)
+///
+/// The raw value is a 6-bit operation code, combined with the [UDI_GIO_DIR_READ] and [UDI_GIO_DIR_WRITE] flags.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum GioOp {
+    /// Read from the device (`UDI_GIO_OP_READ`), with [RwParams]
+    Read,
+    /// Write to the device (`UDI_GIO_OP_WRITE`), with [RwParams]
+    Write,
+    /// Another standard operation (code below [UDI_GIO_OP_CUSTOM]), as the raw value
+    Standard(u8),
+    /// Driver-specific operation (code from [UDI_GIO_OP_CUSTOM] up to [UDI_GIO_OP_MAX]), as the raw value
+    Custom(u8),
+}
+impl GioOp {
+    const CODE_MASK: u8 = UDI_GIO_OP_MAX - 1;
+    /// Create a driver-specific operation, `index` is the offset from [UDI_GIO_OP_CUSTOM]
+    ///
+    /// Panics if the code is outside the custom range
+    pub fn custom(index: u8, read: bool, write: bool) -> Self {
+        assert!(index < UDI_GIO_OP_MAX - UDI_GIO_OP_CUSTOM, "Custom GIO operation {} out of range", index);
+        let mut rv = UDI_GIO_OP_CUSTOM + index;
+        if read { rv |= UDI_GIO_DIR_READ; }
+        if write { rv |= UDI_GIO_DIR_WRITE; }
+        GioOp::Custom(rv)
+    }
+    /// Decode a raw `op` value
+    pub fn from_raw(op: u8) -> Self {
+        match op {
+        UDI_GIO_OP_READ => GioOp::Read,
+        UDI_GIO_OP_WRITE => GioOp::Write,
+        _ if op & Self::CODE_MASK >= UDI_GIO_OP_CUSTOM => GioOp::Custom(op),
+        _ => GioOp::Standard(op),
+        }
+    }
+    /// Get the raw `op` value
+    pub fn to_raw(self) -> u8 {
+        match self {
+        GioOp::Read => UDI_GIO_OP_READ,
+        GioOp::Write => UDI_GIO_OP_WRITE,
+        GioOp::Standard(v) | GioOp::Custom(v) => v,
+        }
+    }
+    /// Operation code (without the direction flags)
+    pub fn code(self) -> u8 {
+        self.to_raw() & Self::CODE_MASK
+    }
+    /// Data is transferred from the device to the client (in `data_buf`)
+    pub fn is_read(self) -> bool {
+        self.to_raw() & UDI_GIO_DIR_READ != 0
+    }
+    /// Data is transferred from the client to the device (in `data_buf`)
+    pub fn is_write(self) -> bool {
+        self.to_raw() & UDI_GIO_DIR_WRITE != 0
+    }
+}
+
+/// Inline data passed in `tr_params` or `event_params`
+///
+/// # Safety
+/// `LAYOUT` must describe `Self` (terminated by `UDI_DL_END`), and `Self` must be valid for any value of the
+/// fields in that layout.
+pub unsafe trait InlineParams: Copy + 'static {
+    /// Layout of the data (used by the environment to copy it between regions)
+    const LAYOUT: &'static [crate::ffi::udi_layout_t];
+}
+/// Transfer parameters (`tr_params`) for a set of operations
+pub trait TrParams: InlineParams {
+    /// Check if this is the parameter type used for `op`
+    fn valid_for(op: GioOp) -> bool;
+}
+// SAFE: Zero-sized, with an empty layout
+unsafe impl InlineParams for () {
+    const LAYOUT: &'static [crate::ffi::udi_layout_t] = &[crate::ffi::layout::UDI_DL_END];
+}
+/// No parameters, for any operation other than [GioOp::Read] and [GioOp::Write]
+impl TrParams for () {
+    fn valid_for(op: GioOp) -> bool {
+        !RwParams::valid_for(op)
+    }
+}
+
+/// Parameters for [GioOp::Read] and [GioOp::Write] (`udi_gio_rw_params_t`)
+#[repr(C)]
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct RwParams {
+    offset_lo: u32,
+    offset_hi: u32,
+}
+const _: () = assert!(::core::mem::size_of::<RwParams>() == ::core::mem::size_of::<udi_gio_rw_params_t>());
+impl RwParams {
+    /// Parameters for a transfer at `offset` bytes into the device
+    pub fn new(offset: u64) -> Self {
+        RwParams { offset_lo: offset as u32, offset_hi: (offset >> 32) as u32 }
+    }
+    /// Byte offset of the transfer
+    pub fn offset(&self) -> u64 {
+        self.offset_lo as u64 | (self.offset_hi as u64) << 32
+    }
+}
+// SAFE: Layout matches, and all values are valid
+unsafe impl InlineParams for RwParams {
+    const LAYOUT: &'static [crate::ffi::udi_layout_t] = &[
+        crate::ffi::layout::UDI_DL_UBIT32_T,
+        crate::ffi::layout::UDI_DL_UBIT32_T,
+        crate::ffi::layout::UDI_DL_END,
+    ];
+}
+impl TrParams for RwParams {
+    fn valid_for(op: GioOp) -> bool {
+        matches!(op, GioOp::Read | GioOp::Write)
+    }
+}
+
+/// Transfer constraints reported by a provider when binding (`udi_xfer_constraints_t`)
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct XferConstraints {
+    /// Maximum size of a single transfer in bytes (zero for no limit)
+    pub max: u32,
+    /// Typical transfer size in bytes
+    pub typical: u32,
+    /// Transfer sizes should be a multiple of this many bytes
+    pub granularity: u32,
+    /// Data buffers must be passed in one piece (not split into multiple transfers)
+    pub one_piece: bool,
+    /// Transfers must be exactly a multiple of `granularity`
+    pub exact_size: bool,
+    /// Transfers must be completed in the order they were requested
+    pub no_reorder: bool,
+}
+impl XferConstraints {
+    /// Decode the raw structure
+    pub fn from_raw(raw: &crate::ffi::buf::udi_xfer_constraints_t) -> Self {
+        XferConstraints {
+            max: raw.udi_xfer_max,
+            typical: raw.udi_xfer_typical,
+            granularity: raw.udi_xfer_granularity,
+            one_piece: raw.udi_xfer_one_piece.to_bool(),
+            exact_size: raw.udi_xfer_exact_size.to_bool(),
+            no_reorder: raw.udi_xfer_no_reorder.to_bool(),
+        }
+    }
+    /// Encode into the raw structure
+    pub fn to_raw(&self) -> crate::ffi::buf::udi_xfer_constraints_t {
+        crate::ffi::buf::udi_xfer_constraints_t {
+            udi_xfer_max: self.max,
+            udi_xfer_typical: self.typical,
+            udi_xfer_granularity: self.granularity,
+            udi_xfer_one_piece: self.one_piece.into(),
+            udi_xfer_exact_size: self.exact_size.into(),
+            udi_xfer_no_reorder: self.no_reorder.into(),
+        }
+    }
+}
+
+/// An event indicated by a provider (see [event_ind] and [Client::event_ind])
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct GioEvent {
+    /// Provider-specific event code
+    pub code: u8,
+}
 
 impl_metalanguage!{
     static METALANG_SPEC;
@@ -19,11 +236,18 @@ impl_metalanguage!{
         ;
     CBS
         1 => udi_gio_bind_cb_t,
-        2 => udi_gio_xfer_cb_t : BUF data_buf,
-        3 => udi_gio_event_cb_t,
+        2 => udi_gio_xfer_cb_t : BUF data_buf : INLINE_DATA tr_params,
+        3 => udi_gio_event_cb_t : INLINE_DATA event_params,
         ;
 }
 
+impl crate::cb::CbRef<'_, ffi::udi_gio_bind_cb_t>
+{
+    /// Transfer constraints reported by the provider (valid in [Client::bind_ack])
+    pub fn xfer_constraints(&self) -> XferConstraints {
+        XferConstraints::from_raw(&self.xfer_constraints)
+    }
+}
 impl crate::cb::CbRef<'_, ffi::udi_gio_xfer_cb_t>
 {
     /// Read from the data buffer in the CB
@@ -33,6 +257,24 @@ impl crate::cb::CbRef<'_, ffi::udi_gio_xfer_cb_t>
             crate::buf::Handle::from_ref(&self.data_buf)
         }
     }
+    /// Get the operation
+    pub fn op(&self) -> GioOp {
+        GioOp::from_raw(self.op)
+    }
+    /// Get the transfer parameters, if `P` is the parameter type for the operation and they are present
+    ///
+    /// # Safety
+    /// The inline data is sized by whoever allocated the CB, it must be at least as large as `P` (e.g. allocated
+    /// with [alloc_xfer], or by a client following the specification's parameter type for the operation)
+    pub unsafe fn tr_params<P: TrParams>(&self) -> Option<&P> {
+        if P::valid_for(self.op()) && !self.tr_params.is_null() {
+            // SAFE: Non-null, the parameter type matches the operation, and the caller ensures the size
+            Some(unsafe { &*(self.tr_params as *const P) })
+        }
+        else {
+            None
+        }
+    }
 }
 impl crate::cb::CbHandle<ffi::udi_gio_xfer_cb_t>
 {
@@ -44,10 +286,43 @@ impl crate::cb::CbHandle<ffi::udi_gio_xfer_cb_t>
         }
     }
 
-    /// Set the operation code
-    pub fn set_op(&mut self, op: u8) {
+    /// Set the operation
+    pub fn set_op(&mut self, op: GioOp) {
         unsafe {
-            self.get_mut().op = op;
+            self.get_mut().op = op.to_raw();
+        }
+    }
+    /// Get the transfer parameters for modification
+    ///
+    /// # Safety
+    /// As for [crate::cb::CbRef::tr_params], the inline data must be at least as large as `P`
+    pub unsafe fn tr_params_mut<P: TrParams>(&mut self) -> Option<&mut P> {
+        if P::valid_for(GioOp::from_raw(self.op)) && !self.tr_params.is_null() {
+            // SAFE: Non-null, the parameter type matches the operation, the caller ensures the size, and the CB is owned
+            Some(unsafe { &mut *(self.get_mut().tr_params as *mut P) })
+        }
+        else {
+            None
+        }
+    }
+}
+impl crate::cb::CbRef<'_, ffi::udi_gio_event_cb_t>
+{
+    /// Get the event
+    pub fn event(&self) -> GioEvent {
+        GioEvent { code: self.event_code }
+    }
+    /// Get the event parameters
+    ///
+    /// # Safety
+    /// The parameters must have been allocated as `P` (e.g. with [alloc_event])
+    pub unsafe fn event_params<P: InlineParams>(&self) -> Option<&P> {
+        if self.event_params.is_null() {
+            None
+        }
+        else {
+            // SAFE: Non-null, caller ensures the type
+            Some(unsafe { &*(self.event_params as *const P) })
         }
     }
 }
@@ -83,8 +358,8 @@ pub trait Client: 'static + crate::imc::ChannelInit + crate::async_trickery::CbC
         as Future_xfer_nak
     );
     async_method!(
-        /// Handle an event from the provider
-        fn event_ind (&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_event_cb_t>)->()
+        /// Handle an event from the provider, [Provider::event_res] is called once this completes
+        fn event_ind (&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_event_cb_t>, event: GioEvent)->()
         as Future_event_ind
     );
 
@@ -130,7 +405,7 @@ future_wrapper!(gio_xfer_nak_op => <T as Client>(cb: *mut ffi::udi_gio_xfer_cb_t
     val.xfer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) })
 });
 future_wrapper!(gio_event_ind_op => <T as Client>(cb: *mut ffi::udi_gio_event_cb_t) val @ {
-    val.event_ind(cb)
+    val.event_ind(cb, cb.event())
 } finally( () ) {
     unsafe { ::udi_sys::meta_gio::udi_gio_event_res(cb) }
 });
@@ -153,8 +428,9 @@ map_ops_structure!{
 pub trait Provider: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
 {
     async_method!(
-        /// A binding has been requested
-        fn bind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_bind_cb_t>)->()
+        /// A binding has been requested, returns the device size (in bytes, zero if not applicable) and the
+        /// transfer constraints
+        fn bind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_bind_cb_t>)->crate::Result<(u64,XferConstraints)>
         as Future_bind_req
     );
     async_method!(
@@ -163,13 +439,13 @@ pub trait Provider: 'static + crate::imc::ChannelInit + crate::async_trickery::C
         as Future_unbind_req
     );
     async_method!(
-        /// A transfer has been requested
-        fn xfer_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_xfer_cb_t>)->()
+        /// A transfer has been requested, the CB is completed with [xfer_ack] or [xfer_nak] depending on the result
+        fn xfer_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_xfer_cb_t>, op: GioOp)->crate::Result<()>
         as Future_xfer_req
     );
     async_method!(
-        /// The event has been handled
-        fn event_res(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_event_cb_t>)->()
+        /// The client has handled an event (see [event_ind])
+        fn event_res(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_gio_event_cb_t>, event: GioEvent)->()
         as Future_event_res
     );
     /// Return/relase an event CB
@@ -184,15 +460,36 @@ where
 
 future_wrapper!(gio_bind_req_op => <T as Provider>(cb: *mut ffi::udi_gio_bind_cb_t) val @ {
     val.bind_req(cb)
+} finally(res) {
+    unsafe {
+        let (size,status) = match res
+            {
+            Ok((size,constraints)) => {
+                (*cb).xfer_constraints = constraints.to_raw();
+                (size,0)
+                },
+            Err(e) => (0,e.into_inner()),
+            };
+        ffi::udi_gio_bind_ack(cb, size as u32, (size >> 32) as u32, status)
+    }
 });
 future_wrapper!(gio_unbind_req_op => <T as Provider>(cb: *mut ffi::udi_gio_bind_cb_t) val @ {
     val.unbind_req(cb)
+} finally( () ) {
+    unsafe { ffi::udi_gio_unbind_ack(cb) }
 });
 future_wrapper!(gio_xfer_req_op => <T as Provider>(cb: *mut ffi::udi_gio_xfer_cb_t) val @ {
-    val.xfer_req(cb)
+    val.xfer_req(cb, cb.op())
+} finally(res) {
+    // SAFE: The task has completed, so the CB is no longer in use
+    let cb = unsafe { crate::cb::CbHandle::from_raw(cb) };
+    match res {
+    Ok(()) => xfer_ack(cb),
+    Err(e) => xfer_nak(cb, e),
+    }
 });
 future_wrapper!(gio_event_res_op => <T as Provider>(cb: *mut ffi::udi_gio_event_cb_t) val @ {
-    val.event_res(cb)
+    val.event_res(cb, cb.event())
 } finally( () ) {
     val.event_ret(unsafe { crate::cb::CbHandle::from_raw(cb) })
 });
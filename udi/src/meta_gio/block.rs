@@ -0,0 +1,151 @@
+//! Block device layer over a GIO provider
+//!
+//! A [BlockProvider] describes a device as a number of fixed-size blocks, and any type implementing it also
+//! implements [Provider](super::Provider). [GioOp::Read] and [GioOp::Write] requests are decoded from their
+//! [RwParams], checked against the device, and split into calls of at most [BlockProvider::max_blocks] blocks. The
+//! result of the request is then returned to the client with [xfer_ack](super::xfer_ack) or
+//! [xfer_nak](super::xfer_nak).
+//!
+//! - Requests that are not a multiple of the block size fail with `UDI_STAT_NOT_SUPPORTED`
+//! - Requests that extend past the end of the device are truncated, and fail with `UDI_STAT_DATA_UNDERRUN` (with
+//!   `data_buf` shortened to the transferred data)
+//! - [OP_FLUSH] calls [BlockProvider::flush]
+//! - Other operations fail with `UDI_STAT_NOT_UNDERSTOOD`
+//!
+//! Binding fails with `UDI_STAT_NOT_SUPPORTED` if the size of the device in bytes does not fit in a `u64`.
+use crate::ffi::udi_cb_t;
+use crate::ffi::meta_gio as ffi;
+use super::{GioOp, GioEvent, RwParams, XferConstraints};
+
+/// Custom operation to write any cached data to the device (no parameters or data)
+pub const OP_FLUSH: GioOp = GioOp::Custom(crate::ffi::meta_gio::UDI_GIO_OP_CUSTOM);
+
+/// A block device, exposed as a GIO provider
+pub trait BlockProvider: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    /// Size of a block in bytes (must be non-zero)
+    fn block_size(&self) -> u32;
+    /// Number of blocks on the device
+    fn capacity(&self) -> u64;
+    /// Largest number of blocks passed to a single [BlockProvider::read_blocks] or [BlockProvider::write_blocks]
+    /// call (e.g. from the device's DMA constraints)
+    fn max_blocks(&self) -> u32;
+
+    async_method!(
+        /// Read `count` blocks starting at `lba`, into `buf` starting at byte `buf_ofs`
+        fn read_blocks(&'s self, gcb: crate::CbRef<'s, udi_cb_t>, lba: u64, count: u32, buf: &'s mut crate::buf::Handle, buf_ofs: usize)->crate::Result<()>
+        as Future_read_blocks
+    );
+    async_method!(
+        /// Write `count` blocks starting at `lba`, from `buf` starting at byte `buf_ofs`
+        fn write_blocks(&'s self, gcb: crate::CbRef<'s, udi_cb_t>, lba: u64, count: u32, buf: &'s crate::buf::Handle, buf_ofs: usize)->crate::Result<()>
+        as Future_write_blocks
+    );
+    async_method!(
+        /// Ensure that all written blocks have reached the device
+        fn flush(&'s self, gcb: crate::CbRef<'s, udi_cb_t>)->crate::Result<()>
+        as Future_flush
+    );
+}
+
+/// A transfer in blocks, decoded from a byte offset and length
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct BlockRange {
+    /// First block
+    pub lba: u64,
+    /// Number of blocks
+    pub count: u64,
+}
+impl BlockRange {
+    /// Convert a transfer of `len` bytes at `offset` into blocks, truncated to the end of the device
+    ///
+    /// Returns `UDI_STAT_NOT_SUPPORTED` if the offset or length are not a multiple of the block size
+    pub fn from_bytes(block_size: u32, capacity: u64, offset: u64, len: usize) -> crate::Result<Self> {
+        let bs = block_size as u64;
+        if !offset.is_multiple_of(bs) || !(len as u64).is_multiple_of(bs) {
+            return Err(error(crate::ffi::UDI_STAT_NOT_SUPPORTED as _));
+        }
+        let lba = offset / bs;
+        let count = (len as u64 / bs).min(capacity.saturating_sub(lba));
+        Ok(BlockRange { lba, count })
+    }
+    /// Split into pieces of at most `max_blocks` blocks, as `(lba, count, byte offset)`
+    pub fn chunks(self, block_size: u32, max_blocks: u32) -> impl Iterator<Item=(u64,u32,usize)> {
+        let max_blocks = max_blocks.max(1) as u64;
+        let end = self.lba + self.count;
+        (self.lba .. end).step_by(max_blocks as usize).map(move |lba| {
+            let count = (end - lba).min(max_blocks) as u32;
+            (lba, count, ((lba - self.lba) * block_size as u64) as usize)
+        })
+    }
+}
+
+fn error(status: crate::ffi::udi_status_t) -> crate::Error {
+    crate::Error::from_status(status).unwrap_err()
+}
+
+impl<T: BlockProvider> super::Provider for T
+{
+    type Future_bind_req<'s> = impl ::core::future::Future<Output=crate::Result<(u64,XferConstraints)>> + 's;
+    fn bind_req<'s>(&'s self, _cb: crate::CbRef<'s, ffi::udi_gio_bind_cb_t>) -> Self::Future_bind_req<'s> {
+        let bs = self.block_size();
+        let Some(size) = self.capacity().checked_mul(bs as u64) else {
+            return ::core::future::ready(Err(error(crate::ffi::UDI_STAT_NOT_SUPPORTED as _)));
+        };
+        let constraints = XferConstraints {
+            max: self.max_blocks().saturating_mul(bs),
+            typical: bs,
+            granularity: bs,
+            exact_size: true,
+            ..Default::default()
+        };
+        ::core::future::ready(Ok((size, constraints)))
+    }
+
+    type Future_unbind_req<'s> = ::core::future::Ready<()>;
+    fn unbind_req<'s>(&'s self, _cb: crate::CbRef<'s, ffi::udi_gio_bind_cb_t>) -> Self::Future_unbind_req<'s> {
+        ::core::future::ready(())
+    }
+
+    type Future_xfer_req<'s> = impl ::core::future::Future<Output=crate::Result<()>> + 's;
+    fn xfer_req<'s>(&'s self, cb: crate::CbRef<'s, ffi::udi_gio_xfer_cb_t>, op: GioOp) -> Self::Future_xfer_req<'s> {
+        async move {
+            if op == OP_FLUSH {
+                return self.flush(cb.gcb()).await;
+            }
+            // SAFE: The specification defines the parameters for read/write as `udi_gio_rw_params_t`, so clients
+            // must allocate at least that much inline data
+            let Some(params) = (unsafe { cb.tr_params::<RwParams>() }) else {
+                return Err(error(crate::ffi::UDI_STAT_NOT_UNDERSTOOD as _));
+            };
+            let bs = self.block_size();
+            let len = cb.data_buf().len();
+            let range = BlockRange::from_bytes(bs, self.capacity(), params.offset(), len)?;
+            // SAFE: The CB is owned by this request until it is acknowledged
+            let buf = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).data_buf) };
+            for (lba, count, buf_ofs) in range.chunks(bs, self.max_blocks()) {
+                if op == GioOp::Read {
+                    self.read_blocks(cb.gcb(), lba, count, buf, buf_ofs).await?;
+                }
+                else {
+                    self.write_blocks(cb.gcb(), lba, count, buf, buf_ofs).await?;
+                }
+            }
+            let done = (range.count * bs as u64) as usize;
+            if done < len {
+                buf.truncate(done);
+                return Err(error(crate::ffi::UDI_STAT_DATA_UNDERRUN as _));
+            }
+            Ok(())
+        }
+    }
+
+    type Future_event_res<'s> = ::core::future::Ready<()>;
+    fn event_res<'s>(&'s self, _cb: crate::CbRef<'s, ffi::udi_gio_event_cb_t>, _event: GioEvent) -> Self::Future_event_res<'s> {
+        ::core::future::ready(())
+    }
+    fn event_ret(&self, cb: crate::cb::CbHandle<ffi::udi_gio_event_cb_t>) {
+        // Block devices don't indicate events
+        drop(cb);
+    }
+}
@@ -1,15 +1,18 @@
 /*!
  * Network Interface Card Metalanguage
  * 
- * # Safety of Rx/Tx traits
- * The [NdTx]/[NdRx]/[NsrTx]/[NsrRx] traits are all marked as `unsafe` as they
- * pass an owning handle to the control block to the method. If this control
- * block is dropped before the future completes, the future will read freed
- * memory.
+ * # Ownership of Rx/Tx CBs
+ * The async state for an operation is stored in the scratch space of the CB it was
+ * called with, so that CB cannot be handed on (or freed) until the operation completes.
  * 
- * The implementation of these methods can avoid UB by storing the cb passed in the
- * region data after the last async call. This ensures that it will not be reused
- * until after the future has been cleaned up.
+ * [NdTx] passes the CBs as a [TxRequest], which the driver hands to a [TxQueue] to own
+ * them; the queue holds back returning CBs to the NSR until the request has completed.
+ * [NsrRx] only borrows the CBs while its future runs ([RxChainRef]), and then passes
+ * ownership as a [RxChain] to a synchronous method once the future has completed. The
+ * [NdRx] and [NsrTx] operations are synchronous, and pass ownership directly.
+ * 
+ * The original `unsafe` traits (which pass an owned CB to the future) are available
+ * in [compat].
  * 
  * # Terms
  * - ND: "Network Device" - the network card driver
@@ -18,9 +21,13 @@
  *               It is expected that these packets are handled (sent or processed) before
  *               any lower-priority packets are.
  */
+use ::core::cell::Cell;
 use crate::ffi::udi_index_t;
 use crate::ffi::meta_nic as ffi;
 
+pub mod compat;
+pub mod nsr;
+
 /// Request enabling of a network device
 pub fn nd_enable_req(cb: crate::cb::CbHandle<ffi::udi_nic_cb_t>) {
     unsafe { ffi::udi_nd_enable_req(cb.into_raw()) }
@@ -29,7 +36,7 @@ pub fn nd_enable_req(cb: crate::cb::CbHandle<ffi::udi_nic_cb_t>) {
 pub fn nd_disable_req(cb: crate::cb::CbHandle<ffi::udi_nic_cb_t>) {
     unsafe { ffi::udi_nd_disable_req(cb.into_raw()) }
 }
-/// Send a control request to a network device
+/// Send a control request to a network device (populated using [crate::cb::CbHandle::set_ctrl_op])
 pub fn nd_ctrl_req(cb: crate::cb::CbHandle<ffi::udi_nic_ctrl_cb_t>) {
     unsafe { ffi::udi_nd_ctrl_req(cb.into_raw()) }
 }
@@ -38,29 +45,34 @@ pub fn nd_info_req(cb: crate::cb::CbHandle<ffi::udi_nic_info_cb_t>, reset_statis
     unsafe { ffi::udi_nd_info_req(cb.into_raw(), reset_statistics.into()) }
 }
 /// Inform the NSR that a device's status has changed
-pub fn nsr_status_ind(cb: crate::cb::CbHandle<ffi::udi_nic_status_cb_t>) {
-    unsafe { ffi::udi_nsr_status_ind(cb.into_raw()) }
+///
+/// The NSR frees the CB once it has handled the indication
+pub fn nsr_status_ind(mut cb: crate::cb::CbHandle<ffi::udi_nic_status_cb_t>, status: NicStatus) {
+    unsafe {
+        cb.get_mut().event = status.to_raw();
+        ffi::udi_nsr_status_ind(cb.into_raw())
+    }
 }
 
-/// Inform the NSR that a packet has been recived
-pub fn nsr_rx_ind(rx_cb: CbHandleNicRx) {
-    unsafe { ffi::udi_nsr_rx_ind(rx_cb.into_raw()) }
+/// Inform the NSR that packets have been recived
+pub fn nsr_rx_ind(rx_cbs: impl Into<RxChain>) {
+    unsafe { ffi::udi_nsr_rx_ind(rx_cbs.into().into_raw()) }
 }
-/// Hand/return the network device a CB to use for incoming packets
-pub fn nd_rx_rdy(cb: CbHandleNicRx) {
-    unsafe { ffi::udi_nd_rx_rdy(cb.into_raw()) }
+/// Hand/return the network device CBs to use for incoming packets
+pub fn nd_rx_rdy(cbs: impl Into<RxChain>) {
+    unsafe { ffi::udi_nd_rx_rdy(cbs.into().into_raw()) }
 }
-/// Request the network device send a packet
-pub fn nd_tx_req(tx_cb: CbHandleNicTx) {
-    unsafe { ffi::udi_nd_tx_req(tx_cb.into_raw()) }
+/// Request the network device send packets
+pub fn nd_tx_req(tx_cbs: impl Into<TxChain>) {
+    unsafe { ffi::udi_nd_tx_req(tx_cbs.into().into_raw()) }
 }
-/// Request the network device send a packet (expedited)
-pub fn nd_exp_tx_req(tx_cb: CbHandleNicTx) {
-    unsafe { ffi::udi_nd_exp_tx_req(tx_cb.into_raw()) }
+/// Request the network device send packets (expedited)
+pub fn nd_exp_tx_req(tx_cbs: impl Into<TxChain>) {
+    unsafe { ffi::udi_nd_exp_tx_req(tx_cbs.into().into_raw()) }
 }
-/// Hand the NSR a CB to use for outgoing packets
-pub fn nsr_tx_rdy(cb: CbHandleNicTx) {
-    unsafe { ffi::udi_nsr_tx_rdy(cb.into_raw()) }
+/// Hand the NSR CBs to use for outgoing packets
+pub fn nsr_tx_rdy(cbs: impl Into<TxChain>) {
+    unsafe { ffi::udi_nsr_tx_rdy(cbs.into().into_raw()) }
 }
 
 macro_rules! def_cb {
@@ -84,7 +96,7 @@ impl_metalanguage!{
     CBS
         1 => ffi::udi_nic_cb_t,
         2 => ffi::udi_nic_bind_cb_t,
-        3 => ffi::udi_nic_ctrl_cb_t,
+        3 => ffi::udi_nic_ctrl_cb_t : BUF data_buf,
         4 => ffi::udi_nic_status_cb_t,
         5 => ffi::udi_nic_info_cb_t,
         6 => ffi::udi_nic_tx_cb_t : BUF tx_buf : CHAIN chain,
@@ -209,6 +221,276 @@ impl CbRefNicRx<'_>
     }
 }
 
+impl crate::cb::CbHandle<ffi::udi_nic_ctrl_cb_t>
+{
+    /// Get a mutable reference to the data buffer
+    pub fn data_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        unsafe { crate::buf::Handle::from_mut( &mut self.get_mut().data_buf ) }
+    }
+    /// Populate the command, indicator, and data buffer for a control request
+    ///
+    /// `gcb` is the CB of the currently executing operation, used to wait for buffer writes
+    pub fn set_ctrl_op<'a>(&'a mut self, gcb: crate::CbRef<'a, crate::ffi::udi_cb_t>, op: CtrlOp<'a>) -> impl ::core::future::Future<Output=()> + 'a {
+        let (command, indicator) = op.to_raw();
+        // SAFE: Just setting integer fields
+        unsafe {
+            let cb = self.get_mut();
+            cb.command = command;
+            cb.indicator = indicator;
+        }
+        async move {
+            op.write_data(gcb, self.data_buf_mut()).await;
+        }
+    }
+}
+impl CbRefNicCtrl<'_>
+{
+    /// Get a reference to the data buffer
+    pub fn data_buf_ref(&self) -> &crate::buf::Handle {
+        unsafe { crate::buf::Handle::from_ref( &self.data_buf ) }
+    }
+    /// Read the MAC address returned by a [CtrlOp::GetCurrMac] or [CtrlOp::GetFactMac] request
+    pub fn read_mac_addr(&self) -> crate::Result<MacAddress> {
+        MacAddress::from_buf(self.data_buf_ref(), self.indicator as usize)
+    }
+}
+
+macro_rules! def_chain {
+    ($(#[$a:meta])* $name:ident => $cb_ty:ty, $handle:ty, $ref:ident) => {
+        $(#[$a])*
+        pub struct $name(::core::ptr::NonNull<$cb_ty>);
+        impl $name {
+            /// Take ownership of a raw chain
+            ///
+            /// SAFETY: The caller must own all CBs in the chain, and no task may be running on the first CB
+            pub unsafe fn from_raw(cb: *mut $cb_ty) -> Self {
+                Self(::core::ptr::NonNull::new(cb).expect(concat!(stringify!($name), " from a null pointer")))
+            }
+            /// Release ownership of the chain as a raw pointer
+            pub fn into_raw(self) -> *mut $cb_ty {
+                let rv = self.0.as_ptr();
+                ::core::mem::forget(self);
+                rv
+            }
+            /// Convert into a handle to the first CB (which owns the rest of the chain)
+            pub fn into_handle(self) -> $handle {
+                // SAFE: Owned
+                unsafe { <$handle>::from_raw(self.into_raw()) }
+            }
+            /// Get the number of CBs in the chain
+            pub fn count(&self) -> usize {
+                self.iter().count()
+            }
+            /// Get an iterator over the CBs in the chain
+            pub fn iter(&self) -> impl Iterator<Item=$ref<'_>> {
+                // SAFE: All CBs are owned by `self`
+                unsafe { chain_iter(self.0.as_ptr()) }
+            }
+            /// Split the first CB off the chain
+            ///
+            /// Only the first CB's channel is updated when a chain is passed over a channel, so the remainder
+            /// is given the same channel as the first CB.
+            pub fn split_first(self) -> (Self, Option<Self>) {
+                let head = self.into_raw();
+                // SAFE: Owned, and the link is cleared before the two are separated
+                unsafe {
+                    let next = ::core::mem::replace(&mut (*head).chain, ::core::ptr::null_mut());
+                    if !next.is_null() {
+                        (*next).gcb.channel = (*head).gcb.channel;
+                    }
+                    (Self::from_raw(head), ::core::ptr::NonNull::new(next).map(Self))
+                }
+            }
+            /// Add `other` to the end of this chain
+            pub fn append(&mut self, other: Self) {
+                let mut cursor = self.0.as_ptr();
+                // SAFE: Owned
+                unsafe {
+                    while ! (*cursor).chain.is_null() {
+                        cursor = (*cursor).chain;
+                    }
+                    (*cursor).chain = other.into_raw();
+                }
+            }
+        }
+        impl From<$handle> for $name {
+            fn from(cb: $handle) -> Self {
+                // SAFE: The handle owns the chain
+                unsafe { Self::from_raw(cb.into_raw()) }
+            }
+        }
+        impl Drop for $name {
+            fn drop(&mut self) {
+                let mut cursor = self.0.as_ptr();
+                while !cursor.is_null() {
+                    // SAFE: Owned, and the next pointer is read before the CB is freed
+                    unsafe {
+                        let next = (*cursor).chain;
+                        ::udi_sys::cb::udi_cb_free(cursor as *mut ::udi_sys::udi_cb_t);
+                        cursor = next;
+                    }
+                }
+            }
+        }
+    };
+}
+/// CBs with a `chain` field
+trait ChainedCb: crate::async_trickery::GetCb + 'static {
+    /// SAFETY: `cb` must be valid
+    unsafe fn next(cb: *mut Self) -> *mut Self;
+}
+impl ChainedCb for ffi::udi_nic_tx_cb_t {
+    unsafe fn next(cb: *mut Self) -> *mut Self { (*cb).chain }
+}
+impl ChainedCb for ffi::udi_nic_rx_cb_t {
+    unsafe fn next(cb: *mut Self) -> *mut Self { (*cb).chain }
+}
+/// Iterate a raw chain of CBs, yielding references
+///
+/// SAFETY: The chain must be valid for `'a`
+unsafe fn chain_iter<'a, T: ChainedCb>(head: *mut T) -> impl Iterator<Item=crate::CbRef<'a, T>> {
+    // SAFE: Caller has ensured that the chain is valid
+    ::core::iter::successors(::core::ptr::NonNull::new(head), |cb| ::core::ptr::NonNull::new(unsafe { T::next(cb.as_ptr()) }))
+        .map(|cb| unsafe { crate::CbRef::new(cb.as_ptr()) })
+}
+
+def_chain!{
+    /// An owned chain of TX CBs
+    ///
+    /// Moving this value moves ownership of every CB in the chain, so each CB can only be returned once.
+    /// Dropping the chain frees the CBs (which the NSR will see as a leak)
+    TxChain => ffi::udi_nic_tx_cb_t, CbHandleNicTx, CbRefNicTx
+}
+def_chain!{
+    /// An owned chain of RX CBs
+    ///
+    /// Moving this value moves ownership of every CB in the chain, so each CB can only be returned once.
+    /// Dropping the chain frees the CBs (which the NSR will see as a leak)
+    RxChain => ffi::udi_nic_rx_cb_t, CbHandleNicRx, CbRefNicRx
+}
+
+/// The CBs of a running [NdTx::tx_req] (or [NdTx::exp_tx_req])
+///
+/// The request's async state is stored in the first CB, so these CBs are handed to the driver's [TxQueue] with
+/// [TxRequest::enqueue], which holds back returning any CBs to the NSR until the request has completed. Dropping the
+/// request frees the CBs (which the NSR will see as a leak).
+pub struct TxRequest<'a> {
+    head: ::core::ptr::NonNull<ffi::udi_nic_tx_cb_t>,
+    expedited: bool,
+    state: &'a Cell<TxRequestState>,
+}
+/// What happened to the CBs of a [TxRequest], handled by the op wrapper once the request's task has completed
+#[derive(Copy,Clone)]
+enum TxRequestState {
+    /// Dropped, the first CB needs to be freed
+    Dropped,
+    /// Handed to a queue, which needs to be told that the request is complete
+    Queued(*const TxQueue),
+    /// Ownership passed elsewhere (by [compat::NdTx])
+    Released,
+}
+impl<'a> TxRequest<'a> {
+    /// SAFETY: The chain must be owned by the request's task, and `state` must be handled by [TxRequestState::finish]
+    unsafe fn new(cb: *mut ffi::udi_nic_tx_cb_t, expedited: bool, state: &'a Cell<TxRequestState>) -> Self {
+        TxRequest { head: ::core::ptr::NonNull::new(cb).expect("TxRequest from a null pointer"), expedited, state }
+    }
+    /// Get the GCB of the first CB, which is the CB running the request (for use with async calls)
+    pub fn gcb(&self) -> crate::CbRef<'a, crate::ffi::udi_cb_t> {
+        // SAFE: The first CB is not released until the request's task has completed
+        unsafe { crate::CbRef::new(self.head.as_ptr() as *mut _) }
+    }
+    /// Check if this is an expedited request
+    pub fn is_expedited(&self) -> bool {
+        self.expedited
+    }
+    /// Get the number of CBs (i.e. packets) in the request
+    pub fn count(&self) -> usize {
+        self.iter().count()
+    }
+    /// Get an iterator over the CBs in the request
+    pub fn iter(&self) -> impl Iterator<Item=CbRefNicTx<'_>> {
+        // SAFE: Owned
+        unsafe { chain_iter(self.head.as_ptr()) }
+    }
+    /// Hand the CBs to `queue`, at the front if this is an expedited request
+    pub fn enqueue(self, queue: &'a TxQueue) {
+        queue.running.set(queue.running.get() + 1);
+        self.state.set(TxRequestState::Queued(queue));
+        let cbs = self.release();
+        if self.expedited {
+            queue.enqueue_expedited(cbs);
+        }
+        else {
+            queue.enqueue(cbs);
+        }
+    }
+    /// Take ownership of the CBs without the first CB being held back
+    ///
+    /// SAFETY: The caller must ensure that the first CB is not handed on (or freed) until the request's task completes
+    pub(crate) unsafe fn into_handle(self) -> CbHandleNicTx {
+        self.state.set(TxRequestState::Released);
+        self.release().into_handle()
+    }
+    fn release(&self) -> TxChain {
+        let rv = self.head.as_ptr();
+        // SAFE: Owned, and `self` is dropped by the caller without touching the chain (as the state isn't `Dropped`)
+        unsafe { TxChain::from_raw(rv) }
+    }
+}
+impl Drop for TxRequest<'_> {
+    fn drop(&mut self) {
+        if let TxRequestState::Dropped = self.state.get() {
+            // Free all but the first CB, which is freed once the task completes
+            // SAFE: Owned
+            let (head, rest) = unsafe { TxChain::from_raw(self.head.as_ptr()) }.split_first();
+            drop(rest);
+            let _ = head.into_raw();
+        }
+    }
+}
+impl TxRequestState {
+    /// Called once the request's task has completed
+    ///
+    /// SAFETY: `cb` must be the first CB of the request, and the task must have completed
+    unsafe fn finish(self, cb: *mut ffi::udi_nic_tx_cb_t) {
+        match self {
+        TxRequestState::Dropped => drop(CbHandleNicTx::from_raw(cb)),
+        TxRequestState::Queued(queue) => (*queue).request_done(),
+        TxRequestState::Released => {},
+        }
+    }
+}
+
+/// A chain of RX CBs borrowed by a running [NsrRx::rx_ind] (or [NsrRx::exp_rx_ind])
+///
+/// Ownership is passed to [NsrRx::rx_cb_ret] once the indication has been handled
+#[derive(Copy,Clone)]
+pub struct RxChainRef<'a>(::core::ptr::NonNull<ffi::udi_nic_rx_cb_t>, ::core::marker::PhantomData<&'a ffi::udi_nic_rx_cb_t>);
+impl<'a> RxChainRef<'a> {
+    /// SAFETY: The chain must be valid for `'a`
+    pub(crate) unsafe fn from_raw(cb: *mut ffi::udi_nic_rx_cb_t) -> Self {
+        Self(::core::ptr::NonNull::new(cb).expect("RxChainRef from a null pointer"), ::core::marker::PhantomData)
+    }
+    /// Get the GCB of the first CB, which is the CB running the indication (for use with async calls)
+    pub fn gcb(&self) -> crate::CbRef<'a, crate::ffi::udi_cb_t> {
+        self.first().gcb()
+    }
+    /// Get the first CB in the chain
+    pub fn first(&self) -> CbRefNicRx<'a> {
+        // SAFE: Valid for `'a`
+        unsafe { crate::CbRef::new(self.0.as_ptr()) }
+    }
+    /// Get the number of CBs (i.e. packets) in the chain
+    pub fn count(&self) -> usize {
+        self.iter().count()
+    }
+    /// Get an iterator over the CBs in the chain
+    pub fn iter(&self) -> impl Iterator<Item=CbRefNicRx<'a>> {
+        // SAFE: Valid for `'a`
+        unsafe { chain_iter(self.0.as_ptr()) }
+    }
+}
+
 /// A FIFO queue of RX CBs
 #[derive(Default)]
 pub struct ReadCbQueue( crate::cb::SharedQueue<ffi::udi_nic_rx_cb_t> );
@@ -218,9 +500,14 @@ impl ReadCbQueue
     pub const fn new() -> Self {
         Self(crate::cb::SharedQueue::new())
     }
-    /// Push a CB onto the back of the queue
-    pub fn push(&self, cb: CbHandleNicRx) {
-        self.0.push_back(cb)
+    /// Push a CB (or chain of CBs) onto the back of the queue
+    pub fn push(&self, cbs: impl Into<RxChain>) {
+        let mut cur = Some(cbs.into());
+        while let Some(cbs) = cur {
+            let (cb, next) = cbs.split_first();
+            self.0.push_back(cb.into_handle());
+            cur = next;
+        }
     }
     /// Pop a CB from the front of the queue
     pub fn pop(&self) -> Option< CbHandleNicRx > {
@@ -228,6 +515,219 @@ impl ReadCbQueue
     }
 }
 
+/// Queue of TX CBs between the NSR and the hardware, intended to be stored in region data
+///
+/// [NdTx::tx_req] hands the request's CBs to the queue with [TxRequest::enqueue] (CBs from elsewhere can be added
+/// with [TxQueue::enqueue]), and then submits them to the hardware: [TxQueue::next_submit] only returns a CB while a
+/// hardware slot is free, and the CB is handed back with [TxQueue::complete] once the hardware is done (possibly from
+/// a later request, or an interrupt). Completed CBs are returned to the NSR in batches (see [nsr_tx_rdy]), which in
+/// turn limits how much the NSR can send - CBs held in the queue are the back-pressure.
+///
+/// A request's async state is stored in its first CB, so no CBs are returned while any request that was enqueued
+/// is still running; they are returned once the last one completes.
+///
+/// The pending queue length is tracked against a pair of watermarks, so drivers can (for example) only request
+/// TX completion interrupts while the queue is congested.
+pub struct TxQueue {
+    pending: crate::cb::SharedQueue<ffi::udi_nic_tx_cb_t>,
+    completed: crate::cb::SharedQueue<ffi::udi_nic_tx_cb_t>,
+    n_pending: Cell<usize>,
+    n_completed: Cell<usize>,
+    in_flight: Cell<usize>,
+    hw_slots: Cell<usize>,
+    batch: Cell<usize>,
+    low_watermark: Cell<usize>,
+    high_watermark: Cell<usize>,
+    congested: Cell<bool>,
+    /// Number of enqueued [TxRequest]s that are still running
+    running: Cell<usize>,
+    stats: Cell<TxQueueStats>,
+}
+/// Statistics from a [TxQueue]
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct TxQueueStats {
+    /// Number of CBs queued
+    pub enqueued: u32,
+    /// Number of CBs returned to the NSR
+    pub returned: u32,
+    /// Number of times a pending CB could not be submitted due to all hardware slots being in use
+    pub stalls: u32,
+    /// Number of times the pending queue reached the high watermark
+    pub congestion_events: u32,
+    /// Largest number of pending CBs seen
+    pub max_pending: usize,
+}
+impl Drop for TxQueue {
+    fn drop(&mut self) {
+        // A running request would be left with a dangling pointer to this queue
+        assert!(self.running.get() == 0, "TxQueue dropped while a TxRequest is running");
+    }
+}
+impl Default for TxQueue {
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+impl TxQueue {
+    /// Create a queue for hardware with `hw_slots` transmit slots, returning completed CBs to the NSR in groups of `batch`
+    ///
+    /// The watermarks default to being disabled (see [TxQueue::set_watermarks])
+    pub const fn new(hw_slots: usize, batch: usize) -> Self {
+        assert!(hw_slots > 0, "TxQueue with no hardware slots");
+        assert!(batch > 0, "TxQueue with a zero batch size");
+        TxQueue {
+            pending: crate::cb::SharedQueue::new(),
+            completed: crate::cb::SharedQueue::new(),
+            n_pending: Cell::new(0),
+            n_completed: Cell::new(0),
+            in_flight: Cell::new(0),
+            hw_slots: Cell::new(hw_slots),
+            batch: Cell::new(batch),
+            low_watermark: Cell::new(usize::MAX),
+            high_watermark: Cell::new(usize::MAX),
+            congested: Cell::new(false),
+            running: Cell::new(0),
+            stats: Cell::new(TxQueueStats { enqueued: 0, returned: 0, stalls: 0, congestion_events: 0, max_pending: 0 }),
+        }
+    }
+    /// Change the number of hardware slots (e.g. once the device has been probed)
+    pub fn set_hw_slots(&self, hw_slots: usize) {
+        assert!(hw_slots > 0, "TxQueue with no hardware slots");
+        self.hw_slots.set(hw_slots);
+    }
+    /// Set the pending queue length at which the queue becomes congested (`high`), and at which it stops being congested (`low`)
+    pub fn set_watermarks(&self, low: usize, high: usize) {
+        assert!(low < high, "TxQueue low watermark ({}) must be below the high watermark ({})", low, high);
+        self.low_watermark.set(low);
+        self.high_watermark.set(high);
+        self.update_congestion();
+    }
+
+    /// Add a (potentially chained) set of CBs to the end of the pending queue
+    pub fn enqueue(&self, cbs: impl Into<TxChain>) {
+        let mut cur = Some(cbs.into());
+        let mut count = 0;
+        while let Some(cbs) = cur {
+            let (cb, next) = cbs.split_first();
+            self.pending.push_back(cb.into_handle());
+            count += 1;
+            cur = next;
+        }
+        self.n_pending.set(self.n_pending.get() + count);
+        self.update_stats(|s| {
+            s.enqueued = s.enqueued.wrapping_add(count as u32);
+            s.max_pending = s.max_pending.max(self.n_pending.get());
+        });
+        self.update_congestion();
+    }
+    /// Add a (potentially chained) set of CBs to the front of the pending queue, keeping their order
+    pub fn enqueue_expedited(&self, cbs: impl Into<TxChain>) {
+        let new = crate::cb::SharedQueue::new();
+        let mut cur = Some(cbs.into());
+        let mut count = 0;
+        while let Some(cbs) = cur {
+            let (cb, next) = cbs.split_first();
+            new.push_back(cb.into_handle());
+            count += 1;
+            cur = next;
+        }
+        if let Some(cbs) = new.take_all() {
+            self.pending.push_front(cbs);
+        }
+        self.n_pending.set(self.n_pending.get() + count);
+        self.update_stats(|s| {
+            s.enqueued = s.enqueued.wrapping_add(count as u32);
+            s.max_pending = s.max_pending.max(self.n_pending.get());
+        });
+        self.update_congestion();
+    }
+    /// Take the next pending CB for submission to the hardware, if there is a free hardware slot
+    pub fn next_submit(&self) -> Option<CbHandleNicTx> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        if self.in_flight.get() >= self.hw_slots.get() {
+            self.update_stats(|s| s.stalls = s.stalls.wrapping_add(1));
+            return None;
+        }
+        let rv = self.pending.pop_front();
+        self.n_pending.set(self.n_pending.get() - 1);
+        self.in_flight.set(self.in_flight.get() + 1);
+        self.update_congestion();
+        rv
+    }
+    /// Hand back a CB taken with [TxQueue::next_submit] once the hardware is finished with it
+    ///
+    /// The CB is returned to the NSR once a full batch has completed, or when the queue becomes idle.
+    pub fn complete(&self, cb: CbHandleNicTx) {
+        assert!(self.in_flight.get() > 0, "TxQueue::complete with no CBs in flight");
+        self.in_flight.set(self.in_flight.get() - 1);
+        self.completed.push_back(cb);
+        self.n_completed.set(self.n_completed.get() + 1);
+        self.maybe_flush();
+    }
+    /// Return all completed CBs to the NSR now (or once no [TxRequest]s are running)
+    pub fn flush(&self) {
+        if self.running.get() > 0 {
+            return ;
+        }
+        if let Some(cbs) = self.completed.take_all() {
+            let count = self.n_completed.replace(0);
+            self.update_stats(|s| s.returned = s.returned.wrapping_add(count as u32));
+            nsr_tx_rdy(cbs);
+        }
+    }
+
+    /// Number of CBs waiting for a hardware slot
+    pub fn pending(&self) -> usize {
+        self.n_pending.get()
+    }
+    /// Number of CBs currently held by the hardware
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.get()
+    }
+    /// Check if there are no CBs pending or in flight
+    pub fn is_idle(&self) -> bool {
+        self.n_pending.get() == 0 && self.in_flight.get() == 0
+    }
+    /// Check if the pending queue has reached the high watermark (and not yet dropped to the low watermark)
+    pub fn is_congested(&self) -> bool {
+        self.congested.get()
+    }
+    /// Get the accumulated statistics
+    pub fn stats(&self) -> TxQueueStats {
+        self.stats.get()
+    }
+
+    fn maybe_flush(&self) {
+        if self.n_completed.get() >= self.batch.get() || self.is_idle() {
+            self.flush();
+        }
+    }
+    /// A [TxRequest] handed to this queue has completed
+    fn request_done(&self) {
+        self.running.set(self.running.get() - 1);
+        if self.n_completed.get() > 0 {
+            self.maybe_flush();
+        }
+    }
+    fn update_stats(&self, f: impl FnOnce(&mut TxQueueStats)) {
+        let mut s = self.stats.get();
+        f(&mut s);
+        self.stats.set(s);
+    }
+    fn update_congestion(&self) {
+        let n = self.n_pending.get();
+        if !self.congested.get() && n >= self.high_watermark.get() {
+            self.congested.set(true);
+            self.update_stats(|s| s.congestion_events = s.congestion_events.wrapping_add(1));
+        }
+        else if self.congested.get() && n <= self.low_watermark.get() {
+            self.congested.set(false);
+        }
+    }
+}
+
 #[cfg(any())]
 #[repr(u8)]
 pub enum OpsNum
@@ -267,14 +767,17 @@ pub trait Control: 'static + crate::async_trickery::CbContext + crate::imc::Chan
     );
     async_method!(
         /// Handle a control request
-        fn ctrl_req(&'a self, cb: CbRefNicCtrl<'a>)->crate::Result<()>
+        ///
+        /// For [CtrlOp::GetCurrMac] and [CtrlOp::GetFactMac] the requested address shall be returned, it is then
+        /// written to the CB's data buffer for the NSR.
+        fn ctrl_req(&'a self, cb: CbRefNicCtrl<'a>, op: CtrlOp<'a>)->crate::Result<Option<MacAddress>>
         as Future_ctrl_req
     );
     async_method!(
-        /// Handle an information request
+        /// Handle an information request, returning the link state and statistics (usually from [Stats::report])
         ///
         /// - `reset_statistics` is a request to reset the internal statistics counters
-        fn info_req(&'a self, cb: CbRefNicInfo<'a>, reset_statistics: bool)->()
+        fn info_req(&'a self, cb: CbRefNicInfo<'a>, reset_statistics: bool)->InfoAck
         as Future_info_req
     );
 }
@@ -292,17 +795,10 @@ future_wrapper!(nd_bind_req_op => <T as Control>(cb: *mut ffi::udi_nic_bind_cb_t
         // SAFE: Correct FFI and CB access
         unsafe {
             let status = match res {
+                // An inconsistent description would confuse the NSR, so refuse to bind
+                Ok(v) if v.validate().is_err() => crate::ffi::UDI_STAT_CANNOT_BIND as _,
                 Ok(v) => {
-                    let cb = &mut *cb;
-                    cb.media_type = v.media_type as _;
-                    cb.min_pdu_size = v.min_pdu_size;
-                    cb.max_pdu_size = v.max_pdu_size;
-                    cb.rx_hw_threshold = v.rx_hw_threshold;
-                    cb.capabilities = v.capabilities;
-                    cb.max_perfect_multicast = v.max_perfect_multicast;
-                    cb.max_total_multicast = v.max_total_multicast;
-                    cb.mac_addr_len = v.mac_addr_len;
-                    cb.mac_addr = v.mac_addr;
+                    v.write_cb(&mut *cb);
                     0
                     },
                 Err(s) => s.into_inner(),
@@ -323,13 +819,28 @@ future_wrapper!(nd_disable_req_op => <T as Control>(cb: *mut ffi::udi_nic_cb_t)
     val.disable_req(cb)
 });
 future_wrapper!(nd_ctrl_req_op => <T as Control>(cb: *mut ffi::udi_nic_ctrl_cb_t) val @ {
-    val.ctrl_req(cb)
+    let op = CtrlOp::from_cb(cb);
+    async move {
+        if let Some(mac_addr) = val.ctrl_req(cb, op?).await? {
+            // SAFE: The CB is owned by this task, and the driver's borrow (via `op`) has ended
+            let buf = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).data_buf) };
+            buf.write(cb.gcb(), .., mac_addr.as_bytes()).await;
+            unsafe { (*cb.to_raw()).indicator = mac_addr.as_bytes().len() as u32; }
+        }
+        Ok(())
+    }
 } finally(res) {
     // SAFE: Correct FFIs
     unsafe { ffi::udi_nsr_ctrl_ack(cb, crate::Error::to_status(res)) }
 });
 future_wrapper!(nd_info_req_op => <T as Control>(cb: *mut ffi::udi_nic_info_cb_t, reset_statistics: crate::ffi::udi_boolean_t) val @ {
     val.info_req(cb, reset_statistics.to_bool())
+} finally(res) {
+    // SAFE: Correct FFI and CB access
+    unsafe {
+        res.write_cb(&mut *cb);
+        ffi::udi_nsr_info_ack(cb)
+    }
 });
 
 map_ops_structure!{
@@ -391,8 +902,8 @@ pub trait NsrControl: 'static + crate::async_trickery::CbContext + crate::imc::C
         as Future_info_ack
     );
     async_method!(
-        /// Handle a change in status from the device
-        fn status_ind(&'a self, cb: CbRefNicStatus<'a>)->()
+        /// Handle a change in status from the device (the CB is freed afterwards)
+        fn status_ind(&'a self, cb: CbRefNicStatus<'a>, status: NicStatus)->()
         as Future_status_ind
     );
     /// Return/release a generic NIC CB
@@ -455,7 +966,13 @@ future_wrapper!(nsr_info_ack_op => <T as NsrControl>(cb: *mut ffi::udi_nic_info_
     val.ret_cb_nic_info(unsafe { crate::cb::CbHandle::from_raw(cb) });
 });
 future_wrapper!(nsr_status_ind_op => <T as NsrControl>(cb: *mut ffi::udi_nic_status_cb_t) val @ {
-    val.status_ind(cb)
+    // Unknown events are ignored
+    let status = NicStatus::from_raw(cb.event);
+    async move {
+        if let Some(status) = status {
+            val.status_ind(cb, status).await
+        }
+    }
 } finally( () ) {
     // See the docs for `udi_nsr_status_ind` - Expected to deallocate the CB
     // SAFE: Owns the CB
@@ -474,6 +991,7 @@ map_ops_structure!{
         ffi::udi_nic_cb_t,
         ffi::udi_nic_bind_cb_t,
         ffi::udi_nic_ctrl_cb_t,
+        ffi::udi_nic_status_cb_t,
         ffi::udi_nic_info_cb_t,
     }
     EXTRA_OP nsr_channel_bound
@@ -481,20 +999,18 @@ map_ops_structure!{
 
 // --------------------------------------------------------------------
 
-/// SAFETY:
-/// The implementations of `tx_req`/`exp_tx_req` shall ensure that the
-/// cb is not dropped until after the future completes.
-/// 
-/// Failure to do so can lead to crashes. See the module documentation for more details
-pub unsafe trait NdTx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+/// Network Device - Transmit operations
+///
+/// The CBs are passed as a [TxRequest], which the driver hands to its [TxQueue] to take ownership of them.
+pub trait NdTx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
     async_method!(
         /// Schedule transmission of a packet, using normal priority rules
-        fn tx_req(&'a self, cb: CbHandleNicTx)->()
+        fn tx_req(&'a self, cbs: TxRequest<'a>)->()
         as Future_tx_req
     );
     async_method!(
         /// Schedule transmission of a packet, expediting the transmission
-        fn exp_tx_req(&'a self, cb: CbHandleNicTx)->()
+        fn exp_tx_req(&'a self, cbs: TxRequest<'a>)->()
         as Future_exp_tx_req
     );
 }
@@ -506,12 +1022,26 @@ where
 }
 
 future_wrapper!(nd_tx_req_op => <T as NdTx>(cb: *mut ffi::udi_nic_tx_cb_t) val @ {
-    // SAFE: Trait is unsafe
-    val.tx_req(unsafe { cb.into_owned() })
+    async move {
+        let state = Cell::new(TxRequestState::Dropped);
+        // SAFE: Owned by this task, and the state is handled in `finally`
+        val.tx_req(unsafe { TxRequest::new(cb.to_raw(), false, &state) }).await;
+        state.get()
+    }
+} finally(state) {
+    // SAFE: The task is complete
+    unsafe { state.finish(cb) }
 });
 future_wrapper!(nd_exp_tx_req_op => <T as NdTx>(cb: *mut ffi::udi_nic_tx_cb_t) val @ {
-    // SAFE: Trait is unsafe
-    val.exp_tx_req(unsafe { cb.into_owned() })
+    async move {
+        let state = Cell::new(TxRequestState::Dropped);
+        // SAFE: Owned by this task, and the state is handled in `finally`
+        val.exp_tx_req(unsafe { TxRequest::new(cb.to_raw(), true, &state) }).await;
+        state.get()
+    }
+} finally(state) {
+    // SAFE: The task is complete
+    unsafe { state.finish(cb) }
 });
 map_ops_structure!{
     ffi::udi_nd_tx_ops_t => NdTx,MarkerNdTx {
@@ -524,17 +1054,14 @@ map_ops_structure!{
 }
 // --------------------------------------------------------------------
 
-/// SAFETY:
-/// The implementations of `tx_rdy` shall ensure that the cb is not
-/// dropped until after the future completes.
-/// 
-/// Failure to do so can lead to crashes. See the module documentation for more details
-pub unsafe trait NsrTx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
-    async_method!(
-        /// Return a TX CB to the pool
-        fn tx_rdy(&'a self, cb: CbHandleNicTx)->()
-        as Future_tx_rdy
-    );
+/// Network Service Requester - Transmit operations
+pub trait NsrTx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+    /// Return TX CBs to the pool
+    fn tx_rdy(&self, cbs: TxChain);
+
+    #[doc(hidden)]
+    /// Scratch space needed by a [compat::NsrTx] implementation
+    const COMPAT_SCRATCH: usize = 0;
 }
 struct MarkerNsrTx;
 impl<T> crate::imc::ChannelHandler<MarkerNsrTx> for T
@@ -544,9 +1071,17 @@ where
 }
 
 future_wrapper!(nsr_tx_rdy_op => <T as NsrTx>(cb: *mut ffi::udi_nic_tx_cb_t) val @ {
-    // SAFE: Trait is unsafe
-    val.tx_rdy(unsafe { cb.into_owned() })
+    let _ = (val, cb);
+    ::core::future::ready(())
+} finally( () ) {
+    // SAFE: No task is running on the CB
+    val.tx_rdy(unsafe { TxChain::from_raw(cb) });
 });
+mod nsr_tx_rdy_compat {
+    pub const fn task_size<T: super::NsrTx>() -> usize {
+        T::COMPAT_SCRATCH
+    }
+}
 map_ops_structure!{
     ffi::udi_nsr_tx_ops_t => NsrTx,MarkerNsrTx {
         nsr_tx_rdy_op,
@@ -554,21 +1089,19 @@ map_ops_structure!{
     CBS {
         ffi::udi_nic_tx_cb_t,
     }
+    EXTRA_OP nsr_tx_rdy_compat
 }
 
 // --------------------------------------------------------------------
 
-/// SAFETY:
-/// The implementations of `rx_rdy` shall ensure that the cb is not
-/// dropped until after the future completes.
-/// 
-/// Failure to do so can lead to crashes. See the module documentation for more details
-pub unsafe trait NdRx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
-    async_method!(
-        /// Add a RX CB to the pool of available CBs for incoming packets
-        fn rx_rdy(&'a self, cb: CbHandleNicRx)->()
-        as Future_rx_rdy
-    );
+/// Network Device - Receive operations
+pub trait NdRx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+    /// Add RX CBs to the pool of available CBs for incoming packets
+    fn rx_rdy(&self, cbs: RxChain);
+
+    #[doc(hidden)]
+    /// Scratch space needed by a [compat::NdRx] implementation
+    const COMPAT_SCRATCH: usize = 0;
 }
 struct MarkerNdRx;
 impl<T> crate::imc::ChannelHandler<MarkerNdRx> for T
@@ -577,9 +1110,17 @@ where
 {
 }
 future_wrapper!(nd_rx_rdy_op => <T as NdRx>(cb: *mut ffi::udi_nic_rx_cb_t) val @ {
-    // SAFE: The trait is unsafe
-    val.rx_rdy( unsafe { cb.into_owned() } )
+    let _ = (val, cb);
+    ::core::future::ready(())
+} finally( () ) {
+    // SAFE: No task is running on the CB
+    val.rx_rdy(unsafe { RxChain::from_raw(cb) });
 });
+mod nd_rx_rdy_compat {
+    pub const fn task_size<T: super::NdRx>() -> usize {
+        T::COMPAT_SCRATCH
+    }
+}
 map_ops_structure!{
     ffi::udi_nd_rx_ops_t => NdRx,MarkerNdRx {
         nd_rx_rdy_op,
@@ -587,27 +1128,24 @@ map_ops_structure!{
     CBS {
         ffi::udi_nic_rx_cb_t,
     }
+    EXTRA_OP nd_rx_rdy_compat
 }
 // --------------------------------------------------------------------
 /// Network Service Requester - Receive operations
-/// 
-/// SAFETY:
-/// The implementations of `rx_ind`/`exp_rx_ind` shall ensure that the
-/// cb is not dropped until after the future completes.
-/// 
-/// Failure to do so can lead to crashes. See the module documentation for more details
-pub unsafe trait NsrRx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+///
+/// The CBs are borrowed while `rx_ind`/`exp_rx_ind` runs, and then handed back through `rx_cb_ret`.
+pub trait NsrRx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
     async_method!{
-        /// Indication of a received packet
-        fn rx_ind(&'a self, cb: CbRefNicRx<'a>)->() as Future_rx_ind
+        /// Indication of received packets
+        fn rx_ind(&'a self, cbs: RxChainRef<'a>)->() as Future_rx_ind
     }
     async_method!(
-        /// Indication of a newly recivied packet that should be processed in an expedited manner
-        fn exp_rx_ind(&'a self, cb: CbRefNicRx<'a>)->()
+        /// Indication of newly received packets that should be processed in an expedited manner
+        fn exp_rx_ind(&'a self, cbs: RxChainRef<'a>)->()
         as Future_exp_rx_ind
     );
-    /// Return the CB to either the ND or release it
-    fn rx_cb_ret(&self, cb: CbHandleNicRx);
+    /// Return the CBs to either the ND (with [nd_rx_rdy]) or release them
+    fn rx_cb_ret(&self, cbs: RxChain);
 }
 struct MarkerNsrRx;
 impl<T> crate::imc::ChannelHandler<MarkerNsrRx> for T
@@ -616,16 +1154,18 @@ where
 {
 }
 future_wrapper!(nsr_rx_ind_op => <T as NsrRx>(cb: *mut ffi::udi_nic_rx_cb_t) val @ {
-    val.rx_ind(cb)
+    // SAFE: The chain is only handed on in `finally`, after the future has completed
+    val.rx_ind(unsafe { RxChainRef::from_raw(cb.to_raw()) })
 } finally( () ) {
-    // SAFE: CB is valid
-    val.rx_cb_ret(unsafe { CbHandleNicRx::from_raw(cb) });
+    // SAFE: The task is complete, so ownership can be released
+    val.rx_cb_ret(unsafe { RxChain::from_raw(cb) });
 });
 future_wrapper!(nsr_exp_rx_ind_op => <T as NsrRx>(cb: *mut ffi::udi_nic_rx_cb_t) val @ {
-    val.exp_rx_ind(cb)
+    // SAFE: The chain is only handed on in `finally`, after the future has completed
+    val.exp_rx_ind(unsafe { RxChainRef::from_raw(cb.to_raw()) })
 } finally( () ) {
-    // SAFE: CB is valid
-    val.rx_cb_ret(unsafe { CbHandleNicRx::from_raw(cb) });
+    // SAFE: The task is complete, so ownership can be released
+    val.rx_cb_ret(unsafe { RxChain::from_raw(cb) });
 });
 map_ops_structure!{
     ffi::udi_nsr_rx_ops_t => NsrRx,MarkerNsrRx {
@@ -636,27 +1176,680 @@ map_ops_structure!{
         ffi::udi_nic_rx_cb_t,
     }
 }
-
 // --------------------------------------------------------------------
 
 /// Result type from a bind
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
 pub struct NicInfo {
     /// Type of media used for the network device
-    pub media_type: ffi::MediaType,
+    pub media_type: MediaType,
     /// Minimum packet size the device can send - if zero then a value is assumed from `media_type`
     pub min_pdu_size: u32,
     /// Maximum packet size the device can send - if zero then a value is assumed from `media_type`
     pub max_pdu_size: u32,
     /// A hint to the NSR as to how many RX slots the device has (thus how many RX CBs to allocate)
     pub rx_hw_threshold: u32,
-    /// Bitset listing device capabilities
-    pub capabilities: u32,
+    /// Device capabilities
+    pub capabilities: Capabilities,
+    /// Multicast filtering supported by the device
+    pub multicast: MulticastFilter,
+    /// MAC (physical layer) address of the network device
+    pub mac_addr: MacAddress,
+}
+impl NicInfo {
+    /// Read (and validate) the device description from a bind CB
+    pub fn from_cb(cb: &ffi::udi_nic_bind_cb_t) -> Result<Self,BindError> {
+        let rv = NicInfo {
+            media_type: MediaType::from_raw(cb.media_type).ok_or(BindError::MediaType(cb.media_type))?,
+            min_pdu_size: cb.min_pdu_size,
+            max_pdu_size: cb.max_pdu_size,
+            rx_hw_threshold: cb.rx_hw_threshold,
+            capabilities: Capabilities::from_raw(cb.capabilities),
+            multicast: MulticastFilter {
+                max_perfect: cb.max_perfect_multicast,
+                max_total: cb.max_total_multicast,
+            },
+            mac_addr: if (cb.mac_addr_len as usize) <= ffi::UDI_NIC_MAC_ADDRESS_SIZE {
+                MacAddress::new(&cb.mac_addr[..cb.mac_addr_len as usize])
+            }
+            else {
+                return Err(BindError::MacAddrLength(cb.mac_addr_len))
+            },
+        };
+        rv.validate()?;
+        Ok(rv)
+    }
+    /// Populate a bind CB
+    pub fn write_cb(&self, cb: &mut ffi::udi_nic_bind_cb_t) {
+        cb.media_type = self.media_type.to_raw();
+        cb.min_pdu_size = self.min_pdu_size;
+        cb.max_pdu_size = self.max_pdu_size;
+        cb.rx_hw_threshold = self.rx_hw_threshold;
+        cb.capabilities = self.capabilities.to_raw();
+        cb.max_perfect_multicast = self.multicast.max_perfect;
+        cb.max_total_multicast = self.multicast.max_total;
+        cb.mac_addr_len = self.mac_addr.as_bytes().len() as u8;
+        cb.mac_addr = [0; ffi::UDI_NIC_MAC_ADDRESS_SIZE];
+        cb.mac_addr[..self.mac_addr.as_bytes().len()].copy_from_slice(self.mac_addr.as_bytes());
+    }
+
+    /// Get the PDU size range, using the defaults for the media type in place of zero values
+    pub fn pdu_range(&self) -> ::core::ops::RangeInclusive<u32> {
+        let def = self.media_type.default_pdu_range();
+        let min = if self.min_pdu_size != 0 { self.min_pdu_size } else { def.as_ref().map_or(0, |r| *r.start()) };
+        let max = if self.max_pdu_size != 0 { self.max_pdu_size } else { def.as_ref().map_or(u32::MAX, |r| *r.end()) };
+        min ..= max
+    }
+
+    /// Check that the description is self-consistent, and matches the requirements of the media type
+    pub fn validate(&self) -> Result<(),BindError> {
+        let mac_len = self.mac_addr.as_bytes().len();
+        if mac_len == 0 || (self.media_type.is_ethernet() && mac_len != 6) {
+            return Err(BindError::MacAddrLength(mac_len as u8));
+        }
+        if self.multicast.max_perfect > self.multicast.max_total {
+            return Err(BindError::Multicast(self.multicast));
+        }
+        let range = self.pdu_range();
+        let (min, max) = (*range.start(), *range.end());
+        if min > max {
+            return Err(BindError::PduRange { min, max });
+        }
+        if self.media_type.is_ethernet() {
+            // Largest frame (without FCS), allowing for jumbo frames on gigabit and for a VLAN tag
+            let max_limit = match self.media_type {
+                MediaType::GigabitEthernet => 9014,
+                _ => 1514,
+                } + if self.capabilities.contains(Capabilities::NONSTANDARD_VLAN_TAGGING) { 4 } else { 0 };
+            // The device must be able to send a header, and must be able to send a minimum-sized frame
+            if !(14 ..= 60).contains(&min) || !(60 ..= max_limit).contains(&max) {
+                return Err(BindError::PduRange { min, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error from validating a [NicInfo]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum BindError {
+    /// Unknown `media_type` value
+    MediaType(u8),
+    /// MAC address length is not valid for the media
+    MacAddrLength(u8),
+    /// The PDU size range is empty or outside of what the media allows
+    PduRange {
+        /// Minimum PDU size (after applying defaults)
+        min: u32,
+        /// Maximum PDU size (after applying defaults)
+        max: u32,
+    },
+    /// More perfect-match multicast addresses than total multicast addresses
+    Multicast(MulticastFilter),
+}
+impl ::core::fmt::Display for BindError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        match *self {
+        BindError::MediaType(v) => write!(f, "unknown media type {}", v),
+        BindError::MacAddrLength(l) => write!(f, "invalid MAC address length {}", l),
+        BindError::PduRange { min, max } => write!(f, "invalid PDU size range {}-{}", min, max),
+        BindError::Multicast(m) => write!(f, "{} perfect multicast addresses exceeds total of {}", m.max_perfect, m.max_total),
+        }
+    }
+}
+
+/// Network media type (`udi_nic_bind_cb_t.media_type`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum MediaType {
+    /// 10Mbps Ethernet (IEEE 802.3)
+    Ethernet,
+    /// 100Mbps Ethernet (IEEE 802.3u)
+    FastEthernet,
+    /// Gigabit Ethernet (IEEE 802.3z/ab)
+    GigabitEthernet,
+    /// Token Ring (IEEE 802.5)
+    TokenRing,
+    /// 100VG-AnyLAN (IEEE 802.12)
+    VgAnyLan,
+    /// Fiber Distributed Data Interface
+    Fddi,
+    /// Asynchronous Transfer Mode
+    Atm,
+    /// Fibre Channel
+    FibreChannel,
+    /// Any other media
+    Misc,
+}
+impl MediaType {
+    /// Decode a raw `media_type` value
+    pub fn from_raw(v: u8) -> Option<Self> {
+        const ETHER: u8 = ffi::UDI_NIC_ETHER as u8;
+        const FASTETHER: u8 = ffi::UDI_NIC_FASTETHER as u8;
+        const GIGETHER: u8 = ffi::UDI_NIC_GIGETHER as u8;
+        const TOKEN: u8 = ffi::UDI_NIC_TOKEN as u8;
+        const VGANYLAN: u8 = ffi::UDI_NIC_VGANYLAN as u8;
+        const FDDI: u8 = ffi::UDI_NIC_FDDI as u8;
+        const ATM: u8 = ffi::UDI_NIC_ATM as u8;
+        const FC: u8 = ffi::UDI_NIC_FC as u8;
+        const MISCMEDIA: u8 = ffi::UDI_NIC_MISCMEDIA as u8;
+        Some(match v {
+        ETHER => MediaType::Ethernet,
+        FASTETHER => MediaType::FastEthernet,
+        GIGETHER => MediaType::GigabitEthernet,
+        TOKEN => MediaType::TokenRing,
+        VGANYLAN => MediaType::VgAnyLan,
+        FDDI => MediaType::Fddi,
+        ATM => MediaType::Atm,
+        FC => MediaType::FibreChannel,
+        MISCMEDIA => MediaType::Misc,
+        _ => return None,
+        })
+    }
+    /// Get the raw `media_type` value
+    pub fn to_raw(self) -> u8 {
+        (match self {
+        MediaType::Ethernet => ffi::UDI_NIC_ETHER,
+        MediaType::FastEthernet => ffi::UDI_NIC_FASTETHER,
+        MediaType::GigabitEthernet => ffi::UDI_NIC_GIGETHER,
+        MediaType::TokenRing => ffi::UDI_NIC_TOKEN,
+        MediaType::VgAnyLan => ffi::UDI_NIC_VGANYLAN,
+        MediaType::Fddi => ffi::UDI_NIC_FDDI,
+        MediaType::Atm => ffi::UDI_NIC_ATM,
+        MediaType::FibreChannel => ffi::UDI_NIC_FC,
+        MediaType::Misc => ffi::UDI_NIC_MISCMEDIA,
+        }) as u8
+    }
+    /// Check if this is one of the Ethernet (IEEE 802.3) media types
+    pub fn is_ethernet(self) -> bool {
+        matches!(self, MediaType::Ethernet | MediaType::FastEthernet | MediaType::GigabitEthernet)
+    }
+    /// PDU size range (excluding FCS) assumed when the device reports zero, if the media defines one
+    pub fn default_pdu_range(self) -> Option<::core::ops::RangeInclusive<u32>> {
+        match self {
+        MediaType::Ethernet | MediaType::FastEthernet | MediaType::GigabitEthernet => Some(60 ..= 1514),
+        MediaType::Fddi => Some(17 ..= 4495),
+        _ => None,
+        }
+    }
+    /// Nominal link speed of the media
+    pub fn nominal_speed(self) -> LinkSpeed {
+        match self {
+        MediaType::Ethernet => LinkSpeed::Mbps(10),
+        MediaType::FastEthernet => LinkSpeed::Mbps(100),
+        MediaType::GigabitEthernet => LinkSpeed::Mbps(1000),
+        MediaType::VgAnyLan => LinkSpeed::Mbps(100),
+        MediaType::Fddi => LinkSpeed::Mbps(100),
+        _ => LinkSpeed::Unknown,
+        }
+    }
+}
+
+/// Link speed, as reported in an information request (`link_mbps`/`link_bps`)
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub enum LinkSpeed {
+    /// Speed not known
+    #[default]
+    Unknown,
+    /// Megabits per second
+    Mbps(u32),
+    /// Bits per second, for links slower than 1Mbps
+    Bps(u32),
+}
+impl LinkSpeed {
+    /// Decode from the `link_mbps` and `link_bps` fields (`link_mbps` takes priority)
+    pub fn from_raw(link_mbps: u32, link_bps: u32) -> Self {
+        match (link_mbps, link_bps) {
+        (0, 0) => LinkSpeed::Unknown,
+        (0, bps) => LinkSpeed::Bps(bps),
+        (mbps, _) => LinkSpeed::Mbps(mbps),
+        }
+    }
+    /// Get the `link_mbps` and `link_bps` values
+    pub fn to_raw(self) -> (u32, u32) {
+        match self {
+        LinkSpeed::Unknown => (0, 0),
+        LinkSpeed::Mbps(v) => (v, 0),
+        LinkSpeed::Bps(v) => (0, v),
+        }
+    }
+    /// Speed in bits per second
+    pub fn bits_per_second(self) -> Option<u64> {
+        match self {
+        LinkSpeed::Unknown => None,
+        LinkSpeed::Mbps(v) => Some(v as u64 * 1_000_000),
+        LinkSpeed::Bps(v) => Some(v as u64),
+        }
+    }
+}
+
+/// Device capabilities (`udi_nic_bind_cb_t.capabilities`)
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct Capabilities(u32);
+impl Capabilities {
+    /// No capabilities
+    pub const NONE: Self = Capabilities(0);
+    /// The device computes IP header checksums on transmit
+    pub const TX_IP_CKSUM: Self = Capabilities(ffi::UDI_NIC_CAP_TX_IP_CKSUM);
+    /// The device computes TCP checksums on transmit
+    pub const TX_TCP_CKSUM: Self = Capabilities(ffi::UDI_NIC_CAP_TX_TCP_CKSUM);
+    /// The device computes UDP checksums on transmit
+    pub const TX_UDP_CKSUM: Self = Capabilities(ffi::UDI_NIC_CAP_TX_UDP_CKSUM);
+    /// Transmitted multicast packets are looped back to the receive path by the device
+    pub const MCAST_LOOPBK: Self = Capabilities(ffi::UDI_NIC_CAP_MCAST_LOOPBK);
+    /// Transmitted broadcast packets are looped back to the receive path by the device
+    pub const BCAST_LOOPBK: Self = Capabilities(ffi::UDI_NIC_CAP_BCAST_LOOPBK);
+    /// Non-standard (not in UDI 1.01): The device inserts/strips IEEE 802.1Q VLAN tags, and accepts tagged frames
+    ///
+    /// Uses a bit that the specification leaves unassigned, so only meaningful between drivers built on this crate
+    pub const NONSTANDARD_VLAN_TAGGING: Self = Capabilities(1 << 31);
+    /// All checksum offload capabilities
+    pub const TX_CKSUM_ALL: Self = Capabilities(ffi::UDI_NIC_CAP_TX_IP_CKSUM|ffi::UDI_NIC_CAP_TX_TCP_CKSUM|ffi::UDI_NIC_CAP_TX_UDP_CKSUM);
+
+    /// Construct from the raw `capabilities` value
+    pub const fn from_raw(v: u32) -> Self {
+        Capabilities(v)
+    }
+    /// Get the raw `capabilities` value
+    pub const fn to_raw(self) -> u32 {
+        self.0
+    }
+    /// Check if all of the capabilities in `other` are present
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+impl ::core::ops::BitOr for Capabilities {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Capabilities(self.0 | rhs.0)
+    }
+}
+
+/// Multicast filter limits of a device
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct MulticastFilter {
     /// Number of multicast addresses that can be perfectly handled (i.e. not using fuzzy matching)
-    pub max_perfect_multicast: u8,
+    pub max_perfect: u8,
     /// Total number of multicast addresses that can be handled
-    pub max_total_multicast: u8,
-    /// Length of the MAC (physical layer) address
-    pub mac_addr_len: u8,
-    /// MAC (physical layer) address of the network device
-    pub mac_addr: [u8; ffi::UDI_NIC_MAC_ADDRESS_SIZE],
+    pub max_total: u8,
+}
+
+/// A NIC statistics counter (see [Stats])
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Counter {
+    /// Packets successfully transmitted
+    TxPackets,
+    /// Packets successfully received
+    RxPackets,
+    /// Packets that failed to transmit due to an error
+    TxErrors,
+    /// Received packets that had errors
+    RxErrors,
+    /// Packets dropped before transmission (e.g. due to resource shortages)
+    TxDiscards,
+    /// Received packets dropped (e.g. due to no RX CBs being available)
+    RxDiscards,
+    /// Transmit underruns
+    TxUnderrun,
+    /// Receive overruns
+    RxOverrun,
+    /// Collisions detected on the medium
+    Collisions,
+}
+impl Counter {
+    const COUNT: usize = 9;
+}
+
+/// Accumulator for the statistics reported in a `udi_nic_info_cb_t`, intended to be stored in region data
+///
+/// Counters wrap on overflow, as expected by the NSR.
+#[derive(Default)]
+pub struct Stats {
+    counters: [::core::cell::Cell<u32>; Counter::COUNT],
+}
+impl Stats {
+    /// Create a zeroed set of counters
+    pub const fn new() -> Self {
+        Stats { counters: [const { ::core::cell::Cell::new(0) }; Counter::COUNT] }
+    }
+    /// Increment a counter
+    pub fn inc(&self, counter: Counter) {
+        self.add(counter, 1)
+    }
+    /// Add `count` to a counter
+    pub fn add(&self, counter: Counter, count: u32) {
+        let c = &self.counters[counter as usize];
+        c.set(c.get().wrapping_add(count));
+    }
+    /// Get the current value of a counter
+    pub fn get(&self, counter: Counter) -> u32 {
+        self.counters[counter as usize].get()
+    }
+    /// Reset all counters to zero
+    pub fn reset(&self) {
+        for c in &self.counters {
+            c.set(0);
+        }
+    }
+    /// Get a copy of all counters
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let g = |c| self.get(c);
+        StatsSnapshot {
+            tx_packets: g(Counter::TxPackets),
+            rx_packets: g(Counter::RxPackets),
+            tx_errors: g(Counter::TxErrors),
+            rx_errors: g(Counter::RxErrors),
+            tx_discards: g(Counter::TxDiscards),
+            rx_discards: g(Counter::RxDiscards),
+            tx_underrun: g(Counter::TxUnderrun),
+            rx_overrun: g(Counter::RxOverrun),
+            collisions: g(Counter::Collisions),
+        }
+    }
+    /// Get the counters for an information request, resetting them afterwards if `reset_statistics` was requested
+    pub fn report(&self, reset_statistics: bool) -> StatsSnapshot {
+        let rv = self.snapshot();
+        if reset_statistics {
+            self.reset();
+        }
+        rv
+    }
+}
+
+/// Values of the statistics counters in a `udi_nic_info_cb_t` (see [Stats])
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct StatsSnapshot {
+    /// See [Counter::TxPackets]
+    pub tx_packets: u32,
+    /// See [Counter::RxPackets]
+    pub rx_packets: u32,
+    /// See [Counter::TxErrors]
+    pub tx_errors: u32,
+    /// See [Counter::RxErrors]
+    pub rx_errors: u32,
+    /// See [Counter::TxDiscards]
+    pub tx_discards: u32,
+    /// See [Counter::RxDiscards]
+    pub rx_discards: u32,
+    /// See [Counter::TxUnderrun]
+    pub tx_underrun: u32,
+    /// See [Counter::RxOverrun]
+    pub rx_overrun: u32,
+    /// See [Counter::Collisions]
+    pub collisions: u32,
+}
+impl StatsSnapshot {
+    /// Read the counters from an info CB
+    pub fn from_cb(cb: &ffi::udi_nic_info_cb_t) -> Self {
+        StatsSnapshot {
+            tx_packets: cb.tx_packets,
+            rx_packets: cb.rx_packets,
+            tx_errors: cb.tx_errors,
+            rx_errors: cb.rx_errors,
+            tx_discards: cb.tx_discards,
+            rx_discards: cb.rx_discards,
+            tx_underrun: cb.tx_underrun,
+            rx_overrun: cb.rx_overrun,
+            collisions: cb.collisions,
+        }
+    }
+    /// Populate the counters in an info CB
+    pub fn write_cb(&self, cb: &mut ffi::udi_nic_info_cb_t) {
+        cb.tx_packets = self.tx_packets;
+        cb.rx_packets = self.rx_packets;
+        cb.tx_errors = self.tx_errors;
+        cb.rx_errors = self.rx_errors;
+        cb.tx_discards = self.tx_discards;
+        cb.rx_discards = self.rx_discards;
+        cb.tx_underrun = self.tx_underrun;
+        cb.rx_overrun = self.rx_overrun;
+        cb.collisions = self.collisions;
+    }
+}
+
+/// Result of an information request (see [Control::info_req] and [NsrControl::info_ack])
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub struct InfoAck {
+    /// The interface has been enabled
+    pub interface_is_active: bool,
+    /// The link is up
+    pub link_is_active: bool,
+    /// The link is operating in full-duplex mode
+    pub is_full_duplex: bool,
+    /// Link speed
+    pub link_speed: LinkSpeed,
+    /// Statistics counters
+    pub stats: StatsSnapshot,
+}
+impl InfoAck {
+    /// Read the response from an info CB
+    pub fn from_cb(cb: &ffi::udi_nic_info_cb_t) -> Self {
+        InfoAck {
+            interface_is_active: cb.interface_is_active.to_bool(),
+            link_is_active: cb.link_is_active.to_bool(),
+            is_full_duplex: cb.is_full_duplex.to_bool(),
+            link_speed: LinkSpeed::from_raw(cb.link_mbps, cb.link_bps),
+            stats: StatsSnapshot::from_cb(cb),
+        }
+    }
+    /// Populate an info CB
+    pub fn write_cb(&self, cb: &mut ffi::udi_nic_info_cb_t) {
+        cb.interface_is_active = self.interface_is_active.into();
+        cb.link_is_active = self.link_is_active.into();
+        cb.is_full_duplex = self.is_full_duplex.into();
+        (cb.link_mbps, cb.link_bps) = self.link_speed.to_raw();
+        self.stats.write_cb(cb);
+    }
+}
+
+/// Error for a malformed control request
+fn not_understood<T>() -> crate::Result<T> {
+    Err(crate::Error::from_status(crate::ffi::UDI_STAT_NOT_UNDERSTOOD as _).unwrap_err())
+}
+
+/// A MAC (physical layer) address, of up to [ffi::UDI_NIC_MAC_ADDRESS_SIZE] bytes
+#[derive(Copy,Clone,PartialEq,Eq)]
+pub struct MacAddress {
+    len: u8,
+    bytes: [u8; ffi::UDI_NIC_MAC_ADDRESS_SIZE],
+}
+impl MacAddress {
+    /// Create an address from its bytes
+    /// 
+    /// Panics if `addr` is longer than [ffi::UDI_NIC_MAC_ADDRESS_SIZE]
+    pub fn new(addr: &[u8]) -> Self {
+        assert!(addr.len() <= ffi::UDI_NIC_MAC_ADDRESS_SIZE, "MAC address too long ({} bytes)", addr.len());
+        let mut bytes = [0; ffi::UDI_NIC_MAC_ADDRESS_SIZE];
+        bytes[..addr.len()].copy_from_slice(addr);
+        MacAddress { len: addr.len() as u8, bytes }
+    }
+    /// Get the address bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+    /// Read a `len` byte address from the start of a buffer
+    fn from_buf(buf: &crate::buf::Handle, len: usize) -> crate::Result<Self> {
+        Self::from_buf_at(buf, 0, len)
+    }
+    fn from_buf_at(buf: &crate::buf::Handle, ofs: usize, len: usize) -> crate::Result<Self> {
+        if len > ffi::UDI_NIC_MAC_ADDRESS_SIZE || ofs + len > buf.len() {
+            return not_understood();
+        }
+        let mut bytes = [0; ffi::UDI_NIC_MAC_ADDRESS_SIZE];
+        buf.read(ofs, &mut bytes[..len]);
+        Ok(MacAddress { len: len as u8, bytes })
+    }
+}
+impl ::core::fmt::Debug for MacAddress {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        for (i,b) in self.as_bytes().iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// A list of multicast addresses, either stored in a control CB's data buffer or in a slice
+#[derive(Copy,Clone)]
+pub struct MulticastList<'a>(MulticastRepr<'a>);
+#[derive(Copy,Clone)]
+enum MulticastRepr<'a> {
+    Buf { buf: &'a crate::buf::Handle, count: usize, addr_len: usize },
+    Slice(&'a [MacAddress]),
+}
+impl<'a> MulticastList<'a> {
+    /// Create a list from a slice of addresses (for sending a request)
+    pub fn from_slice(addrs: &'a [MacAddress]) -> Self {
+        MulticastList(MulticastRepr::Slice(addrs))
+    }
+    /// `count` equal-sized addresses packed in a buffer
+    fn from_buf(buf: &'a crate::buf::Handle, count: usize) -> crate::Result<Self> {
+        let addr_len = buf.len().checked_div(count).unwrap_or(0);
+        if count > 0 && (addr_len == 0 || addr_len > ffi::UDI_NIC_MAC_ADDRESS_SIZE || addr_len * count != buf.len()) {
+            return not_understood();
+        }
+        Ok(MulticastList(MulticastRepr::Buf { buf, count, addr_len }))
+    }
+    /// Number of addresses in the list
+    pub fn count(&self) -> usize {
+        match self.0 {
+        MulticastRepr::Buf { count, .. } => count,
+        MulticastRepr::Slice(s) => s.len(),
+        }
+    }
+    /// Get an address from the list
+    pub fn get(&self, idx: usize) -> Option<MacAddress> {
+        match self.0 {
+        MulticastRepr::Buf { buf, count, addr_len } if idx < count => MacAddress::from_buf_at(buf, idx * addr_len, addr_len).ok(),
+        MulticastRepr::Buf { .. } => None,
+        MulticastRepr::Slice(s) => s.get(idx).copied(),
+        }
+    }
+    /// Iterate the addresses in the list
+    pub fn iter(&self) -> impl Iterator<Item=MacAddress> + 'a {
+        let s = *self;
+        (0 .. s.count()).filter_map(move |i| s.get(i))
+    }
+}
+impl ::core::fmt::Debug for MulticastList<'_> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A control operation on a network device (see [Control::ctrl_req] and [nd_ctrl_req])
+#[derive(Debug,Copy,Clone)]
+pub enum CtrlOp<'a> {
+    /// Add addresses to the multicast filter (`UDI_NIC_ADD_MULTI`)
+    AddMulti(MulticastList<'a>),
+    /// Remove addresses from the multicast filter (`UDI_NIC_DEL_MULTI`)
+    DelMulti(MulticastList<'a>),
+    /// Enable or disable reception of all multicast packets (`UDI_NIC_ALLMULTI_ON`/`UDI_NIC_ALLMULTI_OFF`)
+    AllMulti(bool),
+    /// Get the current MAC address (`UDI_NIC_GET_CURR_MAC`)
+    GetCurrMac,
+    /// Change the current MAC address (`UDI_NIC_SET_CURR_MAC`)
+    SetCurrMac(MacAddress),
+    /// Get the factory-assigned MAC address (`UDI_NIC_GET_FACT_MAC`)
+    GetFactMac,
+    /// Enable or disable promiscuous reception (`UDI_NIC_PROMISC_ON`/`UDI_NIC_PROMISC_OFF`)
+    Promisc(bool),
+    /// Reset the hardware (`UDI_NIC_HW_RESET`)
+    HwReset,
+    /// Enable or disable delivery of bad (e.g. bad CRC) packets (`UDI_NIC_BAD_RXPKT`)
+    BadRxPkt(bool),
+}
+impl<'a> CtrlOp<'a> {
+    /// Decode the operation from a control CB
+    pub fn from_cb(cb: CbRefNicCtrl<'a>) -> crate::Result<Self> {
+        // SAFE: The CB (and its buffer) is valid for `'a`
+        let buf = unsafe { crate::buf::Handle::from_ref(&(*cb.to_raw()).data_buf) };
+        let indicator = cb.indicator;
+        Ok(match cb.command {
+        ffi::UDI_NIC_ADD_MULTI    => CtrlOp::AddMulti(MulticastList::from_buf(buf, indicator as usize)?),
+        ffi::UDI_NIC_DEL_MULTI    => CtrlOp::DelMulti(MulticastList::from_buf(buf, indicator as usize)?),
+        ffi::UDI_NIC_ALLMULTI_ON  => CtrlOp::AllMulti(true),
+        ffi::UDI_NIC_ALLMULTI_OFF => CtrlOp::AllMulti(false),
+        ffi::UDI_NIC_GET_CURR_MAC => CtrlOp::GetCurrMac,
+        ffi::UDI_NIC_SET_CURR_MAC => CtrlOp::SetCurrMac(MacAddress::from_buf(buf, indicator as usize)?),
+        ffi::UDI_NIC_GET_FACT_MAC => CtrlOp::GetFactMac,
+        ffi::UDI_NIC_PROMISC_ON   => CtrlOp::Promisc(true),
+        ffi::UDI_NIC_PROMISC_OFF  => CtrlOp::Promisc(false),
+        ffi::UDI_NIC_HW_RESET     => CtrlOp::HwReset,
+        ffi::UDI_NIC_BAD_RXPKT    => CtrlOp::BadRxPkt(indicator != 0),
+        _ => return not_understood(),
+        })
+    }
+    /// Get the `command` and `indicator` values for this operation
+    pub fn to_raw(&self) -> (u8, u32) {
+        match *self {
+        CtrlOp::AddMulti(ref l) => (ffi::UDI_NIC_ADD_MULTI, l.count() as u32),
+        CtrlOp::DelMulti(ref l) => (ffi::UDI_NIC_DEL_MULTI, l.count() as u32),
+        CtrlOp::AllMulti(true ) => (ffi::UDI_NIC_ALLMULTI_ON , 0),
+        CtrlOp::AllMulti(false) => (ffi::UDI_NIC_ALLMULTI_OFF, 0),
+        CtrlOp::GetCurrMac => (ffi::UDI_NIC_GET_CURR_MAC, 0),
+        CtrlOp::SetCurrMac(ref a) => (ffi::UDI_NIC_SET_CURR_MAC, a.as_bytes().len() as u32),
+        CtrlOp::GetFactMac => (ffi::UDI_NIC_GET_FACT_MAC, 0),
+        CtrlOp::Promisc(true ) => (ffi::UDI_NIC_PROMISC_ON , 0),
+        CtrlOp::Promisc(false) => (ffi::UDI_NIC_PROMISC_OFF, 0),
+        CtrlOp::HwReset => (ffi::UDI_NIC_HW_RESET, 0),
+        CtrlOp::BadRxPkt(v) => (ffi::UDI_NIC_BAD_RXPKT, v as u32),
+        }
+    }
+    /// Write the operation's payload (if any) to a data buffer
+    fn write_data<'b>(&'b self, gcb: crate::CbRef<'b, crate::ffi::udi_cb_t>, buf: &'b mut crate::buf::Handle) -> impl ::core::future::Future<Output=()> + 'b {
+        async move {
+            match *self {
+            CtrlOp::AddMulti(ref l)
+            |CtrlOp::DelMulti(ref l) => {
+                let mut ofs = 0;
+                for (i,addr) in l.iter().enumerate() {
+                    let addr = addr.as_bytes();
+                    // Replace the existing contents with the first address, then append
+                    let dst = if i == 0 { 0 .. buf.len() } else { ofs .. ofs };
+                    buf.write(gcb, dst, addr).await;
+                    ofs += addr.len();
+                }
+                if ofs == 0 && buf.len() > 0 {
+                    buf.truncate(0);
+                }
+                },
+            CtrlOp::SetCurrMac(ref a) => buf.write(gcb, .., a.as_bytes()).await,
+            _ => {},
+            }
+        }
+    }
+}
+
+/// Link status reported by a device (see [nsr_status_ind] and [NsrControl::status_ind])
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum NicStatus {
+    /// The link has gone down (`UDI_NIC_LINK_DOWN`)
+    LinkDown,
+    /// The link is up (`UDI_NIC_LINK_UP`)
+    LinkUp,
+    /// The device has been reset, any state set by control requests has been lost (`UDI_NIC_LINK_RESET`)
+    LinkReset,
+}
+impl NicStatus {
+    /// Decode a raw `event` value
+    pub fn from_raw(event: u8) -> Option<Self> {
+        match event {
+        ffi::UDI_NIC_LINK_DOWN => Some(NicStatus::LinkDown),
+        ffi::UDI_NIC_LINK_UP => Some(NicStatus::LinkUp),
+        ffi::UDI_NIC_LINK_RESET => Some(NicStatus::LinkReset),
+        _ => None,
+        }
+    }
+    /// Get the raw `event` value
+    pub fn to_raw(self) -> u8 {
+        match self {
+        NicStatus::LinkDown => ffi::UDI_NIC_LINK_DOWN,
+        NicStatus::LinkUp => ffi::UDI_NIC_LINK_UP,
+        NicStatus::LinkReset => ffi::UDI_NIC_LINK_RESET,
+        }
+    }
 }
@@ -0,0 +1,124 @@
+//! Original (`unsafe`) versions of the NIC Rx/Tx traits
+//!
+//! These traits pass an owning handle to the CB to the method. If this control block is handed on or dropped before
+//! the future completes, the future will read freed memory - the implementation of these methods can avoid UB by
+//! storing the cb passed in the region data after the last async call. This ensures that it will not be reused
+//! until after the future has been cleaned up.
+//!
+//! Any type implementing one of these traits also implements the matching trait in [super], so can be used directly
+//! in [crate::define_driver].
+use crate::ffi::meta_nic as ffi;
+use super::{CbHandleNicRx, CbHandleNicTx, CbRefNicRx};
+use super::{RxChain, RxChainRef, TxChain, TxRequest};
+
+/// SAFETY:
+/// The implementations of `tx_req`/`exp_tx_req` shall ensure that the
+/// cb is not dropped until after the future completes.
+///
+/// Failure to do so can lead to crashes. See the module documentation for more details
+pub unsafe trait NdTx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+    async_method!(
+        /// Schedule transmission of a packet, using normal priority rules
+        fn tx_req(&'a self, cb: CbHandleNicTx)->()
+        as Future_tx_req
+    );
+    async_method!(
+        /// Schedule transmission of a packet, expediting the transmission
+        fn exp_tx_req(&'a self, cb: CbHandleNicTx)->()
+        as Future_exp_tx_req
+    );
+}
+impl<T: NdTx> super::NdTx for T {
+    type Future_tx_req<'s> = <T as NdTx>::Future_tx_req<'s>;
+    fn tx_req<'a>(&'a self, cbs: TxRequest<'a>) -> Self::Future_tx_req<'a> {
+        // SAFE: Trait is unsafe
+        <T as NdTx>::tx_req(self, unsafe { cbs.into_handle() })
+    }
+    type Future_exp_tx_req<'s> = <T as NdTx>::Future_exp_tx_req<'s>;
+    fn exp_tx_req<'a>(&'a self, cbs: TxRequest<'a>) -> Self::Future_exp_tx_req<'a> {
+        // SAFE: Trait is unsafe
+        <T as NdTx>::exp_tx_req(self, unsafe { cbs.into_handle() })
+    }
+}
+
+/// SAFETY:
+/// The implementations of `tx_rdy` shall ensure that the cb is not
+/// dropped until after the future completes.
+///
+/// Failure to do so can lead to crashes. See the module documentation for more details
+pub unsafe trait NsrTx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+    async_method!(
+        /// Return a TX CB to the pool
+        fn tx_rdy(&'a self, cb: CbHandleNicTx)->()
+        as Future_tx_rdy
+    );
+}
+future_wrapper!(nsr_tx_rdy_op => <T as NsrTx>(cb: *mut ffi::udi_nic_tx_cb_t) val @ {
+    // SAFE: Trait is unsafe
+    val.tx_rdy(unsafe { cb.into_owned() })
+});
+impl<T: NsrTx> super::NsrTx for T {
+    fn tx_rdy(&self, cbs: TxChain) {
+        // SAFE: The CB has no running task, and the scratch is sized using `COMPAT_SCRATCH`
+        unsafe { nsr_tx_rdy_op::<T>(cbs.into_raw()) }
+    }
+    const COMPAT_SCRATCH: usize = nsr_tx_rdy_op::task_size::<T>();
+}
+
+/// SAFETY:
+/// The implementations of `rx_rdy` shall ensure that the cb is not
+/// dropped until after the future completes.
+///
+/// Failure to do so can lead to crashes. See the module documentation for more details
+pub unsafe trait NdRx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+    async_method!(
+        /// Add a RX CB to the pool of available CBs for incoming packets
+        fn rx_rdy(&'a self, cb: CbHandleNicRx)->()
+        as Future_rx_rdy
+    );
+}
+future_wrapper!(nd_rx_rdy_op => <T as NdRx>(cb: *mut ffi::udi_nic_rx_cb_t) val @ {
+    // SAFE: The trait is unsafe
+    val.rx_rdy( unsafe { cb.into_owned() } )
+});
+impl<T: NdRx> super::NdRx for T {
+    fn rx_rdy(&self, cbs: RxChain) {
+        // SAFE: The CB has no running task, and the scratch is sized using `COMPAT_SCRATCH`
+        unsafe { nd_rx_rdy_op::<T>(cbs.into_raw()) }
+    }
+    const COMPAT_SCRATCH: usize = nd_rx_rdy_op::task_size::<T>();
+}
+
+/// Network Service Requester - Receive operations
+///
+/// SAFETY:
+/// The implementations of `rx_ind`/`exp_rx_ind` shall ensure that the
+/// cb is not dropped until after the future completes.
+///
+/// Failure to do so can lead to crashes. See the module documentation for more details
+pub unsafe trait NsrRx: 'static + crate::async_trickery::CbContext + crate::imc::ChannelInit {
+    async_method!{
+        /// Indication of a received packet
+        fn rx_ind(&'a self, cb: CbRefNicRx<'a>)->() as Future_rx_ind
+    }
+    async_method!(
+        /// Indication of a newly recivied packet that should be processed in an expedited manner
+        fn exp_rx_ind(&'a self, cb: CbRefNicRx<'a>)->()
+        as Future_exp_rx_ind
+    );
+    /// Return the CB to either the ND or release it
+    fn rx_cb_ret(&self, cb: CbHandleNicRx);
+}
+impl<T: NsrRx> super::NsrRx for T {
+    type Future_rx_ind<'s> = <T as NsrRx>::Future_rx_ind<'s>;
+    fn rx_ind<'a>(&'a self, cbs: RxChainRef<'a>) -> Self::Future_rx_ind<'a> {
+        <T as NsrRx>::rx_ind(self, cbs.first())
+    }
+    type Future_exp_rx_ind<'s> = <T as NsrRx>::Future_exp_rx_ind<'s>;
+    fn exp_rx_ind<'a>(&'a self, cbs: RxChainRef<'a>) -> Self::Future_exp_rx_ind<'a> {
+        <T as NsrRx>::exp_rx_ind(self, cbs.first())
+    }
+    fn rx_cb_ret(&self, cbs: RxChain) {
+        <T as NsrRx>::rx_cb_ret(self, cbs.into_handle())
+    }
+}
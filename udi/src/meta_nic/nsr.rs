@@ -0,0 +1,249 @@
+//! Helpers for writing a Network Service Requester (the protocol side of a NIC binding)
+//!
+//! A [Binding] is stored in the NSR's region data, and is driven from the NSR's implementations of
+//! [NsrControl](super::NsrControl), [NsrTx](super::NsrTx) and [NsrRx](super::NsrRx):
+//! - `get_bind_channels` - [Binding::spawn_channels]
+//! - `bind_ack` - [Binding::bound], then [Binding::post_rx] and [Binding::enable]
+//! - `enable_ack` - [Binding::enable_ack] (sends any packets submitted while the device was disabled)
+//! - `status_ind` - [Binding::status_ind]
+//! - `tx_rdy` - [Binding::tx_rdy]
+//! - `rx_cb_ret` - [Binding::rx_cb_ret]
+//!
+//! Packets are then sent with [Binding::send] (or [Binding::tx_cb]/[Binding::tx_submit]/[Binding::tx_flush] for
+//! batches), and received packets arrive in `rx_ind`. Sending needs a free TX CB, which only exist once the ND has
+//! handed some over with `tx_rdy` - NDs do this while binding, so they are available by `enable_ack`.
+use ::core::cell::{Cell, OnceCell};
+use crate::ffi::meta_nic as ffi;
+use crate::ffi::{udi_cb_t, udi_channel_t};
+use crate::imc::ChannelHandle;
+use super::{BindChannels, BindError, CbHandleNicTx, CbRefNicBind, NicInfo, NicStatus, RxChain, TxChain};
+
+/// Error from [Binding::send]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum SendError {
+    /// No TX CBs are available (the ND hasn't supplied or returned any with `tx_rdy`)
+    NoCb,
+    /// The packet is larger than the device's maximum PDU size
+    TooLarge,
+}
+
+/// State of a NSR's binding to a network device, intended to be stored in region data
+pub struct Binding {
+    rx_channel: OnceCell<ChannelHandle>,
+    tx_channel: OnceCell<ChannelHandle>,
+    nd_channel: Cell<udi_channel_t>,
+    info: Cell<Option<NicInfo>>,
+    enabled: Cell<bool>,
+    link: Cell<Option<NicStatus>>,
+    tx_free: crate::cb::SharedQueue<ffi::udi_nic_tx_cb_t>,
+    n_tx_free: Cell<usize>,
+    tx_pending: crate::cb::SharedQueue<ffi::udi_nic_tx_cb_t>,
+    n_tx_pending: Cell<usize>,
+    tx_packets: Cell<u32>,
+    rx_packets: Cell<u32>,
+}
+impl Default for Binding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Binding {
+    /// Create an unbound state
+    pub const fn new() -> Self {
+        Binding {
+            rx_channel: OnceCell::new(),
+            tx_channel: OnceCell::new(),
+            nd_channel: Cell::new(::core::ptr::null_mut()),
+            info: Cell::new(None),
+            enabled: Cell::new(false),
+            link: Cell::new(None),
+            tx_free: crate::cb::SharedQueue::new(),
+            n_tx_free: Cell::new(0),
+            tx_pending: crate::cb::SharedQueue::new(),
+            n_tx_pending: Cell::new(0),
+            tx_packets: Cell::new(0),
+            rx_packets: Cell::new(0),
+        }
+    }
+
+    /// Spawn the RX and TX channels (from [NsrControl::get_bind_channels](super::NsrControl::get_bind_channels)),
+    /// returning the channel indexes to pass to the ND
+    ///
+    /// `OpsRx` and `OpsTx` are the driver's ops for the [NsrRx](super::NsrRx) and [NsrTx](super::NsrTx) traits
+    pub async fn spawn_channels<OpsRx, OpsTx, C>(&self, cb: CbRefNicBind<'_>, context: &C, chans: BindChannels) -> BindChannels
+    where
+        OpsRx: crate::ops_markers::Ops,
+        OpsTx: crate::ops_markers::Ops,
+        C: AsRef<OpsRx::Context> + AsRef<OpsTx::Context>,
+    {
+        let rx = crate::imc::channel_spawn::<OpsRx>(cb.gcb(), context, chans.rx).await;
+        let tx = crate::imc::channel_spawn::<OpsTx>(cb.gcb(), context, chans.tx).await;
+        if self.rx_channel.set(rx).is_err() || self.tx_channel.set(tx).is_err() {
+            panic!("NSR bound twice");
+        }
+        chans
+    }
+
+    /// Record a successful bind (from [NsrControl::bind_ack](super::NsrControl::bind_ack)), returning the
+    /// device's description
+    pub fn bound(&self, cb: CbRefNicBind<'_>) -> Result<NicInfo,BindError> {
+        let info = NicInfo::from_cb(&cb)?;
+        self.nd_channel.set(cb.gcb.channel);
+        self.info.set(Some(info));
+        Ok(info)
+    }
+
+    /// Allocate `count` RX CBs (of type `CbDef`) with buffers for the largest PDU, and hand them to the ND
+    ///
+    /// Must be called after [Binding::bound]
+    pub async fn post_rx<CbDef>(&self, gcb: crate::CbRef<'_, udi_cb_t>, count: u8)
+    where
+        CbDef: crate::cb::CbDefinition<Cb=ffi::udi_nic_rx_cb_t>,
+    {
+        let info = self.info.get().expect("`post_rx` called before `bound`");
+        let rx_channel = self.rx_channel.get().expect("`post_rx` called before `spawn_channels`");
+        // Media without a maximum PDU size get the largest buffer a CB can describe
+        let buf_size = (*info.pdu_range().end()).min(u16::MAX as u32) as usize;
+        let mut cbs = crate::cb::alloc_batch::<CbDef>(gcb, count, Some((buf_size, crate::ffi::buf::UDI_NULL_PATH_BUF))).await;
+        let mut chain: Option<RxChain> = None;
+        while let Some(mut cb) = cbs.pop_front() {
+            cb.set_channel(rx_channel);
+            match chain {
+            Some(ref mut c) => c.append(cb.into()),
+            None => chain = Some(cb.into()),
+            }
+        }
+        if let Some(chain) = chain {
+            super::nd_rx_rdy(chain);
+        }
+    }
+
+    /// Request that the ND enable the device, using a CB of type `CbDef`
+    pub async fn enable<CbDef>(&self, gcb: crate::CbRef<'_, udi_cb_t>)
+    where
+        CbDef: crate::cb::CbDefinition<Cb=ffi::udi_nic_cb_t>,
+    {
+        let cb = crate::cb::alloc::<CbDef>(gcb, self.nd_channel()).await;
+        super::nd_enable_req(cb);
+    }
+    /// Handle the result of [Binding::enable] (from [NsrControl::enable_ack](super::NsrControl::enable_ack))
+    ///
+    /// On success any packets already submitted are sent.
+    pub fn enable_ack(&self, res: crate::Result<()>) -> crate::Result<()> {
+        res?;
+        self.enabled.set(true);
+        self.tx_flush();
+        Ok(())
+    }
+    /// Request that the ND disable the device, using a CB of type `CbDef`
+    ///
+    /// Submitted packets are held until the device is enabled again
+    pub async fn disable<CbDef>(&self, gcb: crate::CbRef<'_, udi_cb_t>)
+    where
+        CbDef: crate::cb::CbDefinition<Cb=ffi::udi_nic_cb_t>,
+    {
+        self.enabled.set(false);
+        let cb = crate::cb::alloc::<CbDef>(gcb, self.nd_channel()).await;
+        super::nd_disable_req(cb);
+    }
+
+    /// Record a link status change (from [NsrControl::status_ind](super::NsrControl::status_ind)), returning
+    /// the previous status
+    pub fn status_ind(&self, status: NicStatus) -> Option<NicStatus> {
+        self.link.replace(Some(status))
+    }
+
+    /// Add TX CBs returned by the ND (from [NsrTx::tx_rdy](super::NsrTx::tx_rdy)) to the free pool
+    pub fn tx_rdy(&self, cbs: TxChain) {
+        let mut cur = Some(cbs);
+        while let Some(cbs) = cur {
+            let (cb, next) = cbs.split_first();
+            self.tx_free.push_back(cb.into_handle());
+            self.n_tx_free.set(self.n_tx_free.get() + 1);
+            cur = next;
+        }
+    }
+    /// Take a free TX CB, to be populated and passed to [Binding::tx_submit]
+    pub fn tx_cb(&self) -> Option<CbHandleNicTx> {
+        let rv = self.tx_free.pop_front();
+        if rv.is_some() {
+            self.n_tx_free.set(self.n_tx_free.get() - 1);
+        }
+        rv
+    }
+    /// Queue a populated TX CB, it is sent by the next [Binding::tx_flush]
+    pub fn tx_submit(&self, cb: CbHandleNicTx) {
+        self.tx_pending.push_back(cb);
+        self.n_tx_pending.set(self.n_tx_pending.get() + 1);
+    }
+    /// Send all submitted TX CBs to the ND as a single chain (if the device is enabled)
+    pub fn tx_flush(&self) {
+        if !self.enabled.get() {
+            return;
+        }
+        if let Some(cbs) = self.tx_pending.take_all() {
+            self.tx_packets.set(self.tx_packets.get().wrapping_add(self.n_tx_pending.replace(0) as u32));
+            super::nd_tx_req(cbs);
+        }
+    }
+    /// Copy `data` into a free TX CB and send it
+    pub async fn send(&self, gcb: crate::CbRef<'_, udi_cb_t>, data: &[u8]) -> Result<(),SendError> {
+        if let Some(info) = self.info.get() {
+            if data.len() as u64 > *info.pdu_range().end() as u64 {
+                return Err(SendError::TooLarge);
+            }
+        }
+        let mut cb = self.tx_cb().ok_or(SendError::NoCb)?;
+        cb.tx_buf_mut().write(gcb, .., data).await;
+        self.tx_submit(cb);
+        self.tx_flush();
+        Ok(())
+    }
+
+    /// Handle RX CBs returned after `rx_ind` (from [NsrRx::rx_cb_ret](super::NsrRx::rx_cb_ret))
+    ///
+    /// The CBs are handed back to the ND for re-use, or released if the device has not been bound.
+    pub fn rx_cb_ret(&self, cbs: RxChain) {
+        self.rx_packets.set(self.rx_packets.get().wrapping_add(cbs.count() as u32));
+        if self.info.get().is_some() {
+            super::nd_rx_rdy(cbs);
+        }
+    }
+
+    /// Channel to the ND's control endpoint (for allocating control/info CBs)
+    ///
+    /// Panics if the device has not been bound
+    pub fn nd_channel(&self) -> udi_channel_t {
+        let rv = self.nd_channel.get();
+        assert!(!rv.is_null(), "NSR not bound");
+        rv
+    }
+    /// Description of the bound device
+    pub fn info(&self) -> Option<NicInfo> {
+        self.info.get()
+    }
+    /// Check if the device has been enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+    /// Last link status reported by the device
+    pub fn link_status(&self) -> Option<NicStatus> {
+        self.link.get()
+    }
+    /// Number of free TX CBs
+    pub fn tx_free_count(&self) -> usize {
+        self.n_tx_free.get()
+    }
+    /// Number of submitted TX CBs waiting for [Binding::tx_flush]
+    pub fn tx_pending_count(&self) -> usize {
+        self.n_tx_pending.get()
+    }
+    /// Number of packets sent to the ND
+    pub fn tx_packets(&self) -> u32 {
+        self.tx_packets.get()
+    }
+    /// Number of received packets handled
+    pub fn rx_packets(&self) -> u32 {
+        self.rx_packets.get()
+    }
+}
@@ -1,4 +1,8 @@
 //! SCSI metalanguage definition
+//!
+//! A peripheral driver (e.g. a disk driver) binds to a host bus adapter (HBA) driver, and sends it
+//! commands ([Cdb]) to run on the target. Failed commands are reported with a [ScsiStatus], along
+//! with any sense data returned by the target (decoded with [SenseData]).
 use ::udi_sys::meta_scsi as ffi;
 
 impl_metalanguage!{
@@ -10,7 +14,7 @@ impl_metalanguage!{
         ;
     CBS
         1 => ffi::udi_scsi_bind_cb_t,
-        2 => ffi::udi_scsi_io_cb_t : BUF data_buf,
+        2 => ffi::udi_scsi_io_cb_t : BUF data_buf : INLINE_DATA cdb_ptr,
         3 => ffi::udi_scsi_ctl_cb_t,
         4 => ffi::udi_scsi_event_cb_t : BUF aen_data_buf,
         ;
@@ -20,26 +24,808 @@ impl_metalanguage!{
 pub fn unbind_req(cb: crate::cb::CbHandle<ffi::udi_scsi_bind_cb_t>) {
     unsafe { ffi::udi_scsi_unbind_req(cb.into_raw()) }
 }
-/// Start a new IO operation
+/// Start a new IO operation (populated using [crate::cb::CbHandle::set_request], or allocated with [alloc_io])
 pub fn io_req(cb: crate::cb::CbHandle<ffi::udi_scsi_io_cb_t>) {
     unsafe { ffi::udi_scsi_io_req(cb.into_raw()) }
 }
-/// Make a control request
+/// Make a control request (populated using [crate::cb::CbHandle::set_op], or allocated with [alloc_ctl])
 pub fn ctl_req(cb: crate::cb::CbHandle<ffi::udi_scsi_ctl_cb_t>) {
     unsafe { ffi::udi_scsi_ctl_req(cb.into_raw()) }
 }
-/// Indicate to the peripheral that an event has occurred
-pub fn event_ind(cb: crate::cb::CbHandle<ffi::udi_scsi_event_cb_t>) {
-    unsafe { ffi::udi_scsi_event_ind(cb.into_raw()) }
+/// Indicate to the peripheral that an event has occurred, which responds with [Host::event_res]
+pub fn event_ind(mut cb: crate::cb::CbHandle<ffi::udi_scsi_event_cb_t>, event: ScsiEvent) {
+    unsafe {
+        cb.get_mut().event = event.to_raw();
+        ffi::udi_scsi_event_ind(cb.into_raw())
+    }
+}
+
+/// Layout of the inline CDB data in an IO CB
+const CDB_LAYOUT: &[crate::ffi::udi_layout_t] = &[
+    crate::ffi::layout::UDI_DL_ARRAY, Cdb::MAX_LEN as _,
+        crate::ffi::layout::UDI_DL_UBIT8_T,
+        crate::ffi::layout::UDI_DL_END,
+    crate::ffi::layout::UDI_DL_END,
+];
+
+/// Allocate an IO CB (of type `CbDef`) with space for a CDB of up to [Cdb::MAX_LEN] bytes, populated from `req`
+pub async fn alloc_io<CbDef>(gcb: crate::CbRef<'_, crate::ffi::udi_cb_t>, channel: crate::ffi::udi_channel_t, req: &IoRequest)
+    -> crate::cb::CbHandle<ffi::udi_scsi_io_cb_t>
+where
+    CbDef: crate::cb::CbDefinition<Cb=ffi::udi_scsi_io_cb_t>,
+{
+    // SAFE: `CDB_LAYOUT` describes a CDB of `Cdb::MAX_LEN` bytes
+    let mut cb = unsafe { crate::cb::alloc_dynamic::<CbDef>(gcb, channel, Cdb::MAX_LEN, CDB_LAYOUT) }.await;
+    cb.set_request(req);
+    cb
+}
+/// Allocate a control CB (of type `CbDef`) for `op`
+pub async fn alloc_ctl<CbDef>(gcb: crate::CbRef<'_, crate::ffi::udi_cb_t>, channel: crate::ffi::udi_channel_t, op: CtlOp)
+    -> crate::cb::CbHandle<ffi::udi_scsi_ctl_cb_t>
+where
+    CbDef: crate::cb::CbDefinition<Cb=ffi::udi_scsi_ctl_cb_t>,
+{
+    let mut cb = crate::cb::alloc::<CbDef>(gcb, channel).await;
+    cb.set_op(op);
+    cb
+}
+
+/// A SCSI Command Descriptor Block, of up to [Cdb::MAX_LEN] bytes
+#[derive(Copy,Clone,PartialEq,Eq)]
+pub struct Cdb {
+    len: u8,
+    bytes: [u8; Cdb::MAX_LEN],
+}
+impl Cdb {
+    /// Maximum supported CDB length
+    pub const MAX_LEN: usize = 16;
+
+    /// Construct from raw bytes, returning `None` if empty or longer than [Cdb::MAX_LEN]
+    pub fn new(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() || bytes.len() > Self::MAX_LEN {
+            return None;
+        }
+        let mut rv = Cdb { len: bytes.len() as u8, bytes: [0; Self::MAX_LEN] };
+        rv.bytes[..bytes.len()].copy_from_slice(bytes);
+        Some(rv)
+    }
+    /// Get the CDB bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+    /// Operation code (first byte of the CDB)
+    pub fn opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    /// TEST UNIT READY (6)
+    pub fn test_unit_ready() -> Self {
+        Self::build::<6>(0x00, |_| {})
+    }
+    /// REQUEST SENSE (6), with an allocation length of `alloc_len`
+    pub fn request_sense(alloc_len: u8) -> Self {
+        Self::build::<6>(0x03, |b| b[4] = alloc_len)
+    }
+    /// INQUIRY (6) for the standard inquiry data, with an allocation length of `alloc_len`
+    pub fn inquiry(alloc_len: u16) -> Self {
+        Self::build::<6>(0x12, |b| b[3..5].copy_from_slice(&alloc_len.to_be_bytes()))
+    }
+    /// READ CAPACITY (10)
+    pub fn read_capacity10() -> Self {
+        Self::build::<10>(0x25, |_| {})
+    }
+    /// READ (10) of `count` blocks starting at `lba`
+    pub fn read10(lba: u32, count: u16) -> Self {
+        Self::build::<10>(0x28, |b| Self::rw10(b, lba, count))
+    }
+    /// WRITE (10) of `count` blocks starting at `lba`
+    pub fn write10(lba: u32, count: u16) -> Self {
+        Self::build::<10>(0x2A, |b| Self::rw10(b, lba, count))
+    }
+    /// READ (16) of `count` blocks starting at `lba`
+    pub fn read16(lba: u64, count: u32) -> Self {
+        Self::build::<16>(0x88, |b| Self::rw16(b, lba, count))
+    }
+    /// WRITE (16) of `count` blocks starting at `lba`
+    pub fn write16(lba: u64, count: u32) -> Self {
+        Self::build::<16>(0x8A, |b| Self::rw16(b, lba, count))
+    }
+    /// Read `count` blocks starting at `lba`, using READ (10) if the values fit and READ (16) otherwise
+    pub fn read(lba: u64, count: u32) -> Self {
+        match (u32::try_from(lba), u16::try_from(count)) {
+        (Ok(lba), Ok(count)) => Self::read10(lba, count),
+        _ => Self::read16(lba, count),
+        }
+    }
+    /// Write `count` blocks starting at `lba`, using WRITE (10) if the values fit and WRITE (16) otherwise
+    pub fn write(lba: u64, count: u32) -> Self {
+        match (u32::try_from(lba), u16::try_from(count)) {
+        (Ok(lba), Ok(count)) => Self::write10(lba, count),
+        _ => Self::write16(lba, count),
+        }
+    }
+
+    fn build<const N: usize>(opcode: u8, f: impl FnOnce(&mut [u8; N])) -> Self {
+        let mut b = [0; N];
+        b[0] = opcode;
+        f(&mut b);
+        Self::new(&b).unwrap()
+    }
+    fn rw10(b: &mut [u8; 10], lba: u32, count: u16) {
+        b[2..6].copy_from_slice(&lba.to_be_bytes());
+        b[7..9].copy_from_slice(&count.to_be_bytes());
+    }
+    fn rw16(b: &mut [u8; 16], lba: u64, count: u32) {
+        b[2..10].copy_from_slice(&lba.to_be_bytes());
+        b[10..14].copy_from_slice(&count.to_be_bytes());
+    }
+}
+impl ::core::fmt::Debug for Cdb {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.write_str("Cdb(")?;
+        for (i,b) in self.as_bytes().iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        f.write_str(")")
+    }
+}
+
+def_flags!{
+    /// Flags passed when binding to a host (`bind_flags`)
+    BindFlags: u16 {
+        /// No other peripheral driver may bind to the same LUN (`UDI_SCSI_BIND_EXCLUSIVE`)
+        EXCLUSIVE = ffi::UDI_SCSI_BIND_EXCLUSIVE,
+        /// Exclusive binding, that may be broken by a request from another driver (`UDI_SCSI_TEMP_BIND_EXCLUSIVE`)
+        TEMP_EXCLUSIVE = ffi::UDI_SCSI_TEMP_BIND_EXCLUSIVE,
+    }
+}
+def_flags!{
+    /// Set of events a peripheral wants to be told about (`udi_scsi_bind_cb_t.events`)
+    ScsiEvents: u16 {
+        /// Asynchronous event notifications (`UDI_SCSI_EVENT_AEN`)
+        AEN = ffi::UDI_SCSI_EVENT_AEN,
+        /// Target reset (`UDI_SCSI_EVENT_TGT_RESET`)
+        TGT_RESET = ffi::UDI_SCSI_EVENT_TGT_RESET,
+        /// Bus reset (`UDI_SCSI_EVENT_BUS_RESET`)
+        BUS_RESET = ffi::UDI_SCSI_EVENT_BUS_RESET,
+        /// Unsolicited reselection by the target (`UDI_SCSI_EVENT_UNSOLICITED_RESELECT`)
+        UNSOLICITED_RESELECT = ffi::UDI_SCSI_EVENT_UNSOLICITED_RESELECT,
+    }
+}
+def_flags!{
+    /// IO request flags (`udi_scsi_io_cb_t.flags`)
+    IoFlags: u16 {
+        /// Data is transferred from the target into `data_buf` (`UDI_SCSI_DATA_IN`)
+        DATA_IN = ffi::UDI_SCSI_DATA_IN,
+        /// Data is transferred from `data_buf` to the target (`UDI_SCSI_DATA_OUT`)
+        DATA_OUT = ffi::UDI_SCSI_DATA_OUT,
+        /// The target should not disconnect during the command (`UDI_SCSI_NO_DISCONNECT`)
+        NO_DISCONNECT = ffi::UDI_SCSI_NO_DISCONNECT,
+    }
 }
 
 /// Options for binding a peripheral to a host
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
 pub struct BindOpts
 {
-    bind_flags: ::udi_sys::udi_ubit16_t,
-    queue_depth: ::udi_sys::udi_ubit16_t,
-    max_sense_len: ::udi_sys::udi_ubit16_t,
-    aen_buf_size: ::udi_sys::udi_ubit16_t
+    /// Exclusivity of the binding
+    pub flags: BindFlags,
+    /// Maximum number of IO requests the peripheral will have outstanding at once
+    pub queue_depth: u16,
+    /// Maximum length of sense data returned with a failed IO (zero disables automatic REQUEST SENSE)
+    pub max_sense_len: u16,
+    /// Size of the buffer for asynchronous event notification data
+    pub aen_buf_size: u16,
+    /// Events the peripheral wants to be indicated (see [Peripheral::event_ind])
+    pub events: ScsiEvents,
+}
+
+/// SCSI task attribute (`udi_scsi_io_cb_t.attribute`) for tagged queueing
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub enum TaskAttribute {
+    /// Simple queue tag, the host/target may reorder the task (`UDI_SCSI_SIMPLE_TASK`)
+    #[default]
+    Simple,
+    /// Ordered queue tag, the task runs after all previous tasks (`UDI_SCSI_ORDERED_TASK`)
+    Ordered,
+    /// Head of queue tag, the task runs before any queued tasks (`UDI_SCSI_HEAD_OF_Q_TASK`)
+    HeadOfQueue,
+    /// Auto contingent allegiance task (`UDI_SCSI_ACA_TASK`)
+    Aca,
+    /// Untagged, no other task may be outstanding (`UDI_SCSI_UNTAGGED_TASK`)
+    Untagged,
+}
+impl TaskAttribute {
+    /// Decode a raw `attribute` value
+    pub fn from_raw(v: u8) -> Option<Self> {
+        Some(match v {
+        ffi::UDI_SCSI_SIMPLE_TASK => TaskAttribute::Simple,
+        ffi::UDI_SCSI_ORDERED_TASK => TaskAttribute::Ordered,
+        ffi::UDI_SCSI_HEAD_OF_Q_TASK => TaskAttribute::HeadOfQueue,
+        ffi::UDI_SCSI_ACA_TASK => TaskAttribute::Aca,
+        ffi::UDI_SCSI_UNTAGGED_TASK => TaskAttribute::Untagged,
+        _ => return None,
+        })
+    }
+    /// Get the raw `attribute` value
+    pub fn to_raw(self) -> u8 {
+        match self {
+        TaskAttribute::Simple => ffi::UDI_SCSI_SIMPLE_TASK,
+        TaskAttribute::Ordered => ffi::UDI_SCSI_ORDERED_TASK,
+        TaskAttribute::HeadOfQueue => ffi::UDI_SCSI_HEAD_OF_Q_TASK,
+        TaskAttribute::Aca => ffi::UDI_SCSI_ACA_TASK,
+        TaskAttribute::Untagged => ffi::UDI_SCSI_UNTAGGED_TASK,
+        }
+    }
+}
+
+/// Description of an IO request (the fields of a [ffi::udi_scsi_io_cb_t] other than the data buffer)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct IoRequest {
+    /// Command to send to the target
+    pub cdb: Cdb,
+    /// Data direction and other flags
+    pub flags: IoFlags,
+    /// Task attribute used for tagged queueing
+    pub attribute: TaskAttribute,
+    /// Timeout in milliseconds (zero for no timeout)
+    pub timeout: u32,
+}
+impl IoRequest {
+    /// A request for `cdb` with no data transfer, using a simple task and no timeout
+    pub fn new(cdb: Cdb) -> Self {
+        IoRequest { cdb, flags: IoFlags::NONE, attribute: TaskAttribute::Simple, timeout: 0 }
+    }
+    /// Transfer data from the target (into `data_buf`)
+    pub fn data_in(self) -> Self {
+        IoRequest { flags: self.flags | IoFlags::DATA_IN, ..self }
+    }
+    /// Transfer data to the target (from `data_buf`)
+    pub fn data_out(self) -> Self {
+        IoRequest { flags: self.flags | IoFlags::DATA_OUT, ..self }
+    }
+    /// Set the task attribute
+    pub fn with_attribute(self, attribute: TaskAttribute) -> Self {
+        IoRequest { attribute, ..self }
+    }
+    /// Set the timeout (in milliseconds)
+    pub fn with_timeout(self, timeout: u32) -> Self {
+        IoRequest { timeout, ..self }
+    }
+
+    /// Decode the request from a raw CB
+    ///
+    /// # Safety
+    /// `cb.cdb_ptr` must be valid for `cb.cdb_len` bytes
+    pub unsafe fn from_cb(cb: &ffi::udi_scsi_io_cb_t) -> crate::Result<Self> {
+        let cdb = if cb.cdb_ptr.is_null() {
+            None
+        }
+        else {
+            // SAFE: Caller ensures validity
+            Cdb::new(unsafe { ::core::slice::from_raw_parts(cb.cdb_ptr, cb.cdb_len as usize) })
+        };
+        match (cdb, TaskAttribute::from_raw(cb.attribute)) {
+        (Some(cdb), Some(attribute)) => Ok(IoRequest {
+            cdb,
+            flags: IoFlags::from_raw(cb.flags),
+            attribute,
+            timeout: cb.timeout,
+            }),
+        _ => not_understood(),
+        }
+    }
+    /// Write the request into a raw CB
+    ///
+    /// # Safety
+    /// `cb.cdb_ptr` must be valid for writing [Cdb::MAX_LEN] bytes
+    pub unsafe fn write_cb(&self, cb: &mut ffi::udi_scsi_io_cb_t) {
+        let cdb = self.cdb.as_bytes();
+        // SAFE: Caller ensures validity
+        unsafe { ::core::ptr::copy_nonoverlapping(cdb.as_ptr(), cb.cdb_ptr, cdb.len()); }
+        cb.cdb_len = cdb.len() as u8;
+        cb.flags = self.flags.to_raw();
+        cb.attribute = self.attribute.to_raw();
+        cb.timeout = self.timeout;
+    }
+}
+
+fn not_understood<T>() -> crate::Result<T> {
+    Err(crate::Error::from_status(crate::ffi::UDI_STAT_NOT_UNDERSTOOD as _).unwrap_err())
+}
+
+/// Overall status of an IO request (`udi_scsi_status_t.req_status`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ReqStatus {
+    /// The request completed (`UDI_OK`)
+    Ok,
+    /// The target has an ACA condition pending (`UDI_SCSI_STAT_ACA_PENDING`)
+    AcaPending,
+    /// The target returned a status byte other than GOOD (`UDI_SCSI_STAT_NONZERO_STATUS_BYTE`)
+    NonzeroStatusByte,
+    /// Aborted due to a bus reset by the host (`UDI_SCSI_STAT_ABORTED_HD_BUS_RESET`)
+    AbortedHdBusReset,
+    /// Aborted due to a bus reset by another device (`UDI_SCSI_STAT_ABORTED_RMT_BUS_RESET`)
+    AbortedRmtBusReset,
+    /// Aborted due to a requested bus reset (`UDI_SCSI_STAT_ABORTED_REQ_BUS_RESET`)
+    AbortedReqBusReset,
+    /// Aborted due to a requested target reset (`UDI_SCSI_STAT_ABORTED_REQ_TGT_RESET`)
+    AbortedReqTgtReset,
+    /// The link to the target failed (`UDI_SCSI_STAT_LINK_FAILURE`)
+    LinkFailure,
+    /// The target did not respond to selection (`UDI_SCSI_STAT_SELECTION_TIMEOUT`)
+    SelectionTimeout,
+    /// The target unexpectedly released the bus (`UDI_SCSI_STAT_UNEXPECTED_BUS_FREE`)
+    UnexpectedBusFree,
+    /// The target went to an unexpected bus phase (`UDI_SCSI_STAT_DEVICE_PHASE_ERROR`)
+    DevicePhaseError,
+    /// Any other (non metalanguage-specific) error, e.g. `UDI_STAT_TIMEOUT`
+    Other(crate::Error),
+}
+impl ReqStatus {
+    /// Decode a raw `req_status` value
+    pub fn from_raw(v: crate::ffi::udi_status_t) -> Self {
+        let e = match crate::Error::from_status(v) {
+            Ok(()) => return ReqStatus::Ok,
+            Err(e) => e,
+            };
+        match v {
+        ffi::UDI_SCSI_STAT_ACA_PENDING => ReqStatus::AcaPending,
+        ffi::UDI_SCSI_STAT_NONZERO_STATUS_BYTE => ReqStatus::NonzeroStatusByte,
+        ffi::UDI_SCSI_STAT_ABORTED_HD_BUS_RESET => ReqStatus::AbortedHdBusReset,
+        ffi::UDI_SCSI_STAT_ABORTED_RMT_BUS_RESET => ReqStatus::AbortedRmtBusReset,
+        ffi::UDI_SCSI_STAT_ABORTED_REQ_BUS_RESET => ReqStatus::AbortedReqBusReset,
+        ffi::UDI_SCSI_STAT_ABORTED_REQ_TGT_RESET => ReqStatus::AbortedReqTgtReset,
+        ffi::UDI_SCSI_STAT_LINK_FAILURE => ReqStatus::LinkFailure,
+        ffi::UDI_SCSI_STAT_SELECTION_TIMEOUT => ReqStatus::SelectionTimeout,
+        ffi::UDI_SCSI_STAT_UNEXPECTED_BUS_FREE => ReqStatus::UnexpectedBusFree,
+        ffi::UDI_SCSI_STAT_DEVICE_PHASE_ERROR => ReqStatus::DevicePhaseError,
+        _ => ReqStatus::Other(e),
+        }
+    }
+    /// Get the raw `req_status` value
+    pub fn to_raw(self) -> crate::ffi::udi_status_t {
+        match self {
+        ReqStatus::Ok => crate::ffi::UDI_OK as _,
+        ReqStatus::AcaPending => ffi::UDI_SCSI_STAT_ACA_PENDING,
+        ReqStatus::NonzeroStatusByte => ffi::UDI_SCSI_STAT_NONZERO_STATUS_BYTE,
+        ReqStatus::AbortedHdBusReset => ffi::UDI_SCSI_STAT_ABORTED_HD_BUS_RESET,
+        ReqStatus::AbortedRmtBusReset => ffi::UDI_SCSI_STAT_ABORTED_RMT_BUS_RESET,
+        ReqStatus::AbortedReqBusReset => ffi::UDI_SCSI_STAT_ABORTED_REQ_BUS_RESET,
+        ReqStatus::AbortedReqTgtReset => ffi::UDI_SCSI_STAT_ABORTED_REQ_TGT_RESET,
+        ReqStatus::LinkFailure => ffi::UDI_SCSI_STAT_LINK_FAILURE,
+        ReqStatus::SelectionTimeout => ffi::UDI_SCSI_STAT_SELECTION_TIMEOUT,
+        ReqStatus::UnexpectedBusFree => ffi::UDI_SCSI_STAT_UNEXPECTED_BUS_FREE,
+        ReqStatus::DevicePhaseError => ffi::UDI_SCSI_STAT_DEVICE_PHASE_ERROR,
+        ReqStatus::Other(e) => e.into_inner(),
+        }
+    }
+}
+
+/// SCSI status byte returned by a target
+#[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+pub enum StatusByte {
+    /// GOOD
+    #[default]
+    Good,
+    /// CHECK CONDITION - sense data is available
+    CheckCondition,
+    /// CONDITION MET
+    ConditionMet,
+    /// BUSY
+    Busy,
+    /// RESERVATION CONFLICT
+    ReservationConflict,
+    /// TASK SET FULL
+    TaskSetFull,
+    /// ACA ACTIVE
+    AcaActive,
+    /// TASK ABORTED
+    TaskAborted,
+    /// Any other (reserved or obsolete) value
+    Other(u8),
+}
+impl StatusByte {
+    /// Decode a raw status byte
+    pub fn from_raw(v: u8) -> Self {
+        match v {
+        0x00 => StatusByte::Good,
+        0x02 => StatusByte::CheckCondition,
+        0x04 => StatusByte::ConditionMet,
+        0x08 => StatusByte::Busy,
+        0x18 => StatusByte::ReservationConflict,
+        0x28 => StatusByte::TaskSetFull,
+        0x30 => StatusByte::AcaActive,
+        0x40 => StatusByte::TaskAborted,
+        _ => StatusByte::Other(v),
+        }
+    }
+    /// Get the raw status byte
+    pub fn to_raw(self) -> u8 {
+        match self {
+        StatusByte::Good => 0x00,
+        StatusByte::CheckCondition => 0x02,
+        StatusByte::ConditionMet => 0x04,
+        StatusByte::Busy => 0x08,
+        StatusByte::ReservationConflict => 0x18,
+        StatusByte::TaskSetFull => 0x28,
+        StatusByte::AcaActive => 0x30,
+        StatusByte::TaskAborted => 0x40,
+        StatusByte::Other(v) => v,
+        }
+    }
+}
+
+/// Status of a failed IO request (`udi_scsi_status_t`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct ScsiStatus {
+    /// Overall status of the request
+    pub req_status: ReqStatus,
+    /// Status byte returned by the target (see [ReqStatus::NonzeroStatusByte])
+    pub scsi_status: StatusByte,
+    /// Status byte of the automatic REQUEST SENSE, valid if sense data was returned
+    pub sense_status: StatusByte,
+}
+impl ScsiStatus {
+    /// The request itself failed (the target did not return a status)
+    pub fn failed(req_status: ReqStatus) -> Self {
+        ScsiStatus { req_status, scsi_status: StatusByte::Good, sense_status: StatusByte::Good }
+    }
+    /// The target completed the command with a non-GOOD status byte
+    pub fn target_status(scsi_status: StatusByte) -> Self {
+        ScsiStatus { req_status: ReqStatus::NonzeroStatusByte, scsi_status, sense_status: StatusByte::Good }
+    }
+    /// Check if the target returned CHECK CONDITION (so the sense data is valid)
+    pub fn is_check_condition(&self) -> bool {
+        self.req_status == ReqStatus::NonzeroStatusByte && self.scsi_status == StatusByte::CheckCondition
+    }
+    /// Decode the raw structure
+    pub fn from_raw(raw: &ffi::udi_scsi_status_t) -> Self {
+        ScsiStatus {
+            req_status: ReqStatus::from_raw(raw.req_status),
+            scsi_status: StatusByte::from_raw(raw.scsi_status),
+            sense_status: StatusByte::from_raw(raw.sense_status),
+        }
+    }
+    /// Encode into the raw structure
+    pub fn to_raw(&self) -> ffi::udi_scsi_status_t {
+        ffi::udi_scsi_status_t {
+            req_status: self.req_status.to_raw(),
+            scsi_status: self.scsi_status.to_raw(),
+            sense_status: self.sense_status.to_raw(),
+        }
+    }
+}
+impl From<crate::Error> for ScsiStatus {
+    fn from(e: crate::Error) -> Self {
+        ScsiStatus::failed(ReqStatus::from_raw(e.into_inner()))
+    }
+}
+
+/// SCSI sense key
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum SenseKey {
+    /// NO SENSE
+    NoSense,
+    /// RECOVERED ERROR
+    RecoveredError,
+    /// NOT READY
+    NotReady,
+    /// MEDIUM ERROR
+    MediumError,
+    /// HARDWARE ERROR
+    HardwareError,
+    /// ILLEGAL REQUEST
+    IllegalRequest,
+    /// UNIT ATTENTION
+    UnitAttention,
+    /// DATA PROTECT
+    DataProtect,
+    /// BLANK CHECK
+    BlankCheck,
+    /// VENDOR SPECIFIC
+    VendorSpecific,
+    /// COPY ABORTED
+    CopyAborted,
+    /// ABORTED COMMAND
+    AbortedCommand,
+    /// VOLUME OVERFLOW
+    VolumeOverflow,
+    /// MISCOMPARE
+    Miscompare,
+    /// COMPLETED
+    Completed,
+    /// Obsolete/reserved value
+    Reserved(u8),
+}
+impl SenseKey {
+    /// Decode a sense key (only the low four bits are used)
+    pub fn from_raw(v: u8) -> Self {
+        match v & 0xF {
+        0x0 => SenseKey::NoSense,
+        0x1 => SenseKey::RecoveredError,
+        0x2 => SenseKey::NotReady,
+        0x3 => SenseKey::MediumError,
+        0x4 => SenseKey::HardwareError,
+        0x5 => SenseKey::IllegalRequest,
+        0x6 => SenseKey::UnitAttention,
+        0x7 => SenseKey::DataProtect,
+        0x8 => SenseKey::BlankCheck,
+        0x9 => SenseKey::VendorSpecific,
+        0xA => SenseKey::CopyAborted,
+        0xB => SenseKey::AbortedCommand,
+        0xD => SenseKey::VolumeOverflow,
+        0xE => SenseKey::Miscompare,
+        0xF => SenseKey::Completed,
+        v => SenseKey::Reserved(v),
+        }
+    }
+}
+
+/// Decoded sense data, from either the fixed or descriptor format
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct SenseData {
+    /// The error is for the reported command (otherwise it is a deferred error)
+    pub current: bool,
+    /// The data was in the descriptor format
+    pub descriptor_format: bool,
+    /// Sense key
+    pub sense_key: SenseKey,
+    /// Additional sense code
+    pub asc: u8,
+    /// Additional sense code qualifier
+    pub ascq: u8,
+    /// Contents of the INFORMATION field if valid (e.g. the failing LBA for a [SenseKey::MediumError])
+    pub information: Option<u64>,
+}
+impl SenseData {
+    /// Maximum length of sense data read by [SenseData::from_buf]
+    pub const MAX_LEN: usize = 252;
+
+    /// Parse sense data, returning `None` if the response code is not recognised or the data is truncated
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let code = *data.first()? & 0x7F;
+        // The additional sense length (byte 7) limits the data in both formats
+        let len = match data.get(7) {
+            Some(&l) => data.len().min(8 + l as usize),
+            None => data.len(),
+            };
+        let data = &data[..len];
+        match code {
+        0x70 | 0x71 => {
+            if data.len() < 3 {
+                return None;
+            }
+            let information = if data[0] & 0x80 != 0 && data.len() >= 7 {
+                    Some(u32::from_be_bytes([data[3], data[4], data[5], data[6]]) as u64)
+                }
+                else {
+                    None
+                };
+            Some(SenseData {
+                current: code == 0x70,
+                descriptor_format: false,
+                sense_key: SenseKey::from_raw(data[2]),
+                asc: data.get(12).copied().unwrap_or(0),
+                ascq: data.get(13).copied().unwrap_or(0),
+                information,
+            })
+            },
+        0x72 | 0x73 => {
+            if data.len() < 4 {
+                return None;
+            }
+            let mut information = None;
+            let mut descs = data.get(8..).unwrap_or(&[]);
+            while descs.len() >= 2 {
+                let (d, rest) = descs.split_at( (2 + descs[1] as usize).min(descs.len()) );
+                // Information descriptor, with the VALID bit set
+                if d[0] == 0x00 && d.len() == 12 && d[2] & 0x80 != 0 {
+                    information = Some(u64::from_be_bytes(d[4..12].try_into().unwrap()));
+                }
+                descs = rest;
+            }
+            Some(SenseData {
+                current: code == 0x72,
+                descriptor_format: true,
+                sense_key: SenseKey::from_raw(data[1]),
+                asc: data[2],
+                ascq: data[3],
+                information,
+            })
+            },
+        _ => None,
+        }
+    }
+    /// Parse the sense data from a buffer (e.g. from [Peripheral::io_nak])
+    pub fn from_buf(buf: &crate::buf::Handle) -> Option<Self> {
+        let mut data = [0; Self::MAX_LEN];
+        let len = buf.len().min(Self::MAX_LEN);
+        buf.read(0, &mut data[..len]);
+        Self::parse(&data[..len])
+    }
+}
+
+/// A control operation requested by a peripheral (`udi_scsi_ctl_cb_t.ctrl_func`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum CtlOp {
+    /// Abort all tasks from this peripheral (`UDI_SCSI_CTL_ABORT_TASK_SET`)
+    AbortTaskSet,
+    /// Abort all tasks on the LUN (`UDI_SCSI_CTL_CLEAR_TASK_SET`)
+    ClearTaskSet,
+    /// Reset the LUN (`UDI_SCSI_CTL_LUN_RESET`)
+    LunReset,
+    /// Reset the target (`UDI_SCSI_CTL_TGT_RESET`)
+    TargetReset,
+    /// Reset the bus (`UDI_SCSI_CTL_BUS_RESET`)
+    BusReset,
+    /// Clear an auto contingent allegiance condition (`UDI_SCSI_CTL_CLEAR_ACA`)
+    ClearAca,
+    /// Change the maximum number of outstanding requests (`UDI_SCSI_CTL_SET_QUEUE_DEPTH`)
+    SetQueueDepth(u16),
+}
+impl CtlOp {
+    /// Decode from the `ctrl_func` and `queue_depth` fields
+    pub fn from_raw(ctrl_func: u8, queue_depth: u16) -> Option<Self> {
+        Some(match ctrl_func {
+        ffi::UDI_SCSI_CTL_ABORT_TASK_SET => CtlOp::AbortTaskSet,
+        ffi::UDI_SCSI_CTL_CLEAR_TASK_SET => CtlOp::ClearTaskSet,
+        ffi::UDI_SCSI_CTL_LUN_RESET => CtlOp::LunReset,
+        ffi::UDI_SCSI_CTL_TGT_RESET => CtlOp::TargetReset,
+        ffi::UDI_SCSI_CTL_BUS_RESET => CtlOp::BusReset,
+        ffi::UDI_SCSI_CTL_CLEAR_ACA => CtlOp::ClearAca,
+        ffi::UDI_SCSI_CTL_SET_QUEUE_DEPTH => CtlOp::SetQueueDepth(queue_depth),
+        _ => return None,
+        })
+    }
+    /// Get the `ctrl_func` and `queue_depth` values
+    pub fn to_raw(self) -> (u8, u16) {
+        match self {
+        CtlOp::AbortTaskSet => (ffi::UDI_SCSI_CTL_ABORT_TASK_SET, 0),
+        CtlOp::ClearTaskSet => (ffi::UDI_SCSI_CTL_CLEAR_TASK_SET, 0),
+        CtlOp::LunReset => (ffi::UDI_SCSI_CTL_LUN_RESET, 0),
+        CtlOp::TargetReset => (ffi::UDI_SCSI_CTL_TGT_RESET, 0),
+        CtlOp::BusReset => (ffi::UDI_SCSI_CTL_BUS_RESET, 0),
+        CtlOp::ClearAca => (ffi::UDI_SCSI_CTL_CLEAR_ACA, 0),
+        CtlOp::SetQueueDepth(d) => (ffi::UDI_SCSI_CTL_SET_QUEUE_DEPTH, d),
+        }
+    }
+}
+
+/// An event indicated by a host (`udi_scsi_event_cb_t.event`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ScsiEvent {
+    /// Asynchronous event notification, with the data in `aen_data_buf`
+    Aen,
+    /// The target has been reset
+    TargetReset,
+    /// The bus has been reset
+    BusReset,
+    /// The target reselected the host without an outstanding request
+    UnsolicitedReselect,
+}
+impl ScsiEvent {
+    /// Decode a raw `event` value
+    pub fn from_raw(v: u8) -> Option<Self> {
+        Some(match v as u16 {
+        ffi::UDI_SCSI_EVENT_AEN => ScsiEvent::Aen,
+        ffi::UDI_SCSI_EVENT_TGT_RESET => ScsiEvent::TargetReset,
+        ffi::UDI_SCSI_EVENT_BUS_RESET => ScsiEvent::BusReset,
+        ffi::UDI_SCSI_EVENT_UNSOLICITED_RESELECT => ScsiEvent::UnsolicitedReselect,
+        _ => return None,
+        })
+    }
+    /// Get the raw `event` value
+    pub fn to_raw(self) -> u8 {
+        self.mask().to_raw() as u8
+    }
+    /// Get the flag for this event in [ScsiEvents]
+    pub fn mask(self) -> ScsiEvents {
+        match self {
+        ScsiEvent::Aen => ScsiEvents::AEN,
+        ScsiEvent::TargetReset => ScsiEvents::TGT_RESET,
+        ScsiEvent::BusReset => ScsiEvents::BUS_RESET,
+        ScsiEvent::UnsolicitedReselect => ScsiEvents::UNSOLICITED_RESELECT,
+        }
+    }
+}
+
+/// Failure result from [Host::io_req], passed to [Peripheral::io_nak]
+pub struct IoNak {
+    /// Status of the request
+    pub status: ScsiStatus,
+    /// Sense data (empty if there is none)
+    pub sense: crate::buf::Handle,
+}
+impl IoNak {
+    /// A failure without sense data
+    pub fn new(status: ScsiStatus) -> Self {
+        IoNak { status, sense: Default::default() }
+    }
+    /// CHECK CONDITION, with the sense data
+    pub fn check_condition(sense: crate::buf::Handle) -> Self {
+        IoNak {
+            status: ScsiStatus::target_status(StatusByte::CheckCondition),
+            sense,
+        }
+    }
+}
+impl From<crate::Error> for IoNak {
+    fn from(e: crate::Error) -> Self {
+        IoNak::new(e.into())
+    }
+}
+
+impl crate::cb::CbRef<'_, ffi::udi_scsi_bind_cb_t>
+{
+    /// Events that the peripheral wants to be indicated
+    pub fn events(&self) -> ScsiEvents {
+        ScsiEvents::from_raw(self.events)
+    }
+}
+impl crate::cb::CbRef<'_, ffi::udi_scsi_io_cb_t>
+{
+    /// Get the data buffer
+    pub fn data_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.data_buf) }
+    }
+    /// Decode the request, failing with `UDI_STAT_NOT_UNDERSTOOD` if the CDB or task attribute is invalid
+    pub fn request(&self) -> crate::Result<IoRequest> {
+        // SAFE: The CDB pointer and length are valid in a CB from the environment
+        unsafe { IoRequest::from_cb(self) }
+    }
+}
+impl crate::cb::CbHandle<ffi::udi_scsi_io_cb_t>
+{
+    /// Get a mutable handle to the data buffer
+    pub fn data_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().data_buf) }
+    }
+    /// Populate the CDB, flags, task attribute, and timeout
+    ///
+    /// Panics if the CB has no inline CDB data (see [alloc_io])
+    pub fn set_request(&mut self, req: &IoRequest) {
+        assert!(!self.cdb_ptr.is_null(), "SCSI IO CB allocated without CDB space");
+        // SAFE: The inline data is at least `Cdb::MAX_LEN` (assuming it was allocated with `CDB_LAYOUT`)
+        unsafe { req.write_cb(self.get_mut()) }
+    }
+}
+impl crate::cb::CbRef<'_, ffi::udi_scsi_ctl_cb_t>
+{
+    /// Get the requested operation
+    pub fn op(&self) -> Option<CtlOp> {
+        CtlOp::from_raw(self.ctrl_func, self.queue_depth)
+    }
+}
+impl crate::cb::CbHandle<ffi::udi_scsi_ctl_cb_t>
+{
+    /// Set the operation
+    pub fn set_op(&mut self, op: CtlOp) {
+        let (ctrl_func, queue_depth) = op.to_raw();
+        // SAFE: Just setting integer fields
+        unsafe {
+            let cb = self.get_mut();
+            cb.ctrl_func = ctrl_func;
+            cb.queue_depth = queue_depth;
+        }
+    }
+}
+impl crate::cb::CbRef<'_, ffi::udi_scsi_event_cb_t>
+{
+    /// Get the event, if known
+    pub fn event(&self) -> Option<ScsiEvent> {
+        ScsiEvent::from_raw(self.event)
+    }
+    /// Asynchronous event notification data (for [ScsiEvent::Aen])
+    pub fn aen_data_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.aen_data_buf) }
+    }
 }
 
 /// Trait to be implemented by SCSI peripheral drivers
@@ -64,8 +850,8 @@ pub trait Peripheral: 'static + crate::imc::ChannelInit + crate::async_trickery:
         fn io_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_io_cb_t>)->() as Future_io_ack
     );
     async_method!(
-        /// IO has failed, includes the status and SCSI sense value
-        fn io_nak(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_io_cb_t>, res: ffi::udi_scsi_status_t, sense: crate::buf::Handle)->()
+        /// IO has failed, includes the status and the sense data (decode with [SenseData::from_buf])
+        fn io_nak(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_io_cb_t>, res: ScsiStatus, sense: crate::buf::Handle)->()
         as Future_io_nak
     );
     /// Release the CB used for an IO request
@@ -78,8 +864,8 @@ pub trait Peripheral: 'static + crate::imc::ChannelInit + crate::async_trickery:
     /// Release the CB used for an IO control
     fn ctl_ret(&self, cb: crate::cb::CbHandle<ffi::udi_scsi_ctl_cb_t>) { let _ = cb; }
     async_method!(
-        /// Handle an event
-        fn event_ind(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_event_cb_t>)->()
+        /// Handle an event, the host is informed once this completes
+        fn event_ind(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_event_cb_t>, event: ScsiEvent)->()
         as Future_event_ind
     );
 }
@@ -87,7 +873,8 @@ pub trait Peripheral: 'static + crate::imc::ChannelInit + crate::async_trickery:
 pub trait Host: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
 {
     async_method!(
-        /// Handle an incoming binding request with a peripheral device driver
+        /// Handle an incoming binding request with a peripheral device driver, returns the timeout increase (in
+        /// milliseconds) the peripheral should add to its requests
         fn bind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_bind_cb_t>, opts: BindOpts)->crate::Result<u32>
         as Future_bind_ack
     );
@@ -99,17 +886,20 @@ pub trait Host: 'static + crate::imc::ChannelInit + crate::async_trickery::CbCon
     async_method!(
         /// Handle an incoming IO request from the peripheral device driver
         ///
-        /// NOTE: The buffer should be returned if the result is a NAK
-        fn io_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_io_cb_t>)->(ffi::udi_scsi_status_t,crate::buf::Handle)
+        /// Requests with an invalid CDB or task attribute are failed with `UDI_STAT_NOT_UNDERSTOOD` without
+        /// calling this.
+        fn io_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_io_cb_t>, req: IoRequest)->Result<(),IoNak>
         as Future_io_req
     );
     async_method!(
         /// Handle an incoming control request from the peripheral device driver
-        fn ctl_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_ctl_cb_t>)->crate::Result<()>
+        ///
+        /// Unknown operations are failed with `UDI_STAT_NOT_UNDERSTOOD` without calling this.
+        fn ctl_req(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_ctl_cb_t>, op: CtlOp)->crate::Result<()>
         as Future_ctl_ack
     );
     async_method!(
-        /// Called when the peripheral device driver has handled an event
+        /// Called when the peripheral device driver has handled an event (see [event_ind])
         fn event_res(&'s self, cb: crate::cb::CbRef<'s, ffi::udi_scsi_event_cb_t>)->()
         as Future_event_res
     );
@@ -127,14 +917,15 @@ where
         unsafe {
             let cb = params.parent_bound.bind_cb as *mut ffi::udi_scsi_bind_cb_t;
             let opts = self.bind_opts();
-            ffi::udi_scsi_bind_req(cb, opts.bind_flags, opts.queue_depth, opts.max_sense_len, opts.aen_buf_size);
+            (*cb).events = opts.events.to_raw();
+            ffi::udi_scsi_bind_req(cb, opts.flags.to_raw(), opts.queue_depth, opts.max_sense_len, opts.aen_buf_size);
         }
     }
 }
 future_wrapper!(bind_ack_op => <T as Peripheral>(
     cb: *mut ffi::udi_scsi_bind_cb_t,
-    status: ::udi_sys::udi_status_t,
-    hd_timeout_increase: ::udi_sys::udi_ubit32_t
+    hd_timeout_increase: ::udi_sys::udi_ubit32_t,
+    status: ::udi_sys::udi_status_t
 ) val @ {
     let hd_timeout_increase = crate::Error::from_status(status)
         .map(|()| hd_timeout_increase)
@@ -162,7 +953,7 @@ future_wrapper!(io_nak_op => <T as Peripheral>(
     status: ffi::udi_scsi_status_t,
     buf: *mut ::udi_sys::udi_buf_t
 ) val @ {
-    val.io_nak(cb, status, unsafe { crate::buf::Handle::from_raw(buf) })
+    val.io_nak(cb, ScsiStatus::from_raw(&status), unsafe { crate::buf::Handle::from_raw(buf) })
 } finally( () ) {
     val.io_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
 });
@@ -177,7 +968,13 @@ future_wrapper!(ctl_ack_op => <T as Peripheral>(
 future_wrapper!(event_ind_op => <T as Peripheral>(
     cb: *mut ffi::udi_scsi_event_cb_t
 ) val @ {
-    val.event_ind(cb)
+    // Unknown events are ignored
+    let event = cb.event();
+    async move {
+        if let Some(event) = event {
+            val.event_ind(cb, event).await
+        }
+    }
 } finally( () ) {
     unsafe { ffi::udi_scsi_event_res(cb) }
 });
@@ -212,7 +1009,14 @@ future_wrapper!(bind_req_op => <T as Host>(
     max_sense_len: ::udi_sys::udi_ubit16_t,
     aen_buf_size: ::udi_sys::udi_ubit16_t
 ) val @ {
-    val.bind_req(cb, BindOpts { bind_flags, queue_depth, max_sense_len, aen_buf_size })
+    let opts = BindOpts {
+        flags: BindFlags::from_raw(bind_flags),
+        queue_depth,
+        max_sense_len,
+        aen_buf_size,
+        events: cb.events(),
+    };
+    val.bind_req(cb, opts)
 } finally( res ) {
     unsafe { ffi::udi_scsi_bind_ack(cb, res.unwrap_or(0), crate::Error::to_status(res.map(|_| ()))) }
 });
@@ -226,19 +1030,29 @@ future_wrapper!(unbind_req_op => <T as Host>(
 future_wrapper!(io_req_op => <T as Host>(
     cb: *mut ffi::udi_scsi_io_cb_t
 ) val @ {
-    val.io_req(cb)
-} finally( (status, handle) ) {
-    if handle.len() > 0 || status.req_status != ::udi_sys::UDI_OK as _ {
-        unsafe { ffi::udi_scsi_io_nak(cb, status, handle.into_raw()) }
+    let req = cb.request();
+    async move {
+        match req {
+        Ok(req) => val.io_req(cb, req).await,
+        Err(e) => Err(IoNak::from(e)),
+        }
     }
-    else {
-        unsafe { ffi::udi_scsi_io_ack(cb) }
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::udi_scsi_io_ack(cb) },
+    Err(nak) => unsafe { ffi::udi_scsi_io_nak(cb, nak.status.to_raw(), nak.sense.into_raw()) },
     }
 });
 future_wrapper!(ctl_req_op => <T as Host>(
     cb: *mut ffi::udi_scsi_ctl_cb_t
 ) val @ {
-    val.ctl_req(cb)
+    let op = cb.op();
+    async move {
+        match op {
+        Some(op) => val.ctl_req(cb, op).await,
+        None => not_understood(),
+        }
+    }
 } finally( res ) {
     unsafe { ffi::udi_scsi_ctl_ack(cb, crate::Error::to_status(res)) }
 });
@@ -0,0 +1,1910 @@
+//! USB Driver Interface (USBDI) metalanguage
+//!
+//! A USB function driver (the "Logical Device Driver", LDD) binds to the USB host stack (the "USB Driver", USBD)
+//! over an interface channel ([LddIntfc] on the LDD side, [UsbdIntfc] on the USBD side). Once the interface has been
+//! opened, each endpoint is accessed through a pipe channel ([LddPipe]/[UsbdPipe]) spawned from the interface
+//! channel, using the endpoint's index as the spawn index.
+//!
+//! Descriptors returned by the device can be decoded using [DeviceDescriptor] and [Descriptors].
+use ::udi_sys::meta_usb as ffi;
+
+impl_metalanguage!{
+    static METALANG_SPEC;
+    NAME usbdi;
+    OPS
+        1 => ffi::usbdi_ldd_intfc_ops_t,
+        2 => ffi::usbdi_ldd_pipe_ops_t,
+        3 => ffi::usbdi_usbd_intfc_ops_t,
+        4 => ffi::usbdi_usbd_pipe_ops_t,
+        ;
+    CBS
+        1 => ffi::usbdi_misc_cb_t,
+        2 => ffi::usbdi_intr_bulk_xfer_cb_t : BUF data_buf,
+        3 => ffi::usbdi_control_xfer_cb_t : BUF data_buf,
+        4 => ffi::usbdi_isoc_xfer_cb_t : BUF data_buf : INLINE_DATA frame_array,
+        5 => ffi::usbdi_desc_cb_t : BUF desc_buf,
+        6 => ffi::usbdi_state_cb_t,
+        ;
+}
+
+impl crate::ops_markers::ParentBind<ffi::usbdi_misc_cb_t> for ffi::usbdi_ldd_intfc_ops_t {
+    const ASSERT: () = ();
+}
+impl crate::ops_markers::ChildBind for ffi::usbdi_usbd_intfc_ops_t {
+    const ASSERT: () = ();
+}
+
+/// Request unbind from the USBD
+pub fn unbind_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_unbind_req(cb.into_raw()) }
+}
+/// Open the interface, using the alternate setting `alternate_intfc`
+pub fn intfc_open_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>, alternate_intfc: u8, open_flag: u8) {
+    unsafe { ffi::usbdi_intfc_open_req(cb.into_raw(), alternate_intfc, open_flag) }
+}
+/// Close the interface
+pub fn intfc_close_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_intfc_close_req(cb.into_raw()) }
+}
+/// Request the current USB frame number
+pub fn frame_number_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_frame_number_req(cb.into_raw()) }
+}
+/// Request the speed of the device
+pub fn device_speed_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_device_speed_req(cb.into_raw()) }
+}
+/// Reset the device
+pub fn reset_device_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_reset_device_req(cb.into_raw()) }
+}
+/// Abort all outstanding transfers on the interface
+pub fn intfc_abort_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_intfc_abort_req(cb.into_raw()) }
+}
+/// Change the state of all pipes in the interface
+pub fn intfc_state_set_req(mut cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>, state: PipeState) {
+    unsafe {
+        cb.get_mut().state = state.to_raw();
+        ffi::usbdi_intfc_state_set_req(cb.into_raw())
+    }
+}
+/// Get the state of the interface
+pub fn intfc_state_get_req(cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>) {
+    unsafe { ffi::usbdi_intfc_state_get_req(cb.into_raw()) }
+}
+/// Request a descriptor (populated using [crate::cb::CbHandle::set_request])
+pub fn desc_req(cb: crate::cb::CbHandle<ffi::usbdi_desc_cb_t>) {
+    unsafe { ffi::usbdi_desc_req(cb.into_raw()) }
+}
+/// Get the state of the device
+pub fn device_state_get_req(cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>) {
+    unsafe { ffi::usbdi_device_state_get_req(cb.into_raw()) }
+}
+/// Select the device configuration with `bConfigurationValue` of `config_value`
+pub fn config_set_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>, config_value: u16) {
+    unsafe { ffi::usbdi_config_set_req(cb.into_raw(), config_value) }
+}
+/// Indicate an asynchronous event to the LDD, which responds with [UsbdIntfc::async_event_res]
+pub fn async_event_ind(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>, async_event: u16) {
+    unsafe { ffi::usbdi_async_event_ind(cb.into_raw(), async_event) }
+}
+
+/// Start an interrupt or bulk transfer (populated using [crate::cb::CbHandle::set_request])
+pub fn intr_bulk_xfer_req(cb: crate::cb::CbHandle<ffi::usbdi_intr_bulk_xfer_cb_t>) {
+    unsafe { ffi::usbdi_intr_bulk_xfer_req(cb.into_raw()) }
+}
+/// Start a control transfer (populated using [crate::cb::CbHandle::set_request])
+pub fn control_xfer_req(cb: crate::cb::CbHandle<ffi::usbdi_control_xfer_cb_t>) {
+    unsafe { ffi::usbdi_control_xfer_req(cb.into_raw()) }
+}
+/// Start an isochronous transfer (allocated with [alloc_isoc])
+pub fn isoc_xfer_req(cb: crate::cb::CbHandle<ffi::usbdi_isoc_xfer_cb_t>) {
+    unsafe { ffi::usbdi_isoc_xfer_req(cb.into_raw()) }
+}
+/// Abort all outstanding transfers on the pipe
+pub fn pipe_abort_req(cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) {
+    unsafe { ffi::usbdi_pipe_abort_req(cb.into_raw()) }
+}
+/// Change the state of the pipe
+pub fn pipe_state_set_req(mut cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>, state: PipeState) {
+    unsafe {
+        cb.get_mut().state = state.to_raw();
+        ffi::usbdi_pipe_state_set_req(cb.into_raw())
+    }
+}
+/// Get the state of the pipe
+pub fn pipe_state_get_req(cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>) {
+    unsafe { ffi::usbdi_pipe_state_get_req(cb.into_raw()) }
+}
+/// Change the state of the endpoint on the device (e.g. to clear a stall)
+pub fn edpt_state_set_req(mut cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>, state: PipeState) {
+    unsafe {
+        cb.get_mut().state = state.to_raw();
+        ffi::usbdi_edpt_state_set_req(cb.into_raw())
+    }
+}
+/// Get the state of the endpoint on the device
+pub fn edpt_state_get_req(cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>) {
+    unsafe { ffi::usbdi_edpt_state_get_req(cb.into_raw()) }
+}
+
+/// Layout of one entry in the inline frame array of an isochronous transfer CB (repeated for each frame)
+const ISOC_FRAME_LAYOUT: &[crate::ffi::udi_layout_t] = &[
+    crate::ffi::layout::UDI_DL_UBIT32_T,
+    crate::ffi::layout::UDI_DL_STATUS_T,
+    crate::ffi::layout::UDI_DL_END,
+];
+
+/// Allocate an isochronous transfer CB (of type `CbDef`) with `frame_count` frames, populated from `xfer`
+///
+/// The frame lengths are set using [crate::cb::CbHandle::frames_mut]
+pub async fn alloc_isoc<CbDef>(gcb: crate::CbRef<'_, crate::ffi::udi_cb_t>, channel: crate::ffi::udi_channel_t, frame_count: u8, xfer: &IsocXfer)
+    -> crate::cb::CbHandle<ffi::usbdi_isoc_xfer_cb_t>
+where
+    CbDef: crate::cb::CbDefinition<Cb=ffi::usbdi_isoc_xfer_cb_t>,
+{
+    let size = frame_count as usize * ::core::mem::size_of::<ffi::usbdi_isoc_frame_request_t>();
+    // SAFE: `ISOC_FRAME_LAYOUT` describes the frame request array
+    let mut cb = unsafe { crate::cb::alloc_dynamic::<CbDef>(gcb, channel, size, ISOC_FRAME_LAYOUT) }.await;
+    // SAFE: The inline data was allocated for `frame_count` frames
+    unsafe { cb.get_mut().frame_count = frame_count; }
+    cb.set_request(xfer);
+    cb
+}
+
+fn not_understood<T>() -> crate::Result<T> {
+    Err(crate::Error::from_status(crate::ffi::UDI_STAT_NOT_UNDERSTOOD as _).unwrap_err())
+}
+
+def_flags!{
+    /// Transfer flags (`xfer_flags` in the transfer CBs)
+    XferFlags: u8 {
+        /// A transfer shorter than the buffer is not an error (`USBDI_XFER_SHORT_OK`)
+        SHORT_OK = ffi::USBDI_XFER_SHORT_OK,
+        /// Data is transferred from the device (`USBDI_XFER_IN`)
+        IN = ffi::USBDI_XFER_IN,
+        /// Data is transferred to the device (`USBDI_XFER_OUT`)
+        OUT = ffi::USBDI_XFER_OUT,
+        /// Start an isochronous transfer as soon as possible, instead of at `frame_number` (`USBDI_XFER_ASAP`)
+        ASAP = ffi::USBDI_XFER_ASAP,
+    }
+}
+def_flags!{
+    /// Device state, as returned by [device_state_get_req]
+    DeviceState: u8 {
+        /// A configuration has been selected (`USBDI_STATE_CONFIGURED`)
+        CONFIGURED = ffi::USBDI_STATE_CONFIGURED,
+        /// The device is suspended (`USBDI_STATE_SUSPENDED`)
+        SUSPENDED = ffi::USBDI_STATE_SUSPENDED,
+    }
+}
+
+/// Direction of a transfer or endpoint, relative to the host
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Direction {
+    /// Device to host
+    In,
+    /// Host to device
+    Out,
+}
+impl Direction {
+    /// Get the direction from transfer flags, `None` if both or neither of `IN` and `OUT` are set
+    pub fn from_flags(flags: XferFlags) -> Option<Self> {
+        match (flags.contains(XferFlags::IN), flags.contains(XferFlags::OUT)) {
+        (true, false) => Some(Direction::In),
+        (false, true) => Some(Direction::Out),
+        _ => None,
+        }
+    }
+    /// Get the transfer flag for this direction
+    pub fn to_flags(self) -> XferFlags {
+        match self {
+        Direction::In => XferFlags::IN,
+        Direction::Out => XferFlags::OUT,
+        }
+    }
+}
+
+/// State of a pipe, interface, or endpoint
+///
+/// Transfers are only processed while [PipeState::Active]. A pipe is stalled by the device (when an endpoint returns
+/// a STALL handshake), and the stall is cleared by setting the state back to [PipeState::Active].
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum PipeState {
+    /// Transfers are processed (`USBDI_STATE_ACTIVE`)
+    Active,
+    /// The endpoint has stalled, transfers fail until the stall is cleared (`USBDI_STATE_STALLED`)
+    Stalled,
+    /// No transfers are being processed (`USBDI_STATE_IDLE`)
+    Idle,
+    /// The pipe has been halted, transfers fail (`USBDI_STATE_HALTED`)
+    Halted,
+}
+impl PipeState {
+    /// Decode a raw `state` value
+    pub fn from_raw(v: u8) -> Option<Self> {
+        Some(match v {
+        ffi::USBDI_STATE_ACTIVE => PipeState::Active,
+        ffi::USBDI_STATE_STALLED => PipeState::Stalled,
+        ffi::USBDI_STATE_IDLE => PipeState::Idle,
+        ffi::USBDI_STATE_HALTED => PipeState::Halted,
+        _ => return None,
+        })
+    }
+    /// Get the raw `state` value
+    pub fn to_raw(self) -> u8 {
+        match self {
+        PipeState::Active => ffi::USBDI_STATE_ACTIVE,
+        PipeState::Stalled => ffi::USBDI_STATE_STALLED,
+        PipeState::Idle => ffi::USBDI_STATE_IDLE,
+        PipeState::Halted => ffi::USBDI_STATE_HALTED,
+        }
+    }
+    /// Are transfers processed in this state
+    pub fn accepts_transfers(self) -> bool {
+        self == PipeState::Active
+    }
+    /// Check that the LDD can request a change from this state to `new`
+    ///
+    /// Only the device can stall a pipe, so requesting [PipeState::Stalled] fails with `UDI_STAT_INVALID_STATE`
+    pub fn check_set(self, new: PipeState) -> crate::Result<()> {
+        match new {
+        PipeState::Stalled => Err(crate::Error::from_status(crate::ffi::UDI_STAT_INVALID_STATE as _).unwrap_err()),
+        _ => Ok(()),
+        }
+    }
+}
+
+/// Speed of the device, as returned by [device_speed_req]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum DeviceSpeed {
+    /// Low speed, 1.5Mb/s (`USBDI_DEVICE_SPEED_LOW`)
+    Low,
+    /// Full speed, 12Mb/s (`USBDI_DEVICE_SPEED_FULL`)
+    Full,
+    /// High speed, 480Mb/s (`USBDI_DEVICE_SPEED_HIGH`)
+    High,
+    /// Unknown speed value
+    Other(u8),
+}
+impl DeviceSpeed {
+    /// Decode a raw `device_speed` value
+    pub fn from_raw(v: u8) -> Self {
+        match v {
+        ffi::USBDI_DEVICE_SPEED_LOW => DeviceSpeed::Low,
+        ffi::USBDI_DEVICE_SPEED_FULL => DeviceSpeed::Full,
+        ffi::USBDI_DEVICE_SPEED_HIGH => DeviceSpeed::High,
+        _ => DeviceSpeed::Other(v),
+        }
+    }
+    /// Get the raw `device_speed` value
+    pub fn to_raw(self) -> u8 {
+        match self {
+        DeviceSpeed::Low => ffi::USBDI_DEVICE_SPEED_LOW,
+        DeviceSpeed::Full => ffi::USBDI_DEVICE_SPEED_FULL,
+        DeviceSpeed::High => ffi::USBDI_DEVICE_SPEED_HIGH,
+        DeviceSpeed::Other(v) => v,
+        }
+    }
+}
+
+/// Type of a control request (bits 5-6 of `bmRequestType`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum RequestKind {
+    /// Standard request, defined by the USB specification
+    Standard,
+    /// Class-specific request
+    Class,
+    /// Vendor-specific request
+    Vendor,
+}
+/// Recipient of a control request (bits 0-4 of `bmRequestType`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Recipient {
+    /// The device
+    Device,
+    /// An interface (`wIndex` is the interface number)
+    Interface,
+    /// An endpoint (`wIndex` is the endpoint address)
+    Endpoint,
+    /// Other
+    Other,
+}
+/// Decoded `bmRequestType` field of a control request
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct RequestType {
+    /// Direction of the data stage
+    pub direction: Direction,
+    /// Request type
+    pub kind: RequestKind,
+    /// Recipient of the request
+    pub recipient: Recipient,
+}
+impl RequestType {
+    /// Decode a raw `bmRequestType`, returning `None` if the type or recipient are reserved values
+    pub fn from_raw(v: u8) -> Option<Self> {
+        Some(RequestType {
+            direction: if v & 0x80 != 0 { Direction::In } else { Direction::Out },
+            kind: match (v >> 5) & 3 {
+                0 => RequestKind::Standard,
+                1 => RequestKind::Class,
+                2 => RequestKind::Vendor,
+                _ => return None,
+                },
+            recipient: match v & 0x1F {
+                0 => Recipient::Device,
+                1 => Recipient::Interface,
+                2 => Recipient::Endpoint,
+                3 => Recipient::Other,
+                _ => return None,
+                },
+        })
+    }
+    /// Get the raw `bmRequestType` value
+    pub fn to_raw(self) -> u8 {
+        let dir = match self.direction {
+            Direction::In => 0x80,
+            Direction::Out => 0x00,
+            };
+        let kind = match self.kind {
+            RequestKind::Standard => 0,
+            RequestKind::Class => 1,
+            RequestKind::Vendor => 2,
+            };
+        let recipient = match self.recipient {
+            Recipient::Device => 0,
+            Recipient::Interface => 1,
+            Recipient::Endpoint => 2,
+            Recipient::Other => 3,
+            };
+        dir | kind << 5 | recipient
+    }
+}
+
+/// A control request (the SETUP packet of a control transfer)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct DeviceRequest {
+    /// `bmRequestType`
+    pub request_type: RequestType,
+    /// `bRequest`
+    pub request: u8,
+    /// `wValue`
+    pub value: u16,
+    /// `wIndex`
+    pub index: u16,
+    /// `wLength`, the length of the data stage
+    pub length: u16,
+}
+impl DeviceRequest {
+    /// Standard request: GET_STATUS
+    pub const GET_STATUS: u8 = 0;
+    /// Standard request: CLEAR_FEATURE
+    pub const CLEAR_FEATURE: u8 = 1;
+    /// Standard request: SET_FEATURE
+    pub const SET_FEATURE: u8 = 3;
+    /// Standard request: SET_ADDRESS
+    pub const SET_ADDRESS: u8 = 5;
+    /// Standard request: GET_DESCRIPTOR
+    pub const GET_DESCRIPTOR: u8 = 6;
+    /// Standard request: SET_DESCRIPTOR
+    pub const SET_DESCRIPTOR: u8 = 7;
+    /// Standard request: GET_CONFIGURATION
+    pub const GET_CONFIGURATION: u8 = 8;
+    /// Standard request: SET_CONFIGURATION
+    pub const SET_CONFIGURATION: u8 = 9;
+    /// Standard request: GET_INTERFACE
+    pub const GET_INTERFACE: u8 = 10;
+    /// Standard request: SET_INTERFACE
+    pub const SET_INTERFACE: u8 = 11;
+    /// Standard request: SYNCH_FRAME
+    pub const SYNCH_FRAME: u8 = 12;
+
+    /// Feature selector: ENDPOINT_HALT
+    pub const FEATURE_ENDPOINT_HALT: u16 = 0;
+    /// Feature selector: DEVICE_REMOTE_WAKEUP
+    pub const FEATURE_DEVICE_REMOTE_WAKEUP: u16 = 1;
+
+    /// A standard request
+    pub fn standard(direction: Direction, recipient: Recipient, request: u8, value: u16, index: u16, length: u16) -> Self {
+        DeviceRequest {
+            request_type: RequestType { direction, kind: RequestKind::Standard, recipient },
+            request,
+            value,
+            index,
+            length,
+        }
+    }
+    /// GET_STATUS for `recipient` (`index` is the interface number or endpoint address)
+    pub fn get_status(recipient: Recipient, index: u16) -> Self {
+        Self::standard(Direction::In, recipient, Self::GET_STATUS, 0, index, 2)
+    }
+    /// CLEAR_FEATURE of `feature` on `recipient`
+    pub fn clear_feature(recipient: Recipient, feature: u16, index: u16) -> Self {
+        Self::standard(Direction::Out, recipient, Self::CLEAR_FEATURE, feature, index, 0)
+    }
+    /// SET_FEATURE of `feature` on `recipient`
+    pub fn set_feature(recipient: Recipient, feature: u16, index: u16) -> Self {
+        Self::standard(Direction::Out, recipient, Self::SET_FEATURE, feature, index, 0)
+    }
+    /// Clear a halt (stall) on the endpoint with address `endpoint`
+    pub fn clear_halt(endpoint: u8) -> Self {
+        Self::clear_feature(Recipient::Endpoint, Self::FEATURE_ENDPOINT_HALT, endpoint as u16)
+    }
+    /// GET_DESCRIPTOR, returning up to `length` bytes (`lang_id` is only used for string descriptors)
+    pub fn get_descriptor(desc_type: DescriptorType, desc_index: u8, lang_id: u16, length: u16) -> Self {
+        let value = (desc_type.to_raw() as u16) << 8 | desc_index as u16;
+        Self::standard(Direction::In, Recipient::Device, Self::GET_DESCRIPTOR, value, lang_id, length)
+    }
+    /// GET_CONFIGURATION
+    pub fn get_configuration() -> Self {
+        Self::standard(Direction::In, Recipient::Device, Self::GET_CONFIGURATION, 0, 0, 1)
+    }
+    /// SET_CONFIGURATION, selecting the configuration with `bConfigurationValue` of `config_value`
+    pub fn set_configuration(config_value: u8) -> Self {
+        Self::standard(Direction::Out, Recipient::Device, Self::SET_CONFIGURATION, config_value as u16, 0, 0)
+    }
+    /// GET_INTERFACE, returning the current alternate setting of `interface`
+    pub fn get_interface(interface: u8) -> Self {
+        Self::standard(Direction::In, Recipient::Interface, Self::GET_INTERFACE, 0, interface as u16, 1)
+    }
+    /// SET_INTERFACE, selecting `alternate` for `interface`
+    pub fn set_interface(interface: u8, alternate: u8) -> Self {
+        Self::standard(Direction::Out, Recipient::Interface, Self::SET_INTERFACE, alternate as u16, interface as u16, 0)
+    }
+
+    /// Decode the 8 byte SETUP packet, returning `None` if `bmRequestType` is invalid
+    pub fn from_bytes(b: &[u8; 8]) -> Option<Self> {
+        Some(DeviceRequest {
+            request_type: RequestType::from_raw(b[0])?,
+            request: b[1],
+            value: u16::from_le_bytes([b[2], b[3]]),
+            index: u16::from_le_bytes([b[4], b[5]]),
+            length: u16::from_le_bytes([b[6], b[7]]),
+        })
+    }
+    /// Encode as the 8 byte SETUP packet
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let [v0,v1] = self.value.to_le_bytes();
+        let [i0,i1] = self.index.to_le_bytes();
+        let [l0,l1] = self.length.to_le_bytes();
+        [self.request_type.to_raw(), self.request, v0,v1, i0,i1, l0,l1]
+    }
+    /// Decode from the FFI structure
+    pub fn from_raw(raw: &ffi::usb_device_request_t) -> Option<Self> {
+        Self::from_bytes(&[
+            raw.bmRequestType, raw.bRequest,
+            raw.wValue0, raw.wValue1, raw.wIndex0, raw.wIndex1, raw.wLength0, raw.wLength1,
+            ])
+    }
+    /// Encode as the FFI structure
+    pub fn to_raw(&self) -> ffi::usb_device_request_t {
+        let b = self.to_bytes();
+        ffi::usb_device_request_t {
+            bmRequestType: b[0],
+            bRequest: b[1],
+            wValue0: b[2],
+            wValue1: b[3],
+            wIndex0: b[4],
+            wIndex1: b[5],
+            wLength0: b[6],
+            wLength1: b[7],
+        }
+    }
+}
+
+/// An interrupt or bulk transfer (the fields of a [ffi::usbdi_intr_bulk_xfer_cb_t] other than the data buffer)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct IntrBulkXfer {
+    /// Direction of the transfer
+    pub direction: Direction,
+    /// Transferring less than the size of the data buffer is not an error
+    pub short_ok: bool,
+    /// Timeout in milliseconds (zero for no timeout)
+    pub timeout: u32,
+}
+impl IntrBulkXfer {
+    /// A transfer from the device, with no timeout
+    pub fn read() -> Self {
+        IntrBulkXfer { direction: Direction::In, short_ok: false, timeout: 0 }
+    }
+    /// A transfer to the device, with no timeout
+    pub fn write() -> Self {
+        IntrBulkXfer { direction: Direction::Out, short_ok: false, timeout: 0 }
+    }
+    /// Allow short transfers
+    pub fn short_ok(self) -> Self {
+        IntrBulkXfer { short_ok: true, ..self }
+    }
+    /// Set the timeout (in milliseconds)
+    pub fn with_timeout(self, timeout: u32) -> Self {
+        IntrBulkXfer { timeout, ..self }
+    }
+    /// Decode the transfer from a raw CB
+    pub fn from_cb(cb: &ffi::usbdi_intr_bulk_xfer_cb_t) -> crate::Result<Self> {
+        let flags = XferFlags::from_raw(cb.xfer_flags);
+        let Some(direction) = Direction::from_flags(flags) else {
+            return not_understood();
+        };
+        Ok(IntrBulkXfer { direction, short_ok: flags.contains(XferFlags::SHORT_OK), timeout: cb.timeout })
+    }
+    /// Write the transfer into a raw CB
+    pub fn write_cb(&self, cb: &mut ffi::usbdi_intr_bulk_xfer_cb_t) {
+        cb.xfer_flags = xfer_flags(self.direction, self.short_ok).to_raw();
+        cb.timeout = self.timeout;
+    }
+}
+
+/// A control transfer (the fields of a [ffi::usbdi_control_xfer_cb_t] other than the data buffer)
+///
+/// The direction of the data stage is given by the request
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct ControlXfer {
+    /// The SETUP packet
+    pub request: DeviceRequest,
+    /// Transferring less than `wLength` bytes is not an error
+    pub short_ok: bool,
+    /// Timeout in milliseconds (zero for no timeout)
+    pub timeout: u32,
+}
+impl ControlXfer {
+    /// A transfer of `request`, with no timeout
+    pub fn new(request: DeviceRequest) -> Self {
+        ControlXfer { request, short_ok: false, timeout: 0 }
+    }
+    /// Allow short transfers
+    pub fn short_ok(self) -> Self {
+        ControlXfer { short_ok: true, ..self }
+    }
+    /// Set the timeout (in milliseconds)
+    pub fn with_timeout(self, timeout: u32) -> Self {
+        ControlXfer { timeout, ..self }
+    }
+    /// Decode the transfer from a raw CB
+    pub fn from_cb(cb: &ffi::usbdi_control_xfer_cb_t) -> crate::Result<Self> {
+        // SAFE: Both union variants are plain bytes
+        let Some(request) = DeviceRequest::from_raw(unsafe { &cb.request.device_request }) else {
+            return not_understood();
+        };
+        let flags = XferFlags::from_raw(cb.xfer_flags);
+        Ok(ControlXfer { request, short_ok: flags.contains(XferFlags::SHORT_OK), timeout: cb.timeout })
+    }
+    /// Write the transfer into a raw CB
+    pub fn write_cb(&self, cb: &mut ffi::usbdi_control_xfer_cb_t) {
+        cb.request.device_request = self.request.to_raw();
+        cb.xfer_flags = xfer_flags(self.request.request_type.direction, self.short_ok).to_raw();
+        cb.timeout = self.timeout;
+    }
+}
+
+/// An isochronous transfer (the fields of a [ffi::usbdi_isoc_xfer_cb_t] other than the data buffer and frames)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct IsocXfer {
+    /// Direction of the transfer
+    pub direction: Direction,
+    /// Start as soon as possible, ignoring `frame_number`
+    pub asap: bool,
+    /// Frame number to start the transfer at
+    pub frame_number: u32,
+    /// Timeout in milliseconds (zero for no timeout)
+    pub timeout: u32,
+}
+impl IsocXfer {
+    /// A transfer starting as soon as possible
+    pub fn asap(direction: Direction) -> Self {
+        IsocXfer { direction, asap: true, frame_number: 0, timeout: 0 }
+    }
+    /// A transfer starting at `frame_number`
+    pub fn at_frame(direction: Direction, frame_number: u32) -> Self {
+        IsocXfer { direction, asap: false, frame_number, timeout: 0 }
+    }
+    /// Set the timeout (in milliseconds)
+    pub fn with_timeout(self, timeout: u32) -> Self {
+        IsocXfer { timeout, ..self }
+    }
+    /// Decode the transfer from a raw CB
+    pub fn from_cb(cb: &ffi::usbdi_isoc_xfer_cb_t) -> crate::Result<Self> {
+        let flags = XferFlags::from_raw(cb.xfer_flags);
+        let Some(direction) = Direction::from_flags(flags) else {
+            return not_understood();
+        };
+        Ok(IsocXfer {
+            direction,
+            asap: flags.contains(XferFlags::ASAP),
+            frame_number: cb.frame_number,
+            timeout: cb.timeout,
+        })
+    }
+    /// Write the transfer into a raw CB
+    pub fn write_cb(&self, cb: &mut ffi::usbdi_isoc_xfer_cb_t) {
+        let asap = if self.asap { XferFlags::ASAP } else { XferFlags::NONE };
+        cb.xfer_flags = (self.direction.to_flags() | asap).to_raw();
+        cb.frame_number = self.frame_number;
+        cb.timeout = self.timeout;
+    }
+}
+
+fn xfer_flags(direction: Direction, short_ok: bool) -> XferFlags {
+    direction.to_flags() | if short_ok { XferFlags::SHORT_OK } else { XferFlags::NONE }
+}
+
+/// A descriptor request (the fields of a [ffi::usbdi_desc_cb_t] other than the buffer)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct DescRequest {
+    /// Type of descriptor to fetch
+    pub desc_type: DescriptorType,
+    /// Index of the descriptor (e.g. the configuration or string index)
+    pub index: u8,
+    /// Language ID for string descriptors
+    pub lang_id: u16,
+    /// Maximum number of bytes to return
+    pub length: u16,
+}
+impl DescRequest {
+    /// Request up to `length` bytes of a descriptor
+    pub fn new(desc_type: DescriptorType, index: u8, length: u16) -> Self {
+        DescRequest { desc_type, index, lang_id: 0, length }
+    }
+    /// Set the language ID (for string descriptors)
+    pub fn with_lang_id(self, lang_id: u16) -> Self {
+        DescRequest { lang_id, ..self }
+    }
+    /// Decode the request from a raw CB
+    pub fn from_cb(cb: &ffi::usbdi_desc_cb_t) -> Self {
+        DescRequest {
+            desc_type: DescriptorType::from_raw(cb.desc_type),
+            index: cb.desc_index,
+            lang_id: cb.desc_id,
+            length: cb.desc_length,
+        }
+    }
+    /// Write the request into a raw CB
+    pub fn write_cb(&self, cb: &mut ffi::usbdi_desc_cb_t) {
+        cb.desc_type = self.desc_type.to_raw();
+        cb.desc_index = self.index;
+        cb.desc_id = self.lang_id;
+        cb.desc_length = self.length;
+    }
+}
+
+/// Descriptor type (`bDescriptorType`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum DescriptorType {
+    /// Device descriptor (`USB_DESC_TYPE_DEVICE`)
+    Device,
+    /// Configuration descriptor (`USB_DESC_TYPE_CONFIG`)
+    Config,
+    /// String descriptor (`USB_DESC_TYPE_STRING`)
+    String,
+    /// Interface descriptor (`USB_DESC_TYPE_INTFC`)
+    Interface,
+    /// Endpoint descriptor (`USB_DESC_TYPE_EDPT`)
+    Endpoint,
+    /// Any other descriptor (e.g. class-specific)
+    Other(u8),
+}
+impl DescriptorType {
+    /// Decode a raw descriptor type
+    pub fn from_raw(v: u8) -> Self {
+        match v {
+        ffi::USB_DESC_TYPE_DEVICE => DescriptorType::Device,
+        ffi::USB_DESC_TYPE_CONFIG => DescriptorType::Config,
+        ffi::USB_DESC_TYPE_STRING => DescriptorType::String,
+        ffi::USB_DESC_TYPE_INTFC => DescriptorType::Interface,
+        ffi::USB_DESC_TYPE_EDPT => DescriptorType::Endpoint,
+        _ => DescriptorType::Other(v),
+        }
+    }
+    /// Get the raw descriptor type
+    pub fn to_raw(self) -> u8 {
+        match self {
+        DescriptorType::Device => ffi::USB_DESC_TYPE_DEVICE,
+        DescriptorType::Config => ffi::USB_DESC_TYPE_CONFIG,
+        DescriptorType::String => ffi::USB_DESC_TYPE_STRING,
+        DescriptorType::Interface => ffi::USB_DESC_TYPE_INTFC,
+        DescriptorType::Endpoint => ffi::USB_DESC_TYPE_EDPT,
+        DescriptorType::Other(v) => v,
+        }
+    }
+}
+
+/// Check the header of a descriptor, returning the descriptor bytes (trimmed to `bLength`)
+fn check_desc(data: &[u8], ty: DescriptorType, min_len: usize) -> Option<&[u8]> {
+    let len = *data.first()? as usize;
+    if len < min_len || len > data.len() || data[1] != ty.to_raw() {
+        return None;
+    }
+    Some(&data[..len])
+}
+fn get_u16(d: &[u8], ofs: usize) -> u16 {
+    u16::from_le_bytes([d[ofs], d[ofs+1]])
+}
+
+/// Device descriptor
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct DeviceDescriptor {
+    /// USB specification version, in BCD (`bcdUSB`)
+    pub usb_version: u16,
+    /// Device class (`bDeviceClass`)
+    pub class: u8,
+    /// Device subclass (`bDeviceSubClass`)
+    pub subclass: u8,
+    /// Device protocol (`bDeviceProtocol`)
+    pub protocol: u8,
+    /// Maximum packet size of the default control pipe (`bMaxPacketSize0`)
+    pub max_packet_size0: u8,
+    /// Vendor ID (`idVendor`)
+    pub vendor_id: u16,
+    /// Product ID (`idProduct`)
+    pub product_id: u16,
+    /// Device release number, in BCD (`bcdDevice`)
+    pub device_version: u16,
+    /// Index of the manufacturer string (`iManufacturer`)
+    pub manufacturer_str: u8,
+    /// Index of the product string (`iProduct`)
+    pub product_str: u8,
+    /// Index of the serial number string (`iSerialNumber`)
+    pub serial_str: u8,
+    /// Number of configurations (`bNumConfigurations`)
+    pub num_configurations: u8,
+}
+impl DeviceDescriptor {
+    /// Length of a device descriptor
+    pub const LEN: usize = 18;
+    /// Parse a device descriptor
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let d = check_desc(data, DescriptorType::Device, Self::LEN)?;
+        Some(DeviceDescriptor {
+            usb_version: get_u16(d, 2),
+            class: d[4],
+            subclass: d[5],
+            protocol: d[6],
+            max_packet_size0: d[7],
+            vendor_id: get_u16(d, 8),
+            product_id: get_u16(d, 10),
+            device_version: get_u16(d, 12),
+            manufacturer_str: d[14],
+            product_str: d[15],
+            serial_str: d[16],
+            num_configurations: d[17],
+        })
+    }
+}
+
+/// Configuration descriptor (the header of the configuration descriptor set)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct ConfigDescriptor {
+    /// Length of the configuration, including all interface and endpoint descriptors (`wTotalLength`)
+    pub total_length: u16,
+    /// Number of interfaces (`bNumInterfaces`)
+    pub num_interfaces: u8,
+    /// Value to pass to [config_set_req] to select this configuration (`bConfigurationValue`)
+    pub config_value: u8,
+    /// Index of the configuration's string (`iConfiguration`)
+    pub config_str: u8,
+    /// Attributes (`bmAttributes`)
+    pub attributes: u8,
+    /// Maximum power consumption, in units of 2mA (`bMaxPower`)
+    pub max_power: u8,
+}
+impl ConfigDescriptor {
+    /// Length of a configuration descriptor
+    pub const LEN: usize = 9;
+    /// Parse a configuration descriptor
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let d = check_desc(data, DescriptorType::Config, Self::LEN)?;
+        Some(ConfigDescriptor {
+            total_length: get_u16(d, 2),
+            num_interfaces: d[4],
+            config_value: d[5],
+            config_str: d[6],
+            attributes: d[7],
+            max_power: d[8],
+        })
+    }
+    /// The device is self-powered in this configuration
+    pub fn self_powered(&self) -> bool {
+        self.attributes & 0x40 != 0
+    }
+    /// The device supports remote wakeup in this configuration
+    pub fn remote_wakeup(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+    /// Maximum power consumption, in milliamps
+    pub fn max_power_ma(&self) -> u16 {
+        self.max_power as u16 * 2
+    }
+}
+
+/// Interface descriptor
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct InterfaceDescriptor {
+    /// Interface number (`bInterfaceNumber`)
+    pub number: u8,
+    /// Alternate setting (`bAlternateSetting`)
+    pub alternate_setting: u8,
+    /// Number of endpoints, excluding the default control pipe (`bNumEndpoints`)
+    pub num_endpoints: u8,
+    /// Interface class (`bInterfaceClass`)
+    pub class: u8,
+    /// Interface subclass (`bInterfaceSubClass`)
+    pub subclass: u8,
+    /// Interface protocol (`bInterfaceProtocol`)
+    pub protocol: u8,
+    /// Index of the interface's string (`iInterface`)
+    pub interface_str: u8,
+}
+impl InterfaceDescriptor {
+    /// Length of an interface descriptor
+    pub const LEN: usize = 9;
+    /// Parse an interface descriptor
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let d = check_desc(data, DescriptorType::Interface, Self::LEN)?;
+        Some(InterfaceDescriptor {
+            number: d[2],
+            alternate_setting: d[3],
+            num_endpoints: d[4],
+            class: d[5],
+            subclass: d[6],
+            protocol: d[7],
+            interface_str: d[8],
+        })
+    }
+}
+
+/// Type of transfers on an endpoint
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum TransferType {
+    /// Control transfers
+    Control,
+    /// Isochronous transfers
+    Isochronous,
+    /// Bulk transfers
+    Bulk,
+    /// Interrupt transfers
+    Interrupt,
+}
+/// Endpoint descriptor
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct EndpointDescriptor {
+    /// Endpoint address, including the direction bit (`bEndpointAddress`)
+    pub address: u8,
+    /// Attributes, including the transfer type (`bmAttributes`)
+    pub attributes: u8,
+    /// Maximum packet size (`wMaxPacketSize`)
+    pub max_packet_size: u16,
+    /// Polling interval (`bInterval`)
+    pub interval: u8,
+}
+impl EndpointDescriptor {
+    /// Length of an endpoint descriptor
+    pub const LEN: usize = 7;
+    /// Parse an endpoint descriptor
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let d = check_desc(data, DescriptorType::Endpoint, Self::LEN)?;
+        Some(EndpointDescriptor {
+            address: d[2],
+            attributes: d[3],
+            max_packet_size: get_u16(d, 4),
+            interval: d[6],
+        })
+    }
+    /// Endpoint number (without the direction bit)
+    pub fn number(&self) -> u8 {
+        self.address & 0xF
+    }
+    /// Direction of the endpoint (ignored for control endpoints)
+    pub fn direction(&self) -> Direction {
+        if self.address & 0x80 != 0 { Direction::In } else { Direction::Out }
+    }
+    /// Type of transfers on the endpoint
+    pub fn transfer_type(&self) -> TransferType {
+        match self.attributes & 3 {
+        0 => TransferType::Control,
+        1 => TransferType::Isochronous,
+        2 => TransferType::Bulk,
+        _ => TransferType::Interrupt,
+        }
+    }
+    /// Maximum packet size in bytes (excluding the high-bandwidth transaction count)
+    pub fn max_packet_bytes(&self) -> u16 {
+        self.max_packet_size & 0x7FF
+    }
+}
+
+/// A descriptor within a configuration descriptor set
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Descriptor<'a> {
+    /// Device descriptor
+    Device(DeviceDescriptor),
+    /// Configuration descriptor
+    Config(ConfigDescriptor),
+    /// Interface descriptor
+    Interface(InterfaceDescriptor),
+    /// Endpoint descriptor
+    Endpoint(EndpointDescriptor),
+    /// Any other descriptor (e.g. class-specific, or a standard descriptor that is too short), including the header
+    Other(DescriptorType, &'a [u8]),
+}
+/// Iterator over the descriptors in a block of descriptor data (e.g. the full configuration descriptor set)
+///
+/// Iteration stops at the end of the data, or at the first descriptor with an invalid length.
+#[derive(Clone)]
+pub struct Descriptors<'a> {
+    data: &'a [u8],
+}
+impl<'a> Descriptors<'a> {
+    /// Iterate the descriptors in `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Descriptors { data }
+    }
+    /// Get the endpoints of alternate setting `alternate` of interface `number`
+    pub fn interface_endpoints(&self, number: u8, alternate: u8) -> impl Iterator<Item=EndpointDescriptor> + 'a {
+        self.clone()
+            .skip_while(move |d| !matches!(d, Descriptor::Interface(i) if i.number == number && i.alternate_setting == alternate))
+            .skip(1)
+            .take_while(|d| !matches!(d, Descriptor::Interface(_)))
+            .filter_map(|d| match d {
+                Descriptor::Endpoint(e) => Some(e),
+                _ => None,
+                })
+    }
+}
+impl<'a> Iterator for Descriptors<'a> {
+    type Item = Descriptor<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < 2 {
+            return None;
+        }
+        let len = self.data[0] as usize;
+        if len < 2 || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (d, rest) = self.data.split_at(len);
+        self.data = rest;
+        let ty = DescriptorType::from_raw(d[1]);
+        let rv = match ty {
+            DescriptorType::Device => DeviceDescriptor::parse(d).map(Descriptor::Device),
+            DescriptorType::Config => ConfigDescriptor::parse(d).map(Descriptor::Config),
+            DescriptorType::Interface => InterfaceDescriptor::parse(d).map(Descriptor::Interface),
+            DescriptorType::Endpoint => EndpointDescriptor::parse(d).map(Descriptor::Endpoint),
+            _ => None,
+            };
+        Some(rv.unwrap_or(Descriptor::Other(ty, d)))
+    }
+}
+
+impl crate::cb::CbRef<'_, ffi::usbdi_intr_bulk_xfer_cb_t>
+{
+    /// Get the data buffer
+    pub fn data_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.data_buf) }
+    }
+    /// Decode the transfer, failing with `UDI_STAT_NOT_UNDERSTOOD` if the direction is invalid
+    pub fn request(&self) -> crate::Result<IntrBulkXfer> {
+        IntrBulkXfer::from_cb(self)
+    }
+}
+impl crate::cb::CbHandle<ffi::usbdi_intr_bulk_xfer_cb_t>
+{
+    /// Get a mutable handle to the data buffer
+    pub fn data_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().data_buf) }
+    }
+    /// Populate the transfer flags and timeout
+    pub fn set_request(&mut self, xfer: &IntrBulkXfer) {
+        // SAFE: Just setting integer fields
+        xfer.write_cb(unsafe { self.get_mut() })
+    }
+}
+impl crate::cb::CbRef<'_, ffi::usbdi_control_xfer_cb_t>
+{
+    /// Get the data buffer
+    pub fn data_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.data_buf) }
+    }
+    /// Decode the transfer, failing with `UDI_STAT_NOT_UNDERSTOOD` if the request type is invalid
+    pub fn request(&self) -> crate::Result<ControlXfer> {
+        ControlXfer::from_cb(self)
+    }
+}
+impl crate::cb::CbHandle<ffi::usbdi_control_xfer_cb_t>
+{
+    /// Get a mutable handle to the data buffer
+    pub fn data_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().data_buf) }
+    }
+    /// Populate the SETUP packet, transfer flags, and timeout
+    pub fn set_request(&mut self, xfer: &ControlXfer) {
+        // SAFE: Just setting integer fields
+        xfer.write_cb(unsafe { self.get_mut() })
+    }
+}
+impl crate::cb::CbRef<'_, ffi::usbdi_isoc_xfer_cb_t>
+{
+    /// Get the data buffer
+    pub fn data_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.data_buf) }
+    }
+    /// Decode the transfer, failing with `UDI_STAT_NOT_UNDERSTOOD` if the direction is invalid
+    pub fn request(&self) -> crate::Result<IsocXfer> {
+        IsocXfer::from_cb(self)
+    }
+    /// Get the frame array (lengths, and the status of each frame once the transfer completes)
+    pub fn frames(&self) -> &[ffi::usbdi_isoc_frame_request_t] {
+        if self.frame_array.is_null() {
+            &[]
+        }
+        else {
+            // SAFE: The inline data holds `frame_count` entries (see `alloc_isoc`)
+            unsafe { ::core::slice::from_raw_parts(self.frame_array, self.frame_count as usize) }
+        }
+    }
+}
+impl crate::cb::CbHandle<ffi::usbdi_isoc_xfer_cb_t>
+{
+    /// Get a mutable handle to the data buffer
+    pub fn data_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().data_buf) }
+    }
+    /// Populate the transfer flags, start frame, and timeout
+    pub fn set_request(&mut self, xfer: &IsocXfer) {
+        // SAFE: Just setting integer fields
+        xfer.write_cb(unsafe { self.get_mut() })
+    }
+    /// Get the frame array, to set the length of each frame
+    pub fn frames_mut(&mut self) -> &mut [ffi::usbdi_isoc_frame_request_t] {
+        // SAFE: The inline data holds `frame_count` entries (see `alloc_isoc`)
+        unsafe { frames_mut(self.get_mut()) }
+    }
+}
+/// SAFETY: `frame_array` must be null or valid for `frame_count` entries
+unsafe fn frames_mut<'a>(cb: *mut ffi::usbdi_isoc_xfer_cb_t) -> &'a mut [ffi::usbdi_isoc_frame_request_t] {
+    if (*cb).frame_array.is_null() {
+        &mut []
+    }
+    else {
+        ::core::slice::from_raw_parts_mut((*cb).frame_array, (*cb).frame_count as usize)
+    }
+}
+impl crate::cb::CbRef<'_, ffi::usbdi_desc_cb_t>
+{
+    /// Get the descriptor buffer
+    pub fn desc_buf(&self) -> &crate::buf::Handle {
+        // SAFE: Valid pointers
+        unsafe { crate::buf::Handle::from_ref(&self.desc_buf) }
+    }
+    /// Decode the request
+    pub fn request(&self) -> DescRequest {
+        DescRequest::from_cb(self)
+    }
+}
+impl crate::cb::CbHandle<ffi::usbdi_desc_cb_t>
+{
+    /// Get a mutable handle to the descriptor buffer
+    pub fn desc_buf_mut(&mut self) -> &mut crate::buf::Handle {
+        // SAFE: Valid pointers, validity will be maintained (`get_mut`)
+        unsafe { crate::buf::Handle::from_mut(&mut self.get_mut().desc_buf) }
+    }
+    /// Populate the descriptor type, index, language, and length
+    pub fn set_request(&mut self, req: &DescRequest) {
+        // SAFE: Just setting integer fields
+        req.write_cb(unsafe { self.get_mut() })
+    }
+}
+impl crate::cb::CbRef<'_, ffi::usbdi_state_cb_t>
+{
+    /// Decode the `state` field as a pipe/interface/endpoint state
+    pub fn pipe_state(&self) -> crate::Result<PipeState> {
+        match PipeState::from_raw(self.state) {
+        Some(v) => Ok(v),
+        None => not_understood(),
+        }
+    }
+}
+
+/// Interface channel operations for a USB function driver (LDD)
+pub trait LddIntfc: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    async_method!(
+        /// Acknowledgement of a binding, with the number of interfaces the LDD controls
+        fn bind_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, n_intfc: crate::Result<u8>)->()
+        as Future_bind_ack
+    );
+    async_method!(
+        /// Acknowledgement of [unbind_req]
+        fn unbind_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, res: crate::Result<()>)->()
+        as Future_unbind_ack
+    );
+    async_method!(
+        /// The interface has been opened, with the number of endpoints (pipes) in the alternate setting
+        fn intfc_open_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, n_edpt: crate::Result<u8>)->()
+        as Future_intfc_open_ack
+    );
+    async_method!(
+        /// The interface has been closed
+        fn intfc_close_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, res: crate::Result<()>)->()
+        as Future_intfc_close_ack
+    );
+    async_method!(
+        /// Current USB frame number
+        fn frame_number_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, frame_number: u32)->()
+        as Future_frame_number_ack
+    );
+    async_method!(
+        /// Speed of the device
+        fn device_speed_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, speed: DeviceSpeed)->()
+        as Future_device_speed_ack
+    );
+    async_method!(
+        /// The device has been reset
+        fn reset_device_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_reset_device_ack
+    );
+    async_method!(
+        /// All transfers on the interface have been aborted
+        fn intfc_abort_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_intfc_abort_ack
+    );
+    async_method!(
+        /// Acknowledgement of [intfc_state_set_req]
+        fn intfc_state_set_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, res: crate::Result<()>)->()
+        as Future_intfc_state_set_ack
+    );
+    async_method!(
+        /// Current state of the interface
+        fn intfc_state_get_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: crate::Result<PipeState>)->()
+        as Future_intfc_state_get_ack
+    );
+    async_method!(
+        /// A descriptor has been read into the CB's buffer (see [crate::cb::CbRef::desc_buf])
+        fn desc_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_desc_cb_t>, res: crate::Result<()>)->()
+        as Future_desc_ack
+    );
+    async_method!(
+        /// Current state of the device
+        fn device_state_get_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: DeviceState)->()
+        as Future_device_state_get_ack
+    );
+    async_method!(
+        /// Acknowledgement of [config_set_req]
+        fn config_set_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, res: crate::Result<()>)->()
+        as Future_config_set_ack
+    );
+    async_method!(
+        /// Handle an asynchronous event from the USBD, which is informed once this completes
+        fn async_event_ind(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, async_event: u16)->()
+        as Future_async_event_ind
+    );
+    /// Release a CB used for a request on the interface
+    fn misc_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) { let _ = cb; }
+    /// Release a CB used for a state request
+    fn state_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>) { let _ = cb; }
+    /// Release a CB used for a descriptor request
+    fn desc_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_desc_cb_t>) { let _ = cb; }
+}
+/// Pipe channel operations for a USB function driver (LDD)
+pub trait LddPipe: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    async_method!(
+        /// Completion of an interrupt or bulk transfer
+        fn intr_bulk_xfer_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_intr_bulk_xfer_cb_t>, res: crate::Result<()>)->()
+        as Future_intr_bulk_xfer_ack
+    );
+    async_method!(
+        /// Completion of a control transfer
+        fn control_xfer_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_control_xfer_cb_t>, res: crate::Result<()>)->()
+        as Future_control_xfer_ack
+    );
+    async_method!(
+        /// Completion of an isochronous transfer (the status of each frame is in [crate::cb::CbRef::frames])
+        fn isoc_xfer_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_isoc_xfer_cb_t>, res: crate::Result<()>)->()
+        as Future_isoc_xfer_ack
+    );
+    async_method!(
+        /// All transfers on the pipe have been aborted
+        fn pipe_abort_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_pipe_abort_ack
+    );
+    async_method!(
+        /// Acknowledgement of [pipe_state_set_req]
+        fn pipe_state_set_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, res: crate::Result<()>)->()
+        as Future_pipe_state_set_ack
+    );
+    async_method!(
+        /// Current state of the pipe
+        fn pipe_state_get_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: crate::Result<PipeState>)->()
+        as Future_pipe_state_get_ack
+    );
+    async_method!(
+        /// Acknowledgement of [edpt_state_set_req]
+        fn edpt_state_set_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, res: crate::Result<()>)->()
+        as Future_edpt_state_set_ack
+    );
+    async_method!(
+        /// Current state of the endpoint
+        fn edpt_state_get_ack(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: crate::Result<PipeState>)->()
+        as Future_edpt_state_get_ack
+    );
+    /// Release the CB used for an interrupt or bulk transfer
+    fn intr_bulk_xfer_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_intr_bulk_xfer_cb_t>) { let _ = cb; }
+    /// Release the CB used for a control transfer
+    fn control_xfer_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_control_xfer_cb_t>) { let _ = cb; }
+    /// Release the CB used for an isochronous transfer
+    fn isoc_xfer_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_isoc_xfer_cb_t>) { let _ = cb; }
+    /// Release the CB used for an abort request
+    fn misc_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>) { let _ = cb; }
+    /// Release a CB used for a state request
+    fn state_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_state_cb_t>) { let _ = cb; }
+}
+/// Interface channel operations for the USB host stack (USBD)
+pub trait UsbdIntfc: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    async_method!(
+        /// Handle a binding request from an LDD, returning the number of interfaces it controls
+        fn bind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->crate::Result<u8>
+        as Future_bind_req
+    );
+    async_method!(
+        /// Handle an unbind request
+        fn unbind_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->crate::Result<()>
+        as Future_unbind_req
+    );
+    async_method!(
+        /// Open the interface with the alternate setting `alternate_intfc`, returning the number of endpoints
+        fn intfc_open_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, alternate_intfc: u8, open_flag: u8)->crate::Result<u8>
+        as Future_intfc_open_req
+    );
+    async_method!(
+        /// Close the interface
+        fn intfc_close_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->crate::Result<()>
+        as Future_intfc_close_req
+    );
+    async_method!(
+        /// Get the current USB frame number
+        fn frame_number_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->u32
+        as Future_frame_number_req
+    );
+    async_method!(
+        /// Get the speed of the device
+        fn device_speed_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->DeviceSpeed
+        as Future_device_speed_req
+    );
+    async_method!(
+        /// Reset the device
+        fn reset_device_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_reset_device_req
+    );
+    async_method!(
+        /// Abort all outstanding transfers on the interface
+        fn intfc_abort_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_intfc_abort_req
+    );
+    async_method!(
+        /// Change the state of the interface
+        ///
+        /// Invalid states are failed with `UDI_STAT_NOT_UNDERSTOOD` without calling this.
+        fn intfc_state_set_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: PipeState)->crate::Result<()>
+        as Future_intfc_state_set_req
+    );
+    async_method!(
+        /// Get the state of the interface
+        fn intfc_state_get_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>)->PipeState
+        as Future_intfc_state_get_req
+    );
+    async_method!(
+        /// Read a descriptor into `buf` (at most `req.length` bytes)
+        fn desc_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_desc_cb_t>, req: DescRequest, buf: &'s mut crate::buf::Handle)->crate::Result<()>
+        as Future_desc_req
+    );
+    async_method!(
+        /// Get the state of the device
+        fn device_state_get_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>)->DeviceState
+        as Future_device_state_get_req
+    );
+    async_method!(
+        /// Select a device configuration
+        fn config_set_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>, config_value: u16)->crate::Result<()>
+        as Future_config_set_req
+    );
+    async_method!(
+        /// The LDD has handled an event (see [async_event_ind])
+        fn async_event_res(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_async_event_res
+    );
+    /// Return/release an event CB
+    fn async_event_ret(&self, cb: crate::cb::CbHandle<ffi::usbdi_misc_cb_t>);
+}
+/// Pipe channel operations for the USB host stack (USBD)
+///
+/// Transfers with invalid flags or requests are failed with `UDI_STAT_NOT_UNDERSTOOD` without calling the trait
+/// methods.
+pub trait UsbdPipe: 'static + crate::imc::ChannelInit + crate::async_trickery::CbContext
+{
+    async_method!(
+        /// Run an interrupt or bulk transfer, using `data` as the source or destination
+        fn intr_bulk_xfer_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_intr_bulk_xfer_cb_t>, xfer: IntrBulkXfer, data: &'s mut crate::buf::Handle)->crate::Result<()>
+        as Future_intr_bulk_xfer_req
+    );
+    async_method!(
+        /// Run a control transfer, using `data` for the data stage
+        fn control_xfer_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_control_xfer_cb_t>, xfer: ControlXfer, data: &'s mut crate::buf::Handle)->crate::Result<()>
+        as Future_control_xfer_req
+    );
+    async_method!(
+        /// Run an isochronous transfer, updating the status (and actual length) of each frame in `frames`
+        fn isoc_xfer_req(&'s self,
+            cb: crate::cb::CbRef<'s, ffi::usbdi_isoc_xfer_cb_t>,
+            xfer: IsocXfer,
+            data: &'s mut crate::buf::Handle,
+            frames: &'s mut [ffi::usbdi_isoc_frame_request_t]
+        )->crate::Result<()>
+        as Future_isoc_xfer_req
+    );
+    async_method!(
+        /// Abort all outstanding transfers on the pipe
+        fn pipe_abort_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_misc_cb_t>)->()
+        as Future_pipe_abort_req
+    );
+    async_method!(
+        /// Change the state of the pipe (invalid states are failed with `UDI_STAT_NOT_UNDERSTOOD`)
+        fn pipe_state_set_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: PipeState)->crate::Result<()>
+        as Future_pipe_state_set_req
+    );
+    async_method!(
+        /// Get the state of the pipe
+        fn pipe_state_get_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>)->PipeState
+        as Future_pipe_state_get_req
+    );
+    async_method!(
+        /// Change the state of the endpoint (invalid states are failed with `UDI_STAT_NOT_UNDERSTOOD`)
+        fn edpt_state_set_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>, state: PipeState)->crate::Result<()>
+        as Future_edpt_state_set_req
+    );
+    async_method!(
+        /// Get the state of the endpoint
+        fn edpt_state_get_req(&'s self, cb: crate::cb::CbRef<'s, ffi::usbdi_state_cb_t>)->crate::Result<PipeState>
+        as Future_edpt_state_get_req
+    );
+}
+
+
+struct MarkerLddIntfc;
+impl<T> crate::imc::ChannelHandler<MarkerLddIntfc> for T
+where
+    T: LddIntfc
+{
+    fn channel_bound(&self, params: &crate::ffi::imc::udi_channel_event_cb_t_params) {
+        unsafe {
+            ffi::usbdi_bind_req(params.parent_bound.bind_cb as *mut ffi::usbdi_misc_cb_t);
+        }
+    }
+}
+future_wrapper!(bind_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    n_intfc: crate::ffi::udi_index_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.bind_ack(cb, crate::Error::from_status(status).map(|()| n_intfc.0))
+} finally( () ) {
+    unsafe { crate::async_trickery::channel_event_complete::<T,ffi::usbdi_misc_cb_t>(cb, crate::ffi::UDI_OK as _) }
+});
+future_wrapper!(unbind_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.unbind_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(intfc_open_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    n_edpt: crate::ffi::udi_index_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.intfc_open_ack(cb, crate::Error::from_status(status).map(|()| n_edpt.0))
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(intfc_close_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.intfc_close_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(frame_number_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    frame_number: crate::ffi::udi_ubit32_t
+) val @ {
+    val.frame_number_ack(cb, frame_number)
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(device_speed_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    device_speed: crate::ffi::udi_ubit8_t
+) val @ {
+    val.device_speed_ack(cb, DeviceSpeed::from_raw(device_speed))
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(reset_device_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.reset_device_ack(cb)
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(intfc_abort_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.intfc_abort_ack(cb)
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(intfc_state_set_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_state_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.intfc_state_set_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(intfc_state_get_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.intfc_state_get_ack(cb, cb.pipe_state())
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(desc_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_desc_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.desc_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.desc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(device_state_get_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.device_state_get_ack(cb, DeviceState::from_raw(cb.state))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(config_set_ack_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.config_set_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(async_event_ind_op => <T as LddIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    async_event: crate::ffi::udi_ubit16_t
+) val @ {
+    val.async_event_ind(cb, async_event)
+} finally( () ) {
+    unsafe { ffi::usbdi_async_event_res(cb) }
+});
+map_ops_structure!{
+    ffi::usbdi_ldd_intfc_ops_t => LddIntfc,MarkerLddIntfc {
+        bind_ack_op,
+        unbind_ack_op,
+        intfc_open_ack_op,
+        intfc_close_ack_op,
+        frame_number_ack_op,
+        device_speed_ack_op,
+        reset_device_ack_op,
+        intfc_abort_ack_op,
+        intfc_state_set_ack_op,
+        intfc_state_get_ack_op,
+        desc_ack_op,
+        device_state_get_ack_op,
+        config_set_ack_op,
+        async_event_ind_op,
+    }
+    CBS {
+        ffi::usbdi_misc_cb_t,
+        ffi::usbdi_state_cb_t,
+        ffi::usbdi_desc_cb_t,
+    }
+}
+
+
+struct MarkerLddPipe;
+impl<T> crate::imc::ChannelHandler<MarkerLddPipe> for T
+where
+    T: LddPipe
+{
+}
+future_wrapper!(intr_bulk_xfer_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_intr_bulk_xfer_cb_t
+) val @ {
+    val.intr_bulk_xfer_ack(cb, Ok(()))
+} finally( () ) {
+    val.intr_bulk_xfer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(intr_bulk_xfer_nak_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_intr_bulk_xfer_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.intr_bulk_xfer_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.intr_bulk_xfer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(control_xfer_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_control_xfer_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.control_xfer_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.control_xfer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(isoc_xfer_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_isoc_xfer_cb_t
+) val @ {
+    val.isoc_xfer_ack(cb, Ok(()))
+} finally( () ) {
+    val.isoc_xfer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(isoc_xfer_nak_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_isoc_xfer_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.isoc_xfer_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.isoc_xfer_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(pipe_abort_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.pipe_abort_ack(cb)
+} finally( () ) {
+    val.misc_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(pipe_state_set_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_state_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.pipe_state_set_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(pipe_state_get_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.pipe_state_get_ack(cb, cb.pipe_state())
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(edpt_state_set_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_state_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    val.edpt_state_set_ack(cb, crate::Error::from_status(status))
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+future_wrapper!(edpt_state_get_ack_op => <T as LddPipe>(
+    cb: *mut ffi::usbdi_state_cb_t,
+    status: crate::ffi::udi_status_t
+) val @ {
+    let state = crate::Error::from_status(status).and_then(|()| cb.pipe_state());
+    val.edpt_state_get_ack(cb, state)
+} finally( () ) {
+    val.state_ret(unsafe { crate::cb::CbHandle::from_raw(cb) });
+});
+map_ops_structure!{
+    ffi::usbdi_ldd_pipe_ops_t => LddPipe,MarkerLddPipe {
+        intr_bulk_xfer_ack_op,
+        intr_bulk_xfer_nak_op,
+        control_xfer_ack_op,
+        isoc_xfer_ack_op,
+        isoc_xfer_nak_op,
+        pipe_abort_ack_op,
+        pipe_state_set_ack_op,
+        pipe_state_get_ack_op,
+        edpt_state_set_ack_op,
+        edpt_state_get_ack_op,
+    }
+    CBS {
+        ffi::usbdi_intr_bulk_xfer_cb_t,
+        ffi::usbdi_control_xfer_cb_t,
+        ffi::usbdi_isoc_xfer_cb_t,
+        ffi::usbdi_misc_cb_t,
+        ffi::usbdi_state_cb_t,
+    }
+}
+
+
+struct MarkerUsbdIntfc;
+impl<T> crate::imc::ChannelHandler<MarkerUsbdIntfc> for T
+where
+    T: UsbdIntfc
+{
+}
+future_wrapper!(bind_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.bind_req(cb)
+} finally( res ) {
+    let n_intfc = crate::ffi::udi_index_t(*res.as_ref().unwrap_or(&0));
+    unsafe { ffi::usbdi_bind_ack(cb, n_intfc, crate::Error::to_status(res.map(|_| ()))) }
+});
+future_wrapper!(unbind_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.unbind_req(cb)
+} finally( res ) {
+    unsafe { ffi::usbdi_unbind_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(intfc_open_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    alternate_intfc: crate::ffi::udi_ubit8_t,
+    open_flag: crate::ffi::udi_ubit8_t
+) val @ {
+    val.intfc_open_req(cb, alternate_intfc, open_flag)
+} finally( res ) {
+    let n_edpt = crate::ffi::udi_index_t(*res.as_ref().unwrap_or(&0));
+    unsafe { ffi::usbdi_intfc_open_ack(cb, n_edpt, crate::Error::to_status(res.map(|_| ()))) }
+});
+future_wrapper!(intfc_close_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.intfc_close_req(cb)
+} finally( res ) {
+    unsafe { ffi::usbdi_intfc_close_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(frame_number_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.frame_number_req(cb)
+} finally( frame_number ) {
+    unsafe { ffi::usbdi_frame_number_ack(cb, frame_number) }
+});
+future_wrapper!(device_speed_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.device_speed_req(cb)
+} finally( speed ) {
+    unsafe { ffi::usbdi_device_speed_ack(cb, speed.to_raw()) }
+});
+future_wrapper!(reset_device_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.reset_device_req(cb)
+} finally( () ) {
+    unsafe { ffi::usbdi_reset_device_ack(cb) }
+});
+future_wrapper!(intfc_abort_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.intfc_abort_req(cb)
+} finally( () ) {
+    unsafe { ffi::usbdi_intfc_abort_ack(cb) }
+});
+future_wrapper!(intfc_state_set_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    let state = cb.pipe_state();
+    async move {
+        match state {
+        Ok(state) => val.intfc_state_set_req(cb, state).await,
+        Err(e) => Err(e),
+        }
+    }
+} finally( res ) {
+    unsafe { ffi::usbdi_intfc_state_set_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(intfc_state_get_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.intfc_state_get_req(cb)
+} finally( state ) {
+    unsafe {
+        (*cb).state = state.to_raw();
+        ffi::usbdi_intfc_state_get_ack(cb)
+    }
+});
+future_wrapper!(desc_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_desc_cb_t
+) val @ {
+    let req = cb.request();
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let buf = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).desc_buf) };
+    val.desc_req(cb, req, buf)
+} finally( res ) {
+    unsafe { ffi::usbdi_desc_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(device_state_get_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.device_state_get_req(cb)
+} finally( state ) {
+    unsafe {
+        (*cb).state = state.to_raw();
+        ffi::usbdi_device_state_get_ack(cb)
+    }
+});
+future_wrapper!(config_set_req_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t,
+    config_value: crate::ffi::udi_ubit16_t
+) val @ {
+    val.config_set_req(cb, config_value)
+} finally( res ) {
+    unsafe { ffi::usbdi_config_set_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(async_event_res_op => <T as UsbdIntfc>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.async_event_res(cb)
+} finally( () ) {
+    val.async_event_ret(unsafe { crate::cb::CbHandle::from_raw(cb) })
+});
+map_ops_structure!{
+    ffi::usbdi_usbd_intfc_ops_t => UsbdIntfc,MarkerUsbdIntfc {
+        bind_req_op,
+        unbind_req_op,
+        intfc_open_req_op,
+        intfc_close_req_op,
+        frame_number_req_op,
+        device_speed_req_op,
+        reset_device_req_op,
+        intfc_abort_req_op,
+        intfc_state_set_req_op,
+        intfc_state_get_req_op,
+        desc_req_op,
+        device_state_get_req_op,
+        config_set_req_op,
+        async_event_res_op,
+    }
+    CBS {
+        ffi::usbdi_misc_cb_t,
+        ffi::usbdi_state_cb_t,
+        ffi::usbdi_desc_cb_t,
+    }
+}
+
+
+struct MarkerUsbdPipe;
+impl<T> crate::imc::ChannelHandler<MarkerUsbdPipe> for T
+where
+    T: UsbdPipe
+{
+}
+future_wrapper!(intr_bulk_xfer_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_intr_bulk_xfer_cb_t
+) val @ {
+    let xfer = cb.request();
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let data = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).data_buf) };
+    async move {
+        match xfer {
+        Ok(xfer) => val.intr_bulk_xfer_req(cb, xfer, data).await,
+        Err(e) => Err(e),
+        }
+    }
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::usbdi_intr_bulk_xfer_ack(cb) },
+    Err(e) => unsafe { ffi::usbdi_intr_bulk_xfer_nak(cb, e.into_inner()) },
+    }
+});
+future_wrapper!(control_xfer_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_control_xfer_cb_t
+) val @ {
+    let xfer = cb.request();
+    // SAFE: The CB is owned by this request until it is acknowledged
+    let data = unsafe { crate::buf::Handle::from_mut(&mut (*cb.to_raw()).data_buf) };
+    async move {
+        match xfer {
+        Ok(xfer) => val.control_xfer_req(cb, xfer, data).await,
+        Err(e) => Err(e),
+        }
+    }
+} finally( res ) {
+    unsafe { ffi::usbdi_control_xfer_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(isoc_xfer_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_isoc_xfer_cb_t
+) val @ {
+    let xfer = cb.request();
+    // SAFE: The CB is owned by this request until it is acknowledged, and the frame array is `frame_count` long
+    let (data, frames) = unsafe { (crate::buf::Handle::from_mut(&mut (*cb.to_raw()).data_buf), frames_mut(cb.to_raw())) };
+    async move {
+        match xfer {
+        Ok(xfer) => val.isoc_xfer_req(cb, xfer, data, frames).await,
+        Err(e) => Err(e),
+        }
+    }
+} finally( res ) {
+    match res {
+    Ok(()) => unsafe { ffi::usbdi_isoc_xfer_ack(cb) },
+    Err(e) => unsafe { ffi::usbdi_isoc_xfer_nak(cb, e.into_inner()) },
+    }
+});
+future_wrapper!(pipe_abort_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_misc_cb_t
+) val @ {
+    val.pipe_abort_req(cb)
+} finally( () ) {
+    unsafe { ffi::usbdi_pipe_abort_ack(cb) }
+});
+future_wrapper!(pipe_state_set_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    let state = cb.pipe_state();
+    async move {
+        match state {
+        Ok(state) => val.pipe_state_set_req(cb, state).await,
+        Err(e) => Err(e),
+        }
+    }
+} finally( res ) {
+    unsafe { ffi::usbdi_pipe_state_set_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(pipe_state_get_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.pipe_state_get_req(cb)
+} finally( state ) {
+    unsafe {
+        (*cb).state = state.to_raw();
+        ffi::usbdi_pipe_state_get_ack(cb)
+    }
+});
+future_wrapper!(edpt_state_set_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    let state = cb.pipe_state();
+    async move {
+        match state {
+        Ok(state) => val.edpt_state_set_req(cb, state).await,
+        Err(e) => Err(e),
+        }
+    }
+} finally( res ) {
+    unsafe { ffi::usbdi_edpt_state_set_ack(cb, crate::Error::to_status(res)) }
+});
+future_wrapper!(edpt_state_get_req_op => <T as UsbdPipe>(
+    cb: *mut ffi::usbdi_state_cb_t
+) val @ {
+    val.edpt_state_get_req(cb)
+} finally( res ) {
+    unsafe {
+        if let Ok(state) = res {
+            (*cb).state = state.to_raw();
+        }
+        ffi::usbdi_edpt_state_get_ack(cb, crate::Error::to_status(res.map(|_| ())))
+    }
+});
+map_ops_structure!{
+    ffi::usbdi_usbd_pipe_ops_t => UsbdPipe,MarkerUsbdPipe {
+        intr_bulk_xfer_req_op,
+        control_xfer_req_op,
+        isoc_xfer_req_op,
+        pipe_abort_req_op,
+        pipe_state_set_req_op,
+        pipe_state_get_req_op,
+        edpt_state_set_req_op,
+        edpt_state_get_req_op,
+    }
+    CBS {
+        ffi::usbdi_intr_bulk_xfer_cb_t,
+        ffi::usbdi_control_xfer_cb_t,
+        ffi::usbdi_isoc_xfer_cb_t,
+        ffi::usbdi_misc_cb_t,
+        ffi::usbdi_state_cb_t,
+    }
+}
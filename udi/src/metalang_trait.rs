@@ -9,6 +9,8 @@ pub trait Metalanguage
     unsafe fn get_ops(&self, ops_idx: ::udi_sys::udi_index_t, ops_vector: crate::ffi::udi_ops_vector_t) -> Option<&'static dyn MetalangOpsHandler>;
     /// Obtain a metalanguage-specific definition of a CB
     fn get_cb(&self, cb_idx: ::udi_sys::udi_index_t) -> Option<&dyn MetalangCbHandler>;
+    /// MEI description of the metalanguage, if it is called through `udi_mei_call` (see [crate::define_metalanguage])
+    fn mei_init(&self) -> Option<&'static crate::ffi::mei::udi_mei_init_t> { None }
 }
 /// Trait used for dynamic dispatch of an `ops` structure
 /// 
@@ -60,11 +62,14 @@ pub unsafe trait MetalangCb
     fn get_chain(&mut self) -> Option<&mut *mut crate::ffi::udi_cb_t> { None }
 }
 
+#[doc(hidden)]
+#[macro_export]
 macro_rules! impl_metalanguage
 {
     (
         static $spec_name:ident;
         NAME $name:ident ;
+        $( MEI $mei_name:ident ; )?
         OPS $( $ops_idx:literal => $ops_ty:ty),* $(,)? ;
         CBS $( $cb_idx:literal => $cb_ty:ty $(: BUF $buf_fld:ident)? $(: INLINE_DATA $inline_data_fld:ident)? $(: CHAIN $chain_fld:ident)? ),* $(,)? ;
     ) => {
@@ -101,7 +106,8 @@ macro_rules! impl_metalanguage
                         $(
                         unsafe fn get_inline_data<'a>(&self, cb: &'a *mut $crate::ffi::udi_cb_t) -> Option<&'a mut *mut $crate::ffi::c_void> {
                             let cb = &mut *(*cb as *mut $crate::ffi::udi_cb_t as *mut $cb_ty);
-                            Some(&mut cb.$inline_data_fld)
+                            // The field may be a typed pointer (e.g. `cdb_ptr`), the environment only needs the address
+                            Some(&mut *(&mut cb.$inline_data_fld as *mut *mut _ as *mut *mut $crate::ffi::c_void))
                         }
                         )?
                         $(
@@ -116,6 +122,11 @@ macro_rules! impl_metalanguage
                 _ => None,
                 }
             }
+            $(
+            fn mei_init(&self) -> Option<&'static $crate::ffi::mei::udi_mei_init_t> {
+                Some(&$mei_name)
+            }
+            )?
         }
         $(
         impl $crate::metalang_trait::MetalangOpsHandler for $ops_ty {
@@ -160,6 +171,8 @@ macro_rules! impl_metalanguage
 /// Helper to generate code to create a ops structure based on a trait
 /// 
 /// See [future_wrapper]
+#[doc(hidden)]
+#[macro_export]
 macro_rules! map_ops_structure {
     (
         $struct:path => $trait:path,$marker:ty {
@@ -170,28 +183,59 @@ macro_rules! map_ops_structure {
         }
         $( EXTRA_OP $extra_op:ident );*
     ) => {
-        impl<T,CbList> crate::OpsStructure<$struct, T,CbList>
+        unsafe impl<T,CbList> $crate::OpsFor<T,CbList> for $struct
         where
             T: $trait,
-            $( CbList: crate::HasCb<$cb>, )*
+            $( CbList: $crate::HasCb<$cb>, )*
         {
-            /// Obtain the amount of scratch space required for ops in this structure
-            pub const fn scratch_requirement() -> usize {
-                let v = crate::imc::task_size::<T, $marker>();
-                $(let v = crate::const_max(v, $name::task_size::<T>());)*
-                $(let v = crate::const_max(v, $extra_op::task_size::<T>());)*
+            const SCRATCH_REQUIREMENT: usize = {
+                let v = $crate::imc::task_size::<T, $marker>();
+                $(let v = $crate::const_max(v, $name::task_size::<T>());)*
+                $(let v = $crate::const_max(v, $extra_op::task_size::<T>());)*
                 v
+            };
+            const OPS: Self = Self {
+                channel_event_ind_op: $crate::imc::channel_event_ind_op::<T, $marker>,
+                $( $name: $name::<T>, )*
+            };
+        }
+        
+    };
+}
+
+/// Define a bit-flags structure for a metalanguage field
+macro_rules! def_flags {
+    (
+        $(#[$a:meta])* $name:ident: $t:ty {
+            $( $(#[$fa:meta])* $flag:ident = $val:expr, )*
+        }
+    ) => {
+        $(#[$a])*
+        #[derive(Debug,Default,Copy,Clone,PartialEq,Eq)]
+        pub struct $name($t);
+        impl $name {
+            /// No flags set
+            pub const NONE: Self = $name(0);
+            $( $(#[$fa])* pub const $flag: Self = $name($val); )*
+
+            /// Construct from the raw value
+            pub const fn from_raw(v: $t) -> Self {
+                $name(v)
             }
-            /// Generate an ops structure for a driver
-            /// SAFETY: Caller must ensure that the ops are only used with matching `T` region
-            /// SAFETY: The scratch size must be >= value returned by [Self::scratch_requirement]
-            pub const unsafe fn for_driver() -> $struct {
-                $struct {
-                    channel_event_ind_op: crate::imc::channel_event_ind_op::<T, $marker>,
-                    $( $name: $name::<T>, )*
-                }
+            /// Get the raw value
+            pub const fn to_raw(self) -> $t {
+                self.0
+            }
+            /// Check if all of the flags in `other` are set
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+        }
+        impl ::core::ops::BitOr for $name {
+            type Output = Self;
+            fn bitor(self, rhs: Self) -> Self {
+                $name(self.0 | rhs.0)
             }
         }
-        
     };
-}
\ No newline at end of file
+}
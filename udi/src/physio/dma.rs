@@ -6,9 +6,19 @@ use ::udi_sys::physio::udi_dma_constraints_attr_spec_t;
 use ::udi_sys::physio::udi_dma_constraints_t;
 use ::udi_sys::physio::udi_dma_handle_t;
 
+mod constraints;
+mod plan;
+mod ring;
+mod scgth;
+
+pub use self::constraints::{Attribute, ConstraintsBuilder, ConstraintsAttrs, ConstraintsError, ScgthFormat};
+pub use self::plan::{limits, Limits, AllocPlan, PlanError};
+pub use self::ring::{DmaRing, DmaSwap};
+pub use self::scgth::{NotDriverMapped, ScgthIter, ScatterListBuilder, ScatterListError};
+
 #[derive(Debug)]
 /// Handle to a collection of DMA constraints
-pub struct DmaConstraints(udi_dma_constraints_t);
+pub struct DmaConstraints(udi_dma_constraints_t, ConstraintsBuilder, [bool; constraints::NUM_ATTRS]);
 impl Drop for DmaConstraints
 {
     fn drop(&mut self) {
@@ -26,11 +36,33 @@ impl DmaConstraints
 {
     /// Create a null/invalid set of DMA constraints
     pub fn null() -> DmaConstraints {
-        DmaConstraints(::udi_sys::physio::UDI_NULL_DMA_CONSTRAINTS)
+        DmaConstraints(::udi_sys::physio::UDI_NULL_DMA_CONSTRAINTS, ConstraintsBuilder::new(), [false; constraints::NUM_ATTRS])
     }
     /// Construct a handle from a provided raw pointer. The pointer must be valid (i.e. obtained from the environment)
+    ///
+    /// The attribute values of the raw handle aren't known, so [DmaConstraints::is_known] is false until they are set
+    /// or reset
     pub unsafe fn from_raw(v: udi_dma_constraints_t) -> Self {
-        DmaConstraints(v)
+        DmaConstraints(v, ConstraintsBuilder::new(), [true; constraints::NUM_ATTRS])
+    }
+    /// Release ownership of the raw handle (e.g. when passing the constraints to a child in `udi_bus_bind_ack`)
+    pub fn into_raw(self) -> udi_dma_constraints_t {
+        let rv = self.0;
+        ::core::mem::forget(self);
+        rv
+    }
+
+    /// Get the value of an attribute set through this handle
+    ///
+    /// The environment doesn't report attribute values, so this is `None` for attributes left at their defaults (and
+    /// for any not set since [DmaConstraints::from_raw], see [DmaConstraints::is_known]).
+    pub fn get(&self, attr: Attribute) -> Option<u32> {
+        self.1.get(attr)
+    }
+    /// Check if the value of an attribute is known (i.e. it is not from a [DmaConstraints::from_raw] handle, or has
+    /// been set or reset since)
+    pub fn is_known(&self, attr: Attribute) -> bool {
+        !self.2[ConstraintsBuilder::index(attr)]
     }
 
     /// Reset the specifided attribute to its default (usually the least restrictive)
@@ -39,9 +71,21 @@ impl DmaConstraints
         unsafe {
             ::udi_sys::physio::udi_dma_constraints_attr_reset(self.0, attr_type)
         }
+        if let Some(attr) = Attribute::from_raw(attr_type) {
+            self.1.set_value(attr, None);
+            self.2[ConstraintsBuilder::index(attr)] = false;
+        }
+    }
+
+    /// Reset the specifided attribute to its default (typed version of [DmaConstraints::reset])
+    pub fn reset_attr(&mut self, attr: Attribute)
+    {
+        self.reset(attr.to_raw())
     }
 
     /// Set a collection of attributes
+    /// 
+    /// Prefer building the list with [ConstraintsBuilder], which checks it for consistency first
     pub fn set<'a>(
         &'a mut self,
         gcb: crate::cb::CbRef<::udi_sys::udi_cb_t>,
@@ -64,7 +108,16 @@ impl DmaConstraints
                 match res {
                 crate::async_trickery::WaitRes::PointerResult(v) => match v
                     {
-                    Ok(p) => { self.0 = p as _; Ok(())},
+                    Ok(p) => {
+                        self.0 = p as _;
+                        for a in attrs {
+                            if let Some(attr) = Attribute::from_raw(a.attr_type) {
+                                self.1.set_value(attr, Some(a.attr_value));
+                                self.2[ConstraintsBuilder::index(attr)] = false;
+                            }
+                        }
+                        Ok(())
+                        },
                     Err(e) => Err(e),
                     },
                 _ => panic!(),
@@ -325,6 +378,8 @@ impl DmaBuf {
 }
 
 /// A handle to a DMA-allocated block of memory
+/// 
+/// Dropping this handle frees the memory and the scatter-gather list (via `udi_dma_free`)
 pub struct DmaAlloc {
     /// DMA allocation handle
     handle: DmaHandle,
@@ -338,10 +393,6 @@ pub struct DmaAlloc {
     /// endianess of the values in this allocation
     pub must_swap: bool,
 }
-impl Drop for DmaAlloc {
-    fn drop(&mut self) {
-    }
-}
 impl DmaAlloc {
     /// Allocate DMA-able memory for shared device structures
     /// 
@@ -416,6 +467,8 @@ impl DmaAlloc {
                     let single_element = gap_flags & 1 != 0;
                     let must_swap = gap_flags & 2 != 0;
                     let gap_size = gap_flags >> 2;
+                    // Without `UDI_MEM_NOWAIT` the environment must wait for memory instead of failing
+                    assert!(!new_ptr.is_null(), "`udi_dma_mem_alloc` failed without UDI_MEM_NOWAIT");
                     DmaAlloc {
                         handle: DmaHandle(new_ptr as _),
                         scgth: unsafe { ScGth::from_raw(scgth as _) },
@@ -463,17 +516,32 @@ impl DmaAlloc {
         self.handle.mem_barrier();
     }
 
-    // TODO: `mem_to_buf`
-    #[cfg(any())]
-    /// Free the DMA handle, and copy the data within `src_range` to the output buffer (as the full buffer contents)
+    /// Free the DMA handle, and copy `len` bytes at `offset` in the allocation into `dst_buf` (replacing its contents)
+    /// 
+    /// If `dst_buf` is empty (null), the environment allocates a new buffer.
     pub fn mem_to_buf(
         self,
         gcb: crate::cb::CbRef<::udi_sys::udi_cb_t>,
-        src_range: impl ::core::ops::RangeBounds<usize>,
+        offset: usize,
+        len: usize,
         dst_buf: crate::buf::Handle
-    ) -> Future<Output=crate::buf::Handle>
+    ) -> impl Future<Output=crate::buf::Handle>
     {
-        let (offset, len) = range_to_ofs_len(self.len, range);
+        unsafe extern "C" fn callback(gcb: *mut ::udi_sys::udi_cb_t, new_dst_buf: *mut ::udi_sys::udi_buf_t) {
+            crate::async_trickery::signal_waiter(gcb, crate::async_trickery::WaitRes::Pointer(new_dst_buf as _))
+        }
+        // The environment frees the handle as part of this call, so don't run the destructor
+        let handle = ::core::mem::ManuallyDrop::new(self).handle.0;
+        let dst_buf = dst_buf.into_raw();
+        crate::async_trickery::wait_task(gcb,
+            move |gcb| unsafe {
+                ffi::udi_dma_mem_to_buf(callback, gcb, handle, offset, len, dst_buf)
+            },
+            |res| {
+                let crate::async_trickery::WaitRes::Pointer(p) = res else { panic!() };
+                unsafe { crate::buf::Handle::from_raw(p as _) }
+            },
+        )
     }
 }
 
@@ -485,10 +553,13 @@ impl<'a> ScGth<'a> {
         ScGth(&*p)
     }
     /// Get the raw scatter-gather table data
+    ///
+    /// See [ScGth::iter] for a format-independent view of the data elements.
     pub fn raw_entries(&self) -> ScgthRaw {
+        assert!(self.is_driver_mapped(), "Scatter-gather list {:p} is not driver-mapped", self.0);
         let len = self.0.scgth_num_elements as _;
         unsafe {
-            match self.0.scgth_format {
+            match self.0.scgth_format & (ffi::UDI_SCGTH_32|ffi::UDI_SCGTH_64) {
             ffi::UDI_SCGTH_32 => ScgthRaw::Bits32( ::core::slice::from_raw_parts(self.0.scgth_elements.el32p, len) ),
             ffi::UDI_SCGTH_64 => ScgthRaw::Bits64( ::core::slice::from_raw_parts(self.0.scgth_elements.el64p, len) ),
             _ => panic!("Malformed `scgth_format` {} ({:p})", self.0.scgth_format, self.0),
@@ -0,0 +1,352 @@
+//! Typed construction and validation of DMA constraint attribute lists
+use ::udi_sys::physio as ffi;
+use ::udi_sys::physio::udi_dma_constraints_attr_spec_t;
+use super::Endianness;
+
+macro_rules! attributes {
+    ( $( $(#[$a:meta])* $name:ident = $code:ident, )* ) => {
+        /// A DMA constraints attribute code (`UDI_DMA_*`)
+        #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+        #[repr(u8)]
+        pub enum Attribute {
+            $( $(#[$a])* $name = ffi::$code, )*
+        }
+        impl Attribute {
+            /// All attribute codes, in numeric order
+            pub const ALL: &'static [Attribute] = &[ $(Attribute::$name,)* ];
+            /// Get the raw `udi_dma_constraints_attr_t` value
+            pub fn to_raw(self) -> ffi::udi_dma_constraints_attr_t {
+                self as u8
+            }
+            /// Look up an attribute from its raw code
+            pub fn from_raw(v: ffi::udi_dma_constraints_attr_t) -> Option<Self> {
+                match v {
+                $( ffi::$code => Some(Attribute::$name), )*
+                _ => None,
+                }
+            }
+        }
+    };
+}
+attributes!{
+    /// Convenience: Sets both `DataAddressableBits` and `ScgthAddressableBits`
+    AddressableBits = UDI_DMA_ADDRESSABLE_BITS,
+    /// Convenience: Sets both `ElementAlignmentBits` and `ScgthAlignmentBits`
+    AlignmentBits = UDI_DMA_ALIGNMENT_BITS,
+    /// Number of address bits the device can generate for data
+    DataAddressableBits = UDI_DMA_DATA_ADDRESSABLE_BITS,
+    /// The whole request must be mapped at once
+    NoPartial = UDI_DMA_NO_PARTIAL,
+    /// Maximum number of elements in one scatter-gather list
+    ScgthMaxElements = UDI_DMA_SCGTH_MAX_ELEMENTS,
+    /// Acceptable scatter-gather list formats
+    ScgthFormat = UDI_DMA_SCGTH_FORMAT,
+    /// Endianness of the scatter-gather list
+    ScgthEndianness = UDI_DMA_SCGTH_ENDIANNESS,
+    /// Number of address bits the device can generate for the scatter-gather list
+    ScgthAddressableBits = UDI_DMA_SCGTH_ADDRESSABLE_BITS,
+    /// Maximum number of segments in a scatter-gather list
+    ScgthMaxSegments = UDI_DMA_SCGTH_MAX_SEGMENTS,
+    /// Required alignment of scatter-gather segments
+    ScgthAlignmentBits = UDI_DMA_SCGTH_ALIGNMENT_BITS,
+    /// Maximum number of elements in each scatter-gather segment
+    ScgthMaxElPerSeg = UDI_DMA_SCGTH_MAX_EL_PER_SEG,
+    /// Bytes reserved before each scatter-gather segment
+    ScgthPrefixBytes = UDI_DMA_SCGTH_PREFIX_BYTES,
+    /// Required alignment of each element's address
+    ElementAlignmentBits = UDI_DMA_ELEMENT_ALIGNMENT_BITS,
+    /// Number of bits in each element's length (the maximum length is `2^bits - 1`)
+    ElementLengthBits = UDI_DMA_ELEMENT_LENGTH_BITS,
+    /// Element lengths must be a multiple of `2^bits`
+    ElementGranularityBits = UDI_DMA_ELEMENT_GRANULARITY_BITS,
+    /// Number of fixed address bits
+    AddrFixedBits = UDI_DMA_ADDR_FIXED_BITS,
+    /// How the fixed address bits are applied
+    AddrFixedType = UDI_DMA_ADDR_FIXED_TYPE,
+    /// Fixed address value (low 32 bits)
+    AddrFixedValueLo = UDI_DMA_ADDR_FIXED_VALUE_LO,
+    /// Fixed address value (high 32 bits)
+    AddrFixedValueHi = UDI_DMA_ADDR_FIXED_VALUE_HI,
+    /// The device accesses memory sequentially
+    Sequential = UDI_DMA_SEQUENTIAL,
+    /// Inbound transfers may overrun by up to `2^bits` bytes
+    SlopInBits = UDI_DMA_SLOP_IN_BITS,
+    /// Outbound transfers may overrun by up to `2^bits` bytes
+    SlopOutBits = UDI_DMA_SLOP_OUT_BITS,
+    /// Additional outbound overrun in bytes
+    SlopOutExtra = UDI_DMA_SLOP_OUT_EXTRA,
+    /// Overrun will not cross a `2^bits` boundary
+    SlopBarrierBits = UDI_DMA_SLOP_BARRIER_BITS,
+}
+pub(super) const NUM_ATTRS: usize = Attribute::ALL.len();
+
+/// Acceptable formats for the scatter-gather list
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ScgthFormat {
+    /// 32-bit addresses only (`udi_scgth_element_32_t`)
+    Bits32,
+    /// 64-bit addresses only (`udi_scgth_element_64_t`)
+    Bits64,
+    /// The driver can handle either format
+    Either,
+}
+
+/// Error from [ConstraintsBuilder::build]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ConstraintsError {
+    /// An attribute value is outside of its legal range
+    OutOfRange {
+        /// Offending attribute
+        attr: Attribute,
+        /// Requested value
+        value: u32,
+        /// Largest legal value
+        max: u32,
+    },
+    /// Two attributes can't both be satisfied
+    Conflict(Attribute, Attribute),
+    /// `ScgthFormat` was set (e.g. with only mapping flags) without an element width, see [ConstraintsBuilder::scgth_format]
+    MissingScgthWidth,
+}
+impl ::core::fmt::Display for ConstraintsError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        match *self {
+        ConstraintsError::OutOfRange { attr, value, max } => write!(f, "{:?} = {} exceeds {}", attr, value, max),
+        ConstraintsError::Conflict(a, b) => write!(f, "{:?} conflicts with {:?}", a, b),
+        ConstraintsError::MissingScgthWidth => f.write_str("scatter-gather format has no element width"),
+        }
+    }
+}
+
+/// Typed builder for a DMA constraints attribute list, validated before being passed to [super::DmaConstraints::set]
+///
+/// ```
+/// use udi::physio::dma::{ConstraintsBuilder, ConstraintsError, ScgthFormat, Attribute, Endianness};
+/// let attrs = ConstraintsBuilder::new()
+///     .addressable_bits(32)
+///     .alignment_bits(4)
+///     .max_scgth_elements(16)
+///     .scgth_format(ScgthFormat::Bits32)
+///     .scgth_endianness(Endianness::Little)
+///     .build().unwrap();
+/// assert_eq!(attrs.len(), 5);
+/// // A 64-bit capable device can't use 32-bit only scatter-gather lists
+/// let err = ConstraintsBuilder::new().addressable_bits(64).scgth_format(ScgthFormat::Bits32).build().unwrap_err();
+/// assert_eq!(err, ConstraintsError::Conflict(Attribute::ScgthFormat, Attribute::AddressableBits));
+/// ```
+#[derive(Debug,Clone,Default)]
+pub struct ConstraintsBuilder {
+    values: [Option<u32>; NUM_ATTRS],
+}
+impl ConstraintsBuilder {
+    /// Create an empty builder (all attributes left at the environment defaults)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an attribute using its raw value
+    pub fn attr(mut self, attr: Attribute, value: u32) -> Self {
+        self.values[Self::index(attr)] = Some(value);
+        self
+    }
+    /// Get the currently set value of an attribute
+    pub fn get(&self, attr: Attribute) -> Option<u32> {
+        self.values[Self::index(attr)]
+    }
+    /// Record (or clear) a value in-place, used to track the values applied to a [super::DmaConstraints]
+    pub(crate) fn set_value(&mut self, attr: Attribute, value: Option<u32>) {
+        self.values[Self::index(attr)] = value;
+    }
+    pub(super) fn index(attr: Attribute) -> usize {
+        Attribute::ALL.iter().position(|&a| a == attr).unwrap()
+    }
+
+    /// Number of address bits the device can generate (for both data and the scatter-gather list)
+    pub fn addressable_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::AddressableBits, bits as u32)
+    }
+    /// Number of address bits the device can generate for data
+    pub fn data_addressable_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::DataAddressableBits, bits as u32)
+    }
+    /// Required alignment (as a power of two) of both data elements and scatter-gather segments
+    pub fn alignment_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::AlignmentBits, bits as u32)
+    }
+    /// Require that the entire request is mapped at once
+    pub fn no_partial(self, no_partial: bool) -> Self {
+        self.attr(Attribute::NoPartial, no_partial as u32)
+    }
+    /// Maximum number of elements in one scatter-gather list (1 for devices without scatter-gather support)
+    pub fn max_scgth_elements(self, count: u16) -> Self {
+        self.attr(Attribute::ScgthMaxElements, count as u32)
+    }
+    /// Acceptable scatter-gather list format
+    pub fn scgth_format(self, format: ScgthFormat) -> Self {
+        let v = match format {
+            ScgthFormat::Bits32 => ffi::UDI_SCGTH_32,
+            ScgthFormat::Bits64 => ffi::UDI_SCGTH_64,
+            ScgthFormat::Either => ffi::UDI_SCGTH_32 | ffi::UDI_SCGTH_64,
+            };
+        let flags = self.get(Attribute::ScgthFormat).unwrap_or(0) & !((ffi::UDI_SCGTH_32 | ffi::UDI_SCGTH_64) as u32);
+        self.attr(Attribute::ScgthFormat, flags | v as u32)
+    }
+    /// The scatter-gather list must be mapped for device access (it is read by the device)
+    ///
+    /// The element width must also be chosen, with [ConstraintsBuilder::scgth_format]
+    pub fn scgth_dma_mapped(self) -> Self {
+        let v = self.get(Attribute::ScgthFormat).unwrap_or(0);
+        self.attr(Attribute::ScgthFormat, v | ffi::UDI_SCGTH_DMA_MAPPED as u32)
+    }
+    /// The scatter-gather list must be mapped for driver access
+    ///
+    /// The element width must also be chosen, with [ConstraintsBuilder::scgth_format]
+    pub fn scgth_driver_mapped(self) -> Self {
+        let v = self.get(Attribute::ScgthFormat).unwrap_or(0);
+        self.attr(Attribute::ScgthFormat, v | ffi::UDI_SCGTH_DRIVER_MAPPED as u32)
+    }
+    /// Endianness of the scatter-gather list, as seen by the device
+    pub fn scgth_endianness(self, endian: Endianness) -> Self {
+        self.attr(Attribute::ScgthEndianness, endian.to_flags() as u32)
+    }
+    /// Number of address bits the device can generate when reading the scatter-gather list
+    pub fn scgth_addressable_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::ScgthAddressableBits, bits as u32)
+    }
+    /// Maximum number of segments in a scatter-gather list
+    pub fn max_scgth_segments(self, count: u16) -> Self {
+        self.attr(Attribute::ScgthMaxSegments, count as u32)
+    }
+    /// Required alignment (as a power of two) of scatter-gather segments
+    pub fn scgth_alignment_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::ScgthAlignmentBits, bits as u32)
+    }
+    /// Maximum number of elements in each scatter-gather segment
+    pub fn max_scgth_el_per_seg(self, count: u16) -> Self {
+        self.attr(Attribute::ScgthMaxElPerSeg, count as u32)
+    }
+    /// Bytes to reserve before each scatter-gather segment
+    pub fn scgth_prefix_bytes(self, bytes: u16) -> Self {
+        self.attr(Attribute::ScgthPrefixBytes, bytes as u32)
+    }
+    /// Required alignment (as a power of two) of each element's address
+    pub fn element_alignment_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::ElementAlignmentBits, bits as u32)
+    }
+    /// Number of bits in the device's element length field
+    pub fn element_length_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::ElementLengthBits, bits as u32)
+    }
+    /// Element lengths must be a multiple of `2^bits`
+    pub fn element_granularity_bits(self, bits: u8) -> Self {
+        self.attr(Attribute::ElementGranularityBits, bits as u32)
+    }
+    /// The device accesses memory sequentially
+    pub fn sequential(self, sequential: bool) -> Self {
+        self.attr(Attribute::Sequential, sequential as u32)
+    }
+
+    /// Check the attributes for consistency, and produce the list for [super::DmaConstraints::set]
+    pub fn build(&self) -> Result<ConstraintsAttrs,ConstraintsError> {
+        use self::Attribute as A;
+        let get = |a| self.get(a);
+        // Range checks
+        for &a in Attribute::ALL {
+            let Some(value) = get(a) else { continue };
+            let max = match a {
+                A::AddressableBits | A::DataAddressableBits | A::ScgthAddressableBits | A::AddrFixedBits => 64,
+                A::AlignmentBits | A::ScgthAlignmentBits | A::ElementAlignmentBits | A::ElementLengthBits | A::ElementGranularityBits
+                | A::SlopInBits | A::SlopOutBits | A::SlopBarrierBits => 32,
+                A::NoPartial | A::Sequential => 1,
+                A::ScgthMaxElements | A::ScgthMaxSegments | A::ScgthMaxElPerSeg | A::ScgthPrefixBytes => 0xFFFF,
+                _ => continue,
+                };
+            if value > max {
+                return Err(ConstraintsError::OutOfRange { attr: a, value, max });
+            }
+        }
+        // Pick the more specific attribute if set, falling back to the convenience one
+        let either = |specific, convenience| match get(specific) {
+            Some(v) => Some((specific, v)),
+            None => get(convenience).map(|v| (convenience, v)),
+            };
+        let conflict = |a, b| Err(ConstraintsError::Conflict(a, b));
+
+        // Elements must be able to hold at least their alignment and granularity
+        if let Some(len_bits) = get(A::ElementLengthBits) {
+            if let Some((a, align)) = either(A::ElementAlignmentBits, A::AlignmentBits) {
+                if align >= len_bits {
+                    return conflict(a, A::ElementLengthBits);
+                }
+            }
+            if let Some(gran) = get(A::ElementGranularityBits) {
+                if gran >= len_bits {
+                    return conflict(A::ElementGranularityBits, A::ElementLengthBits);
+                }
+            }
+        }
+        // Alignment can't be coarser than the addressable range
+        if let (Some((a, align)), Some((b, addr))) = (either(A::ElementAlignmentBits, A::AlignmentBits), either(A::DataAddressableBits, A::AddressableBits)) {
+            if align >= addr {
+                return conflict(a, b);
+            }
+        }
+        // Segment limits must fit within the element limits
+        if let Some(max_el) = get(A::ScgthMaxElements).filter(|&v| v != 0) {
+            if get(A::ScgthMaxElPerSeg).is_some_and(|v| v > max_el) {
+                return conflict(A::ScgthMaxElPerSeg, A::ScgthMaxElements);
+            }
+            if get(A::ScgthMaxSegments).is_some_and(|v| v > max_el) {
+                return conflict(A::ScgthMaxSegments, A::ScgthMaxElements);
+            }
+        }
+        // The scatter-gather format must be able to express the device's addresses
+        if let Some(format) = get(A::ScgthFormat) {
+            let fmt_bits = format & (ffi::UDI_SCGTH_32 | ffi::UDI_SCGTH_64) as u32;
+            if fmt_bits == 0 {
+                return Err(ConstraintsError::MissingScgthWidth);
+            }
+            if fmt_bits == ffi::UDI_SCGTH_32 as u32 {
+                for (a, b) in [(A::DataAddressableBits, A::AddressableBits), (A::ScgthAddressableBits, A::AddressableBits)] {
+                    if let Some((a, bits)) = either(a, b) {
+                        if bits > 32 {
+                            return conflict(A::ScgthFormat, a);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut rv = ConstraintsAttrs {
+            attrs: [udi_dma_constraints_attr_spec_t { attr_type: 0, attr_value: 0 }; NUM_ATTRS],
+            len: 0,
+        };
+        for (&a, v) in Iterator::zip(Attribute::ALL.iter(), self.values.iter()) {
+            if let Some(v) = *v {
+                rv.attrs[rv.len] = udi_dma_constraints_attr_spec_t { attr_type: a.to_raw(), attr_value: v };
+                rv.len += 1;
+            }
+        }
+        Ok(rv)
+    }
+}
+
+/// A validated DMA constraints attribute list (from [ConstraintsBuilder::build])
+#[derive(Copy,Clone)]
+pub struct ConstraintsAttrs {
+    attrs: [udi_dma_constraints_attr_spec_t; NUM_ATTRS],
+    len: usize,
+}
+impl ::core::ops::Deref for ConstraintsAttrs {
+    type Target = [udi_dma_constraints_attr_spec_t];
+    fn deref(&self) -> &Self::Target {
+        &self.attrs[..self.len]
+    }
+}
+impl ::core::fmt::Debug for ConstraintsAttrs {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|a| (Attribute::from_raw(a.attr_type).unwrap(), a.attr_value)))
+            .finish()
+    }
+}
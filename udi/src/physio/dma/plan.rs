@@ -0,0 +1,131 @@
+//! Environment DMA limits, and splitting of large allocations into multiple [super::DmaAlloc] calls
+use ::udi_sys::physio as ffi;
+use super::{Attribute, DmaConstraints};
+
+/// Environment limits on contiguous DMA-able memory (see `udi_dma_limits`)
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Limits {
+    /// Largest contiguous element (in bytes) that the environment can ever allocate
+    pub max_legal_contig_alloc: usize,
+    /// Largest contiguous element that can be allocated without risking an indefinite wait for memory
+    pub max_safe_contig_alloc: usize,
+    /// CPU cache line size in bytes, used to keep separate DMA regions from sharing cache lines
+    pub cache_line_size: usize,
+}
+impl From<ffi::udi_dma_limits_t> for Limits {
+    fn from(v: ffi::udi_dma_limits_t) -> Self {
+        Limits {
+            max_legal_contig_alloc: v.max_legal_contig_alloc,
+            max_safe_contig_alloc: v.max_safe_contig_alloc,
+            cache_line_size: v.cache_line_size,
+        }
+    }
+}
+
+/// Query the environment's limits on DMA-able memory allocations
+pub fn limits() -> Limits {
+    let mut rv = ffi::udi_dma_limits_t {
+        max_legal_contig_alloc: 0,
+        max_safe_contig_alloc: 0,
+        cache_line_size: 0,
+    };
+    unsafe { ffi::udi_dma_limits(&mut rv) };
+    Limits::from(rv)
+}
+
+/// Error from [AllocPlan::new]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum PlanError {
+    /// A zero element size or count was requested
+    Empty,
+    /// A single element is larger than the environment or the device can handle contiguously
+    ElementTooLarge {
+        /// Requested element size
+        element_size: usize,
+        /// Largest supported contiguous element
+        max: usize,
+    },
+    /// The value of a relevant constraints attribute isn't known (see [DmaConstraints::is_known])
+    UnknownAttribute(Attribute),
+}
+impl ::core::fmt::Display for PlanError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        match *self {
+        PlanError::Empty => f.write_str("empty allocation"),
+        PlanError::ElementTooLarge { element_size, max } => write!(f, "element size {:#x} exceeds maximum contiguous size {:#x}", element_size, max),
+        PlanError::UnknownAttribute(attr) => write!(f, "value of {:?} is not known", attr),
+        }
+    }
+}
+
+/// A split of `count` equal-sized elements (e.g. ring descriptors or pool buffers) into the fewest
+/// [super::DmaAlloc::alloc] calls allowed by the environment [Limits] and the driver's DMA constraints.
+///
+/// Elements are never split between allocations, and each element is kept contiguous.
+/// ```
+/// use udi::physio::dma::{AllocPlan, DmaConstraints, Limits, PlanError};
+/// # // Dropping the constraints calls into the environment
+/// # #[no_mangle] extern "C" fn udi_dma_constraints_free(_: udi::ffi::physio::udi_dma_constraints_t) {}
+/// let limits = Limits { max_legal_contig_alloc: 0x10000, max_safe_contig_alloc: 0x1000, cache_line_size: 64 };
+/// // With no scatter-gather limit, all of the elements fit in one allocation
+/// let plan = AllocPlan::new(&limits, &DmaConstraints::null(), 1536, 16).unwrap();
+/// assert_eq!(plan.elements_per_alloc, 16);
+/// assert_eq!(plan.allocs().collect::<Vec<_>>(), [16]);
+/// // But each element must still be contiguous
+/// let err = AllocPlan::new(&limits, &DmaConstraints::null(), 0x1001, 1).unwrap_err();
+/// assert_eq!(err, PlanError::ElementTooLarge { element_size: 0x1001, max: 0x1000 });
+/// ```
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct AllocPlan {
+    /// Size of each element in bytes
+    pub element_size: usize,
+    /// Total number of elements
+    pub count: usize,
+    /// Number of elements (`nelements`) in each allocation, the final allocation may have fewer
+    pub elements_per_alloc: u16,
+}
+impl AllocPlan {
+    /// Plan the allocation of `count` elements of `element_size` bytes
+    ///
+    /// `constraints` are the constraints used for the allocations, only the `ScgthMaxElements` and
+    /// `ElementLengthBits` attributes (as reported by [DmaConstraints::get]) are considered. Both must be known, so
+    /// constraints from [DmaConstraints::from_raw] need them set (or reset) first.
+    pub fn new(limits: &Limits, constraints: &DmaConstraints, element_size: usize, count: usize) -> Result<Self,PlanError> {
+        if element_size == 0 || count == 0 {
+            return Err(PlanError::Empty);
+        }
+        for attr in [Attribute::ElementLengthBits, Attribute::ScgthMaxElements] {
+            if !constraints.is_known(attr) {
+                return Err(PlanError::UnknownAttribute(attr));
+            }
+        }
+        let get = |attr| constraints.get(attr);
+        // Largest single contiguous block (a scatter-gather element)
+        let max_contig = match get(Attribute::ElementLengthBits) {
+            Some(bits) if bits < usize::BITS => limits.max_safe_contig_alloc.min((1 << bits) - 1),
+            _ => limits.max_safe_contig_alloc,
+            };
+        if element_size > max_contig {
+            return Err(PlanError::ElementTooLarge { element_size, max: max_contig });
+        }
+        let per_block = max_contig / element_size;
+        // Each allocation may be made up of up to `UDI_DMA_SCGTH_MAX_ELEMENTS` blocks (zero is unlimited)
+        let per_alloc = match get(Attribute::ScgthMaxElements) {
+            Some(0) | None => usize::MAX,
+            Some(max_elements) => per_block.saturating_mul(max_elements as usize),
+            };
+        let elements_per_alloc = per_alloc.min(count).min(u16::MAX as usize) as u16;
+        Ok(AllocPlan { element_size, count, elements_per_alloc })
+    }
+
+    /// Number of allocation calls required
+    pub fn num_allocs(&self) -> usize {
+        self.count.div_ceil(self.elements_per_alloc as usize)
+    }
+    /// Iterate the `nelements` value for each allocation call
+    pub fn allocs(&self) -> impl Iterator<Item=u16> {
+        let per = self.elements_per_alloc as usize;
+        let count = self.count;
+        (0 .. self.num_allocs()).map(move |i| (count - i * per).min(per) as u16)
+    }
+}
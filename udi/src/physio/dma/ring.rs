@@ -0,0 +1,156 @@
+//! Ring of typed DMA descriptors
+use ::core::future::Future;
+use ::core::marker::PhantomData;
+use super::{DmaAlloc, DmaConstraints, Direction, Endianness};
+
+/// A value that can be stored in DMA memory shared with a device, and byte-swapped when the environment reports
+/// that device and driver endianness differ (see [DmaAlloc::must_swap])
+pub trait DmaSwap: Copy {
+    /// Reverse the byte order of each field
+    fn swap_bytes(self) -> Self;
+}
+macro_rules! impl_dma_swap {
+    ($($t:ty),*) => {$(
+        impl DmaSwap for $t {
+            fn swap_bytes(self) -> Self { <$t>::swap_bytes(self) }
+        }
+    )*};
+}
+impl_dma_swap!{ u8, u16, u32, u64, i8, i16, i32, i64 }
+impl<T: DmaSwap, const N: usize> DmaSwap for [T; N] {
+    fn swap_bytes(self) -> Self {
+        self.map(DmaSwap::swap_bytes)
+    }
+}
+
+/// A ring of `T` descriptors in a single contiguous DMA allocation, with producer (`head`) and consumer (`tail`) indices
+///
+/// The driver writes slots with [DmaRing::push] and retires them with [DmaRing::pop], syncing each range of slots
+/// to the device ([DmaRing::sync_for_device]) or back to the CPU ([DmaRing::sync_for_cpu]) as required.
+pub struct DmaRing<T: Copy> {
+    alloc: DmaAlloc,
+    capacity: usize,
+    /// Index of the oldest filled slot
+    tail: usize,
+    /// Number of filled slots
+    len: usize,
+    _pd: PhantomData<T>,
+}
+impl<T: DmaSwap> DmaRing<T> {
+    /// Allocate a zeroed ring of `capacity` slots
+    pub fn alloc<'a>(
+        gcb: crate::cb::CbRef<::udi_sys::udi_cb_t>,
+        constraints: &'a DmaConstraints,
+        endian: Endianness,
+        capacity: usize,
+    ) -> impl Future<Output=Self> + 'a {
+        assert!(capacity > 0, "Empty DMA ring");
+        let size = capacity.checked_mul(::core::mem::size_of::<T>()).expect("DMA ring size overflow");
+        let alloc = DmaAlloc::alloc_single(gcb, constraints, Direction::Both, endian, false, size);
+        async move {
+            let alloc = alloc.await;
+            assert!((alloc.mem_ptr as *const T).is_aligned(), "DMA ring memory misaligned for element");
+            DmaRing { alloc, capacity, tail: 0, len: 0, _pd: PhantomData }
+        }
+    }
+
+    /// Number of slots in the ring
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Number of filled slots (pushed but not yet popped)
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Check if there are no filled slots
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Check if all slots are filled
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity
+    }
+    /// Slot index that the next [DmaRing::push] will fill
+    pub fn head(&self) -> usize {
+        (self.tail + self.len) % self.capacity
+    }
+    /// Slot index that the next [DmaRing::pop] will read
+    pub fn tail(&self) -> usize {
+        self.tail
+    }
+
+    /// Read a slot (converting from device byte order)
+    pub fn get(&self, slot: usize) -> T {
+        assert!(slot < self.capacity, "DMA ring slot {} out of range ({})", slot, self.capacity);
+        // SAFE: In-bounds and aligned (checked in `alloc`), the device may be writing so use a volatile read
+        let v = unsafe { ::core::ptr::read_volatile((self.alloc.mem_ptr as *const T).add(slot)) };
+        if self.alloc.must_swap { v.swap_bytes() } else { v }
+    }
+    /// Write a slot (converting to device byte order)
+    pub fn set(&mut self, slot: usize, val: T) {
+        assert!(slot < self.capacity, "DMA ring slot {} out of range ({})", slot, self.capacity);
+        let v = if self.alloc.must_swap { val.swap_bytes() } else { val };
+        // SAFE: In-bounds and aligned (checked in `alloc`)
+        unsafe { ::core::ptr::write_volatile((self.alloc.mem_ptr as *mut T).add(slot), v) }
+    }
+
+    /// Fill the slot at the head of the ring, returning the slot index (or the value if the ring is full)
+    pub fn push(&mut self, val: T) -> Result<usize,T> {
+        if self.is_full() {
+            return Err(val);
+        }
+        let slot = self.head();
+        self.set(slot, val);
+        self.len += 1;
+        Ok(slot)
+    }
+    /// Retire the slot at the tail of the ring, returning its index and current value
+    pub fn pop(&mut self) -> Option<(usize, T)> {
+        if self.is_empty() {
+            return None;
+        }
+        let slot = self.tail();
+        self.tail = (self.tail + 1) % self.capacity;
+        self.len -= 1;
+        Some( (slot, self.get(slot)) )
+    }
+
+    /// Bus address of a slot, for passing to the device
+    ///
+    /// # Panics
+    /// Panics if the ring's constraints made its scatter-gather list unreadable by the driver (see [super::ScGth::iter])
+    pub fn bus_addr(&self, slot: usize) -> u64 {
+        assert!(slot < self.capacity, "DMA ring slot {} out of range ({})", slot, self.capacity);
+        let mut ofs = (slot * ::core::mem::size_of::<T>()) as u64;
+        let mut els = self.alloc.scgth().iter().expect("DMA ring scatter-gather list not driver-mapped");
+        let rv = els.find_map(|(addr, len)| if ofs < len as u64 { Some(addr + ofs) } else { ofs -= len as u64; None });
+        rv.expect("DMA ring slot not covered by the scatter-gather list")
+    }
+
+    /// Make driver writes to a range of slots visible to the device
+    pub fn sync_for_device<'a>(
+        &'a self,
+        gcb: crate::cb::CbRef<::udi_sys::udi_cb_t>,
+        slots: ::core::ops::Range<usize>,
+    ) -> impl Future<Output=()> + 'a {
+        self.sync(gcb, slots, Direction::Out)
+    }
+    /// Make device writes to a range of slots visible to the driver
+    pub fn sync_for_cpu<'a>(
+        &'a self,
+        gcb: crate::cb::CbRef<::udi_sys::udi_cb_t>,
+        slots: ::core::ops::Range<usize>,
+    ) -> impl Future<Output=()> + 'a {
+        self.sync(gcb, slots, Direction::In)
+    }
+    fn sync<'a>(
+        &'a self,
+        gcb: crate::cb::CbRef<::udi_sys::udi_cb_t>,
+        slots: ::core::ops::Range<usize>,
+        dir: Direction,
+    ) -> impl Future<Output=()> + 'a {
+        assert!(slots.start < slots.end && slots.end <= self.capacity, "Invalid DMA ring slot range {:?} ({})", slots, self.capacity);
+        let size = ::core::mem::size_of::<T>();
+        self.alloc.handle.sync(gcb, slots.start * size, (slots.end - slots.start) * size, dir)
+    }
+}
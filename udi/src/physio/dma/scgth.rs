@@ -0,0 +1,219 @@
+//! Iteration of scatter-gather lists, and conversion into device-specific scatter lists
+use ::udi_sys::physio as ffi;
+use super::ScGth;
+
+impl<'a> ScGth<'a> {
+    /// Check if the list elements are in the opposite byte order to the driver (`scgth_must_swap`)
+    pub fn must_swap(&self) -> bool {
+        self.0.scgth_must_swap != ::udi_sys::FALSE
+    }
+    /// Check if the list uses 64-bit elements (`UDI_SCGTH_64`)
+    pub fn is_64(&self) -> bool {
+        self.0.scgth_format & ffi::UDI_SCGTH_64 != 0
+    }
+    /// Check if the driver can access the list elements (`scgth_elements`)
+    ///
+    /// When only `UDI_SCGTH_DMA_MAPPED` is set, the only element visible to the driver is `scgth_first_segment`.
+    pub fn is_driver_mapped(&self) -> bool {
+        let f = self.0.scgth_format;
+        f & ffi::UDI_SCGTH_DRIVER_MAPPED != 0 || f & ffi::UDI_SCGTH_DMA_MAPPED == 0
+    }
+
+    /// Iterate the data elements as `(bus_addr, len)` pairs, in driver byte order
+    ///
+    /// Extension (`UDI_SCGTH_EXT`) elements that chain segments together are skipped. Fails if the list is only
+    /// DMA-mapped and has more than one element, as the driver can't read it.
+    pub fn iter(&self) -> Result<ScgthIter<'a>, NotDriverMapped> {
+        let s = self.0;
+        let direct = !self.is_driver_mapped();
+        let len = s.scgth_num_elements as usize;
+        // SAFE: The environment provides `len` driver-visible elements of the indicated format, or a valid first segment
+        let inner = unsafe {
+            if self.is_64() {
+                IterInner::Bits64(if direct { ::core::slice::from_ref(&s.scgth_first_segment.el64) } else { ::core::slice::from_raw_parts(s.scgth_elements.el64p, len) }.iter())
+            }
+            else {
+                IterInner::Bits32(if direct { ::core::slice::from_ref(&s.scgth_first_segment.el32) } else { ::core::slice::from_raw_parts(s.scgth_elements.el32p, len) }.iter())
+            }
+        };
+        let rv = ScgthIter { inner, swap: self.must_swap() };
+        if direct && !rv.first_is_data() {
+            return Err(NotDriverMapped);
+        }
+        Ok(rv)
+    }
+}
+
+/// Error from [ScGth::iter]: The list is only DMA-mapped, and has more than one element
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct NotDriverMapped;
+impl ::core::fmt::Display for NotDriverMapped {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.write_str("scatter-gather list is not mapped for driver access")
+    }
+}
+
+/// Iterator over the data elements of a [ScGth], yielding `(bus_addr, len)`
+#[derive(Clone)]
+pub struct ScgthIter<'a> {
+    inner: IterInner<'a>,
+    swap: bool,
+}
+#[derive(Clone)]
+enum IterInner<'a> {
+    Bits32(::core::slice::Iter<'a, ffi::udi_scgth_element_32_t>),
+    Bits64(::core::slice::Iter<'a, ffi::udi_scgth_element_64_t>),
+}
+impl ScgthIter<'_> {
+    fn sw32(&self, v: u32) -> u32 {
+        if self.swap { v.swap_bytes() } else { v }
+    }
+    /// Returns `(bus_addr, len, is_extension)` for the next raw element
+    fn next_raw(&mut self) -> Option<(u64, u32, bool)> {
+        match self.inner {
+        IterInner::Bits32(ref mut it) => {
+            let e = *it.next()?;
+            let len = self.sw32(e.block_length);
+            Some( (self.sw32(e.block_busaddr) as u64, len & !ffi::UDI_SCGTH_EXT, len & ffi::UDI_SCGTH_EXT != 0) )
+            },
+        IterInner::Bits64(ref mut it) => {
+            let e = *it.next()?;
+            let addr = if self.swap { e.block_busaddr.swap_bytes() } else { e.block_busaddr };
+            Some( (addr, self.sw32(e.block_length), self.sw32(e.el_reserved) & ffi::UDI_SCGTH_EXT != 0) )
+            },
+        }
+    }
+    fn first_is_data(&self) -> bool {
+        matches!(self.clone().next_raw(), Some((_, _, false)))
+    }
+}
+impl Iterator for ScgthIter<'_> {
+    type Item = (u64, u32);
+    fn next(&mut self) -> Option<(u64, u32)> {
+        loop {
+            match self.next_raw()? {
+            (_, _, true) => {},
+            (addr, len, false) => return Some((addr, len)),
+            }
+        }
+    }
+}
+
+/// Error from [ScatterListBuilder::build]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ScatterListError {
+    /// The list needs more entries than the device (or the destination slice) allows
+    TooManyEntries {
+        /// Maximum number of entries
+        max: usize,
+    },
+    /// An element ends above the device's addressable range
+    AddressTooWide {
+        /// Bus address of the offending element
+        bus_addr: u64,
+        /// Number of address bits supported by the device
+        bits: u8,
+    },
+}
+impl ::core::fmt::Display for ScatterListError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        match *self {
+        ScatterListError::TooManyEntries { max } => write!(f, "scatter list needs more than {} entries", max),
+        ScatterListError::AddressTooWide { bus_addr, bits } => write!(f, "element at {:#x} not addressable with {} bits", bus_addr, bits),
+        }
+    }
+}
+
+/// Writes a device's own scatter list (e.g. a PRD table or TX descriptor chain) from `(bus_addr, len)` elements
+///
+/// Elements are optionally merged when contiguous, then split to fit the device's maximum entry length.
+/// ```
+/// use udi::physio::dma::ScatterListBuilder;
+/// // A PRD-style table: up to four entries of a 32-bit address and a 16-bit length (where zero means 64KiB)
+/// let mut prd = [(0u32, 0u16, false); 4];
+/// let elements = [(0x1000, 0x1000), (0x2000, 0xF000), (0x11000, 0x2000), (0x20000, 0x10)];
+/// let n = ScatterListBuilder::new(4)
+///     .max_entry_len(0x10000)
+///     .addressable_bits(32)
+///     .build(elements, &mut prd, |addr, len, last| (addr as u32, len as u16, last))
+///     .unwrap();
+/// assert_eq!(&prd[..n], [(0x1000, 0, false), (0x11000, 0x2000, false), (0x20000, 0x10, true)]);
+/// ```
+#[derive(Debug,Copy,Clone)]
+pub struct ScatterListBuilder {
+    max_entries: usize,
+    max_entry_len: u32,
+    addr_bits: u8,
+    coalesce: bool,
+}
+impl ScatterListBuilder {
+    /// Create a builder for a device list with at most `max_entries` entries
+    ///
+    /// By default entries are unlimited in length, addresses are 64-bit, and contiguous elements are merged.
+    pub fn new(max_entries: usize) -> Self {
+        ScatterListBuilder {
+            max_entries,
+            max_entry_len: u32::MAX,
+            addr_bits: 64,
+            coalesce: true,
+        }
+    }
+    /// Set the largest length of a single device entry, longer elements are split
+    pub fn max_entry_len(self, len: u32) -> Self {
+        assert!(len > 0, "Zero maximum scatter list entry length");
+        ScatterListBuilder { max_entry_len: len, ..self }
+    }
+    /// Set the number of address bits the device supports
+    pub fn addressable_bits(self, bits: u8) -> Self {
+        assert!(bits > 0 && bits <= 64, "Invalid scatter list address width {}", bits);
+        ScatterListBuilder { addr_bits: bits, ..self }
+    }
+    /// Set whether elements that are contiguous on the bus are merged into a single entry
+    pub fn coalesce(self, enable: bool) -> Self {
+        ScatterListBuilder { coalesce: enable, ..self }
+    }
+
+    /// Write entries into `dst` using `encode(bus_addr, len, is_last)`, returning the number of entries written
+    ///
+    /// Zero-length elements are dropped.
+    pub fn build<T>(
+        &self,
+        elements: impl IntoIterator<Item=(u64, u32)>,
+        dst: &mut [T],
+        mut encode: impl FnMut(u64, u32, bool) -> T,
+    ) -> Result<usize, ScatterListError> {
+        let max = self.max_entries.min(dst.len());
+        let coalesce = self.coalesce;
+        let max_len = self.max_entry_len as u64;
+        let mut src = elements.into_iter().filter(|e| e.1 > 0).map(|(a, l)| (a, l as u64)).peekable();
+        let merged = ::core::iter::from_fn(move || {
+            let (addr, mut len) = src.next()?;
+            if coalesce {
+                while let Some((_, l)) = src.next_if(|e| e.0 == addr.wrapping_add(len)) {
+                    len += l;
+                }
+            }
+            Some((addr, len))
+        });
+        let mut entries = merged.flat_map(|(addr, len)| {
+            (0 .. len.div_ceil(max_len)).map(move |i| {
+                let ofs = i * max_len;
+                (addr + ofs, (len - ofs).min(max_len) as u32)
+            })
+        }).peekable();
+
+        let mut count = 0;
+        while let Some((addr, len)) = entries.next() {
+            let end = addr + (len as u64 - 1);
+            if self.addr_bits < 64 && end >> self.addr_bits != 0 {
+                return Err(ScatterListError::AddressTooWide { bus_addr: addr, bits: self.addr_bits });
+            }
+            if count == max {
+                return Err(ScatterListError::TooManyEntries { max });
+            }
+            dst[count] = encode(addr, len, entries.peek().is_none());
+            count += 1;
+        }
+        Ok(count)
+    }
+}
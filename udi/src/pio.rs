@@ -3,6 +3,14 @@
 //! A feature that allows doing register IO using a simple register-based VM
 //! instead of needing drivers to run with direct IO access.
 
+mod decode;
+mod disasm;
+mod validate;
+
+pub use self::decode::{Op, MemRef, Condition, RepArgs, DecodeError};
+pub use self::disasm::PioProgram;
+pub use self::validate::{validate, validate_map, Summary, ValidateError, ValidateErrorKind, MAX_OPS};
+
 #[derive(Debug)]
 /// Handle to a registered PIO operation
 pub struct Handle(crate::ffi::pio::udi_pio_handle_t);
@@ -135,6 +143,61 @@ pub mod vals {
 			}
 		}
 	}
+	/// Get the first label number available for named labels (one after the highest numeric label)
+	pub const fn label_base(numeric_labels: &[u16]) -> u16 {
+		let mut rv = 1;
+		let mut i = 0;
+		while i < numeric_labels.len() {
+			if numeric_labels[i] >= rv {
+				rv = numeric_labels[i] + 1;
+			}
+			i += 1;
+		}
+		rv
+	}
+	/// Compile-time check that labels are unique, and that all branches have a target
+	pub const fn check_labels(trans_list: &[crate::ffi::pio::udi_pio_trans_t]) {
+		let mut i = 0;
+		while i < trans_list.len() {
+			let op = &trans_list[i];
+			if op.pio_op == ops_group_c::LABEL {
+				if op.operand == 0 {
+					panic!("define_pio_ops: LABEL 0 is the implicit start of the list");
+				}
+				let mut j = i + 1;
+				while j < trans_list.len() {
+					if trans_list[j].pio_op == ops_group_c::LABEL && trans_list[j].operand == op.operand {
+						panic!("define_pio_ops: Duplicate LABEL");
+					}
+					j += 1;
+				}
+			}
+			if op.pio_op == ops_group_c::BRANCH && op.operand != 0 {
+				let mut j = 0;
+				while j < trans_list.len() {
+					if trans_list[j].pio_op == ops_group_c::LABEL && trans_list[j].operand == op.operand {
+						break;
+					}
+					j += 1;
+				}
+				if j == trans_list.len() {
+					panic!("define_pio_ops: BRANCH to an undefined LABEL");
+				}
+			}
+			i += 1;
+		}
+	}
+	/// Compile-time check that a (named) label is the target of at least one branch
+	pub const fn check_label_used(trans_list: &[crate::ffi::pio::udi_pio_trans_t], label: u16, msg: &str) {
+		let mut i = 0;
+		while i < trans_list.len() {
+			if trans_list[i].pio_op == ops_group_c::BRANCH && trans_list[i].operand == label {
+				return ;
+			}
+			i += 1;
+		}
+		panic!("{}", msg);
+	}
 	pub mod stride {
 		pub const STEP1: u16 = 1;
 		pub const STEP2: u16 = 2;
@@ -211,7 +274,68 @@ pub mod vals {
 /// Commands:
 /// - `IN.size Rd, regaddr` - Read from IO
 /// - `OUT.size regaddr, Rs` - Write to IO
+/// - `LABEL n` / `LABEL (CONST)` / `LABEL name` - Branch target. Numeric labels (a literal, or a parenthesised
+///   constant expression) are also entrypoints for [trans]. Named labels are allocated after the highest numeric label
+///   and must be the target of a `BRANCH`
+/// - `BRANCH n` / `BRANCH name` - Jump to a label
 /// - ...
+///
+/// Aliases (only visible within the definition):
+/// - `let name = Rn;` - Give a register a descriptive name
+/// - `const NAME = value;` - Name a constant, e.g. a device register offset
+///
+/// The result is a `const` array of [crate::ffi::pio::udi_pio_trans_t], with label uniqueness and branch targets
+/// checked at compile time.
+///
+/// ```
+/// mod regs { pub const ISR: u8 = 7; }
+/// ::udi::define_pio_ops!{WAIT_ISR =
+///     let status = R1;
+///     const ISR = regs::ISR;
+///     LABEL wait;
+///     IN.B status, ISR;
+///     CSKIP.B status NZ;
+///     BRANCH wait;
+///     END.B status;
+/// }
+/// assert_eq!(WAIT_ISR.len(), 5);
+/// ```
+///
+/// Label errors are compile errors:
+/// ```compile_fail,E0080
+/// // Duplicate numeric label
+/// ::udi::define_pio_ops!{OPS =
+///     END_IMM 0;
+///     LABEL 1;
+///     END_IMM 0;
+///     LABEL 1;
+///     END_IMM 0;
+/// }
+/// ```
+/// ```compile_fail,E0428
+/// // Duplicate named label
+/// ::udi::define_pio_ops!{OPS =
+///     BRANCH again;
+///     LABEL again;
+///     BRANCH again;
+///     LABEL again;
+///     END_IMM 0;
+/// }
+/// ```
+/// ```compile_fail,E0080
+/// // Named label that is never branched to
+/// ::udi::define_pio_ops!{OPS =
+///     LABEL unused;
+///     END_IMM 0;
+/// }
+/// ```
+/// ```compile_fail,E0080
+/// // Branch to a missing label
+/// ::udi::define_pio_ops!{OPS =
+///     BRANCH 2;
+///     END_IMM 0;
+/// }
+/// ```
 #[macro_export]
 macro_rules! define_pio_ops
 {
@@ -219,157 +343,204 @@ macro_rules! define_pio_ops
 		$v:vis $name:ident =
 		$($inner:tt)*
 	) => {
-		$v const $name: &'static [$crate::ffi::pio::udi_pio_trans_t] =
-			&$crate::define_pio_ops!(@expand ; $($inner)*);
+		$crate::define_pio_ops!{@expand { $v $name [] [] (0) } ; $($inner)*}
+	};
+
+	// Final output: items (aliases and label names) are placed in the const's initialiser block, so they're only
+	// visible to this set of operations.
+	(@expand { $v:vis $name:ident [$($items:item)*] [$($numeric_label:expr,)*] ($n_named:expr) } $($output:expr,)*; ) => {
+		$v const $name: [$crate::ffi::pio::udi_pio_trans_t; [$($crate::define_pio_ops!(@unit $output),)*].len()] = {
+			#[allow(unused_imports)]
+			use $crate::pio::vals::regs::*;
+			// Named labels are allocated after the highest numeric label
+			#[allow(dead_code)]
+			const __PIO_LABEL_BASE: u16 = $crate::pio::vals::label_base(&[$($numeric_label,)*]);
+			$($items)*
+			const __PIO_OPS: [$crate::ffi::pio::udi_pio_trans_t; [$($crate::define_pio_ops!(@unit $output),)*].len()] = [ $($output,)* ];
+			$crate::pio::vals::check_labels(&__PIO_OPS);
+			__PIO_OPS
+		};
 	};
+	(@unit $e:expr) => { () };
 
-	(@expand $($output:expr,)*; ) => { [ $($output,)* ] };
+	// Aliases
+	// - `let name = Rn;` - Register alias
+	(@expand { $v:vis $name:ident [$($items:item)*] $labels:tt $n_named:tt } $($output:expr,)*; let $alias:ident = $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand
+		{ $v $name [$($items)* #[allow(non_upper_case_globals)] const $alias: u8 = $reg; ] $labels $n_named }
+		$($output,)*;
+		$($rest)*
+	} };
+	// - `const NAME = value;` - Named constant (e.g. a device register offset)
+	(@expand { $v:vis $name:ident [$($items:item)*] $labels:tt $n_named:tt } $($output:expr,)*; const $cname:ident = $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand
+		{ $v $name [$($items)* #[allow(non_upper_case_globals)] const $cname: u16 = ($val) as u16; ] $labels $n_named }
+		$($output,)*;
+		$($rest)*
+	} };
 
 	// Group A
 	// - IN Rd, reg
-	(@expand $($output:expr,)*; IN.$sizecode:ident $reg:tt, $src:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; IN.$sizecode:ident $reg:tt, $src:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@a $sizecode, IN, $reg, $src), ;
 		$($rest)*
-	) };
+	} };
 	// - OUT reg, Rs
-	(@expand $($output:expr,)*; OUT.$sizecode:ident $dst:expr, $reg:tt; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; OUT.$sizecode:ident $dst:expr, $reg:tt; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@a $sizecode, OUT, $reg, $dst), ;
 		$($rest)*
-	) };
-	(@expand $($output:expr,)*; LOAD.$sizecode:ident $reg:ident, $src:tt; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@a $sizecode, LOAD, $src, $crate::pio::vals::regs::$reg as _), ;
+	} };
+	(@expand $state:tt $($output:expr,)*; LOAD.$sizecode:ident $reg:ident, $src:tt; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@a $sizecode, LOAD, $src, $reg as _), ;
 		$($rest)*
-	) };
-	(@expand $($output:expr,)*; STORE.$sizecode:ident $dst:tt, $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@a $sizecode, STORE, $dst, $crate::pio::vals::regs::$reg as _), ;
+	} };
+	(@expand $state:tt $($output:expr,)*; STORE.$sizecode:ident $dst:tt, $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@a $sizecode, STORE, $dst, $reg as _), ;
 		$($rest)*
-	) };
+	} };
 
 	// Group B
 	// - LOAD_IMM.[BS] Rd, IMM
-	(@expand $($output:expr,)*; LOAD_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; LOAD_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, LOAD_IMM, $reg, $crate::pio::vals::size::$sizecode::to_u16($val)), ;
 		$($rest)*
-	) };
+	} };
 	// - CSKIP.s Rt, cc
-	(@expand $($output:expr,)*; CSKIP.$sizecode:ident $reg:ident $cc:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; CSKIP.$sizecode:ident $reg:ident $cc:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, CSKIP, $reg, $crate::pio::vals::ConditionCode::$cc as _), ;
 		$($rest)*
-	) };
+	} };
 	// - IN_IND
-	(@expand $($output:expr,)*; IN_IND.$sizecode:ident $reg:ident, $pio_reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, IN_IND, $reg, $crate::pio::vals::regs::$pio_reg as _), ;
+	(@expand $state:tt $($output:expr,)*; IN_IND.$sizecode:ident $reg:ident, $pio_reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, IN_IND, $reg, $pio_reg as _), ;
 		$($rest)*
-	) };
+	} };
 	// - OUT_IND
-	(@expand $($output:expr,)*; OUT_IND.$sizecode:ident $pio_reg:ident, $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, OUT_IND, $reg, $crate::pio::vals::regs::$pio_reg as _), ;
+	(@expand $state:tt $($output:expr,)*; OUT_IND.$sizecode:ident $pio_reg:ident, $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, OUT_IND, $reg, $pio_reg as _), ;
 		$($rest)*
-	) };
+	} };
 	// - SHIFT_LEFT.s Rd, bits
-	(@expand $($output:expr,)*; SHIFT_LEFT.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; SHIFT_LEFT.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, SHIFT_LEFT, $reg, $val), ;
 		$($rest)*
-	) };
+	} };
 	// - SHIFT_RIGHT.s Rd, bits
-	(@expand $($output:expr,)*; SHIFT_RIGHT.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; SHIFT_RIGHT.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, SHIFT_RIGHT, $reg, $val), ;
 		$($rest)*
-	) };
+	} };
 	// - AND.s Rd, Rs
-	(@expand $($output:expr,)*; AND.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, AND, $reg, $crate::pio::vals::regs::$rs as _), ;
+	(@expand $state:tt $($output:expr,)*; AND.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, AND, $reg, $rs as _), ;
 		$($rest)*
-	) };
+	} };
 	// - AND_IMM Rd, IMM
-	(@expand $($output:expr,)*; AND_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; AND_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, AND_IMM, $reg, $crate::pio::vals::size::$sizecode::to_u16($val)), ;
 		$($rest)*
-	) };
+	} };
 	// - OR.s Rd, Rs
-	(@expand $($output:expr,)*; OR.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, OR, $reg, $crate::pio::vals::regs::$rs as _), ;
+	(@expand $state:tt $($output:expr,)*; OR.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, OR, $reg, $rs as _), ;
 		$($rest)*
-	) };
+	} };
 	// - OR_IMM.s Rd, IMM
-	(@expand $($output:expr,)*; OR_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; OR_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, OR_IMM, $reg, $crate::pio::vals::size::$sizecode::to_u16($val)), ;
 		$($rest)*
-	) };
+	} };
 	// - XOR.s Rd, Rs
-	(@expand $($output:expr,)*; XOR.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, XOR, $reg, $crate::pio::vals::regs::$rs as _), ;
+	(@expand $state:tt $($output:expr,)*; XOR.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, XOR, $reg, $rs as _), ;
 		$($rest)*
-	) };
+	} };
 	// - ADD.s Rd, Rs
-	(@expand $($output:expr,)*; ADD.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, ADD, $reg, $crate::pio::vals::regs::$rs as _), ;
+	(@expand $state:tt $($output:expr,)*; ADD.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, ADD, $reg, $rs as _), ;
 		$($rest)*
-	) };
+	} };
 	// - ADD_IMM.s Rd, IMM
-	(@expand $($output:expr,)*; ADD_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; ADD_IMM.$sizecode:ident $reg:ident, $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@b $sizecode, ADD_IMM, $reg, $crate::pio::vals::size::$sizecode::to_u16($val)), ;
 		$($rest)*
-	) };
+	} };
 	// - SUB.s Rd, Rs
-	(@expand $($output:expr,)*; SUB.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@b $sizecode, SUB, $reg, $crate::pio::vals::regs::$rs as _), ;
+	(@expand $state:tt $($output:expr,)*; SUB.$sizecode:ident $reg:ident, $rs:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@b $sizecode, SUB, $reg, $rs as _), ;
 		$($rest)*
-	) };
+	} };
 
 	// Group C
 	// - BRANCH idx
-	(@expand $($output:expr,)*; BRANCH $idx:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; BRANCH $idx:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@c B, BRANCH, $idx), ;
 		$($rest)*
-	) };
+	} };
 	// - LABEL idx
-	(@expand $($output:expr,)*; LABEL $idx:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	// Numeric labels: a literal, or a parenthesised expression (e.g. `LABEL (ENTRY_WRITE);`) so a constant isn't taken
+	// as a label name. These arms come before `LABEL name`, which would otherwise capture a bare constant name.
+	(@expand { $v:vis $name:ident $items:tt [$($numeric_label:expr,)*] $n_named:tt } $($output:expr,)*; LABEL $idx:literal; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand
+		{ $v $name $items [$($numeric_label,)* $idx,] $n_named }
 		$($output,)* $crate::define_pio_ops!(@c B, LABEL, $idx), ;
 		$($rest)*
-	) };
+	} };
+	(@expand { $v:vis $name:ident $items:tt [$($numeric_label:expr,)*] $n_named:tt } $($output:expr,)*; LABEL ($idx:expr); $($rest:tt)* ) => { $crate::define_pio_ops!{@expand
+		{ $v $name $items [$($numeric_label,)* $idx,] $n_named }
+		$($output,)* $crate::define_pio_ops!(@c B, LABEL, $idx), ;
+		$($rest)*
+	} };
+	// - LABEL name
+	// Named labels must be branched to (entry points should stay numeric), checked by the `const _`.
+	// Duplicate names are a redefinition error.
+	(@expand { $v:vis $name:ident [$($items:item)*] $labels:tt ($n_named:expr) } $($output:expr,)*; LABEL $label:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand
+		{ $v $name [$($items)*
+			#[allow(non_upper_case_globals)] const $label: u16 = __PIO_LABEL_BASE + $n_named;
+			const _: () = $crate::pio::vals::check_label_used(&__PIO_OPS, $label, concat!("define_pio_ops: LABEL `", stringify!($label), "` is never used"));
+			] $labels ($n_named + 1) }
+		$($output,)* $crate::define_pio_ops!(@c B, LABEL, __PIO_LABEL_BASE + $n_named), ;
+		$($rest)*
+	} };
 	// REP_IN_IND [ {mem,buf} Rmem {|stride} ], Rreg {|stride}, Rcount
-	(@expand $($output:expr,)*;
+	(@expand $state:tt $($output:expr,)*;
 		REP_IN_IND.$sizecode:ident [$ty:ident $mem_reg:ident $($mem_stride:ident)?], $pio_reg:ident $($pio_stride:ident)?, $count_reg:ident;
 		$($rest:tt)*
-	) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@c B, REP_IN_IND, $crate::define_pio_ops!(@rep_args $ty $mem_reg $($mem_stride)?, $pio_reg $($pio_stride)?, $count_reg)), ;
+	) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@c $sizecode, REP_IN_IND, $crate::define_pio_ops!(@rep_args $ty $mem_reg $($mem_stride)?, $pio_reg $($pio_stride)?, $count_reg)), ;
 		$($rest)*
-	) };
+	} };
 	// REP_OUT_IND.s [ [mem|buf] Rmem [stride]], Rreg [stride], Rcount
-	(@expand $($output:expr,)*;
+	(@expand $state:tt $($output:expr,)*;
 		REP_OUT_IND.$sizecode:ident [$ty:ident $mem_reg:ident $($mem_stride:ident)?], $pio_reg:ident $($pio_stride:ident)?, $count_reg:ident;
 		$($rest:tt)*
-	) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@c B, REP_OUT_IND, $crate::define_pio_ops!(@rep_args $ty $mem_reg $($mem_stride)?, $pio_reg $($pio_stride)?, $count_reg)), ;
+	) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@c $sizecode, REP_OUT_IND, $crate::define_pio_ops!(@rep_args $ty $mem_reg $($mem_stride)?, $pio_reg $($pio_stride)?, $count_reg)), ;
 		$($rest)*
-	) };
+	} };
 
 	// `END.[BS] Rn` - 
-	(@expand $($output:expr,)*; END.B $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@c B, END, $crate::pio::vals::regs::$reg as _), ;
+	(@expand $state:tt $($output:expr,)*; END.B $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@c B, END, $reg as _), ;
 		$($rest)*
-	) };
-	(@expand $($output:expr,)*; END.S $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
-		$($output,)* $crate::define_pio_ops!(@c S, END, $crate::pio::vals::regs::$reg as _), ;
+	} };
+	(@expand $state:tt $($output:expr,)*; END.S $reg:ident; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
+		$($output,)* $crate::define_pio_ops!(@c S, END, $reg as _), ;
 		$($rest)*
-	) };
+	} };
 	// `DELAY microseconds` - Delay for AT LEAST `microseconds`
-	(@expand $($output:expr,)*; DELAY $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; DELAY $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@c B, DELAY, $val), ;
 		$($rest)*
-	) };
+	} };
 	// `END imm` - 
-	(@expand $($output:expr,)*; END_IMM $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!(@expand
+	(@expand $state:tt $($output:expr,)*; END_IMM $val:expr; $($rest:tt)* ) => { $crate::define_pio_ops!{@expand $state
 		$($output,)* $crate::define_pio_ops!(@c S, END_IMM, $val), ;
 		$($rest)*
-	) };
+	} };
 
 	// ----- Encoding -----
 	// Group A
 	(@a $size:ident, $opname:ident, $regname:ident, $val:expr) => {
 		$crate::ffi::pio::udi_pio_trans_t {
-			pio_op: $crate::pio::vals::ops_group_a::$opname|$crate::pio::vals::regs::$regname|$crate::ffi::pio::UDI_PIO_DIRECT,
+			pio_op: $crate::pio::vals::ops_group_a::$opname|$regname|$crate::ffi::pio::UDI_PIO_DIRECT,
 			tran_size: $crate::pio::vals::size::$size,
 			operand: $val
 		}
@@ -377,21 +548,21 @@ macro_rules! define_pio_ops
 	// - Scratch could be unsafe, but interrupt handlers need it
 	(@a $size:ident, $opname:ident, [scratch $regname:ident], $val:expr) => {
 		$crate::ffi::pio::udi_pio_trans_t {
-			pio_op: $crate::pio::vals::ops_group_a::$opname|$crate::pio::vals::regs::$regname|$crate::ffi::pio::UDI_PIO_SCRATCH,
+			pio_op: $crate::pio::vals::ops_group_a::$opname|$regname|$crate::ffi::pio::UDI_PIO_SCRATCH,
 			tran_size: $crate::pio::vals::size::$size,
 			operand: $val
 		}
 	};
 	(@a $size:ident, $opname:ident, [buf $regname:ident], $val:expr) => {
 		$crate::ffi::pio::udi_pio_trans_t {
-			pio_op: $crate::pio::vals::ops_group_a::$opname|$crate::pio::vals::regs::$regname|$crate::ffi::pio::UDI_PIO_BUF,
+			pio_op: $crate::pio::vals::ops_group_a::$opname|$regname|$crate::ffi::pio::UDI_PIO_BUF,
 			tran_size: $crate::pio::vals::size::$size,
 			operand: $val
 		}
 	};
 	(@a $size:ident, $opname:ident, [mem $regname:ident], $val:expr) => {
 		$crate::ffi::pio::udi_pio_trans_t {
-			pio_op: $crate::pio::vals::ops_group_a::$opname|$crate::pio::vals::regs::$regname|$crate::ffi::pio::UDI_PIO_MEM,
+			pio_op: $crate::pio::vals::ops_group_a::$opname|$regname|$crate::ffi::pio::UDI_PIO_MEM,
 			tran_size: $crate::pio::vals::size::$size,
 			operand: $val
 		}
@@ -400,7 +571,7 @@ macro_rules! define_pio_ops
 	// Group B
 	(@b $size:ident, $opname:ident, $regname:ident, $val:expr) => {
 		$crate::ffi::pio::udi_pio_trans_t {
-			pio_op: $crate::pio::vals::ops_group_b::$opname|$crate::pio::vals::regs::$regname|$crate::ffi::pio::UDI_PIO_DIRECT,
+			pio_op: $crate::pio::vals::ops_group_b::$opname|$regname|$crate::ffi::pio::UDI_PIO_DIRECT,
 			tran_size: $crate::pio::vals::size::$size,
 			operand: $val
 		}
@@ -417,26 +588,26 @@ macro_rules! define_pio_ops
 	// ----- Arguments for the repeat ops -----
 	(@rep_args mem $mem_reg:ident $($mem_stride:ident)?, $pio_reg:ident $($pio_stride:ident)?, $count_reg:ident) => {
 		$crate::ffi::pio::UDI_PIO_MEM as u16
-		|($crate::pio::vals::regs::$mem_reg as u16)
+		|($mem_reg as u16)
 		$(| $crate::pio::vals::stride::$mem_stride << 5)?
-		|($crate::pio::vals::regs::$pio_reg as u16) << 7
+		|($pio_reg as u16) << 7
 		$(| $crate::pio::vals::stride::$pio_stride << 10)?
-		|($crate::pio::vals::regs::$count_reg as u16) << 12
+		|($count_reg as u16) << 12
 	};
 	(@rep_args buf $mem_reg:ident $($mem_stride:ident)?, $pio_reg:ident $($pio_stride:ident)?, $count_reg:ident) => {
 		$crate::ffi::pio::UDI_PIO_BUF as u16
-		|($crate::pio::vals::regs::$mem_reg as u16)
+		|($mem_reg as u16)
 		$(| $crate::pio::vals::stride::$mem_stride << 5)?
-		|($crate::pio::vals::regs::$pio_reg as u16) << 7
+		|($pio_reg as u16) << 7
 		$(| $crate::pio::vals::stride::$pio_stride << 10)?
-		|($crate::pio::vals::regs::$count_reg as u16) << 12
+		|($count_reg as u16) << 12
 	};
 	(@rep_args scratch $mem_reg:ident $($mem_stride:ident)?, $pio_reg:ident $($pio_stride:ident)?, $count_reg:ident) => {
 		$crate::ffi::pio::UDI_PIO_SCRATCH as u16
-		|($crate::pio::vals::regs::$mem_reg as u16)
+		|($mem_reg as u16)
 		$(| $crate::pio::vals::stride::$mem_stride << 5)?
-		|($crate::pio::vals::regs::$pio_reg as u16) << 7
+		|($pio_reg as u16) << 7
 		$(| $crate::pio::vals::stride::$pio_stride << 10)?
-		|($crate::pio::vals::regs::$count_reg as u16) << 12
+		|($count_reg as u16) << 12
 	};
 }
\ No newline at end of file
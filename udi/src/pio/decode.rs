@@ -0,0 +1,220 @@
+//! Decoding of raw PIO transaction operations
+use crate::ffi::pio::udi_pio_trans_t;
+use super::vals::{ops_group_a, ops_group_b, ops_group_c};
+
+/// A memory/register reference used by group A operations, and by the repeat operations
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum MemRef {
+	/// Register direct
+	Direct(u8),
+	/// Scratch memory, offset given by the register
+	Scratch(u8),
+	/// Buffer, offset given by the register
+	Buf(u8),
+	/// The `mem_ptr` block, offset given by the register
+	Mem(u8),
+}
+impl MemRef {
+	/// Decode from the low five bits of an operation
+	pub const fn from_bits(v: u8) -> MemRef {
+		let r = v & 7;
+		match v & 0x18 {
+		crate::ffi::pio::UDI_PIO_DIRECT => MemRef::Direct(r),
+		crate::ffi::pio::UDI_PIO_SCRATCH => MemRef::Scratch(r),
+		crate::ffi::pio::UDI_PIO_BUF => MemRef::Buf(r),
+		crate::ffi::pio::UDI_PIO_MEM => MemRef::Mem(r),
+		_ => unreachable!(),
+		}
+	}
+	/// The register used by this reference (either directly, or as the memory offset)
+	pub const fn reg(&self) -> u8 {
+		match *self {
+		MemRef::Direct(r) | MemRef::Scratch(r) | MemRef::Buf(r) | MemRef::Mem(r) => r,
+		}
+	}
+}
+
+/// Condition for `CSKIP`
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Condition {
+	/// Skip if zero
+	Z,
+	/// Skip if non-zero
+	NZ,
+	/// Skip if negative
+	Neg,
+	/// Skip if non-negative
+	NNeg,
+}
+
+/// Decoded operands for the `REP_IN_IND`/`REP_OUT_IND` operations
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub struct RepArgs {
+	/// Memory location (incremented by `mem_stride` each iteration, if not direct)
+	pub mem: MemRef,
+	/// Raw stride code for `mem` (0 = none, 1 = STEP1, 2 = STEP2, 3 = STEP4)
+	pub mem_stride: u8,
+	/// Register holding the device register offset
+	pub pio_reg: u8,
+	/// Raw stride code for the device register
+	pub pio_stride: u8,
+	/// Register holding the repeat count
+	pub count_reg: u8,
+}
+impl RepArgs {
+	const fn from_operand(v: u16) -> RepArgs {
+		RepArgs {
+			mem: MemRef::from_bits((v & 0x1F) as u8),
+			mem_stride: ((v >> 5) & 3) as u8,
+			pio_reg: ((v >> 7) & 7) as u8,
+			pio_stride: ((v >> 10) & 3) as u8,
+			count_reg: ((v >> 12) & 7) as u8,
+		}
+	}
+	/// Convert a stride code into a distance
+	pub const fn stride_distance(code: u8) -> u32 {
+		if code == 0 { 0 } else { 1 << (code - 1) }
+	}
+}
+
+/// A decoded PIO operation
+///
+/// `size` fields are the raw `tran_size` (byte count is `1 << size`)
+// NOTE: Fields are named after the operands in the variant documentation
+#[allow(missing_docs)]
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Op {
+	/// `IN.size dst, reg` - Read from the device register at `reg`
+	In { size: u8, dst: MemRef, reg: u16 },
+	/// `OUT.size reg, src` - Write to the device register at `reg`
+	Out { size: u8, src: MemRef, reg: u16 },
+	/// `LOAD.size dst, src` - Copy into a register
+	Load { size: u8, src: MemRef, dst: u8 },
+	/// `STORE.size dst, src` - Copy out of a register
+	Store { size: u8, dst: MemRef, src: u8 },
+	/// `LOAD_IMM.size dst, imm`
+	LoadImm { size: u8, dst: u8, imm: u16 },
+	/// `CSKIP.size reg cond` - Skip the next operation if the condition holds
+	CSkip { size: u8, reg: u8, cond: Condition },
+	/// `IN_IND.size dst, pio_reg` - Read from the device register given in `pio_reg`
+	InInd { size: u8, dst: u8, pio_reg: u8 },
+	/// `OUT_IND.size pio_reg, src` - Write to the device register given in `pio_reg`
+	OutInd { size: u8, src: u8, pio_reg: u8 },
+	/// `SHIFT_LEFT.size reg, bits`
+	ShiftLeft { size: u8, reg: u8, bits: u16 },
+	/// `SHIFT_RIGHT.size reg, bits`
+	ShiftRight { size: u8, reg: u8, bits: u16 },
+	/// `AND.size dst, src`
+	And { size: u8, dst: u8, src: u8 },
+	/// `AND_IMM.size dst, imm`
+	AndImm { size: u8, dst: u8, imm: u16 },
+	/// `OR.size dst, src`
+	Or { size: u8, dst: u8, src: u8 },
+	/// `OR_IMM.size dst, imm`
+	OrImm { size: u8, dst: u8, imm: u16 },
+	/// `XOR.size dst, src`
+	Xor { size: u8, dst: u8, src: u8 },
+	/// `ADD.size dst, src`
+	Add { size: u8, dst: u8, src: u8 },
+	/// `ADD_IMM.size dst, imm` - Immediate is sign extended
+	AddImm { size: u8, dst: u8, imm: u16 },
+	/// `SUB.size dst, src`
+	Sub { size: u8, dst: u8, src: u8 },
+	/// `BRANCH label`
+	Branch(u16),
+	/// `LABEL label`
+	Label(u16),
+	/// `REP_IN_IND.size [mem], pio_reg, count`
+	RepInInd { size: u8, args: RepArgs },
+	/// `REP_OUT_IND.size [mem], pio_reg, count`
+	RepOutInd { size: u8, args: RepArgs },
+	/// `DELAY us`
+	Delay(u16),
+	/// `BARRIER`
+	Barrier(u16),
+	/// `SYNC`
+	Sync(u16),
+	/// `SYNC_OUT`
+	SyncOut(u16),
+	/// `DEBUG`
+	Debug(u16),
+	/// `END.size reg` - End, returning the value of a register
+	End { size: u8, reg: u8 },
+	/// `END_IMM value` - End, returning an immediate
+	EndImm(u16),
+}
+
+/// Error returned by [Op::decode]
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum DecodeError {
+	/// The opcode isn't allocated
+	BadOpcode(u8),
+	/// The `tran_size` field is out of range
+	BadSize(u8),
+	/// The `CSKIP` condition code is out of range
+	BadCondition(u16),
+}
+
+impl Op {
+	/// Decode a raw transaction operation
+	pub const fn decode(op: &udi_pio_trans_t) -> Result<Op,DecodeError> {
+		let size = op.tran_size;
+		if size > 5 {
+			return Err(DecodeError::BadSize(size));
+		}
+		let operand = op.operand;
+		Ok(if op.pio_op < 0x80 {
+			let r = MemRef::from_bits(op.pio_op & 0x1F);
+			match op.pio_op & 0xE0 {
+			ops_group_a::IN => Op::In { size, dst: r, reg: operand },
+			ops_group_a::OUT => Op::Out { size, src: r, reg: operand },
+			ops_group_a::LOAD => Op::Load { size, src: r, dst: operand as u8 & 7 },
+			ops_group_a::STORE => Op::Store { size, dst: r, src: operand as u8 & 7 },
+			_ => unreachable!(),
+			}
+		}
+		else if op.pio_op < 0xF0 {
+			let reg = op.pio_op & 7;
+			let reg2 = operand as u8 & 7;
+			match op.pio_op & 0xF8 {
+			ops_group_b::LOAD_IMM => Op::LoadImm { size, dst: reg, imm: operand },
+			ops_group_b::CSKIP => Op::CSkip { size, reg, cond: match operand {
+				0 => Condition::Z,
+				1 => Condition::NZ,
+				2 => Condition::Neg,
+				3 => Condition::NNeg,
+				_ => return Err(DecodeError::BadCondition(operand)),
+				} },
+			ops_group_b::IN_IND => Op::InInd { size, dst: reg, pio_reg: reg2 },
+			ops_group_b::OUT_IND => Op::OutInd { size, src: reg, pio_reg: reg2 },
+			ops_group_b::SHIFT_LEFT => Op::ShiftLeft { size, reg, bits: operand },
+			ops_group_b::SHIFT_RIGHT => Op::ShiftRight { size, reg, bits: operand },
+			ops_group_b::AND => Op::And { size, dst: reg, src: reg2 },
+			ops_group_b::AND_IMM => Op::AndImm { size, dst: reg, imm: operand },
+			ops_group_b::OR => Op::Or { size, dst: reg, src: reg2 },
+			ops_group_b::OR_IMM => Op::OrImm { size, dst: reg, imm: operand },
+			ops_group_b::XOR => Op::Xor { size, dst: reg, src: reg2 },
+			ops_group_b::ADD => Op::Add { size, dst: reg, src: reg2 },
+			ops_group_b::ADD_IMM => Op::AddImm { size, dst: reg, imm: operand },
+			ops_group_b::SUB => Op::Sub { size, dst: reg, src: reg2 },
+			_ => return Err(DecodeError::BadOpcode(op.pio_op)),
+			}
+		}
+		else {
+			match op.pio_op {
+			ops_group_c::BRANCH => Op::Branch(operand),
+			ops_group_c::LABEL => Op::Label(operand),
+			ops_group_c::REP_IN_IND => Op::RepInInd { size, args: RepArgs::from_operand(operand) },
+			ops_group_c::REP_OUT_IND => Op::RepOutInd { size, args: RepArgs::from_operand(operand) },
+			ops_group_c::DELAY => Op::Delay(operand),
+			ops_group_c::BARRIER => Op::Barrier(operand),
+			ops_group_c::SYNC => Op::Sync(operand),
+			ops_group_c::SYNC_OUT => Op::SyncOut(operand),
+			ops_group_c::DEBUG => Op::Debug(operand),
+			ops_group_c::END => Op::End { size, reg: operand as u8 & 7 },
+			ops_group_c::END_IMM => Op::EndImm(operand),
+			_ => return Err(DecodeError::BadOpcode(op.pio_op)),
+			}
+		})
+	}
+}
@@ -0,0 +1,115 @@
+//! Disassembly of PIO transaction lists, using the [crate::define_pio_ops] syntax
+use crate::ffi::pio::udi_pio_trans_t;
+use super::decode::{Op, MemRef, Condition, RepArgs};
+use ::core::fmt;
+
+/// Wrapper around a transaction list, with a `Debug` impl that prints one operation per line
+///
+/// ```
+/// udi::define_pio_ops!{ READ =
+///     IN.B R0, 0x10;
+///     END.B R0;
+/// }
+/// assert_eq!(format!("{:?}", udi::pio::PioProgram(&READ)), "0: IN.B R0, 0x10;\n1: END.B R0;\n");
+/// ```
+#[derive(Copy,Clone)]
+pub struct PioProgram<'a>(pub &'a [udi_pio_trans_t]);
+impl fmt::Debug for PioProgram<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i,op) in self.0.iter().enumerate() {
+			match Op::decode(op) {
+			Ok(op) => writeln!(f, "{}: {};", i, op)?,
+			Err(e) => writeln!(f, "{}: /* {:?} */ {:#04x} {} {:#06x};", i, e, op.pio_op, op.tran_size, op.operand)?,
+			}
+		}
+		Ok( () )
+	}
+}
+
+/// Size suffix for an operation
+struct Size(u8);
+impl fmt::Display for Size {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.0 {
+		0 => f.write_str("B"),
+		1 => f.write_str("S"),
+		2 => f.write_str("L"),
+		v => write!(f, "_{}", 1 << v),
+		}
+	}
+}
+/// Stride suffix for the repeat operations
+struct Stride(u8);
+impl fmt::Display for Stride {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.0 {
+		0 => Ok( () ),
+		v => write!(f, " STEP{}", RepArgs::stride_distance(v)),
+		}
+	}
+}
+
+impl fmt::Display for MemRef {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+		MemRef::Direct(r) => write!(f, "R{}", r),
+		MemRef::Scratch(r) => write!(f, "[scratch R{}]", r),
+		MemRef::Buf(r) => write!(f, "[buf R{}]", r),
+		MemRef::Mem(r) => write!(f, "[mem R{}]", r),
+		}
+	}
+}
+impl fmt::Display for Condition {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			Condition::Z => "Z",
+			Condition::NZ => "NZ",
+			Condition::Neg => "Neg",
+			Condition::NNeg => "NNeg",
+			})
+	}
+}
+impl fmt::Display for Op {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fn rep(f: &mut fmt::Formatter, name: &str, size: u8, a: &RepArgs) -> fmt::Result {
+			let (ty, r) = match a.mem {
+				MemRef::Direct(r) => ("", r),
+				MemRef::Scratch(r) => ("scratch ", r),
+				MemRef::Buf(r) => ("buf ", r),
+				MemRef::Mem(r) => ("mem ", r),
+				};
+			write!(f, "{}.{} [{}R{}{}], R{}{}, R{}", name, Size(size), ty, r, Stride(a.mem_stride), a.pio_reg, Stride(a.pio_stride), a.count_reg)
+		}
+		match *self {
+		Op::In { size, dst, reg } => write!(f, "IN.{} {}, {:#x}", Size(size), dst, reg),
+		Op::Out { size, src, reg } => write!(f, "OUT.{} {:#x}, {}", Size(size), reg, src),
+		Op::Load { size, src, dst } => write!(f, "LOAD.{} R{}, {}", Size(size), dst, src),
+		Op::Store { size, dst, src } => write!(f, "STORE.{} {}, R{}", Size(size), dst, src),
+		Op::LoadImm { size, dst, imm } => write!(f, "LOAD_IMM.{} R{}, {:#x}", Size(size), dst, imm),
+		Op::CSkip { size, reg, cond } => write!(f, "CSKIP.{} R{} {}", Size(size), reg, cond),
+		Op::InInd { size, dst, pio_reg } => write!(f, "IN_IND.{} R{}, R{}", Size(size), dst, pio_reg),
+		Op::OutInd { size, src, pio_reg } => write!(f, "OUT_IND.{} R{}, R{}", Size(size), pio_reg, src),
+		Op::ShiftLeft { size, reg, bits } => write!(f, "SHIFT_LEFT.{} R{}, {}", Size(size), reg, bits),
+		Op::ShiftRight { size, reg, bits } => write!(f, "SHIFT_RIGHT.{} R{}, {}", Size(size), reg, bits),
+		Op::And { size, dst, src } => write!(f, "AND.{} R{}, R{}", Size(size), dst, src),
+		Op::AndImm { size, dst, imm } => write!(f, "AND_IMM.{} R{}, {:#x}", Size(size), dst, imm),
+		Op::Or { size, dst, src } => write!(f, "OR.{} R{}, R{}", Size(size), dst, src),
+		Op::OrImm { size, dst, imm } => write!(f, "OR_IMM.{} R{}, {:#x}", Size(size), dst, imm),
+		Op::Xor { size, dst, src } => write!(f, "XOR.{} R{}, R{}", Size(size), dst, src),
+		Op::Add { size, dst, src } => write!(f, "ADD.{} R{}, R{}", Size(size), dst, src),
+		Op::AddImm { size, dst, imm } => write!(f, "ADD_IMM.{} R{}, {:#x}", Size(size), dst, imm),
+		Op::Sub { size, dst, src } => write!(f, "SUB.{} R{}, R{}", Size(size), dst, src),
+		Op::Branch(l) => write!(f, "BRANCH {}", l),
+		Op::Label(l) => write!(f, "LABEL {}", l),
+		Op::RepInInd { size, ref args } => rep(f, "REP_IN_IND", size, args),
+		Op::RepOutInd { size, ref args } => rep(f, "REP_OUT_IND", size, args),
+		Op::Delay(v) => write!(f, "DELAY {}", v),
+		Op::Barrier(v) => write!(f, "BARRIER {}", v),
+		Op::Sync(v) => write!(f, "SYNC {}", v),
+		Op::SyncOut(v) => write!(f, "SYNC_OUT {}", v),
+		Op::Debug(v) => write!(f, "DEBUG {}", v),
+		Op::End { size, reg } => write!(f, "END.{} R{}", Size(size), reg),
+		Op::EndImm(v) => write!(f, "END_IMM {:#x}", v),
+		}
+	}
+}
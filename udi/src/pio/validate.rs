@@ -0,0 +1,474 @@
+//! Static validation of PIO transaction lists
+//!
+//! Walks every path through a transaction list (starting at label 0, and at any label that isn't the target of a
+//! `BRANCH`) tracking what is known about each register, so programs can be checked without a running environment.
+//!
+//! Everything here is `const`, so checks can be done at compile time (e.g. `const _: () = assert!(validate(&OPS, 8).is_ok());`)
+use crate::ffi::pio::udi_pio_trans_t;
+use super::decode::{Op, MemRef, RepArgs, DecodeError};
+
+/// Maximum number of operations that [validate] can handle
+pub const MAX_OPS: usize = 256;
+
+/// Information about a valid transaction list, returned by [validate]
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub struct Summary {
+	/// Number of entry points (label 0, plus every label that isn't a branch target)
+	pub entry_points: usize,
+	/// Maximum number of device accesses (reads and writes) made by a single `udi_pio_trans` call
+	///
+	/// `None` if this can't be bounded (the program loops, or a repeat count isn't a constant)
+	pub max_device_accesses: Option<u32>,
+}
+
+/// Error returned by [validate]
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub struct ValidateError {
+	/// Index of the offending operation
+	pub index: usize,
+	/// What was wrong with it
+	pub kind: ValidateErrorKind,
+}
+/// The reason for a [ValidateError]
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum ValidateErrorKind {
+	/// The list is longer than [MAX_OPS]
+	TooLong,
+	/// The operation could not be decoded
+	Decode(DecodeError),
+	/// `LABEL 0` is implicit at the start of the list, and cannot be defined
+	LabelZero,
+	/// A label was defined twice
+	DuplicateLabel(u16),
+	/// A `BRANCH` targets a label that doesn't exist
+	MissingLabel(u16),
+	/// A register might be read before it is written
+	UninitialisedRegister(u8),
+	/// A device access might be outside of the mapped `length`
+	OutOfRange {
+		/// Offset of the (last) access
+		offset: u32,
+		/// Size of the access in bytes
+		size: u32,
+	},
+	/// Execution can run past the end of the list without an `END`/`END_IMM`
+	FallsOffEnd,
+	/// The serialization domain passed to `udi_pio_map` is not below `pio_serialization_limit`
+	SerializationDomain {
+		/// The requested domain
+		domain: u8,
+		/// The driver's `pio_serialization_limit`
+		limit: u8,
+	},
+}
+impl ::core::fmt::Display for ValidateError {
+	fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+		write!(f, "op #{}: ", self.index)?;
+		match self.kind {
+		ValidateErrorKind::TooLong => write!(f, "list is longer than {} operations", MAX_OPS),
+		ValidateErrorKind::Decode(e) => write!(f, "cannot decode ({:?})", e),
+		ValidateErrorKind::LabelZero => f.write_str("LABEL 0 cannot be defined"),
+		ValidateErrorKind::DuplicateLabel(l) => write!(f, "LABEL {} defined twice", l),
+		ValidateErrorKind::MissingLabel(l) => write!(f, "BRANCH to undefined label {}", l),
+		ValidateErrorKind::UninitialisedRegister(r) => write!(f, "R{} may be used uninitialised", r),
+		ValidateErrorKind::OutOfRange { offset, size } => write!(f, "access of {} bytes at {:#x} exceeds mapping", size, offset),
+		ValidateErrorKind::FallsOffEnd => f.write_str("execution runs past the end of the list"),
+		ValidateErrorKind::SerializationDomain { domain, limit } => write!(f, "serialization domain {} not below limit {}", domain, limit),
+		}
+	}
+}
+
+/// Check a transaction list that will be mapped with the given `length`
+///
+/// Reports the first problem found (in operation order), otherwise returns a summary of the list's behaviour.
+///
+/// ```
+/// udi::define_pio_ops!{ PULSE =
+///     LOAD_IMM.B R0, 1;
+///     OUT.B 4, R0;
+///     LOAD_IMM.B R0, 0;
+///     OUT.B 4, R0;
+///     END_IMM 0;
+/// }
+/// const _: () = assert!(udi::pio::validate(&PULSE, 8).is_ok());
+/// const _: () = assert!(udi::pio::validate(&PULSE, 4).is_err());
+/// const SUMMARY: Result<udi::pio::Summary, udi::pio::ValidateError> = udi::pio::validate(&PULSE, 8);
+/// assert!(matches!(SUMMARY, Ok(udi::pio::Summary { max_device_accesses: Some(2), .. })));
+/// ```
+pub const fn validate(trans_list: &[udi_pio_trans_t], length: u32) -> Result<Summary,ValidateError> {
+	let n = trans_list.len();
+	if n > MAX_OPS {
+		return Err(ValidateError { index: n, kind: ValidateErrorKind::TooLong });
+	}
+
+	// Decode everything first, and check the labels
+	let mut ops = [Op::EndImm(0); MAX_OPS];
+	let mut i = 0;
+	while i < n {
+		ops[i] = match Op::decode(&trans_list[i]) {
+			Ok(op) => op,
+			Err(e) => return Err(ValidateError { index: i, kind: ValidateErrorKind::Decode(e) }),
+			};
+		i += 1;
+	}
+	let ops = ops.split_at(n).0;
+	let mut i = 0;
+	while i < n {
+		let kind = match ops[i] {
+			Op::Label(0) => Some(ValidateErrorKind::LabelZero),
+			Op::Label(l) if !matches!(find_label(ops, l), Some(j) if j == i) => Some(ValidateErrorKind::DuplicateLabel(l)),
+			Op::Branch(l) if find_label(ops, l).is_none() => Some(ValidateErrorKind::MissingLabel(l)),
+			_ => None,
+			};
+		if let Some(kind) = kind {
+			return Err(ValidateError { index: i, kind });
+		}
+		i += 1;
+	}
+	if n == 0 {
+		return Err(ValidateError { index: 0, kind: ValidateErrorKind::FallsOffEnd });
+	}
+
+	let mut rv = Summary { entry_points: 0, max_device_accesses: Some(0) };
+	let mut entry = 0;
+	while entry < n {
+		let is_entry = match ops[entry] {
+			_ if entry == 0 => true,
+			Op::Label(l) => !is_branch_target(ops, l),
+			_ => false,
+			};
+		if is_entry {
+			rv.entry_points += 1;
+			let states = propagate(ops, entry);
+			if let Err(e) = check(ops, &states, length) {
+				return Err(e);
+			}
+			rv.max_device_accesses = match (rv.max_device_accesses, max_accesses(ops, &states, entry)) {
+				(Some(a), Some(b)) => Some(if a > b { a } else { b }),
+				_ => None,
+				};
+		}
+		entry += 1;
+	}
+	Ok(rv)
+}
+
+/// Check a transaction list along with the `serialization_domain` it will be mapped with
+///
+/// `serialization_domain` must be below the driver's `pio_serialization_limit` static property.
+pub const fn validate_map(
+	trans_list: &[udi_pio_trans_t], length: u32,
+	serialization_domain: crate::ffi::udi_index_t, pio_serialization_limit: u8
+) -> Result<Summary,ValidateError>
+{
+	if serialization_domain.0 >= pio_serialization_limit {
+		return Err(ValidateError {
+			index: 0,
+			kind: ValidateErrorKind::SerializationDomain { domain: serialization_domain.0, limit: pio_serialization_limit },
+		});
+	}
+	validate(trans_list, length)
+}
+
+/// What is known about a register at a given point
+#[derive(Copy,Clone)]
+enum RegState {
+	/// Never written on any path
+	Uninit,
+	/// Not written on at least one path
+	MaybeUninit,
+	/// Written, with an unknown value
+	Any,
+	/// Written, with a known value
+	Const(u32),
+}
+impl RegState {
+	const fn eq(&self, other: &RegState) -> bool {
+		match (*self, *other) {
+		(RegState::Uninit, RegState::Uninit) => true,
+		(RegState::MaybeUninit, RegState::MaybeUninit) => true,
+		(RegState::Any, RegState::Any) => true,
+		(RegState::Const(a), RegState::Const(b)) => a == b,
+		_ => false,
+		}
+	}
+	const fn merge(self, other: RegState) -> RegState {
+		match (self, other) {
+		(a, b) if a.eq(&b) => a,
+		(RegState::Uninit | RegState::MaybeUninit, _) | (_, RegState::Uninit | RegState::MaybeUninit) => RegState::MaybeUninit,
+		_ => RegState::Any,
+		}
+	}
+	const fn is_init(&self) -> bool {
+		!matches!(self, RegState::Uninit | RegState::MaybeUninit)
+	}
+}
+type Regs = [RegState; 8];
+
+const fn find_label(ops: &[Op], label: u16) -> Option<usize> {
+	if label == 0 {
+		return Some(0);
+	}
+	let mut i = 0;
+	while i < ops.len() {
+		if matches!(ops[i], Op::Label(l) if l == label) {
+			return Some(i);
+		}
+		i += 1;
+	}
+	None
+}
+const fn is_branch_target(ops: &[Op], label: u16) -> bool {
+	let mut i = 0;
+	while i < ops.len() {
+		if matches!(ops[i], Op::Branch(l) if l == label) {
+			return true;
+		}
+		i += 1;
+	}
+	false
+}
+
+/// Get the possible next operations after `i` (`ops.len()` indicates running off the end)
+const fn successors(ops: &[Op], i: usize) -> [Option<usize>; 2] {
+	match ops[i] {
+	Op::End { .. } | Op::EndImm(_) => [None, None],
+	Op::Branch(l) => [find_label(ops, l), None],
+	Op::CSkip { .. } => [Some(i + 1), Some(if i + 2 < ops.len() { i + 2 } else { ops.len() })],
+	_ => [Some(i + 1), None],
+	}
+}
+
+const fn val(regs: &Regs, r: u8) -> Option<u32> {
+	match regs[r as usize] {
+	RegState::Const(v) => Some(v),
+	_ => None,
+	}
+}
+
+/// Truncate a known value to the operation size (sizes above 32 bits are not tracked)
+const fn sized(size: u8, v: Option<u32>) -> RegState {
+	match (size, v) {
+	(0, Some(v)) => RegState::Const(v & 0xFF),
+	(1, Some(v)) => RegState::Const(v & 0xFFFF),
+	(2, Some(v)) => RegState::Const(v),
+	_ => RegState::Any,
+	}
+}
+
+/// Apply the register effects of an operation
+const fn step(op: &Op, regs: &mut Regs) {
+	let (dst, new) = match *op {
+		Op::In { dst: MemRef::Direct(r), .. } => (r, RegState::Any),
+		Op::InInd { dst, .. } => (dst, RegState::Any),
+		Op::RepInInd { args: RepArgs { mem: MemRef::Direct(r), .. }, .. } => (r, RegState::Any),
+		Op::Load { size, src: MemRef::Direct(s), dst } => (dst, sized(size, val(regs, s))),
+		Op::Load { dst, .. } => (dst, RegState::Any),
+		Op::Store { size, dst: MemRef::Direct(d), src } => (d, sized(size, val(regs, src))),
+		Op::LoadImm { size, dst, imm } => (dst, if size <= 2 { sized(size, Some(imm as u32)) } else { RegState::Const(imm as u32) }),
+		Op::ShiftLeft { size, reg, bits } => (reg, sized(size, match val(regs, reg) {
+			Some(v) => Some(if bits < 32 { v << bits } else { 0 }),
+			None => None,
+			})),
+		Op::ShiftRight { size, reg, bits } => (reg, sized(size, match val(regs, reg) {
+			Some(v) => Some(if bits < 32 { v >> bits } else { 0 }),
+			None => None,
+			})),
+		Op::AndImm { size, dst, imm } => (dst, sized(size, match val(regs, dst) { Some(a) => Some(a & imm as u32), None => None })),
+		Op::OrImm { size, dst, imm } => (dst, sized(size, match val(regs, dst) { Some(a) => Some(a | imm as u32), None => None })),
+		Op::AddImm { size, dst, imm } => (dst, sized(size, match val(regs, dst) { Some(a) => Some(a.wrapping_add(imm as i16 as u32)), None => None })),
+		Op::And { size, dst, src } | Op::Or { size, dst, src } | Op::Xor { size, dst, src }
+		| Op::Add { size, dst, src } | Op::Sub { size, dst, src } => (dst, sized(size, match (val(regs, dst), val(regs, src)) {
+			(Some(a), Some(b)) => Some(match *op {
+				Op::And { .. } => a & b,
+				Op::Or { .. } => a | b,
+				Op::Xor { .. } => a ^ b,
+				Op::Add { .. } => a.wrapping_add(b),
+				_ => a.wrapping_sub(b),
+				}),
+			_ => None,
+			})),
+		_ => return,
+		};
+	regs[dst as usize] = new;
+}
+
+/// Get the register used to address memory (if not a direct register reference)
+const fn mem_reg(m: MemRef) -> Option<u8> {
+	match m {
+	MemRef::Direct(_) => None,
+	_ => Some(m.reg()),
+	}
+}
+
+/// Get the registers read by an operation
+const fn reads(op: &Op) -> [Option<u8>; 3] {
+	match *op {
+	Op::In { dst, .. } => [mem_reg(dst), None, None],
+	Op::Out { src, .. } => [Some(src.reg()), None, None],
+	Op::Load { src, .. } => [Some(src.reg()), None, None],
+	Op::Store { dst, src, .. } => [Some(src), mem_reg(dst), None],
+	Op::CSkip { reg, .. } => [Some(reg), None, None],
+	Op::InInd { pio_reg, .. } => [Some(pio_reg), None, None],
+	Op::OutInd { src, pio_reg, .. } => [Some(src), Some(pio_reg), None],
+	Op::ShiftLeft { reg, .. } | Op::ShiftRight { reg, .. } => [Some(reg), None, None],
+	Op::AndImm { dst, .. } | Op::OrImm { dst, .. } | Op::AddImm { dst, .. } => [Some(dst), None, None],
+	Op::And { dst, src, .. } | Op::Or { dst, src, .. } | Op::Xor { dst, src, .. }
+	| Op::Add { dst, src, .. } | Op::Sub { dst, src, .. } => [Some(dst), Some(src), None],
+	Op::RepInInd { args, .. } => [mem_reg(args.mem), Some(args.pio_reg), Some(args.count_reg)],
+	Op::RepOutInd { args, .. } => [Some(args.mem.reg()), Some(args.pio_reg), Some(args.count_reg)],
+	Op::End { reg, .. } => [Some(reg), None, None],
+	_ => [None, None, None],
+	}
+}
+
+/// Calculate the register state on entry to every operation reachable from `entry` (`None` = unreachable)
+const fn propagate(ops: &[Op], entry: usize) -> [Option<Regs>; MAX_OPS] {
+	let mut states = [None; MAX_OPS];
+	states[entry] = Some([RegState::Uninit; 8]);
+	let mut changed = true;
+	while changed {
+		changed = false;
+		let mut i = 0;
+		while i < ops.len() {
+			if let Some(mut regs) = states[i] {
+				step(&ops[i], &mut regs);
+				let next = successors(ops, i);
+				let mut j = 0;
+				while j < next.len() {
+					if let Some(s) = next[j] {
+						if s < ops.len() {
+							changed |= merge_into(&mut states[s], &regs);
+						}
+					}
+					j += 1;
+				}
+			}
+			i += 1;
+		}
+	}
+	states
+}
+/// Merge a register state into a slot, returning `true` if the slot changed
+const fn merge_into(slot: &mut Option<Regs>, regs: &Regs) -> bool {
+	match slot {
+	None => {
+		*slot = Some(*regs);
+		true
+		},
+	Some(old) => {
+		let mut changed = false;
+		let mut r = 0;
+		while r < 8 {
+			let new = old[r].merge(regs[r]);
+			if !new.eq(&old[r]) {
+				old[r] = new;
+				changed = true;
+			}
+			r += 1;
+		}
+		changed
+		},
+	}
+}
+
+/// Device accesses made by an operation
+struct Access {
+	size: u8,
+	/// Offset of the first access, if known
+	first: Option<u32>,
+	stride: u32,
+	/// Number of accesses, if known
+	count: Option<u32>,
+}
+const fn accesses(op: &Op, regs: &Regs) -> Option<Access> {
+	match *op {
+	Op::In { size, reg, .. } | Op::Out { size, reg, .. } => Some(Access { size, first: Some(reg as u32), stride: 0, count: Some(1) }),
+	Op::InInd { size, pio_reg, .. } | Op::OutInd { size, pio_reg, .. } => Some(Access { size, first: val(regs, pio_reg), stride: 0, count: Some(1) }),
+	Op::RepInInd { size, args } | Op::RepOutInd { size, args } => Some(Access {
+		size,
+		first: val(regs, args.pio_reg),
+		stride: RepArgs::stride_distance(args.pio_stride),
+		count: val(regs, args.count_reg),
+		}),
+	_ => None,
+	}
+}
+
+/// Check all reachable operations for register and range errors
+const fn check(ops: &[Op], states: &[Option<Regs>; MAX_OPS], length: u32) -> Result<(),ValidateError> {
+	let mut i = 0;
+	while i < ops.len() {
+		if let Some(regs) = &states[i] {
+			let op = &ops[i];
+			let r = reads(op);
+			let mut j = 0;
+			while j < r.len() {
+				if let Some(r) = r[j] {
+					if !regs[r as usize].is_init() {
+						return Err(ValidateError { index: i, kind: ValidateErrorKind::UninitialisedRegister(r) });
+					}
+				}
+				j += 1;
+			}
+			if let Some(Access { size, first: Some(first), stride, count }) = accesses(op, regs) {
+				let count = match count { Some(c) => c, None => 1 };
+				let size = 1u32 << size;
+				let last = first as u64 + stride as u64 * count.saturating_sub(1) as u64;
+				if count > 0 && last + size as u64 > length as u64 {
+					return Err(ValidateError { index: i, kind: ValidateErrorKind::OutOfRange { offset: last as u32, size } });
+				}
+			}
+			if matches!(successors(ops, i), [Some(s), _] | [_, Some(s)] if s == ops.len()) {
+				return Err(ValidateError { index: i, kind: ValidateErrorKind::FallsOffEnd });
+			}
+		}
+		i += 1;
+	}
+	Ok(())
+}
+
+#[derive(Copy,Clone)]
+enum Visit {
+	New,
+	Active,
+	Done(u32),
+}
+/// Find the largest number of device accesses on any path from `entry`, or `None` if there's a loop
+const fn max_accesses(ops: &[Op], states: &[Option<Regs>; MAX_OPS], entry: usize) -> Option<u32> {
+	walk(ops, states, &mut [Visit::New; MAX_OPS], entry)
+}
+const fn walk(ops: &[Op], states: &[Option<Regs>; MAX_OPS], visits: &mut [Visit; MAX_OPS], i: usize) -> Option<u32> {
+	match visits[i] {
+	Visit::New => {},
+	Visit::Active => return None,
+	Visit::Done(v) => return Some(v),
+	}
+	visits[i] = Visit::Active;
+	let own = match &states[i] {
+		None => return None,
+		Some(regs) => match accesses(&ops[i], regs) {
+			Some(Access { count: Some(c), .. }) => c,
+			Some(Access { count: None, .. }) => return None,
+			None => 0,
+			},
+		};
+	let mut rest = 0;
+	let next = successors(ops, i);
+	let mut j = 0;
+	while j < next.len() {
+		if let Some(s) = next[j] {
+			if s < ops.len() {
+				match walk(ops, states, visits, s) {
+				Some(v) if v > rest => rest = v,
+				Some(_) => {},
+				None => return None,
+				}
+			}
+		}
+		j += 1;
+	}
+	let rv = own + rest;
+	visits[i] = Visit::Done(rv);
+	Some(rv)
+}
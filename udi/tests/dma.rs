@@ -0,0 +1,54 @@
+//! DMA constraint building, and device scatter lists
+//!
+//! NOTE: Allocation planning needs a [udi::physio::dma::DmaConstraints], so is tested in `udi_env`
+
+#[test]
+fn constraints() {
+    use udi::physio::dma::{ConstraintsBuilder, ConstraintsError, Attribute, ScgthFormat};
+    let attrs = ConstraintsBuilder::new()
+        .addressable_bits(32)
+        .max_scgth_elements(1)
+        .scgth_format(ScgthFormat::Bits32)
+        .scgth_driver_mapped()
+        .build().unwrap();
+    let raw: Vec<_> = attrs.iter().map(|a| (a.attr_type, a.attr_value)).collect();
+    assert_eq!(raw, [(100, 32), (120, 1), (121, (1 << 0) | (1 << 7))]);
+    // Changing the format keeps the mapping flags
+    let b = ConstraintsBuilder::new().scgth_driver_mapped().scgth_format(ScgthFormat::Either);
+    assert_eq!(b.get(Attribute::ScgthFormat), Some(0x83));
+
+    let err = |b: ConstraintsBuilder| b.build().unwrap_err();
+    assert_eq!(err(ConstraintsBuilder::new().addressable_bits(65)), ConstraintsError::OutOfRange { attr: Attribute::AddressableBits, value: 65, max: 64 });
+    assert_eq!(err(ConstraintsBuilder::new().element_length_bits(12).alignment_bits(12)),
+        ConstraintsError::Conflict(Attribute::AlignmentBits, Attribute::ElementLengthBits));
+    assert_eq!(err(ConstraintsBuilder::new().element_length_bits(12).element_granularity_bits(16)),
+        ConstraintsError::Conflict(Attribute::ElementGranularityBits, Attribute::ElementLengthBits));
+    assert_eq!(err(ConstraintsBuilder::new().max_scgth_elements(4).max_scgth_el_per_seg(8)),
+        ConstraintsError::Conflict(Attribute::ScgthMaxElPerSeg, Attribute::ScgthMaxElements));
+    // The specific attribute overrides the convenience one
+    assert!(ConstraintsBuilder::new().addressable_bits(64).data_addressable_bits(32).scgth_addressable_bits(32).scgth_format(ScgthFormat::Bits32).build().is_ok());
+    assert_eq!(err(ConstraintsBuilder::new().data_addressable_bits(40).scgth_format(ScgthFormat::Bits32)),
+        ConstraintsError::Conflict(Attribute::ScgthFormat, Attribute::DataAddressableBits));
+    // Mapping flags alone don't choose an element format
+    assert_eq!(err(ConstraintsBuilder::new().scgth_dma_mapped()), ConstraintsError::MissingScgthWidth);
+    assert!(ConstraintsBuilder::new().scgth_dma_mapped().scgth_format(ScgthFormat::Bits64).build().is_ok());
+}
+
+#[test]
+fn scatter_list() {
+    use udi::physio::dma::{ScatterListBuilder, ScatterListError};
+    let mut dst = [(0u64, 0u32); 4];
+    let enc = |a, l, _| (a, l);
+    // Zero-length elements are dropped, and contiguous elements are merged
+    let n = ScatterListBuilder::new(4).build([(0x1000, 0x100), (0x5000, 0), (0x1100, 0x100)], &mut dst, enc).unwrap();
+    assert_eq!(&dst[..n], [(0x1000, 0x200)]);
+    // Ending at the top of the address space is fine, crossing it is not
+    let narrow = ScatterListBuilder::new(4).addressable_bits(32);
+    assert_eq!(narrow.build([(0xFFFF_F000, 0x1000)], &mut dst, enc), Ok(1));
+    assert_eq!(narrow.build([(0xFFFF_F000, 0x1001)], &mut dst, enc), Err(ScatterListError::AddressTooWide { bus_addr: 0xFFFF_F000, bits: 32 }));
+    // The destination slice limits the entry count
+    let split = ScatterListBuilder::new(8).max_entry_len(0x100);
+    assert_eq!(split.build([(0, 0x400)], &mut dst, enc), Ok(4));
+    assert_eq!(split.build([(0, 0x401)], &mut dst, enc), Err(ScatterListError::TooManyEntries { max: 4 }));
+    assert_eq!(split.build([], &mut dst, enc), Ok(0));
+}
@@ -0,0 +1,66 @@
+//! Enumeration levels/results, and child ID tracking
+use udi::ffi::meta_mgmt::*;
+use udi::ffi::udi_index_t;
+use udi::init::{ChildEvent, ChildTracker, EnumerateLevel, EnumerateResult};
+
+#[test]
+fn raw_values() {
+    for l in [EnumerateLevel::Start, EnumerateLevel::StartRescan, EnumerateLevel::Next, EnumerateLevel::New, EnumerateLevel::Directed, EnumerateLevel::Release(7)] {
+        assert_eq!(EnumerateLevel::from_raw(l.to_raw(), 7), Some(l));
+    }
+    assert_eq!(EnumerateLevel::from_raw(UDI_ENUMERATE_RELEASE, 3), Some(EnumerateLevel::Release(3)));
+    assert_eq!(EnumerateLevel::from_raw(0, 0), None);
+    assert_eq!(EnumerateLevel::from_raw(7, 0), None);
+
+    let ok = EnumerateResult::from_raw(UDI_ENUMERATE_OK, udi_index_t(2), 5).unwrap();
+    assert_eq!(ok.to_raw(), (UDI_ENUMERATE_OK, udi_index_t(2), Some(5)));
+    assert_eq!(EnumerateResult::Removed(9).to_raw(), (UDI_ENUMERATE_REMOVED, udi_index_t(0), Some(9)));
+    assert_eq!(EnumerateResult::from_raw(UDI_ENUMERATE_REMOVED, udi_index_t(0), 9), Some(EnumerateResult::Removed(9)));
+    for r in [EnumerateResult::Leaf, EnumerateResult::Done, EnumerateResult::Rescan, EnumerateResult::RemovedSelf, EnumerateResult::Released, EnumerateResult::Failed] {
+        let (v, ops_idx, child_id) = r.to_raw();
+        assert_eq!(child_id, None);
+        assert_eq!(EnumerateResult::from_raw(v, ops_idx, 0), Some(r));
+    }
+    assert_eq!(EnumerateResult::Failed.to_raw().0, 255);
+    assert_eq!(EnumerateResult::from_raw(7, udi_index_t(0), 0), None);
+}
+
+#[test]
+fn child_tracker() {
+    let t = ChildTracker::<3>::new();
+    assert!(t.enumerated(1));
+    assert!(t.enumerated(1), "Re-enumerating is allowed");
+    assert_eq!(t.take_event(), None);
+
+    // Additions are reported once
+    assert!(t.add(2));
+    assert!(!t.add(2));
+    assert!(t.is_present(2));
+    assert_eq!(t.take_event(), Some(ChildEvent::Added(2)));
+    assert_eq!(t.take_event(), None);
+    assert_eq!(t.active().collect::<Vec<_>>(), [1, 2]);
+
+    // Removing an unreported child forgets it
+    assert!(t.add(3));
+    assert!(t.remove(3));
+    assert_eq!(t.take_event(), None);
+
+    // Removed IDs are held until released
+    assert!(!t.release(1));
+    assert!(t.remove(1));
+    assert!(!t.remove(1));
+    assert!(!t.is_present(1));
+    assert!(!t.enumerated(1));
+    assert_eq!(t.take_event(), Some(ChildEvent::Removed(1)));
+    assert!(!t.add(1));
+    assert!(t.release(1));
+    assert!(!t.release(1));
+    assert!(t.add(1));
+    assert_eq!(t.take_event().map(|e| e.child_id()), Some(1));
+
+    // Full
+    assert!(t.add(4));
+    assert!(!t.add(5));
+    assert!(!t.enumerated(5));
+    assert!(!t.remove(5));
+}
@@ -0,0 +1,23 @@
+//! MEI marshalling layouts
+use udi::ffi::layout::*;
+use udi::mei::{layout_concat, marshal_size};
+
+#[test]
+fn marshal_sizes() {
+    const PTR: usize = ::core::mem::size_of::<*mut ()>();
+    assert_eq!(marshal_size(&[UDI_DL_END]), 0);
+    assert_eq!(marshal_size(&[UDI_DL_UBIT8_T, UDI_DL_END]), PTR);
+    // Each entry is aligned to its size
+    assert_eq!(marshal_size(&[UDI_DL_UBIT8_T, UDI_DL_UBIT16_T, UDI_DL_UBIT8_T, UDI_DL_UBIT32_T, UDI_DL_END]), 12usize.next_multiple_of(PTR));
+    assert_eq!(marshal_size(&[UDI_DL_UBIT8_T, UDI_DL_CHANNEL_T, UDI_DL_END]), 2 * PTR);
+    // Matches the equivalent structure
+    #[repr(C)]
+    struct Args(u16, u8, u32, u8);
+    assert!(marshal_size(&[UDI_DL_UBIT16_T, UDI_DL_UBIT8_T, UDI_DL_UBIT32_T, UDI_DL_BOOLEAN_T, UDI_DL_END]) >= ::core::mem::size_of::<Args>());
+}
+
+#[test]
+fn concat() {
+    const L: [u8; 6] = layout_concat(&[&[UDI_DL_UBIT8_T], &[], &[UDI_DL_BUF, 0, 0, 1], &[UDI_DL_END]]);
+    assert_eq!(L, [UDI_DL_UBIT8_T, UDI_DL_BUF, 0, 0, 1, UDI_DL_END]);
+}
@@ -0,0 +1,61 @@
+//! GFX properties, ranges, and buffer areas
+use udi::meta_gfx::{BindInfo, BufferArea, BufferFlags, BufferInfo, Element, EnableState, Operator, Property, Range};
+
+#[test]
+fn values() {
+    for p in [Property::Enable, Property::Width, Property::ConnectorType, Property::VgaVSyncPol, Property::Other(100)] {
+        assert_eq!(Property::from_raw(p.to_raw()), p);
+    }
+    assert_eq!(Property::Height.to_raw(), 3);
+    assert_eq!(Property::from_raw(9), Property::Other(9));
+    assert_eq!(Property::custom(2).to_raw(), 1026);
+    assert_eq!(Property::custom(2).custom_index(), Some(2));
+    assert_eq!(Property::Width.custom_index(), None);
+
+    assert_eq!(EnableState::from_raw(2), EnableState::Reset);
+    assert_eq!(EnableState::from_raw(3), EnableState::Other(3));
+    assert_eq!(Operator::from_raw(35), Operator::DInput);
+    assert_eq!(Operator::Buffer.to_raw(), 27);
+
+    let elements: Vec<_> = BindInfo { connectors: 2, engines: 1 }.elements().collect();
+    assert_eq!(elements, [Element::Connector(0), Element::Connector(1), Element::Engine(0)]);
+    assert_eq!(Element::Engine(3).index(), 3);
+}
+
+#[test]
+fn ranges() {
+    let r = Range::stepped(8, 64, 8);
+    assert!(r.contains(8) && r.contains(16) && r.contains(64));
+    assert!(!r.contains(0) && !r.contains(12) && !r.contains(72));
+    assert!(Range::single(5).contains(5) && !Range::single(5).contains(6));
+    // A zero step only allows the minimum
+    assert!(Range::stepped(4, 8, 0).contains(4) && !Range::stepped(4, 8, 0).contains(5));
+    assert!(Range::list_contains(&[Range::single(1), Range::inclusive(10, 20)], 15));
+    assert!(!Range::list_contains(&[], 0));
+
+    let b = r.to_bytes();
+    assert_eq!(&b[..4], &8u32.to_ne_bytes());
+    assert_eq!(Range::from_bytes(&b), r);
+}
+
+#[test]
+fn buffers() {
+    let info = BufferInfo { width: 10, height: 4, bits_per_entry: 32, flags: BufferFlags::READ | BufferFlags::WRITE };
+    assert!(info.is_valid() && info.flags.contains(BufferFlags::WRITE));
+    assert!(!BufferInfo::default().is_valid());
+    assert_eq!(info.row_bytes(), 40);
+    assert_eq!(BufferInfo { bits_per_entry: 1, ..info }.row_bytes(), 2);
+
+    assert!(info.contains(&BufferArea::new(0, 0, 0, 10, 4)));
+    assert!(info.contains(&BufferArea::new(0, 9, 3, 1, 1)));
+    assert!(!info.contains(&BufferArea::new(0, 9, 0, 2, 1)));
+    assert!(!info.contains(&BufferArea::new(0, 0, !0, 1, 2)));
+    assert_eq!(info.area_bytes(&BufferArea::new(0, 1, 1, 3, 2)), 24);
+
+    // SAFE: All-zero is valid for the CB (null pointers)
+    let mut cb: udi::ffi::meta_gfx::udi_gfx_buffer_cb_t = unsafe { ::core::mem::zeroed() };
+    let area = BufferArea::new(1, 2, 3, 4, 5);
+    area.write_cb(&mut cb);
+    assert_eq!((cb.buffer_index, cb.x, cb.y, cb.width, cb.height), (1, 2, 3, 4, 5));
+    assert_eq!(BufferArea::from_cb(&cb), area);
+}
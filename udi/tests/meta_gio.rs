@@ -0,0 +1,55 @@
+//! GIO operation codes and transfer parameters
+use udi::ffi::meta_gio::{UDI_GIO_DIR_READ, UDI_GIO_DIR_WRITE, UDI_GIO_OP_CUSTOM};
+use udi::meta_gio::{GioOp, RwParams, TrParams};
+use udi::meta_gio::block::BlockRange;
+
+#[test]
+fn op_codes() {
+    assert_eq!(GioOp::from_raw(UDI_GIO_DIR_READ), GioOp::Read);
+    assert_eq!(GioOp::from_raw(UDI_GIO_DIR_WRITE), GioOp::Write);
+    assert!(GioOp::Read.is_read() && !GioOp::Read.is_write());
+    assert_eq!(GioOp::Write.code(), 0);
+
+    // Standard codes (other than read/write) are kept as raw values
+    let op = GioOp::from_raw(3 | UDI_GIO_DIR_READ | UDI_GIO_DIR_WRITE);
+    assert_eq!(op, GioOp::Standard(3 | UDI_GIO_DIR_READ | UDI_GIO_DIR_WRITE));
+    assert!(op.is_read() && op.is_write());
+
+    let op = GioOp::custom(2, false, true);
+    assert_eq!(op.code(), UDI_GIO_OP_CUSTOM + 2);
+    assert_eq!(GioOp::from_raw(op.to_raw()), op);
+    assert!(!op.is_read() && op.is_write());
+    assert_eq!(GioOp::from_raw(UDI_GIO_OP_CUSTOM), GioOp::custom(0, false, false));
+}
+
+#[test]
+#[should_panic]
+fn op_custom_range() {
+    let _ = GioOp::custom(48, true, false);
+}
+
+#[test]
+fn rw_params() {
+    let p = RwParams::new(0x1_2345_6789);
+    assert_eq!(p.offset(), 0x1_2345_6789);
+    assert!(RwParams::valid_for(GioOp::Read));
+    assert!(RwParams::valid_for(GioOp::Write));
+    assert!(!RwParams::valid_for(GioOp::custom(0, true, false)));
+}
+
+#[test]
+fn block_range() {
+    let r = BlockRange::from_bytes(512, 16, 1024, 5*512).unwrap();
+    assert_eq!(r, BlockRange { lba: 2, count: 5 });
+    let chunks: Vec<_> = r.chunks(512, 2).collect();
+    assert_eq!(chunks, [(2, 2, 0), (4, 2, 1024), (6, 1, 2048)]);
+
+    // Truncated at the end of the device
+    assert_eq!(BlockRange::from_bytes(512, 16, 15*512, 4*512).unwrap(), BlockRange { lba: 15, count: 1 });
+    assert_eq!(BlockRange::from_bytes(512, 16, 20*512, 512).unwrap().count, 0);
+
+    // Must be whole blocks
+    let e = BlockRange::from_bytes(512, 16, 100, 512).unwrap_err();
+    assert_eq!(e.into_inner(), udi::ffi::UDI_STAT_NOT_SUPPORTED as _);
+    assert!(BlockRange::from_bytes(512, 16, 0, 100).is_err());
+}
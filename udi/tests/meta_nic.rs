@@ -0,0 +1,71 @@
+//! NIC bind description encoding and validation
+use udi::meta_nic::{BindError, Capabilities, LinkSpeed, MacAddress, MediaType, MulticastFilter, NicInfo};
+
+fn ether() -> NicInfo {
+    NicInfo {
+        media_type: MediaType::Ethernet,
+        min_pdu_size: 0,
+        max_pdu_size: 0,
+        rx_hw_threshold: 4,
+        capabilities: Capabilities::NONE,
+        multicast: MulticastFilter { max_perfect: 0, max_total: 16 },
+        mac_addr: MacAddress::new(&[0x12,0x34,0x56,0x00,0x00,0x01]),
+    }
+}
+
+#[test]
+fn ethernet_pdu() {
+    let info = ether();
+    assert_eq!(info.pdu_range(), 60 ..= 1514);
+    assert_eq!(info.validate(), Ok(()));
+    // Too large without VLAN support, allowed with it
+    let tagged = NicInfo { max_pdu_size: 1518, ..info };
+    assert_eq!(tagged.validate(), Err(BindError::PduRange { min: 60, max: 1518 }));
+    assert_eq!(NicInfo { capabilities: Capabilities::NONSTANDARD_VLAN_TAGGING, ..tagged }.validate(), Ok(()));
+    // Jumbo frames only on gigabit
+    let jumbo = NicInfo { max_pdu_size: 9014, ..info };
+    assert!(jumbo.validate().is_err());
+    assert_eq!(NicInfo { media_type: MediaType::GigabitEthernet, ..jumbo }.validate(), Ok(()));
+    // Empty range
+    assert_eq!(NicInfo { min_pdu_size: 60, max_pdu_size: 59, ..info }.validate(), Err(BindError::PduRange { min: 60, max: 59 }));
+}
+
+#[test]
+fn other_checks() {
+    let info = ether();
+    assert_eq!(NicInfo { mac_addr: MacAddress::new(&[1,2]), ..info }.validate(), Err(BindError::MacAddrLength(2)));
+    let mc = MulticastFilter { max_perfect: 4, max_total: 2 };
+    assert_eq!(NicInfo { multicast: mc, ..info }.validate(), Err(BindError::Multicast(mc)));
+    // No defaults for ATM, so anything goes (other than an empty range)
+    let atm = NicInfo { media_type: MediaType::Atm, mac_addr: MacAddress::new(&[0; 20]), ..info };
+    assert_eq!(atm.pdu_range(), 0 ..= u32::MAX);
+    assert_eq!(atm.validate(), Ok(()));
+}
+
+#[test]
+fn round_trip() {
+    let info = NicInfo { capabilities: Capabilities::TX_CKSUM_ALL | Capabilities::MCAST_LOOPBK, ..ether() };
+    // SAFE: All-zero is valid for the CB (null pointers)
+    let mut cb: udi::ffi::meta_nic::udi_nic_bind_cb_t = unsafe { ::core::mem::zeroed() };
+    info.write_cb(&mut cb);
+    assert_eq!(cb.mac_addr_len, 6);
+    assert_eq!(NicInfo::from_cb(&cb), Ok(info));
+    assert!(info.capabilities.contains(Capabilities::TX_TCP_CKSUM));
+    assert!(!info.capabilities.contains(Capabilities::NONSTANDARD_VLAN_TAGGING));
+    cb.media_type = 0x80;
+    assert_eq!(NicInfo::from_cb(&cb), Err(BindError::MediaType(0x80)));
+
+    for media in [MediaType::Ethernet, MediaType::TokenRing, MediaType::FibreChannel, MediaType::Misc] {
+        assert_eq!(MediaType::from_raw(media.to_raw()), Some(media));
+    }
+}
+
+#[test]
+fn link_speed() {
+    assert_eq!(LinkSpeed::from_raw(0, 0), LinkSpeed::Unknown);
+    assert_eq!(LinkSpeed::from_raw(100, 0), LinkSpeed::Mbps(100));
+    assert_eq!(LinkSpeed::from_raw(0, 9600), LinkSpeed::Bps(9600));
+    assert_eq!(LinkSpeed::Mbps(10).to_raw(), (10, 0));
+    assert_eq!(LinkSpeed::Mbps(1000).bits_per_second(), Some(1_000_000_000));
+    assert_eq!(MediaType::FastEthernet.nominal_speed(), LinkSpeed::Mbps(100));
+}
@@ -0,0 +1,106 @@
+//! SCSI CDBs, status, and sense data
+use udi::meta_scsi::{Cdb, CtlOp, IoFlags, IoRequest, ReqStatus, ScsiEvent, ScsiStatus, SenseData, SenseKey, StatusByte, TaskAttribute};
+
+#[test]
+fn cdb() {
+    assert_eq!(Cdb::test_unit_ready().as_bytes(), [0; 6]);
+    assert_eq!(Cdb::read10(0x12345678, 8).as_bytes(), [0x28, 0, 0x12,0x34,0x56,0x78, 0, 0,8, 0]);
+    // Falls back to the 16-byte form if the LBA or count do not fit
+    assert_eq!(Cdb::read(0x1234, 1).opcode(), 0x28);
+    let c = Cdb::write(0x1_0000_0000, 2);
+    assert_eq!(c.as_bytes(), [0x8A, 0, 0,0,0,1,0,0,0,0, 0,0,0,2, 0, 0]);
+    assert_eq!(Cdb::read(0, 0x10000).opcode(), 0x88);
+
+    assert!(Cdb::new(&[]).is_none());
+    assert!(Cdb::new(&[0; 17]).is_none());
+    assert_eq!(Cdb::new(&[0x12, 0, 0, 0, 36, 0]), Some(Cdb::inquiry(36)));
+}
+
+#[test]
+fn io_request() {
+    let req = IoRequest::new(Cdb::read10(1, 1)).data_in()
+        .with_attribute(TaskAttribute::Ordered)
+        .with_timeout(5000);
+    let mut cdb = [0u8; Cdb::MAX_LEN];
+    // SAFE: All-zero is valid for the CB (null pointers)
+    let mut cb: udi::ffi::meta_scsi::udi_scsi_io_cb_t = unsafe { ::core::mem::zeroed() };
+    cb.cdb_ptr = cdb.as_mut_ptr();
+    // SAFE: `cdb_ptr` is valid for `Cdb::MAX_LEN` bytes
+    unsafe { req.write_cb(&mut cb); }
+    assert_eq!(cb.cdb_len, 10);
+    assert_eq!(cb.flags, udi::ffi::meta_scsi::UDI_SCSI_DATA_IN);
+    assert_eq!(cb.attribute, udi::ffi::meta_scsi::UDI_SCSI_ORDERED_TASK);
+    // SAFE: `cdb_ptr` is valid for `cdb_len` bytes
+    assert_eq!(unsafe { IoRequest::from_cb(&cb) }, Ok(req));
+    assert!(req.flags.contains(IoFlags::DATA_IN) && !req.flags.contains(IoFlags::DATA_OUT));
+
+    cb.attribute = 0;
+    // SAFE: As above
+    assert!(unsafe { IoRequest::from_cb(&cb) }.is_err());
+}
+
+#[test]
+fn status() {
+    let s = ScsiStatus::target_status(StatusByte::CheckCondition);
+    assert!(s.is_check_condition());
+    assert_eq!(ScsiStatus::from_raw(&s.to_raw()), s);
+
+    for st in [ReqStatus::Ok, ReqStatus::AcaPending, ReqStatus::SelectionTimeout, ReqStatus::DevicePhaseError] {
+        assert_eq!(ReqStatus::from_raw(st.to_raw()), st);
+    }
+    let timeout = ReqStatus::from_raw(udi::ffi::UDI_STAT_TIMEOUT as _);
+    assert!(matches!(timeout, ReqStatus::Other(e) if e.into_inner() == udi::ffi::UDI_STAT_TIMEOUT as u32));
+    assert!(!ScsiStatus::failed(timeout).is_check_condition());
+    assert_eq!(StatusByte::from_raw(0x28), StatusByte::TaskSetFull);
+    assert_eq!(StatusByte::from_raw(0x22), StatusByte::Other(0x22));
+}
+
+#[test]
+fn sense_fixed() {
+    // Medium error at LBA 0x1234, unrecovered read error (11/00)
+    let data = [0xF0, 0, 0x03, 0,0,0x12,0x34, 10, 0,0,0,0, 0x11, 0x00, 0,0,0,0];
+    let s = SenseData::parse(&data).unwrap();
+    assert!(s.current && !s.descriptor_format);
+    assert_eq!(s.sense_key, SenseKey::MediumError);
+    assert_eq!((s.asc, s.ascq), (0x11, 0x00));
+    assert_eq!(s.information, Some(0x1234));
+
+    // Without the VALID bit, and truncated by the additional length
+    let data = [0x71, 0, 0x06, 0,0,0,0, 4, 0,0,0,0, 0x29, 0x00];
+    let s = SenseData::parse(&data).unwrap();
+    assert!(!s.current);
+    assert_eq!(s.sense_key, SenseKey::UnitAttention);
+    assert_eq!((s.asc, s.information), (0, None));
+
+    assert!(SenseData::parse(&[]).is_none());
+    assert!(SenseData::parse(&[0x7F, 0, 0]).is_none());
+}
+
+#[test]
+fn sense_descriptor() {
+    let data = [
+        0x72, 0x05, 0x24, 0x00, 0,0,0, 14,
+        // Unknown descriptor, skipped
+        0x80, 0x00,
+        // Information descriptor
+        0x00, 0x0A, 0x80, 0, 0,0,0,1,0,0,0,2,
+    ];
+    let s = SenseData::parse(&data).unwrap();
+    assert!(s.current && s.descriptor_format);
+    assert_eq!(s.sense_key, SenseKey::IllegalRequest);
+    assert_eq!((s.asc, s.ascq), (0x24, 0x00));
+    assert_eq!(s.information, Some(0x1_0000_0002));
+}
+
+#[test]
+fn ctl_and_events() {
+    for op in [CtlOp::AbortTaskSet, CtlOp::LunReset, CtlOp::BusReset, CtlOp::SetQueueDepth(32)] {
+        let (f, d) = op.to_raw();
+        assert_eq!(CtlOp::from_raw(f, d), Some(op));
+    }
+    assert_eq!(CtlOp::from_raw(0, 0), None);
+    for e in [ScsiEvent::Aen, ScsiEvent::TargetReset, ScsiEvent::BusReset, ScsiEvent::UnsolicitedReselect] {
+        assert_eq!(ScsiEvent::from_raw(e.to_raw()), Some(e));
+    }
+    assert_eq!(ScsiEvent::from_raw(3), None);
+}
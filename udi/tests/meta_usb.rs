@@ -0,0 +1,133 @@
+//! USB descriptors, control requests, transfers, and pipe states
+use udi::meta_usb::{
+    ConfigDescriptor, ControlXfer, DescRequest, Descriptor, DescriptorType, Descriptors, DeviceDescriptor, DeviceRequest,
+    DeviceSpeed, Direction, EndpointDescriptor, IntrBulkXfer, IsocXfer, PipeState, Recipient, RequestKind, TransferType,
+};
+
+const CONFIG: [u8; 9+9+7+7+5] = [
+    9, 2, 37,0, 1, 1, 0, 0xC0, 50,
+    9, 4, 0, 0, 2, 0xFF,0,0, 0,
+    7, 5, 0x81, 0x03, 8,0, 10,
+    // Class-specific descriptor between the endpoints
+    5, 0x24, 1, 2, 3,
+    7, 5, 0x02, 0x02, 0x00,0x02, 0,
+];
+
+#[test]
+fn descriptors() {
+    let dev = [18, 1, 0x00,0x02, 0,0,0, 64, 0x34,0x12, 0x78,0x56, 0x01,0x02, 1,2,3, 1];
+    let d = DeviceDescriptor::parse(&dev).unwrap();
+    assert_eq!(d.usb_version, 0x200);
+    assert_eq!((d.vendor_id, d.product_id), (0x1234, 0x5678));
+    assert_eq!(d.max_packet_size0, 64);
+    assert_eq!(d.num_configurations, 1);
+    // Too short, or the wrong type
+    assert_eq!(DeviceDescriptor::parse(&dev[..17]), None);
+    assert_eq!(DeviceDescriptor::parse(&CONFIG), None);
+
+    let c = ConfigDescriptor::parse(&CONFIG).unwrap();
+    assert_eq!(c.total_length as usize, CONFIG.len());
+    assert_eq!(c.config_value, 1);
+    assert!(c.self_powered() && !c.remote_wakeup());
+    assert_eq!(c.max_power_ma(), 100);
+
+    let all: Vec<_> = Descriptors::new(&CONFIG).collect();
+    assert_eq!(all.len(), 5);
+    assert!(matches!(all[1], Descriptor::Interface(i) if i.num_endpoints == 2 && i.class == 0xFF));
+    assert_eq!(all[3], Descriptor::Other(DescriptorType::Other(0x24), &CONFIG[25..30]));
+
+    let eps: Vec<_> = Descriptors::new(&CONFIG).interface_endpoints(0, 0).collect();
+    assert_eq!(eps.len(), 2);
+    assert_eq!((eps[0].number(), eps[0].direction(), eps[0].transfer_type()), (1, Direction::In, TransferType::Interrupt));
+    assert_eq!(eps[0].interval, 10);
+    assert_eq!((eps[1].number(), eps[1].direction(), eps[1].transfer_type()), (2, Direction::Out, TransferType::Bulk));
+    assert_eq!(eps[1].max_packet_bytes(), 512);
+    assert_eq!(Descriptors::new(&CONFIG).interface_endpoints(0, 1).count(), 0);
+    assert_eq!(EndpointDescriptor::parse(&CONFIG[18..]), Some(eps[0]));
+
+    // Iteration stops at a bad length
+    let mut bad = CONFIG;
+    bad[18] = 60;
+    assert_eq!(Descriptors::new(&bad).count(), 2);
+    bad[18] = 0;
+    assert_eq!(Descriptors::new(&bad).count(), 2);
+}
+
+#[test]
+fn device_request() {
+    let r = DeviceRequest::get_descriptor(DescriptorType::Config, 0, 0, 9);
+    assert_eq!(r.to_bytes(), [0x80, 6, 0,2, 0,0, 9,0]);
+    assert_eq!(DeviceRequest::from_bytes(&r.to_bytes()), Some(r));
+    assert_eq!(DeviceRequest::from_raw(&r.to_raw()), Some(r));
+
+    let r = DeviceRequest::clear_halt(0x81);
+    assert_eq!(r.to_bytes(), [0x02, 1, 0,0, 0x81,0, 0,0]);
+    assert_eq!(r.request_type.recipient, Recipient::Endpoint);
+    assert_eq!(DeviceRequest::set_configuration(1).to_bytes(), [0x00, 9, 1,0, 0,0, 0,0]);
+    assert_eq!(DeviceRequest::set_interface(2, 1).to_bytes(), [0x01, 11, 1,0, 2,0, 0,0]);
+
+    let r = DeviceRequest::from_bytes(&[0xC1, 0x42, 0x34,0x12, 0,0, 4,0]).unwrap();
+    assert_eq!(r.request_type.kind, RequestKind::Vendor);
+    assert_eq!(r.request_type.direction, Direction::In);
+    assert_eq!((r.request, r.value, r.length), (0x42, 0x1234, 4));
+    // Reserved type or recipient
+    assert_eq!(DeviceRequest::from_bytes(&[0x60, 0, 0,0, 0,0, 0,0]), None);
+    assert_eq!(DeviceRequest::from_bytes(&[0x04, 0, 0,0, 0,0, 0,0]), None);
+}
+
+#[test]
+fn transfers() {
+    // SAFE: All-zero is valid for the CBs (null pointers)
+    let mut cb: udi::ffi::meta_usb::usbdi_intr_bulk_xfer_cb_t = unsafe { ::core::mem::zeroed() };
+    let x = IntrBulkXfer::read().short_ok().with_timeout(100);
+    x.write_cb(&mut cb);
+    assert_eq!(cb.xfer_flags, udi::ffi::meta_usb::USBDI_XFER_IN | udi::ffi::meta_usb::USBDI_XFER_SHORT_OK);
+    assert_eq!(IntrBulkXfer::from_cb(&cb), Ok(x));
+    // Both or neither direction is invalid
+    cb.xfer_flags = udi::ffi::meta_usb::USBDI_XFER_IN | udi::ffi::meta_usb::USBDI_XFER_OUT;
+    assert!(IntrBulkXfer::from_cb(&cb).is_err());
+    cb.xfer_flags = 0;
+    assert!(IntrBulkXfer::from_cb(&cb).is_err());
+
+    // SAFE: As above
+    let mut cb: udi::ffi::meta_usb::usbdi_control_xfer_cb_t = unsafe { ::core::mem::zeroed() };
+    let x = ControlXfer::new(DeviceRequest::set_configuration(1));
+    x.write_cb(&mut cb);
+    assert_eq!(cb.xfer_flags, udi::ffi::meta_usb::USBDI_XFER_OUT);
+    // SAFE: Plain bytes
+    assert_eq!(unsafe { cb.request.request }, [0, 9, 1,0, 0,0, 0,0]);
+    assert_eq!(ControlXfer::from_cb(&cb), Ok(x));
+
+    // SAFE: As above
+    let mut cb: udi::ffi::meta_usb::usbdi_isoc_xfer_cb_t = unsafe { ::core::mem::zeroed() };
+    let x = IsocXfer::at_frame(Direction::Out, 1234);
+    x.write_cb(&mut cb);
+    assert_eq!((cb.xfer_flags, cb.frame_number), (udi::ffi::meta_usb::USBDI_XFER_OUT, 1234));
+    assert_eq!(IsocXfer::from_cb(&cb), Ok(x));
+    IsocXfer::asap(Direction::In).write_cb(&mut cb);
+    assert!(IsocXfer::from_cb(&cb).unwrap().asap);
+
+    // SAFE: As above
+    let mut cb: udi::ffi::meta_usb::usbdi_desc_cb_t = unsafe { ::core::mem::zeroed() };
+    let r = DescRequest::new(DescriptorType::String, 2, 255).with_lang_id(0x409);
+    r.write_cb(&mut cb);
+    assert_eq!((cb.desc_type, cb.desc_index, cb.desc_id), (udi::ffi::meta_usb::USB_DESC_TYPE_STRING, 2, 0x409));
+    assert_eq!(DescRequest::from_cb(&cb), r);
+}
+
+#[test]
+fn states() {
+    for s in [PipeState::Active, PipeState::Stalled, PipeState::Idle, PipeState::Halted] {
+        assert_eq!(PipeState::from_raw(s.to_raw()), Some(s));
+        assert_eq!(s.accepts_transfers(), s == PipeState::Active);
+        // Only the device can stall a pipe, but any state can be left by going active
+        assert!(s.check_set(PipeState::Stalled).is_err());
+        assert!(s.check_set(PipeState::Active).is_ok());
+    }
+    assert_eq!(PipeState::from_raw(0), None);
+    assert_eq!(PipeState::from_raw(5), None);
+
+    for s in [DeviceSpeed::Low, DeviceSpeed::Full, DeviceSpeed::High, DeviceSpeed::Other(9)] {
+        assert_eq!(DeviceSpeed::from_raw(s.to_raw()), s);
+    }
+}
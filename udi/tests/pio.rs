@@ -0,0 +1,170 @@
+//! PIO validation tests
+//!
+//! NOTE: Results are computed in `const`s, so these tests don't link against the UDI environment functions
+use udi::ffi::pio::udi_pio_trans_t;
+use udi::pio::{validate, Summary, ValidateError, ValidateErrorKind};
+
+const fn raw(pio_op: u8, tran_size: u8, operand: u16) -> udi_pio_trans_t {
+    udi_pio_trans_t { pio_op, tran_size, operand }
+}
+
+#[test]
+fn straight_line() {
+    udi::define_pio_ops!{ READ_ID =
+        IN.B R0, 0;
+        LOAD_IMM.B R1, 6;
+        IN_IND.B R2, R1;
+        OR.B R0, R2;
+        END.B R0;
+    }
+    const OK: Result<Summary,ValidateError> = validate(&READ_ID, 8);
+    assert!(matches!(OK, Ok(Summary { entry_points: 1, max_device_accesses: Some(2) })));
+    // Indirect access at 6 is outside of a 6 byte mapping
+    const SHORT: Result<Summary,ValidateError> = validate(&READ_ID, 6);
+    assert!(matches!(SHORT, Err(ValidateError { index: 2, kind: ValidateErrorKind::OutOfRange { offset: 6, size: 1 } })));
+}
+
+#[test]
+fn entry_points() {
+    udi::define_pio_ops!{ OPS =
+        // 0: Read
+        IN.B R0, 1;
+        END.B R0;
+        LABEL 1;    // Write
+        LOAD_IMM.B R0, 0xFF;
+        BRANCH write;
+        LABEL 2;    // Write twice
+        LOAD_IMM.B R0, 0xAA;
+        OUT.B 1, R0;
+        LABEL write;
+        OUT.B 1, R0;
+        END_IMM 0;
+    }
+    const RES: Result<Summary,ValidateError> = validate(&OPS, 2);
+    assert!(matches!(RES, Ok(Summary { entry_points: 3, max_device_accesses: Some(2) })));
+}
+
+#[test]
+fn const_labels() {
+    const ENTRY_WRITE: u16 = 3;
+    udi::define_pio_ops!{ OPS =
+        const ENTRY_RESET = 5;
+        END_IMM 0;
+        LABEL (ENTRY_WRITE);
+        BRANCH next;
+        LABEL (ENTRY_RESET);
+        LABEL next;
+        END_IMM 0;
+    }
+    const RES: Result<Summary,ValidateError> = validate(&OPS, 0);
+    assert!(matches!(RES, Ok(Summary { entry_points: 3, .. })));
+    // Named labels are allocated after the highest numeric label
+    assert_eq!((OPS[1].operand, OPS[2].operand, OPS[3].operand, OPS[4].operand), (3, 6, 5, 6));
+}
+
+#[test]
+fn loops_unbounded() {
+    udi::define_pio_ops!{ WAIT =
+        LOAD_IMM.B R1, 0x80;
+        LABEL wait;
+        IN.B R0, 7;
+        AND.B R0, R1;
+        CSKIP.B R0 NZ;
+        BRANCH wait;
+        END_IMM 0;
+    }
+    const RES: Result<Summary,ValidateError> = validate(&WAIT, 8);
+    assert!(matches!(RES, Ok(Summary { max_device_accesses: None, .. })));
+}
+
+#[test]
+fn uninitialised() {
+    udi::define_pio_ops!{ MAYBE =
+        IN.B R0, 0;
+        CSKIP.B R0 Z;
+        LOAD_IMM.B R1, 1;
+        OUT.B 0, R1;
+        END_IMM 0;
+    }
+    const RES: Result<Summary,ValidateError> = validate(&MAYBE, 1);
+    assert!(matches!(RES, Err(ValidateError { index: 3, kind: ValidateErrorKind::UninitialisedRegister(1) })));
+}
+
+#[test]
+fn labels() {
+    use udi::pio::vals::ops_group_c::{BRANCH, LABEL, END_IMM};
+    const MISSING: Result<Summary,ValidateError> = validate(&[raw(BRANCH, 0, 3), raw(END_IMM, 1, 0)], 0);
+    assert!(matches!(MISSING, Err(ValidateError { index: 0, kind: ValidateErrorKind::MissingLabel(3) })));
+    const DUPLICATE: Result<Summary,ValidateError> = validate(&[raw(LABEL, 0, 1), raw(LABEL, 0, 1), raw(END_IMM, 1, 0)], 0);
+    assert!(matches!(DUPLICATE, Err(ValidateError { index: 1, kind: ValidateErrorKind::DuplicateLabel(1) })));
+    const ZERO: Result<Summary,ValidateError> = validate(&[raw(LABEL, 0, 0), raw(END_IMM, 1, 0)], 0);
+    assert!(matches!(ZERO, Err(ValidateError { index: 0, kind: ValidateErrorKind::LabelZero })));
+    const NO_END: Result<Summary,ValidateError> = validate(&[raw(LABEL, 0, 1)], 0);
+    assert!(matches!(NO_END, Err(ValidateError { index: 0, kind: ValidateErrorKind::FallsOffEnd })));
+}
+
+#[test]
+fn repeat() {
+    udi::define_pio_ops!{ READ_FIFO =
+        LOAD_IMM.B R0, 0;
+        LOAD_IMM.B R1, 0x10;
+        LOAD_IMM.B R2, 4;
+        REP_IN_IND.B [mem R0 STEP1], R1 STEP1, R2;
+        END_IMM 0;
+    }
+    const OK: Result<Summary,ValidateError> = validate(&READ_FIFO, 0x14);
+    assert!(matches!(OK, Ok(Summary { max_device_accesses: Some(4), .. })));
+    const SHORT: Result<Summary,ValidateError> = validate(&READ_FIFO, 0x13);
+    assert!(matches!(SHORT, Err(ValidateError { index: 3, kind: ValidateErrorKind::OutOfRange { offset: 0x13, size: 1 } })));
+
+    // The transfer size is taken from the size code
+    udi::define_pio_ops!{ WRITE_FIFO =
+        LOAD_IMM.B R0, 0;
+        LOAD_IMM.B R1, 0x10;
+        LOAD_IMM.B R2, 2;
+        REP_OUT_IND.S [buf R0 STEP2], R1, R2;
+        END_IMM 0;
+    }
+    assert_eq!(WRITE_FIFO[3].tran_size, udi::pio::vals::size::S);
+    const SHORT_S: Result<Summary,ValidateError> = validate(&WRITE_FIFO, 0x11);
+    assert!(matches!(SHORT_S, Err(ValidateError { index: 3, kind: ValidateErrorKind::OutOfRange { offset: 0x10, size: 2 } })));
+}
+
+#[test]
+fn serialization_limit() {
+    udi::define_pio_ops!{ NOP = END_IMM 0; }
+    const OK: Result<Summary,ValidateError> = udi::pio::validate_map(&NOP, 0, udi::ffi::udi_index_t(0), 1);
+    assert!(OK.is_ok());
+    const BAD: Result<Summary,ValidateError> = udi::pio::validate_map(&NOP, 0, udi::ffi::udi_index_t(1), 1);
+    assert!(matches!(BAD, Err(ValidateError { kind: ValidateErrorKind::SerializationDomain { domain: 1, limit: 1 }, .. })));
+}
+
+#[test]
+fn disassemble() {
+    udi::define_pio_ops!{ OPS =
+        LOAD_IMM.S R0, 0x100;
+        IN.B [mem R0], 0x1F;
+        LOAD.L R1, [buf R0];
+        CSKIP.B R1 NZ;
+        BRANCH done;
+        ADD_IMM.B R1, 0xFF;
+        REP_OUT_IND.B [buf R0 STEP2], R2 STEP1, R1;
+        OUT_IND.S R2, R1;
+        LABEL done;
+        END_IMM 0;
+    }
+    assert_eq!(format!("{:?}", udi::pio::PioProgram(&OPS)), "\
+0: LOAD_IMM.S R0, 0x100;
+1: IN.B [mem R0], 0x1f;
+2: LOAD.L R1, [buf R0];
+3: CSKIP.B R1 NZ;
+4: BRANCH 1;
+5: ADD_IMM.B R1, 0xff;
+6: REP_OUT_IND.B [buf R0 STEP2], R2 STEP1, R1;
+7: OUT_IND.S R2, R1;
+8: LABEL 1;
+9: END_IMM 0x0;
+");
+    let bad = [udi_pio_trans_t { pio_op: 0xF9, tran_size: 0, operand: 0 }];
+    assert_eq!(format!("{:?}", udi::pio::PioProgram(&bad)), "0: /* BadOpcode(249) */ 0xf9 0 0x0000;\n");
+}